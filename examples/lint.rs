@@ -1,3 +1,4 @@
+use brawllib_rs::analysis;
 use brawllib_rs::brawl_mod::BrawlMod;
 use brawllib_rs::high_level_fighter::{HighLevelFighter, HighLevelHitBox, CollisionBoxValues, GrabBoxValues};
 use brawllib_rs::script_ast::GrabTarget;
@@ -89,6 +90,19 @@ fn main() {
         }
     }
 
+    println!("\nHitbox id mistakes:");
+    println!("Reusing an id of an active hitbox, not terminating hitboxes, mixing special and normal hitboxes on an id and zero sized hitboxes.");
+    for fighter in fighters.iter() {
+        if let Some(fighter_data) = fighter.get_fighter_data() {
+            for (i, script) in fighter_data.subaction_main.iter().enumerate() {
+                let name = fighter_data.subaction_flags.get(i).map(|x| x.name.as_str()).unwrap_or("");
+                for lint in analysis::hitbox_lint(script) {
+                    println!("{} {} {:?}", fighter.cased_name, name, lint);
+                }
+            }
+        }
+    }
+
     println!("\nCreating an unconditional interrupt on the first line of a subaction:");
     println!("This was used by PMDT before action overrides were understood.");
     println!("All of these cases should be replaced with action overrides so that move staling is properly handled.");
@@ -0,0 +1,96 @@
+use brawllib_rs::brawl_mod::BrawlMod;
+use brawllib_rs::high_level_fighter::HighLevelFighter;
+
+use getopts::Options;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::env;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts the heap used while loading fighters and creating their HighLevelFighters.
+/// Peak is the most heap allocated at once during a step, retained is what is still allocated after it.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!("Usage: {} [options]", program);
+    print!("{}", opts.usage(&brief));
+}
+
+/// Resets the peak to the currently allocated heap, returns the currently allocated heap
+fn start_step() -> usize {
+    let allocated = ALLOCATED.load(Ordering::SeqCst);
+    PEAK.store(allocated, Ordering::SeqCst);
+    allocated
+}
+
+fn print_step(name: &str, start: usize) {
+    let mib = |bytes: usize| bytes as f64 / (1024.0 * 1024.0);
+    let peak = PEAK.load(Ordering::SeqCst);
+    let retained = ALLOCATED.load(Ordering::SeqCst);
+    println!("{}: peak {:.2} MiB, retained {:.2} MiB", name, mib(peak - start), mib(retained.saturating_sub(start)));
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let program = &args[0];
+
+    let mut opts = Options::new();
+    opts.optopt("d", "dir", "full path to a brawl directory", "DIRECTORY_NAME");
+    opts.optopt("m", "mod", "full path to a mod directory that will overwrite brawl files", "DIRECTORY_NAME");
+    opts.optflag("s", "single-model", "only load the first model of each fighter");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(_) => {
+            print_usage(program, opts);
+            return;
+        }
+    };
+
+    let brawl_path = if let Some(path) = matches.opt_str("d") {
+        PathBuf::from(path)
+    } else {
+        println!("Need to pass a brawl directory\n");
+        print_usage(program, opts);
+        return;
+    };
+    let mod_path = matches.opt_str("m").map(PathBuf::from);
+    let brawl_mod = BrawlMod::new(&brawl_path, mod_path.as_deref());
+
+    let start = start_step();
+    let fighters = match brawl_mod.load_fighters(matches.opt_present("s")) {
+        Ok(fighters) => fighters,
+        Err(err) => {
+            println!("Failed to load brawl mod: {}", err);
+            return;
+        }
+    };
+    print_step(&format!("BrawlMod::load_fighters ({} fighters)", fighters.len()), start);
+
+    let start = start_step();
+    let high_level_fighters: Vec<_> = fighters.iter().map(HighLevelFighter::new).collect();
+    print_step(&format!("HighLevelFighter::new ({} fighters)", high_level_fighters.len()), start);
+}
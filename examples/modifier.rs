@@ -60,7 +60,7 @@ fn main() {
 
     for fighter in fighters {
         if fighter.cased_name.to_lowercase() == fighter_name.to_lowercase() {
-            let model = fighter.models.get(0).unwrap().compile();
+            let model = fighter.models.first().unwrap().compile();
             let mut file = File::create("modifier_output.pac").unwrap();
             file.write_all(&model).unwrap();
 
@@ -0,0 +1,109 @@
+/*
+ * Exercises the C ABI of brawllib_rs and its memory ownership rules.
+ *
+ * Build and run from the repository root:
+ *     cargo rustc --release --features ffi --lib --crate-type cdylib
+ *     cc ffi/ffi_test.c -Iinclude -Ltarget/release -lbrawllib_rs -o target/ffi_test
+ *     LD_LIBRARY_PATH=target/release target/ffi_test [path/to/brawl/dump]
+ *
+ * When a brawl dump is given, its fighters are loaded and converted to JSON as well.
+ * Exits with a non-zero status when a check fails.
+ */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "brawllib.h"
+
+static int failures = 0;
+
+#define CHECK(condition) do { \
+    if (!(condition)) { \
+        fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #condition); \
+        failures++; \
+    } \
+} while (0)
+
+static void parse_gct(void) {
+    const uint8_t gct[] = {
+        0x00, 0xD0, 0xC0, 0xDE, 0x00, 0xD0, 0xC0, 0xDE, /* header */
+        0x04, 0x00, 0x12, 0x34, 0x00, 0x00, 0x00, 0x01, /* 32 bit write */
+        0xF0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, /* end of codes */
+    };
+
+    /* the returned string is owned by the caller */
+    char *json = brawllib_parse_gct(gct, sizeof(gct));
+    CHECK(json != NULL);
+    CHECK(brawllib_last_error_message() == NULL);
+    if (json != NULL) {
+        CHECK(strstr(json, "\"codes\"") != NULL);
+        brawllib_free_string(json);
+    }
+
+    /* failures return null and set the last error, which is owned by the library */
+    CHECK(brawllib_parse_gct(gct, 4) == NULL);
+    CHECK(brawllib_last_error_message() != NULL);
+    CHECK(brawllib_parse_gct(NULL, 0) == NULL);
+    CHECK(strcmp(brawllib_last_error_message(), "bytes is null") == 0);
+}
+
+static void load_errors(void) {
+    BrawllibFighters *handle = NULL;
+    CHECK(brawllib_load_fighters("/path/that/does/not/exist", NULL, &handle) == BRAWLLIB_ERROR_LOAD_FAILED);
+    CHECK(handle == NULL);
+    CHECK(brawllib_last_error_message() != NULL);
+
+    CHECK(brawllib_load_fighters(NULL, NULL, &handle) == BRAWLLIB_ERROR_NULL_ARGUMENT);
+    CHECK(brawllib_load_fighters("/path/that/does/not/exist", NULL, NULL) == BRAWLLIB_ERROR_NULL_ARGUMENT);
+
+    char *json = NULL;
+    CHECK(brawllib_fighter_to_json(NULL, 0, &json) == BRAWLLIB_ERROR_NULL_ARGUMENT);
+    CHECK(json == NULL);
+
+    /* null handles and strings are accepted everywhere */
+    CHECK(brawllib_fighter_count(NULL) == 0);
+    brawllib_free(NULL);
+    brawllib_free_string(NULL);
+}
+
+static void load_fighters(const char *brawl_path) {
+    BrawllibFighters *handle = NULL;
+    BrawllibError error = brawllib_load_fighters(brawl_path, NULL, &handle);
+    if (error != BRAWLLIB_ERROR_OK) {
+        fprintf(stderr, "failed to load %s: %s\n", brawl_path, brawllib_last_error_message());
+        failures++;
+        return;
+    }
+
+    size_t count = brawllib_fighter_count(handle);
+    printf("loaded %zu fighters\n", count);
+
+    char *json = NULL;
+    if (count > 0) {
+        CHECK(brawllib_fighter_to_json(handle, 0, &json) == BRAWLLIB_ERROR_OK);
+    }
+    char *out_of_range = NULL;
+    CHECK(brawllib_fighter_to_json(handle, count, &out_of_range) == BRAWLLIB_ERROR_INDEX_OUT_OF_RANGE);
+    CHECK(out_of_range == NULL);
+
+    /* strings do not borrow from the handle, so the json outlives it */
+    brawllib_free(handle);
+    if (json != NULL) {
+        printf("the first fighter is %zu bytes of JSON\n", strlen(json));
+        brawllib_free_string(json);
+    }
+}
+
+int main(int argc, char **argv) {
+    parse_gct();
+    load_errors();
+    if (argc > 1) {
+        load_fighters(argv[1]);
+    }
+
+    if (failures == 0) {
+        printf("all checks passed\n");
+        return 0;
+    }
+    return 1;
+}
@@ -0,0 +1,114 @@
+#ifndef BRAWLLIB_H
+#define BRAWLLIB_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit. Regenerate with: cbindgen --config cbindgen.toml --output include/brawllib.h */
+
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * The result of a call into the library
+ */
+typedef enum BrawllibError {
+  BRAWLLIB_ERROR_OK = 0,
+  /**
+   * A pointer argument that must not be null was null
+   */
+  BRAWLLIB_ERROR_NULL_ARGUMENT = 1,
+  /**
+   * A string argument was not valid UTF-8
+   */
+  BRAWLLIB_ERROR_INVALID_UTF8 = 2,
+  /**
+   * The fighters could not be loaded e.g. the brawl path does not exist
+   */
+  BRAWLLIB_ERROR_LOAD_FAILED = 3,
+  /**
+   * The index is not less than `brawllib_fighter_count`
+   */
+  BRAWLLIB_ERROR_INDEX_OUT_OF_RANGE = 4,
+  /**
+   * The data could not be parsed or serialized
+   */
+  BRAWLLIB_ERROR_PARSE_FAILED = 5,
+  /**
+   * The library panicked, this is a bug in the library
+   */
+  BRAWLLIB_ERROR_PANIC = 6,
+} BrawllibError;
+
+/**
+ * The fighters loaded by `brawllib_load_fighters`, opaque to C.
+ */
+typedef struct BrawllibFighters BrawllibFighters;
+
+/**
+ * Loads every fighter from the brawl dump at `brawl_path` and the optional mod sd card at `mod_path`, see `BrawlMod::load_fighters_with_config`.
+ * `mod_path` may be null to load vanilla brawl.
+ * On success `*out_handle` is set to a handle that must be freed with `brawllib_free`, otherwise it is set to null.
+ *
+ * # Safety
+ *
+ * `brawl_path` and `mod_path` must be null or point to null terminated strings.
+ * `out_handle` must be null or valid for writes.
+ */
+BrawllibError brawllib_load_fighters(const char *brawl_path,
+                                     const char *mod_path,
+                                     BrawllibFighters **out_handle);
+
+/**
+ * Returns the number of fighters in the handle, 0 if `handle` is null.
+ *
+ * # Safety
+ *
+ * `handle` must be null or a handle returned by `brawllib_load_fighters` that has not been freed.
+ */
+size_t brawllib_fighter_count(const BrawllibFighters *handle);
+
+/**
+ * Sets `*out_buf` to the `HighLevelFighter` of the fighter at `index` as JSON, the string must be freed with `brawllib_free_string`.
+ * On failure `*out_buf` is set to null.
+ *
+ * # Safety
+ *
+ * `handle` must be null or a handle returned by `brawllib_load_fighters` that has not been freed.
+ * `out_buf` must be null or valid for writes.
+ */
+BrawllibError brawllib_fighter_to_json(const BrawllibFighters *handle, size_t index, char **out_buf);
+
+/**
+ * Parses the contents of a gct codeset file and returns its codes as JSON, see `wiird::WiiRDBlock`.
+ * Returns null on failure, the string must be freed with `brawllib_free_string`.
+ *
+ * # Safety
+ *
+ * `bytes` must be valid for reads of `len` bytes.
+ */
+char *brawllib_parse_gct(const uint8_t *bytes, size_t len);
+
+/**
+ * Returns a description of the last failure on this thread, or null if the last call succeeded.
+ * The string is owned by the library and is valid until the next call into the library on this thread.
+ */
+const char *brawllib_last_error_message(void);
+
+/**
+ * Frees a handle returned by `brawllib_load_fighters`, does nothing if `handle` is null.
+ *
+ * # Safety
+ *
+ * `handle` must be null or a handle returned by `brawllib_load_fighters` that has not been freed.
+ */
+void brawllib_free(BrawllibFighters *handle);
+
+/**
+ * Frees a string returned by the library, does nothing if `string` is null.
+ *
+ * # Safety
+ *
+ * `string` must be null or a string returned by the library that has not been freed.
+ * Must not be called on the string returned by `brawllib_last_error_message`.
+ */
+void brawllib_free_string(char *string);
+
+#endif /* BRAWLLIB_H */
@@ -0,0 +1,196 @@
+//! Newtypes for the two kinds of location used when patching files with WiiRD codes.
+//!
+//! A `RamAddress` is a location in Wii RAM e.g. 0x80F9FC20.
+//! A `FileOffset` is a location relative to the start of a file e.g. 0x80.
+//! Converting between the two requires knowing where the file is loaded, described by a `LoadedRegion`.
+//!
+//! Keeping them as separate types prevents comparing a file offset against a RAM address:
+//!
+//! ```compile_fail
+//! use brawllib_rs::address::{RamAddress, FileOffset};
+//!
+//! let address = RamAddress::new(0x80F9FC20);
+//! let offset = FileOffset::new(0x80);
+//! if address == offset { }
+//! ```
+
+use std::fmt;
+use std::ops::{Add, AddAssign};
+
+/// A location in Wii RAM.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct RamAddress(u32);
+
+impl RamAddress {
+    pub const fn new(address: u32) -> RamAddress {
+        RamAddress(address)
+    }
+
+    pub fn into_inner(self) -> u32 {
+        self.0
+    }
+
+    /// Returns None if the address does not fit in 32 bits.
+    pub fn checked_add(self, rhs: u32) -> Option<RamAddress> {
+        self.0.checked_add(rhs).map(RamAddress)
+    }
+}
+
+impl From<u32> for RamAddress {
+    fn from(address: u32) -> RamAddress {
+        RamAddress(address)
+    }
+}
+
+impl From<RamAddress> for u32 {
+    fn from(address: RamAddress) -> u32 {
+        address.0
+    }
+}
+
+impl Add<u32> for RamAddress {
+    type Output = RamAddress;
+
+    fn add(self, rhs: u32) -> RamAddress {
+        RamAddress(self.0.wrapping_add(rhs))
+    }
+}
+
+impl AddAssign<u32> for RamAddress {
+    fn add_assign(&mut self, rhs: u32) {
+        self.0 = self.0.wrapping_add(rhs);
+    }
+}
+
+impl fmt::Debug for RamAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RamAddress(0x{:08x})", self.0)
+    }
+}
+
+/// A location relative to the start of a file.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct FileOffset(u32);
+
+impl FileOffset {
+    pub fn new(offset: u32) -> FileOffset {
+        FileOffset(offset)
+    }
+
+    pub fn into_inner(self) -> u32 {
+        self.0
+    }
+
+    /// Returns None if the offset does not fit in 32 bits.
+    pub fn checked_add(self, rhs: u32) -> Option<FileOffset> {
+        self.0.checked_add(rhs).map(FileOffset)
+    }
+}
+
+impl From<u32> for FileOffset {
+    fn from(offset: u32) -> FileOffset {
+        FileOffset(offset)
+    }
+}
+
+impl From<FileOffset> for u32 {
+    fn from(offset: FileOffset) -> u32 {
+        offset.0
+    }
+}
+
+impl fmt::Debug for FileOffset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FileOffset(0x{:x})", self.0)
+    }
+}
+
+/// A file of `len` bytes loaded into Wii RAM starting at `ram_base`.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct LoadedRegion {
+    pub ram_base: RamAddress,
+    pub len: u32,
+}
+
+impl LoadedRegion {
+    pub fn new(ram_base: RamAddress, len: u32) -> LoadedRegion {
+        LoadedRegion { ram_base, len }
+    }
+
+    /// Creates the region for a file of `len` bytes, where the byte at `offset` is known to be loaded at `address`.
+    /// Returns None if the file would start below address 0.
+    pub fn from_known_location(address: RamAddress, offset: FileOffset, len: u32) -> Option<LoadedRegion> {
+        address.0.checked_sub(offset.0).map(|ram_base| LoadedRegion {
+            ram_base: RamAddress(ram_base),
+            len,
+        })
+    }
+
+    /// Returns true if the address lies within the loaded file.
+    pub fn contains(&self, address: RamAddress) -> bool {
+        address.0 >= self.ram_base.0 && (address.0 - self.ram_base.0) < self.len
+    }
+
+    /// Returns None if the address lies outside of the loaded file.
+    pub fn to_file_offset(&self, address: RamAddress) -> Option<FileOffset> {
+        if self.contains(address) {
+            Some(FileOffset(address.0 - self.ram_base.0))
+        } else {
+            None
+        }
+    }
+
+    /// Returns None if the offset lies outside of the loaded file or past the end of RAM.
+    pub fn to_ram_address(&self, offset: FileOffset) -> Option<RamAddress> {
+        if offset.0 < self.len {
+            self.ram_base.0.checked_add(offset.0).map(RamAddress)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_boundaries() {
+        let region = LoadedRegion::from_known_location(RamAddress::new(0x80F9FC20), FileOffset::new(0x80), 0x100).unwrap();
+        assert_eq!(region.ram_base, RamAddress::new(0x80F9FBA0));
+
+        assert_eq!(region.to_file_offset(RamAddress::new(0x80F9FB9F)), None);
+        assert_eq!(region.to_file_offset(RamAddress::new(0x80F9FBA0)), Some(FileOffset::new(0)));
+        assert_eq!(region.to_file_offset(RamAddress::new(0x80F9FC9F)), Some(FileOffset::new(0xFF)));
+        assert_eq!(region.to_file_offset(RamAddress::new(0x80F9FCA0)), None);
+
+        assert_eq!(region.to_ram_address(FileOffset::new(0)), Some(RamAddress::new(0x80F9FBA0)));
+        assert_eq!(region.to_ram_address(FileOffset::new(0xFF)), Some(RamAddress::new(0x80F9FC9F)));
+        assert_eq!(region.to_ram_address(FileOffset::new(0x100)), None);
+    }
+
+    #[test]
+    fn empty_region() {
+        let region = LoadedRegion::new(RamAddress::new(0x80000000), 0);
+        assert_eq!(region.to_file_offset(RamAddress::new(0x80000000)), None);
+        assert_eq!(region.to_ram_address(FileOffset::new(0)), None);
+    }
+
+    #[test]
+    fn overflow() {
+        assert_eq!(LoadedRegion::from_known_location(RamAddress::new(0x10), FileOffset::new(0x80), 0x100), None);
+        assert_eq!(LoadedRegion::from_known_location(RamAddress::new(0x80), FileOffset::new(0x80), 0x100), Some(LoadedRegion::new(RamAddress::new(0), 0x100)));
+
+        let region = LoadedRegion::new(RamAddress::new(0xFFFFFFF0), 0x100);
+        assert_eq!(region.to_ram_address(FileOffset::new(0xF)), Some(RamAddress::new(0xFFFFFFFF)));
+        assert_eq!(region.to_ram_address(FileOffset::new(0x10)), None);
+
+        assert_eq!(FileOffset::new(0xFFFFFFF0).checked_add(0xF), Some(FileOffset::new(0xFFFFFFFF)));
+        assert_eq!(FileOffset::new(0xFFFFFFF0).checked_add(0x10), None);
+        assert_eq!(RamAddress::new(0xFFFFFFF0).checked_add(0xF), Some(RamAddress::new(0xFFFFFFFF)));
+        assert_eq!(RamAddress::new(0xFFFFFFF0).checked_add(0x10), None);
+    }
+}
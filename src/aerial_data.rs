@@ -0,0 +1,133 @@
+use std::ops::Range;
+
+use crate::high_level_fighter::{HighLevelFighter, HighLevelSubaction};
+use crate::subaction_environment::Environment;
+
+/// Landing data for a single aerial attack.
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct AerialData {
+    pub aerial: Aerial,
+    /// Frames of lag when landing outside of an autocancel window.
+    /// Taken from the fighters attributes, falls back to the length of the landing subaction when the attribute is 0.
+    pub landing_lag: f32,
+    /// Frames of lag when landing with an L-cancel, None when the codeset doesnt implement L-canceling.
+    pub lcancel_lag: Option<f32>,
+    /// Frames where landing does not cause any landing lag.
+    /// Frames start at 1 and the end of each range is exclusive.
+    /// Empty when the subaction is only performed on the ground, as it can never land.
+    pub autocancel_windows: Vec<Range<f32>>,
+    /// Whether the aerial subaction is performed on the ground, in the air or both, see `Fighter::subaction_environment`
+    pub environment: Environment,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum Aerial {
+    Nair,
+    Fair,
+    Bair,
+    Uair,
+    Dair,
+}
+
+impl Aerial {
+    pub const ALL: [Aerial; 5] = [Aerial::Nair, Aerial::Fair, Aerial::Bair, Aerial::Uair, Aerial::Dair];
+
+    pub fn subaction_name(&self) -> &'static str {
+        match self {
+            Aerial::Nair => "AttackAirN",
+            Aerial::Fair => "AttackAirF",
+            Aerial::Bair => "AttackAirB",
+            Aerial::Uair => "AttackAirHi",
+            Aerial::Dair => "AttackAirLw",
+        }
+    }
+
+    pub fn landing_subaction_name(&self) -> &'static str {
+        match self {
+            Aerial::Nair => "LandingAirN",
+            Aerial::Fair => "LandingAirF",
+            Aerial::Bair => "LandingAirB",
+            Aerial::Uair => "LandingAirHi",
+            Aerial::Dair => "LandingAirLw",
+        }
+    }
+}
+
+impl HighLevelFighter {
+    /// Returns the landing data of each aerial the fighter has.
+    ///
+    /// Brawl has no L-canceling, set `l_cancel` if the mod being inspected implements it via codes e.g. Project M.
+    pub fn aerial_data(&self, l_cancel: bool) -> Vec<AerialData> {
+        let mut aerials = vec!();
+        for aerial in Aerial::ALL.iter().cloned() {
+            if let Some(subaction) = self.subactions.iter().find(|x| x.name == aerial.subaction_name()) {
+                let landing_frames = self.subactions.iter()
+                    .find(|x| x.name == aerial.landing_subaction_name())
+                    .map(|x| x.frames.len() as f32)
+                    .unwrap_or(0.0);
+                let landing_lag = match subaction.landing_lag {
+                    Some(landing_lag) if landing_lag != 0.0 => landing_lag,
+                    _ => landing_frames,
+                };
+
+                aerials.push(AerialData {
+                    aerial,
+                    landing_lag,
+                    lcancel_lag: if l_cancel { Some(landing_lag / 2.0) } else { None },
+                    autocancel_windows: subaction_autocancel_windows(subaction),
+                    environment: subaction.environment,
+                });
+            }
+        }
+        aerials
+    }
+}
+
+fn subaction_autocancel_windows(subaction: &HighLevelSubaction) -> Vec<Range<f32>> {
+    if subaction.environment == Environment::Grounded {
+        return vec!();
+    }
+    let landing_lag: Vec<bool> = subaction.frames.iter().map(|x| x.landing_lag).collect();
+    autocancel_windows(&landing_lag)
+}
+
+/// Takes the state of the landing lag flag on each frame and returns the ranges of frames where it is disabled.
+fn autocancel_windows(landing_lag: &[bool]) -> Vec<Range<f32>> {
+    let mut windows = vec!();
+    let mut start = None;
+    for (i, landing_lag) in landing_lag.iter().enumerate() {
+        let frame = i as f32 + 1.0;
+        match (start, landing_lag) {
+            (None, false)       => start = Some(frame),
+            (Some(window), true) => {
+                windows.push(window..frame);
+                start = None;
+            }
+            _ => { }
+        }
+    }
+    if let Some(window) = start {
+        windows.push(window..landing_lag.len() as f32 + 1.0);
+    }
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn autocancel_toggles() {
+        // landing lag is enabled on frame 5 and disabled on frame 20
+        let landing_lag: Vec<bool> = (1..=30).map(|frame| (5..20).contains(&frame)).collect();
+        assert_eq!(autocancel_windows(&landing_lag), vec!(1.0..5.0, 20.0..31.0));
+    }
+
+    #[test]
+    fn autocancel_never() {
+        assert_eq!(autocancel_windows(&[true; 10]), vec!());
+        assert_eq!(autocancel_windows(&[]), vec!());
+    }
+}
@@ -0,0 +1,1258 @@
+use std::collections::HashMap;
+
+use crate::aerial_data::Aerial;
+use crate::fighter::{Fighter, NotLoaded};
+use crate::sakurai::fighter_data::FighterAttributes;
+use crate::script::{Script, Event, Argument, ArgRole, Requirement, CallKind, Variable, VariableMemoryType, normalize_timers};
+use crate::script_ast::{ScriptAst, Block, EventAst, Expression, BinaryExpression, ComparisonOperator};
+use crate::symbols::SymbolTable;
+use crate::validation::{self, ScriptLocation};
+
+/// A likely mistake in how a script manages its hitboxes.
+/// Event indices refer to `Script::events`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum HitboxLint {
+    /// A hitbox was created while a hitbox with the same id was still active.
+    /// The new hitbox replaces the old one, which changes which opponents can be hit again.
+    IdReused { hitbox_id: u8, previous_event: usize, event: usize },
+    /// The hitbox is still active after the last event of the script.
+    NotTerminated { hitbox_id: u8, event: usize },
+    /// A normal hitbox and a special hitbox use the same id.
+    MixedKinds { hitbox_id: u8, normal_event: usize, special_event: usize },
+    /// The hitbox has a size of 0.
+    ZeroSize { hitbox_id: u8, event: usize },
+}
+
+impl HitboxLint {
+    /// The indices of every event involved in the finding
+    pub fn event_indices(&self) -> Vec<usize> {
+        match self {
+            HitboxLint::IdReused { previous_event, event, .. } => vec!(*previous_event, *event),
+            HitboxLint::NotTerminated { event, .. } => vec!(*event),
+            HitboxLint::MixedKinds { normal_event, special_event, .. } => vec!(*normal_event, *special_event),
+            HitboxLint::ZeroSize { event, .. } => vec!(*event),
+        }
+    }
+}
+
+/// Finds likely mistakes in how the script creates and removes hitboxes.
+///
+/// Events are visited in the order given by `script::normalize_timers`, so both branches of an if statement are considered executed
+/// and finite loops are unrolled, findings repeated by unrolling are only reported once.
+/// `HitboxLint::NotTerminated` is not reported for scripts ending in an infinite loop as they have no end,
+/// nor for scripts whose unrolled loops were truncated as their end was not reached.
+pub fn hitbox_lint(script: &Script) -> Vec<HitboxLint> {
+    let mut lints = vec!();
+    let mut add_lint = |lint: HitboxLint| {
+        if !lints.contains(&lint) {
+            lints.push(lint);
+        }
+    };
+
+    // hitbox_id -> index of the event that created it
+    let mut active: HashMap<u8, usize> = HashMap::new();
+    // hitbox_id -> index of the first event creating a normal/special hitbox with that id
+    let mut first_normal: HashMap<u8, usize> = HashMap::new();
+    let mut first_special: HashMap<u8, usize> = HashMap::new();
+    let mut repeating = false;
+
+    let timed_events = normalize_timers(script);
+    for timed_event in timed_events.events {
+        repeating |= timed_event.repeating;
+        let index = match script.events.iter().position(|x| std::ptr::eq(x, timed_event.event)) {
+            Some(index) => index,
+            None => continue,
+        };
+
+        match HitboxEvent::new(timed_event.event) {
+            Some(HitboxEvent::Create { hitbox_id, size, special }) => {
+                if let Some(previous_event) = active.insert(hitbox_id, index) {
+                    add_lint(HitboxLint::IdReused { hitbox_id, previous_event, event: index });
+                }
+                if size == 0 {
+                    add_lint(HitboxLint::ZeroSize { hitbox_id, event: index });
+                }
+
+                let (same, other) = if special { (&mut first_special, &first_normal) } else { (&mut first_normal, &first_special) };
+                same.entry(hitbox_id).or_insert(index);
+                if let Some(&other_event) = other.get(&hitbox_id) {
+                    let (normal_event, special_event) = if special { (other_event, index) } else { (index, other_event) };
+                    add_lint(HitboxLint::MixedKinds { hitbox_id, normal_event, special_event });
+                }
+            }
+            Some(HitboxEvent::Delete (hitbox_id)) => {
+                active.remove(&hitbox_id);
+            }
+            Some(HitboxEvent::DeleteAll) => active.clear(),
+            None => { }
+        }
+    }
+
+    if !repeating && !timed_events.truncated {
+        let mut remaining: Vec<_> = active.into_iter().collect();
+        remaining.sort_by_key(|(_, event)| *event);
+        for (hitbox_id, event) in remaining {
+            add_lint(HitboxLint::NotTerminated { hitbox_id, event });
+        }
+    }
+
+    lints
+}
+
+enum HitboxEvent {
+    /// `size` is the raw fixed point value
+    Create { hitbox_id: u8, size: i32, special: bool },
+    Delete (u8),
+    DeleteAll,
+}
+
+impl HitboxEvent {
+    fn new(event: &Event) -> Option<HitboxEvent> {
+        let id = |argument: Option<&Argument>| match argument {
+            Some(Argument::Value (value)) => Some(*value as u8),
+            _ => None,
+        };
+        let size = match event.arguments.get(5) {
+            Some(Argument::Scalar (size)) => size.raw,
+            _ => 0,
+        };
+
+        match (event.namespace, event.code) {
+            (0x06, 0x00) => id(event.arguments.first()).map(|hitbox_id| HitboxEvent::Create { hitbox_id, size, special: false }),
+            (0x06, 0x15) => id(event.arguments.first()).map(|hitbox_id| HitboxEvent::Create { hitbox_id, size, special: true }),
+            (0x06, 0x03) => id(event.arguments.first()).map(HitboxEvent::Delete),
+            (0x06, 0x04) => Some(HitboxEvent::DeleteAll),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a hitbox event is executed when the script runs, based on the if statements and Gotos around it.
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum HitboxCondition {
+    /// Executed whenever the script reaches it
+    Unconditional,
+    /// Executed only when every expression is true, outermost first.
+    /// The expressions include the And/Or events of the if statement and are negated for else branches.
+    Conditional (Vec<Expression>),
+    /// Can never be executed e.g. inside an `if !Always` or after a Goto
+    Unreachable,
+}
+
+impl HitboxCondition {
+    /// The name of the variant, as used by exporters
+    pub fn kind(&self) -> &'static str {
+        match self {
+            HitboxCondition::Unconditional    => "unconditional",
+            HitboxCondition::Conditional (_)  => "conditional",
+            HitboxCondition::Unreachable      => "unreachable",
+        }
+    }
+}
+
+/// A hitbox creating event and the condition it is executed under.
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct HitboxReachability {
+    /// Index of the event within each nested block, numbered the same as `validation::SubactionRefIssue::event_path`.
+    pub event_path: Vec<usize>,
+    pub hitbox_id: u8,
+    pub condition: HitboxCondition,
+}
+
+/// Classifies every CreateHitBox, ThrownHitBox and CreateSpecialHitBox event by the condition it is executed under.
+///
+/// Expressions that only compare constants or use the Always requirement are evaluated, so `if !Always` branches are unreachable.
+/// All other expressions are assumed to be either true or false at runtime.
+/// Events after a Goto in the same block are unreachable, Goto destinations are not followed.
+pub fn hitbox_reachability(script: &ScriptAst) -> Vec<HitboxReachability> {
+    let mut results = vec!();
+    let mut path = vec!();
+    block_reachability(&script.block, 0, &mut vec!(), true, &mut path, &mut results);
+    results
+}
+
+/// `conditions` are the expressions of the enclosing if statements that could not be evaluated.
+/// `first_index` is the index used for the first event of the block in the event path.
+/// Returns true if the block contains a Goto, making the rest of the enclosing block unreachable.
+fn block_reachability(block: &Block, first_index: usize, conditions: &mut Vec<Expression>, reachable: bool, path: &mut Vec<usize>, results: &mut Vec<HitboxReachability>) -> bool {
+    let mut reachable = reachable;
+    let mut goto = false;
+    for (i, event) in block.events.iter().enumerate() {
+        path.push(first_index + i);
+
+        let hitbox_id = match event {
+            EventAst::CreateHitBox (args) |
+            EventAst::ThrownHitBox (args) => Some(args.hitbox_id),
+            EventAst::CreateSpecialHitBox (args) => Some(args.hitbox_args.hitbox_id),
+            _ => None,
+        };
+        if let Some(hitbox_id) = hitbox_id {
+            let condition = if !reachable {
+                HitboxCondition::Unreachable
+            } else if conditions.is_empty() {
+                HitboxCondition::Unconditional
+            } else {
+                HitboxCondition::Conditional (conditions.clone())
+            };
+            results.push(HitboxReachability { event_path: path.clone(), hitbox_id, condition });
+        }
+
+        let event_goto = match event {
+            EventAst::ForLoop (for_loop) => block_reachability(&for_loop.block, 0, conditions, reachable, path, results),
+            EventAst::IfStatement (if_statement) => {
+                let then_branch = &if_statement.then_branch;
+                // And/Or events in the then branch change the condition of the events after them, and of the else branch
+                let mut test = if_statement.test.clone();
+                let mut then_start = 0;
+                let mut then_goto = false;
+                for (j, then_event) in then_branch.events.iter().enumerate() {
+                    let (operator, expression) = match then_event {
+                        EventAst::IfStatementAnd (expression) => (ComparisonOperator::And, expression),
+                        EventAst::IfStatementOr  (expression) => (ComparisonOperator::Or,  expression),
+                        _ => continue,
+                    };
+                    let events = Block { events: then_branch.events[then_start..j].to_vec() };
+                    then_goto |= branch_reachability(&events, then_start, test.clone(), conditions, reachable && !then_goto, path, results);
+                    test = Expression::Binary (BinaryExpression { left: Box::new(test), right: Box::new(expression.clone()), operator });
+                    then_start = j + 1;
+                }
+                let events = Block { events: then_branch.events[then_start..].to_vec() };
+                branch_reachability(&events, then_start, test.clone(), conditions, reachable && !then_goto, path, results);
+
+                if let Some(else_branch) = &if_statement.else_branch {
+                    branch_reachability(else_branch, then_branch.events.len(), Expression::Not (Box::new(test)), conditions, reachable, path, results);
+                }
+                false
+            }
+            EventAst::Goto (_) => true,
+            _ => false,
+        };
+        if event_goto {
+            reachable = false;
+            goto = true;
+        }
+
+        path.pop();
+    }
+    goto
+}
+
+/// Visits a block that is executed when `test` is true.
+/// Returns true if the block contains a Goto.
+fn branch_reachability(block: &Block, first_index: usize, test: Expression, conditions: &mut Vec<Expression>, reachable: bool, path: &mut Vec<usize>, results: &mut Vec<HitboxReachability>) -> bool {
+    match evaluate_constant(&test) {
+        Some(true) => block_reachability(block, first_index, conditions, reachable, path, results),
+        Some(false) => block_reachability(block, first_index, conditions, false, path, results),
+        None => {
+            conditions.push(test);
+            let goto = block_reachability(block, first_index, conditions, reachable, path, results);
+            conditions.pop();
+            goto
+        }
+    }
+}
+
+/// Returns the result of the expression if it does not depend on the game state
+fn evaluate_constant(expression: &Expression) -> Option<bool> {
+    let number = |expression: &Expression| match expression {
+        Expression::Value (value)  => Some(*value as f32),
+        Expression::Scalar (value) => Some(*value),
+        _ => None,
+    };
+
+    match expression {
+        Expression::Nullary (Requirement::Always) => Some(true),
+        Expression::Not (expression) => evaluate_constant(expression).map(|x| !x),
+        Expression::Binary (binary) => {
+            match binary.operator {
+                ComparisonOperator::And => {
+                    match (evaluate_constant(&binary.left), evaluate_constant(&binary.right)) {
+                        (Some(false), _) | (_, Some(false)) => Some(false),
+                        (Some(true), Some(true)) => Some(true),
+                        _ => None,
+                    }
+                }
+                ComparisonOperator::Or => {
+                    match (evaluate_constant(&binary.left), evaluate_constant(&binary.right)) {
+                        (Some(true), _) | (_, Some(true)) => Some(true),
+                        (Some(false), Some(false)) => Some(false),
+                        _ => None,
+                    }
+                }
+                ref operator => {
+                    let left = number(&binary.left)?;
+                    let right = number(&binary.right)?;
+                    match operator {
+                        ComparisonOperator::LessThan           => Some(left <  right),
+                        ComparisonOperator::LessThanOrEqual    => Some(left <= right),
+                        ComparisonOperator::Equal              => Some(left == right),
+                        ComparisonOperator::NotEqual           => Some(left != right),
+                        ComparisonOperator::GreaterThanOrEqual => Some(left >= right),
+                        ComparisonOperator::GreaterThan        => Some(left >  right),
+                        _ => None,
+                    }
+                }
+            }
+        }
+        _ => None,
+    }
+}
+
+/// An event in one script that runs another script.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct CallEdge {
+    /// `Script::offset` of the calling script
+    pub caller: i32,
+    /// Index into the events of the calling script
+    pub event: usize,
+    /// The offset of the called script, compare with `Script::offset`
+    pub callee: i32,
+    pub kind: CallKind,
+}
+
+impl CallEdge {
+    /// Describes the edge for display, naming the scripts that have a symbol e.g. `@0x1A0 -> @0x9E48 (KnockbackCheck)`.
+    /// Calls other than subroutines include their kind e.g. `@0x1A0 -> @0x9E48 (KnockbackCheck) [Concurrent]`.
+    pub fn label(&self, symbols: &SymbolTable) -> String {
+        let label = format!("{} -> {}", symbols.format_offset(self.caller), symbols.format_offset(self.callee));
+        match self.kind {
+            CallKind::Subroutine => label,
+            kind => format!("{} [{:?}]", label, kind),
+        }
+    }
+}
+
+/// Finds every call between the scripts, in the order of `scripts` and their events.
+///
+/// Concurrent loops started by CallEveryFrame are included with `CallKind::Concurrent` so they can be told apart from subroutines, as they run alongside the caller every frame.
+/// Include the fragment scripts e.g. `ArcSakurai::fragment_scripts` to also get the calls made by fragments.
+pub fn call_graph(scripts: &[&Script]) -> Vec<CallEdge> {
+    let mut edges = vec!();
+    for script in scripts {
+        for (i, event) in script.events.iter().enumerate() {
+            if let Some((kind, callee)) = event.call_offset() {
+                edges.push(CallEdge { caller: script.offset, event: i, callee, kind });
+            }
+        }
+    }
+    edges
+}
+
+/// Whether a cycle of scripts gives the game a chance to advance a frame on every time around, see `script_cycles`.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum CycleKind {
+    /// Every path around the cycle waits for a frame, this is a legitimate loop e.g. a move that repeats until the button is released.
+    FrameGated,
+    /// A path around the cycle never waits, the game freezes if it is ever taken.
+    Tight,
+}
+
+/// Scripts that call each other in a cycle through Subroutine or Goto events.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct ScriptCycle {
+    /// Each script calls the next one, the last script calls the first one.
+    /// A script that goes to itself is a cycle of a single script.
+    pub scripts: Vec<ScriptLocation>,
+    pub kind: CycleKind,
+}
+
+/// The most cycles returned by `script_cycles`
+const MAX_SCRIPT_CYCLES: usize = 100;
+
+/// The most calls followed while searching for cycles, the search stops early on fighters with an enormous number of paths between scripts.
+const MAX_CYCLE_SEARCH_STEPS: usize = 1_000_000;
+
+/// Finds the elementary cycles between the scripts of the fighter, following Subroutine and Goto events.
+///
+/// Each cycle is returned once, starting at the script that comes first in the order of `validation::fighter_scripts`.
+/// At most 100 cycles are returned.
+/// Concurrent and independent subroutines run in their own thread, so they are not part of any cycle.
+///
+/// A cycle is `CycleKind::FrameGated` when one of its scripts waits on every path from its start to the call of the next script.
+/// Only synchronous timers of more than 0 frames and LoopRest events wait:
+/// an asynchronous timer waits for a fixed frame of the animation, so after the first time around it no longer waits at all.
+/// Timers inside an if statement or switch case only count for the calls inside that branch, unless every branch of the if statement waits.
+/// Timers inside a subroutine called before the next script are not counted.
+pub fn script_cycles(fighter: &Fighter) -> Vec<ScriptCycle> {
+    let mut locations: Vec<ScriptLocation> = vec!();
+    let mut offsets: Vec<i32> = vec!();
+    let mut calls: Vec<Vec<(i32, bool)>> = vec!();
+    for (location, script) in validation::fighter_scripts(fighter) {
+        // the same script can be referenced from multiple places, empty scripts cannot call anything
+        if script.events.is_empty() || offsets.contains(&script.offset) {
+            continue;
+        }
+        let mut script_calls = vec!();
+        block_calls(&ScriptAst::new(script).block, false, &mut script_calls);
+        locations.push(location);
+        offsets.push(script.offset);
+        calls.push(script_calls);
+    }
+
+    let successors: Vec<Vec<usize>> = calls.iter().map(|script_calls| {
+        let mut successors: Vec<usize> = script_calls.iter()
+            .filter_map(|(callee, _)| offsets.iter().position(|x| x == callee))
+            .collect();
+        successors.sort_unstable();
+        successors.dedup();
+        successors
+    }).collect();
+
+    elementary_cycles(&successors).into_iter().map(|cycle| {
+        let gated = cycle.iter().enumerate().any(|(i, script)| {
+            let next = offsets[cycle[(i + 1) % cycle.len()]];
+            calls[*script].iter().filter(|(callee, _)| *callee == next).all(|(_, gated)| *gated)
+        });
+        ScriptCycle {
+            scripts: cycle.iter().map(|x| locations[*x].clone()).collect(),
+            kind:    if gated { CycleKind::FrameGated } else { CycleKind::Tight },
+        }
+    }).collect()
+}
+
+/// Adds the offset of every script called by a Subroutine or Goto to `calls`, along with whether every path to the call waits for a frame.
+/// `gated` is true when every path to the start of the block waits.
+/// Returns true when every path to the end of the block waits.
+fn block_calls(block: &Block, gated: bool, calls: &mut Vec<(i32, bool)>) -> bool {
+    let mut gated = gated;
+    // whether every path waits at the start of each enclosing switch statement
+    let mut switches: Vec<bool> = vec!();
+    for event in &block.events {
+        match event {
+            EventAst::SyncWait (frames) if *frames > 0.0 => gated = true,
+            EventAst::LoopRest => gated = true,
+            EventAst::Subroutine (offset) |
+            EventAst::Goto (offset) => calls.push((offset.offset, gated)),
+            // the loop body runs at least once, and later iterations start with at least as many waits as the first
+            EventAst::ForLoop (for_loop) => gated = block_calls(&for_loop.block, gated, calls),
+            EventAst::IfStatement (if_statement) => {
+                let then_gated = block_calls(&if_statement.then_branch, gated, calls);
+                let else_gated = match &if_statement.else_branch {
+                    Some(else_branch) => block_calls(else_branch, gated, calls),
+                    None => gated,
+                };
+                gated = then_gated && else_gated;
+            }
+            EventAst::Switch (_, _) => switches.push(gated),
+            EventAst::Case (_) | EventAst::DefaultCase => gated = switches.last().cloned().unwrap_or(gated),
+            EventAst::EndSwitch => gated = switches.pop().unwrap_or(gated),
+            _ => { }
+        }
+    }
+    gated
+}
+
+/// Returns every elementary cycle of the graph, as the indexes of the nodes in the cycle starting from the lowest index.
+/// `successors` contains the nodes that each node has an edge to.
+fn elementary_cycles(successors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut cycles = vec!();
+    let mut steps = 0;
+    let mut on_path = vec!(false; successors.len());
+    // Every cycle is found from its lowest node, by only visiting higher nodes
+    for start in 0..successors.len() {
+        let mut path = vec!(start);
+        // the index into the successors of each node in the path that is visited next
+        let mut next = vec!(0);
+        on_path[start] = true;
+        while let Some(node) = path.last().cloned() {
+            let successor_index = next.last_mut().unwrap();
+            match successors[node].get(*successor_index) {
+                Some(&successor) => {
+                    *successor_index += 1;
+                    steps += 1;
+                    if steps > MAX_CYCLE_SEARCH_STEPS {
+                        return cycles;
+                    }
+                    if successor == start {
+                        cycles.push(path.clone());
+                        if cycles.len() >= MAX_SCRIPT_CYCLES {
+                            return cycles;
+                        }
+                    } else if successor > start && !on_path[successor] {
+                        on_path[successor] = true;
+                        path.push(successor);
+                        next.push(0);
+                    }
+                }
+                None => {
+                    on_path[node] = false;
+                    path.pop();
+                    next.pop();
+                }
+            }
+        }
+    }
+    cycles
+}
+
+/// An argument of an event that reads or writes a variable, see `variable_usage`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct VariableAccess {
+    pub location: ScriptLocation,
+    /// Index into `Script::events`
+    pub event: usize,
+    /// Index into `Event::arguments`
+    pub argument: usize,
+}
+
+/// Every event argument that reads or writes a single variable.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct VariableUsage {
+    pub variable: Variable,
+    pub readers: Vec<VariableAccess>,
+    pub writers: Vec<VariableAccess>,
+}
+
+/// The readers and writers of each variable used by a fighter, in the order the variables are first used.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct VariableUsageMap {
+    pub variables: Vec<VariableUsage>,
+}
+
+impl VariableUsageMap {
+    /// Variables are the same when their memory type, data type and address all match
+    pub fn get(&self, variable: &Variable) -> Option<&VariableUsage> {
+        self.variables.iter().find(|x| &x.variable == variable)
+    }
+}
+
+/// Finds where each variable is read and written across all scripts of the fighter, classified by `Event::argument_roles`.
+///
+/// Arguments with the `ArgRole::ReadWrite` role are listed as both a reader and a writer.
+/// Variables in the arguments of events without argument roles are not included.
+pub fn variable_usage(fighter: &Fighter) -> VariableUsageMap {
+    let mut variables: Vec<VariableUsage> = vec!();
+    for (location, script) in validation::fighter_scripts(fighter) {
+        for (event_index, event) in script.events.iter().enumerate() {
+            for (argument_index, (argument, role)) in event.arguments.iter().zip(event.argument_roles()).enumerate() {
+                let variable = match argument {
+                    Argument::Variable (variable) => variable,
+                    _ => continue,
+                };
+                let (read, write) = match role {
+                    ArgRole::Read      => (true, false),
+                    ArgRole::Write     => (false, true),
+                    ArgRole::ReadWrite => (true, true),
+                    ArgRole::Target | ArgRole::Immediate => continue,
+                };
+
+                let usage = match variables.iter().position(|x| &x.variable == variable) {
+                    Some(index) => &mut variables[index],
+                    None => {
+                        variables.push(VariableUsage { variable: variable.clone(), readers: vec!(), writers: vec!() });
+                        variables.last_mut().unwrap()
+                    }
+                };
+                let access = VariableAccess { location: location.clone(), event: event_index, argument: argument_index };
+                if read {
+                    usage.readers.push(access.clone());
+                }
+                if write {
+                    usage.writers.push(access);
+                }
+            }
+        }
+    }
+    VariableUsageMap { variables }
+}
+
+/// A LongtermAccess variable used by a fighter, see `state_variables`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct StateVariable {
+    pub variable: Variable,
+    pub readers: Vec<VariableAccess>,
+    pub writers: Vec<VariableAccess>,
+    /// The variable is written by a script run when the fighter enters the stage:
+    /// the fighters override of the Entrance action or a subaction played by it e.g. EntryR.
+    pub initialized_in_entry: bool,
+}
+
+/// The action played when the fighter enters the stage
+const ENTRANCE_ACTION: u32 = 0x10E;
+
+/// Returns the LongtermAccess variables used by the fighter, most used first.
+///
+/// LongtermAccess variables keep their value between actions, so fighters use them for state such as Lucario's aura or meters added by mods.
+/// Built on `variable_usage`, so the same events are included.
+/// Variables with the same number of readers and writers are in the order they are first used.
+pub fn state_variables(fighter: &Fighter) -> Vec<StateVariable> {
+    let subaction_names: Vec<&str> = fighter.get_fighter_data()
+        .map(|x| x.subaction_flags.iter().map(|x| x.name.as_str()).collect())
+        .unwrap_or_default();
+    let in_entry = |access: &VariableAccess| match &access.location {
+        ScriptLocation::EntryActionOverride (action) => *action == ENTRANCE_ACTION,
+        ScriptLocation::Subaction { index, .. } => subaction_names.get(*index).map(|x| x.starts_with("Entry")).unwrap_or(false),
+        _ => false,
+    };
+
+    let mut variables: Vec<StateVariable> = variable_usage(fighter).variables.into_iter()
+        .filter(|x| x.variable.memory_type == VariableMemoryType::LongtermAccess)
+        .map(|x| StateVariable {
+            initialized_in_entry: x.writers.iter().any(in_entry),
+            variable:             x.variable,
+            readers:              x.readers,
+            writers:              x.writers,
+        })
+        .collect();
+    variables.sort_by_key(|x| std::cmp::Reverse(x.readers.len() + x.writers.len()));
+    variables
+}
+
+/// A move that every fighter has, identified across fighters by the vanilla subaction naming conventions.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum MoveName {
+    Jab,
+    ForwardTilt,
+    UpTilt,
+    DownTilt,
+    ForwardSmash,
+    UpSmash,
+    DownSmash,
+    DashAttack,
+    Aerial (Aerial),
+    Grab,
+    NeutralSpecial,
+    SideSpecial,
+    UpSpecial,
+    DownSpecial,
+}
+
+impl MoveName {
+    /// The subactions that may implement the move, in order of preference.
+    ///
+    /// Only the first hit of multi-hit jabs is used and angled tilts and smashes use their unangled variant,
+    /// so that the same part of the move is compared across fighters.
+    pub fn subaction_names(&self) -> &'static [&'static str] {
+        match self {
+            MoveName::Jab            => &["Attack11"],
+            MoveName::ForwardTilt    => &["AttackS3S", "AttackS3"],
+            MoveName::UpTilt         => &["AttackHi3"],
+            MoveName::DownTilt       => &["AttackLw3"],
+            MoveName::ForwardSmash   => &["AttackS4S", "AttackS4"],
+            MoveName::UpSmash        => &["AttackHi4"],
+            MoveName::DownSmash      => &["AttackLw4"],
+            MoveName::DashAttack     => &["AttackDash"],
+            MoveName::Aerial (Aerial::Nair) => &["AttackAirN"],
+            MoveName::Aerial (Aerial::Fair) => &["AttackAirF"],
+            MoveName::Aerial (Aerial::Bair) => &["AttackAirB"],
+            MoveName::Aerial (Aerial::Uair) => &["AttackAirHi"],
+            MoveName::Aerial (Aerial::Dair) => &["AttackAirLw"],
+            MoveName::Grab           => &["Catch"],
+            MoveName::NeutralSpecial => &["SpecialN", "SpecialNStart"],
+            MoveName::SideSpecial    => &["SpecialS", "SpecialSStart"],
+            MoveName::UpSpecial      => &["SpecialHi", "SpecialHiStart"],
+            MoveName::DownSpecial    => &["SpecialLw", "SpecialLwStart"],
+        }
+    }
+}
+
+/// A value that can be compared between fighters for a single move.
+/// All frames start at 1.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum Metric {
+    /// The first frame a hitbox is active
+    Startup,
+    /// The length of the subactions animation
+    TotalFrames,
+    /// The highest damage of the hitboxes created on the first active frame
+    Damage,
+    /// The first frame the fighter can act again, taken from the first Allow Interrupts event
+    Faf,
+    /// Landing lag from the fighters attributes, only aerials have landing lag
+    LandingLag,
+}
+
+/// The value of a metric for a move across a roster, sorted from lowest to highest.
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct RosterTable {
+    pub move_name: MoveName,
+    pub metric: Metric,
+    pub rows: Vec<RosterRow>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct RosterRow {
+    pub fighter: String,
+    /// The subaction the value was taken from, None when the fighter lacks the move
+    pub subaction: Option<String>,
+    /// None when the fighter lacks the move or the metric does not apply to the move
+    pub value: Option<f32>,
+    /// Set when the value is None because the fighter was loaded without the data the metric needs
+    pub not_loaded: Option<NotLoaded>,
+}
+
+/// Compares a metric of a move across the fighters.
+///
+/// Values are calculated from the main subaction scripts, so they are only as accurate as `script::normalize_timers`.
+/// Followers and transformations e.g. Nana and WarioMan get their own rows.
+/// Rows are sorted by value, fighters without a value are sorted last.
+pub fn roster_table(fighters: &[Fighter], move_name: MoveName, metric: Metric) -> RosterTable {
+    let fighters = fighters.iter().flat_map(|fighter| {
+        std::iter::once(fighter)
+            .chain(fighter.follower.as_ref().map(|x| &x.fighter))
+            .chain(fighter.transformation.as_deref())
+    });
+    let mut rows: Vec<RosterRow> = fighters.map(|fighter| {
+        let subaction = fighter.get_fighter_data().and_then(|fighter_data| {
+            move_name.subaction_names().iter().find_map(|name| {
+                fighter_data.subaction_flags.iter().position(|x| x.name == *name)
+                    .map(|index| (index, fighter_data))
+            })
+        });
+        match subaction {
+            Some((index, fighter_data)) => {
+                let name = fighter_data.subaction_flags[index].name.clone();
+                let script = fighter_data.subaction_main.get(index);
+                let mut not_loaded = None;
+                let value = match metric {
+                    Metric::Startup     => script.and_then(first_hitboxes).map(|(frame, _)| frame),
+                    Metric::Damage      => script.and_then(first_hitboxes).map(|(_, damage)| damage),
+                    Metric::Faf         => script.and_then(first_interrupt),
+                    Metric::TotalFrames => match fighter.get_animations() {
+                        Ok(animations) => animations.into_iter().find(|x| x.name == name).map(|x| x.num_frames as f32),
+                        Err(err) => {
+                            not_loaded = Some(err);
+                            None
+                        }
+                    }
+                    Metric::LandingLag  => match move_name {
+                        MoveName::Aerial (aerial) => Some(landing_lag(&fighter_data.attributes, aerial)),
+                        _ => None,
+                    }
+                };
+                RosterRow { fighter: fighter.cased_name.clone(), subaction: Some(name), value, not_loaded }
+            }
+            None => RosterRow { fighter: fighter.cased_name.clone(), subaction: None, value: None, not_loaded: None },
+        }
+    }).collect();
+
+    rows.sort_by(|a, b| match (a.value, b.value) {
+        (Some(a_value), Some(b_value)) => a_value.total_cmp(&b_value).then_with(|| a.fighter.cmp(&b.fighter)),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => a.fighter.cmp(&b.fighter),
+    });
+
+    RosterTable { move_name, metric, rows }
+}
+
+impl RosterTable {
+    /// Formats the table as a markdown table, missing values are shown as `-`
+    pub fn to_markdown(&self) -> String {
+        let mut output = format!("| Fighter | Subaction | {:?} {:?} |\n|---|---|---|\n", self.move_name, self.metric);
+        for row in &self.rows {
+            output.push_str(&format!("| {} | {} | {} |\n",
+                row.fighter,
+                row.subaction.as_deref().unwrap_or("-"),
+                row.value.map(|x| x.to_string()).unwrap_or_else(|| String::from("-")),
+            ));
+        }
+        output
+    }
+
+    /// Formats the table as CSV with a header row, missing values are left empty
+    pub fn to_csv(&self) -> String {
+        let mut output = String::from("fighter,subaction,value\n");
+        for row in &self.rows {
+            output.push_str(&format!("{},{},{}\n",
+                row.fighter,
+                row.subaction.as_deref().unwrap_or(""),
+                row.value.map(|x| x.to_string()).unwrap_or_default(),
+            ));
+        }
+        output
+    }
+}
+
+/// Returns the frame of the first Allow Interrupts event
+pub(crate) fn first_interrupt(script: &Script) -> Option<f32> {
+    normalize_timers(script).events.into_iter()
+        .find(|x| x.event.namespace == 0x64 && x.event.code == 0x00)
+        .map(|x| x.frame.as_f32())
+}
+
+/// Returns the landing lag of the aerial from the fighters attributes
+pub(crate) fn landing_lag(attributes: &FighterAttributes, aerial: Aerial) -> f32 {
+    match aerial {
+        Aerial::Nair => attributes.nair_landing_lag,
+        Aerial::Fair => attributes.fair_landing_lag,
+        Aerial::Bair => attributes.bair_landing_lag,
+        Aerial::Uair => attributes.uair_landing_lag,
+        Aerial::Dair => attributes.dair_landing_lag,
+    }
+}
+
+/// Returns the first frame a hitbox is created on and the highest damage of the hitboxes created on that frame
+pub(crate) fn first_hitboxes(script: &Script) -> Option<(f32, f32)> {
+    let mut first: Option<(f32, f32)> = None;
+    for timed_event in normalize_timers(script).events {
+        let event = timed_event.event;
+        if event.namespace != 0x06 || (event.code != 0x00 && event.code != 0x15) {
+            continue;
+        }
+        let damage = match event.arguments.get(1) {
+            Some(Argument::Value (damage)) => *damage as f32,
+            _ => 0.0,
+        };
+        match &mut first {
+            Some((frame, max_damage)) if *frame == timed_event.frame.as_f32() => *max_damage = max_damage.max(damage),
+            Some(_) => break,
+            None => first = Some((timed_event.frame.as_f32(), damage)),
+        }
+    }
+    first
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{ScriptBuilder, TestFighterBuilder, damage};
+
+    #[test]
+    fn clean_script() {
+        let script = ScriptBuilder::new()
+            .async_timer(5.0)
+            .hitbox(damage(10).id(0))
+            .hitbox(damage(8).id(1))
+            .sync_timer(2.0)
+            .delete_hitbox(0)
+            .hitbox(damage(6).id(0))
+            .sync_timer(2.0)
+            .terminate_collisions()
+            .build();
+        assert_eq!(hitbox_lint(&script), vec!());
+    }
+
+    #[test]
+    fn id_reused() {
+        let script = ScriptBuilder::new()
+            .hitbox(damage(10).id(0))
+            .sync_timer(2.0)
+            .hitbox(damage(6).id(0))
+            .terminate_collisions()
+            .build();
+        assert_eq!(hitbox_lint(&script), vec!(
+            HitboxLint::IdReused { hitbox_id: 0, previous_event: 0, event: 2 },
+        ));
+    }
+
+    #[test]
+    fn not_terminated() {
+        let script = ScriptBuilder::new()
+            .hitbox(damage(10).id(2))
+            .hitbox(damage(10).id(1))
+            .sync_timer(2.0)
+            .delete_hitbox(2)
+            .build();
+        assert_eq!(hitbox_lint(&script), vec!(
+            HitboxLint::NotTerminated { hitbox_id: 1, event: 1 },
+        ));
+    }
+
+    #[test]
+    fn mixed_kinds() {
+        let script = ScriptBuilder::new()
+            .special_hitbox(damage(10).id(3))
+            .terminate_collisions()
+            .hitbox(damage(10).id(3))
+            .terminate_collisions()
+            .build();
+        let lints = hitbox_lint(&script);
+        assert_eq!(lints, vec!(
+            HitboxLint::MixedKinds { hitbox_id: 3, normal_event: 2, special_event: 0 },
+        ));
+        assert_eq!(lints[0].event_indices(), vec!(2, 0));
+    }
+
+    #[test]
+    fn zero_size() {
+        let script = ScriptBuilder::new()
+            .hitbox(damage(10).id(0).size(0.0))
+            .hitbox(damage(10).id(1).size(0.5))
+            .terminate_collisions()
+            .build();
+        assert_eq!(hitbox_lint(&script), vec!(
+            HitboxLint::ZeroSize { hitbox_id: 0, event: 0 },
+        ));
+    }
+
+    #[test]
+    fn loop_findings_reported_once() {
+        let script = ScriptBuilder::new()
+            .event(0x00, 0x04, vec!(Argument::Value (3))) // loop 3 times
+            .hitbox(damage(10).id(0))
+            .sync_timer(1.0)
+            .event(0x00, 0x05, vec!()) // loop end
+            .terminate_collisions()
+            .build();
+        assert_eq!(hitbox_lint(&script), vec!(
+            HitboxLint::IdReused { hitbox_id: 0, previous_event: 1, event: 1 },
+        ));
+    }
+
+    fn condition(builder: ScriptBuilder, code: u8, ty: Requirement, flip: bool) -> ScriptBuilder {
+        builder.event(0x00, code, vec!(Argument::Requirement { ty, flip }))
+    }
+
+    /// Returns the (event_path, hitbox_id, condition) of each hitbox event, ordered by hitbox id
+    fn reachability(script: ScriptBuilder) -> Vec<(Vec<usize>, u8, HitboxCondition)> {
+        let mut result: Vec<_> = hitbox_reachability(&ScriptAst::new(&script.build())).into_iter()
+            .map(|x| (x.event_path, x.hitbox_id, x.condition))
+            .collect();
+        result.sort_by_key(|x| x.1);
+        result
+    }
+
+    #[test]
+    fn reachability_classifications() {
+        let script = ScriptBuilder::new().hitbox(damage(10).id(0));
+        let script = condition(script, 0x0A, Requirement::OnGround, false)
+            .hitbox(damage(10).id(1));
+        let script = condition(script, 0x0B, Requirement::InAir, false)
+            .hitbox(damage(10).id(2))
+            .event(0x00, 0x0E, vec!())
+            .hitbox(damage(10).id(3))
+            .event(0x00, 0x0F, vec!());
+        let script = condition(script, 0x0A, Requirement::Always, true)
+            .hitbox(damage(10).id(4))
+            .event(0x00, 0x0E, vec!())
+            .hitbox(damage(10).id(5))
+            .event(0x00, 0x0F, vec!());
+        let script = condition(script, 0x0A, Requirement::Always, false)
+            .hitbox(damage(10).id(6))
+            .event(0x00, 0x0F, vec!())
+            .event(0x00, 0x09, vec!(Argument::Value (0x100)))
+            .hitbox(damage(10).id(7));
+
+        let result = reachability(script);
+        let paths: Vec<_> = result.iter().map(|x| x.0.clone()).collect();
+        assert_eq!(paths, vec!(vec!(0), vec!(1, 0), vec!(1, 2), vec!(1, 3), vec!(2, 0), vec!(2, 1), vec!(3, 0), vec!(5)));
+
+        let kinds: Vec<_> = result.iter().map(|x| x.2.kind()).collect();
+        assert_eq!(kinds, vec!("unconditional", "conditional", "conditional", "conditional", "unreachable", "unconditional", "unconditional", "unreachable"));
+
+        match &result[1].2 {
+            HitboxCondition::Conditional (conditions) => assert!(matches!(conditions.as_slice(), [Expression::Nullary (Requirement::OnGround)])),
+            condition => panic!("Unexpected condition {:?}", condition),
+        }
+        match &result[2].2 {
+            HitboxCondition::Conditional (conditions) => match conditions.as_slice() {
+                [Expression::Binary (binary)] => {
+                    assert!(matches!(binary.operator, ComparisonOperator::And));
+                    assert!(matches!(*binary.left, Expression::Nullary (Requirement::OnGround)));
+                    assert!(matches!(*binary.right, Expression::Nullary (Requirement::InAir)));
+                }
+                conditions => panic!("Unexpected conditions {:?}", conditions),
+            }
+            condition => panic!("Unexpected condition {:?}", condition),
+        }
+        match &result[3].2 {
+            HitboxCondition::Conditional (conditions) => assert!(matches!(conditions.as_slice(), [Expression::Not (inner)] if matches!(**inner, Expression::Binary (_)))),
+            condition => panic!("Unexpected condition {:?}", condition),
+        }
+    }
+
+    #[test]
+    fn reachability_flipped_and_nested() {
+        // if !OnGround { if 1 == 2 { hitbox 0 } hitbox 1; goto; hitbox 2 } hitbox 3
+        let script = condition(ScriptBuilder::new(), 0x0A, Requirement::OnGround, true)
+            .event(0x00, 0x0A, vec!(Argument::Requirement { ty: Requirement::Comparison, flip: false }, Argument::Value (1), Argument::Value (2), Argument::Value (2)))
+            .hitbox(damage(10).id(0))
+            .event(0x00, 0x0F, vec!())
+            .hitbox(damage(10).id(1))
+            .event(0x00, 0x09, vec!(Argument::Value (0x100)))
+            .hitbox(damage(10).id(2))
+            .event(0x00, 0x0F, vec!())
+            .hitbox(damage(10).id(3));
+
+        let result = reachability(script);
+        let kinds: Vec<_> = result.iter().map(|x| x.2.kind()).collect();
+        assert_eq!(kinds, vec!("unreachable", "conditional", "unreachable", "unconditional"));
+        match &result[1].2 {
+            HitboxCondition::Conditional (conditions) => assert!(matches!(conditions.as_slice(), [Expression::Not (inner)] if matches!(**inner, Expression::Nullary (Requirement::OnGround)))),
+            condition => panic!("Unexpected condition {:?}", condition),
+        }
+    }
+
+    fn roster() -> Vec<Fighter> {
+        let mario = TestFighterBuilder::new("Mario")
+            .subaction("Attack11", |s| s
+                .async_timer(2.0)
+                .hitbox(damage(3).id(0))
+                .hitbox(damage(2).id(1))
+                .sync_timer(2.0)
+                .terminate_collisions()
+                .async_timer(17.0)
+                .event(0x64, 0x00, vec!()) // allow interrupts
+            )
+            .subaction("AttackAirN", |s| s)
+            .attribute_f32(0x140, 15.0) // nair landing lag
+            .build();
+        let luigi = TestFighterBuilder::new("Luigi")
+            .subaction("Attack11", |s| s
+                .hitbox(damage(2).id(0))
+                .sync_timer(3.0)
+                .terminate_collisions()
+            )
+            .build();
+        let pit = TestFighterBuilder::new("Pit")
+            .subaction("Wait1", |s| s)
+            .build();
+        vec!(mario, luigi, pit)
+    }
+
+    #[test]
+    fn roster_table_sorted() {
+        let table = roster_table(&roster(), MoveName::Jab, Metric::Startup);
+        assert_eq!(table.rows, vec!(
+            RosterRow { fighter: "Luigi".into(), subaction: Some("Attack11".into()), value: Some(1.0), not_loaded: None },
+            RosterRow { fighter: "Mario".into(), subaction: Some("Attack11".into()), value: Some(3.0), not_loaded: None },
+            RosterRow { fighter: "Pit".into(),   subaction: None,                    value: None, not_loaded: None },
+        ));
+        assert_eq!(table.to_csv(), "fighter,subaction,value\nLuigi,Attack11,1\nMario,Attack11,3\nPit,,\n");
+        assert_eq!(table.to_markdown().lines().last(), Some("| Pit | - | - |"));
+
+        let damage: Vec<_> = roster_table(&roster(), MoveName::Jab, Metric::Damage).rows.iter().map(|x| x.value).collect();
+        assert_eq!(damage, vec!(Some(2.0), Some(3.0), None));
+
+        let faf: Vec<_> = roster_table(&roster(), MoveName::Jab, Metric::Faf).rows.iter().map(|x| x.value).collect();
+        assert_eq!(faf, vec!(Some(18.0), None, None));
+    }
+
+    #[test]
+    fn roster_table_transformations() {
+        let mut roster = roster();
+        roster[0].transformation = Some(Box::new(TestFighterBuilder::new("WarioMan")
+            .subaction("Attack11", |s| s.async_timer(1.0).hitbox(damage(20)))
+            .build()));
+        let table = roster_table(&roster, MoveName::Jab, Metric::Startup);
+        let fighters: Vec<_> = table.rows.iter().map(|x| (x.fighter.as_str(), x.value)).collect();
+        assert_eq!(fighters, vec!(("Luigi", Some(1.0)), ("WarioMan", Some(2.0)), ("Mario", Some(3.0)), ("Pit", None)));
+    }
+
+    #[test]
+    fn frame_speed_startup() {
+        use crate::script::FixedPoint;
+        let frame_speed = |speed| Argument::Scalar (FixedPoint::from_frames(speed));
+
+        // an aerial slowed to 0.9 speed for its startup: dividing 18 by 0.9 gives frame 21, but the accumulated timer only reaches 18 on game frame 22
+        let aerial = ScriptBuilder::new()
+            .event(0x04, 0x07, vec!(frame_speed(0.9)))
+            .async_timer(18.0)
+            .hitbox(damage(9).id(0))
+            .event(0x04, 0x07, vec!(frame_speed(1.0)))
+            .sync_timer(3.0)
+            .terminate_collisions()
+            .build();
+        assert_eq!(first_hitboxes(&aerial), Some((22.0, 9.0)));
+
+        // a synchronous timer at 0.7 speed
+        let slowed = ScriptBuilder::new()
+            .event(0x04, 0x07, vec!(frame_speed(0.7)))
+            .sync_timer(7.0)
+            .event(0x64, 0x00, vec!())
+            .build();
+        assert_eq!(first_interrupt(&slowed), Some(12.0));
+
+        // the speed changes after the timer has already advanced
+        let sped_up = ScriptBuilder::new()
+            .async_timer(2.0)
+            .event(0x04, 0x07, vec!(frame_speed(1.2)))
+            .async_timer(14.0)
+            .hitbox(damage(4).id(0))
+            .build();
+        assert_eq!(first_hitboxes(&sped_up), Some((14.0, 4.0)));
+
+        // exactly representable speeds are unaffected
+        let halved = ScriptBuilder::new()
+            .event(0x04, 0x07, vec!(frame_speed(0.5)))
+            .async_timer(9.0)
+            .hitbox(damage(1).id(0))
+            .build();
+        assert_eq!(first_hitboxes(&halved), Some((19.0, 1.0)));
+    }
+
+    #[test]
+    fn roster_table_missing_data() {
+        let table = roster_table(&roster(), MoveName::Aerial (Aerial::Nair), Metric::LandingLag);
+        assert_eq!(table.rows[0], RosterRow { fighter: "Mario".into(), subaction: Some("AttackAirN".into()), value: Some(15.0), not_loaded: None });
+        assert!(table.rows[1..].iter().all(|x| x.subaction.is_none() && x.value.is_none()));
+
+        // no hitboxes and no animation
+        let table = roster_table(&roster(), MoveName::Aerial (Aerial::Nair), Metric::Startup);
+        assert_eq!(table.rows[0].value, None);
+        let table = roster_table(&roster(), MoveName::Jab, Metric::TotalFrames);
+        assert!(table.rows.iter().all(|x| x.value.is_none()));
+        assert_eq!(table.rows[0].not_loaded, Some(NotLoaded::Animations));
+        assert_eq!(table.rows.last().unwrap().not_loaded, None);
+    }
+
+    #[test]
+    fn concurrent_loop_call_graph() {
+        // The concurrent loop runs the last event of the script, which is not the start of any other script so it becomes a fragment
+        let fighter = |fragment_offset| TestFighterBuilder::new("Test")
+            .subaction("AttackS3S", |s| s
+                .async_timer(3.0)
+                .event(0x0D, 0x00, vec!(Argument::Value (1), Argument::Offset (crate::script::Offset { offset: fragment_offset, origin: 0 })))
+                .async_timer(10.0)
+                .event(0x00, 0x02, vec!())
+            )
+            .build();
+        let script_offset = fighter(0).get_fighter_data().unwrap().subaction_main[0].offset;
+        let fragment_offset = script_offset + 3 * 8;
+        let fighter = fighter(fragment_offset);
+
+        let sakurai = fighter.get_fighter_sakurai().unwrap();
+        assert_eq!(sakurai.fragment_scripts.len(), 1);
+        assert_eq!(sakurai.fragment_scripts[0].offset, fragment_offset);
+
+        let script = &fighter.get_fighter_data().unwrap().subaction_main[0];
+        let scripts: Vec<&Script> = std::iter::once(script).chain(sakurai.fragment_scripts.iter()).collect();
+        assert_eq!(call_graph(&scripts), vec!(CallEdge { caller: script_offset, event: 1, callee: fragment_offset, kind: CallKind::Concurrent }));
+    }
+
+    #[test]
+    fn call_edge_labels() {
+        let symbols = SymbolTable::parse("0x9E48 KnockbackCheck").unwrap();
+        let edge = |kind| CallEdge { caller: 0x1A0, event: 0, callee: 0x9E48, kind };
+        assert_eq!(edge(CallKind::Subroutine).label(&symbols), "@0x1A0 -> @0x9E48 (KnockbackCheck)");
+        assert_eq!(edge(CallKind::Concurrent).label(&symbols), "@0x1A0 -> @0x9E48 (KnockbackCheck) [Concurrent]");
+        assert_eq!(edge(CallKind::Goto).label(&SymbolTable::default()), "@0x1A0 -> @0x9E48 [Goto]");
+    }
+
+    #[test]
+    fn script_cycles_of_fighter() {
+        use crate::sakurai::fighter_data::ScriptCollection;
+
+        let call = |s: ScriptBuilder, code, offset| s.event(0x00, code, vec!(Argument::Offset (crate::script::Offset { offset, origin: 0 })));
+        // The first subaction calls the second which goes back to the first, the third goes to itself
+        let fighter = |offsets: [i32; 3], timer: bool| TestFighterBuilder::new("Test")
+            .subaction("SpecialN", |s| call(s, 0x07, offsets[1]))
+            // an asynchronous timer only waits the first time around
+            .subaction("SpecialNLoop", |s| call(if timer { s.sync_timer(1.0) } else { s.async_timer(1.0) }, 0x09, offsets[0]))
+            .subaction("SpecialS", |s| call(s.event(0x01, 0x01, vec!()), 0x09, offsets[2]))
+            .build();
+        let subaction_offsets = |fighter: &Fighter| {
+            let main = &fighter.get_fighter_data().unwrap().subaction_main;
+            [main[0].offset, main[1].offset, main[2].offset]
+        };
+        let offsets = subaction_offsets(&fighter([0; 3], true));
+        let location = |index| ScriptLocation::Subaction { collection: ScriptCollection::Main, index };
+
+        let gated = fighter(offsets, true);
+        assert_eq!(subaction_offsets(&gated), offsets);
+        assert_eq!(gated.script_cycles(), vec!(
+            ScriptCycle { scripts: vec!(location(0), location(1)), kind: CycleKind::FrameGated },
+            ScriptCycle { scripts: vec!(location(2)), kind: CycleKind::FrameGated },
+        ));
+
+        let tight = fighter(offsets, false);
+        assert_eq!(subaction_offsets(&tight), offsets);
+        assert_eq!(tight.script_cycles()[0], ScriptCycle { scripts: vec!(location(0), location(1)), kind: CycleKind::Tight });
+    }
+
+    #[test]
+    fn script_cycle_timer_in_branch() {
+        let if_event = ScriptBuilder::new()
+            .event(0x00, 0x0A, vec!(Argument::Requirement { flip: false, ty: Requirement::AnimationEnd }))
+            .sync_timer(1.0)
+            .event(0x00, 0x0E, vec!());
+        let call = |script: ScriptBuilder| script.event(0x00, 0x09, vec!(Argument::Value (0x100))).build();
+
+        // only waits in the then branch
+        let mut calls = vec!();
+        block_calls(&ScriptAst::new(&call(if_event.clone().event(0x00, 0x0F, vec!()))).block, false, &mut calls);
+        assert_eq!(calls, vec!((0x100, false)));
+
+        // waits in both branches
+        let mut calls = vec!();
+        block_calls(&ScriptAst::new(&call(if_event.sync_timer(2.0).event(0x00, 0x0F, vec!()))).block, false, &mut calls);
+        assert_eq!(calls, vec!((0x100, true)));
+    }
+
+    #[test]
+    fn elementary_cycles_of_graph() {
+        // 0 -> 1 -> 2 -> 0, 1 -> 0, 2 -> 2 and 3 -> 0
+        let successors = vec!(vec!(1), vec!(0, 2), vec!(0, 2), vec!(0));
+        assert_eq!(elementary_cycles(&successors), vec!(vec!(0, 1), vec!(0, 1, 2), vec!(2)));
+    }
+
+    #[test]
+    fn variable_written_then_read() {
+        use crate::sakurai::fighter_data::ScriptCollection;
+        use crate::script::{FixedPoint, VariableDataType, VariableMemoryType};
+
+        let la_float = |address| Argument::Variable (Variable { memory_type: VariableMemoryType::LongtermAccess, data_type: VariableDataType::Float, address });
+        let scalar = |value| Argument::Scalar (FixedPoint::from_frames(value));
+        let fighter = TestFighterBuilder::new("Test")
+            .subaction("Wait1", |s| s)
+            .subaction("SpecialN", |s| s
+                .event(0x12, 0x06, vec!(scalar(2.0), la_float(3)))
+                .event(0x00, 0x0A, vec!(Argument::Requirement { flip: false, ty: Requirement::Comparison }, la_float(3), Argument::Value (4), scalar(1.0)))
+                .event(0x00, 0x0F, vec!())
+            )
+            .build();
+
+        let usage = variable_usage(&fighter);
+        assert_eq!(usage.variables.len(), 1);
+
+        let usage = usage.get(&Variable { memory_type: VariableMemoryType::LongtermAccess, data_type: VariableDataType::Float, address: 3 }).unwrap();
+        let location = ScriptLocation::Subaction { collection: ScriptCollection::Main, index: 1 };
+        assert_eq!(usage.writers, vec!(VariableAccess { location: location.clone(), event: 0, argument: 1 }));
+        assert_eq!(usage.readers, vec!(VariableAccess { location, event: 1, argument: 1 }));
+    }
+
+    #[test]
+    fn state_variables_of_fighter() {
+        use crate::sakurai::fighter_data::ScriptCollection;
+        use crate::script::{FixedPoint, VariableDataType, VariableMemoryType};
+
+        let variable = |memory_type, data_type, address| Argument::Variable (Variable { memory_type, data_type, address });
+        let la_float = variable(VariableMemoryType::LongtermAccess, VariableDataType::Float, 3);
+        let scalar = |value| Argument::Scalar (FixedPoint::from_frames(value));
+        let read = |variable: &Argument| vec!(Argument::Requirement { flip: false, ty: Requirement::Comparison }, variable.clone(), Argument::Value (4), scalar(1.0));
+        let fighter = TestFighterBuilder::new("Test")
+            .subaction("EntryR", |s| s
+                .event(0x12, 0x06, vec!(scalar(100.0), la_float.clone()))
+            )
+            .subaction("SpecialN", |s| s
+                .event(0x00, 0x0A, read(&la_float))
+                .event(0x00, 0x0F, vec!())
+                .event(0x12, 0x06, vec!(scalar(1.0), variable(VariableMemoryType::LongtermAccess, VariableDataType::Float, 7)))
+                .event(0x12, 0x06, vec!(scalar(1.0), variable(VariableMemoryType::RandomAccess, VariableDataType::Float, 1)))
+            )
+            .subaction("SpecialS", |s| s
+                .event(0x00, 0x0A, read(&la_float))
+                .event(0x00, 0x0F, vec!())
+            )
+            .build();
+
+        let variables = fighter.state_variables();
+        assert_eq!(variables.len(), 2);
+
+        assert_eq!(Argument::Variable (variables[0].variable.clone()), la_float);
+        assert_eq!(variables[0].writers.len(), 1);
+        assert_eq!(variables[0].readers.len(), 2);
+        assert_eq!(variables[0].readers[1].location, ScriptLocation::Subaction { collection: ScriptCollection::Main, index: 2 });
+        assert!(variables[0].initialized_in_entry);
+
+        assert_eq!(variables[1].variable.address, 7);
+        assert_eq!(variables[1].writers.len(), 1);
+        assert!(variables[1].readers.is_empty());
+        assert!(!variables[1].initialized_in_entry);
+    }
+}
@@ -1,26 +1,49 @@
+use std::ops::Range;
+
 use crate::bres::*;
+use crate::cancel::{self, CancellationToken};
 use crate::util;
 use crate::sakurai;
-use crate::sakurai::ArcSakurai;
+use crate::sakurai::{ArcSakurai, MemorySection, SectionData};
+use crate::sakurai::fighter_data_common::ArcFighterDataCommon;
+use crate::script::{EventBudget, ScriptLimits};
 use crate::wii_memory::WiiMemory;
 
 use fancy_slice::FancySlice;
 
 pub(crate) fn arc(data: FancySlice, wii_memory: &WiiMemory, item: bool) -> Arc {
+    arc_cancellable(data, wii_memory, &mut EventBudget::new(ScriptLimits::default()), item, None)
+}
+
+/// Same as `arc` but reads scripts within `budget` and stops reading children once `cancel` is cancelled, the returned arc is then incomplete.
+/// Share the budget between all the files of a fighter.
+pub(crate) fn arc_cancellable(data: FancySlice, wii_memory: &WiiMemory, budget: &mut EventBudget, item: bool, cancel: Option<&CancellationToken>) -> Arc {
+    arc_at(data, 0, wii_memory, budget, item, cancel)
+}
+
+/// `file_offset` is the offset of `data` from the start of the data the top level arc is parsed from
+fn arc_at(data: FancySlice, file_offset: usize, wii_memory: &WiiMemory, budget: &mut EventBudget, item: bool, cancel: Option<&CancellationToken>) -> Arc {
     // read the main header
     let num_sub_headers = data.u16_be(6);
     let name = data.str(0x10).unwrap().to_string();
+    phase_span!("arc", name = %name, bytes = data.len());
 
     // read the sub headers
     let mut children = vec!();
     let mut header_index = ARC_HEADER_SIZE;
     for i in 0..num_sub_headers {
+        if cancel::is_cancelled(cancel) {
+            break;
+        }
+
         let mut arc_child = arc_child(data.relative_fancy_slice(header_index..));
+        let data_start = header_index + ARC_CHILD_HEADER_SIZE;
+        let data_end = (data_start + arc_child.size.max(0) as usize).min(data.len());
         if arc_child.redirect_index == -1 {
-            let tag = util::parse_tag(&data.relative_slice(header_index + ARC_CHILD_HEADER_SIZE ..));
-            let child_data = data.relative_fancy_slice(header_index + ARC_CHILD_HEADER_SIZE ..);
+            let tag = util::parse_tag(data.relative_slice(data_start ..));
+            let child_data = data.relative_fancy_slice(data_start ..);
             arc_child.data = match tag.as_ref() {
-                "ARC"  => ArcChildData::Arc(arc(child_data, wii_memory, item)),
+                "ARC"  => ArcChildData::Arc(arc_at(child_data, file_offset + data_start, wii_memory, budget, item, cancel)),
                 "EFLS" => ArcChildData::Efls,
                 "bres" => ArcChildData::Bres(bres(child_data)),
                 "ATKD" => ArcChildData::Atkd,
@@ -28,19 +51,17 @@ pub(crate) fn arc(data: FancySlice, wii_memory: &WiiMemory, item: bool) -> Arc {
                 "REFT" => ArcChildData::Reft,
                 "AIPD" => ArcChildData::Aipd,
                 "W"    => ArcChildData::W,
-                "" if i == 0 => ArcChildData::Sakurai(sakurai::arc_sakurai(data.relative_fancy_slice(header_index + ARC_CHILD_HEADER_SIZE ..), wii_memory, item)),
+                "" if i == 0 => ArcChildData::Sakurai(sakurai::arc_sakurai(child_data, wii_memory, budget, item)),
                 _ => ArcChildData::Unknown
             };
+        } else if arc_child.redirect_index >= 0 {
+            arc_child.data = ArcChildData::Redirect { target_index: arc_child.redirect_index as usize };
+        }
 
-            header_index += ARC_CHILD_HEADER_SIZE + arc_child.size as usize;
+        arc_child.data_range = file_offset + data_start .. file_offset + data_end.max(data_start);
 
-            // align to the next ARC_CHILD_HEADER_SIZE
-            let offset = header_index % ARC_CHILD_HEADER_SIZE;
-            if offset != 0 {
-                header_index += ARC_CHILD_HEADER_SIZE - offset;
-            }
-            children.push(arc_child);
-        }
+        header_index = align(data_start + arc_child.size.max(0) as usize);
+        children.push(arc_child);
     }
 
     Arc { name, children }
@@ -53,45 +74,147 @@ fn arc_child(data: FancySlice) -> ArcChild {
         size:           data.i32_be(4),
         group_index:    data.u8(8),
         redirect_index: data.i16_be(10),
+        data_range:     0..0,
         data:           ArcChildData::Unknown,
     }
 }
 
+/// Align to the next ARC_CHILD_HEADER_SIZE
+fn align(offset: usize) -> usize {
+    let remainder = offset % ARC_CHILD_HEADER_SIZE;
+    if remainder == 0 {
+        offset
+    } else {
+        offset + ARC_CHILD_HEADER_SIZE - remainder
+    }
+}
+
+impl ArcChild {
+    /// The bytes of the child as stored in `file`, the data the top level arc was parsed from.
+    /// The bytes are not kept after parsing, so they are sliced from `file` on each call.
+    /// Returns an empty slice when `file` is shorter than the child.
+    pub fn raw_data<'a>(&self, file: &'a [u8]) -> &'a [u8] {
+        file.get(self.data_range.clone()).unwrap_or(&[])
+    }
+
+    /// The size of the child data as stored in the file
+    pub fn raw_size(&self) -> usize {
+        self.data_range.len()
+    }
+
+    /// The name of a nested arc, None for all other children.
+    pub fn name(&self) -> Option<&str> {
+        match &self.data {
+            ArcChildData::Arc (arc) => Some(&arc.name),
+            _                       => None,
+        }
+    }
+}
+
 impl Arc {
+    /// Returns the child at `index`, following redirects to the child they point at.
+    /// Returns None if the index is out of bounds, a redirect points out of bounds or redirects form a cycle.
+    pub fn resolve(&self, index: usize) -> Option<&ArcChild> {
+        let mut index = index;
+        // a chain longer than the number of children must contain a cycle
+        for _ in 0..=self.children.len() {
+            let child = self.children.get(index)?;
+            match child.data {
+                ArcChildData::Redirect { target_index } => index = target_index,
+                _ => return Some(child),
+            }
+        }
+        None
+    }
+
+    /// Returns every child with the given name along with its index, redirects are resolved before comparing names.
+    /// Arc children do not store a name, only nested arcs are named, so all other children are never matched.
+    ///
+    /// The same data can be returned multiple times when redirects point at it, use the index to tell the entries apart.
+    pub fn children_named(&self, name: &str) -> Vec<(usize, &ArcChild)> {
+        (0..self.children.len())
+            .filter_map(|i| self.resolve(i).map(|child| (i, child)))
+            .filter(|(_, child)| child.name() == Some(name))
+            .collect()
+    }
+
+    /// Returns every script in the arc and its nested arcs that a WiiRD code redirected to RAM, see `ArcSakurai::memory_sections`.
+    pub fn memory_sections(&self) -> Vec<MemorySection> {
+        let mut sections = vec!();
+        for child in &self.children {
+            match &child.data {
+                ArcChildData::Arc (arc)         => sections.extend(arc.memory_sections()),
+                ArcChildData::Sakurai (sakurai) => sections.extend(sakurai.memory_sections()),
+                _ => { }
+            }
+        }
+        sections
+    }
+
+    /// Returns the dataCommon section of a common Fighter.pac
+    pub fn fighter_data_common(&self) -> Option<&ArcFighterDataCommon> {
+        for child in &self.children {
+            if let ArcChildData::Sakurai (sakurai) = &child.data {
+                for section in &sakurai.sections {
+                    if let SectionData::FighterDataCommon (data_common) = &section.data {
+                        return Some(data_common);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Rebuilds the arc from its parsed data, so modifications to nested arcs and BRES are included.
+    ///
+    /// The bytes of the other children are not kept after parsing, so they are written empty.
+    /// Use `compile_with_original` to copy them from the file the arc was parsed from.
     pub fn compile(&self) -> Vec<u8> {
-        // TODO: Would be more efficient to allocate once, then overwrite the bytes at specific offsets.
-        // However, for now, having each section create its own vec which get `extend`ed together makes for a cleaner implementation.
-        let mut output = Vec::with_capacity(1024 * 1024); // Preallocate 1MB, we will likely need more, but dont want to overdo it as we have arcs in arcs.
+        self.compile_replacing(None, &mut |_, _| None)
+    }
+
+    /// Same as `compile` but children that cannot be written from their parsed data are copied from `file`, the data the arc was parsed from.
+    /// When the arc only contains such children and nothing was modified the output is identical to the original file.
+    pub fn compile_with_original(&self, file: &[u8]) -> Vec<u8> {
+        self.compile_replacing(Some(file), &mut |_, _| None)
+    }
 
-        // create arc header
-        output.extend("ARC".chars().map(|x| x as u8));
-        output.extend(&[0x00, 0x01, 0x01]); // TODO: ??
+    /// Same as `compile_with_original` but writes the bytes returned by `replace` instead of a child when it returns Some.
+    /// `replace` is called with every child that is not a nested arc and its original bytes, which are empty when `file` is None.
+    /// Child sizes and offsets are recalculated, so a child can be replaced with data of a different length.
+    pub(crate) fn compile_replacing<F>(&self, file: Option<&[u8]>, replace: &mut F) -> Vec<u8> where F: FnMut(&ArcChild, &[u8]) -> Option<Vec<u8>> {
+        let mut output = vec!();
+        output.extend(b"ARC\0");
+        output.extend(&u16::to_be_bytes(0x0101)); // version
         output.extend(&u16::to_be_bytes(self.children.len() as u16));
         output.extend(&[0x00; 8]);
-        output.extend(self.name.chars().map(|x| x as u8));
-        while output.len() < ARC_HEADER_SIZE {
-            output.push(0x00);
-        }
+        output.extend(self.name.bytes().take(ARC_HEADER_SIZE - 0x11));
+        output.resize(ARC_HEADER_SIZE, 0x00);
 
         for child in &self.children {
-            // create arc child header
+            let child_output = match &child.data {
+                ArcChildData::Arc (arc) => arc.compile_replacing(file, replace),
+                _ => {
+                    let raw_data = file.map(|file| child.raw_data(file)).unwrap_or(&[]);
+                    match (replace(child, raw_data), &child.data) {
+                        (Some (data), _)                  => data,
+                        (None, ArcChildData::Bres (bres)) => bres.compile(),
+                        (None, _)                         => raw_data.to_vec(),
+                    }
+                }
+            };
+
             let start = output.len();
             output.extend(&i16::to_be_bytes(child.ty));
             output.extend(&i16::to_be_bytes(child.index));
-            output.extend(&i32::to_be_bytes(child.size)); // TODO: remove this field and calculate it instead
+            output.extend(&i32::to_be_bytes(child_output.len() as i32));
             output.push(child.group_index);
             output.push(0x00);
             output.extend(&i16::to_be_bytes(child.redirect_index));
-            while output.len() < start + ARC_CHILD_HEADER_SIZE {
-                output.push(0x00);
-            }
+            output.resize(start + ARC_CHILD_HEADER_SIZE, 0x00);
 
-            match &child.data {
-                ArcChildData::Arc  (arc)  => output.extend(arc.compile()),
-                ArcChildData::Bres (bres) => output.extend(bres.compile()),
-                // TODO
-                _ => { }
-            }
+            output.extend(child_output);
+            output.resize(align(output.len()), 0x00);
         }
 
         output
@@ -101,6 +224,7 @@ impl Arc {
 const ARC_HEADER_SIZE: usize = 0x40;
 /// Arc is for archive not to be confused with an atomic reference count
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Arc {
     pub name: String,
     pub children: Vec<ArcChild>,
@@ -108,16 +232,20 @@ pub struct Arc {
 
 const ARC_CHILD_HEADER_SIZE: usize = 0x20;
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct ArcChild {
     ty: i16,
     index: i16,
     size: i32,
     group_index: u8,
     redirect_index: i16, // The index of a different file to read
+    /// Where the child data is stored, relative to the start of the data the top level arc was parsed from
+    pub(crate) data_range: Range<usize>,
     pub data: ArcChildData,
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub enum ArcChildData {
     Arc (Arc),
     Sakurai (ArcSakurai),
@@ -128,5 +256,130 @@ pub enum ArcChildData {
     Reft,
     Aipd,
     W,
+    /// The child reuses the data of the child at `target_index` instead of storing its own, use `Arc::resolve` to get it.
+    Redirect { target_index: usize },
     Unknown
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::arc_blob;
+
+    fn fixture() -> Vec<u8> {
+        let nested = arc_blob("nested", &[(-1, b"REFT and some more bytes to cross the 0x20 boundary")]);
+        arc_blob("FitTest", &[
+            (-1, b"EFLS!"),
+            (0,  b""),
+            (-1, &nested),
+            (-1, b"XYZ"),
+        ])
+    }
+
+    #[test]
+    fn write_identity() {
+        let data = fixture();
+        let arc = arc(FancySlice::new(&data), &WiiMemory::new(), false);
+        assert_eq!(arc.name, "FitTest");
+        assert_eq!(arc.children.len(), 4);
+        assert!(matches!(arc.children[0].data, ArcChildData::Efls));
+        assert_eq!(arc.children[1].redirect_index, 0);
+        assert!(matches!(arc.children[1].data, ArcChildData::Redirect { target_index: 0 }));
+        match &arc.children[2].data {
+            ArcChildData::Arc (nested) => assert!(matches!(nested.children[0].data, ArcChildData::Reft)),
+            _ => panic!("Expected a nested arc"),
+        }
+
+        assert_eq!(arc.compile_with_original(&data), data);
+    }
+
+    #[test]
+    fn write_replaced_child() {
+        let data = fixture();
+        let arc = arc(FancySlice::new(&data), &WiiMemory::new(), false);
+        let replacement = b"EFLS with a much longer body than the original".to_vec();
+        let output = arc.compile_replacing(Some(&data), &mut |child, _| match child.data {
+            ArcChildData::Efls => Some(replacement.clone()),
+            _ => None,
+        });
+
+        assert_eq!(output.len(), data.len() + 0x20);
+        let rebuilt = super::arc(FancySlice::new(&output), &WiiMemory::new(), false);
+        assert_eq!(rebuilt.children[0].size as usize, replacement.len());
+        assert_eq!(rebuilt.children[0].raw_data(&output), replacement.as_slice());
+        match &rebuilt.children[2].data {
+            ArcChildData::Arc (nested) => assert!(nested.children[0].raw_data(&output).starts_with(b"REFT")),
+            _ => panic!("Expected a nested arc"),
+        }
+        assert_eq!(rebuilt.children[3].raw_data(&output), b"XYZ");
+    }
+
+    #[test]
+    fn write_parsed_bres() {
+        let bres = Bres { endian: 0xfeff, version: 0, children: vec!() }.compile();
+        let data = arc_blob("FitTestMotionEtc", &[(-1, &bres), (-1, b"EFLS!")]);
+        let mut arc = arc(FancySlice::new(&data), &WiiMemory::new(), false);
+        match &mut arc.children[0].data {
+            ArcChildData::Bres (bres) => bres.version = 2,
+            _ => panic!("Expected a bres"),
+        }
+
+        for (output, efls) in [(arc.compile(), &b""[..]), (arc.compile_with_original(&data), &b"EFLS!"[..])] {
+            let rebuilt = super::arc(FancySlice::new(&output), &WiiMemory::new(), false);
+            match &rebuilt.children[0].data {
+                ArcChildData::Bres (bres) => assert_eq!(bres.version, 2),
+                _ => panic!("Expected a bres"),
+            }
+            assert_eq!(rebuilt.children[1].raw_data(&output), efls);
+        }
+    }
+
+    #[test]
+    fn redirects_and_duplicate_names() {
+        let motion = arc_blob("FitTestMotion", &[(-1, b"REFT")]);
+        let etc = arc_blob("FitTestEtc", &[(-1, b"REFT")]);
+        let data = arc_blob("FitTestMotionEtc", &[
+            (-1, &motion),
+            (-1, &etc),
+            (0,  b""),
+            (-1, &motion),
+            (2,  b""),
+            (9,  b""),
+            (7,  b""),
+            (6,  b""),
+        ]);
+        let arc = arc(FancySlice::new(&data), &WiiMemory::new(), false);
+
+        assert!(matches!(arc.children[2].data, ArcChildData::Redirect { target_index: 0 }));
+        assert_eq!(arc.resolve(2).and_then(|x| x.name()), Some("FitTestMotion"));
+        // redirect to a redirect
+        assert_eq!(arc.resolve(4).and_then(|x| x.name()), Some("FitTestMotion"));
+        // out of bounds
+        assert!(arc.resolve(5).is_none());
+        assert!(arc.resolve(8).is_none());
+        // cycle
+        assert!(arc.resolve(6).is_none());
+
+        let indexes: Vec<usize> = arc.children_named("FitTestMotion").iter().map(|x| x.0).collect();
+        assert_eq!(indexes, vec!(0, 2, 3, 4));
+        assert_eq!(arc.children_named("FitTestEtc").len(), 1);
+        assert!(arc.children_named("Missing").is_empty());
+
+        assert_eq!(arc.compile_with_original(&data), data);
+    }
+
+    /// Run with `BRAWLLIB_PAC_DIR=path/to/fighter/folder cargo test write_identity_real_files -- --ignored`
+    #[test]
+    #[ignore]
+    fn write_identity_real_files() {
+        let dir = std::env::var("BRAWLLIB_PAC_DIR").expect("BRAWLLIB_PAC_DIR is not set");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().map(|x| x.to_string_lossy().to_lowercase() == "pac").unwrap_or(false) {
+                let data = std::fs::read(&path).unwrap();
+                let arc = arc(FancySlice::new(&data), &WiiMemory::new(), false);
+                assert!(arc.compile_with_original(&data) == data, "{:?} was not rebuilt identically", path);
+            }
+        }
+    }
+}
@@ -1,14 +1,25 @@
-use std::fs::File;
+use std::collections::HashMap;
+use std::fmt;
 use std::fs;
-use std::io::Read;
 use std::path::{Path, PathBuf};
 
-use crate::fighter::Fighter;
-use crate::wii_memory::WiiMemory;
+use crate::address::{RamAddress, FileOffset, LoadedRegion};
+use crate::cancel::{self, CancellationToken, Cancelled};
+use crate::fighter::{self, Fighter, FighterFailure, FighterLoadResult, CommonFighterProvenance, FileRole, LoadDetail, PatchedLoadOptions};
+use crate::msbin::{MenuText, TextEscapes};
+use crate::file_provider::{BrawlDisc, DirFiles, FileProvider};
+use crate::memory_files::MemoryFiles;
+use crate::msbin;
+use crate::stage::{self, StageData};
+use crate::wii_memory::{BufferView, WiiMemory};
 use crate::wiird::WiiRDBlock;
 use crate::wiird;
 use crate::wiird_runner;
 use crate::arc;
+use crate::code_modifications;
+use crate::provenance::{self, CodesetProvenance, PathFormatter};
+use crate::script::{EventBudget, ScriptLimits};
+use crate::slot_metadata::BrawlExConfigs;
 
 use failure::Error;
 use failure::bail;
@@ -20,129 +31,1033 @@ use fancy_slice::FancySlice;
 pub struct BrawlMod {
     brawl_path: PathBuf,
     mod_path: Option<PathBuf>,
+    /// The mod files when the mod is held in memory instead of read from `mod_path`
+    memory: Option<MemoryFiles>,
+}
+
+/// The mod path does not lead to a usable mod folder.
+/// Use `failure::Error::downcast_ref` to access this from the errors returned by `BrawlMod` methods.
+#[derive(Clone, Debug)]
+pub enum ModLayoutError {
+    /// No folder contains `required`, `probed` contains every path that was checked.
+    NotFound { required: String, probed: Vec<PathBuf> },
+    /// Multiple mod folders contain `required` so it is unclear which one to use.
+    MultipleCandidates { required: String, candidates: Vec<PathBuf> },
+}
+
+impl fmt::Display for ModLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ModLayoutError::NotFound { required, probed } => {
+                write!(f, "Missing mod_name/{} directory, point the mod path at the sd card root or the mod folder. Checked:", required)?;
+                for path in probed {
+                    write!(f, "\n    {}", path.display())?;
+                }
+                Ok(())
+            }
+            ModLayoutError::MultipleCandidates { required, candidates } => {
+                write!(f, "Multiple mod folders contain a {} directory, point the mod path at one of:", required)?;
+                for path in candidates {
+                    write!(f, "\n    {}", path.display())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ModLayoutError { }
+
+impl ModLayoutError {
+    fn redact(self, paths: &PathFormatter) -> ModLayoutError {
+        let redact = |x: Vec<PathBuf>| x.iter().map(|x| paths.path(x)).collect();
+        match self {
+            ModLayoutError::NotFound { required, probed } => ModLayoutError::NotFound { required, probed: redact(probed) },
+            ModLayoutError::MultipleCandidates { required, candidates } => ModLayoutError::MultipleCandidates { required, candidates: redact(candidates) },
+        }
+    }
+}
+
+/// Formats the paths of a `ModLayoutError` with `paths`, other errors are returned as is.
+fn redact_layout_error(err: Error, paths: &PathFormatter) -> Error {
+    match err.downcast::<ModLayoutError>() {
+        Ok(err) => err.redact(paths).into(),
+        Err(err) => err,
+    }
+}
+
+/// Returns true when `path` is a disc image file instead of a brawl dump folder
+fn is_disc_image(path: &Path) -> bool {
+    let extension = path.extension().map(|x| x.eq_ignore_ascii_case("iso") || x.eq_ignore_ascii_case("wbfs")).unwrap_or(false);
+    extension && path.is_file()
+}
+
+/// Controls how `BrawlMod::load_fighters_with_config` loads fighters.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct LoadConfig {
+    /// Which files of each fighter are loaded.
+    /// Use `LoadDetail::ScriptsOnly` when only scripts and attributes are needed, it skips reading the motion and model files entirely.
+    pub detail: LoadDetail,
+    /// Whether the WiiRD codeset is applied to the common Fighter.pac
+    pub common_fighter_codes: CommonFighterCodes,
+    /// The files the WiiRD codeset is applied to and where they are loaded in RAM.
+    /// Codes writing outside of a file have no effect on that file.
+    ///
+    /// `PatchFile::CommonFighter` is only patched when allowed by `common_fighter_codes`, without an entry for it the common Fighter.pac is never patched.
+    /// Fighter files are only patched when loading a mod.
+    pub patch_targets: Vec<PatchTarget>,
+    /// Find the events of the common Fighter.pac scripts that the WiiRD codeset writes to, see `Fighter::gct_modified_events`.
+    /// Only done when the codeset is applied to the common Fighter.pac.
+    pub track_code_modifications: bool,
+    /// Limits on the number of events read from the scripts of each fighter and the common Fighter.pac.
+    /// Scripts that reach a limit are truncated and reported in the `ArcSakurai::warnings` of their file e.g. `Fighter::parse_warnings`.
+    pub script_limits: ScriptLimits,
+    /// Fail fighters whose moveset declares overlapping sections, see `ArcSakurai::section_overlaps`.
+    /// Otherwise the overlapping sections are skipped and reported in `Fighter::parse_warnings`.
+    pub strict: bool,
+    /// Write paths relative to the brawl dump or mod in errors and provenance e.g. `<mod[0]>/projectm/pf/fighter/Fighter.pac` instead of the full path on disk.
+    /// Use this when the results are published, so they do not reveal usernames or the layout of the machine they were generated on.
+    /// `Provenance::matches_files` still works with the redacted paths.
+    pub redact_paths: bool,
+    /// Load the final smash transformations e.g. WarioMan as standalone fighters instead of attaching them to their base fighter as `Fighter::transformation`.
+    pub standalone_transformations: bool,
+    /// Stops the load when cancelled from another thread, the load then returns a `cancel::Cancelled` error.
+    /// Checked between fighters and between the files of each fighter.
+    #[serde(skip)]
+    pub cancel: Option<CancellationToken>,
+}
+
+impl Default for LoadConfig {
+    fn default() -> LoadConfig {
+        LoadConfig {
+            detail: LoadDetail::default(),
+            common_fighter_codes: CommonFighterCodes::Auto,
+            patch_targets: vec!(PatchTarget::common_fighter()),
+            track_code_modifications: false,
+            script_limits: ScriptLimits::default(),
+            strict: false,
+            redact_paths: false,
+            standalone_transformations: false,
+            cancel: None,
+        }
+    }
+}
+
+/// A file that the WiiRD codeset is applied to before it is parsed.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct PatchTarget {
+    pub file: PatchFile,
+    /// The address in RAM that the start of the file is loaded at
+    pub ram_base: RamAddress,
+}
+
+impl PatchTarget {
+    /// The common Fighter.pac at its location in RAM during a match
+    pub fn common_fighter() -> PatchTarget {
+        let sakurai_ram_address = RamAddress::new(0x80F9FC20);
+        let sakurai_fighter_pac_offset = FileOffset::new(0x80);
+        PatchTarget {
+            file: PatchFile::CommonFighter,
+            ram_base: LoadedRegion::from_known_location(sakurai_ram_address, sakurai_fighter_pac_offset, 0).unwrap().ram_base,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum PatchFile {
+    /// The Fighter.pac shared by all fighters
+    CommonFighter,
+    /// A file of a single fighter e.g. `Fighter { cased_name: "Marth".into(), role: FileRole::Moveset }` for FitMarth.pac
+    Fighter { cased_name: String, role: FileRole },
+}
+
+/// Whether the WiiRD codeset is applied to the common Fighter.pac.
+/// The codeset is never applied when loading vanilla brawl as there is no codeset.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum CommonFighterCodes {
+    /// Apply the codeset only when the Fighter.pac comes from the brawl dump.
+    /// Mods that include their own Fighter.pac usually already have the changes made by the codeset baked in.
+    Auto,
+    /// Always apply the codeset
+    Always,
+    /// Never apply the codeset
+    Never,
+}
+
+/// The common Fighter.pac as returned by `BrawlMod::load_common_fighter`
+struct CommonFighterFile {
+    file_data: Vec<u8>,
+    /// The file before the codeset was applied to it, None when the codeset was not applied
+    unpatched_data: Option<Vec<u8>>,
+    wii_memory: WiiMemory,
+    provenance: CommonFighterProvenance,
 }
 
 impl BrawlMod {
     /// This is the main entry point of the library.
     /// Provide the path to a brawl dump and optionally a brawl mod sd card.
     ///
+    /// With the `disc` feature the brawl path may instead be an unencrypted .iso or .wbfs disc image, see the `disc` module.
+    ///
     /// Then you can load various other structs from the BrawlMod methods.
     pub fn new(brawl_path: &Path, mod_path: Option<&Path>) -> BrawlMod {
         BrawlMod {
             brawl_path: brawl_path.to_path_buf(),
             mod_path: mod_path.map(|x| x.to_path_buf()),
+            memory: None,
         }
     }
 
+    /// Same as `BrawlMod::new` but the files of the mod are held in memory instead of read from an sd card.
+    /// This allows previewing a combination of mod files e.g. a mod with some of its options toggled, without writing it to disk.
+    ///
+    /// The keys of `files` are paths relative to the mods pf folder e.g. `fighter/mario/FitMario.pac`.
+    /// They are compared case insensitively and may be separated by either `/` or `\`.
+    /// A file in `files` replaces the file of the brawl dump at the same path, every other file is read from the brawl dump.
+    /// The WiiRD codeset is outside of the pf folder, so it is instead read from the first key naming a file RSBE01.gct e.g. `../codes/RSBE01.gct`.
+    ///
+    /// Files read from `files` are recorded in the `Provenance` of the fighters by their normalized key.
+    /// They can not be hashed again, so `Provenance::matches_files` is always false for fighters loaded this way.
+    pub fn from_memory(files: HashMap<String, Vec<u8>>, brawl_path: &Path) -> BrawlMod {
+        BrawlMod {
+            brawl_path: brawl_path.to_path_buf(),
+            mod_path: None,
+            memory: Some(MemoryFiles::new(files)),
+        }
+    }
+
+    /// Returns true when loading a mod instead of vanilla brawl
+    pub(crate) fn is_mod(&self) -> bool {
+        self.mod_path.is_some() || self.memory.is_some()
+    }
+
     /// Returns Err(..) on failure to read required files from disk.
     /// Fighter specific missing files and errors encountered when parsing data is reported via the `error!()` macro from the log crate.
     /// You will need to use one of these crates to view the logged errors https://github.com/rust-lang-nursery/log#in-executables
     pub fn load_fighters(&self, single_model: bool) -> Result<Vec<Fighter>, Error> {
-        let brawl_fighter_path = self.brawl_path.join("fighter");
-        let brawl_fighter_dir = match fs::read_dir(&brawl_fighter_path) {
-            Ok(dir) => dir,
-            Err(err) => bail!("Cannot read fighter directory in the brawl dump: {}", err)
+        self.load_fighters_detailed(single_model).map(|x| x.fighters)
+    }
+
+    /// Same as `BrawlMod::load_fighters` but also reports which fighters failed to load and which folders were skipped.
+    pub fn load_fighters_detailed(&self, single_model: bool) -> Result<FighterLoadResult, Error> {
+        self.load_fighters_with_config(&LoadConfig { detail: LoadDetail::Full { single_model }, ..LoadConfig::default() })
+    }
+
+    /// Same as `BrawlMod::load_fighters_detailed` but with every loading option exposed via `LoadConfig`.
+    ///
+    /// Returns a `cancel::Cancelled` error if `LoadConfig::cancel` is cancelled before the load finishes.
+    pub fn load_fighters_with_config(&self, config: &LoadConfig) -> Result<FighterLoadResult, Error> {
+        phase_span!("load_fighters", brawl_path = %self.brawl_path.display(), mod_path = ?self.mod_path);
+        let cancel = config.cancel.as_ref();
+        let check_cancelled = || if cancel::is_cancelled(cancel) { Err(Cancelled) } else { Ok(()) };
+        let paths = self.path_formatter(config.redact_paths);
+
+        let brawl_files = self.brawl_files()?;
+        if !brawl_files.is_dir("fighter") {
+            bail!("Cannot read fighter directory {} in the brawl dump: the directory does not exist", paths.display(&brawl_files.source_path("fighter")));
+        }
+        let brawl_folders = match fighter::provider_fighter_folders(&*brawl_files) {
+            Ok(folders) => folders,
+            Err(err) => bail!("Cannot read fighter directory {} in the brawl dump: {}", paths.display(&brawl_files.source_path("fighter")), err),
         };
 
-        let mut mod_fighter_dir = None;
-        if let Some(mod_path) = &self.mod_path {
-            let dir_reader = match fs::read_dir(mod_path) {
-                Ok(dir) => dir,
-                Err(err) => bail!("Cannot read brawl mod directory: {}", err)
-            };
-
-            for dir in dir_reader {
-                if let Ok(dir) = dir {
-                    let path = dir.path().join("pf/fighter");
-                    if path.exists() {
-                        mod_fighter_dir = Some(match fs::read_dir(path) {
-                            Ok(dir) => dir,
-                            Err(err) => bail!("Cannot read fighter directory in the brawl mod: {}", err),
-                        });
-                        break;
-                    }
-                }
+        let mod_fighter_path = self.mod_fighter_path().map_err(|err| redact_layout_error(err, &paths))?;
+        let mod_files = self.mod_files(mod_fighter_path.as_deref().and_then(|x| x.parent()));
+        let mod_folders = match &mod_files {
+            Some(files) => match fighter::provider_fighter_folders(&**files) {
+                Ok(folders) => folders,
+                Err(err) => bail!("Cannot read fighter directory {} in the brawl mod: {}", paths.display(&files.source_path("fighter")), err),
+            }
+            None => vec!(),
+        };
+
+        let common_ram_base = config.patch_targets.iter().find(|x| x.file == PatchFile::CommonFighter).map(|x| x.ram_base);
+        let CommonFighterFile { file_data, unpatched_data, wii_memory, mut provenance } = self.load_common_fighter(&*brawl_files, mod_files.as_deref(), config.common_fighter_codes, common_ram_base, &paths)?;
+        check_cancelled()?;
+        let common_fighter = arc::arc_cancellable(FancySlice::new(&file_data), &wii_memory, &mut EventBudget::new(config.script_limits), false, cancel);
+        check_cancelled()?;
+        provenance.revision = common_fighter.fighter_data_common().map(|x| x.revision);
+
+        let (codeset, codeset_provenance, patches) = if self.is_mod() {
+            let patches: Vec<_> = config.patch_targets.iter().filter_map(|target| match &target.file {
+                PatchFile::Fighter { cased_name, role } => Some((cased_name.clone(), *role, target.ram_base)),
+                PatchFile::CommonFighter => None,
+            }).collect();
+            let (codeset, codeset_provenance) = self.read_wiird_codeset(&paths)?;
+            (codeset, Some(codeset_provenance), patches)
+        } else {
+            (vec!(), None, vec!())
+        };
+
+        let options = PatchedLoadOptions {
+            detail: config.detail,
+            limits: config.script_limits,
+            codeset: &codeset,
+            patches: &patches,
+            standalone_transformations: config.standalone_transformations,
+            paths: &paths,
+        };
+        let mut result = Fighter::load_detailed_patched(brawl_folders, mod_folders, &common_fighter, &wii_memory, &options, cancel);
+        check_cancelled()?;
+
+        if config.strict {
+            let (fighters, rejected): (Vec<_>, Vec<_>) = result.fighters.into_iter()
+                .partition(|x| x.get_fighter_sakurai().map(|x| x.section_overlaps.is_empty()).unwrap_or(true));
+            result.fighters = fighters;
+            for fighter in rejected {
+                let overlap = &fighter.get_fighter_sakurai().unwrap().section_overlaps[0];
+                let error = format!("the moveset sections {} and {} overlap", overlap.first, overlap.second);
+                error!("Failed to load {}, {}", fighter.cased_name, error);
+                result.failures.push(FighterFailure { folder: fighter.cased_name, error });
             }
         }
-        if self.mod_path.is_some() && mod_fighter_dir.is_none() {
-            bail!("Missing mod_name/pf/fighter directory");
+
+        if let (true, Some(unpatched_data), Some(ram_base)) = (config.track_code_modifications, &unpatched_data, common_ram_base) {
+            // if statements are evaluated against the file as it was before the codes modified it
+            let memory = BufferView::new(unpatched_data, ram_base);
+            let modified_events = code_modifications::common_fighter_modified_events(&common_fighter, &file_data, ram_base, &wiird::wiird_codes(&codeset), &memory);
+            for fighter in &mut result.fighters {
+                fighter.modified_events = modified_events.clone();
+            }
+        }
+
+        let mod_pf_path = self.mod_pf_path().map_err(|err| redact_layout_error(err, &paths))?;
+        if let Some(files) = self.mod_files(mod_pf_path.as_deref()) {
+            let configs = BrawlExConfigs::load(&*files, &paths);
+            for fighter in &mut result.fighters {
+                fighter.slot_metadata = configs.slot_metadata(&fighter.cased_name);
+                if let Some(follower) = &mut fighter.follower {
+                    follower.fighter.slot_metadata = configs.slot_metadata(&follower.fighter.cased_name);
+                }
+                if let Some(transformation) = &mut fighter.transformation {
+                    transformation.slot_metadata = configs.slot_metadata(&transformation.cased_name);
+                }
+            }
         }
 
-        let common_fighter_path = brawl_fighter_path.join("Fighter.pac");
-        let (common_fighter, wii_memory) = if let Ok(mut fighter_file) = File::open(common_fighter_path) {
-            let mut file_data: Vec<u8> = vec!();
-            if let Err(err) = fighter_file.read_to_end(&mut file_data) {
-                bail!("Cannot read Fighter.pac in the brawl dump: {}", err);
+        let record_inputs = |fighter: &mut Fighter| {
+            fighter.provenance.load_config = Some(config.clone());
+            fighter.provenance.common_fighter = Some(provenance.clone());
+            fighter.provenance.codeset = codeset_provenance.clone();
+        };
+        for fighter in &mut result.fighters {
+            record_inputs(fighter);
+            if let Some(follower) = &mut fighter.follower {
+                record_inputs(&mut follower.fighter);
             }
+            if let Some(transformation) = &mut fighter.transformation {
+                record_inputs(transformation);
+            }
+        }
+
+        result.common_fighter = Some(provenance);
+        Ok(result)
+    }
+
+    /// Formats paths relative to the brawl dump and mod when `redact`, see `LoadConfig::redact_paths`
+    pub(crate) fn path_formatter(&self, redact: bool) -> PathFormatter {
+        PathFormatter::new(redact, &self.brawl_path, self.mod_path.as_deref())
+    }
+
+    /// Returns where the files of brawl are read from: the disc image or the dump folder.
+    /// The files of a disc image are not read until they are needed.
+    fn brawl_files(&self) -> Result<Box<dyn FileProvider>, Error> {
+        if is_disc_image(&self.brawl_path) {
+            Ok(Box::new(BrawlDisc::open(&self.brawl_path)?))
+        } else {
+            Ok(Box::new(DirFiles::new(&self.brawl_path)))
+        }
+    }
+
+    /// Returns where the files of the mod are read from: the files held in memory or the pf folder at `mod_pf_path`.
+    /// Returns None if this is not a mod.
+    fn mod_files(&self, mod_pf_path: Option<&Path>) -> Option<Box<dyn FileProvider + '_>> {
+        match (&self.memory, mod_pf_path) {
+            (Some(memory), _) => Some(Box::new(memory)),
+            (None, Some(mod_pf_path)) => Some(Box::new(DirFiles::new(mod_pf_path))),
+            (None, None) => None,
+        }
+    }
+
+    /// Returns the mod_name/pf/fighter directory or None if this is not a mod
+    pub(crate) fn mod_fighter_path(&self) -> Result<Option<PathBuf>, Error> {
+        Ok(self.find_mod_folder("pf/fighter")?.map(|x| x.join("pf/fighter")))
+    }
+
+    /// Finds the folder containing the mods pf folder, `required` is a path relative to that folder that must exist.
+    /// Returns None if this is not a mod.
+    ///
+    /// The mod path may point at the sd card root containing the mod folder, at the mod folder itself or at its pf folder.
+    fn find_mod_folder(&self, required: &str) -> Result<Option<PathBuf>, Error> {
+        let mod_path = if let Some(mod_path) = &self.mod_path {
+            mod_path
+        } else {
+            return Ok(None);
+        };
+
+        let mut probed = vec!();
 
-            let wii_memory = if self.mod_path.is_some() {
-                let codeset = self.load_wiird_codeset_raw()?;
-                let sakurai_ram_offset = 0x80F9FC20;
-                let sakurai_fighter_pac_offset = 0x80;
-                let fighter_pac_offset = sakurai_ram_offset - sakurai_fighter_pac_offset;
+        // pointed at the mod folder
+        let path = mod_path.join(required);
+        if path.exists() {
+            return Ok(Some(mod_path.clone()));
+        }
+        probed.push(path);
+
+        // pointed at the pf folder
+        if mod_path.file_name().map(|x| x.to_string_lossy().eq_ignore_ascii_case("pf")).unwrap_or(false) {
+            if let Some(parent) = mod_path.parent() {
+                let path = parent.join(required);
+                if path.exists() {
+                    return Ok(Some(parent.to_path_buf()));
+                }
+                probed.push(path);
+            }
+        }
 
-                wiird_runner::process(&codeset, &mut file_data, fighter_pac_offset)
+        // pointed at the sd card root
+        let dir_reader = match fs::read_dir(mod_path) {
+            Ok(dir) => dir,
+            Err(err) => bail!("Cannot read brawl mod directory: {}", err)
+        };
+        let mut candidates = vec!();
+        let mut dirs: Vec<PathBuf> = dir_reader.flatten().map(|x| x.path()).filter(|x| x.is_dir()).collect();
+        dirs.sort();
+        for dir in dirs {
+            let path = dir.join(required);
+            if path.exists() {
+                candidates.push(dir);
             } else {
-                WiiMemory::new()
-            };
+                probed.push(path);
+            }
+        }
+
+        match candidates.len() {
+            0 => Err(ModLayoutError::NotFound { required: required.to_string(), probed }.into()),
+            1 => Ok(candidates.pop()),
+            _ => Err(ModLayoutError::MultipleCandidates { required: required.to_string(), candidates }.into()),
+        }
+    }
+
+    /// Returns the directory whose subdirectories are searched for the WiiRD codeset.
+    /// This is the folder containing the mod folder, so the codes folder is found no matter which level the mod path points at.
+    fn codeset_search_path(&self, mod_path: &Path) -> PathBuf {
+        match self.find_mod_folder("pf") {
+            Ok(Some(mod_folder)) => mod_folder.parent().map(|x| x.to_path_buf()).unwrap_or(mod_folder),
+            _ => mod_path.to_path_buf(),
+        }
+    }
+
+    /// Reads the Fighter.pac of `mod_files`, falling back to the Fighter.pac of `brawl_files`, and applies the WiiRD codeset to it as configured by `codes`.
+    ///
+    /// `ram_base` is where the file is loaded in RAM, the codeset is not applied to the file when None.
+    ///
+    /// When the codeset is not applied to the file, it is still run on a copy of the file so that memory written by the codes is available.
+    /// When the codeset is applied to the file, a copy of the file from before the codes were applied is also returned.
+    /// Paths are written to errors and provenance as formatted by `paths`.
+    fn load_common_fighter(&self, brawl_files: &dyn FileProvider, mod_files: Option<&dyn FileProvider>, codes: CommonFighterCodes, ram_base: Option<RamAddress>, paths: &PathFormatter) -> Result<CommonFighterFile, Error> {
+        let common_fighter_path = "fighter/Fighter.pac";
+        let mod_common_fighter = mod_files.filter(|x| x.contains(common_fighter_path));
+        let from_mod = mod_common_fighter.is_some();
+        let files = match mod_common_fighter.or_else(|| Some(brawl_files).filter(|x| x.contains(common_fighter_path))) {
+            Some(files) => files,
+            None => bail!("Missing Fighter.pac"),
+        };
+
+        let path = files.source_path(common_fighter_path);
+        let mut file_data = match files.read(common_fighter_path) {
+            Ok(data) => data,
+            Err(err) => bail!("Cannot read Fighter.pac {}: {}", paths.display(&path), err),
+        };
+        let sha1 = provenance::sha1(&file_data);
+        let path = paths.path(&path);
+
+        let codes_applied = self.is_mod() && ram_base.is_some() && match codes {
+            CommonFighterCodes::Auto   => !from_mod,
+            CommonFighterCodes::Always => true,
+            CommonFighterCodes::Never  => false,
+        };
+
+        let unpatched_data = if codes_applied { Some(file_data.clone()) } else { None };
 
-            let data = FancySlice::new(&file_data);
+        let wii_memory = if self.is_mod() {
+            let (codeset, _) = self.read_wiird_codeset(paths)?;
+            let ram_base = ram_base.unwrap_or_else(|| PatchTarget::common_fighter().ram_base);
 
-            (arc::arc(data, &wii_memory, false), wii_memory)
+            if codes_applied {
+                wiird_runner::process(&codeset, &mut file_data, ram_base)
+            } else {
+                wiird_runner::process(&codeset, &mut file_data.clone(), ram_base)
+            }
         } else {
-            bail!("Missing Fighter.pac");
+            WiiMemory::new()
         };
 
-        Ok(Fighter::load(brawl_fighter_dir, mod_fighter_dir, &common_fighter, &wii_memory, single_model))
+        // the revision is only known once the file is parsed
+        let provenance = CommonFighterProvenance { path, from_mod, codes_applied, sha1, revision: None };
+        Ok(CommonFighterFile { file_data, unpatched_data, wii_memory, provenance })
+    }
+
+    /// Parses the menu text files of the brawl dump, replacing them with the files from the mod when present.
+    /// Refer to the `msbin` module for details.
+    pub fn load_menu_text(&self, escapes: TextEscapes) -> Result<Vec<MenuText>, Error> {
+        let mod_files = self.mod_files(self.mod_pf_path()?.as_deref());
+        msbin::load_menu_text(&*self.brawl_files()?, mod_files.as_deref(), escapes, &PathFormatter::default())
+    }
+
+    /// Reads the stage parameters of the brawl dump, replacing the stages with the files from the mod when present.
+    /// Refer to the `stage` module for details.
+    pub fn load_stage_data(&self) -> Result<Vec<StageData>, Error> {
+        let mod_files = self.mod_files(self.mod_pf_path()?.as_deref());
+        stage::load_stage_data(&*self.brawl_files()?, mod_files.as_deref(), &PathFormatter::default())
+    }
+
+    /// Returns the mods pf directory or None if this is not a mod or the mod has no pf folder.
+    /// A mod without a pf folder uses the vanilla files for everything in it.
+    fn mod_pf_path(&self) -> Result<Option<PathBuf>, Error> {
+        match self.find_mod_folder("pf") {
+            Ok(mod_folder) => Ok(mod_folder.map(|x| x.join("pf"))),
+            Err(err) => match err.downcast_ref::<ModLayoutError>() {
+                Some(ModLayoutError::NotFound { .. }) => Ok(None),
+                _ => Err(err),
+            }
+        }
     }
 
     pub fn load_wiird_codeset_raw(&self) -> Result<Vec<u8>, Error> {
-        // RSBE01.gct is usually located in the codes folder but can also be in the main sub folder e.g. LXP 2.1
-        // So, just check every subdirectory of the root.
-        if let Some(mod_path) = &self.mod_path {
-            for dir in fs::read_dir(mod_path).unwrap() {
-                if let Ok(dir) = dir {
-                    let codeset_path = dir.path().join("RSBE01.gct");
-                    if codeset_path.exists() {
-                        let mut data: Vec<u8> = vec!();
-                        match File::open(&codeset_path) {
-                            Ok(mut file) => {
-                                if let Err(err) = file.read_to_end(&mut data) {
-                                    bail!("Cannot read WiiRD codeset {:?}: {}", codeset_path, err);
-                                }
-                            }
-                            Err(err) => bail!("Cannot read WiiRD codeset {:?}: {}", codeset_path, err)
-                        }
-
-                        if data.len() < 8 {
-                            bail!("Not a WiiRD gct codeset file: File size is less than 8 bytes");
-                        }
-
-                        return Ok(data[8..].to_vec()) // Skip the header
-                    }
-                }
+        self.read_wiird_codeset(&PathFormatter::default()).map(|(data, _)| data)
+    }
+
+    /// Returns the codeset without its header and where it was read from, formatted by `paths`
+    fn read_wiird_codeset(&self, paths: &PathFormatter) -> Result<(Vec<u8>, CodesetProvenance), Error> {
+        let (codeset_path, data, sha1) = if let Some(memory) = &self.memory {
+            match memory.codeset() {
+                Some(file) => (file.provenance_path(), file.data.clone(), provenance::sha1(&file.data)),
+                None => bail!("Cannot find the WiiRD codeset (RSBE01.gct)"),
             }
-            bail!("Cannot find the WiiRD codeset (RSBE01.gct)");
         } else {
-            bail!("Not a mod, vanilla brawl does not have a WiiRD codeset.");
+            let codeset_path = self.wiird_codeset_path()?;
+            match provenance::read_hashed(&codeset_path) {
+                Ok((data, sha1)) => (paths.path(&codeset_path), data, sha1),
+                Err(err) => bail!("Cannot read WiiRD codeset {}: {}", paths.display(&codeset_path), err),
+            }
+        };
+
+        if data.len() < 8 {
+            bail!("Not a WiiRD gct codeset file: File size is less than 8 bytes");
         }
+
+        Ok((data[8..].to_vec(), CodesetProvenance { path: codeset_path, sha1 })) // Skip the header
     }
 
     pub fn load_wiird_codeset(&self) -> Result<WiiRDBlock, Error> {
+        Ok(wiird::wiird_codes(&self.load_wiird_codeset_raw()?))
+    }
+
+    pub(crate) fn wiird_codeset_path(&self) -> Result<PathBuf, Error> {
         // RSBE01.gct is usually located in the codes folder but can also be in the main sub folder e.g. LXP 2.1
         // So, just check every subdirectory of the root.
         if let Some(mod_path) = &self.mod_path {
-            for dir in fs::read_dir(mod_path).unwrap() {
-                if let Ok(dir) = dir {
-                    let codeset_path = dir.path().join("RSBE01.gct");
-                    if codeset_path.exists() {
-                        return wiird::wiird_load_gct(&codeset_path)
-                    }
+            for dir in fs::read_dir(self.codeset_search_path(mod_path)).unwrap().flatten() {
+                let codeset_path = dir.path().join("RSBE01.gct");
+                if codeset_path.exists() {
+                    return Ok(codeset_path);
                 }
             }
             bail!("Cannot find the WiiRD codeset (RSBE01.gct)");
+        } else if self.memory.is_some() {
+            bail!("The WiiRD codeset is held in memory, it has no path.");
         } else {
             bail!("Not a mod, vanilla brawl does not have a WiiRD codeset.");
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{TestFighterBuilder, gct, write_fighter};
+    use crate::provenance::Provenance;
+
+    /// Offset into Fighter.pac that the test codeset writes to
+    const PATCHED_OFFSET: usize = 0x40;
+    const PATCHED_VALUE: [u8; 4] = [0xDE, 0xAD, 0xBE, 0xEF];
+
+    fn fighter_pac(marker: u8) -> Vec<u8> {
+        let mut data = vec!(marker; 0x100);
+        data[..0x40].copy_from_slice(&[0; 0x40]);
+        data[..3].copy_from_slice(b"ARC");
+        data
+    }
+
+    /// Creates a brawl dump and a mod, the mod includes its own Fighter.pac when `mod_fighter_pac` is true.
+    /// Returns the Fighter.pac data after loading.
+    fn load(mod_fighter_pac: bool, codes: CommonFighterCodes) -> (Vec<u8>, CommonFighterProvenance) {
+        let brawl = tempfile::tempdir().unwrap();
+        let modded = tempfile::tempdir().unwrap();
+        fs::create_dir_all(brawl.path().join("fighter")).unwrap();
+        fs::write(brawl.path().join("fighter/Fighter.pac"), fighter_pac(0x11)).unwrap();
+
+        let mod_fighter_path = modded.path().join("projectm/pf/fighter");
+        fs::create_dir_all(&mod_fighter_path).unwrap();
+        if mod_fighter_pac {
+            fs::write(mod_fighter_path.join("Fighter.pac"), fighter_pac(0x22)).unwrap();
+        }
+
+        // A single 32 bit write to Fighter.pac + PATCHED_OFFSET, Fighter.pac + 0x80 is loaded at 0x80F9FC20
+        fs::create_dir_all(modded.path().join("codes")).unwrap();
+        fs::write(modded.path().join("codes/RSBE01.gct"), gct(&[(0x80F9FC20 - 0x80 + PATCHED_OFFSET as u32, PATCHED_VALUE)])).unwrap();
+
+        let brawl_mod = BrawlMod::new(brawl.path(), Some(modded.path()));
+        let CommonFighterFile { file_data: data, unpatched_data, wii_memory, provenance } = brawl_mod.load_common_fighter(&DirFiles::new(brawl.path()), Some(&DirFiles::new(&modded.path().join("projectm/pf"))), codes, Some(PatchTarget::common_fighter().ram_base), &PathFormatter::default()).unwrap();
+
+        // the codes are always run, so the written memory is available either way
+        assert_eq!(wii_memory.read_u32(RamAddress::new(0x80F9FC20 - 0x80 + PATCHED_OFFSET as u32)), 0xDEADBEEF);
+        assert_eq!(provenance.from_mod, mod_fighter_pac);
+        let expected_path = if mod_fighter_pac { mod_fighter_path.join("Fighter.pac") } else { brawl.path().join("fighter/Fighter.pac") };
+        assert_eq!(provenance.path, expected_path);
+        assert_eq!(data[0x80], if mod_fighter_pac { 0x22 } else { 0x11 });
+        assert_eq!(unpatched_data.is_some(), provenance.codes_applied);
+
+        (data, provenance)
+    }
+
+    fn assert_patched(mod_fighter_pac: bool, codes: CommonFighterCodes, patched: bool) {
+        let (data, provenance) = load(mod_fighter_pac, codes);
+        assert_eq!(provenance.codes_applied, patched);
+        assert_eq!(data[PATCHED_OFFSET..PATCHED_OFFSET + 4] == PATCHED_VALUE, patched);
+    }
+
+    #[test]
+    fn common_fighter_codes_auto() {
+        assert_patched(false, CommonFighterCodes::Auto, true);
+        assert_patched(true,  CommonFighterCodes::Auto, false);
+    }
+
+    #[test]
+    fn common_fighter_codes_always() {
+        assert_patched(false, CommonFighterCodes::Always, true);
+        assert_patched(true,  CommonFighterCodes::Always, true);
+    }
+
+    #[test]
+    fn common_fighter_codes_never() {
+        assert_patched(false, CommonFighterCodes::Never, false);
+        assert_patched(true,  CommonFighterCodes::Never, false);
+    }
+
+    /// Creates an sd card with a mod folder for each of `mods` containing pf/fighter and a codes folder.
+    fn sd_card(mods: &[&str]) -> tempfile::TempDir {
+        let sd = tempfile::tempdir().unwrap();
+        for name in mods {
+            fs::create_dir_all(sd.path().join(name).join("pf/fighter")).unwrap();
+        }
+        fs::create_dir_all(sd.path().join("codes")).unwrap();
+        fs::write(sd.path().join("codes/RSBE01.gct"), gct(&[])).unwrap();
+        sd
+    }
+
+    fn layout_error(brawl_mod: &BrawlMod) -> ModLayoutError {
+        brawl_mod.mod_fighter_path().unwrap_err().downcast::<ModLayoutError>().unwrap()
+    }
+
+    #[test]
+    fn mod_layout_sd_root() {
+        let sd = sd_card(&["projectm"]);
+        let brawl_mod = BrawlMod::new(Path::new("brawl"), Some(sd.path()));
+        assert_eq!(brawl_mod.mod_fighter_path().unwrap(), Some(sd.path().join("projectm/pf/fighter")));
+        assert_eq!(brawl_mod.load_wiird_codeset_raw().unwrap(), vec!(0xF0, 0, 0, 0, 0, 0, 0, 0));
+    }
+
+    #[test]
+    fn mod_layout_mod_folder() {
+        let sd = sd_card(&["projectm"]);
+        let brawl_mod = BrawlMod::new(Path::new("brawl"), Some(&sd.path().join("projectm")));
+        assert_eq!(brawl_mod.mod_fighter_path().unwrap(), Some(sd.path().join("projectm/pf/fighter")));
+        assert!(brawl_mod.load_wiird_codeset_raw().is_ok());
+    }
+
+    #[test]
+    fn mod_layout_pf_folder() {
+        let sd = sd_card(&["projectm"]);
+        let brawl_mod = BrawlMod::new(Path::new("brawl"), Some(&sd.path().join("projectm/pf")));
+        assert_eq!(brawl_mod.mod_fighter_path().unwrap(), Some(sd.path().join("projectm/pf/fighter")));
+        assert!(brawl_mod.load_wiird_codeset_raw().is_ok());
+    }
+
+    #[test]
+    fn mod_layout_ignores_folders_without_fighter() {
+        let sd = sd_card(&["projectm"]);
+        fs::create_dir_all(sd.path().join("private/wii")).unwrap();
+        fs::create_dir_all(sd.path().join("other/pf/menu")).unwrap();
+        let brawl_mod = BrawlMod::new(Path::new("brawl"), Some(sd.path()));
+        assert_eq!(brawl_mod.mod_fighter_path().unwrap(), Some(sd.path().join("projectm/pf/fighter")));
+    }
+
+    #[test]
+    fn mod_layout_multiple_candidates() {
+        let sd = sd_card(&["projectm", "minus"]);
+        let brawl_mod = BrawlMod::new(Path::new("brawl"), Some(sd.path()));
+        match layout_error(&brawl_mod) {
+            ModLayoutError::MultipleCandidates { required, candidates } => {
+                assert_eq!(required, "pf/fighter");
+                assert_eq!(candidates, vec!(sd.path().join("minus"), sd.path().join("projectm")));
+            }
+            err => panic!("Unexpected error {:?}", err),
+        }
+
+        let message = layout_error(&brawl_mod).to_string();
+        assert!(message.starts_with("Multiple mod folders contain a pf/fighter directory"));
+        assert!(message.contains(&sd.path().join("minus").display().to_string()));
+        assert!(message.contains(&sd.path().join("projectm").display().to_string()));
+    }
+
+    #[test]
+    fn mod_layout_not_found() {
+        let sd = sd_card(&[]);
+        let brawl_mod = BrawlMod::new(Path::new("brawl"), Some(sd.path()));
+        match layout_error(&brawl_mod) {
+            ModLayoutError::NotFound { required, probed } => {
+                assert_eq!(required, "pf/fighter");
+                assert_eq!(probed, vec!(sd.path().join("pf/fighter"), sd.path().join("codes/pf/fighter")));
+            }
+            err => panic!("Unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn redact_paths() {
+        let brawl = many_fighters(1);
+        let config = LoadConfig { detail: LoadDetail::ScriptsOnly, redact_paths: true, ..LoadConfig::default() };
+
+        let sd = sd_card(&[]);
+        let message = BrawlMod::new(brawl.path(), Some(sd.path())).load_fighters_with_config(&config).unwrap_err().to_string();
+        assert!(message.contains(&Path::new("<mod[0]>").join("codes/pf/fighter").display().to_string()), "{}", message);
+        assert!(!message.contains(&sd.path().display().to_string()), "{}", message);
+
+        let sd = sd_card(&["projectm"]);
+        let brawl_mod = BrawlMod::new(brawl.path(), Some(sd.path()));
+        let result = brawl_mod.load_fighters_with_config(&config).unwrap();
+        assert_eq!(result.common_fighter.unwrap().path, Path::new("<brawl>").join("fighter/Fighter.pac"));
+        let provenance = &result.fighters[0].provenance;
+        assert_eq!(provenance.codeset.as_ref().unwrap().path, Path::new("<mod[0]>").join("codes/RSBE01.gct"));
+        assert!(provenance.files.iter().all(|x| x.path.starts_with("<brawl>/fighter/test0")));
+        assert!(provenance.matches_files(&brawl_mod));
+    }
+
+    #[test]
+    fn patch_fighter_files() {
+        let brawl = tempfile::tempdir().unwrap();
+        let sd = sd_card(&["projectm"]);
+        fs::create_dir_all(brawl.path().join("fighter")).unwrap();
+        fs::write(brawl.path().join("fighter/Fighter.pac"), fighter_pac(0x11)).unwrap();
+
+        let moveset = TestFighterBuilder::new("Test")
+            .attribute_f32(0x00, 1.25)
+            .attribute_f32(0x04, 1.25)
+            .moveset_pac();
+        let attributes_offset = moveset.windows(4).position(|x| x == 1.25_f32.to_be_bytes()).unwrap() as u32;
+        for name in &["Mario", "Luigi"] {
+            write_fighter(&brawl.path().join("fighter"), name, &moveset).unwrap();
+        }
+
+        // Mario has walk_init_vel written, Luigi has walk_acc written
+        let mario_base = 0x81000000;
+        let luigi_base = 0x81100000;
+        let gct = gct(&[
+            (mario_base + attributes_offset,     2.0_f32.to_be_bytes()),
+            (luigi_base + attributes_offset + 4, 3.0_f32.to_be_bytes()),
+        ]);
+        fs::write(sd.path().join("codes/RSBE01.gct"), gct).unwrap();
+
+        let target = |cased_name: &str, ram_base| PatchTarget {
+            file: PatchFile::Fighter { cased_name: cased_name.to_string(), role: FileRole::Moveset },
+            ram_base: RamAddress::new(ram_base),
+        };
+        let config = LoadConfig {
+            detail: LoadDetail::Full { single_model: true },
+            patch_targets: vec!(target("Mario", mario_base), target("luigi", luigi_base)),
+            ..LoadConfig::default()
+        };
+        let result = BrawlMod::new(brawl.path(), Some(sd.path())).load_fighters_with_config(&config).unwrap();
+        assert!(result.failures.is_empty());
+        assert!(!result.common_fighter.as_ref().unwrap().codes_applied);
+
+        let attributes = |name: &str| {
+            let fighter = result.fighters.iter().find(|x| x.cased_name == name).unwrap();
+            let attributes = &fighter.get_fighter_data().unwrap().attributes;
+            (attributes.walk_init_vel, attributes.walk_acc)
+        };
+        assert_eq!(attributes("Mario"), (2.0, 1.25));
+        assert_eq!(attributes("Luigi"), (1.25, 3.0));
+    }
+
+    /// Every file in `dir` and its subfolders along with its contents, sorted by path
+    fn snapshot(dir: &Path) -> Vec<(PathBuf, Vec<u8>)> {
+        let mut files = vec!();
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                files.extend(snapshot(&path));
+            } else {
+                files.push((path.clone(), fs::read(&path).unwrap()));
+            }
+        }
+        files.sort();
+        files
+    }
+
+    fn set_readonly(dir: &Path, readonly: bool) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                set_readonly(&path, readonly);
+            }
+            let mut permissions = fs::metadata(&path).unwrap().permissions();
+            permissions.set_readonly(readonly);
+            fs::set_permissions(&path, permissions).unwrap();
+        }
+    }
+
+    #[test]
+    fn fighters_from_memory() {
+        let brawl = tempfile::tempdir().unwrap();
+        fs::create_dir_all(brawl.path().join("fighter")).unwrap();
+        fs::write(brawl.path().join("fighter/Fighter.pac"), fighter_pac(0x11)).unwrap();
+        let moveset = |walk_init_vel| TestFighterBuilder::new("Test").attribute_f32(0x00, walk_init_vel).moveset_pac();
+        for name in &["Mario", "Luigi"] {
+            let dir = brawl.path().join("fighter").join(name.to_lowercase());
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join(format!("Fit{}.pac", name)), moveset(1.25)).unwrap();
+            fs::write(dir.join(format!("Fit{}MotionEtc.pac", name)), moveset(1.25)).unwrap();
+        }
+
+        let mut files = HashMap::new();
+        // the separators and case do not match the brawl dump
+        files.insert(String::from("Fighter\\MARIO\\fitmario.pac"), moveset(2.0));
+        files.insert(String::from("../codes/RSBE01.gct"), gct(&[]));
+        let brawl_mod = BrawlMod::from_memory(files, brawl.path());
+        assert_eq!(brawl_mod.load_wiird_codeset_raw().unwrap(), vec!(0xF0, 0, 0, 0, 0, 0, 0, 0));
+
+        let before = snapshot(brawl.path());
+        set_readonly(brawl.path(), true);
+        let result = brawl_mod.load_fighters_with_config(&LoadConfig { detail: LoadDetail::ScriptsOnly, ..LoadConfig::default() });
+        set_readonly(brawl.path(), false);
+        assert_eq!(snapshot(brawl.path()), before);
+
+        let result = result.unwrap();
+        assert!(result.failures.is_empty());
+        let fighter = |name: &str| result.fighters.iter().find(|x| x.cased_name == name).unwrap();
+        assert_eq!(fighter("Mario").get_fighter_data().unwrap().attributes.walk_init_vel, 2.0);
+        assert_eq!(fighter("Luigi").get_fighter_data().unwrap().attributes.walk_init_vel, 1.25);
+
+        let moveset = fighter("Mario").provenance.files.iter().find(|x| x.role == FileRole::Moveset).unwrap().clone();
+        assert!(moveset.from_mod);
+        assert_eq!(moveset.path, PathBuf::from("Fighter/MARIO/fitmario.pac"));
+        assert!(!fighter("Luigi").provenance.files.iter().any(|x| x.from_mod));
+        assert_eq!(result.common_fighter.unwrap().path, brawl.path().join("fighter/Fighter.pac"));
+    }
+
+    #[cfg(feature = "disc")]
+    #[test]
+    fn fighters_from_disc_image() {
+        let moveset = |walk_init_vel| TestFighterBuilder::new("Test").attribute_f32(0x00, walk_init_vel).moveset_pac();
+        let image = crate::disc::tests::iso(&[
+            ("fighter/Fighter.pac", fighter_pac(0x11)),
+            ("fighter/luigi/FitLuigi.pac", moveset(1.25)),
+            ("fighter/luigi/FitLuigiMotionEtc.pac", moveset(1.25)),
+            ("fighter/mario/FitMario.pac", moveset(1.25)),
+            ("fighter/mario/FitMarioMotionEtc.pac", moveset(1.25)),
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("RSBE01.iso");
+        fs::write(&path, image).unwrap();
+
+        let config = LoadConfig { detail: LoadDetail::ScriptsOnly, ..LoadConfig::default() };
+        let result = BrawlMod::new(&path, None).load_fighters_with_config(&config).unwrap();
+        assert!(result.failures.is_empty());
+        assert_eq!(result.fighters.len(), 2);
+        let common_fighter = result.common_fighter.unwrap();
+        assert!(!common_fighter.from_mod);
+        assert_eq!(common_fighter.path, PathBuf::from("fighter/Fighter.pac"));
+        // files not needed for the LoadDetail are not read from the image
+        for fighter in &result.fighters {
+            assert_eq!(fighter.provenance.files.len(), 1, "{}", fighter.cased_name);
+        }
+
+        // a mod replaces the files of the disc image
+        let mut files = HashMap::new();
+        files.insert(String::from("fighter/mario/FitMario.pac"), moveset(2.0));
+        files.insert(String::from("../codes/RSBE01.gct"), gct(&[]));
+        let result = BrawlMod::from_memory(files, &path).load_fighters_with_config(&config).unwrap();
+        let fighter = |name: &str| result.fighters.iter().find(|x| x.cased_name == name).unwrap();
+        assert_eq!(fighter("Mario").get_fighter_data().unwrap().attributes.walk_init_vel, 2.0);
+        assert_eq!(fighter("Luigi").get_fighter_data().unwrap().attributes.walk_init_vel, 1.25);
+        assert!(!fighter("Luigi").provenance.files.iter().any(|x| x.from_mod));
+    }
+
+    #[cfg(not(feature = "disc"))]
+    #[test]
+    fn disc_image_requires_feature() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("RSBE01.iso");
+        fs::write(&path, vec!(0; 0x100)).unwrap();
+        let err = BrawlMod::new(&path, None).load_fighters(false).err().unwrap().to_string();
+        assert!(err.contains("must be built with the disc feature"), "{}", err);
+    }
+
+    /// Creates a brawl dump containing `count` fighters
+    fn many_fighters(count: usize) -> tempfile::TempDir {
+        let brawl = tempfile::tempdir().unwrap();
+        fs::create_dir_all(brawl.path().join("fighter")).unwrap();
+        fs::write(brawl.path().join("fighter/Fighter.pac"), fighter_pac(0x11)).unwrap();
+
+        let moveset = TestFighterBuilder::new("Test").moveset_pac();
+        for i in 0..count {
+            let name = format!("Test{}", i);
+            let dir = brawl.path().join("fighter").join(name.to_lowercase());
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join(format!("Fit{}.pac", name)), &moveset).unwrap();
+            fs::write(dir.join(format!("Fit{}MotionEtc.pac", name)), &moveset).unwrap();
+        }
+        brawl
+    }
+
+    #[test]
+    fn load_fighters_cancelled_before_start() {
+        let brawl = many_fighters(2);
+        let cancel = CancellationToken::new();
+        let other = cancel.clone();
+        std::thread::spawn(move || other.cancel()).join().unwrap();
+
+        let config = LoadConfig { cancel: Some(cancel), ..LoadConfig::default() };
+        let err = BrawlMod::new(brawl.path(), None).load_fighters_with_config(&config).unwrap_err();
+        assert_eq!(err.downcast_ref::<Cancelled>(), Some(&Cancelled));
+    }
+
+    #[test]
+    fn load_fighters_cancelled_from_another_thread() {
+        let brawl = many_fighters(64);
+        let cancel = CancellationToken::new();
+        let config = LoadConfig { cancel: Some(cancel.clone()), ..LoadConfig::default() };
+
+        let brawl_path = brawl.path().to_path_buf();
+        let start = std::time::Instant::now();
+        let load = std::thread::spawn(move || BrawlMod::new(&brawl_path, None).load_fighters_with_config(&config).map(|x| x.fighters.len()));
+        cancel.cancel();
+        let result = load.join().unwrap();
+
+        assert_eq!(result.unwrap_err().downcast_ref::<Cancelled>(), Some(&Cancelled));
+        assert!(start.elapsed() < std::time::Duration::from_secs(10), "cancelling took {:?}", start.elapsed());
+
+        // the same dump loads fine without cancelling
+        let result = BrawlMod::new(brawl.path(), None).load_fighters_with_config(&LoadConfig::default()).unwrap();
+        assert_eq!(result.fighters.len(), 64);
+    }
+
+    #[test]
+    fn load_fighters_records_common_fighter() {
+        let brawl = tempfile::tempdir().unwrap();
+        fs::create_dir_all(brawl.path().join("fighter")).unwrap();
+        fs::write(brawl.path().join("fighter/Fighter.pac"), fighter_pac(0x11)).unwrap();
+
+        let result = BrawlMod::new(brawl.path(), None).load_fighters_with_config(&LoadConfig::default()).unwrap();
+        let provenance = result.common_fighter.unwrap();
+        assert!(!provenance.from_mod);
+        assert!(!provenance.codes_applied);
+        assert_eq!(provenance.path, brawl.path().join("fighter/Fighter.pac"));
+    }
+
+    #[test]
+    fn load_fighters_slot_metadata() {
+        let brawl = tempfile::tempdir().unwrap();
+        let sd = sd_card(&["projectm"]);
+        fs::create_dir_all(brawl.path().join("fighter")).unwrap();
+        fs::write(brawl.path().join("fighter/Fighter.pac"), fighter_pac(0x11)).unwrap();
+
+        let moveset = TestFighterBuilder::new("Test").moveset_pac();
+        let fighter_dirs = [(brawl.path().join("fighter"), "Mario"), (brawl.path().join("fighter"), "Luigi"), (sd.path().join("projectm/pf/fighter"), "Knuckles")];
+        for (fighter_path, name) in &fighter_dirs {
+            let dir = fighter_path.join(name.to_lowercase());
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join(format!("Fit{}.pac", name)), &moveset).unwrap();
+            fs::write(dir.join(format!("Fit{}MotionEtc.pac", name)), &moveset).unwrap();
+        }
+
+        let brawl_ex = sd.path().join("projectm/pf/BrawlEx");
+        let config = |dir: &str, file: &str, tag: &[u8], values: &[(usize, &[u8])]| {
+            let mut data = tag.to_vec();
+            data.resize(0x100, 0);
+            for (offset, value) in values {
+                data[*offset .. offset + value.len()].copy_from_slice(value);
+            }
+            fs::create_dir_all(brawl_ex.join(dir)).unwrap();
+            fs::write(brawl_ex.join(dir).join(file), data).unwrap();
+        };
+        config("FighterConfig", "Fighter40.dat", b"FCFG", &[(0x60, b"Knuckles")]);
+        config("SlotConfig", "Slot40.dat", b"SLTC", &[(0x0C, &0x2710_u32.to_be_bytes()), (0x10, &0x1F40_u32.to_be_bytes())]);
+        config("CosmeticConfig", "Cosmetic40.dat", b"COSC", &[(0x0A, &[18])]);
+        config("SlotConfig", "Slot00.dat", b"SLTC", &[(0x0C, &0x2711_u32.to_be_bytes()), (0x10, &0x1F41_u32.to_be_bytes())]);
+
+        let result = BrawlMod::new(brawl.path(), Some(sd.path())).load_fighters_with_config(&LoadConfig::default()).unwrap();
+        assert!(result.failures.is_empty());
+        let slot_metadata = |name: &str| {
+            let fighter = result.fighters.iter().find(|x| x.cased_name == name).unwrap();
+            let slot_metadata = fighter.slot_metadata().unwrap();
+            (slot_metadata.victory_theme, slot_metadata.announcer_call, slot_metadata.series_icon)
+        };
+        assert_eq!(slot_metadata("Knuckles"), (Some(0x2710), Some(0x1F40), Some(18)));
+        assert_eq!(slot_metadata("Mario"),    (Some(0x2711), Some(0x1F41), Some(0)));
+        assert_eq!(slot_metadata("Luigi"),    (None, None, Some(0)));
+    }
+
+    #[test]
+    fn load_fighters_provenance() {
+        let brawl = tempfile::tempdir().unwrap();
+        let sd = sd_card(&["projectm"]);
+        fs::create_dir_all(brawl.path().join("fighter/mario")).unwrap();
+        fs::write(brawl.path().join("fighter/Fighter.pac"), fighter_pac(0x11)).unwrap();
+        let moveset = TestFighterBuilder::new("Test").moveset_pac();
+        fs::write(brawl.path().join("fighter/mario/FitMario.pac"), &moveset).unwrap();
+        fs::write(brawl.path().join("fighter/mario/FitMarioMotionEtc.pac"), &moveset).unwrap();
+
+        let brawl_mod = BrawlMod::new(brawl.path(), Some(sd.path()));
+        let load = || {
+            let mut result = brawl_mod.load_fighters_with_config(&LoadConfig::default()).unwrap();
+            assert!(result.failures.is_empty());
+            result.fighters.remove(0).provenance
+        };
+        let moveset_sha1 = |provenance: &Provenance| provenance.files.iter().find(|x| x.role == FileRole::Moveset).unwrap().sha1.clone();
+
+        let provenance = load();
+        assert_eq!(provenance.crate_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(provenance.load_config, Some(LoadConfig::default()));
+        assert_eq!(provenance.files.len(), 2);
+        assert!(provenance.files.iter().all(|x| !x.from_mod && x.sha1.len() == 40));
+        assert_eq!(provenance.common_fighter.as_ref().unwrap().path, brawl.path().join("fighter/Fighter.pac"));
+        assert_eq!(provenance.codeset.as_ref().unwrap().path, sd.path().join("codes/RSBE01.gct"));
+        assert!(provenance.matches_files(&brawl_mod));
+
+        // altering a file changes its hash
+        let mut altered = moveset.clone();
+        *altered.last_mut().unwrap() ^= 0xFF;
+        fs::write(brawl.path().join("fighter/mario/FitMario.pac"), &altered).unwrap();
+        assert!(!provenance.matches_files(&brawl_mod));
+        let altered_provenance = load();
+        assert_ne!(moveset_sha1(&provenance), moveset_sha1(&altered_provenance));
+        assert!(altered_provenance.matches_files(&brawl_mod));
+
+        // a vanilla file replaced by the mod
+        fs::create_dir_all(sd.path().join("projectm/pf/fighter/mario")).unwrap();
+        fs::write(sd.path().join("projectm/pf/fighter/mario/FitMario.pac"), &moveset).unwrap();
+        assert!(!altered_provenance.matches_files(&brawl_mod));
+        let modded_provenance = load();
+        assert!(modded_provenance.files.iter().find(|x| x.role == FileRole::Moveset).unwrap().from_mod);
+        assert_eq!(moveset_sha1(&provenance), moveset_sha1(&modded_provenance));
+        assert!(modded_provenance.matches_files(&brawl_mod));
+    }
+}
@@ -0,0 +1,333 @@
+//! Writes events in the text format BrawlBox displays them in, so output can be diffed against existing BrawlBox dumps.
+//!
+//! This is separate from `script_assembler`, which has its own format that can be assembled back into events.
+//! Events in the table below are written as `Name: Parameter=value, Parameter=value` using BrawlBox's event and parameter names:
+//! *   Values are hexadecimal with upper case digits e.g. `Damage=0xC`, negative values are written as 32 bit two's complement.
+//! *   Scalars are decimal in frame units without trailing zeros e.g. `Size=4.5`.
+//! *   Offsets are hexadecimal, external offsets (stored as negative values) are written as `External`.
+//! *   Bools are `true` or `false`.
+//! *   Variables are written as `IC-Basic[21]`, `LA-Bit[3]` or `RA-Float[5]`.
+//! *   Requirements are written as words e.g. `Animation End`, with `Not ` in front when flipped.
+//! *   Events without arguments are just the name e.g. `Terminate Collisions`.
+//! *   Fields packed into one value are split into separate parameters e.g. the bone and id of an Offensive Collision.
+//!
+//! Every other event falls back to a generic format of the raw event id followed by its arguments e.g. `0C0B0200: Arg0=0x1, Arg1=2.5`.
+//! The generic format is also used when a covered event has a different number of arguments or a packed field is not a value.
+//! Currently covered are the control flow, interrupt, subaction timing, hitbox, sound, variable and direction events listed in `EVENTS`.
+//!
+//! The expected strings in the tests follow BrawlBox's names and parameter order, they were not captured from BrawlBox itself.
+//! Regenerate them from a BrawlBox dump when one is available, as a difference in spacing or hex casing would break a diff.
+
+use crate::script::{Argument, Event, Script, VariableDataType, VariableMemoryType};
+
+impl Script {
+    /// Writes every event in the format BrawlBox displays it in, one event per line.
+    /// See the `brawlbox_text` module documentation for which events are covered.
+    pub fn to_brawlbox_text(&self) -> String {
+        let mut text = String::new();
+        for event in &self.events {
+            text.push_str(&event_text(event));
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// Writes a single event in the format BrawlBox displays it in.
+pub fn event_text(event: &Event) -> String {
+    EVENTS.iter()
+        .find(|x| x.matches(event))
+        .map(|x| x.text(event))
+        .unwrap_or_else(|| generic_text(event))
+}
+
+fn generic_text(event: &Event) -> String {
+    let id = format!("{:08X}", event.raw_id());
+    if event.arguments.is_empty() {
+        return id;
+    }
+    let arguments: Vec<String> = event.arguments.iter().enumerate().map(|(i, x)| format!("Arg{}={}", i, argument_text(x))).collect();
+    format!("{}: {}", id, arguments.join(", "))
+}
+
+fn argument_text(argument: &Argument) -> String {
+    match argument {
+        Argument::Value (value) => format!("0x{:X}", *value as u32),
+        Argument::Scalar (value) => value.as_frames().to_string(),
+        Argument::Offset (offset) if offset.offset < 0 => "External".to_string(),
+        Argument::Offset (offset) => format!("0x{:X}", offset.offset),
+        Argument::Bool (value) => value.to_string(),
+        Argument::File (value) => format!("0x{:X}", *value as u32),
+        Argument::Variable (variable) => {
+            let memory_type = match variable.memory_type {
+                VariableMemoryType::InternalConstant => "IC".to_string(),
+                VariableMemoryType::LongtermAccess   => "LA".to_string(),
+                VariableMemoryType::RandomAccess     => "RA".to_string(),
+                VariableMemoryType::Unknown (value)  => format!("0x{:X}", value),
+            };
+            let data_type = match variable.data_type {
+                VariableDataType::Int             => "Basic".to_string(),
+                VariableDataType::Float           => "Float".to_string(),
+                VariableDataType::Bool            => "Bit".to_string(),
+                VariableDataType::Unknown (value) => format!("0x{:X}", value),
+            };
+            format!("{}-{}[{}]", memory_type, data_type, variable.address)
+        }
+        Argument::Requirement { flip, ty } => {
+            let name = match ty.name() {
+                "Unknown" => format!("0x{:X}", ty.id()),
+                name      => words(name),
+            };
+            if *flip { format!("Not {}", name) } else { name }
+        }
+        Argument::Unknown (_, value) => format!("0x{:X}", *value as u32),
+    }
+}
+
+/// Splits a CamelCase name into words e.g. `HoldingALedge` becomes `Holding A Ledge`
+fn words(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut text = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if i > 0 && c.is_ascii_uppercase() {
+            let prev = chars[i - 1];
+            let next_lowercase = chars.get(i + 1).map(|x| x.is_ascii_lowercase()).unwrap_or(false);
+            if prev.is_ascii_lowercase() || prev.is_ascii_digit() || (prev.is_ascii_uppercase() && next_lowercase) {
+                text.push(' ');
+            }
+        }
+        text.push(*c);
+    }
+    text
+}
+
+/// A parameter displayed by BrawlBox, usually an entire argument
+struct Param {
+    name: &'static str,
+    argument: usize,
+    shift: u32,
+    width: u32,
+}
+
+const fn param(name: &'static str, argument: usize) -> Param {
+    Param { name, argument, shift: 0, width: 32 }
+}
+
+/// A parameter stored in `width` bits of a value argument, starting at bit `shift`
+const fn packed(name: &'static str, argument: usize, shift: u32, width: u32) -> Param {
+    Param { name, argument, shift, width }
+}
+
+struct EventText {
+    namespace: u8,
+    code: u8,
+    name: &'static str,
+    /// Events with fewer arguments leave off the parameters of the missing arguments
+    min_arguments: usize,
+    params: &'static [Param],
+}
+
+const fn event(namespace: u8, code: u8, name: &'static str, params: &'static [Param]) -> EventText {
+    let mut arguments = 0;
+    let mut i = 0;
+    while i < params.len() {
+        if params[i].argument + 1 > arguments {
+            arguments = params[i].argument + 1;
+        }
+        i += 1;
+    }
+    EventText { namespace, code, name, min_arguments: arguments, params }
+}
+
+impl EventText {
+    const fn min_arguments(mut self, min_arguments: usize) -> EventText {
+        self.min_arguments = min_arguments;
+        self
+    }
+
+    fn max_arguments(&self) -> usize {
+        self.params.iter().map(|x| x.argument + 1).max().unwrap_or(0)
+    }
+
+    fn matches(&self, event: &Event) -> bool {
+        self.namespace == event.namespace && self.code == event.code &&
+            event.arguments.len() >= self.min_arguments && event.arguments.len() <= self.max_arguments() &&
+            self.params.iter()
+                .filter(|x| x.width != 32 && x.argument < event.arguments.len())
+                .all(|x| matches!(event.arguments[x.argument], Argument::Value (_)))
+    }
+
+    fn text(&self, event: &Event) -> String {
+        let params: Vec<String> = self.params.iter().filter(|x| x.argument < event.arguments.len()).map(|param| {
+            let value = match &event.arguments[param.argument] {
+                Argument::Value (value) if param.width != 32 => format!("0x{:X}", (*value as u32 >> param.shift) & ((1 << param.width) - 1)),
+                argument => argument_text(argument),
+            };
+            format!("{}={}", param.name, value)
+        }).collect();
+
+        if params.is_empty() {
+            self.name.to_string()
+        } else {
+            format!("{}: {}", self.name, params.join(", "))
+        }
+    }
+}
+
+const CONDITION: &[Param] = &[param("Requirement", 0), param("Variable", 1), param("Comparison", 2), param("Compare To", 3)];
+
+/// The first argument holds the bone, id and set id, which BrawlBox displays first, before the damage
+const HITBOX: &[Param] = &[
+    packed("Bone", 0, 16, 16),
+    packed("Id", 0, 0, 8),
+    packed("SetId", 0, 8, 8),
+    param("Damage", 1),
+    param("Trajectory", 2),
+    packed("WeightKnockback", 3, 16, 16),
+    packed("KnockbackGrowth", 3, 0, 16),
+    packed("ShieldDamage", 4, 16, 16),
+    packed("BaseKnockback", 4, 0, 16),
+    param("Size", 5),
+    param("X Offset", 6),
+    param("Y Offset", 7),
+    param("Z Offset", 8),
+    param("TripRate", 9),
+    param("HitlagMultiplier", 10),
+    param("SDIMultiplier", 11),
+    param("Flags", 12),
+];
+
+/// Events with BrawlBox names, an event is written with the first entry that matches it.
+const EVENTS: &[EventText] = &[
+    // control flow
+    event(0x00, 0x01, "Synchronous Timer",  &[param("Frames", 0)]),
+    event(0x00, 0x02, "Nop",                &[]),
+    event(0x00, 0x02, "Asynchronous Timer", &[param("Frames", 0)]),
+    event(0x00, 0x04, "Set Loop",           &[param("Iterations", 0)]),
+    event(0x00, 0x05, "Execute Loop",       &[]),
+    event(0x00, 0x07, "Subroutine",         &[param("Offset", 0)]),
+    event(0x00, 0x08, "Return",             &[]),
+    event(0x00, 0x09, "Goto",               &[param("Offset", 0)]),
+    event(0x00, 0x0A, "If",                 CONDITION).min_arguments(1),
+    event(0x00, 0x0B, "And",                CONDITION).min_arguments(1),
+    event(0x00, 0x0C, "Or",                 CONDITION).min_arguments(1),
+    event(0x00, 0x0D, "Else If",            CONDITION).min_arguments(1),
+    event(0x00, 0x0E, "Else",               &[]),
+    event(0x00, 0x0F, "End If",             &[]),
+    event(0x00, 0x10, "Switch",             &[param("Unknown", 0), param("Value", 1)]),
+    event(0x00, 0x11, "Default Case",       &[]),
+    event(0x00, 0x11, "Case",               &[param("Value", 0)]),
+    event(0x00, 0x13, "End Switch",         &[]),
+    event(0x01, 0x01, "Loop Rest",          &[]),
+
+    // interrupts
+    event(0x02, 0x00, "Change Action Status", &[param("Status ID", 0), param("Action", 1), param("Requirement", 2), param("Variable", 3), param("Comparison", 4), param("Compare To", 5)]).min_arguments(3),
+    event(0x02, 0x01, "Change Action",        &[param("Action", 0), param("Requirement", 1), param("Variable", 2), param("Comparison", 3), param("Compare To", 4)]).min_arguments(2),
+    event(0x02, 0x04, "Additional Change Action Requirement", CONDITION).min_arguments(1),
+    event(0x64, 0x00, "Allow Interrupt",    &[]),
+
+    // subactions and timing
+    event(0x04, 0x00, "Change Subaction",     &[param("Subaction", 0), param("Pass Frame", 1)]).min_arguments(1),
+    event(0x04, 0x06, "Set Frame",            &[param("Frame", 0)]),
+    event(0x04, 0x07, "Frame Speed Modifier", &[param("Multiplier", 0), param("Unknown", 1)]).min_arguments(1),
+
+    // collisions
+    event(0x06, 0x00, "Offensive Collision",  HITBOX),
+    event(0x06, 0x04, "Terminate Collisions", &[]),
+    event(0x06, 0x01, "Change Hitbox Damage", &[param("Id", 0), param("Damage", 1)]),
+    event(0x06, 0x02, "Change Hitbox Size",   &[param("Id", 0), param("Size", 1)]),
+    event(0x06, 0x03, "Delete Hitbox",        &[param("Id", 0)]),
+
+    // sound
+    event(0x0A, 0x00, "Sound Effect",             &[param("Sound", 0)]),
+    event(0x0A, 0x01, "Sound Effect 2",           &[param("Sound", 0)]),
+    event(0x0A, 0x02, "Sound Effect (Transient)", &[param("Sound", 0)]),
+    event(0x0A, 0x03, "Stop Sound Effect",        &[param("Sound", 0)]),
+
+    // variables
+    event(0x12, 0x00, "Basic Variable Set",       &[param("Value", 0), param("Variable", 1)]),
+    event(0x12, 0x01, "Basic Variable Add",       &[param("Value", 0), param("Variable", 1)]),
+    event(0x12, 0x02, "Basic Variable Subtract",  &[param("Value", 0), param("Variable", 1)]),
+    event(0x12, 0x03, "Basic Variable Increment", &[param("Variable", 0)]),
+    event(0x12, 0x04, "Basic Variable Decrement", &[param("Variable", 0)]),
+    event(0x12, 0x06, "Float Variable Set",       &[param("Value", 0), param("Variable", 1)]),
+    event(0x12, 0x07, "Float Variable Add",       &[param("Value", 0), param("Variable", 1)]),
+    event(0x12, 0x08, "Float Variable Subtract",  &[param("Value", 0), param("Variable", 1)]),
+    event(0x12, 0x0A, "Bit Variable Set",         &[param("Variable", 0)]),
+    event(0x12, 0x0B, "Bit Variable Clear",       &[param("Variable", 0)]),
+
+    // direction and ground state
+    event(0x05, 0x00, "Reverse Direction", &[]),
+    event(0x0E, 0x00, "Set Air/Ground",    &[param("State", 0)]),
+    event(0x08, 0x00, "Set Edge Slide",    &[param("State", 0)]),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::{FixedPoint, Offset, Requirement, Variable};
+    use crate::test_util::{damage, ScriptBuilder};
+
+    fn variable(memory_type: VariableMemoryType, data_type: VariableDataType, address: u32) -> Argument {
+        Argument::Variable (Variable { memory_type, data_type, address })
+    }
+
+    #[test]
+    fn covered_events() {
+        let script = ScriptBuilder::new()
+            .sync_timer(5.0)
+            .async_timer(14.0)
+            .hitbox(damage(12).id(1).bone(0x10).angle(361).kbg(100).bkb(30).size(4.5).offset(0.0, 2.25, -1.0))
+            .event(0x00, 0x0A, vec!(Argument::Requirement { flip: true, ty: Requirement::HoldingALedge }))
+            .event(0x12, 0x00, vec!(Argument::Value (-1), variable(VariableMemoryType::LongtermAccess, VariableDataType::Int, 21)))
+            .event(0x12, 0x0A, vec!(variable(VariableMemoryType::RandomAccess, VariableDataType::Bool, 16)))
+            .event(0x00, 0x07, vec!(Argument::Offset (Offset { offset: 0x1A40, origin: 0 })))
+            .event(0x00, 0x07, vec!(Argument::Offset (Offset { offset: -1, origin: 0 })))
+            .event(0x04, 0x07, vec!(Argument::Scalar (FixedPoint::from_frames(0.5))))
+            .event(0x0E, 0x00, vec!(Argument::Value (2)))
+            .event(0x00, 0x0F, vec!())
+            .terminate_collisions()
+            .build();
+
+        assert_eq!(script.to_brawlbox_text(), "\
+Synchronous Timer: Frames=5
+Asynchronous Timer: Frames=14
+Offensive Collision: Bone=0x10, Id=0x1, SetId=0x0, Damage=0xC, Trajectory=0x169, WeightKnockback=0x0, KnockbackGrowth=0x64, ShieldDamage=0x0, BaseKnockback=0x1E, Size=4.5, X Offset=0, Y Offset=2.25, Z Offset=-1, TripRate=0, HitlagMultiplier=1, SDIMultiplier=1, Flags=0x30000
+If: Requirement=Not Holding A Ledge
+Basic Variable Set: Value=0xFFFFFFFF, Variable=LA-Basic[21]
+Bit Variable Set: Variable=RA-Bit[16]
+Subroutine: Offset=0x1A40
+Subroutine: Offset=External
+Frame Speed Modifier: Multiplier=0.5
+Set Air/Ground: State=0x2
+End If
+Terminate Collisions
+");
+    }
+
+    #[test]
+    fn generic_fallback() {
+        // not in the table
+        let event = Event { namespace: 0x0C, code: 0x0B, unk1: 0, arguments: vec!(Argument::Value (1), Argument::Scalar (FixedPoint::from_frames(2.5))) };
+        assert_eq!(event_text(&event), "0C0B0200: Arg0=0x1, Arg1=2.5");
+
+        // a covered event with an unexpected number of arguments
+        let event = Event { namespace: 0x06, code: 0x04, unk1: 0, arguments: vec!(Argument::Bool (true)) };
+        assert_eq!(event_text(&event), "06040100: Arg0=true");
+
+        // a packed field that is not a value
+        let mut hitbox = ScriptBuilder::new().hitbox(damage(3)).build().events.remove(0);
+        hitbox.arguments[0] = variable(VariableMemoryType::InternalConstant, VariableDataType::Int, 3);
+        assert!(event_text(&hitbox).starts_with("06000D00: Arg0=IC-Basic[3], Arg1=0x3, Arg2=0x0, "));
+
+        let event = Event { namespace: 0x0F, code: 0x00, unk1: 0, arguments: vec!() };
+        assert_eq!(event_text(&event), "0F000000");
+    }
+
+    #[test]
+    fn requirement_words() {
+        assert_eq!(words("AnimationEnd"), "Animation End");
+        assert_eq!(words("HoldingALedge"), "Holding A Ledge");
+        assert_eq!(words("OnGround"), "On Ground");
+    }
+}
@@ -5,6 +5,7 @@ use crate::resources;
 use crate::chr0::*;
 use crate::mdl0::*;
 use crate::plt0::*;
+use crate::vis0::*;
 
 pub fn bres(data: FancySlice) -> Bres {
     let endian         = data.u16_be(0x4);
@@ -27,6 +28,7 @@ fn bres_group(data: FancySlice) -> Vec<BresChild> {
             "CHR0" => BresChildData::Chr0 (chr0(child_data)),
             "MDL0" => BresChildData::Mdl0 (mdl0(child_data)),
             "PLT0" => BresChildData::Plt0 (plt0(child_data)),
+            "VIS0" => BresChildData::Vis0 (vis0(child_data)),
             "" => BresChildData::Bres (bres_group(data.relative_fancy_slice(resource.data_offset as usize ..))), // TODO: I suspect the match on "" is succeeding by accident
             _  => BresChildData::Unknown (tag),
         };
@@ -45,6 +47,7 @@ fn bres_group(data: FancySlice) -> Vec<BresChild> {
 // BRESHeader and RootHeader are combined because without BRESEntry they appear to be sequential
 const BRES_HEADER_SIZE: usize = 0x10;
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Bres {
     pub endian:   u16,
     pub version:  u16,
@@ -237,16 +240,19 @@ impl BresChild {
 
 const ROOT_HEADER_SIZE: usize = 0x8;
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct BresChild {
     pub name: String,
     pub data: BresChildData
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub enum BresChildData {
     Chr0 (Chr0),
     Mdl0 (Mdl0),
     Plt0 (Plt0),
+    Vis0 (Vis0),
     Bres (Vec<BresChild>),
     Unknown (String)
 }
@@ -258,13 +264,15 @@ mod tests {
     use std::fs;
     use fancy_slice::FancySlice;
 
+    /// Needs a brres file that is not in the repo, run with `cargo test compile_bres -- --ignored --nocapture`
     #[test]
+    #[ignore]
     fn compile_bres() {
         let itembox = fs::read("/home/mier/Downloads/old_koopa_gba.d/itembox.brres")
             .unwrap();
 
         let itembox_bres = super::bres(FancySlice::new(&itembox));
 
-        io::stdout().write(&itembox_bres.compile2()).unwrap();
+        io::stdout().write_all(&itembox_bres.compile()).unwrap();
     }
 }
@@ -0,0 +1,271 @@
+//! Knockback calculations, estimating the percent a hitbox kills at on a stage.
+//!
+//! The estimate is for comparing hitboxes and fighters with each other, it will not match the game exactly:
+//! *   The launch is simulated in empty space, stage collision e.g. ground bouncing is not modelled.
+//! *   The defender does nothing after the hit: no hitstun cancelling, air dodging, jumping, teching or recovering.
+//!     A defender survives if they are still inside the blast zone when their hitstun has ended and their launch speed has decayed.
+//! *   Hitlag, smash DI, crouch cancelling, rage like effects and charged smash attacks are not modelled.
+//!     Stale move negation can be included with `Attacker::damage_multiplier`.
+//! *   The top blast zone only kills during hitstun, like the game, but there is no distinction between star and screen KOs.
+//! *   The sakurai angle (361) uses its grounded value.
+
+use crate::high_level_fighter::HitBoxValues;
+use crate::sakurai::fighter_data::FighterAttributes;
+use crate::script_ast::AngleFlip;
+
+/// Knockback is converted into launch speed by multiplying with this
+const LAUNCH_SPEED_MULTIPLIER: f32 = 0.03;
+/// The launch speed decreases by this amount every frame until it reaches 0
+const LAUNCH_SPEED_DECAY: f32 = 0.051;
+/// Knockback is converted into frames of hitstun by multiplying with this
+const HITSTUN_MULTIPLIER: f32 = 0.4;
+/// The largest change to the launch angle that DI can make
+pub const DI_MAX_DEGREES: f32 = 18.0;
+/// Damage can not go higher than this
+pub const MAX_PERCENT: u32 = 999;
+/// Launches are never simulated for longer than this, in case the launch speed never decays
+const MAX_LAUNCH_FRAMES: u32 = 600;
+
+const SAKURAI_ANGLE: i32 = 361;
+
+/// The parts of a stage that determine whether a launch kills.
+/// The origin is the same as the fighters, the center of the stage with y = 0 at the top of the main platform.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct StageParameters {
+    pub blast_zone_left:   f32,
+    pub blast_zone_right:  f32,
+    pub blast_zone_top:    f32,
+    pub blast_zone_bottom: f32,
+}
+
+impl StageParameters {
+    fn outside(&self, x: f32, y: f32, in_hitstun: bool) -> bool {
+        x < self.blast_zone_left || x > self.blast_zone_right || y < self.blast_zone_bottom || (in_hitstun && y > self.blast_zone_top)
+    }
+}
+
+/// The fighter that owns the hitbox
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct Attacker {
+    pub x:                 f32,
+    pub facing_right:      bool,
+    /// Multiplies the damage of the hitbox e.g. 0.9 for a move that is stale once
+    pub damage_multiplier: f32,
+}
+
+impl Default for Attacker {
+    fn default() -> Attacker {
+        Attacker {
+            x:                 0.0,
+            facing_right:      true,
+            damage_multiplier: 1.0,
+        }
+    }
+}
+
+/// How the defender holds the control stick during hitlag
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum DiMode {
+    /// No DI, the defender is launched at the angle of the hitbox
+    None,
+    /// The defender DIs to survive for as long as possible
+    Optimal,
+    /// The defender DIs to die as early as possible
+    Worst,
+}
+
+/// Angle changes tried when searching for the best and worst DI
+const DI_CANDIDATES: [f32; 5] = [-DI_MAX_DEGREES, -DI_MAX_DEGREES / 2.0, 0.0, DI_MAX_DEGREES / 2.0, DI_MAX_DEGREES];
+
+/// Returns the knockback dealt by `hitbox` to a defender with `weight`, who is at `percent` after the damage of the hit has been added.
+pub fn knockback(hitbox: &HitBoxValues, damage: f32, percent: f32, weight: f32) -> f32 {
+    let (damage, percent) = if hitbox.wdsk != 0 {
+        // weight dependent set knockback replaces the damage and treats the defender as being at 10%
+        (hitbox.wdsk as f32, 10.0)
+    } else {
+        (damage, percent)
+    };
+    let scaled = (percent / 10.0 + percent * damage / 20.0) * (200.0 / (weight + 100.0)) * 1.4 + 18.0;
+    scaled * hitbox.kbg as f32 / 100.0 + hitbox.bkb as f32
+}
+
+/// Returns the frames of hitstun caused by `knockback`
+pub fn hitstun(knockback: f32) -> u32 {
+    (knockback * HITSTUN_MULTIPLIER) as u32
+}
+
+/// Returns the launch angle in degrees, before DI, with 0 pointing right and 90 pointing up
+fn launch_angle(hitbox: &HitBoxValues, knockback: f32, attacker: &Attacker, defender_x: f32) -> f32 {
+    let angle = if hitbox.trajectory == SAKURAI_ANGLE {
+        if knockback < 32.0 { 0.0 } else { 44.0 }
+    } else {
+        hitbox.trajectory as f32
+    };
+
+    let launch_right = match hitbox.angle_flipping {
+        AngleFlip::AwayFromAttacker   => defender_x >= attacker.x,
+        AngleFlip::AttackerDirReverse => !attacker.facing_right,
+        AngleFlip::AttackerDir | AngleFlip::FaceZaxis | AngleFlip::Unknown (_) => attacker.facing_right,
+    };
+    if launch_right { angle } else { 180.0 - angle }
+}
+
+/// Returns true if a defender launched from `position` leaves the blast zone
+fn launch_kills(knockback: f32, angle: f32, defender: &FighterAttributes, stage: &StageParameters, position: (f32, f32)) -> bool {
+    let (x_dir, y_dir) = (angle.to_radians().cos(), angle.to_radians().sin());
+    let hitstun = hitstun(knockback);
+    let mut launch_speed = knockback * LAUNCH_SPEED_MULTIPLIER;
+    let mut fall_speed = 0.0;
+    let (mut x, mut y) = position;
+
+    for frame in 0..MAX_LAUNCH_FRAMES {
+        let in_hitstun = frame < hitstun;
+        if !in_hitstun && launch_speed <= 0.0 {
+            return false;
+        }
+
+        x += launch_speed * x_dir;
+        y += launch_speed * y_dir - fall_speed;
+        if stage.outside(x, y, in_hitstun) {
+            return true;
+        }
+
+        launch_speed = (launch_speed - LAUNCH_SPEED_DECAY).max(0.0);
+        fall_speed = (fall_speed + defender.gravity).min(defender.term_vel);
+    }
+    false
+}
+
+/// Returns true if the hit kills a defender at `percent` before the hit
+fn kills(hitbox: &HitBoxValues, attacker: &Attacker, defender: &FighterAttributes, stage: &StageParameters, position: (f32, f32), di: DiMode, percent: u32) -> bool {
+    let damage = hitbox.damage * attacker.damage_multiplier;
+    let knockback = knockback(hitbox, damage, percent as f32 + damage, defender.weight);
+    let angle = launch_angle(hitbox, knockback, attacker, position.0);
+    let kills_with_di = |di_angle: &f32| launch_kills(knockback, angle + di_angle, defender, stage, position);
+
+    match di {
+        DiMode::None    => kills_with_di(&0.0),
+        DiMode::Optimal => DI_CANDIDATES.iter().all(kills_with_di),
+        DiMode::Worst   => DI_CANDIDATES.iter().any(kills_with_di),
+    }
+}
+
+/// Estimates the lowest percent, before the hit, at which `hitbox` kills a defender with `defender_attrs` standing at `position` on `stage`.
+/// Returns None if the hitbox does not kill even at 999%.
+///
+/// The percent is found by bisection, which assumes that any percent above a killing percent also kills.
+/// See the module documentation for what the estimate does not take into account.
+pub fn kill_percent(hitbox: &HitBoxValues, attacker: &Attacker, defender_attrs: &FighterAttributes, stage: &StageParameters, position: (f32, f32), di: DiMode) -> Option<u32> {
+    let kills = |percent| kills(hitbox, attacker, defender_attrs, stage, position, di, percent);
+
+    if !kills(MAX_PERCENT) {
+        return None;
+    }
+
+    // invariant: `high` kills and everything below `low` does not
+    let mut low = 0;
+    let mut high = MAX_PERCENT;
+    while low < high {
+        let mid = (low + high) / 2;
+        if kills(mid) {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    Some(high)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::high_level_fighter::CollisionBoxValues;
+    use crate::script_ast::{EventAst, FloatValue, HitBoxArguments, ScriptAst};
+    use crate::test_util::{damage, HitBoxBuilder, ScriptBuilder, TestFighterBuilder};
+
+    const WEIGHT: usize = 0xb0;
+    const GRAVITY: usize = 0x64;
+    const TERM_VEL: usize = 0x68;
+
+    const STAGE: StageParameters = StageParameters {
+        blast_zone_left:   -200.0,
+        blast_zone_right:  200.0,
+        blast_zone_top:    180.0,
+        blast_zone_bottom: -1000.0,
+    };
+
+    /// Returns the values of `hitbox` and the attributes of a fighter with `weight`
+    fn hitbox_and_attributes(hitbox: HitBoxBuilder, weight: f32) -> (HitBoxValues, FighterAttributes) {
+        let script = ScriptAst::new(&ScriptBuilder::new().hitbox(hitbox).build());
+        let values = match &script.block.events[0] {
+            EventAst::CreateHitBox (args @ HitBoxArguments { damage: FloatValue::Constant (damage), .. }) => CollisionBoxValues::from_hitbox(args, *damage),
+            _ => unreachable!(),
+        };
+        let values = match values {
+            CollisionBoxValues::Hit (values) => values,
+            CollisionBoxValues::Grab (_) => unreachable!(),
+        };
+
+        let fighter = TestFighterBuilder::new("Test")
+            .attribute_f32(WEIGHT, weight)
+            .attribute_f32(GRAVITY, 0.1)
+            .attribute_f32(TERM_VEL, 1.8)
+            .build();
+        (values, fighter.get_fighter_data().unwrap().attributes.clone())
+    }
+
+    #[test]
+    fn knockback_formula() {
+        let (hitbox, _) = hitbox_and_attributes(damage(10).id(0).angle(45).bkb(30).kbg(100), 100.0);
+        // ((50 / 10 + 50 * 10 / 20) * 1 * 1.4 + 18) * 1 + 30
+        assert!((knockback(&hitbox, 10.0, 50.0, 100.0) - 90.0).abs() < 0.001);
+        assert_eq!(hitstun(90.0), 36);
+
+        let (set, _) = hitbox_and_attributes(damage(10).id(0).angle(45).bkb(30).kbg(100).wdsk(20), 100.0);
+        assert_eq!(knockback(&set, 10.0, 0.0, 100.0), knockback(&set, 10.0, 150.0, 100.0));
+    }
+
+    #[test]
+    fn horizontal_kill_percent() {
+        // with a horizontal launch the distance travelled before the launch speed decays is about v^2 / 2a,
+        // so the knockback needed to reach the right blast zone can be solved for directly
+        let (hitbox, attributes) = hitbox_and_attributes(damage(15).id(0).angle(0).bkb(40).kbg(100), 100.0);
+        let needed_knockback = (2.0 * LAUNCH_SPEED_DECAY * STAGE.blast_zone_right).sqrt() / LAUNCH_SPEED_MULTIPLIER;
+        // ((p / 10 + p * 15 / 20) * 1.4 + 18) + 40 = needed_knockback, where p includes the damage of the hit
+        let expected = (needed_knockback - 58.0) / (0.85 * 1.4) - 15.0;
+
+        let percent = kill_percent(&hitbox, &Attacker::default(), &attributes, &STAGE, (0.0, 0.0), DiMode::None).unwrap();
+        assert!((percent as f32 - expected).abs() <= 3.0, "expected about {}% but was {}%", expected, percent);
+
+        // launched the other way when behind the attacker
+        let attacker = Attacker { x: 10.0, .. Attacker::default() };
+        assert_eq!(kill_percent(&hitbox, &attacker, &attributes, &STAGE, (0.0, 0.0), DiMode::None), Some(percent));
+    }
+
+    #[test]
+    fn kill_percent_ordering() {
+        let attacker = Attacker::default();
+        let kill = |hitbox: HitBoxBuilder, weight, di| {
+            let (hitbox, attributes) = hitbox_and_attributes(hitbox, weight);
+            kill_percent(&hitbox, &attacker, &attributes, &STAGE, (0.0, 0.0), di)
+        };
+
+        let weak = kill(damage(12).id(0).angle(40).bkb(20).kbg(100), 100.0, DiMode::None).unwrap();
+        let strong = kill(damage(12).id(0).angle(40).bkb(60).kbg(100), 100.0, DiMode::None).unwrap();
+        assert!(strong < weak);
+
+        let heavy = kill(damage(12).id(0).angle(40).bkb(20).kbg(100), 130.0, DiMode::None).unwrap();
+        assert!(heavy > weak);
+
+        let optimal = kill(damage(12).id(0).angle(40).bkb(20).kbg(100), 100.0, DiMode::Optimal).unwrap();
+        let worst = kill(damage(12).id(0).angle(40).bkb(20).kbg(100), 100.0, DiMode::Worst).unwrap();
+        assert!(worst <= weak);
+        assert!(optimal >= weak);
+        assert!(optimal > worst);
+
+        assert_eq!(kill(damage(1).id(0).angle(40).bkb(0).kbg(1), 100.0, DiMode::None), None);
+    }
+}
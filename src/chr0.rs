@@ -68,6 +68,7 @@ pub(crate) fn chr0(data: FancySlice) -> Chr0 {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Chr0 {
     pub name: String,
     size: i32,
@@ -81,8 +82,150 @@ pub struct Chr0 {
     pub children: Vec<Chr0Child>
 }
 
+impl Chr0 {
+    pub fn new(name: String, num_frames: u16, loop_value: bool, children: Vec<Chr0Child>) -> Chr0 {
+        Chr0 {
+            name,
+            size:             0,
+            version:          4,
+            bres_offset:      0,
+            orig_path_offset: 0,
+            num_frames,
+            num_children:     children.len() as u16,
+            loop_value,
+            scaling_rule:     0,
+            children,
+        }
+    }
+
+    /// Returns the name of the first bone with a linear track that does not have a value for every frame of the animation.
+    /// Linear tracks store one value per frame, so they can not be written when the number of frames differs.
+    pub fn mismatched_linear_track(&self) -> Option<&str> {
+        let frames = self.num_frames as usize;
+        self.children.iter().find(|child| {
+            [&child.scale, &child.rot, &child.translation].iter().flat_map(|x| x.keyframes()).any(|keyframe| match keyframe {
+                Keyframe::Linear1 (header) => header.children_steps.len() != frames,
+                Keyframe::Linear2 (header) => header.children_steps.len() != frames,
+                Keyframe::Linear4 (values) => values.len() != frames,
+                _ => false,
+            })
+        }).map(|x| x.name.as_str())
+    }
+
+    /// Encodes the animation as a CHR0 placed `-bres_offset` bytes after the start of its BRES.
+    ///
+    /// Each track is written in the smallest format that stores it, the x, y and z keyframes of a track share a format:
+    /// *   Keyframes with the same value on every frame are stored as fixed values.
+    /// *   Linear rotation keyframes use Linear1, Linear2 or Linear4.
+    ///     Linear formats can only be stored for rotation, linear scale and translation keyframes become interpolated keyframes on every frame.
+    /// *   Interpolated keyframes use Interpolated4, Interpolated6 or Interpolated12.
+    ///
+    /// A smaller format is only used when it stores every value and tangent within `QUANTIZE_TOLERANCE`, keyframes already in the chosen format are written unchanged.
+    /// The names are stored after the keyframe data inside the CHR0, instead of in the string table of the BRES.
+    ///
+    /// Check `Chr0::mismatched_linear_track` first, linear tracks with the wrong number of values are written as is.
+    pub fn compile(&self, bres_offset: i32) -> Vec<u8> {
+        let names: Vec<&str> = self.children.iter().map(|x| x.name.as_str()).collect();
+        let tree = resources::resource_tree(&names);
+        let resources_size = resources::RESOURCE_HEADER_SIZE + resources::RESOURCE_SIZE * (self.children.len() + 1);
+        let children_offset = CHR0_HEADER_SIZE + resources_size;
+
+        let layouts: Vec<_> = self.children.iter().map(|x| x.layouts(self.num_frames)).collect();
+        let keyframes_offset = children_offset + layouts.iter()
+            .map(|layouts| CHR0_CHILD_SIZE + layouts.iter().map(|x| x.keyframes.len() * 4).sum::<usize>())
+            .sum::<usize>();
+
+        // child headers and the keyframes they point to
+        let mut child_headers = vec!();
+        let mut child_offsets = vec!();
+        let mut keyframe_data = vec!();
+        for (child, layouts) in self.children.iter().zip(layouts.iter()) {
+            let child_offset = children_offset + child_headers.len();
+            child_offsets.push(child_offset);
+            child_headers.extend(&i32::to_be_bytes(0)); // string_offset, filled in after the strings are placed
+            child_headers.extend(&u32::to_be_bytes(child.code.with_layouts(layouts).value));
+
+            for keyframe in layouts.iter().flat_map(|x| x.keyframes.iter()) {
+                if let Keyframe::Fixed (value) = keyframe {
+                    child_headers.extend(&f32::to_be_bytes(*value));
+                } else {
+                    let offset = keyframes_offset + keyframe_data.len() - child_offset;
+                    child_headers.extend(&u32::to_be_bytes(offset as u32));
+                    write_keyframe(&mut keyframe_data, keyframe);
+                    align4(&mut keyframe_data);
+                }
+            }
+        }
+
+        // the animation name followed by the bone names, each prefixed with its length
+        let strings_offset = keyframes_offset + keyframe_data.len();
+        let mut strings = vec!();
+        let mut string_offsets = vec!();
+        for name in std::iter::once(self.name.as_str()).chain(names.iter().cloned()) {
+            strings.extend(&u32::to_be_bytes(name.len() as u32));
+            string_offsets.push(strings_offset + strings.len());
+            strings.extend(name.as_bytes());
+            strings.push(0);
+            align4(&mut strings);
+        }
+
+        for (i, child_offset) in child_offsets.iter().enumerate() {
+            let header_index = child_offset - children_offset;
+            let string_offset = (string_offsets[i + 1] - child_offset) as i32;
+            child_headers[header_index .. header_index + 4].copy_from_slice(&string_offset.to_be_bytes());
+        }
+
+        let size = strings_offset + strings.len();
+        let mut output = Vec::with_capacity(size);
+
+        // create CHR0 header
+        output.extend(b"CHR0");
+        output.extend(&i32::to_be_bytes(size as i32));
+        output.extend(&i32::to_be_bytes(self.version));
+        output.extend(&i32::to_be_bytes(bres_offset));
+        output.extend(&i32::to_be_bytes(CHR0_HEADER_SIZE as i32)); // resources_offset
+        output.extend(&i32::to_be_bytes(string_offsets[0] as i32));
+        output.extend(&i32::to_be_bytes(0)); // orig_path_offset, the original path is not kept
+        output.extend(&u16::to_be_bytes(self.num_frames));
+        output.extend(&u16::to_be_bytes(self.children.len() as u16));
+        output.extend(&i32::to_be_bytes(self.loop_value as i32));
+        output.extend(&i32::to_be_bytes(self.scaling_rule));
+
+        // create resources, offsets are relative to the start of the resources
+        output.extend(&i32::to_be_bytes(resources_size as i32));
+        output.extend(&i32::to_be_bytes(self.children.len() as i32));
+        for (i, entry) in tree.iter().enumerate() {
+            output.extend(&u16::to_be_bytes(entry.id));
+            output.extend(&u16::to_be_bytes(0)); // flag
+            output.extend(&u16::to_be_bytes(entry.left_index));
+            output.extend(&u16::to_be_bytes(entry.right_index));
+            if i == 0 {
+                output.extend(&i32::to_be_bytes(0));
+                output.extend(&i32::to_be_bytes(0));
+            } else {
+                output.extend(&i32::to_be_bytes((string_offsets[i] - CHR0_HEADER_SIZE) as i32));
+                output.extend(&i32::to_be_bytes((child_offsets[i - 1] - CHR0_HEADER_SIZE) as i32));
+            }
+        }
+
+        output.extend(child_headers);
+        output.extend(keyframe_data);
+        output.extend(strings);
+        output
+    }
+
+    /// Returns the translation of the named bone on each frame of the animation, or None if the animation does not contain the bone.
+    pub fn bone_translations(&self, bone_name: &str) -> Option<Vec<Vector3<f32>>> {
+        let child = self.children.iter().find(|x| x.name == bone_name)?;
+        Some((0..self.num_frames as i32).map(|frame| child.translation.get_value(self.loop_value, frame, 0.0)).collect())
+    }
+}
+
+const CHR0_HEADER_SIZE: usize = 0x28;
+
 const CHR0_CHILD_SIZE: usize = 0x8;
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Chr0Child {
     pub name: String,
     pub scale:       KeyframeHolder,
@@ -92,6 +235,26 @@ pub struct Chr0Child {
 }
 
 impl Chr0Child {
+    /// Creates a bone track, tracks that are `KeyframeHolder::None` use the value of the bone in the model.
+    pub fn new(name: String, scale: KeyframeHolder, rot: KeyframeHolder, translation: KeyframeHolder) -> Chr0Child {
+        Chr0Child {
+            name,
+            scale,
+            rot,
+            translation,
+            code: Chr0ChildCode { value: 1 },
+        }
+    }
+
+    /// The keyframes of each track, converted to the format they are written in
+    fn layouts(&self, num_frames: u16) -> [TrackLayout; 3] {
+        [
+            TrackLayout::new(&self.scale, false, num_frames),
+            TrackLayout::new(&self.rot, true, num_frames),
+            TrackLayout::new(&self.translation, false, num_frames),
+        ]
+    }
+
     pub fn get_transform(&self, loop_value: bool, frame: i32) -> Matrix4<f32> {
         let scale = self.scale.get_value(loop_value, frame, 1.0);
         let rot = self.rot.get_value(loop_value, frame, 0.0);
@@ -107,7 +270,8 @@ impl Chr0Child {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Chr0ChildCode {
     value: u32,
 }
@@ -118,6 +282,31 @@ impl Chr0ChildCode {
         Chr0ChildCode { value }
     }
 
+    /// Returns the code with the bits describing how the tracks are stored replaced to match `layouts`, the order is scale, rotation then translation.
+    /// The remaining flags e.g. use_model_scale are kept.
+    fn with_layouts(&self, layouts: &[TrackLayout; 3]) -> Chr0ChildCode {
+        let mut value = self.value & !CODE_LAYOUT_BITS;
+        for (i, layout) in layouts.iter().enumerate() {
+            if layout.keyframes.is_empty() {
+                continue;
+            }
+            value |= 1 << (22 + i); // exists
+            if layout.isotropic {
+                value |= 1 << (4 + i);
+            }
+            // an isotropic track stores its single value in the z position
+            let fixed_components = if layout.isotropic { &layout.keyframes[0..1] } else { &layout.keyframes[..] };
+            for (component, keyframe) in fixed_components.iter().enumerate() {
+                if let Keyframe::Fixed (_) = keyframe {
+                    let component = if layout.isotropic { 2 } else { component };
+                    value |= 1 << (13 + i * 3 + component);
+                }
+            }
+            value |= layout.format.value() << [25, 27, 30][i];
+        }
+        Chr0ChildCode { value }
+    }
+
     pub fn identity                   (&self) -> bool { self.value & 0b0000_0000_0000_0000_0000_0000_0000_0010 != 0 } // Scale = 1, Rot = 0, Trans = 0
     pub fn rot_translation_zero       (&self) -> bool { self.value & 0b0000_0000_0000_0000_0000_0000_0000_0100 != 0 } // Rot = 0, Trans = 0 - Must be set if Identity is set
     pub fn scale_one                  (&self) -> bool { self.value & 0b0000_0000_0000_0000_0000_0000_0000_1000 != 0 } // Scale = 1          - Must be set if Identity is set
@@ -168,7 +357,11 @@ fn f(value: u32) -> Chr0Format {
     }
 }
 
-#[derive(Clone, Debug)]
+/// The bits of a `Chr0ChildCode` that depend on how the tracks are stored: isotropic, fixed, exists and format
+const CODE_LAYOUT_BITS: u32 = 0b1111_1111_1111_1111_1110_0000_0111_0000;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub enum Chr0Format {
     None,
     Interpolated4,
@@ -179,6 +372,60 @@ pub enum Chr0Format {
     Linear4,
 }
 
+impl Chr0Format {
+    fn value(&self) -> u32 {
+        match self {
+            Chr0Format::None           => 0,
+            Chr0Format::Interpolated4  => 1,
+            Chr0Format::Interpolated6  => 2,
+            Chr0Format::Interpolated12 => 3,
+            Chr0Format::Linear1        => 4,
+            Chr0Format::Linear2        => 5,
+            Chr0Format::Linear4        => 6,
+        }
+    }
+
+    fn is_linear(&self) -> bool {
+        matches!(self, Chr0Format::Linear1 | Chr0Format::Linear2 | Chr0Format::Linear4)
+    }
+}
+
+/// The largest error in a value or tangent allowed when storing a keyframe in a smaller format
+const QUANTIZE_TOLERANCE: f32 = 0.0001;
+
+/// A track with its keyframes converted to the single format they are written in
+struct TrackLayout {
+    isotropic: bool,
+    format:    Chr0Format,
+    /// Empty when the track does not exist, one keyframe when isotropic, otherwise x, y and z
+    keyframes: Vec<Keyframe>,
+}
+
+impl TrackLayout {
+    /// Selects the smallest format that stores every keyframe of the track, see `Chr0::compile`
+    fn new(holder: &KeyframeHolder, allow_linear: bool, num_frames: u16) -> TrackLayout {
+        let keyframes: Vec<Keyframe> = holder.keyframes().into_iter().map(|x| x.fixed_if_constant(num_frames)).collect();
+        let linear = allow_linear && keyframes.iter().filter_map(|x| x.format()).all(|x| x.is_linear());
+        let formats: &[Chr0Format] = if linear {
+            &[Chr0Format::Linear1, Chr0Format::Linear2, Chr0Format::Linear4]
+        } else {
+            &[Chr0Format::Interpolated4, Chr0Format::Interpolated6, Chr0Format::Interpolated12]
+        };
+
+        let isotropic = matches!(holder, KeyframeHolder::Isotropic (_));
+        if keyframes.iter().all(|x| x.format().is_none()) {
+            return TrackLayout { isotropic, format: Chr0Format::None, keyframes };
+        }
+        for format in formats {
+            let encoded: Option<Vec<Keyframe>> = keyframes.iter().map(|x| x.encode(format, num_frames)).collect();
+            if let Some(keyframes) = encoded {
+                return TrackLayout { isotropic, format: format.clone(), keyframes };
+            }
+        }
+        unreachable!("Interpolated12 and Linear4 store any keyframe")
+    }
+}
+
 fn keyframe_holder(child_data: FancySlice, data_offset: &mut usize, exists: bool, isotropic: bool, fixed_x: bool, fixed_y: bool, fixed_z: bool, format: Chr0Format, num_frames: u16) -> KeyframeHolder {
     if !exists {
         KeyframeHolder::None
@@ -219,7 +466,8 @@ fn keyframe_holder(child_data: FancySlice, data_offset: &mut usize, exists: bool
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub enum KeyframeHolder {
     Isotropic (Keyframe),
     Individual { x: Keyframe, y: Keyframe, z: Keyframe },
@@ -227,6 +475,15 @@ pub enum KeyframeHolder {
 }
 
 impl KeyframeHolder {
+    /// Returns the keyframe of an isotropic track or the x, y and z keyframes of an individual track
+    pub fn keyframes(&self) -> Vec<&Keyframe> {
+        match self {
+            KeyframeHolder::Isotropic (keyframe)    => vec!(keyframe),
+            KeyframeHolder::Individual { x, y, z } => vec!(x, y, z),
+            KeyframeHolder::None                   => vec!(),
+        }
+    }
+
     pub fn get_value(&self, loop_value: bool, frame: i32, default: f32) -> Vector3<f32> {
         match self {
             &KeyframeHolder::Isotropic (ref keyframe) => {
@@ -241,7 +498,8 @@ impl KeyframeHolder {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub enum Keyframe {
     Fixed (f32),
     Interpolated4 (Interpolated4Header),
@@ -253,6 +511,101 @@ pub enum Keyframe {
 }
 
 impl Keyframe {
+    /// The format the keyframe is stored in, None for fixed values which are stored in the child header
+    fn format(&self) -> Option<Chr0Format> {
+        match self {
+            Keyframe::Fixed (_)          => None,
+            Keyframe::Interpolated4 (_)  => Some(Chr0Format::Interpolated4),
+            Keyframe::Interpolated6 (_)  => Some(Chr0Format::Interpolated6),
+            Keyframe::Interpolated12 (_) => Some(Chr0Format::Interpolated12),
+            Keyframe::Linear1 (_)        => Some(Chr0Format::Linear1),
+            Keyframe::Linear2 (_)        => Some(Chr0Format::Linear2),
+            Keyframe::Linear4 (_)        => Some(Chr0Format::Linear4),
+        }
+    }
+
+    /// Returns a fixed keyframe when the keyframe has the same value on every frame, otherwise the keyframe unchanged
+    fn fixed_if_constant(&self, num_frames: u16) -> Keyframe {
+        let values = self.frame_values(num_frames);
+        match values.first() {
+            Some (first) if self.format().is_some() && values.iter().all(|x| x == first) => Keyframe::Fixed (*first),
+            _ => self.clone(),
+        }
+    }
+
+    /// The value on each frame of the animation
+    fn frame_values(&self, num_frames: u16) -> Vec<f32> {
+        (0..num_frames as i32).map(|frame| self.get_value(false, frame)).collect()
+    }
+
+    /// The keyframe as Interpolated12 entries.
+    /// Linear keyframes have an entry on every frame, consecutive entries are interpolated linearly.
+    fn interpolated_entries(&self, num_frames: u16) -> Vec<Interpolated12Entry> {
+        match self {
+            Keyframe::Interpolated4 (header) => header.children.iter().map(|child| Interpolated12Entry {
+                frame_index: child.frame_index() as f32,
+                value:       header.base + header.step * child.step() as f32,
+                tangent:     child.tangent(),
+            }).collect(),
+            Keyframe::Interpolated6 (header) => header.children.iter().map(|child| Interpolated12Entry {
+                frame_index: child.frame_index() as f32,
+                value:       header.base + header.step * child.step as f32,
+                tangent:     child.tangent(),
+            }).collect(),
+            Keyframe::Interpolated12 (header) => header.children.clone(),
+            _ => self.frame_values(num_frames).into_iter().enumerate().map(|(frame, value)| Interpolated12Entry {
+                frame_index: frame as f32,
+                value,
+                tangent:     0.0,
+            }).collect(),
+        }
+    }
+
+    /// Stores the keyframe in `format`, None if the format can not store it within `QUANTIZE_TOLERANCE`.
+    /// Fixed keyframes and keyframes already in the format are returned unchanged.
+    fn encode(&self, format: &Chr0Format, num_frames: u16) -> Option<Keyframe> {
+        if self.format().map(|x| &x == format).unwrap_or(true) {
+            return Some(self.clone());
+        }
+        let frame_scale = if num_frames > 1 { 1.0 / (num_frames - 1) as f32 } else { 0.0 };
+        match format {
+            Chr0Format::Interpolated4 => {
+                let entries = self.interpolated_entries(num_frames);
+                let (base, step, steps) = quantize(entries.iter().map(|x| x.value), 0xFFF)?;
+                let children = entries.iter().zip(steps).map(|(entry, value_step)| {
+                    let frame = whole_frame(entry.frame_index, 0xFF)?;
+                    let tangent = quantize_tangent(entry.tangent, 32.0, -0x800, 0x7FF)?;
+                    Some(Interpolated4Entry { data: frame << 24 | value_step << 12 | (tangent as u32 & 0xFFF) })
+                }).collect::<Option<Vec<_>>>()?;
+                Some(Keyframe::Interpolated4 (Interpolated4Header { entries: children.len() as u16, unk: 0, frame_scale, step, base, children }))
+            }
+            Chr0Format::Interpolated6 => {
+                let entries = self.interpolated_entries(num_frames);
+                let (base, step, steps) = quantize(entries.iter().map(|x| x.value), 0xFFFF)?;
+                let children = entries.iter().zip(steps).map(|(entry, value_step)| {
+                    let frame = whole_frame(entry.frame_index, 0x7FF)?;
+                    let tangent = quantize_tangent(entry.tangent, 256.0, i16::MIN as i32, i16::MAX as i32)?;
+                    Some(Interpolated6Entry { frame_index: (frame << 5) as u16, step: value_step as u16, tangent: tangent as i16 })
+                }).collect::<Option<Vec<_>>>()?;
+                Some(Keyframe::Interpolated6 (Interpolated6Header { num_frames: children.len() as u16, unk: 0, frame_scale, step, base, children }))
+            }
+            Chr0Format::Interpolated12 => {
+                let children = self.interpolated_entries(num_frames);
+                Some(Keyframe::Interpolated12 (Interpolated12Header { num_frames: children.len() as u16, unk: 0, frame_scale, children }))
+            }
+            Chr0Format::Linear1 => {
+                let (base, step, steps) = quantize(self.frame_values(num_frames).into_iter(), 0xFF)?;
+                Some(Keyframe::Linear1 (Linear1Header { step, base, children_steps: steps.into_iter().map(|x| x as u8).collect() }))
+            }
+            Chr0Format::Linear2 => {
+                let (base, step, steps) = quantize(self.frame_values(num_frames).into_iter(), 0xFFFF)?;
+                Some(Keyframe::Linear2 (Linear2Header { step, base, children_steps: steps.into_iter().map(|x| x as u16).collect() }))
+            }
+            Chr0Format::Linear4 => Some(Keyframe::Linear4 (self.frame_values(num_frames))),
+            Chr0Format::None => None,
+        }
+    }
+
     pub fn get_value(&self, loop_value: bool, frame: i32) -> f32 {
         match self {
             &Keyframe::Fixed(value) => {
@@ -400,7 +753,8 @@ impl Keyframe {
 }
 
 const INTERPOLATED_4_HEADER_SIZE: usize = 0x10;
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Interpolated4Header {
     pub entries: u16,
     pub unk: u16,
@@ -411,7 +765,8 @@ pub struct Interpolated4Header {
 }
 
 const INTERPOLATED_4_ENTRY_SIZE: usize = 0x4;
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Interpolated4Entry {
     pub data: u32,
 }
@@ -432,7 +787,8 @@ impl Interpolated4Entry {
 }
 
 const INTERPOLATED_6_HEADER_SIZE: usize = 0x10;
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Interpolated6Header {
     pub num_frames: u16,
     pub unk: u16,
@@ -443,7 +799,8 @@ pub struct Interpolated6Header {
 }
 
 const INTERPOLATED_6_ENTRY_SIZE: usize = 0x6;
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Interpolated6Entry {
     frame_index: u16,
     pub step: u16,
@@ -456,7 +813,8 @@ impl Interpolated6Entry {
 }
 
 const INTERPOLATED_12_HEADER_SIZE: usize = 0x8;
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Interpolated12Header {
     pub num_frames: u16,
     pub unk: u16,
@@ -465,7 +823,8 @@ pub struct Interpolated12Header {
 }
 
 const INTERPOLATED_12_ENTRY_SIZE: usize = 0xc;
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Interpolated12Entry {
     pub frame_index: f32,
     pub value: f32,
@@ -473,6 +832,7 @@ pub struct Interpolated12Entry {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct InterpolatedNEntry {
     pub frame_index: i32,
     pub value: f32,
@@ -480,7 +840,8 @@ pub struct InterpolatedNEntry {
 }
 
 const LINEAR_1_HEADER_SIZE: usize = 0x8;
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Linear1Header {
     pub step: f32,
     pub base: f32,
@@ -488,7 +849,8 @@ pub struct Linear1Header {
 }
 
 const LINEAR_2_HEADER_SIZE: usize = 0x8;
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Linear2Header {
     pub step: f32,
     pub base: f32,
@@ -571,3 +933,130 @@ fn keyframe(data: FancySlice, format: &Chr0Format, num_frames: u16) -> Keyframe
         &Chr0Format::None => panic!("this function should not be called with a format of None"),
     }
 }
+
+fn write_keyframe(output: &mut Vec<u8>, keyframe: &Keyframe) {
+    match keyframe {
+        Keyframe::Fixed (_) => panic!("fixed keyframes are stored in the child header"),
+        Keyframe::Interpolated4 (header) => {
+            output.extend(&u16::to_be_bytes(header.children.len() as u16));
+            output.extend(&u16::to_be_bytes(header.unk));
+            output.extend(&f32::to_be_bytes(header.frame_scale));
+            output.extend(&f32::to_be_bytes(header.step));
+            output.extend(&f32::to_be_bytes(header.base));
+            for child in &header.children {
+                output.extend(&u32::to_be_bytes(child.data));
+            }
+        }
+        Keyframe::Interpolated6 (header) => {
+            output.extend(&u16::to_be_bytes(header.children.len() as u16));
+            output.extend(&u16::to_be_bytes(header.unk));
+            output.extend(&f32::to_be_bytes(header.frame_scale));
+            output.extend(&f32::to_be_bytes(header.step));
+            output.extend(&f32::to_be_bytes(header.base));
+            for child in &header.children {
+                output.extend(&u16::to_be_bytes(child.frame_index));
+                output.extend(&u16::to_be_bytes(child.step));
+                output.extend(&i16::to_be_bytes(child.tangent));
+            }
+        }
+        Keyframe::Interpolated12 (header) => {
+            output.extend(&u16::to_be_bytes(header.children.len() as u16));
+            output.extend(&u16::to_be_bytes(header.unk));
+            output.extend(&f32::to_be_bytes(header.frame_scale));
+            for child in &header.children {
+                output.extend(&f32::to_be_bytes(child.frame_index));
+                output.extend(&f32::to_be_bytes(child.value));
+                output.extend(&f32::to_be_bytes(child.tangent));
+            }
+        }
+        Keyframe::Linear1 (header) => {
+            output.extend(&f32::to_be_bytes(header.step));
+            output.extend(&f32::to_be_bytes(header.base));
+            output.extend(&header.children_steps);
+        }
+        Keyframe::Linear2 (header) => {
+            output.extend(&f32::to_be_bytes(header.step));
+            output.extend(&f32::to_be_bytes(header.base));
+            for step in &header.children_steps {
+                output.extend(&u16::to_be_bytes(*step));
+            }
+        }
+        Keyframe::Linear4 (values) => {
+            for value in values {
+                output.extend(&f32::to_be_bytes(*value));
+            }
+        }
+    }
+}
+
+/// Stores each value as `base + step * n` where n is at most `max_steps`.
+/// Returns the base, step and n of each value, None if a stored value differs by more than `QUANTIZE_TOLERANCE`.
+fn quantize(values: impl Iterator<Item=f32> + Clone, max_steps: u32) -> Option<(f32, f32, Vec<u32>)> {
+    let base = values.clone().fold(f32::INFINITY, f32::min);
+    let max = values.clone().fold(f32::NEG_INFINITY, f32::max);
+    let step = if max > base { (max - base) / max_steps as f32 } else { 0.0 };
+    let steps = values.map(|value| {
+        let n = if step == 0.0 { 0 } else { (((value - base) / step).round() as u32).min(max_steps) };
+        if (base + step * n as f32 - value).abs() <= QUANTIZE_TOLERANCE { Some(n) } else { None }
+    }).collect::<Option<Vec<_>>>()?;
+    Some((base, step, steps))
+}
+
+/// Returns the frame index as an integer, None if it has a fraction or is larger than `max`
+fn whole_frame(frame_index: f32, max: u32) -> Option<u32> {
+    if frame_index.fract() == 0.0 && frame_index >= 0.0 && frame_index <= max as f32 {
+        Some(frame_index as u32)
+    } else {
+        None
+    }
+}
+
+/// Returns the tangent multiplied by `scale`, None if it does not fit between `min` and `max` or is not stored within `QUANTIZE_TOLERANCE`
+fn quantize_tangent(tangent: f32, scale: f32, min: i32, max: i32) -> Option<i32> {
+    let value = (tangent * scale).round();
+    if value >= min as f32 && value <= max as f32 && (value / scale - tangent).abs() <= QUANTIZE_TOLERANCE {
+        Some(value as i32)
+    } else {
+        None
+    }
+}
+
+fn align4(output: &mut Vec<u8>) {
+    output.resize(output.len() + (4 - output.len() % 4) % 4, 0x00);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bone_translations() {
+        let chr0 = Chr0 {
+            name:             String::from("AttackDash"),
+            size:             0,
+            version:          0,
+            bres_offset:      0,
+            orig_path_offset: 0,
+            num_frames:       5,
+            num_children:     1,
+            loop_value:       false,
+            scaling_rule:     0,
+            children:         vec!(Chr0Child {
+                name:        String::from("TransN"),
+                scale:       KeyframeHolder::None,
+                rot:         KeyframeHolder::None,
+                translation: KeyframeHolder::Individual {
+                    x: Keyframe::Fixed (0.0),
+                    y: Keyframe::Fixed (0.0),
+                    z: Keyframe::Linear4 (vec!(0.0, 1.0, 2.0, 3.0, 4.0)),
+                },
+                code: Chr0ChildCode { value: 0 },
+            }),
+        };
+
+        let translations = chr0.bone_translations("TransN").unwrap();
+        let z: Vec<f32> = translations.iter().map(|x| x.z).collect();
+        assert_eq!(z, vec!(0.0, 1.0, 2.0, 3.0, 4.0));
+        assert!(chr0.bone_translations("HipN").is_none());
+    }
+}
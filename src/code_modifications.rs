@@ -0,0 +1,168 @@
+use std::ops::Range;
+
+use crate::address::RamAddress;
+use crate::arc::{Arc, ArcChildData};
+use crate::sakurai::SectionData;
+use crate::script::Script;
+use crate::wii_memory::MemoryView;
+use crate::wiird::{self, WiiRDBlock, CodePath};
+
+/// Identifies a script in the common Fighter.pac
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum ScriptRef {
+    CommonEntryAction (usize),
+    CommonExitAction (usize),
+    CommonFragment (usize),
+    /// A script section, identified by its name
+    CommonSection (String),
+}
+
+/// An event of a common Fighter.pac script that a WiiRD code writes to.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct ModifiedEvent {
+    pub script: ScriptRef,
+    /// Index into `Script::events`
+    pub event: usize,
+    /// The code in the codeset that writes to the event or its arguments
+    pub code: CodePath,
+}
+
+/// Finds the events of the common Fighter.pac scripts that the codes write to.
+///
+/// `common_fighter_file` is the data `common_fighter` was parsed from and `ram_base` is the address it is loaded at.
+/// Write addresses are resolved by `wiird::write_ranges_with_memory`, so writes whose address is only known at runtime are not included.
+/// Writes inside if statements that can never pass when run against `memory` are not included either.
+/// Only events and arguments stored in the file are checked, scripts injected into memory by codes are not.
+pub fn common_fighter_modified_events(common_fighter: &Arc, common_fighter_file: &[u8], ram_base: RamAddress, codes: &WiiRDBlock, memory: &dyn MemoryView) -> Vec<ModifiedEvent> {
+    let writes = wiird::write_ranges_with_memory(codes, memory);
+    let mut modified = vec!();
+
+    for child in &common_fighter.children {
+        if let ArcChildData::Sakurai (sakurai) = &child.data {
+            let raw_data = child.raw_data(common_fighter_file);
+            let parent_data = &raw_data[SAKURAI_HEADER_SIZE.min(raw_data.len())..];
+            let parent_data_ram = ram_base.into_inner() + (child.data_range.start + SAKURAI_HEADER_SIZE) as u32;
+
+            let mut scripts: Vec<(ScriptRef, &Script)> = vec!();
+            for section in &sakurai.sections {
+                match &section.data {
+                    SectionData::FighterDataCommon (data) => {
+                        scripts.extend(data.entry_actions.iter().enumerate().map(|(i, x)| (ScriptRef::CommonEntryAction (i), x)));
+                        scripts.extend(data.exit_actions.iter().enumerate().map(|(i, x)| (ScriptRef::CommonExitAction (i), x)));
+                    }
+                    SectionData::Script (section_script) => scripts.push((ScriptRef::CommonSection (section_script.name.clone()), &section_script.script)),
+                    _ => { }
+                }
+            }
+            scripts.extend(sakurai.fragment_scripts.iter().enumerate().map(|(i, x)| (ScriptRef::CommonFragment (i), x)));
+
+            for (script_ref, script) in scripts {
+                modified.extend(modified_events(script_ref, script.offset, parent_data, parent_data_ram, &writes));
+            }
+        }
+    }
+
+    modified
+}
+
+/// Returns an entry for every pair of event and write where the write overlaps the event or its arguments.
+/// `parent_data_ram` is the address `parent_data` is loaded at.
+fn modified_events(script: ScriptRef, offset: i32, parent_data: &[u8], parent_data_ram: u32, writes: &[(CodePath, Range<u32>)]) -> Vec<ModifiedEvent> {
+    let mut modified = vec!();
+    for (event, ranges) in event_ranges(parent_data, offset).into_iter().enumerate() {
+        for (code, write) in writes {
+            let overlaps = ranges.iter().any(|range| {
+                let start = parent_data_ram + range.start as u32;
+                let end = parent_data_ram + range.end as u32;
+                write.start < end && start < write.end
+            });
+            if overlaps {
+                modified.push(ModifiedEvent { script: script.clone(), event, code: code.clone() });
+            }
+        }
+    }
+    modified
+}
+
+/// Returns the byte ranges of each event and its arguments, for the script at `offset` in `parent_data`.
+/// Events are numbered the same as `Script::events`, so PSA free space markers are skipped.
+fn event_ranges(parent_data: &[u8], offset: i32) -> Vec<Vec<Range<usize>>> {
+    let mut events = vec!();
+    if offset <= 0 || offset as usize >= parent_data.len() {
+        return events;
+    }
+    let read_u32 = |offset: usize| u32::from_be_bytes([parent_data[offset], parent_data[offset + 1], parent_data[offset + 2], parent_data[offset + 3]]);
+
+    let mut event_offset = offset as usize;
+    while event_offset + EVENT_SIZE <= parent_data.len() {
+        let namespace     = parent_data[event_offset];
+        let code          = parent_data[event_offset + 1];
+        let num_arguments = parent_data[event_offset + 2] as usize;
+        let raw_id        = read_u32(event_offset);
+
+        if code == 0 && namespace == 0 { // end of script
+            break;
+        }
+
+        if raw_id != FREE_SPACE && raw_id != END_OF_SPACE {
+            let mut ranges = vec!();
+            ranges.push(event_offset .. event_offset + EVENT_SIZE);
+            let argument_offset = read_u32(event_offset + 4) as usize;
+            let argument_end = argument_offset + num_arguments * ARGUMENT_SIZE;
+            if num_arguments > 0 && argument_end <= parent_data.len() {
+                ranges.push(argument_offset .. argument_end);
+            }
+            events.push(ranges);
+        }
+        event_offset += EVENT_SIZE;
+    }
+    events
+}
+
+const SAKURAI_HEADER_SIZE: usize = 0x20;
+const EVENT_SIZE: usize = 0x8;
+const ARGUMENT_SIZE: usize = 0x8;
+/// PSA fills empty, usable space with this value
+const FREE_SPACE: u32 = 0xFADEF00D;
+/// PSA marks the end of usable space with this value
+const END_OF_SPACE: u32 = 0xFADE0D8A;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_inside_argument() {
+        let mut parent_data = vec!(0; 0x8);
+        // script at 0x8 with two events, the second events argument is at 0x28
+        parent_data.extend(&[0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        parent_data.extend(&[0x06, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x28]);
+        parent_data.extend(&[0; 0x10]);
+        parent_data.extend(&[0, 0, 0, 0, 0, 0, 0, 5]);
+        let parent_data_ram = 0x80F9FC20;
+
+        let codeset: Vec<u8> = vec!(
+            0x04, 0xF9, 0xFC, 0x4C, 0x00, 0x00, 0x00, 0x0C, // 32 bit write to the arguments value
+            0x04, 0xF9, 0xFD, 0x00, 0x00, 0x00, 0x00, 0x01, // 32 bit write outside of the script
+        );
+        let writes = wiird::write_ranges(&wiird::wiird_codes(&codeset));
+        assert_eq!(writes.len(), 2);
+
+        let modified = modified_events(ScriptRef::CommonFragment (0), 0x8, &parent_data, parent_data_ram, &writes);
+        assert_eq!(modified, vec!(
+            ModifiedEvent { script: ScriptRef::CommonFragment (0), event: 1, code: CodePath (vec!(0)) },
+        ));
+    }
+
+    #[test]
+    fn free_space_is_not_numbered() {
+        let mut parent_data = vec!(0; 0x8);
+        parent_data.extend(&FREE_SPACE.to_be_bytes());
+        parent_data.extend(&[0; 4]);
+        parent_data.extend(&[0x06, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        parent_data.extend(&[0; 8]);
+        assert_eq!(event_ranges(&parent_data, 0x8), vec!(vec!(0x10..0x18)));
+    }
+}
@@ -0,0 +1,154 @@
+//! Decompression of the LZ77 compressed files used by brawl e.g. the .pcs costume files.
+
+use std::fmt;
+
+/// Errors from decompressing malformed data
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecompressError {
+    /// The first byte does not identify a supported compression format
+    UnknownFormat (u8),
+    /// The data ended before the header or the stated decompressed size was reached
+    UnexpectedEnd,
+    /// A back reference points before the start of the decompressed data
+    InvalidReference { offset: usize, distance: usize },
+}
+
+impl fmt::Display for DecompressError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecompressError::UnknownFormat (tag) =>
+                write!(f, "Unknown compression format 0x{:02x}, only LZ77 (0x10) and extended LZ77 (0x11) are supported", tag),
+            DecompressError::UnexpectedEnd =>
+                write!(f, "The compressed data ended before the decompressed size was reached"),
+            DecompressError::InvalidReference { offset, distance } =>
+                write!(f, "A back reference at decompressed offset 0x{:x} points 0x{:x} bytes back, before the start of the data", offset, distance),
+        }
+    }
+}
+
+impl std::error::Error for DecompressError { }
+
+const LZ77: u8 = 0x10;
+const LZ77_EXTENDED: u8 = 0x11;
+
+/// Decompresses LZ77 (0x10) and extended LZ77 (0x11) data, as stored in .pcs files.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, DecompressError> {
+    let tag = *data.first().ok_or(DecompressError::UnexpectedEnd)?;
+    if tag != LZ77 && tag != LZ77_EXTENDED {
+        return Err(DecompressError::UnknownFormat (tag));
+    }
+    let header = data.get(..4).ok_or(DecompressError::UnexpectedEnd)?;
+    let mut size = u32::from_le_bytes([header[1], header[2], header[3], 0]) as usize;
+    let mut offset = 4;
+    // sizes that do not fit in 24 bits are stored in the following 4 bytes instead
+    if size == 0 {
+        let size_bytes = data.get(4..8).ok_or(DecompressError::UnexpectedEnd)?;
+        size = u32::from_le_bytes([size_bytes[0], size_bytes[1], size_bytes[2], size_bytes[3]]) as usize;
+        offset = 8;
+    }
+
+    let mut next = || -> Result<usize, DecompressError> {
+        let byte = *data.get(offset).ok_or(DecompressError::UnexpectedEnd)?;
+        offset += 1;
+        Ok(byte as usize)
+    };
+
+    let mut output = Vec::with_capacity(size);
+    while output.len() < size {
+        // each bit of the flags, starting from the most significant, says whether the next block is a back reference or a literal byte
+        let flags = next()?;
+        for bit in (0..8).rev() {
+            if output.len() >= size {
+                break;
+            }
+            if flags & (1 << bit) == 0 {
+                output.push(next()? as u8);
+                continue;
+            }
+
+            let b0 = next()?;
+            let (length, distance) = if tag == LZ77 {
+                let b1 = next()?;
+                ((b0 >> 4) + 3, ((b0 & 0xF) << 8 | b1) + 1)
+            } else {
+                match b0 >> 4 {
+                    0 => {
+                        let b1 = next()?;
+                        let b2 = next()?;
+                        (((b0 & 0xF) << 4 | b1 >> 4) + 0x11, ((b1 & 0xF) << 8 | b2) + 1)
+                    }
+                    1 => {
+                        let b1 = next()?;
+                        let b2 = next()?;
+                        let b3 = next()?;
+                        (((b0 & 0xF) << 12 | b1 << 4 | b2 >> 4) + 0x111, ((b2 & 0xF) << 8 | b3) + 1)
+                    }
+                    _ => {
+                        let b1 = next()?;
+                        ((b0 >> 4) + 1, ((b0 & 0xF) << 8 | b1) + 1)
+                    }
+                }
+            };
+
+            if distance > output.len() {
+                return Err(DecompressError::InvalidReference { offset: output.len(), distance });
+            }
+            // the reference can overlap the bytes it writes, so copy one byte at a time
+            for _ in 0..length.min(size - output.len()) {
+                output.push(output[output.len() - distance]);
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Stores `data` as LZ77 containing only literal bytes, for creating .pcs files in tests
+#[cfg(test)]
+pub(crate) fn lz77_literals(data: &[u8]) -> Vec<u8> {
+    let mut output = vec!(LZ77);
+    output.extend(&(data.len() as u32).to_le_bytes()[..3]);
+    for chunk in data.chunks(8) {
+        output.push(0);
+        output.extend(chunk);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lz77_back_references() {
+        let data = [
+            LZ77, 0x0C, 0x00, 0x00,
+            // literals "ab", then copy 5 bytes from 2 back, then literal "c", then copy 4 bytes from 6 back
+            0b0010_1000, b'a', b'b', 0x20, 0x01, b'c', 0x10, 0x05,
+        ];
+        assert_eq!(decompress(&data).unwrap(), b"abababacabab");
+    }
+
+    #[test]
+    fn lz77_extended_back_references() {
+        let data = [
+            LZ77_EXTENDED, 0x18, 0x00, 0x00,
+            // literal "x", then copy 0x17 bytes from 1 back using the 3 byte form
+            0b0100_0000, b'x', 0x00, 0x60, 0x00,
+        ];
+        assert_eq!(decompress(&data).unwrap(), vec!(b'x'; 0x18));
+    }
+
+    #[test]
+    fn literals_round_trip() {
+        let data: Vec<u8> = (0..20).collect();
+        assert_eq!(decompress(&lz77_literals(&data)).unwrap(), data);
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(decompress(&[]), Err(DecompressError::UnexpectedEnd));
+        assert_eq!(decompress(b"ARC\0"), Err(DecompressError::UnknownFormat (b'A')));
+        assert_eq!(decompress(&[LZ77, 0x04, 0x00, 0x00, 0x00, b'a']), Err(DecompressError::UnexpectedEnd));
+        assert_eq!(decompress(&[LZ77, 0x04, 0x00, 0x00, 0x80, 0x00, 0x00]), Err(DecompressError::InvalidReference { offset: 0, distance: 1 }));
+    }
+}
@@ -0,0 +1,724 @@
+//! Hashes of the parsed content of fighters, for detecting which fighters changed between two loads.
+//!
+//! Only data that affects gameplay is hashed: the scripts, action overrides, attributes, hurtboxes, ledge grab boxes, action flags and subaction flags of the moveset.
+//! Where the files were loaded from, their timestamps and the version of brawllib_rs are not hashed,
+//! so the same moveset loaded from different paths or on different machines has the same hash.
+//!
+//! The content is hashed in a canonical form:
+//! *   Values are hashed in declaration order, names of fields and variants are not hashed.
+//! *   Floats are hashed by their bit pattern, with -0.0 hashed as 0.0 and every NaN hashed as the same NaN.
+//! *   Entries of maps are hashed individually and combined in sorted order, so the iteration order of a `HashMap` does not matter.
+//! *   Where scripts are stored is not hashed, so moving scripts within the file does not change the hash:
+//!     *   The offsets of scripts and the offsets event arguments were read from are not hashed.
+//!     *   An offset argument pointing at a script is hashed as the label of that script.
+//!         Scripts of the tables are labelled in table order, the remaining scripts in the order they are first pointed at, and are hashed in that order.
+//!     *   An offset argument pointing within the script containing it is hashed relative to the start of that script.
+//!     *   An offset argument calling an external subroutine is hashed as the name of the subroutine.
+//!     *   Any other offset argument is hashed as is.
+//!
+//! Hashes are stable across patch versions of brawllib_rs on a best-effort basis.
+//! `CONTENT_HASH_VERSION` is bumped whenever the canonical form changes, it is hashed into every hash so hashes from different versions never compare equal.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use rayon::prelude::*;
+use serde::ser::{self, Serialize, Serializer};
+use sha1_smol::Sha1;
+
+use crate::fighter::Fighter;
+use crate::sakurai::SectionData;
+use crate::script::{Argument, Offset, Script};
+
+/// Bumped whenever the canonical form of the hashed content changes.
+pub const CONTENT_HASH_VERSION: u32 = 3;
+
+const TAG_NONE:    u8 = 0;
+const TAG_SOME:    u8 = 1;
+const TAG_UNIT:    u8 = 2;
+const TAG_BOOL:    u8 = 3;
+const TAG_INT:     u8 = 4;
+const TAG_UINT:    u8 = 5;
+const TAG_FLOAT:   u8 = 6;
+const TAG_CHAR:    u8 = 7;
+const TAG_STR:     u8 = 8;
+const TAG_BYTES:   u8 = 9;
+const TAG_SEQ:     u8 = 10;
+const TAG_END:     u8 = 11;
+const TAG_MAP:     u8 = 12;
+const TAG_VARIANT: u8 = 13;
+
+pub(crate) fn fighter_hash(fighter: &Fighter) -> u64 {
+    let mut hasher = ContentHasher::new();
+    hasher.write_fighter(fighter);
+    hasher.finish()
+}
+
+pub(crate) fn roster_hash(fighters: &[Fighter]) -> u64 {
+    // fighters are loaded in parallel so their order is not part of the content
+    let mut hashes: Vec<(&str, u64)> = fighters.par_iter().map(|x| (x.cased_name.as_str(), fighter_hash(x))).collect();
+    hashes.sort_unstable();
+
+    let mut hasher = ContentHasher::new();
+    hasher.write(&hashes);
+    hasher.finish()
+}
+
+/// Feeds values to SHA-1 in the canonical form described in the module docs.
+pub(crate) struct ContentHasher {
+    sha1: Sha1,
+}
+
+impl ContentHasher {
+    pub(crate) fn new() -> ContentHasher {
+        let mut hasher = ContentHasher { sha1: Sha1::new() };
+        hasher.sha1.update(&CONTENT_HASH_VERSION.to_le_bytes());
+        hasher
+    }
+
+    pub(crate) fn write<T: Serialize + ?Sized>(&mut self, value: &T) {
+        value.serialize(&mut *self).expect("The hashed types never fail to serialize");
+    }
+
+    /// The first 8 bytes of the digest
+    pub(crate) fn finish(&self) -> u64 {
+        let digest = self.digest();
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        u64::from_be_bytes(bytes)
+    }
+
+    fn digest(&self) -> [u8; 20] {
+        self.sha1.digest().bytes()
+    }
+
+    fn tag(&mut self, tag: u8) {
+        self.sha1.update(&[tag]);
+    }
+
+    fn len(&mut self, len: usize) {
+        self.sha1.update(&(len as u64).to_le_bytes());
+    }
+
+    fn write_fighter(&mut self, fighter: &Fighter) {
+        self.write(&fighter.cased_name);
+
+        let data = fighter.get_fighter_data();
+        let sakurai = fighter.get_fighter_sakurai();
+        let mut labels = ScriptLabels::default();
+        if let Some(data) = data {
+            let tables = [&data.entry_actions, &data.exit_actions, &data.subaction_main, &data.subaction_gfx, &data.subaction_sfx, &data.subaction_other];
+            for script in tables.iter().flat_map(|x| x.iter()) {
+                labels.label(script);
+            }
+            for action_override in data.entry_action_overrides.iter().chain(data.exit_action_overrides.iter()) {
+                labels.label(&action_override.script);
+            }
+        }
+        if let Some(sakurai) = sakurai {
+            for section in &sakurai.sections {
+                if let SectionData::Script (section_script) = &section.data {
+                    labels.label(&section_script.script);
+                }
+            }
+            labels.fragments = sakurai.fragment_scripts.iter().map(|x| (x.offset, x)).collect();
+            for external in &sakurai.external_subroutines {
+                for origin in &external.offsets {
+                    labels.external.insert(*origin, &external.name);
+                }
+            }
+        }
+
+        match data {
+            Some(data) => {
+                self.tag(TAG_SOME);
+                self.write(&data.attributes);
+                self.write(&data.action_flags);
+                self.write(&data.misc.hurt_boxes);
+                self.write(&data.misc.ledge_grab_boxes);
+                self.tag(TAG_SEQ);
+                for flags in &data.subaction_flags {
+                    self.write(&(flags.in_translation_time, flags.animation_flags, &flags.name));
+                }
+                self.tag(TAG_END);
+                for scripts in [&data.entry_actions, &data.exit_actions, &data.subaction_main, &data.subaction_gfx, &data.subaction_sfx, &data.subaction_other] {
+                    self.tag(TAG_SEQ);
+                    for script in scripts {
+                        self.write_script(script, &mut labels);
+                    }
+                    self.tag(TAG_END);
+                }
+                for overrides in [&data.entry_action_overrides, &data.exit_action_overrides] {
+                    self.tag(TAG_SEQ);
+                    for action_override in overrides {
+                        self.write(&action_override.action_id);
+                        self.write_script(&action_override.script, &mut labels);
+                    }
+                    self.tag(TAG_END);
+                }
+            }
+            None => self.tag(TAG_NONE),
+        }
+
+        match sakurai {
+            Some(sakurai) => {
+                self.tag(TAG_SOME);
+                for section in &sakurai.sections {
+                    if let SectionData::Script (section_script) = &section.data {
+                        self.write(&section_script.name);
+                        self.write_script(&section_script.script, &mut labels);
+                    }
+                }
+
+                // scripts are labelled while hashing, so hashing a script can append to `referenced`
+                self.tag(TAG_SEQ);
+                let mut i = 0;
+                while let Some(script) = labels.referenced.get(i).cloned() {
+                    self.write_script(script, &mut labels);
+                    i += 1;
+                }
+                self.tag(TAG_END);
+
+                // fragment scripts that nothing in the moveset points at, e.g. ones only called by the common Fighter.pac
+                self.tag(TAG_SEQ);
+                for script in &sakurai.fragment_scripts {
+                    if !labels.labels.contains_key(&script.offset) {
+                        self.write_script(script, &mut labels);
+                    }
+                }
+                self.tag(TAG_END);
+            }
+            None => self.tag(TAG_NONE),
+        }
+
+        match &fighter.follower {
+            Some(follower) => {
+                self.tag(TAG_SOME);
+                self.write(&follower.leader);
+                self.write_fighter(&follower.fighter);
+            }
+            None => self.tag(TAG_NONE),
+        }
+
+        match &fighter.transformation {
+            Some(transformation) => {
+                self.tag(TAG_SOME);
+                self.write_fighter(transformation);
+            }
+            None => self.tag(TAG_NONE),
+        }
+    }
+
+    fn write_script<'a>(&mut self, script: &'a Script, labels: &mut ScriptLabels<'a>) {
+        self.tag(TAG_SEQ);
+        for event in &script.events {
+            self.write(&(event.namespace, event.code, event.unk1));
+            self.tag(TAG_SEQ);
+            for argument in &event.arguments {
+                match argument {
+                    Argument::Offset (offset) => self.write(&HashedArgument::Offset (labels.target(script, offset))),
+                    argument                  => self.write(&HashedArgument::Argument (argument)),
+                }
+            }
+            self.tag(TAG_END);
+        }
+        self.tag(TAG_END);
+    }
+}
+
+/// Labels scripts independently of where they are stored in the file, refer to the module docs.
+#[derive(Default)]
+struct ScriptLabels<'a> {
+    /// Keyed by the offset of the script
+    labels: HashMap<i32, usize>,
+    /// Scripts labelled when first pointed at, in label order
+    referenced: Vec<&'a Script>,
+    /// Keyed by the offset of the script
+    fragments: HashMap<i32, &'a Script>,
+    /// The name of the external subroutine called by the argument at the offset
+    external: HashMap<i32, &'a str>,
+}
+
+impl<'a> ScriptLabels<'a> {
+    /// Labels a script of a table, scripts sharing an offset share a label
+    fn label(&mut self, script: &Script) {
+        let next = self.labels.len();
+        self.labels.entry(script.offset).or_insert(next);
+    }
+
+    /// The canonical target of an offset argument of `script`
+    fn target(&mut self, script: &Script, offset: &Offset) -> OffsetTarget<'a> {
+        if let Some(name) = self.external.get(&offset.origin) {
+            return OffsetTarget::External (name);
+        }
+        if let Some(label) = self.labels.get(&offset.offset) {
+            return OffsetTarget::Script (*label);
+        }
+        if let Some(fragment) = self.fragments.get(&offset.offset) {
+            let label = self.labels.len();
+            self.labels.insert(offset.offset, label);
+            self.referenced.push(fragment);
+            return OffsetTarget::Script (label);
+        }
+
+        // each event is 8 bytes followed by an 8 byte terminator
+        let script_end = script.offset + 8 * (script.events.len() as i32 + 1);
+        if script.offset <= offset.offset && offset.offset < script_end {
+            OffsetTarget::WithinScript (offset.offset - script.offset)
+        } else {
+            OffsetTarget::Unknown (offset.offset)
+        }
+    }
+}
+
+/// Every argument is hashed as one of these, so offset arguments can be hashed in their canonical form
+#[derive(Serialize)]
+enum HashedArgument<'a> {
+    Argument (&'a Argument),
+    Offset (OffsetTarget<'a>),
+}
+
+#[derive(Serialize)]
+enum OffsetTarget<'a> {
+    Script (usize),
+    WithinScript (i32),
+    External (&'a str),
+    Unknown (i32),
+}
+
+/// Hashing cannot fail, this only exists because `Serializer` requires an error type.
+#[derive(Debug)]
+pub(crate) struct ContentHashError (String);
+
+impl fmt::Display for ContentHashError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ContentHashError { }
+
+impl ser::Error for ContentHashError {
+    fn custom<T: fmt::Display>(msg: T) -> ContentHashError {
+        ContentHashError (msg.to_string())
+    }
+}
+
+impl<'a> Serializer for &'a mut ContentHasher {
+    type Ok = ();
+    type Error = ContentHashError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = MapHasher<'a>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, value: bool) -> Result<(), ContentHashError> {
+        self.tag(TAG_BOOL);
+        self.sha1.update(&[value as u8]);
+        Ok(())
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<(), ContentHashError> {
+        self.serialize_i64(value as i64)
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<(), ContentHashError> {
+        self.serialize_i64(value as i64)
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<(), ContentHashError> {
+        self.serialize_i64(value as i64)
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<(), ContentHashError> {
+        self.tag(TAG_INT);
+        self.sha1.update(&value.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<(), ContentHashError> {
+        self.serialize_u64(value as u64)
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<(), ContentHashError> {
+        self.serialize_u64(value as u64)
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<(), ContentHashError> {
+        self.serialize_u64(value as u64)
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<(), ContentHashError> {
+        self.tag(TAG_UINT);
+        self.sha1.update(&value.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<(), ContentHashError> {
+        // every f32 is exactly representable as an f64
+        self.serialize_f64(value as f64)
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<(), ContentHashError> {
+        let value = if value.is_nan() {
+            f64::NAN
+        } else if value == 0.0 {
+            0.0
+        } else {
+            value
+        };
+        self.tag(TAG_FLOAT);
+        self.sha1.update(&value.to_bits().to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, value: char) -> Result<(), ContentHashError> {
+        self.tag(TAG_CHAR);
+        self.sha1.update(&(value as u32).to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_str(self, value: &str) -> Result<(), ContentHashError> {
+        self.tag(TAG_STR);
+        self.len(value.len());
+        self.sha1.update(value.as_bytes());
+        Ok(())
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<(), ContentHashError> {
+        self.tag(TAG_BYTES);
+        self.len(value.len());
+        self.sha1.update(value);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), ContentHashError> {
+        self.tag(TAG_NONE);
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), ContentHashError> {
+        self.tag(TAG_SOME);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), ContentHashError> {
+        self.tag(TAG_UNIT);
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), ContentHashError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, variant_index: u32, _variant: &'static str) -> Result<(), ContentHashError> {
+        self.tag(TAG_VARIANT);
+        self.sha1.update(&variant_index.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<(), ContentHashError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, name: &'static str, variant_index: u32, variant: &'static str, value: &T) -> Result<(), ContentHashError> {
+        self.serialize_unit_variant(name, variant_index, variant)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self, ContentHashError> {
+        self.tag(TAG_SEQ);
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self, ContentHashError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Self, ContentHashError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, name: &'static str, variant_index: u32, variant: &'static str, len: usize) -> Result<Self, ContentHashError> {
+        self.serialize_unit_variant(name, variant_index, variant)?;
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapHasher<'a>, ContentHashError> {
+        Ok(MapHasher { parent: self, entry: None, entries: vec!() })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self, ContentHashError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_struct_variant(self, name: &'static str, variant_index: u32, variant: &'static str, len: usize) -> Result<Self, ContentHashError> {
+        self.serialize_tuple_variant(name, variant_index, variant, len)
+    }
+}
+
+impl ser::SerializeSeq for &mut ContentHasher {
+    type Ok = ();
+    type Error = ContentHashError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ContentHashError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ContentHashError> {
+        self.tag(TAG_END);
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut ContentHasher {
+    type Ok = ();
+    type Error = ContentHashError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ContentHashError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ContentHashError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut ContentHasher {
+    type Ok = ();
+    type Error = ContentHashError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ContentHashError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ContentHashError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut ContentHasher {
+    type Ok = ();
+    type Error = ContentHashError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ContentHashError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ContentHashError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeStruct for &mut ContentHasher {
+    type Ok = ();
+    type Error = ContentHashError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<(), ContentHashError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ContentHashError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for &mut ContentHasher {
+    type Ok = ();
+    type Error = ContentHashError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<(), ContentHashError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ContentHashError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+/// Hashes each entry separately, the digests of the entries are sorted before being hashed into the parent.
+pub(crate) struct MapHasher<'a> {
+    parent:  &'a mut ContentHasher,
+    entry:   Option<ContentHasher>,
+    entries: Vec<[u8; 20]>,
+}
+
+impl ser::SerializeMap for MapHasher<'_> {
+    type Ok = ();
+    type Error = ContentHashError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), ContentHashError> {
+        let mut entry = ContentHasher::new();
+        key.serialize(&mut entry)?;
+        self.entry = Some(entry);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ContentHashError> {
+        let mut entry = self.entry.take().expect("serialize_key is always called before serialize_value");
+        value.serialize(&mut entry)?;
+        self.entries.push(entry.digest());
+        Ok(())
+    }
+
+    fn end(mut self) -> Result<(), ContentHashError> {
+        self.entries.sort_unstable();
+        self.parent.tag(TAG_MAP);
+        self.parent.len(self.entries.len());
+        for entry in &self.entries {
+            self.parent.sha1.update(entry);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fighter::FighterLoadResult;
+    use crate::sakurai::fighter_data::{ActionOverride, AnimationFlags, ArcFighterData};
+    use crate::test_util::{ScriptBuilder, TestFighterBuilder, damage};
+
+    use std::collections::HashMap;
+
+    fn hash<T: Serialize + ?Sized>(value: &T) -> u64 {
+        let mut hasher = ContentHasher::new();
+        hasher.write(value);
+        hasher.finish()
+    }
+
+    fn fighter(name: &str, frames: f32) -> Fighter {
+        TestFighterBuilder::new(name)
+            .subaction("AttackS3S", |s| s
+                .sync_timer(frames)
+                .hitbox(damage(8).angle(361).bkb(30).kbg(100).size(4.5).bone(3))
+                .async_timer(12.0)
+                .event(0x06, 0x04, vec!())
+            )
+            .build()
+    }
+
+    fn fighter_data_mut(fighter: &mut Fighter) -> &mut ArcFighterData {
+        fighter.get_fighter_sakurai_mut().unwrap().sections.iter_mut().find_map(|x| match &mut x.data {
+            SectionData::FighterData (data) => Some(data),
+            _ => None,
+        }).unwrap()
+    }
+
+    /// Moves every script of the moveset by `delta` bytes, as if data was inserted before them
+    fn relocate(fighter: &mut Fighter, delta: i32) {
+        let data = fighter_data_mut(fighter);
+        let tables = vec!(&mut data.entry_actions, &mut data.exit_actions, &mut data.subaction_main, &mut data.subaction_gfx, &mut data.subaction_sfx, &mut data.subaction_other);
+        for script in tables.into_iter().flat_map(|x| x.iter_mut()) {
+            script.offset += delta;
+            for event in &mut script.events {
+                for argument in &mut event.arguments {
+                    if let Argument::Offset (offset) = argument {
+                        offset.offset += delta;
+                        offset.origin += delta;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Replaces the arguments of the first event of subaction 0 with an offset pointing at `target` bytes after the start of subaction `index`
+    fn point_at(fighter: &mut Fighter, index: usize, target: i32) {
+        let data = fighter_data_mut(fighter);
+        let offset = data.subaction_main[index].offset + target;
+        let origin = data.subaction_main[0].offset + 0x100;
+        data.subaction_main[0].events[0].arguments = vec!(Argument::Offset (Offset { offset, origin }));
+    }
+
+    fn calling_fighter() -> Fighter {
+        TestFighterBuilder::new("Mario")
+            .subaction("Caller", |s| s.event(0x00, 0x07, vec!(Argument::Value (0))).sync_timer(1.0))
+            .subaction("Callee", |s| s.hitbox(damage(8)))
+            .build()
+    }
+
+    #[test]
+    fn fighter_hash_ignores_relocation() {
+        let mut fighter = calling_fighter();
+        point_at(&mut fighter, 1, 0);
+        let hash = fighter.content_hash();
+
+        relocate(&mut fighter, 0x100);
+        assert_eq!(fighter.content_hash(), hash);
+
+        // the argument offset is not part of the content
+        fighter_data_mut(&mut fighter).subaction_main[0].events[0].arguments = vec!(Argument::Offset (Offset {
+            offset: fighter_data_mut(&mut fighter).subaction_main[1].offset,
+            origin: 0x1234,
+        }));
+        assert_eq!(fighter.content_hash(), hash);
+
+        // pointing at a different script is
+        point_at(&mut fighter, 0, 0);
+        assert_ne!(fighter.content_hash(), hash);
+
+        // pointing within the same script is hashed relative to the script
+        point_at(&mut fighter, 0, 8);
+        let within = fighter.content_hash();
+        relocate(&mut fighter, -0x80);
+        assert_eq!(fighter.content_hash(), within);
+    }
+
+    #[test]
+    fn fighter_hash_includes_overrides_and_subaction_flags() {
+        let hash = calling_fighter().content_hash();
+
+        let mut fighter = calling_fighter();
+        fighter_data_mut(&mut fighter).subaction_flags[0].animation_flags = AnimationFlags::NO_OUT_TRANSITION;
+        assert_ne!(fighter.content_hash(), hash);
+
+        let mut fighter = calling_fighter();
+        let script = ScriptBuilder::new().sync_timer(1.0).build();
+        fighter_data_mut(&mut fighter).entry_action_overrides.push(ActionOverride { action_id: 0x10, script });
+        assert_ne!(fighter.content_hash(), hash);
+    }
+
+    fn roster(fighters: Vec<Fighter>) -> FighterLoadResult {
+        FighterLoadResult { fighters, failures: vec!(), skipped: vec!(), common_fighter: None }
+    }
+
+    #[test]
+    fn fighter_hash_changes_with_content() {
+        assert_eq!(fighter("Mario", 5.0).content_hash(), fighter("Mario", 5.0).content_hash());
+        assert_ne!(fighter("Mario", 5.0).content_hash(), fighter("Mario", 5.5).content_hash());
+        assert_ne!(fighter("Mario", 5.0).content_hash(), fighter("Luigi", 5.0).content_hash());
+
+        let before = roster(vec!(fighter("Mario", 5.0), fighter("Luigi", 5.0)));
+        let reordered = roster(vec!(fighter("Luigi", 5.0), fighter("Mario", 5.0)));
+        let changed = roster(vec!(fighter("Mario", 5.0), fighter("Luigi", 5.5)));
+        assert_eq!(before.roster_hash(), reordered.roster_hash());
+        assert_ne!(before.roster_hash(), changed.roster_hash());
+    }
+
+    #[test]
+    fn canonical_form() {
+        assert_eq!(hash(&0.0_f32), hash(&-0.0_f32));
+        assert_eq!(hash(&f32::NAN), hash(&-f32::NAN));
+        assert_eq!(hash(&1.5_f32), hash(&1.5_f64));
+        assert_ne!(hash(&1.5_f32), hash(&1.25_f32));
+        assert_eq!(hash(&5_i32), hash(&5_i64));
+        assert_ne!(hash(&Argument::Value (5)), hash(&Argument::Bool (true)));
+
+        // split points of nested sequences are part of the hash
+        assert_ne!(hash(&vec!(vec!(1, 2), vec!(3))), hash(&vec!(vec!(1), vec!(2, 3))));
+        assert_ne!(hash(&("ab", "c")), hash(&("a", "bc")));
+
+        let mut forwards = HashMap::new();
+        let mut backwards = HashMap::with_capacity(100);
+        for i in 0..50 {
+            forwards.insert(i, i * 2);
+            backwards.insert(49 - i, (49 - i) * 2);
+        }
+        assert_eq!(hash(&forwards), hash(&backwards));
+        backwards.insert(0, 1);
+        assert_ne!(hash(&forwards), hash(&backwards));
+    }
+}
@@ -0,0 +1,580 @@
+//! Reads files from a Wii disc image, so `BrawlMod` can load brawl from an image instead of an extracted dump.
+//!
+//! Supports .iso images and .wbfs files containing a single game.
+//! Only images with an unencrypted data partition can be read, e.g. images created with the disc encryption disabled.
+//! The data partition of a retail image is encrypted and must be decrypted by another tool first.
+//!
+//! Files read from an image are recorded in provenance by their path on the disc e.g. `fighter/mario/FitMario.pac`,
+//! so `Provenance::matches_files` cannot check them and always reports fighters loaded from an image as stale.
+//!
+//! Every offset and length read from the image is checked against the size of the image or partition before anything is allocated for it,
+//! so a corrupted image fails with an error instead of running out of memory.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use failure::Error;
+use failure::bail;
+
+/// The magic number at 0x18 of every Wii disc header
+const WII_MAGIC: u32 = 0x5D1C9EA3;
+/// The offset of the table listing the partition tables
+const PARTITION_INFO_OFFSET: u64 = 0x40000;
+/// The partition type of the partition containing the game files
+const DATA_PARTITION: u32 = 0;
+
+/// Partition data is stored in clusters, each starting with hashes of the data that follows.
+/// Unless hashing is disabled by the disc header, then the data is stored contiguously.
+const CLUSTER_SIZE: u64 = 0x8000;
+const CLUSTER_HASH_SIZE: u64 = 0x400;
+const CLUSTER_DATA_SIZE: u64 = CLUSTER_SIZE - CLUSTER_HASH_SIZE;
+
+/// The number of 0x8000 byte sectors on a dual layer disc, the size of the sector table of a wbfs file
+const WII_SECTORS_PER_DISC: usize = 143432 * 2;
+
+/// A file in the data partition of a disc
+#[derive(Clone, Debug)]
+pub struct DiscFile {
+    /// The path of the file separated by `/` e.g. `fighter/mario/FitMario.pac`
+    pub path: String,
+    /// The offset of the file within the partition data
+    offset: u64,
+    pub size: u32,
+}
+
+/// An opened disc image, see the module documentation.
+pub struct Disc {
+    image: Image,
+    /// The offset on the disc of the data of the data partition
+    partition_data: u64,
+    /// The number of bytes of data readable from the data partition, excluding the hashes
+    partition_size: u64,
+    /// False when the hashes are disabled and the partition data is not split into clusters
+    hashed: bool,
+    files: Vec<DiscFile>,
+}
+
+impl Disc {
+    /// Opens the image and reads its file system table.
+    pub fn open(path: &Path) -> Result<Disc, Error> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) => bail!("Cannot read disc image {:?}: {}", path, err),
+        };
+        let wbfs = path.extension().map(|x| x.eq_ignore_ascii_case("wbfs")).unwrap_or(false);
+        let image = if wbfs { Image::wbfs(file)? } else { Image::iso(file)? };
+        Disc::new(image)
+    }
+
+    fn new(mut image: Image) -> Result<Disc, Error> {
+        let header = image.read(0, 0x80)?;
+        if be_u32(&header, 0x18) != WII_MAGIC {
+            bail!("Not a Wii disc image: the disc header is missing the Wii magic number");
+        }
+        // set when the hash verification is disabled, the same as dolphins VolumeWii the partition data then has no hashes
+        let hashed = header[0x60] == 0;
+        // set when the disc encryption is disabled
+        if header[0x61] == 0 {
+            bail!("Encrypted Wii disc images are not supported: the data partition must be decrypted by another tool first");
+        }
+
+        let partition = match data_partition(&mut image)? {
+            Some(partition) => partition,
+            None => bail!("The Wii disc image has no data partition"),
+        };
+        let partition_header = image.read(partition + 0x2B8, 8)?;
+        let partition_data = partition + be_u32(&partition_header, 0) as u64 * 4;
+        let stored_size = be_u32(&partition_header, 4) as u64 * 4;
+        if partition_data + stored_size > image.len() {
+            bail!("The Wii disc image is truncated: the data partition ends at 0x{:x} but the image is 0x{:x} bytes", partition_data + stored_size, image.len());
+        }
+        let partition_size = if hashed { stored_size / CLUSTER_SIZE * CLUSTER_DATA_SIZE } else { stored_size };
+
+        let mut disc = Disc { image, partition_data, partition_size, hashed, files: vec!() };
+        let partition_header = disc.read_partition(0, 0x430)?;
+        let fst_offset = be_u32(&partition_header, 0x424) as u64 * 4;
+        let fst_size = be_u32(&partition_header, 0x428) as u64 * 4;
+        let fst = disc.read_partition(fst_offset, fst_size as usize)?;
+        disc.files = fst_files(&fst)?;
+        Ok(disc)
+    }
+
+    /// Every file in the data partition, in the order of the file system table
+    pub fn files(&self) -> &[DiscFile] {
+        &self.files
+    }
+
+    /// Returns the contents of the file at `path`, the path is compared case insensitively
+    pub fn read_file(&mut self, path: &str) -> Result<Vec<u8>, Error> {
+        let file = match self.files.iter().find(|x| x.path.eq_ignore_ascii_case(path)) {
+            Some(file) => file.clone(),
+            None => bail!("The disc image does not contain {}", path),
+        };
+        self.read_partition(file.offset, file.size as usize)
+    }
+
+    /// Returns the path and contents of every file that `filter` returns true for
+    pub fn read_files<F>(&mut self, filter: F) -> Result<HashMap<String, Vec<u8>>, Error> where F: Fn(&str) -> bool {
+        let files: Vec<DiscFile> = self.files.iter().filter(|x| filter(&x.path)).cloned().collect();
+        let mut result = HashMap::new();
+        for file in files {
+            let data = self.read_partition(file.offset, file.size as usize)?;
+            result.insert(file.path, data);
+        }
+        Ok(result)
+    }
+
+    /// Reads from the partition data, skipping the hashes at the start of each cluster
+    fn read_partition(&mut self, offset: u64, len: usize) -> Result<Vec<u8>, Error> {
+        if offset.checked_add(len as u64).map(|end| end > self.partition_size).unwrap_or(true) {
+            bail!("Cannot read 0x{:x} bytes at 0x{:x} of the data partition, it is only 0x{:x} bytes", len, offset, self.partition_size);
+        }
+        if !self.hashed {
+            return self.image.read(self.partition_data + offset, len);
+        }
+        let mut data = Vec::with_capacity(len);
+        let mut offset = offset;
+        while data.len() < len {
+            let cluster = offset / CLUSTER_DATA_SIZE;
+            let within = offset % CLUSTER_DATA_SIZE;
+            let read_len = ((CLUSTER_DATA_SIZE - within) as usize).min(len - data.len());
+            let disc_offset = self.partition_data + cluster * CLUSTER_SIZE + CLUSTER_HASH_SIZE + within;
+            data.extend(self.image.read(disc_offset, read_len)?);
+            offset += read_len as u64;
+        }
+        Ok(data)
+    }
+}
+
+/// Returns the offset of the first data partition
+fn data_partition(image: &mut Image) -> Result<Option<u64>, Error> {
+    let info = image.read(PARTITION_INFO_OFFSET, 0x20)?;
+    for table in 0..4 {
+        let count = be_u32(&info, table * 8) as usize;
+        let table_offset = be_u32(&info, table * 8 + 4) as u64 * 4;
+        if count == 0 {
+            continue;
+        }
+        // checked by `Image::read` before the entries are allocated
+        let entries = image.read(table_offset, count * 8)?;
+        for entry in 0..count {
+            if be_u32(&entries, entry * 8 + 4) == DATA_PARTITION {
+                return Ok(Some(be_u32(&entries, entry * 8) as u64 * 4));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Parses the file system table, returning every file in it.
+///
+/// Each 12 byte entry is a flag set for directories, the offset of the name in the string table, then for files the offset / 4 and size of the file
+/// and for directories the index of the parent and the index of the entry after its last child.
+/// The first entry is the root directory, the string table follows the last entry.
+fn fst_files(fst: &[u8]) -> Result<Vec<DiscFile>, Error> {
+    if fst.len() < 12 {
+        bail!("The file system table of the disc image is truncated");
+    }
+    let count = be_u32(fst, 8) as usize;
+    let strings = count * 12;
+    if strings > fst.len() {
+        bail!("The file system table of the disc image is truncated");
+    }
+
+    let name = |entry: usize| -> Result<String, Error> {
+        let start = strings + (be_u32(fst, entry * 12) & 0xFFFFFF) as usize;
+        match fst.get(start..).and_then(|x| x.iter().position(|x| *x == 0).map(|end| &x[..end])) {
+            Some(name) => Ok(String::from_utf8_lossy(name).into_owned()),
+            None => bail!("The file system table of the disc image has a name outside of its string table"),
+        }
+    };
+
+    let mut files = vec!();
+    // the directories containing the current entry, the index of the entry after the directory and its path
+    let mut dirs: Vec<(usize, String)> = vec!((count, String::new()));
+    for entry in 1..count {
+        while dirs.last().map(|x| entry >= x.0).unwrap_or(false) {
+            dirs.pop();
+        }
+        let prefix = dirs.last().map(|x| x.1.clone()).unwrap_or_default();
+        let path = format!("{}{}", prefix, name(entry)?);
+        if fst[entry * 12] != 0 {
+            dirs.push((be_u32(fst, entry * 12 + 8) as usize, format!("{}/", path)));
+        } else {
+            files.push(DiscFile {
+                path,
+                offset: be_u32(fst, entry * 12 + 4) as u64 * 4,
+                size:   be_u32(fst, entry * 12 + 8),
+            });
+        }
+    }
+    Ok(files)
+}
+
+/// `len` is the size of the disc
+enum Image {
+    Iso { file: File, len: u64 },
+    /// The disc is split into sectors of `1 << sector_shift` bytes, `sectors` contains the index in the file of each disc sector, 0 when the sector is not stored.
+    Wbfs { file: File, len: u64, sector_shift: u8, sectors: Vec<u16> },
+}
+
+impl Image {
+    fn iso(file: File) -> Result<Image, Error> {
+        let len = file.metadata()?.len();
+        Ok(Image::Iso { file, len })
+    }
+
+    fn wbfs(mut file: File) -> Result<Image, Error> {
+        let mut header = [0; 0x10];
+        read_exact_at(&mut file, 0, &mut header)?;
+        if &header[..4] != b"WBFS" {
+            bail!("Not a wbfs file: the header is missing the WBFS magic number");
+        }
+        let hd_sector_shift = header[8];
+        let sector_shift = header[9];
+        if !(9..32).contains(&hd_sector_shift) {
+            bail!("Invalid wbfs file: the hard drive sector size 1 << {} is not supported", hd_sector_shift);
+        }
+        if !(15..32).contains(&sector_shift) {
+            bail!("Invalid wbfs file: the wbfs sector size 1 << {} is not supported", sector_shift);
+        }
+        if header[0xC] == 0 {
+            bail!("The wbfs file does not contain a disc");
+        }
+
+        // the disc info of the first disc follows the header, a copy of the disc header followed by the sector table
+        let table_offset = (1_u64 << hd_sector_shift) + 0x100;
+        let table_len = (WII_SECTORS_PER_DISC >> (sector_shift - 15)) * 2;
+        let file_len = file.metadata()?.len();
+        if table_offset + table_len as u64 > file_len {
+            bail!("The wbfs file is truncated: the sector table ends at 0x{:x} but the file is 0x{:x} bytes", table_offset + table_len as u64, file_len);
+        }
+        let mut table = vec!(0; table_len);
+        read_exact_at(&mut file, table_offset, &mut table)?;
+        let sectors: Vec<u16> = table.chunks(2).map(|x| u16::from_be_bytes([x[0], x[1]])).collect();
+        // sectors left out of the file are still part of the disc, they are read as zeroes
+        let len = (sectors.len() as u64) << sector_shift;
+        Ok(Image::Wbfs { file, len, sector_shift, sectors })
+    }
+
+    /// The size of the disc, for a wbfs file including the sectors that are not stored in the file
+    fn len(&self) -> u64 {
+        match self {
+            Image::Iso { len, .. } | Image::Wbfs { len, .. } => *len,
+        }
+    }
+
+    /// Reads `len` bytes from the disc starting at `offset`, sectors missing from a wbfs file are read as zeroes.
+    /// Fails without allocating when the range is not within the disc.
+    fn read(&mut self, offset: u64, len: usize) -> Result<Vec<u8>, Error> {
+        let disc_len = self.len();
+        if offset.checked_add(len as u64).map(|end| end > disc_len).unwrap_or(true) {
+            bail!("Cannot read 0x{:x} bytes at 0x{:x} of the disc image, it is only 0x{:x} bytes", len, offset, disc_len);
+        }
+        let mut data = vec!(0; len);
+        match self {
+            Image::Iso { file, .. } => read_exact_at(file, offset, &mut data)?,
+            Image::Wbfs { file, sector_shift, sectors, .. } => {
+                let sector_size = 1_u64 << *sector_shift;
+                let mut read = 0;
+                while read < len {
+                    let disc_offset = offset + read as u64;
+                    let within = disc_offset % sector_size;
+                    let read_len = ((sector_size - within) as usize).min(len - read);
+                    match sectors.get((disc_offset >> *sector_shift) as usize) {
+                        Some(0) => { }
+                        Some(sector) => read_exact_at(file, *sector as u64 * sector_size + within, &mut data[read..read + read_len])?,
+                        None => bail!("Cannot read past the end of the disc at 0x{:x}", disc_offset),
+                    }
+                    read += read_len;
+                }
+            }
+        }
+        Ok(data)
+    }
+}
+
+fn read_exact_at(file: &mut File, offset: u64, buffer: &mut [u8]) -> Result<(), Error> {
+    file.seek(SeekFrom::Start(offset))?;
+    if let Err(err) = file.read_exact(buffer) {
+        bail!("Cannot read 0x{:x} bytes at 0x{:x} of the disc image: {}", buffer.len(), offset, err);
+    }
+    Ok(())
+}
+
+fn be_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    const PARTITION: u64 = 0x50000;
+    const PARTITION_DATA: u64 = PARTITION + 0x20000;
+
+    /// Writes `data` at `offset` of the partition data of `image`, following the cluster layout when `hashed`
+    fn write_partition(image: &mut Vec<u8>, hashed: bool, offset: u64, data: &[u8]) {
+        for (i, byte) in data.iter().enumerate() {
+            let offset = offset + i as u64;
+            let disc_offset = if hashed {
+                (PARTITION_DATA + offset / CLUSTER_DATA_SIZE * CLUSTER_SIZE + CLUSTER_HASH_SIZE + offset % CLUSTER_DATA_SIZE) as usize
+            } else {
+                (PARTITION_DATA + offset) as usize
+            };
+            if image.len() <= disc_offset {
+                image.resize(disc_offset + 1, 0);
+            }
+            image[disc_offset] = *byte;
+        }
+    }
+
+    /// Creates an unencrypted iso image containing `files`, keyed by their path separated by `/`.
+    /// The files are stored after the file system table in order, so a large enough file crosses a cluster boundary.
+    pub(crate) fn iso(files: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        iso_image(files, true)
+    }
+
+    /// Same as `iso` but with the hashes disabled, so the partition data is stored contiguously
+    fn iso_without_hashes(files: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        iso_image(files, false)
+    }
+
+    fn iso_image(files: &[(&str, Vec<u8>)], hashed: bool) -> Vec<u8> {
+        let mut image = vec!(0; PARTITION_INFO_OFFSET as usize);
+        image[..6].copy_from_slice(b"RSBE01");
+        image[0x18..0x1C].copy_from_slice(&WII_MAGIC.to_be_bytes());
+        image[0x60] = !hashed as u8;
+        image[0x61] = 1;
+
+        // a single partition table with a single data partition
+        image.extend(&1_u32.to_be_bytes());
+        image.extend(&((PARTITION_INFO_OFFSET as u32 + 0x20) / 4).to_be_bytes());
+        image.resize(PARTITION_INFO_OFFSET as usize + 0x20, 0);
+        image.extend(&(PARTITION as u32 / 4).to_be_bytes());
+        image.extend(&DATA_PARTITION.to_be_bytes());
+        image.resize(PARTITION as usize + 0x2B8, 0);
+        image.extend(&((PARTITION_DATA - PARTITION) as u32 / 4).to_be_bytes());
+
+        // build the directory tree, each directory is followed by its files and subdirectories
+        let mut entries: Vec<(bool, String, usize)> = vec!(); // is_dir, name, parent or file index
+        let mut dir_paths: Vec<String> = vec!();
+        for (i, (path, _)) in files.iter().enumerate() {
+            let components: Vec<&str> = path.split('/').collect();
+            for depth in 1..components.len() {
+                let dir = components[..depth].join("/");
+                if !dir_paths.contains(&dir) {
+                    dir_paths.push(dir.clone());
+                    entries.push((true, dir, 0));
+                }
+            }
+            entries.push((false, path.to_string(), i));
+        }
+        entries.sort_by(|a, b| a.1.cmp(&b.1));
+
+        let count = entries.len() + 1;
+        let mut strings = vec!(0);
+        let mut fst = vec!();
+        fst.extend(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        fst.extend(&(count as u32).to_be_bytes());
+        let fst_offset = 0x440_u64;
+        let mut file_offset = 0x7B00_u64;
+        let mut file_offsets = vec!(0; files.len());
+        for (i, (is_dir, path, file)) in entries.iter().enumerate() {
+            let name = path.rsplit('/').next().unwrap();
+            fst.push(*is_dir as u8);
+            fst.extend(&(strings.len() as u32).to_be_bytes()[1..]);
+            strings.extend(name.bytes());
+            strings.push(0);
+            if *is_dir {
+                let end = entries.iter().skip(i + 1).position(|x| !x.1.starts_with(&format!("{}/", path))).map(|x| x + i + 2).unwrap_or(count);
+                fst.extend(&0_u32.to_be_bytes());
+                fst.extend(&(end as u32).to_be_bytes());
+            } else {
+                file_offsets[*file] = file_offset;
+                fst.extend(&((file_offset / 4) as u32).to_be_bytes());
+                fst.extend(&(files[*file].1.len() as u32).to_be_bytes());
+                file_offset = align(file_offset as usize + files[*file].1.len(), 4) as u64;
+            }
+        }
+        fst.extend(strings);
+
+        let mut header = vec!(0; 0x430);
+        header[0x424..0x428].copy_from_slice(&((fst_offset / 4) as u32).to_be_bytes());
+        header[0x428..0x42C].copy_from_slice(&((align(fst.len(), 4) / 4) as u32).to_be_bytes());
+        write_partition(&mut image, hashed, 0, &header);
+        write_partition(&mut image, hashed, fst_offset, &fst);
+        for (i, (_, data)) in files.iter().enumerate() {
+            write_partition(&mut image, hashed, file_offsets[i], data);
+        }
+
+        // the partition data ends with the last cluster
+        let stored_size = align(image.len() - PARTITION_DATA as usize, if hashed { CLUSTER_SIZE as usize } else { 4 });
+        image.resize(PARTITION_DATA as usize + stored_size, 0);
+        set_u32(&mut image, PARTITION + 0x2BC, stored_size as u32 / 4);
+        image
+    }
+
+    fn set_u32(image: &mut [u8], offset: u64, value: u32) {
+        image[offset as usize..offset as usize + 4].copy_from_slice(&value.to_be_bytes());
+    }
+
+    /// Rounds `value` up to a multiple of `alignment`
+    fn align(value: usize, alignment: usize) -> usize {
+        value + (alignment - value % alignment) % alignment
+    }
+
+    /// Stores the iso image in a wbfs file, sectors of the image that are entirely zero are left out
+    fn wbfs(iso: &[u8]) -> Vec<u8> {
+        let hd_sector_size = 0x200;
+        let sector_size = 0x8000;
+        let mut wbfs = vec!(0; hd_sector_size);
+        wbfs[..4].copy_from_slice(b"WBFS");
+        wbfs[8] = 9;
+        wbfs[9] = 15;
+        wbfs[0xC] = 1;
+
+        let mut table = vec!(0_u16; WII_SECTORS_PER_DISC);
+        wbfs.extend(&iso[..0x100]);
+        let table_offset = wbfs.len();
+        wbfs.resize(table_offset + WII_SECTORS_PER_DISC * 2, 0);
+        wbfs.resize(align(wbfs.len(), sector_size), 0);
+
+        for (i, sector) in iso.chunks(sector_size).enumerate() {
+            if sector.iter().any(|x| *x != 0) {
+                table[i] = (wbfs.len() / sector_size) as u16;
+                wbfs.extend(sector);
+                wbfs.resize(align(wbfs.len(), sector_size), 0);
+            }
+        }
+        for (i, sector) in table.iter().enumerate() {
+            wbfs[table_offset + i * 2..table_offset + i * 2 + 2].copy_from_slice(&sector.to_be_bytes());
+        }
+        wbfs
+    }
+
+    fn fixture() -> Vec<(&'static str, Vec<u8>)> {
+        vec!(
+            ("fighter/Fighter.pac", vec!(1, 2, 3)),
+            ("fighter/mario/FitMario.pac", (0..0x300).map(|x| x as u8).collect()),
+            ("menu2/mu_menumain.pac", vec!(4; 5)),
+        )
+    }
+
+    fn assert_files(disc: &mut Disc) {
+        let paths: Vec<&str> = disc.files().iter().map(|x| x.path.as_str()).collect();
+        assert_eq!(paths, vec!("fighter/Fighter.pac", "fighter/mario/FitMario.pac", "menu2/mu_menumain.pac"));
+        for (path, data) in fixture() {
+            assert_eq!(disc.read_file(path).unwrap(), data, "{}", path);
+        }
+        assert_eq!(disc.read_file("FIGHTER/MARIO/fitmario.pac").unwrap().len(), 0x300);
+
+        let fighter = disc.read_files(|x| x.starts_with("fighter/")).unwrap();
+        assert_eq!(fighter.len(), 2);
+        assert_eq!(fighter["fighter/Fighter.pac"], vec!(1, 2, 3));
+    }
+
+    #[test]
+    fn read_iso() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("brawl.iso");
+        std::fs::write(&path, iso(&fixture())).unwrap();
+        assert_files(&mut Disc::open(&path).unwrap());
+    }
+
+    #[test]
+    fn read_iso_without_hashes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("brawl.iso");
+        let image = iso_without_hashes(&fixture());
+        // FitMario.pac follows the 3 bytes of Fighter.pac, stored contiguously across what would be a cluster boundary
+        let fit_mario = (PARTITION_DATA + 0x7B04) as usize;
+        assert_eq!(image[fit_mario + 0x2FF], 0xFF);
+        std::fs::write(&path, image).unwrap();
+        assert_files(&mut Disc::open(&path).unwrap());
+    }
+
+    #[test]
+    fn read_wbfs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("RSBE01.WBFS");
+        std::fs::write(&path, wbfs(&iso(&fixture()))).unwrap();
+        assert_files(&mut Disc::open(&path).unwrap());
+    }
+
+    /// Opens `image` as an iso, returning the error
+    fn open_err(image: &[u8]) -> String {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("brawl.iso");
+        std::fs::write(&path, image).unwrap();
+        Disc::open(&path).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn lengths_out_of_bounds() {
+        let image = iso(&fixture());
+
+        // a partition table with u32::MAX entries
+        let mut broken = image.clone();
+        set_u32(&mut broken, PARTITION_INFO_OFFSET, u32::MAX);
+        let err = open_err(&broken);
+        assert!(err.starts_with("Cannot read 0x7fffffff8 bytes at 0x40020 of the disc image"), "{}", err);
+
+        // a partition extending past the end of the image
+        let mut broken = image.clone();
+        set_u32(&mut broken, PARTITION + 0x2BC, u32::MAX);
+        let err = open_err(&broken);
+        assert!(err.starts_with("The Wii disc image is truncated"), "{}", err);
+
+        // a file system table of u32::MAX * 4 bytes, the partition header is the first cluster
+        let mut broken = image.clone();
+        set_u32(&mut broken, PARTITION_DATA + CLUSTER_HASH_SIZE + 0x428, u32::MAX);
+        let err = open_err(&broken);
+        assert!(err.starts_with("Cannot read 0x3fffffffc bytes at 0x440 of the data partition"), "{}", err);
+
+        // a file larger than the partition
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("brawl.iso");
+        let mut broken = image;
+        let fst = (PARTITION_DATA + CLUSTER_HASH_SIZE + 0x440) as usize;
+        let entry = (1..6).find(|i| broken[fst + i * 12] == 0).unwrap();
+        set_u32(&mut broken, (fst + entry * 12 + 8) as u64, u32::MAX);
+        std::fs::write(&path, broken).unwrap();
+        let mut disc = Disc::open(&path).unwrap();
+        let path = disc.files()[0].path.clone();
+        let err = disc.read_file(&path).err().unwrap().to_string();
+        assert!(err.contains("of the data partition, it is only"), "{}", err);
+        assert!(disc.read_files(|_| true).is_err());
+    }
+
+    #[test]
+    fn wbfs_sector_shift_out_of_bounds() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("RSBE01.wbfs");
+        let mut image = wbfs(&iso(&fixture()));
+        image[9] = 200;
+        std::fs::write(&path, &image).unwrap();
+        let err = Disc::open(&path).err().unwrap().to_string();
+        assert!(err.starts_with("Invalid wbfs file: the wbfs sector size 1 << 200"), "{}", err);
+
+        // the sector table does not fit in the file
+        image[9] = 15;
+        image.truncate(0x1000);
+        std::fs::write(&path, &image).unwrap();
+        let err = Disc::open(&path).err().unwrap().to_string();
+        assert!(err.starts_with("The wbfs file is truncated"), "{}", err);
+    }
+
+    #[test]
+    fn encrypted_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("brawl.iso");
+        let mut image = iso(&fixture());
+        image[0x61] = 0;
+        std::fs::write(&path, image).unwrap();
+        let err = Disc::open(&path).err().unwrap().to_string();
+        assert!(err.starts_with("Encrypted Wii disc images are not supported"), "{}", err);
+    }
+}
@@ -0,0 +1,522 @@
+//! Helpers for modifying parsed scripts.
+//!
+//! Events are replaced with Nop events instead of being removed.
+//! Removing an event would move every following event to a new offset, breaking any Goto, Subroutine or If event that refers to them by offset.
+//! A Nop keeps the 8 byte event header in the same place and takes no arguments, so the script occupies the same bytes as before.
+//! The arguments of a replaced event are left unreferenced rather than reclaimed.
+//!
+//! `Fighter::begin_edit` groups edits to the arguments of a fighters scripts into a single `EditChange` that can be undone, for use by editors.
+//! Edits only replace the values of existing arguments, so like the helpers above they never move an event.
+//! `EditChange::write_in_place` writes a change into the file the fighter was loaded from, overwriting only the edited arguments.
+
+use std::fmt;
+
+use crate::arc::ArcChildData;
+use crate::fighter::Fighter;
+use crate::sakurai::SectionData;
+use crate::sakurai::fighter_data::ScriptCollection;
+use crate::script::{Argument, DataSource, Event, FixedPoint, Script};
+use crate::validation::{self, ScriptLocation};
+
+/// Replaces every Rumble and RumbleLoop event in the script with a Nop event.
+/// Returns the number of events replaced.
+pub fn remove_rumble(script: &mut Script) -> usize {
+    blank_events(script, |event| event.namespace == 0x07 && (event.code == 0x07 || event.code == 0x0B))
+}
+
+/// Replaces every ScreenShake event in the script with a Nop event.
+/// Returns the number of events replaced.
+pub fn remove_screen_shake(script: &mut Script) -> usize {
+    blank_events(script, |event| event.namespace == 0x1A && event.code == 0x00)
+}
+
+/// Replaces every event in the script matching `filter` with a Nop event.
+/// Returns the number of events replaced.
+pub fn blank_events<F>(script: &mut Script, filter: F) -> usize where F: Fn(&Event) -> bool {
+    let mut count = 0;
+    for event in &mut script.events {
+        if filter(event) {
+            *event = Event {
+                namespace: 0x00,
+                code:      0x02,
+                unk1:      event.unk1,
+                arguments: vec!(),
+            };
+            count += 1;
+        }
+    }
+    count
+}
+
+/// An argument of an event in one of the scripts of a fighter.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct ArgumentRef {
+    pub location: ScriptLocation,
+    /// Index into `Script::events`
+    pub event: usize,
+    /// Index into `Event::arguments`
+    pub argument: usize,
+}
+
+impl fmt::Display for ArgumentRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "argument {} of event {} in {:?}", self.argument, self.event, self.location)
+    }
+}
+
+/// The value of a single argument before and after an edit.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct ArgumentEdit {
+    pub target: ArgumentRef,
+    pub before: Argument,
+    pub after:  Argument,
+}
+
+/// Edits that are applied and undone as a unit, returned by `EditTransaction::commit`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct EditChange {
+    /// In the order they are applied
+    pub edits: Vec<ArgumentEdit>,
+}
+
+impl EditChange {
+    /// Returns the change that restores every argument to its value from before this change, pass it to `Fighter::apply` to undo.
+    pub fn inverse(&self) -> EditChange {
+        let edits = self.edits.iter().rev()
+            .map(|x| ArgumentEdit { target: x.target.clone(), before: x.after.clone(), after: x.before.clone() })
+            .collect();
+        EditChange { edits }
+    }
+
+    /// Writes the change into `moveset_pac`, the bytes of the Fit{}.pac file `fighter` was loaded from.
+    /// Only the 8 bytes of each edited argument are overwritten, so writing a change and then its inverse restores the exact original file.
+    /// Every argument must be stored with the value from before the change, otherwise `EditError::Stale` is returned and nothing is written.
+    pub fn write_in_place(&self, fighter: &Fighter, moveset_pac: &mut [u8]) -> Result<(), EditError> {
+        let data_range = fighter.moveset.children.iter()
+            .find(|x| matches!(x.data, ArcChildData::Sakurai (_)))
+            // script offsets are relative to the end of the moveset header
+            .map(|x| x.data_range.start + SAKURAI_HEADER_SIZE .. x.data_range.end)
+            .unwrap_or(0..0);
+        let moveset = moveset_pac.get_mut(data_range).unwrap_or(&mut []);
+        let scripts = validation::fighter_scripts(fighter);
+
+        let mut offsets = vec!();
+        for (i, edit) in self.edits.iter().enumerate() {
+            let target = &edit.target;
+            let script = scripts.iter().find(|(location, _)| *location == target.location).map(|(_, script)| *script)
+                .ok_or_else(|| EditError::MissingScript (target.location.clone()))?;
+            let offset = match script.source {
+                DataSource::File { offset } => argument_offset(moveset, offset as usize, target)?,
+                DataSource::WiiMemory { .. } => return Err(EditError::NotInFile (target.clone())),
+            };
+            let current = match self.edits[..i].iter().rev().find(|x| x.target == *target) {
+                Some(previous) => previous.after.raw(),
+                None => read_argument(moveset, offset),
+            };
+            if current != edit.before.raw() {
+                return Err(EditError::Stale (target.clone()));
+            }
+            offsets.push(offset);
+        }
+        for (edit, offset) in self.edits.iter().zip(offsets) {
+            write_argument(moveset, offset, &edit.after);
+        }
+        Ok(())
+    }
+}
+
+/// Returns the offset into `moveset` of the targeted argument, stored in the event of the script at `script_offset`
+fn argument_offset(moveset: &[u8], script_offset: usize, target: &ArgumentRef) -> Result<usize, EditError> {
+    let event_offset = script_offset + target.event * EVENT_SIZE;
+    let arguments = moveset.get(event_offset + 4 .. event_offset + EVENT_SIZE)
+        .map(|x| u32::from_be_bytes([x[0], x[1], x[2], x[3]]) as usize)
+        .ok_or_else(|| EditError::NotInFile (target.clone()))?;
+    let offset = arguments + target.argument * ARGUMENT_SIZE;
+    if offset + ARGUMENT_SIZE <= moveset.len() {
+        Ok(offset)
+    } else {
+        Err(EditError::NotInFile (target.clone()))
+    }
+}
+
+fn read_argument(data: &[u8], offset: usize) -> (i32, i32) {
+    let word = |x: usize| i32::from_be_bytes([data[x], data[x + 1], data[x + 2], data[x + 3]]);
+    (word(offset), word(offset + 4))
+}
+
+/// Overwrites the 8 bytes of the argument stored at `offset` in `data`, which must contain them.
+pub fn write_argument(data: &mut [u8], offset: usize, argument: &Argument) {
+    let (ty, value) = argument.raw();
+    data[offset     .. offset + 4].copy_from_slice(&ty.to_be_bytes());
+    data[offset + 4 .. offset + 8].copy_from_slice(&value.to_be_bytes());
+}
+
+const SAKURAI_HEADER_SIZE: usize = 0x20;
+const EVENT_SIZE: usize = 0x8;
+const ARGUMENT_SIZE: usize = 0x8;
+
+/// What `EditTransaction::commit` does when the transaction edits the same argument more than once
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum ConflictPolicy {
+    /// Fail the commit with `EditError::Conflict`
+    Error,
+    /// Keep the value from before the first edit and the value after the last edit.
+    /// Each edit builds on the previous edits e.g. two timer shifts of 2 frames shift the timer by 4 frames.
+    Merge,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EditError {
+    /// The fighter has no script at the location
+    MissingScript (ScriptLocation),
+    /// The script has no such event or the event has no such argument
+    MissingArgument (ArgumentRef),
+    /// The event or argument is not what the edit operates on e.g. setting the damage of an event that is not a hitbox
+    UnexpectedArgument (ArgumentRef),
+    /// The transaction edits the argument more than once with `ConflictPolicy::Error`
+    Conflict (ArgumentRef),
+    /// The argument is not stored in the moveset file e.g. the script was read from RAM
+    NotInFile (ArgumentRef),
+    /// The argument does not have the value the change expects it to have before the change is applied.
+    /// The change was made against a different version of the script, or was already applied.
+    Stale (ArgumentRef),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditError::MissingScript (location)    => write!(f, "The fighter has no script at {:?}", location),
+            EditError::MissingArgument (target)    => write!(f, "The script has no {}", target),
+            EditError::UnexpectedArgument (target) => write!(f, "The edit does not apply to {}", target),
+            EditError::Conflict (target)           => write!(f, "The transaction edits {} more than once", target),
+            EditError::NotInFile (target)          => write!(f, "The moveset file does not contain {}", target),
+            EditError::Stale (target)              => write!(f, "The change does not match the current value of {}", target),
+        }
+    }
+}
+
+impl std::error::Error for EditError { }
+
+/// Edits staged by `Fighter::begin_edit`, nothing is written to the fighter until `EditTransaction::commit`.
+/// Dropping the transaction without committing discards the edits.
+pub struct EditTransaction<'a> {
+    fighter: &'a mut Fighter,
+    policy:  ConflictPolicy,
+    edits:   Vec<ArgumentEdit>,
+}
+
+impl<'a> EditTransaction<'a> {
+    /// Defaults to `ConflictPolicy::Error`
+    pub fn set_conflict_policy(&mut self, policy: ConflictPolicy) {
+        self.policy = policy;
+    }
+
+    /// Sets the argument to `value`, which must be the same kind of argument e.g. a Scalar can only be replaced by another Scalar
+    pub fn set_argument(&mut self, target: ArgumentRef, value: Argument) -> Result<(), EditError> {
+        let before = self.staged_value(&target)?;
+        if std::mem::discriminant(&before) != std::mem::discriminant(&value) {
+            return Err(EditError::UnexpectedArgument (target));
+        }
+        self.edits.push(ArgumentEdit { target, before, after: value });
+        Ok(())
+    }
+
+    /// Sets the damage of the hitbox created by the event
+    pub fn set_hitbox_damage(&mut self, location: ScriptLocation, event: usize, damage: i32) -> Result<(), EditError> {
+        let target = self.hitbox_argument(location, event, 1)?;
+        self.set_argument(target, Argument::Value (damage))
+    }
+
+    /// Sets the trajectory of the hitbox created by the event, in degrees
+    pub fn set_hitbox_angle(&mut self, location: ScriptLocation, event: usize, angle: i32) -> Result<(), EditError> {
+        let target = self.hitbox_argument(location, event, 2)?;
+        self.set_argument(target, Argument::Value (angle))
+    }
+
+    /// Sets the size of the hitbox created by the event
+    pub fn set_hitbox_size(&mut self, location: ScriptLocation, event: usize, size: f32) -> Result<(), EditError> {
+        let target = self.hitbox_argument(location, event, 5)?;
+        self.set_argument(target, Argument::Scalar (FixedPoint::from_frames(size)))
+    }
+
+    /// Adds `frames` to the synchronous or asynchronous timer event, negative values shorten the wait.
+    /// The raw value is shifted so shifting back by the same amount restores the exact original value.
+    pub fn shift_timer(&mut self, location: ScriptLocation, event: usize, frames: f32) -> Result<(), EditError> {
+        let target = ArgumentRef { location, event, argument: 0 };
+        let is_timer = self.event(&target)?.namespace == 0x00 && matches!(self.event(&target)?.code, 0x01 | 0x02);
+        match self.staged_value(&target)? {
+            Argument::Scalar (value) if is_timer => {
+                let shifted = FixedPoint { raw: value.raw + FixedPoint::from_frames(frames).raw };
+                self.set_argument(target, Argument::Scalar (shifted))
+            }
+            _ => Err(EditError::UnexpectedArgument (target)),
+        }
+    }
+
+    /// Checks the staged edits against the `ConflictPolicy`, then applies them to the fighter.
+    /// Nothing is written when an error is returned.
+    pub fn commit(self) -> Result<EditChange, EditError> {
+        let EditTransaction { fighter, policy, edits: staged } = self;
+        let mut edits: Vec<ArgumentEdit> = vec!();
+        for edit in staged {
+            match edits.iter_mut().find(|x| x.target == edit.target) {
+                Some(existing) => match policy {
+                    ConflictPolicy::Error => return Err(EditError::Conflict (edit.target)),
+                    ConflictPolicy::Merge => existing.after = edit.after,
+                }
+                None => edits.push(edit),
+            }
+        }
+
+        let change = EditChange { edits };
+        fighter.apply(change.clone())?;
+        Ok(change)
+    }
+
+    /// The value of the argument after the edits staged so far
+    fn staged_value(&mut self, target: &ArgumentRef) -> Result<Argument, EditError> {
+        match self.edits.iter().rev().find(|x| x.target == *target) {
+            Some(edit) => Ok(edit.after.clone()),
+            None => argument_mut(self.fighter, target).map(|x| x.clone()),
+        }
+    }
+
+    fn event(&mut self, target: &ArgumentRef) -> Result<&Event, EditError> {
+        let script = script_mut(self.fighter, &target.location).ok_or_else(|| EditError::MissingScript (target.location.clone()))?;
+        script.events.get(target.event).ok_or_else(|| EditError::MissingArgument (target.clone()))
+    }
+
+    /// Returns the argument of a CreateHitBox or CreateSpecialHitBox event
+    fn hitbox_argument(&mut self, location: ScriptLocation, event: usize, argument: usize) -> Result<ArgumentRef, EditError> {
+        let target = ArgumentRef { location, event, argument };
+        let event = self.event(&target)?;
+        if event.namespace == 0x06 && (event.code == 0x00 || event.code == 0x15) {
+            Ok(target)
+        } else {
+            Err(EditError::UnexpectedArgument (target))
+        }
+    }
+}
+
+impl Fighter {
+    /// Starts a group of edits to the arguments of the fighters scripts that is applied and undone as a unit.
+    pub fn begin_edit(&mut self) -> EditTransaction<'_> {
+        EditTransaction { fighter: self, policy: ConflictPolicy::Error, edits: vec!() }
+    }
+
+    /// Applies a change returned by `EditTransaction::commit` or `EditChange::inverse`.
+    /// Every argument must have the value from before the change, otherwise `EditError::Stale` is returned and nothing is written.
+    pub fn apply(&mut self, change: EditChange) -> Result<(), EditError> {
+        for (i, edit) in change.edits.iter().enumerate() {
+            let current = match change.edits[..i].iter().rev().find(|x| x.target == edit.target) {
+                Some(previous) => previous.after.clone(),
+                None => argument_mut(self, &edit.target)?.clone(),
+            };
+            if current != edit.before {
+                return Err(EditError::Stale (edit.target.clone()));
+            }
+        }
+        for edit in change.edits {
+            *argument_mut(self, &edit.target)? = edit.after;
+        }
+        Ok(())
+    }
+}
+
+fn argument_mut<'a>(fighter: &'a mut Fighter, target: &ArgumentRef) -> Result<&'a mut Argument, EditError> {
+    let script = script_mut(fighter, &target.location).ok_or_else(|| EditError::MissingScript (target.location.clone()))?;
+    script.events.get_mut(target.event)
+        .and_then(|x| x.arguments.get_mut(target.argument))
+        .ok_or_else(|| EditError::MissingArgument (target.clone()))
+}
+
+fn script_mut<'a>(fighter: &'a mut Fighter, location: &ScriptLocation) -> Option<&'a mut Script> {
+    let sakurai = fighter.get_fighter_sakurai_mut()?;
+    match location {
+        ScriptLocation::Fragment (offset) => return sakurai.fragment_scripts.iter_mut().find(|x| x.offset == *offset),
+        ScriptLocation::Section (name) => return sakurai.sections.iter_mut().find_map(|x| match &mut x.data {
+            SectionData::Script (section_script) if section_script.name == *name => Some(&mut section_script.script),
+            _ => None,
+        }),
+        _ => { }
+    }
+
+    let fighter_data = sakurai.sections.iter_mut().find_map(|x| match &mut x.data {
+        SectionData::FighterData (fighter_data) => Some(fighter_data),
+        _ => None,
+    })?;
+    match location {
+        ScriptLocation::Subaction { collection, index } => match collection {
+            ScriptCollection::Main  => fighter_data.subaction_main.get_mut(*index),
+            ScriptCollection::Gfx   => fighter_data.subaction_gfx.get_mut(*index),
+            ScriptCollection::Sfx   => fighter_data.subaction_sfx.get_mut(*index),
+            ScriptCollection::Other => fighter_data.subaction_other.get_mut(*index),
+        }
+        ScriptLocation::EntryAction (index) => fighter_data.entry_actions.get_mut(*index),
+        ScriptLocation::ExitAction (index)  => fighter_data.exit_actions.get_mut(*index),
+        ScriptLocation::EntryActionOverride (action_id) => fighter_data.entry_action_overrides.iter_mut().find(|x| x.action_id == *action_id).map(|x| &mut x.script),
+        ScriptLocation::ExitActionOverride (action_id)  => fighter_data.exit_action_overrides.iter_mut().find(|x| x.action_id == *action_id).map(|x| &mut x.script),
+        ScriptLocation::Fragment (_) | ScriptLocation::Section (_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::Argument;
+    use crate::script_ast::{EventAst, ScriptAst};
+    use crate::arc;
+    use crate::test_util::{ScriptBuilder, TestFighterBuilder, damage};
+    use crate::wii_memory::WiiMemory;
+    use fancy_slice::FancySlice;
+
+    #[test]
+    fn remove_rumble_keeps_layout() {
+        let original = ScriptBuilder::new()
+            .event(0x07, 0x07, vec!(Argument::Value (0), Argument::Value (3)))
+            .async_timer(4.0)
+            .hitbox(damage(10))
+            .event(0x07, 0x0B, vec!(Argument::Value (1), Argument::Value (2)))
+            .event(0x1A, 0x00, vec!(Argument::Value (5)))
+            .sync_timer(2.0)
+            .event(0x07, 0x07, vec!(Argument::Value (2), Argument::Value (3)))
+            .terminate_collisions()
+            .build();
+
+        let mut script = original.clone();
+        assert_eq!(remove_rumble(&mut script), 3);
+        assert_eq!(script.offset, original.offset);
+        assert_eq!(script.events.len(), original.events.len());
+
+        // every event is still in the same 8 byte slot, the unrelated events are untouched
+        for (i, (event, original)) in script.events.iter().zip(original.events.iter()).enumerate() {
+            if i == 0 || i == 3 || i == 6 {
+                assert_eq!((event.namespace, event.code, event.arguments.len()), (0x00, 0x02, 0), "event {}", i);
+            } else {
+                assert_eq!(event, original, "event {}", i);
+            }
+        }
+
+        let ast = ScriptAst::new(&script);
+        assert!(matches!(ast.block.events[0], EventAst::Nop));
+        assert!(!ast.block.events.iter().any(|x| matches!(x, EventAst::Rumble { .. } | EventAst::RumbleLoop { .. })));
+        assert!(ast.block.events.iter().any(|x| matches!(x, EventAst::ScreenShake { magnitude: 5 })));
+
+        // nothing left to remove
+        assert_eq!(remove_rumble(&mut script), 0);
+        assert_eq!(remove_screen_shake(&mut script), 1);
+    }
+
+    fn main(index: usize) -> ScriptLocation {
+        ScriptLocation::Subaction { collection: ScriptCollection::Main, index }
+    }
+
+    fn builder() -> TestFighterBuilder {
+        TestFighterBuilder::new("Test")
+            .subaction("Wait1", |s| s.sync_timer(10.0))
+            .subaction("AttackS4S", |s| s.async_timer(7.0).hitbox(damage(14)).sync_timer(3.0).terminate_collisions())
+    }
+
+    fn fighter() -> Fighter {
+        builder().build()
+    }
+
+    /// Parses the moveset file into the scripts of the fighter
+    fn parse_main_events(moveset_pac: &[u8], index: usize) -> Vec<Event> {
+        let moveset = arc::arc(FancySlice::new(moveset_pac), &WiiMemory::new(), false);
+        let sakurai = moveset.children.iter().find_map(|x| match &x.data {
+            ArcChildData::Sakurai (sakurai) => Some(sakurai),
+            _ => None,
+        }).unwrap();
+        sakurai.sections.iter().find_map(|x| match &x.data {
+            SectionData::FighterData (fighter_data) => Some(fighter_data.subaction_main[index].events.clone()),
+            _ => None,
+        }).unwrap()
+    }
+
+    fn main_events(fighter: &Fighter, index: usize) -> Vec<Event> {
+        fighter.get_fighter_data().unwrap().subaction_main[index].events.clone()
+    }
+
+    #[test]
+    fn transaction_undo() {
+        let mut fighter = fighter();
+        let original = main_events(&fighter, 1);
+
+        let mut tx = fighter.begin_edit();
+        tx.set_hitbox_damage(main(1), 1, 16).unwrap();
+        tx.set_hitbox_angle(main(1), 1, 45).unwrap();
+        tx.set_hitbox_size(main(1), 1, 5.5).unwrap();
+        tx.shift_timer(main(1), 0, -0.9).unwrap();
+        let change = tx.commit().unwrap();
+        assert_eq!(change.edits.len(), 4);
+
+        let edited = main_events(&fighter, 1);
+        assert_eq!(edited[1].arguments[1], Argument::Value (16));
+        assert_eq!(edited[1].arguments[2], Argument::Value (45));
+        assert_eq!(edited[1].arguments[5], Argument::Scalar (FixedPoint::from_frames(5.5)));
+        assert_eq!(edited[0].arguments[0], Argument::Scalar (FixedPoint { raw: FixedPoint::from_frames(7.0).raw + FixedPoint::from_frames(-0.9).raw }));
+        assert_eq!(edited[2..], original[2..]);
+
+        // the change written into the file is read back as the edited script, writing the inverse restores every byte
+        let original_pac = builder().moveset_pac();
+        let mut pac = original_pac.clone();
+        change.write_in_place(&fighter, &mut pac).unwrap();
+        assert_eq!(parse_main_events(&pac, 1), edited);
+        assert_eq!(change.write_in_place(&fighter, &mut pac), Err(EditError::Stale (ArgumentRef { location: main(1), event: 1, argument: 1 })));
+        change.inverse().write_in_place(&fighter, &mut pac).unwrap();
+        assert!(pac == original_pac);
+
+        fighter.apply(change.inverse()).unwrap();
+        assert_eq!(main_events(&fighter, 1), original);
+
+        // the change was undone, so its inverse no longer matches
+        assert_eq!(fighter.apply(change.inverse()), Err(EditError::Stale (ArgumentRef { location: main(1), event: 0, argument: 0 })));
+        fighter.apply(change).unwrap();
+        assert_eq!(main_events(&fighter, 1), edited);
+    }
+
+    #[test]
+    fn transaction_conflicts() {
+        let mut fighter = fighter();
+        let original = main_events(&fighter, 1);
+
+        let mut tx = fighter.begin_edit();
+        tx.shift_timer(main(1), 2, 2.0).unwrap();
+        tx.set_hitbox_damage(main(1), 1, 16).unwrap();
+        tx.shift_timer(main(1), 2, 2.0).unwrap();
+        assert_eq!(tx.commit(), Err(EditError::Conflict (ArgumentRef { location: main(1), event: 2, argument: 0 })));
+        assert_eq!(main_events(&fighter, 1), original);
+
+        let mut tx = fighter.begin_edit();
+        tx.set_conflict_policy(ConflictPolicy::Merge);
+        tx.shift_timer(main(1), 2, 2.0).unwrap();
+        tx.shift_timer(main(1), 2, 2.0).unwrap();
+        let change = tx.commit().unwrap();
+        assert_eq!(change.edits.len(), 1);
+        assert_eq!(change.edits[0].before, original[2].arguments[0]);
+        assert_eq!(main_events(&fighter, 1)[2].arguments[0], Argument::Scalar (FixedPoint::from_frames(7.0)));
+
+        fighter.apply(change.inverse()).unwrap();
+        assert_eq!(main_events(&fighter, 1), original);
+    }
+
+    #[test]
+    fn transaction_errors() {
+        let mut fighter = fighter();
+        let mut tx = fighter.begin_edit();
+        assert_eq!(tx.set_hitbox_damage(main(1), 0, 16), Err(EditError::UnexpectedArgument (ArgumentRef { location: main(1), event: 0, argument: 1 })));
+        assert_eq!(tx.shift_timer(main(1), 1, 1.0), Err(EditError::UnexpectedArgument (ArgumentRef { location: main(1), event: 1, argument: 0 })));
+        assert_eq!(tx.shift_timer(main(1), 9, 1.0), Err(EditError::MissingArgument (ArgumentRef { location: main(1), event: 9, argument: 0 })));
+        assert_eq!(tx.shift_timer(main(5), 0, 1.0), Err(EditError::MissingScript (main(5))));
+
+        // the damage is a Value argument
+        let damage = ArgumentRef { location: main(1), event: 1, argument: 1 };
+        assert_eq!(tx.set_argument(damage.clone(), Argument::Scalar (FixedPoint::from_frames(16.0))), Err(EditError::UnexpectedArgument (damage.clone())));
+        assert_eq!(tx.set_argument(damage, Argument::Value (16)), Ok(()));
+    }
+}
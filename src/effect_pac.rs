@@ -0,0 +1,92 @@
+//! The effects provided by an effect file e.g. `ef_mario.pac`, used to validate the graphic effects referenced by scripts.
+//!
+//! Scripts refer to an effect by a 32 bit id, the upper 16 bits are the id of the effect file and the lower 16 bits are the index of the effect within that file.
+//! `EffectPac::from_pac` reads the effects from the EFLS effect list of the file, the REFF particle data and REFT textures it refers to are not needed for the ids.
+//! A registry can also be built by hand from the effect indices known to be in a file with `EffectPac::new`.
+
+use crate::arc::{self, ArcChildData};
+use crate::util;
+use crate::wii_memory::WiiMemory;
+
+use fancy_slice::FancySlice;
+
+/// The effects of a single effect file.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct EffectPac {
+    /// The id scripts use to refer to this file, the upper 16 bits of a graphic id
+    pub file: i16,
+    /// The index of every effect in the file, sorted
+    pub effects: Vec<i16>,
+}
+
+impl EffectPac {
+    pub fn new<I>(file: i16, effects: I) -> EffectPac where I: IntoIterator<Item=i16> {
+        let mut effects: Vec<i16> = effects.into_iter().collect();
+        effects.sort_unstable();
+        effects.dedup();
+        EffectPac { file, effects }
+    }
+
+    /// Reads the effects of an effect file e.g. `ef_mario.pac`, `file` is the id scripts use to refer to it.
+    /// The file is an ARC containing an EFLS effect list, the index of an effect is its position in that list.
+    /// Returns None when `data` is not an ARC or contains no EFLS child.
+    pub fn from_pac(file: i16, data: &[u8]) -> Option<EffectPac> {
+        if data.len() < ARC_HEADER_SIZE || util::parse_tag(data) != "ARC" {
+            return None;
+        }
+        let arc = arc::arc(FancySlice::new(data), &WiiMemory::new(), false);
+        let efls = arc.children.iter().find(|x| matches!(x.data, ArcChildData::Efls))?;
+        let efls = FancySlice::new(efls.raw_data(data));
+        if efls.len() < EFLS_HEADER_SIZE {
+            return None;
+        }
+        let count = efls.i32_be(0xc).max(0).min(i16::MAX as i32 + 1);
+        Some(EffectPac::new(file, (0..count).map(|x| x as i16)))
+    }
+
+    /// Returns true if the effect is in this file
+    pub fn contains(&self, effect: i16) -> bool {
+        self.effects.binary_search(&effect).is_ok()
+    }
+}
+
+const ARC_HEADER_SIZE: usize = 0x40;
+const EFLS_HEADER_SIZE: usize = 0x10;
+
+/// Splits a 32 bit graphic id into the id of its effect file and the index of the effect within that file
+pub fn split_graphic_id(graphic: i32) -> (i16, i16) {
+    ((graphic >> 16) as i16, graphic as i16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::arc_blob;
+
+    fn efls(count: i32) -> Vec<u8> {
+        let mut data = b"EFLS".to_vec();
+        data.extend(&0_i32.to_be_bytes());
+        data.extend(&1_i32.to_be_bytes());
+        data.extend(&count.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn effects_from_pac() {
+        let efls = efls(3);
+        let pac = arc_blob("ef_test", &[(-1, b"REFF"), (-1, &efls), (-1, b"REFT")]);
+        let effect_pac = EffectPac::from_pac(0x0C, &pac).unwrap();
+        assert_eq!(effect_pac, EffectPac::new(0x0C, vec!(0, 1, 2)));
+        assert!(effect_pac.contains(2));
+        assert!(!effect_pac.contains(3));
+    }
+
+    #[test]
+    fn pac_without_effect_list() {
+        let pac = arc_blob("ef_test", &[(-1, b"REFF"), (-1, b"REFT")]);
+        assert_eq!(EffectPac::from_pac(0x0C, &pac), None);
+        assert_eq!(EffectPac::from_pac(0x0C, b"REFF"), None);
+        assert_eq!(EffectPac::from_pac(0x0C, &[]), None);
+    }
+}
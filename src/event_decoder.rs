@@ -0,0 +1,323 @@
+//! Naming the fields of events, including events added by mods that brawllib_rs does not know about.
+//!
+//! Mods like Project M add engine events in namespaces vanilla never uses.
+//! Instead of the crate hardcoding every one of them, tools register an `EventDecoder` for each event their mod documents:
+//! ```rust
+//! use brawllib_rs::event_decoder::{EventDecoder, EventDecoderRegistry, DecodedEvent, FieldValue};
+//! use brawllib_rs::script::Event;
+//!
+//! struct SetMeterGain;
+//!
+//! impl EventDecoder for SetMeterGain {
+//!     fn decode(&self, event: &Event) -> Option<DecodedEvent> {
+//!         let gain = event.arguments.get(0)?;
+//!         Some(DecodedEvent { name: "SetMeterGain".into(), fields: vec!(("gain".into(), FieldValue::new(gain))) })
+//!     }
+//! }
+//!
+//! let mut decoders = EventDecoderRegistry::default();
+//! decoders.register(0x40, 0x01, Box::new(SetMeterGain));
+//! ```
+//! The default registry contains the vanilla events known to `script::disassemble`, so vanilla and custom events are decoded the same way.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::item_id::ItemId;
+use crate::sakurai::fighter_data::{ArcFighterData, ScriptCollection};
+use crate::script::{Argument, Event, Requirement, Script, Variable};
+use crate::script_assembler::{self, SignatureDecoder};
+
+/// Decodes events with a single namespace and code, see `EventDecoderRegistry::register`.
+pub trait EventDecoder: Send + Sync {
+    /// Returns None when the event is not understood by this decoder e.g. it has the wrong arguments.
+    fn decode(&self, event: &Event) -> Option<DecodedEvent>;
+}
+
+/// An event with a name and named fields.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct DecodedEvent {
+    pub name:   String,
+    pub fields: Vec<(String, FieldValue)>,
+}
+
+/// Written as the event name followed by `field=value` for each field, the same as named arguments in `script::disassemble`.
+impl fmt::Display for DecodedEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        for (name, value) in &self.fields {
+            write!(f, " {}={}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// The value of a field, scalars are converted to frame units.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum FieldValue {
+    Value (i32),
+    Scalar (f32),
+    Offset (i32),
+    Bool (bool),
+    File (i32),
+    Variable (Variable),
+    Requirement { flip: bool, ty: Requirement },
+    Unknown (i32, i32),
+}
+
+impl FieldValue {
+    /// The value of a field that is an entire argument
+    pub fn new(argument: &Argument) -> FieldValue {
+        match argument {
+            Argument::Value (value)            => FieldValue::Value (*value),
+            Argument::Scalar (value)           => FieldValue::Scalar (value.as_frames()),
+            Argument::Offset (offset)          => FieldValue::Offset (offset.offset),
+            Argument::Bool (value)             => FieldValue::Bool (*value),
+            Argument::File (value)             => FieldValue::File (*value),
+            Argument::Variable (variable)      => FieldValue::Variable (variable.clone()),
+            Argument::Requirement { flip, ty } => FieldValue::Requirement { flip: *flip, ty: ty.clone() },
+            Argument::Unknown (ty, value)      => FieldValue::Unknown (*ty, *value),
+        }
+    }
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldValue::Value (value)  => write!(f, "{}", value),
+            FieldValue::Scalar (value) => write!(f, "{}", value),
+            FieldValue::Offset (value) if *value < 0 => write!(f, "{}", value),
+            FieldValue::Offset (value) => write!(f, "0x{:X}", value),
+            FieldValue::Bool (value)   => write!(f, "{}", value),
+            FieldValue::File (value)   => write!(f, "{}", value),
+            FieldValue::Variable (variable) => write!(f, "{}", script_assembler::untyped_text(&Argument::Variable (variable.clone())).unwrap()),
+            FieldValue::Requirement { flip, ty } => write!(f, "{}", script_assembler::untyped_text(&Argument::Requirement { flip: *flip, ty: ty.clone() }).unwrap()),
+            FieldValue::Unknown (ty, value) => write!(f, "type{}:{}", ty, value),
+        }
+    }
+}
+
+/// An event found by `EventDecoderRegistry::query`
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct EventMatch {
+    pub subaction_index: usize,
+    pub subaction_name:  String,
+    pub collection:      ScriptCollection,
+    /// Index into `Script::events`
+    pub event_index:     usize,
+    pub event:           DecodedEvent,
+}
+
+struct RegisteredDecoder {
+    decoder: Box<dyn EventDecoder>,
+    /// Vanilla events are disassembled by name, other events are disassembled raw so they can be assembled again.
+    vanilla: bool,
+}
+
+/// The decoder used for each namespace and code.
+///
+/// `EventDecoderRegistry::default()` contains the vanilla events, `EventDecoderRegistry::empty()` contains nothing.
+pub struct EventDecoderRegistry {
+    decoders: HashMap<(u8, u8), RegisteredDecoder>,
+}
+
+impl Default for EventDecoderRegistry {
+    fn default() -> EventDecoderRegistry {
+        let mut registry = EventDecoderRegistry::empty();
+        for decoder in SignatureDecoder::all() {
+            let key = (decoder.namespace(), decoder.code());
+            registry.decoders.insert(key, RegisteredDecoder { decoder: Box::new(decoder), vanilla: true });
+        }
+        registry
+    }
+}
+
+impl EventDecoderRegistry {
+    pub fn empty() -> EventDecoderRegistry {
+        EventDecoderRegistry { decoders: HashMap::new() }
+    }
+
+    /// Use `decoder` for events with the given namespace and code, replacing any decoder already registered for them.
+    pub fn register(&mut self, namespace: u8, code: u8, decoder: Box<dyn EventDecoder>) {
+        self.decoders.insert((namespace, code), RegisteredDecoder { decoder, vanilla: false });
+    }
+
+    /// Returns None when no decoder is registered for the event or the registered decoder does not understand it.
+    pub fn decode(&self, event: &Event) -> Option<DecodedEvent> {
+        self.decoders.get(&(event.namespace, event.code)).and_then(|x| x.decoder.decode(event))
+    }
+
+    /// Same as `script::disassemble_event` except events decoded by a registered decoder are followed by a comment containing the decoded event.
+    /// The event itself is written raw so the text can still be assembled.
+    pub fn disassemble_event(&self, event: &Event) -> String {
+        match self.decoders.get(&(event.namespace, event.code)) {
+            Some(registered) if registered.vanilla => script_assembler::disassemble_event(event),
+            Some(registered) => match registered.decoder.decode(event) {
+                Some(decoded) => format!("{} // {}", script_assembler::disassemble_raw(event), decoded),
+                None          => script_assembler::disassemble_raw(event),
+            }
+            None => script_assembler::disassemble_raw(event),
+        }
+    }
+
+    /// Same as `script::disassemble` but events are written with `EventDecoderRegistry::disassemble_event`.
+    pub fn disassemble(&self, script: &Script) -> String {
+        let mut text = String::new();
+        for event in &script.events {
+            text.push_str(&self.disassemble_event(event));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Every event in the subaction scripts of the fighter that decodes to an event named `name`, compared case insensitively.
+    /// Only the events directly in the subaction scripts are searched, not the routines they call.
+    pub fn query(&self, fighter_data: &ArcFighterData, name: &str) -> Vec<EventMatch> {
+        let collections = [&fighter_data.subaction_main, &fighter_data.subaction_gfx, &fighter_data.subaction_sfx, &fighter_data.subaction_other];
+        let mut matches = vec!();
+        for (subaction_index, flags) in fighter_data.subaction_flags.iter().enumerate() {
+            for (scripts, collection) in collections.iter().zip(ScriptCollection::ALL.iter()) {
+                if let Some(script) = scripts.get(subaction_index) {
+                    for (event_index, event) in script.events.iter().enumerate() {
+                        if let Some(decoded) = self.decode(event) {
+                            if decoded.name.eq_ignore_ascii_case(name) {
+                                matches.push(EventMatch {
+                                    subaction_index,
+                                    subaction_name: flags.name.clone(),
+                                    collection:     *collection,
+                                    event_index,
+                                    event:          decoded,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        matches
+    }
+
+    /// Every GenerateItem event in the subaction scripts of the fighter that creates `item`.
+    /// Searches the same events as `EventDecoderRegistry::query`.
+    pub fn query_item(&self, fighter_data: &ArcFighterData, item: ItemId) -> Vec<EventMatch> {
+        self.query(fighter_data, "GenerateItem").into_iter()
+            .filter(|x| x.event.fields.iter().any(|(name, value)| name == "item" && *value == FieldValue::Value (item.value())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::{DataSource, FixedPoint};
+    use crate::test_util::{TestFighterBuilder, damage};
+
+    /// A made up event in a namespace vanilla never uses
+    struct SetMeterGain;
+
+    impl EventDecoder for SetMeterGain {
+        fn decode(&self, event: &Event) -> Option<DecodedEvent> {
+            match event.arguments.as_slice() {
+                [Argument::Scalar (gain), Argument::Bool (stacks)] => Some(DecodedEvent {
+                    name: "SetMeterGain".into(),
+                    fields: vec!(
+                        ("gain".into(), FieldValue::Scalar (gain.as_frames())),
+                        ("stacks".into(), FieldValue::Bool (*stacks)),
+                    ),
+                }),
+                _ => None,
+            }
+        }
+    }
+
+    fn meter_gain(gain: f32) -> Vec<Argument> {
+        vec!(Argument::Scalar (FixedPoint::from_frames(gain)), Argument::Bool (true))
+    }
+
+    #[test]
+    fn custom_event_disassembly() {
+        let mut registry = EventDecoderRegistry::default();
+        let event = Event { namespace: 0x40, code: 0x01, unk1: 0, arguments: meter_gain(1.5) };
+        assert_eq!(registry.decode(&event), None);
+        assert_eq!(registry.disassemble_event(&event), "raw ns=0x40 code=0x01 args=[s:1.5, b:true]");
+
+        registry.register(0x40, 0x01, Box::new(SetMeterGain));
+        assert_eq!(registry.decode(&event).unwrap().name, "SetMeterGain");
+        let text = registry.disassemble(&Script { events: vec!(event.clone()), offset: 0, source: DataSource::File { offset: 0 } });
+        assert_eq!(text, "raw ns=0x40 code=0x01 args=[s:1.5, b:true] // SetMeterGain gain=1.5 stacks=true\n");
+        assert_eq!(crate::script::assemble(&text).unwrap().events, vec!(event));
+
+        // the decoder rejects events it does not understand
+        let event = Event { namespace: 0x40, code: 0x01, unk1: 0, arguments: vec!() };
+        assert_eq!(registry.disassemble_event(&event), "raw ns=0x40 code=0x01 args=[]");
+    }
+
+    #[test]
+    fn vanilla_events() {
+        let registry = EventDecoderRegistry::default();
+        let event = Event { namespace: 0x00, code: 0x02, unk1: 0, arguments: vec!(Argument::Scalar (FixedPoint::from_frames(14.0))) };
+        assert_eq!(registry.decode(&event), Some(DecodedEvent { name: "AsyncTimer".into(), fields: vec!(("frame".into(), FieldValue::Scalar (14.0))) }));
+        assert_eq!(registry.disassemble_event(&event), crate::script::disassemble_event(&event));
+        assert_eq!(EventDecoderRegistry::empty().decode(&event), None);
+    }
+
+    #[test]
+    fn query_by_decoded_name() {
+        let fighter = TestFighterBuilder::new("Test")
+            .subaction("Wait1", |s| s.async_timer(2.0).event(0x40, 0x01, meter_gain(1.0)))
+            .subaction("AttackS3S", |s| s.hitbox(damage(8)).event(0x40, 0x01, meter_gain(2.0)).event(0x40, 0x01, vec!()))
+            .build();
+        let fighter_data = fighter.get_fighter_data().unwrap();
+
+        let mut registry = EventDecoderRegistry::default();
+        assert!(registry.query(fighter_data, "SetMeterGain").is_empty());
+        registry.register(0x40, 0x01, Box::new(SetMeterGain));
+
+        let matches = registry.query(fighter_data, "setmetergain");
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].subaction_name, "Wait1");
+        assert_eq!(matches[0].event_index, 1);
+        assert_eq!(matches[1].subaction_name, "AttackS3S");
+        assert_eq!(matches[1].collection, ScriptCollection::Main);
+        assert_eq!(matches[1].event.fields[0], ("gain".to_string(), FieldValue::Scalar (2.0)));
+
+        // vanilla events are found the same way
+        let hitboxes = registry.query(fighter_data, "OffensiveCollision");
+        assert_eq!(hitboxes.len(), 1);
+        assert_eq!(hitboxes[0].subaction_name, "AttackS3S");
+    }
+
+    #[test]
+    fn query_generated_items() {
+        let generate_item = |item: i32| vec!(Argument::Value (item));
+        let fighter = TestFighterBuilder::new("Test")
+            .subaction("ItemScrewAttack", |s| s.event(0x1F, 0x08, generate_item(0x32)))
+            .subaction("SpecialLw", |s| s.async_timer(3.0).event(0x1F, 0x08, generate_item(0x04)).event(0x1F, 0x08, generate_item(0x400)))
+            .build();
+        let fighter_data = fighter.get_fighter_data().unwrap();
+        let registry = EventDecoderRegistry::default();
+
+        let beam_swords = registry.query_item(fighter_data, ItemId::BeamSword);
+        assert_eq!(beam_swords.len(), 1);
+        assert_eq!(beam_swords[0].subaction_name, "SpecialLw");
+        assert_eq!(beam_swords[0].event_index, 1);
+
+        let unknown = registry.query_item(fighter_data, ItemId::Unknown (0x400));
+        assert_eq!(unknown.len(), 1);
+        assert_eq!(unknown[0].event_index, 2);
+        assert!(registry.query_item(fighter_data, ItemId::BobOmb).is_empty());
+        assert_eq!(registry.query(fighter_data, "GenerateItem").len(), 3);
+
+        let script = &fighter_data.subaction_main[1];
+        let ast = crate::script_ast::ScriptAst::new(script);
+        let items: Vec<_> = ast.block.events.iter().filter_map(|x| match x {
+            crate::script_ast::EventAst::ItemCreate { item } => Some(*item),
+            _ => None,
+        }).collect();
+        assert_eq!(items, vec!(ItemId::BeamSword, ItemId::Unknown (0x400)));
+        assert_eq!(crate::script::disassemble_event(&script.events[1]), "GenerateItem 4");
+    }
+}
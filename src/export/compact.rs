@@ -0,0 +1,205 @@
+//! A compact binary format for `Fighter`, suitable for caching or sending to a web frontend.
+//!
+//! Enabled by the `export-compact` feature.
+//!
+//! # Format
+//!
+//! *   4 bytes - magic number `BLRS`
+//! *   2 bytes - big endian format version, incremented whenever the framing changes
+//! *   1 byte  - length of the crate version string
+//! *   n bytes - crate version string of the brawllib_rs that created the file e.g. `0.19.0`
+//! *   rest    - zstd compressed bincode of the `Fighter`
+//!
+//! Files created by a different major version of brawllib_rs are refused as the layout of `Fighter` may have changed.
+//! For 0.x versions the minor version is treated as the major version.
+
+use std::fmt;
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::fighter::Fighter;
+
+const MAGIC: &[u8] = b"BLRS";
+pub const FORMAT_VERSION: u16 = 1;
+const COMPRESSION_LEVEL: i32 = 19;
+
+#[derive(Debug)]
+pub enum CompressedError {
+    NotCompressedFighter,
+    FormatVersionMismatch { found: u16, expected: u16 },
+    CrateVersionMismatch { found: String, expected: String },
+    Encode (String),
+    Decode (String),
+}
+
+impl fmt::Display for CompressedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompressedError::NotCompressedFighter =>
+                write!(f, "The data is not a compressed fighter"),
+            CompressedError::FormatVersionMismatch { found, expected } =>
+                write!(f, "The compressed fighter uses format version {} but only version {} is supported", found, expected),
+            CompressedError::CrateVersionMismatch { found, expected } =>
+                write!(f, "The compressed fighter was created by brawllib_rs {} which is incompatible with brawllib_rs {}", found, expected),
+            CompressedError::Encode (err) =>
+                write!(f, "Failed to compress the fighter: {}", err),
+            CompressedError::Decode (err) =>
+                write!(f, "Failed to decompress the fighter: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for CompressedError { }
+
+impl Fighter {
+    /// Serializes and compresses the fighter into the format described in `export::compact`
+    pub fn to_compressed(&self) -> Result<Vec<u8>, CompressedError> {
+        let crate_version = env!("CARGO_PKG_VERSION");
+
+        let mut output = MAGIC.to_vec();
+        let mut format_version = [0; 2];
+        BigEndian::write_u16(&mut format_version, FORMAT_VERSION);
+        output.extend(&format_version);
+        output.push(crate_version.len() as u8);
+        output.extend(crate_version.as_bytes());
+
+        let serialized = bincode::serialize(self).map_err(|x| CompressedError::Encode(x.to_string()))?;
+        let compressed = zstd::encode_all(serialized.as_slice(), COMPRESSION_LEVEL).map_err(|x| CompressedError::Encode(x.to_string()))?;
+        output.extend(compressed);
+
+        Ok(output)
+    }
+
+    /// Decompresses and deserializes a fighter created by `Fighter::to_compressed`
+    pub fn from_compressed(data: &[u8]) -> Result<Fighter, CompressedError> {
+        if data.len() < 7 || &data[..4] != MAGIC {
+            return Err(CompressedError::NotCompressedFighter);
+        }
+
+        let format_version = BigEndian::read_u16(&data[4..]);
+        if format_version != FORMAT_VERSION {
+            return Err(CompressedError::FormatVersionMismatch { found: format_version, expected: FORMAT_VERSION });
+        }
+
+        let version_len = data[6] as usize;
+        let version_end = 7 + version_len;
+        if data.len() < version_end {
+            return Err(CompressedError::NotCompressedFighter);
+        }
+        let found = String::from_utf8_lossy(&data[7..version_end]).to_string();
+        let expected = env!("CARGO_PKG_VERSION").to_string();
+        if major_version(&found) != major_version(&expected) {
+            return Err(CompressedError::CrateVersionMismatch { found, expected });
+        }
+
+        let decompressed = zstd::decode_all(&data[version_end..]).map_err(|x| CompressedError::Decode(x.to_string()))?;
+        bincode::deserialize(&decompressed).map_err(|x| CompressedError::Decode(x.to_string()))
+    }
+}
+
+/// Returns the part of a semver version that indicates breaking changes
+fn major_version(version: &str) -> (&str, Option<&str>) {
+    let mut split = version.split('.');
+    let major = split.next().unwrap_or("");
+    if major == "0" {
+        (major, split.next())
+    } else {
+        (major, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{TestFighterBuilder, damage};
+
+    use std::time::Instant;
+
+    fn fighter() -> Fighter {
+        let mut builder = TestFighterBuilder::new("Test")
+            .attribute_f32(0x00, 1.5)
+            .action(|s| s.sync_timer(1.0), |s| s);
+        for i in 0..200 {
+            builder = builder.subaction(&format!("Subaction{}", i), |s| s
+                .async_timer(3.0)
+                .hitbox(damage(i).angle(361).size(4.5).offset(0.0, 2.0, 1.5))
+                .sync_timer(2.5)
+                .terminate_collisions()
+            );
+        }
+        builder.build()
+    }
+
+    /// Compares the parts of the fighter that are not empty in the fixture, `Fighter` does not implement PartialEq
+    fn assert_fighter_eq(a: &Fighter, b: &Fighter) {
+        assert_eq!(a.cased_name, b.cased_name);
+        assert_eq!(a.load_detail(), b.load_detail());
+        assert_eq!(format!("{:?}", a.get_fighter_data()), format!("{:?}", b.get_fighter_data()));
+    }
+
+    #[test]
+    fn round_trip() {
+        let fighter = fighter();
+        let compressed = fighter.to_compressed().unwrap();
+        assert_eq!(&compressed[..4], b"BLRS");
+        assert_fighter_eq(&Fighter::from_compressed(&compressed).unwrap(), &fighter);
+    }
+
+    #[test]
+    fn size_and_decode_time() {
+        let compressed = fighter().to_compressed().unwrap();
+        assert!(compressed.len() < 1024 * 1024, "compressed fighter is {} bytes", compressed.len());
+
+        let start = Instant::now();
+        Fighter::from_compressed(&compressed).unwrap();
+        let elapsed = start.elapsed();
+        assert!(elapsed.as_millis() < 50, "decoding took {:?}", elapsed);
+    }
+
+    #[test]
+    fn wrong_magic() {
+        let mut compressed = fighter().to_compressed().unwrap();
+        compressed[0] = b'X';
+        assert!(matches!(Fighter::from_compressed(&compressed), Err(CompressedError::NotCompressedFighter)));
+        assert!(matches!(Fighter::from_compressed(b"BLR"), Err(CompressedError::NotCompressedFighter)));
+    }
+
+    #[test]
+    fn wrong_format_version() {
+        let mut compressed = fighter().to_compressed().unwrap();
+        BigEndian::write_u16(&mut compressed[4..], FORMAT_VERSION + 1);
+        match Fighter::from_compressed(&compressed) {
+            Err(CompressedError::FormatVersionMismatch { found, expected }) => {
+                assert_eq!(found, FORMAT_VERSION + 1);
+                assert_eq!(expected, FORMAT_VERSION);
+            }
+            other => panic!("Expected FormatVersionMismatch, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn wrong_crate_version() {
+        let compressed = fighter().to_compressed().unwrap();
+        let version_end = 7 + compressed[6] as usize;
+        let mut modified = compressed[..6].to_vec();
+        modified.push(6);
+        modified.extend(b"99.0.0");
+        modified.extend(&compressed[version_end..]);
+        assert!(matches!(Fighter::from_compressed(&modified), Err(CompressedError::CrateVersionMismatch { .. })));
+
+        // a different patch version is compatible
+        let (major, minor) = major_version(env!("CARGO_PKG_VERSION"));
+        let version = format!("{}.{}.999", major, minor.unwrap_or("0"));
+        let mut modified = compressed[..6].to_vec();
+        modified.push(version.len() as u8);
+        modified.extend(version.as_bytes());
+        modified.extend(&compressed[version_end..]);
+        assert!(Fighter::from_compressed(&modified).is_ok());
+    }
+
+    #[test]
+    fn major_versions() {
+        assert_eq!(major_version("0.18.0"), ("0", Some("18")));
+        assert_eq!(major_version("1.2.3"), ("1", None));
+    }
+}
@@ -0,0 +1,212 @@
+//! Writes the `HighLevelFighter` of each fighter as json, without building the json of the whole roster in memory.
+//!
+//! Enabled by the `json-export` feature.
+//!
+//! The json is written to the writer as it is serialized, one fighter and within it one subaction at a time.
+//! Each subaction is generated, written and dropped before the next one is generated,
+//! so memory use peaks at a single `HighLevelSubaction` instead of the hundreds of MB taken by the json of every fighter.
+//! Pass a `BufWriter`, the json is written in many small pieces.
+//!
+//! Each fighter is written as the fields of its `HighLevelFighter` followed by:
+//! *   `follower` - the same object for the fighters follower e.g. Nana for Popo, or null
+//! *   `transformation` - the same object for the fighters transformation e.g. WarioMan for Wario, or null
+//! *   `moveset` - the parsed Fit{}.pac sections, only when `JsonOpts::raw_sections` is set
+//! *   `models` - the parsed model files, only when `JsonOpts::models` is set
+
+use std::io::Write;
+
+use failure::Error;
+use serde::ser::{Serialize, Serializer, SerializeSeq, SerializeStruct};
+
+use crate::fighter::Fighter;
+use crate::high_level_fighter::{HighLevelFighter, CommonScripts, SubactionGenerator};
+
+/// Controls what is included by `write_fighters` and `write_fighter`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JsonOpts {
+    /// Include the raw sections of the fighters moveset file, as parsed into `Fighter::moveset`.
+    pub raw_sections: bool,
+    /// Include the fighters model data, as parsed into `Fighter::models`. This is by far the largest part of a fighter.
+    pub models: bool,
+}
+
+/// Writes a json array containing each fighter.
+pub fn write_fighters<W: Write>(fighters: &[Fighter], mut writer: W, opts: JsonOpts) -> Result<(), Error> {
+    let commons = CommonScripts::for_fighters(fighters);
+    let mut serializer = serde_json::Serializer::new(&mut writer);
+    let mut seq = serializer.serialize_seq(Some(fighters.len()))?;
+    for (fighter, common) in fighters.iter().zip(commons.iter()) {
+        seq.serialize_element(&FighterJson { fighter, common, opts: &opts })?;
+    }
+    SerializeSeq::end(seq)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes a single fighter as a json object e.g. to write one file per fighter.
+pub fn write_fighter<W: Write>(fighter: &Fighter, mut writer: W, opts: JsonOpts) -> Result<(), Error> {
+    let common = CommonScripts::new(fighter);
+    serde_json::to_writer(&mut writer, &FighterJson { fighter, common: &common, opts: &opts })?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Serializes the same as the derived `Serialize` of `HighLevelFighter`, followed by the fields listed in the module docs.
+/// The `streamed_matches_serialized` test fails if a field of `HighLevelFighter` is missing here.
+struct FighterJson<'a> {
+    fighter: &'a Fighter,
+    common:  &'a CommonScripts,
+    opts:    &'a JsonOpts,
+}
+
+impl Serialize for FighterJson<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (fighter, generator) = HighLevelFighter::without_subactions(self.fighter, self.common);
+        let mut state = serializer.serialize_struct("HighLevelFighter", 16)?;
+        state.serialize_field("name",                     &fighter.name)?;
+        state.serialize_field("internal_name",            &fighter.internal_name)?;
+        state.serialize_field("attributes",               &fighter.attributes)?;
+        state.serialize_field("crawl",                    &fighter.crawl)?;
+        state.serialize_field("glide",                    &fighter.glide)?;
+        state.serialize_field("multi_jump",               &fighter.multi_jump)?;
+        state.serialize_field("actions",                  &fighter.actions)?;
+        state.serialize_field("subactions",               &SubactionsJson { generator: &generator })?;
+        state.serialize_field("scripts_fragment_fighter", &fighter.scripts_fragment_fighter)?;
+        state.serialize_field("scripts_fragment_common",  &fighter.scripts_fragment_common)?;
+        state.serialize_field("scripts_section",          &fighter.scripts_section)?;
+        state.serialize_field("not_loaded",               &fighter.not_loaded)?;
+        drop(generator);
+        drop(fighter);
+
+        // The follower and transformation do not always share the common Fighter.pac of the fighter
+        let related = |related: &Fighter| -> Result<Option<CommonScripts>, S::Error> {
+            Ok(if self.common.is_source_of(related) { None } else { Some(CommonScripts::new(related)) })
+        };
+        match &self.fighter.follower {
+            Some(follower) => {
+                let common = related(&follower.fighter)?;
+                let common = common.as_ref().unwrap_or(self.common);
+                state.serialize_field("follower", &FighterJson { fighter: &follower.fighter, common, opts: self.opts })?;
+            }
+            None => state.serialize_field("follower", &None::<()>)?,
+        }
+        match &self.fighter.transformation {
+            Some(transformation) => {
+                let common = related(transformation)?;
+                let common = common.as_ref().unwrap_or(self.common);
+                state.serialize_field("transformation", &FighterJson { fighter: transformation, common, opts: self.opts })?;
+            }
+            None => state.serialize_field("transformation", &None::<()>)?,
+        }
+
+        if self.opts.raw_sections {
+            state.serialize_field("moveset", &self.fighter.moveset)?;
+        }
+        if self.opts.models {
+            state.serialize_field("models", &self.fighter.models)?;
+        }
+        state.end()
+    }
+}
+
+struct SubactionsJson<'a, 'b> {
+    generator: &'a SubactionGenerator<'b>,
+}
+
+impl Serialize for SubactionsJson<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.generator.len()))?;
+        for i in 0..self.generator.len() {
+            // only a single subaction exists at a time
+            seq.serialize_element(&self.generator.subaction(i))?;
+        }
+        seq.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fighter::FollowerData;
+    use crate::test_util::TestFighterBuilder;
+
+    use serde::Serialize;
+
+    /// The non streamed equivalent of `FighterJson` with `JsonOpts::default()`
+    #[derive(Serialize)]
+    struct Expected {
+        #[serde(flatten)]
+        fighter:        HighLevelFighter,
+        follower:       Option<Box<Expected>>,
+        transformation: Option<Box<Expected>>,
+    }
+
+    impl Expected {
+        fn new(fighter: &Fighter) -> Expected {
+            Expected {
+                fighter:        HighLevelFighter::new(fighter),
+                follower:       fighter.follower.as_ref().map(|x| Box::new(Expected::new(&x.fighter))),
+                transformation: fighter.transformation.as_ref().map(|x| Box::new(Expected::new(x))),
+            }
+        }
+    }
+
+    fn fighters() -> Vec<Fighter> {
+        let mut popo = TestFighterBuilder::new("Popo").build();
+        popo.follower = Some(Box::new(FollowerData { leader: "Popo".into(), fighter: TestFighterBuilder::new("Nana").build() }));
+        let mut wario = TestFighterBuilder::new("Wario").build();
+        wario.transformation = Some(Box::new(TestFighterBuilder::new("WarioMan").build()));
+        vec!(TestFighterBuilder::new("Mario").build(), popo, wario)
+    }
+
+    #[test]
+    fn streamed_matches_serialized() {
+        let fighters = fighters();
+        let expected: Vec<_> = fighters.iter().map(Expected::new).collect();
+
+        let mut streamed = vec!();
+        write_fighters(&fighters, &mut streamed, JsonOpts::default()).unwrap();
+        assert_eq!(streamed, serde_json::to_vec(&expected).unwrap());
+
+        for (fighter, expected) in fighters.iter().zip(expected.iter()) {
+            let mut single = vec!();
+            write_fighter(fighter, &mut single, JsonOpts::default()).unwrap();
+            assert_eq!(single, serde_json::to_vec(expected).unwrap());
+        }
+    }
+
+    #[test]
+    fn follower_and_transformation() {
+        let mut json = vec!();
+        write_fighters(&fighters(), &mut json, JsonOpts::default()).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+
+        assert_eq!(value[0]["follower"], serde_json::Value::Null);
+        assert_eq!(value[0]["transformation"], serde_json::Value::Null);
+        assert_eq!(value[1]["follower"]["internal_name"], "Nana");
+        assert_eq!(value[1]["follower"]["follower"], serde_json::Value::Null);
+        assert_eq!(value[2]["transformation"]["internal_name"], "WarioMan");
+    }
+
+    #[test]
+    fn included_data() {
+        let fighter = TestFighterBuilder::new("Mario").build();
+        let value = |opts| {
+            let mut json = vec!();
+            write_fighter(&fighter, &mut json, opts).unwrap();
+            serde_json::from_slice::<serde_json::Value>(&json).unwrap()
+        };
+
+        let default = value(JsonOpts::default());
+        assert!(default.get("moveset").is_none());
+        assert!(default.get("models").is_none());
+
+        let raw_sections = value(JsonOpts { raw_sections: true, ..JsonOpts::default() });
+        assert_eq!(raw_sections["moveset"]["name"], fighter.moveset.name.as_str());
+        assert!(raw_sections.get("models").is_none());
+
+        let models = value(JsonOpts { models: true, ..JsonOpts::default() });
+        assert_eq!(models["models"], serde_json::json!([]));
+        assert!(models.get("moveset").is_none());
+    }
+}
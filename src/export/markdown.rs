@@ -0,0 +1,282 @@
+//! Generates a wiki style page of a fighters frame data as GitHub flavored markdown, intended as a first draft for community wikis.
+//!
+//! Values are calculated from the attributes and main subaction scripts in the same way as `analysis::roster_table`.
+//! Any value that could not be determined is written as `?` so that editors can see what needs to be filled in by hand.
+
+use std::fmt::Write;
+
+use crate::aerial_data::Aerial;
+use crate::analysis::{self, MoveName};
+use crate::fighter::Fighter;
+use crate::kit_summary::{KitSummary, find_move};
+use crate::sakurai::fighter_data::ArcFighterData;
+use crate::script::{Argument, Script, normalize_timers};
+
+/// The sections of the page and the moves listed in each, in the order they are written
+pub(crate) const SECTIONS: &[(&str, &[MoveName])] = &[
+    ("Ground attacks", &[
+        MoveName::Jab,
+        MoveName::ForwardTilt,
+        MoveName::UpTilt,
+        MoveName::DownTilt,
+        MoveName::ForwardSmash,
+        MoveName::UpSmash,
+        MoveName::DownSmash,
+        MoveName::DashAttack,
+    ]),
+    ("Aerials", &[
+        MoveName::Aerial (Aerial::Nair),
+        MoveName::Aerial (Aerial::Fair),
+        MoveName::Aerial (Aerial::Bair),
+        MoveName::Aerial (Aerial::Uair),
+        MoveName::Aerial (Aerial::Dair),
+    ]),
+    ("Grabs", &[
+        MoveName::Grab,
+    ]),
+    ("Specials", &[
+        MoveName::NeutralSpecial,
+        MoveName::SideSpecial,
+        MoveName::UpSpecial,
+        MoveName::DownSpecial,
+    ]),
+];
+
+/// Returns the frame data page of the fighter.
+///
+/// Moves are grouped into sections following `MoveName`, moves the fighter does not have are left out.
+/// Hitboxes are listed in the order their events occur in the main script.
+pub fn fighter_page(fighter: &Fighter) -> String {
+    let mut page = String::new();
+    writeln!(page, "# {}", fighter.cased_name).unwrap();
+
+    let summary = KitSummary::new(fighter);
+    writeln!(page).unwrap();
+    writeln!(page, "## Attributes").unwrap();
+    writeln!(page).unwrap();
+    writeln!(page, "| Attribute | Value |").unwrap();
+    writeln!(page, "|---|---|").unwrap();
+    let attributes = [
+        ("Weight",          summary.weight),
+        ("Walk speed",      summary.walk_speed),
+        ("Run speed",       summary.run_speed),
+        ("Air speed",       summary.air_speed),
+        ("Fall speed",      summary.fall_speed),
+        ("Fast fall speed", summary.fastfall_speed),
+        ("Jumps",           summary.jumps.map(|x| x as f32)),
+    ];
+    for (name, value) in attributes.iter() {
+        writeln!(page, "| {} | {} |", name, num(*value)).unwrap();
+    }
+
+    let fighter_data = match fighter.get_fighter_data() {
+        Some(fighter_data) => fighter_data,
+        None => return page,
+    };
+
+    for (section, moves) in SECTIONS {
+        let moves: Vec<_> = moves.iter()
+            .filter_map(|move_name| find_move(fighter_data, *move_name).map(|(subaction, script)| (*move_name, subaction, script)))
+            .collect();
+        if moves.is_empty() {
+            continue;
+        }
+
+        writeln!(page).unwrap();
+        writeln!(page, "## {}", section).unwrap();
+        for (move_name, subaction, script) in moves {
+            write_move(&mut page, fighter_data, move_name, &subaction, script);
+        }
+    }
+    page
+}
+
+fn write_move(page: &mut String, fighter_data: &ArcFighterData, move_name: MoveName, subaction: &str, script: Option<&Script>) {
+    writeln!(page).unwrap();
+    writeln!(page, "### {}", move_title(move_name)).unwrap();
+    writeln!(page).unwrap();
+    writeln!(page, "Subaction: `{}`", subaction).unwrap();
+    writeln!(page).unwrap();
+
+    let startup = script.and_then(analysis::first_hitboxes).map(|(frame, _)| frame);
+    let faf = script.and_then(analysis::first_interrupt);
+    match move_name {
+        MoveName::Aerial (aerial) => {
+            writeln!(page, "| Startup | FAF | Landing lag |").unwrap();
+            writeln!(page, "|---|---|---|").unwrap();
+            writeln!(page, "| {} | {} | {} |", num(startup), num(faf), num(Some(analysis::landing_lag(&fighter_data.attributes, aerial)))).unwrap();
+        }
+        _ => {
+            writeln!(page, "| Startup | FAF |").unwrap();
+            writeln!(page, "|---|---|").unwrap();
+            writeln!(page, "| {} | {} |", num(startup), num(faf)).unwrap();
+        }
+    }
+
+    let hitboxes = script.map(hitboxes).unwrap_or_default();
+    if hitboxes.is_empty() {
+        return;
+    }
+    writeln!(page).unwrap();
+    writeln!(page, "| Hitbox | Damage | Angle | BKB | KBG | Size | Active frames |").unwrap();
+    writeln!(page, "|---|---|---|---|---|---|---|").unwrap();
+    for hitbox in hitboxes {
+        writeln!(page, "| {} | {} | {} | {} | {} | {} | {}-{} |",
+            hitbox.hitbox_id,
+            num(hitbox.damage),
+            num(hitbox.angle),
+            num(hitbox.bkb),
+            num(hitbox.kbg),
+            num(hitbox.size),
+            hitbox.start,
+            num(hitbox.end),
+        ).unwrap();
+    }
+}
+
+fn move_title(move_name: MoveName) -> &'static str {
+    match move_name {
+        MoveName::Jab            => "Jab",
+        MoveName::ForwardTilt    => "Forward tilt",
+        MoveName::UpTilt         => "Up tilt",
+        MoveName::DownTilt       => "Down tilt",
+        MoveName::ForwardSmash   => "Forward smash",
+        MoveName::UpSmash        => "Up smash",
+        MoveName::DownSmash      => "Down smash",
+        MoveName::DashAttack     => "Dash attack",
+        MoveName::Aerial (Aerial::Nair) => "Neutral air",
+        MoveName::Aerial (Aerial::Fair) => "Forward air",
+        MoveName::Aerial (Aerial::Bair) => "Back air",
+        MoveName::Aerial (Aerial::Uair) => "Up air",
+        MoveName::Aerial (Aerial::Dair) => "Down air",
+        MoveName::Grab           => "Grab",
+        MoveName::NeutralSpecial => "Neutral special",
+        MoveName::SideSpecial    => "Side special",
+        MoveName::UpSpecial      => "Up special",
+        MoveName::DownSpecial    => "Down special",
+    }
+}
+
+/// A hitbox created by a Create Hitbox or Create Special Hitbox event.
+/// Values are None when the argument is missing or of an unexpected type.
+struct Hitbox {
+    hitbox_id: u8,
+    damage: Option<f32>,
+    angle: Option<f32>,
+    bkb: Option<f32>,
+    kbg: Option<f32>,
+    size: Option<f32>,
+    /// The frame the hitbox is created on
+    start: f32,
+    /// The last frame the hitbox is active on, None when the script does not remove it
+    end: Option<f32>,
+}
+
+/// Returns every hitbox created by the script in the order they are created.
+///
+/// A hitbox is active until it is deleted, all hitboxes are removed or another hitbox is created with the same id.
+fn hitboxes(script: &Script) -> Vec<Hitbox> {
+    let mut hitboxes: Vec<Hitbox> = vec!();
+    for timed_event in normalize_timers(script).events {
+        let event = timed_event.event;
+        let frame = timed_event.frame.as_f32();
+        let value = |index: usize| match event.arguments.get(index) {
+            Some(Argument::Value (value)) => Some(*value),
+            _ => None,
+        };
+        let end_active = |hitboxes: &mut Vec<Hitbox>, hitbox_id: Option<u8>| {
+            for hitbox in hitboxes.iter_mut().filter(|x| x.end.is_none() && hitbox_id.map(|id| x.hitbox_id == id).unwrap_or(true)) {
+                hitbox.end = Some(frame - 1.0);
+            }
+        };
+
+        match (event.namespace, event.code) {
+            (0x06, 0x00) | (0x06, 0x15) => {
+                let hitbox_id = value(0).map(|x| x as u8).unwrap_or(0);
+                end_active(&mut hitboxes, Some(hitbox_id));
+                hitboxes.push(Hitbox {
+                    hitbox_id,
+                    damage: match event.arguments.get(1) {
+                        Some(Argument::Value (damage))  => Some(*damage as f32),
+                        Some(Argument::Scalar (damage)) => Some(damage.as_frames()),
+                        _ => None,
+                    },
+                    angle:  value(2).map(|x| x as f32),
+                    kbg:    value(3).map(|x| x as i16 as f32),
+                    bkb:    value(4).map(|x| x as i16 as f32),
+                    size:   match event.arguments.get(5) {
+                        Some(Argument::Scalar (size)) => Some(size.as_frames()),
+                        _ => None,
+                    },
+                    start:  frame,
+                    end:    None,
+                });
+            }
+            (0x06, 0x03) => end_active(&mut hitboxes, Some(value(0).map(|x| x as u8).unwrap_or(0))),
+            (0x06, 0x04) => end_active(&mut hitboxes, None),
+            _ => { }
+        }
+    }
+    hitboxes
+}
+
+/// Formats a value, or `?` when it is unknown
+fn num(value: Option<f32>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => String::from("?"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{TestFighterBuilder, damage};
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Fails when the page of the test fighter changed without updating fighter_page.md.
+    /// Run with BRAWLLIB_UPDATE_SNAPSHOTS=1 to update it, then check the diff.
+    #[test]
+    fn fighter_page_snapshot() {
+        let fighter = TestFighterBuilder::new("TestChar")
+            .attribute_f32(0xb0, 100.0)  // weight
+            .attribute_f32(0x140, 10.0)  // nair landing lag
+            .attribute_i32(0x60, 2)      // jumps
+            .subaction("Attack11", |s| s
+                .async_timer(3.0)
+                .hitbox(damage(3).angle(361).bkb(10).kbg(50).size(2.5).id(0))
+                .hitbox(damage(2).angle(80).bkb(20).kbg(40).size(3.0).id(1))
+                .sync_timer(2.0)
+                .terminate_collisions()
+                .event(0x64, 0x00, vec!())
+            )
+            .subaction("AttackAirN", |s| s
+                .async_timer(5.0)
+                .hitbox(damage(12).angle(45).bkb(30).kbg(100).size(5.0).id(0))
+                .sync_timer(3.0)
+                .hitbox(damage(8).angle(45).bkb(20).kbg(100).size(4.0).id(0))
+            )
+            .subaction("SpecialN", |s| s)
+            .build();
+
+        let page = fighter_page(&fighter);
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/export/fighter_page.md");
+        if std::env::var("BRAWLLIB_UPDATE_SNAPSHOTS").is_ok() {
+            fs::write(&path, &page).unwrap();
+            return;
+        }
+        let snapshot = fs::read_to_string(&path).unwrap_or_default();
+        assert!(snapshot == page, "The fighter page changed, run the tests with BRAWLLIB_UPDATE_SNAPSHOTS=1 to update fighter_page.md and review the diff");
+    }
+
+    #[test]
+    fn no_fighter_data() {
+        let mut fighter = TestFighterBuilder::new("Empty").build();
+        fighter.moveset.children.clear();
+        let page = fighter_page(&fighter);
+        assert!(page.starts_with("# Empty\n"));
+        assert!(page.contains("| Weight | ? |\n"));
+        assert!(!page.contains("## Ground attacks"));
+    }
+}
@@ -0,0 +1,10 @@
+//! Exporters that flatten fighter data into formats consumable by other tools.
+
+#[cfg(feature = "export-compact")]
+pub mod compact;
+#[cfg(feature = "json-export")]
+pub mod json;
+#[cfg(feature = "sqlite-export")]
+pub mod sqlite;
+pub mod markdown;
+pub mod svg;
@@ -0,0 +1,398 @@
+//! Dumps fighters into a sqlite database so they can be queried without loading pac files at runtime.
+//!
+//! Enabled by the `sqlite-export` feature.
+//!
+//! # Schema
+//!
+//! *   `meta (key, value)` - Contains `schema_version`, bumped whenever the schema changes in an incompatible way.
+//! *   `fighters (id, name, internal_name, mod_type, leader_id, base_id, provenance)` - `leader_id` references `fighters.id` for followers e.g. Nana, otherwise null.
+//!     `base_id` references `fighters.id` for transformations e.g. WarioMan, otherwise null.
+//!     `provenance` is the json of `Fighter::provenance`.
+//! *   `attributes (fighter_id, name, value)` - One row per `FighterAttributes` field, bools are stored as 0/1.
+//! *   `subactions (id, fighter_id, subaction_index, name, category, environment, iasa, landing_lag, frame_count)` - `category` is the `SubactionCategory` variant name and `environment` is the `Environment` variant name, `landing_lag` is NULL for grounded subactions.
+//! *   `events (id, subaction_id, script, event_index, event_id, namespace, code, name)` - `script` is one of `main`, `gfx`, `sfx` or `other`.
+//!     `event_id` is the raw event id as used by existing tools e.g. 0x06000D00
+//!     `name` is the name given by the `EventDecoderRegistry`, null when the event could not be decoded.
+//!     Use `dump_with_decoders` to name the custom events of a mod.
+//! *   `arguments (event_id, argument_index, type, value)` - `event_id` references `events.id`, `value` is json.
+//! *   `hitboxes (subaction_id, frame, kind, hitbox_id, set_id, x, y, size, damage, trajectory, wdsk, kbg, bkb, shield_damage, effect, condition)`
+//!     `kind` is `hit` or `grab`, the hit specific columns are null for grabboxes.
+//!     `x` and `y` are relative to the characters position at the start of the subaction.
+//!     `condition` is `unconditional`, `conditional` or `unreachable` as classified by `analysis::hitbox_reachability` on the main script.
+//!     When multiple events create the hitbox id, the least restrictive condition is used.
+//!     It is null when no event of the main script creates the hitbox id e.g. it is created by a subroutine.
+//! *   `feedback (subaction_id, frame, kind, value1, value2)` - Controller rumble and screen shake.
+//!     `kind` is `rumble`, `rumble_loop` or `screen_shake`, the values are the arguments of the event, `value2` is null for `screen_shake`.
+//!     `rumble_loop` rows are only inserted on frames where the rumble loop changes.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use failure::Error;
+use failure::bail;
+use rusqlite::{Connection, Transaction};
+use rusqlite::types::ToSql;
+
+use crate::analysis::{self, HitboxCondition};
+use crate::event_decoder::EventDecoderRegistry;
+use crate::fighter::Fighter;
+use crate::high_level_fighter::{HighLevelFighter, CollisionBoxValues, CommonScripts};
+use crate::script::{Argument, Script};
+use crate::script_ast::{EventAst, ScriptAst};
+use crate::subaction_category::SubactionCategory;
+use crate::subaction_environment::Environment;
+
+/// Increment when the schema changes
+pub const SCHEMA_VERSION: i32 = 9;
+
+const SCHEMA: &str = "
+CREATE TABLE meta (
+    key   TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+CREATE TABLE fighters (
+    id            INTEGER PRIMARY KEY,
+    name          TEXT NOT NULL,
+    internal_name TEXT NOT NULL,
+    mod_type      TEXT NOT NULL,
+    leader_id     INTEGER REFERENCES fighters(id),
+    base_id       INTEGER REFERENCES fighters(id),
+    provenance    TEXT NOT NULL
+);
+
+CREATE TABLE attributes (
+    fighter_id INTEGER NOT NULL REFERENCES fighters(id),
+    name       TEXT NOT NULL,
+    value      REAL NOT NULL
+);
+
+CREATE TABLE subactions (
+    id              INTEGER PRIMARY KEY,
+    fighter_id      INTEGER NOT NULL REFERENCES fighters(id),
+    subaction_index INTEGER NOT NULL,
+    name            TEXT NOT NULL,
+    category        TEXT NOT NULL,
+    environment     TEXT NOT NULL,
+    iasa            INTEGER,
+    landing_lag     REAL,
+    frame_count     INTEGER NOT NULL
+);
+
+CREATE TABLE events (
+    id          INTEGER PRIMARY KEY,
+    subaction_id INTEGER NOT NULL REFERENCES subactions(id),
+    script      TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    event_id    INTEGER NOT NULL,
+    namespace   INTEGER NOT NULL,
+    code        INTEGER NOT NULL,
+    name        TEXT
+);
+
+CREATE TABLE arguments (
+    event_id       INTEGER NOT NULL REFERENCES events(id),
+    argument_index INTEGER NOT NULL,
+    type           TEXT NOT NULL,
+    value          TEXT NOT NULL
+);
+
+CREATE TABLE hitboxes (
+    subaction_id  INTEGER NOT NULL REFERENCES subactions(id),
+    frame         INTEGER NOT NULL,
+    kind          TEXT NOT NULL,
+    hitbox_id     INTEGER NOT NULL,
+    set_id        INTEGER,
+    x             REAL NOT NULL,
+    y             REAL NOT NULL,
+    size          REAL NOT NULL,
+    damage        REAL,
+    trajectory    INTEGER,
+    wdsk          INTEGER,
+    kbg           INTEGER,
+    bkb           INTEGER,
+    shield_damage INTEGER,
+    effect        TEXT,
+    condition     TEXT
+);
+
+CREATE TABLE feedback (
+    subaction_id INTEGER NOT NULL REFERENCES subactions(id),
+    frame        INTEGER NOT NULL,
+    kind         TEXT NOT NULL,
+    value1       INTEGER NOT NULL,
+    value2       INTEGER
+);
+
+CREATE INDEX fighters_name        ON fighters(name);
+CREATE INDEX attributes_fighter   ON attributes(fighter_id);
+CREATE INDEX subactions_name      ON subactions(name);
+CREATE INDEX subactions_fighter   ON subactions(fighter_id);
+CREATE INDEX events_event_id      ON events(event_id);
+CREATE INDEX events_subaction     ON events(subaction_id);
+CREATE INDEX arguments_event      ON arguments(event_id);
+CREATE INDEX hitboxes_subaction   ON hitboxes(subaction_id);
+CREATE INDEX feedback_subaction   ON feedback(subaction_id);
+";
+
+/// Creates a new sqlite database at `path` containing the data of every fighter in `fighters`.
+/// Entry and victory subactions, followers and transformations are skipped, use `dump_with_options` to include them.
+/// Fails if a database already exists at `path`.
+/// Refer to the module documentation for the schema.
+pub fn dump(fighters: &[Fighter], path: &Path) -> Result<(), Error> {
+    dump_with_options(fighters, path, false, false)
+}
+
+/// Same as `dump` but entry and victory subactions are included when `include_non_gameplay` is true
+/// and followers and transformations are included when `include_related` is true.
+pub fn dump_with_options(fighters: &[Fighter], path: &Path, include_non_gameplay: bool, include_related: bool) -> Result<(), Error> {
+    dump_with_decoders(fighters, path, include_non_gameplay, include_related, &EventDecoderRegistry::default())
+}
+
+/// Same as `dump_with_options` but events are named by `decoders` instead of only the vanilla decoders.
+pub fn dump_with_decoders(fighters: &[Fighter], path: &Path, include_non_gameplay: bool, include_related: bool, decoders: &EventDecoderRegistry) -> Result<(), Error> {
+    if path.exists() {
+        bail!("Cannot dump to {}, the file already exists", path.display());
+    }
+
+    let mut connection = Connection::open(path)?;
+    dump_to_connection(fighters, &mut connection, include_non_gameplay, include_related, decoders)
+}
+
+/// Creates the schema in the empty database of `connection` and inserts every fighter in `fighters`
+fn dump_to_connection(fighters: &[Fighter], connection: &mut Connection, include_non_gameplay: bool, include_related: bool, decoders: &EventDecoderRegistry) -> Result<(), Error> {
+    let transaction = connection.transaction()?;
+    transaction.execute_batch(SCHEMA)?;
+    transaction.execute("INSERT INTO meta (key, value) VALUES ('schema_version', ?1)", &[&SCHEMA_VERSION.to_string()])?;
+
+    for (fighter, common) in fighters.iter().zip(CommonScripts::for_fighters(fighters)) {
+        let fighter_id = dump_fighter(&transaction, fighter, &common, None, None, include_non_gameplay, decoders)?;
+        if !include_related {
+            continue;
+        }
+        if let Some(follower) = &fighter.follower {
+            if common.is_source_of(&follower.fighter) {
+                dump_fighter(&transaction, &follower.fighter, &common, Some(fighter_id), None, include_non_gameplay, decoders)?;
+            } else {
+                dump_fighter(&transaction, &follower.fighter, &CommonScripts::new(&follower.fighter), Some(fighter_id), None, include_non_gameplay, decoders)?;
+            }
+        }
+        if let Some(transformation) = &fighter.transformation {
+            if common.is_source_of(transformation) {
+                dump_fighter(&transaction, transformation, &common, None, Some(fighter_id), include_non_gameplay, decoders)?;
+            } else {
+                dump_fighter(&transaction, transformation, &CommonScripts::new(transformation), None, Some(fighter_id), include_non_gameplay, decoders)?;
+            }
+        }
+    }
+
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Returns the id of the inserted fighter
+fn dump_fighter(transaction: &Transaction, fighter: &Fighter, common: &CommonScripts, leader_id: Option<i64>, base_id: Option<i64>, include_non_gameplay: bool, decoders: &EventDecoderRegistry) -> Result<i64, Error> {
+    let fighter_data = match fighter.get_fighter_data() {
+        Some(fighter_data) => fighter_data,
+        None => bail!("{} has no fighter data", fighter.cased_name),
+    };
+    let hl_fighter = HighLevelFighter::new_with_common(fighter, common);
+
+    transaction.execute(
+        "INSERT INTO fighters (name, internal_name, mod_type, leader_id, base_id, provenance) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        &[&hl_fighter.name as &dyn ToSql, &hl_fighter.internal_name, &format!("{:?}", fighter.mod_type), &leader_id, &base_id, &serde_json::to_string(&fighter.provenance)?]
+    )?;
+    let fighter_id = transaction.last_insert_rowid();
+
+    let mut insert_attribute = transaction.prepare_cached("INSERT INTO attributes (fighter_id, name, value) VALUES (?1, ?2, ?3)")?;
+    if let serde_json::Value::Object(attributes) = serde_json::to_value(&hl_fighter.attributes)? {
+        for (name, value) in attributes {
+            let value = match value {
+                serde_json::Value::Number (number) => number.as_f64().unwrap_or(0.0),
+                serde_json::Value::Bool (true)     => 1.0,
+                _                                  => 0.0,
+            };
+            insert_attribute.execute(&[&fighter_id as &dyn ToSql, &name, &value])?;
+        }
+    }
+
+    let mut insert_subaction = transaction.prepare_cached(
+        "INSERT INTO subactions (fighter_id, subaction_index, name, category, environment, iasa, landing_lag, frame_count) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"
+    )?;
+    let mut insert_hitbox = transaction.prepare_cached(
+        "INSERT INTO hitboxes (subaction_id, frame, kind, hitbox_id, set_id, x, y, size, damage, trajectory, wdsk, kbg, bkb, shield_damage, effect, condition)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)"
+    )?;
+    let mut insert_feedback = transaction.prepare_cached(
+        "INSERT INTO feedback (subaction_id, frame, kind, value1, value2) VALUES (?1, ?2, ?3, ?4, ?5)"
+    )?;
+    let environments = fighter.subaction_environments();
+    for (i, subaction_flags) in fighter_data.subaction_flags.iter().enumerate() {
+        let category = SubactionCategory::from_name(&subaction_flags.name);
+        if !include_non_gameplay && !category.is_gameplay() {
+            continue;
+        }
+
+        // HighLevelFighter does not generate subactions when the fighter has no model
+        let hl_subaction = hl_fighter.subactions.get(i);
+        let name = hl_subaction.map(|x| x.name.clone()).unwrap_or_else(|| subaction_flags.name.clone());
+        let iasa = hl_subaction.and_then(|x| x.iasa).map(|x| x as i64);
+        let environment = environments.get(i).cloned().unwrap_or(Environment::Unknown);
+        // a subaction that is only performed on the ground can never land
+        let landing_lag = hl_subaction.and_then(|x| x.landing_lag).filter(|_| environment != Environment::Grounded).map(|x| x as f64);
+        let frame_count = hl_subaction.map(|x| x.frames.len() as i64).unwrap_or(0);
+        insert_subaction.execute(&[&fighter_id as &dyn ToSql, &(i as i64), &name, &format!("{:?}", category), &format!("{:?}", environment), &iasa, &landing_lag, &frame_count])?;
+        let subaction_id = transaction.last_insert_rowid();
+
+        let scripts = [
+            ("main",  fighter_data.subaction_main.get(i)),
+            ("gfx",   fighter_data.subaction_gfx.get(i)),
+            ("sfx",   fighter_data.subaction_sfx.get(i)),
+            ("other", fighter_data.subaction_other.get(i)),
+        ];
+        for (script_name, script) in scripts.iter() {
+            if let Some(script) = script {
+                dump_script(transaction, subaction_id, script_name, script, decoders)?;
+            }
+        }
+
+        if let Some(hl_subaction) = hl_subaction {
+            let conditions = fighter_data.subaction_main.get(i).map(hitbox_conditions).unwrap_or_default();
+            for (frame_index, frame) in hl_subaction.frames.iter().enumerate() {
+                for hit_box in &frame.hit_boxes {
+                    let x = (hit_box.next_pos.z + frame.x_pos) as f64;
+                    let y = (hit_box.next_pos.y + frame.y_pos) as f64;
+                    let size = hit_box.next_size as f64;
+                    match &hit_box.next_values {
+                        CollisionBoxValues::Hit (hit) => {
+                            insert_hitbox.execute(&[
+                                &subaction_id as &dyn ToSql, &(frame_index as i64), &"hit", &hit.hitbox_id, &hit.set_id, &x, &y, &size,
+                                &(hit.damage as f64), &hit.trajectory, &hit.wdsk, &hit.kbg, &hit.bkb, &hit.shield_damage, &format!("{:?}", hit.effect),
+                                &conditions.get(&hit.hitbox_id).cloned()
+                            ])?;
+                        }
+                        CollisionBoxValues::Grab (grab) => {
+                            let null: Option<i64> = None;
+                            insert_hitbox.execute(&[
+                                &subaction_id as &dyn ToSql, &(frame_index as i64), &"grab", &grab.hitbox_id, &null, &x, &y, &size,
+                                &null, &null, &null, &null, &null, &null, &null, &null
+                            ])?;
+                        }
+                    }
+                }
+            }
+
+            let mut prev_rumble_loop = None;
+            for (frame_index, frame) in hl_subaction.frames.iter().enumerate() {
+                if let Some((unk1, unk2)) = frame.rumble {
+                    insert_feedback.execute(&[&subaction_id as &dyn ToSql, &(frame_index as i64), &"rumble", &unk1, &unk2])?;
+                }
+                if frame.rumble_loop != prev_rumble_loop {
+                    if let Some((unk1, unk2)) = frame.rumble_loop {
+                        insert_feedback.execute(&[&subaction_id as &dyn ToSql, &(frame_index as i64), &"rumble_loop", &unk1, &unk2])?;
+                    }
+                    prev_rumble_loop = frame.rumble_loop;
+                }
+                for effect in &frame.effects {
+                    if let EventAst::ScreenShake { magnitude } = effect.event {
+                        let null: Option<i64> = None;
+                        insert_feedback.execute(&[&subaction_id as &dyn ToSql, &(frame_index as i64), &"screen_shake", &magnitude, &null])?;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(fighter_id)
+}
+
+/// Returns the condition kind of each hitbox id created by the script.
+/// When multiple events create the same hitbox id the least restrictive condition is used.
+fn hitbox_conditions(script: &Script) -> HashMap<u8, &'static str> {
+    let rank = |condition: &HitboxCondition| match condition {
+        HitboxCondition::Unconditional   => 2,
+        HitboxCondition::Conditional (_) => 1,
+        HitboxCondition::Unreachable     => 0,
+    };
+
+    let mut conditions: HashMap<u8, &HitboxCondition> = HashMap::new();
+    let reachability = analysis::hitbox_reachability(&ScriptAst::new(script));
+    for hitbox in &reachability {
+        let condition = conditions.entry(hitbox.hitbox_id).or_insert(&hitbox.condition);
+        if rank(&hitbox.condition) > rank(condition) {
+            *condition = &hitbox.condition;
+        }
+    }
+    conditions.into_iter().map(|(id, condition)| (id, condition.kind())).collect()
+}
+
+fn dump_script(transaction: &Transaction, subaction_id: i64, script_name: &str, script: &Script, decoders: &EventDecoderRegistry) -> Result<(), Error> {
+    let mut insert_event = transaction.prepare_cached(
+        "INSERT INTO events (subaction_id, script, event_index, event_id, namespace, code, name) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+    )?;
+    let mut insert_argument = transaction.prepare_cached(
+        "INSERT INTO arguments (event_id, argument_index, type, value) VALUES (?1, ?2, ?3, ?4)"
+    )?;
+
+    for (event_index, event) in script.events.iter().enumerate() {
+        let name = decoders.decode(event).map(|x| x.name);
+        insert_event.execute(&[
+            &subaction_id as &dyn ToSql, &script_name, &(event_index as i64), &(event.raw_id() as i64), &event.namespace, &event.code, &name
+        ])?;
+        let event_id = transaction.last_insert_rowid();
+
+        for (argument_index, argument) in event.arguments.iter().enumerate() {
+            let ty = match argument {
+                Argument::Value (_)           => "Value",
+                Argument::Scalar (_)          => "Scalar",
+                Argument::Offset (_)          => "Offset",
+                Argument::Bool (_)            => "Bool",
+                Argument::File (_)            => "File",
+                Argument::Variable (_)        => "Variable",
+                Argument::Requirement { .. }  => "Requirement",
+                Argument::Unknown (_, _)      => "Unknown",
+            };
+            let value = serde_json::to_string(argument)?;
+            insert_argument.execute(&[&event_id as &dyn ToSql, &(argument_index as i64), &ty, &value])?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{TestFighterBuilder, damage};
+
+    fn count(connection: &Connection, table: &str) -> i64 {
+        connection.query_row(&format!("SELECT COUNT(*) FROM {}", table), rusqlite::NO_PARAMS, |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn table_row_counts() {
+        let fighter = TestFighterBuilder::new("Test")
+            .attribute_f32(0x00, 1.5)
+            .subaction("Wait1", |s| s.sync_timer(10.0))
+            .subaction("Attack11", |s| s.async_timer(3.0).hitbox(damage(3)).sync_timer(2.0).terminate_collisions())
+            .build();
+
+        let mut connection = Connection::open_in_memory().unwrap();
+        dump_to_connection(&[fighter], &mut connection, true, false, &EventDecoderRegistry::default()).unwrap();
+
+        let counts: Vec<(&str, i64)> = ["meta", "fighters", "subactions", "events", "arguments", "hitboxes", "feedback"].iter()
+            .map(|table| (*table, count(&connection, table)))
+            .collect();
+        // the fixture has no animations, so the subactions have no frames to insert hitboxes from
+        assert_eq!(counts, vec!(
+            ("meta", 1),
+            ("fighters", 1),
+            ("subactions", 2),
+            ("events", 5),
+            ("arguments", 16),
+            ("hitboxes", 0),
+            ("feedback", 0),
+        ));
+        let meta: String = connection.query_row("SELECT value FROM meta WHERE key = 'schema_version'", rusqlite::NO_PARAMS, |row| row.get(0)).unwrap();
+        assert_eq!(meta, SCHEMA_VERSION.to_string());
+    }
+}
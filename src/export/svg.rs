@@ -0,0 +1,371 @@
+//! Renders hitbox visualizations to SVG on the CPU, for use where the wgpu renderer is unavailable e.g. headless CI.
+//!
+//! The character is approximated by drawing each hurtbox as a capsule from the center of its first sphere 8th to the center of its furthest sphere 8th.
+//! Interpolated hitboxes are drawn as the capsule swept from their previous position, see `FrameHitbox`.
+//! This is not as precise as the renderer but the output only depends on the input data, so it can be diffed and cached.
+//!
+//! The side view is used: the horizontal axis is the z axis of the fighter and the vertical axis is the y axis, the same as `Extent`.
+
+use std::fmt::Write;
+
+use cgmath::{InnerSpace, Matrix4, Point3, Transform, Vector3};
+
+use crate::fighter::Fighter;
+use crate::high_level_fighter::{CollisionBoxValues, Extent, HighLevelFighter, HighLevelFrame, HighLevelHurtBox, HighLevelSubaction};
+
+/// Pixels per brawl unit
+const SCALE: f32 = 10.0;
+/// Space left around the boxes of each frame, in brawl units
+const PADDING: f32 = 2.0;
+
+/// Renders the frame of the subaction with the given name.
+///
+/// Creates a `HighLevelFighter` for the fighter, when rendering multiple frames or subactions use `render_high_level_frame` instead.
+/// If the subaction or frame does not exist an SVG with no frames is returned.
+pub fn render_subaction(fighter: &Fighter, subaction: &str, frame: usize) -> String {
+    let high_level_fighter = HighLevelFighter::new(fighter);
+    match high_level_fighter.subactions.iter().find(|x| x.name == subaction) {
+        Some(subaction) => render_high_level_frame(subaction, frame),
+        None => {
+            error!("Cannot render subaction {} as it does not exist", subaction);
+            render(&[], &Extent::new(), false)
+        }
+    }
+}
+
+/// Renders every `every`th frame of the subaction with the given name, laid out horizontally starting at frame 0.
+///
+/// Creates a `HighLevelFighter` for the fighter, when rendering multiple subactions use `render_high_level_strip` instead.
+/// If the subaction does not exist an SVG with no frames is returned.
+pub fn render_strip(fighter: &Fighter, subaction: &str, every: usize) -> String {
+    let high_level_fighter = HighLevelFighter::new(fighter);
+    match high_level_fighter.subactions.iter().find(|x| x.name == subaction) {
+        Some(subaction) => render_high_level_strip(subaction, every),
+        None => {
+            error!("Cannot render subaction {} as it does not exist", subaction);
+            render(&[], &Extent::new(), true)
+        }
+    }
+}
+
+/// Renders a single frame of the subaction.
+///
+/// The view is sized to fit every frame of the subaction, so that frames rendered separately line up with each other.
+/// If the frame does not exist an SVG with no frames is returned.
+pub fn render_high_level_frame(subaction: &HighLevelSubaction, frame: usize) -> String {
+    let frames: Vec<_> = subaction.frames.get(frame).map(|x| (frame, x)).into_iter().collect();
+    render(&frames, &subaction_extent(subaction), false)
+}
+
+/// Renders every `every`th frame of the subaction, laid out horizontally starting at frame 0.
+/// Each frame is labelled with its index.
+///
+/// An `every` of 0 is treated as 1.
+pub fn render_high_level_strip(subaction: &HighLevelSubaction, every: usize) -> String {
+    let frames: Vec<_> = subaction.frames.iter().enumerate().step_by(every.max(1)).collect();
+    render(&frames, &subaction_extent(subaction), true)
+}
+
+fn render(frames: &[(usize, &HighLevelFrame)], extent: &Extent, labels: bool) -> String {
+    let left = extent.left - PADDING;
+    let top = -extent.up - PADDING;
+    let cell_width = extent.right - extent.left + PADDING * 2.0;
+    let height = extent.up - extent.down + PADDING * 2.0;
+    let width = cell_width * frames.len().max(1) as f32;
+
+    let mut svg = String::new();
+    writeln!(svg,
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}">"##,
+        num(width * SCALE), num(height * SCALE), num(left), num(top), num(width), num(height)
+    ).unwrap();
+
+    for (i, (frame_index, frame)) in frames.iter().enumerate() {
+        writeln!(svg, r##"<g class="frame" data-frame="{}" transform="translate({} 0)">"##, frame_index, num(cell_width * i as f32)).unwrap();
+        if labels {
+            writeln!(svg, r##"<text x="{}" y="{}" font-size="{}">{}</text>"##, num(left + 0.5), num(top + 1.5), num(1.5), frame_index).unwrap();
+        }
+        render_frame(&mut svg, frame);
+        writeln!(svg, "</g>").unwrap();
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn render_frame(svg: &mut String, frame: &HighLevelFrame) {
+    for hurt_box in &frame.hurt_boxes {
+        let (start, end, radius) = hurt_box_capsule(hurt_box);
+        let color = if hurt_box.state.is_intangible() {
+            "#0000ff"
+        } else if hurt_box.state.is_invincible() {
+            "#00ff00"
+        } else {
+            "#ffff00"
+        };
+        writeln!(svg,
+            r##"<line class="hurtbox" x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" stroke-opacity="0.3" stroke-width="{}" stroke-linecap="round"/>"##,
+            num(start.z + frame.x_pos), num(-(start.y + frame.y_pos)),
+            num(end.z + frame.x_pos), num(-(end.y + frame.y_pos)),
+            color, num(radius * 2.0)
+        ).unwrap();
+    }
+
+    for (hit_box, frame_hitbox) in frame.hit_boxes.iter().zip(frame.frame_hitboxes()) {
+        // only display hitboxes that are used in regular matches
+        if let CollisionBoxValues::Hit (hit_values) = &hit_box.next_values {
+            if !hit_values.enabled {
+                continue;
+            }
+        }
+
+        // same colors as the renderer
+        let color = match hit_box.hitbox_id {
+            0 => "#ef6400", // orange
+            1 => "#ff0000", // red
+            2 => "#ff00ff", // purple
+            3 => "#18d6c9", // turqoise
+            4 => "#24d618", // green
+            _ => "#ffffff", // white
+        };
+        let center = frame_hitbox.center;
+        if let Some(prev) = frame_hitbox.prev_center {
+            // interpolated hitboxes cover the capsule swept from the previous frame
+            writeln!(svg,
+                r##"<line class="hitbox" data-id="{}" x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" stroke-opacity="0.3" stroke-width="{}" stroke-linecap="round"/>"##,
+                frame_hitbox.hitbox_id,
+                num(prev.z), num(-prev.y),
+                num(center.z), num(-center.y),
+                color, num(frame_hitbox.radius * 2.0)
+            ).unwrap();
+        } else {
+            writeln!(svg,
+                r##"<circle class="hitbox" data-id="{}" cx="{}" cy="{}" r="{}" fill="{}" fill-opacity="0.3"/>"##,
+                frame_hitbox.hitbox_id,
+                num(center.z), num(-center.y),
+                num(frame_hitbox.radius), color
+            ).unwrap();
+        }
+    }
+
+    let ecb = &frame.ecb;
+    let mid_y = (ecb.top + ecb.bottom) / 2.0;
+    writeln!(svg,
+        r##"<polygon class="ecb" points="{},{} {},{} {},{} {},{}" fill="none" stroke="#f15c0a" stroke-width="0.2"/>"##,
+        num(frame.x_pos),             num(-(ecb.top + frame.y_pos)),
+        num(ecb.right + frame.x_pos), num(-(mid_y + frame.y_pos)),
+        num(frame.x_pos),             num(-(ecb.bottom + frame.y_pos)),
+        num(ecb.left + frame.x_pos),  num(-(mid_y + frame.y_pos))
+    ).unwrap();
+}
+
+/// Returns the start point, end point and radius of a capsule approximating the hurtbox, relative to the frame position.
+fn hurt_box_capsule(hurt_box: &HighLevelHurtBox) -> (Point3<f32>, Point3<f32>, f32) {
+    let bone_matrix = hurt_box.bone_matrix;
+    let bone_scale = Vector3::new(bone_matrix.x.magnitude(), bone_matrix.y.magnitude(), bone_matrix.z.magnitude());
+    let radius = hurt_box.hurt_box.radius;
+    let offset = hurt_box.hurt_box.offset;
+    let stretch = hurt_box.hurt_box.stretch;
+
+    // Same transform as the renderer, but applied to the sphere 8th centers instead of the sphere vertices
+    let transform = bone_matrix * Matrix4::from_scale(radius) * Matrix4::from_translation(Vector3::new(
+        offset.x / (bone_scale.x * radius),
+        offset.y / (bone_scale.y * radius),
+        offset.z / (bone_scale.z * radius),
+    ));
+    let stretch_face = Point3::new(
+        stretch.x / (radius * bone_scale.x),
+        stretch.y / (radius * bone_scale.y),
+        stretch.z / (radius * bone_scale.z),
+    );
+
+    let start = transform.transform_point(Point3::new(0.0, 0.0, 0.0));
+    let end = transform.transform_point(stretch_face);
+    let scaled_radius = radius * (bone_scale.x + bone_scale.y + bone_scale.z) / 3.0;
+    (start, end, scaled_radius)
+}
+
+fn subaction_extent(subaction: &HighLevelSubaction) -> Extent {
+    let mut extent = subaction.hurt_box_extent();
+    extent.extend(&subaction.hit_box_extent());
+    extent.extend(&subaction.ledge_grab_box_extent());
+    for frame in &subaction.frames {
+        extent.extend(&Extent {
+            left:  frame.ecb.left   + frame.x_pos,
+            right: frame.ecb.right  + frame.x_pos,
+            up:    frame.ecb.top    + frame.y_pos,
+            down:  frame.ecb.bottom + frame.y_pos,
+        });
+    }
+    extent
+}
+
+/// Formats with a fixed precision so the output is stable across platforms
+fn num(value: f32) -> String {
+    // rounding first avoids printing -0.00, adding 0.0 turns -0.0 into 0.0
+    format!("{:.2}", (value * 100.0).round() / 100.0 + 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_frame::GameFrame;
+    use crate::high_level_fighter::{ECB, GrabBoxValues, HighLevelHitBox, HighLevelScripts};
+    use crate::sakurai::fighter_data::AnimationFlags;
+    use crate::sakurai::fighter_data::misc_section::{HurtBox, HurtBoxZone};
+    use crate::script_ast::{Block, EdgeSlide, GrabTarget, HurtBoxState, ScriptAst};
+    use crate::script_runner::VelModify;
+    use crate::subaction_environment::Environment;
+    use cgmath::SquareMatrix;
+
+    fn hurt_box(offset: Vector3<f32>, stretch: Vector3<f32>, radius: f32, state: HurtBoxState) -> HighLevelHurtBox {
+        HighLevelHurtBox {
+            bone_matrix: Matrix4::identity(),
+            hurt_box: HurtBox {
+                offset,
+                stretch,
+                radius,
+                enabled:            true,
+                zone:               HurtBoxZone::Middle,
+                grabbable:          true,
+                trap_item_hittable: true,
+                bone_index:         0,
+            },
+            state,
+        }
+    }
+
+    fn hit_box(hitbox_id: u8, pos: Point3<f32>, size: f32) -> HighLevelHitBox {
+        HighLevelHitBox {
+            hitbox_id,
+            bone_name:   None,
+            interpolate: false,
+            prev_pos:    None,
+            prev_size:   None,
+            prev_values: None,
+            next_pos:    pos,
+            next_size:   size,
+            next_values: CollisionBoxValues::Grab (GrabBoxValues {
+                hitbox_id:  hitbox_id as i32,
+                size,
+                set_action: 0,
+                target:     GrabTarget::AerialAndGrounded,
+                unk:        None,
+            }),
+        }
+    }
+
+    fn frame(x_pos: f32, hit_boxes: Vec<HighLevelHitBox>) -> HighLevelFrame {
+        HighLevelFrame {
+            timer:                 GameFrame::ZERO,
+            hurt_boxes: vec!(
+                hurt_box(Vector3::new(0.0, 5.0, 0.0), Vector3::new(0.0, 4.0, 0.0), 3.0, HurtBoxState::Normal),
+                hurt_box(Vector3::new(0.0, 12.0, 1.0), Vector3::new(0.0, 0.0, 0.0), 2.0, HurtBoxState::IntangibleNoFlashing),
+            ),
+            hit_boxes,
+            ledge_grab_box:        None,
+            throw_n:               None,
+            x_pos,
+            y_pos:                 0.0,
+            interruptible:         false,
+            edge_slide:            EdgeSlide::SlideOff,
+            reverse_direction:     false,
+            airbourne:             false,
+            landing_lag:           false,
+            ecb:                   ECB { left: -3.0, right: 3.0, top: 14.0, bottom: 0.0, transn_x: 0.0, transn_y: 0.0 },
+            hitbox_sets_rehit:     [false; 10],
+            slope_contour_stand:   None,
+            slope_contour_full:    None,
+            rumble:                None,
+            rumble_loop:           None,
+            grab_interrupt_damage: None,
+            throw:                 None,
+            effects:               vec!(),
+            color_flash:           None,
+            sword_glow:            None,
+            concurrent_loops:      vec!(),
+            x_vel_modify:          VelModify::None,
+            y_vel_modify:          VelModify::None,
+            movement_effects:      vec!(),
+            x_vel_temp:            0.0,
+            y_vel_temp:            0.0,
+        }
+    }
+
+    fn subaction() -> HighLevelSubaction {
+        let script = || ScriptAst { block: Block { events: vec!() }, offset: 0 };
+        HighLevelSubaction {
+            name:            "AttackS3S".into(),
+            iasa:            None,
+            frames:          vec!(
+                frame(0.0, vec!()),
+                frame(0.0, vec!()),
+                frame(1.0, vec!(hit_box(0, Point3::new(0.0, 8.0, 6.0), 4.0), hit_box(1, Point3::new(0.0, 8.0, 10.0), 3.0))),
+                frame(1.0, vec!(hit_box(1, Point3::new(0.0, 8.0, 10.0), 3.0))),
+                frame(1.5, vec!()),
+            ),
+            landing_lag:     None,
+            animation_flags: AnimationFlags::NONE,
+            scripts:         HighLevelScripts { script_main: script(), script_gfx: script(), script_sfx: script(), script_other: script() },
+            bad_interrupts:  false,
+            caveats:         vec!(),
+            environment:     Environment::Unknown,
+        }
+    }
+
+    #[test]
+    fn frame_structure() {
+        let svg = render_high_level_frame(&subaction(), 2);
+        assert!(svg.starts_with(r##"<svg xmlns="http://www.w3.org/2000/svg" width="##));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<g class=\"frame\"").count(), 1);
+        assert_eq!(svg.matches("<line class=\"hurtbox\"").count(), 2);
+        assert_eq!(svg.matches("<circle class=\"hitbox\"").count(), 2);
+        assert_eq!(svg.matches("<polygon class=\"ecb\"").count(), 1);
+        assert_eq!(svg.matches("<text").count(), 0);
+
+        // extent is left -3 right 14 up 14 down 0 with 2 units of padding
+        assert!(svg.contains(r##"viewBox="-5.00 -16.00 21.00 18.00""##));
+        assert!(svg.contains(r##"<line class="hurtbox" x1="1.00" y1="-5.00" x2="1.00" y2="-9.00" stroke="#ffff00" stroke-opacity="0.3" stroke-width="6.00" stroke-linecap="round"/>"##));
+        assert!(svg.contains(r##"stroke="#0000ff""##));
+        assert!(svg.contains(r##"<circle class="hitbox" data-id="0" cx="7.00" cy="-8.00" r="4.00" fill="#ef6400" fill-opacity="0.3"/>"##));
+        assert!(svg.contains(r##"data-id="1" cx="11.00" cy="-8.00" r="3.00" fill="#ff0000""##));
+        assert!(svg.contains(r##"points="1.00,-14.00 4.00,-7.00 1.00,0.00 -2.00,-7.00""##));
+
+        // output is deterministic
+        assert_eq!(svg, render_high_level_frame(&subaction(), 2));
+    }
+
+    #[test]
+    fn strip_structure() {
+        let svg = render_high_level_strip(&subaction(), 2);
+        assert_eq!(svg.matches("<g class=\"frame\"").count(), 3);
+        assert_eq!(svg.matches("<line class=\"hurtbox\"").count(), 6);
+        assert_eq!(svg.matches("<circle class=\"hitbox\"").count(), 2);
+        assert_eq!(svg.matches("<polygon class=\"ecb\"").count(), 3);
+        assert_eq!(svg.matches("<text").count(), 3);
+        assert!(svg.contains(r##"viewBox="-5.00 -16.00 63.00 18.00""##));
+        assert!(svg.contains(r##"<g class="frame" data-frame="0" transform="translate(0.00 0)">"##));
+        assert!(svg.contains(r##"<g class="frame" data-frame="2" transform="translate(21.00 0)">"##));
+        assert!(svg.contains(r##"<g class="frame" data-frame="4" transform="translate(42.00 0)">"##));
+    }
+
+    #[test]
+    fn interpolated_capsule() {
+        let mut subaction = subaction();
+        let hit_box = &mut subaction.frames[3].hit_boxes[0];
+        hit_box.interpolate = true;
+        hit_box.prev_pos = Some(Point3::new(0.0, 8.0, 8.0));
+        hit_box.prev_size = Some(3.0);
+
+        let svg = render_high_level_frame(&subaction, 3);
+        assert_eq!(svg.matches("<circle class=\"hitbox\"").count(), 0);
+        assert!(svg.contains(r##"<line class="hitbox" data-id="1" x1="9.00" y1="-8.00" x2="11.00" y2="-8.00" stroke="#ff0000" stroke-opacity="0.3" stroke-width="6.00" stroke-linecap="round"/>"##));
+    }
+
+    #[test]
+    fn missing_frame() {
+        let svg = render_high_level_frame(&subaction(), 5);
+        assert_eq!(svg.matches("<g").count(), 0);
+        assert!(svg.ends_with("</svg>\n"));
+    }
+}
@@ -0,0 +1,308 @@
+//! A C ABI for using the parser from other languages, enabled by the `ffi` feature.
+//!
+//! Build a shared library with `cargo rustc --release --features ffi --lib --crate-type cdylib` and include `include/brawllib.h`.
+//! The header is generated from this module with `cbindgen --config cbindgen.toml --output include/brawllib.h`, regenerate it whenever a function here changes.
+//! `ffi/ffi_test.c` is a small C program using every function, see the comment at its top for how to run it.
+//!
+//! Only handles, counts and error codes are passed directly, everything else crosses the boundary as a null terminated UTF-8 JSON string.
+//! The JSON has the same layout as the serde serialization of the corresponding rust type.
+//!
+//! # Memory ownership
+//!
+//! *   Strings and bytes passed to the library are only borrowed for the duration of the call, the caller keeps ownership.
+//! *   Strings returned by the library are owned by the caller and must be freed with `brawllib_free_string`, never with `free`.
+//! *   A `BrawllibFighters` handle is owned by the caller and must be freed with `brawllib_free`.
+//!     Strings created from a handle do not borrow from it, they stay valid after the handle is freed.
+//! *   The string returned by `brawllib_last_error_message` is owned by the library.
+//!     It stays valid until the next call into the library on the same thread and must not be freed.
+//!
+//! # Errors
+//!
+//! Functions that can fail return a `BrawllibError` and store a description of the failure for `brawllib_last_error_message`.
+//! Functions that return a string instead return null on failure.
+//! Panics are caught before they reach the caller and reported as `BrawllibError::Panic`.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+use crate::brawl_mod::{BrawlMod, LoadConfig};
+use crate::fighter::Fighter;
+use crate::high_level_fighter::HighLevelFighter;
+use crate::wiird;
+
+/// The result of a call into the library
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BrawllibError {
+    Ok = 0,
+    /// A pointer argument that must not be null was null
+    NullArgument = 1,
+    /// A string argument was not valid UTF-8
+    InvalidUtf8 = 2,
+    /// The fighters could not be loaded e.g. the brawl path does not exist
+    LoadFailed = 3,
+    /// The index is not less than `brawllib_fighter_count`
+    IndexOutOfRange = 4,
+    /// The data could not be parsed or serialized
+    ParseFailed = 5,
+    /// The library panicked, this is a bug in the library
+    Panic = 6,
+}
+
+/// The fighters loaded by `brawllib_load_fighters`, opaque to C.
+pub struct BrawllibFighters {
+    fighters: Vec<Fighter>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    // interior null bytes would truncate the message, so replace them
+    let message = CString::new(message.replace('\0', "\\0")).unwrap();
+    LAST_ERROR.with(|x| *x.borrow_mut() = Some(message));
+}
+
+/// Runs `f` and converts its error or panic into a `BrawllibError` and the last error message
+fn catch_error(f: impl FnOnce() -> Result<(), (BrawllibError, String)>) -> BrawllibError {
+    LAST_ERROR.with(|x| *x.borrow_mut() = None);
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => BrawllibError::Ok,
+        Ok(Err((error, message))) => {
+            set_last_error(message);
+            error
+        }
+        Err(payload) => {
+            let message = payload.downcast_ref::<&str>().map(|x| x.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| String::from("unknown error"));
+            set_last_error(format!("panicked: {}", message));
+            BrawllibError::Panic
+        }
+    }
+}
+
+unsafe fn path_arg<'a>(name: &str, path: *const c_char) -> Result<&'a Path, (BrawllibError, String)> {
+    if path.is_null() {
+        return Err((BrawllibError::NullArgument, format!("{} is null", name)));
+    }
+    CStr::from_ptr(path).to_str()
+        .map(Path::new)
+        .map_err(|_| (BrawllibError::InvalidUtf8, format!("{} is not valid UTF-8", name)))
+}
+
+fn json_string<T: serde::Serialize>(value: &T) -> Result<*mut c_char, (BrawllibError, String)> {
+    let json = serde_json::to_string(value)
+        .map_err(|err| (BrawllibError::ParseFailed, format!("Failed to serialize to JSON: {}", err)))?;
+    // JSON escapes control characters, so it never contains a null byte
+    Ok(CString::new(json).unwrap().into_raw())
+}
+
+/// Loads every fighter from the brawl dump at `brawl_path` and the optional mod sd card at `mod_path`, see `BrawlMod::load_fighters_with_config`.
+/// `mod_path` may be null to load vanilla brawl.
+/// On success `*out_handle` is set to a handle that must be freed with `brawllib_free`, otherwise it is set to null.
+///
+/// # Safety
+///
+/// `brawl_path` and `mod_path` must be null or point to null terminated strings.
+/// `out_handle` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn brawllib_load_fighters(brawl_path: *const c_char, mod_path: *const c_char, out_handle: *mut *mut BrawllibFighters) -> BrawllibError {
+    catch_error(|| {
+        if out_handle.is_null() {
+            return Err((BrawllibError::NullArgument, String::from("out_handle is null")));
+        }
+        *out_handle = ptr::null_mut();
+
+        let brawl_path = path_arg("brawl_path", brawl_path)?;
+        let mod_path = if mod_path.is_null() { None } else { Some(path_arg("mod_path", mod_path)?) };
+        let result = BrawlMod::new(brawl_path, mod_path).load_fighters_with_config(&LoadConfig::default())
+            .map_err(|err| (BrawllibError::LoadFailed, err.to_string()))?;
+
+        *out_handle = Box::into_raw(Box::new(BrawllibFighters { fighters: result.fighters }));
+        Ok(())
+    })
+}
+
+/// Returns the number of fighters in the handle, 0 if `handle` is null.
+///
+/// # Safety
+///
+/// `handle` must be null or a handle returned by `brawllib_load_fighters` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn brawllib_fighter_count(handle: *const BrawllibFighters) -> usize {
+    match handle.as_ref() {
+        Some(handle) => handle.fighters.len(),
+        None         => 0,
+    }
+}
+
+/// Sets `*out_buf` to the `HighLevelFighter` of the fighter at `index` as JSON, the string must be freed with `brawllib_free_string`.
+/// On failure `*out_buf` is set to null.
+///
+/// # Safety
+///
+/// `handle` must be null or a handle returned by `brawllib_load_fighters` that has not been freed.
+/// `out_buf` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn brawllib_fighter_to_json(handle: *const BrawllibFighters, index: usize, out_buf: *mut *mut c_char) -> BrawllibError {
+    catch_error(|| {
+        if out_buf.is_null() {
+            return Err((BrawllibError::NullArgument, String::from("out_buf is null")));
+        }
+        *out_buf = ptr::null_mut();
+
+        let handle = handle.as_ref()
+            .ok_or_else(|| (BrawllibError::NullArgument, String::from("handle is null")))?;
+        let fighter = handle.fighters.get(index)
+            .ok_or_else(|| (BrawllibError::IndexOutOfRange, format!("index {} is out of range for {} fighters", index, handle.fighters.len())))?;
+
+        *out_buf = json_string(&HighLevelFighter::new(fighter))?;
+        Ok(())
+    })
+}
+
+/// Parses the contents of a gct codeset file and returns its codes as JSON, see `wiird::WiiRDBlock`.
+/// Returns null on failure, the string must be freed with `brawllib_free_string`.
+///
+/// # Safety
+///
+/// `bytes` must be valid for reads of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn brawllib_parse_gct(bytes: *const u8, len: usize) -> *mut c_char {
+    let mut json = ptr::null_mut();
+    catch_error(|| {
+        if bytes.is_null() {
+            return Err((BrawllibError::NullArgument, String::from("bytes is null")));
+        }
+        let data = slice::from_raw_parts(bytes, len);
+        if data.len() < 8 {
+            return Err((BrawllibError::ParseFailed, String::from("Not a WiiRD gct codeset: The data is less than 8 bytes")));
+        }
+
+        json = json_string(&wiird::wiird_codes(&data[8..]))?; // Skip the header
+        Ok(())
+    });
+    json
+}
+
+/// Returns a description of the last failure on this thread, or null if the last call succeeded.
+/// The string is owned by the library and is valid until the next call into the library on this thread.
+#[no_mangle]
+pub extern "C" fn brawllib_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|x| x.borrow().as_ref().map(|x| x.as_ptr()).unwrap_or(ptr::null()))
+}
+
+/// Frees a handle returned by `brawllib_load_fighters`, does nothing if `handle` is null.
+///
+/// # Safety
+///
+/// `handle` must be null or a handle returned by `brawllib_load_fighters` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn brawllib_free(handle: *mut BrawllibFighters) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Frees a string returned by the library, does nothing if `string` is null.
+///
+/// # Safety
+///
+/// `string` must be null or a string returned by the library that has not been freed.
+/// Must not be called on the string returned by `brawllib_last_error_message`.
+#[no_mangle]
+pub unsafe extern "C" fn brawllib_free_string(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn last_error() -> Option<String> {
+        let message = brawllib_last_error_message();
+        if message.is_null() {
+            None
+        } else {
+            Some(unsafe { CStr::from_ptr(message) }.to_str().unwrap().to_string())
+        }
+    }
+
+    #[test]
+    fn parse_gct() {
+        let gct = [
+            0x00, 0xD0, 0xC0, 0xDE, 0x00, 0xD0, 0xC0, 0xDE, // header
+            0x04, 0x00, 0x12, 0x34, 0x00, 0x00, 0x00, 0x01, // 32 bit write
+            0xF0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // end of codes
+        ];
+        unsafe {
+            let json = brawllib_parse_gct(gct.as_ptr(), gct.len());
+            assert!(!json.is_null());
+            assert_eq!(last_error(), None);
+            let value: serde_json::Value = serde_json::from_str(CStr::from_ptr(json).to_str().unwrap()).unwrap();
+            assert_eq!(value["codes"].as_array().unwrap().len(), 1);
+            brawllib_free_string(json);
+
+            assert!(brawllib_parse_gct(gct.as_ptr(), 4).is_null());
+            assert!(last_error().unwrap().contains("less than 8 bytes"));
+            assert!(brawllib_parse_gct(ptr::null(), 0).is_null());
+            assert_eq!(last_error().unwrap(), "bytes is null");
+        }
+    }
+
+    #[test]
+    fn load_errors() {
+        let brawl_path = CString::new("/path/that/does/not/exist").unwrap();
+        unsafe {
+            let mut handle = ptr::null_mut();
+            assert_eq!(brawllib_load_fighters(brawl_path.as_ptr(), ptr::null(), &mut handle), BrawllibError::LoadFailed);
+            assert!(handle.is_null());
+            assert!(last_error().is_some());
+
+            assert_eq!(brawllib_load_fighters(ptr::null(), ptr::null(), &mut handle), BrawllibError::NullArgument);
+            assert_eq!(last_error().unwrap(), "brawl_path is null");
+            assert_eq!(brawllib_load_fighters(brawl_path.as_ptr(), ptr::null(), ptr::null_mut()), BrawllibError::NullArgument);
+
+            let invalid = [0xFF_u8, 0x00];
+            assert_eq!(brawllib_load_fighters(invalid.as_ptr() as *const c_char, ptr::null(), &mut handle), BrawllibError::InvalidUtf8);
+
+            // null handles are treated as empty
+            assert_eq!(brawllib_fighter_count(ptr::null()), 0);
+            brawllib_free(ptr::null_mut());
+            brawllib_free_string(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn fighter_json() {
+        let fighter = crate::test_util::TestFighterBuilder::new("Test").subaction("Wait1", |s| s).build();
+        let handle = Box::into_raw(Box::new(BrawllibFighters { fighters: vec!(fighter) }));
+        unsafe {
+            assert_eq!(brawllib_fighter_count(handle), 1);
+
+            let mut json = ptr::null_mut();
+            assert_eq!(brawllib_fighter_to_json(handle, 0, &mut json), BrawllibError::Ok);
+            assert!(!json.is_null());
+
+            let mut out_of_range = ptr::null_mut();
+            assert_eq!(brawllib_fighter_to_json(handle, 1, &mut out_of_range), BrawllibError::IndexOutOfRange);
+            assert!(out_of_range.is_null());
+            assert_eq!(last_error().unwrap(), "index 1 is out of range for 1 fighters");
+
+            // the json is still valid after freeing the handle
+            brawllib_free(handle);
+            let value: serde_json::Value = serde_json::from_str(CStr::from_ptr(json).to_str().unwrap()).unwrap();
+            assert_eq!(value["internal_name"], "Test");
+            brawllib_free_string(json);
+        }
+    }
+}
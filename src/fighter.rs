@@ -1,24 +1,50 @@
 use std::collections::HashMap;
-use std::fs::{File, ReadDir};
+use std::fmt;
+use std::fs::ReadDir;
 use std::fs;
-use std::io::Read;
-use std::path::Path;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync;
 
+use cgmath::Vector3;
 use rayon::prelude::*;
+use failure::Error;
 
+use crate::address::RamAddress;
+use crate::aerial_data::AerialData;
+use crate::analysis::{self, ScriptCycle, StateVariable};
 use crate::arc::{Arc, ArcChildData};
 use crate::arc;
 use crate::bres::BresChildData;
 use crate::chr0::Chr0;
+use crate::code_modifications::{ModifiedEvent, ScriptRef};
+use crate::content_hash;
+use crate::compression;
+use crate::grab_data::GrabData;
+use crate::high_level_fighter::{HighLevelFighter, get_bone_index};
+use crate::item_overrides::ItemOverrides;
+use crate::kit_summary::KitSummary;
+use crate::ledge_options::{self, LedgeOption};
 use crate::mdl0::bones::Bone;
-use crate::sakurai::fighter_data::ArcFighterData;
-use crate::sakurai::fighter_data_common::ArcFighterDataCommon;
-use crate::sakurai::{SectionData, SectionScript, ArcSakurai};
+use crate::file_provider::FileProvider;
+use crate::provenance::{self, PathFormatter, Provenance};
+use crate::sakurai::fighter_data::{ArcFighterData, AnimationFlags, SubactionScripts};
+use crate::sakurai::fighter_data_common::{ArcFighterDataCommon, FormatRevision};
+use crate::cancel::{self, CancellationToken, Cancelled};
+use crate::script::{EventBudget, ScriptLimits};
+use crate::script_ast::{FloatValue, HitBoxArguments, HitBoxEffect, HitBoxSound, HitBoxSseType};
+use crate::sakurai::{SectionData, SectionScript, ArcSakurai, ParseWarning, MemorySection};
+use crate::slot_metadata::SlotMetadata;
+use crate::subaction_category::SubactionCategory;
+use crate::vis0::{Vis0, VisibilityTrack};
 use crate::wii_memory::WiiMemory;
+use crate::wiird::{self, CodePath};
+use crate::wiird_runner;
 
 use fancy_slice::FancySlice;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct WiiRDFrameSpeedModifier {
     pub action: bool,
     pub action_subaction_id: u16,
@@ -27,25 +53,249 @@ pub struct WiiRDFrameSpeedModifier {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Fighter {
     pub cased_name: String,
-    pub moveset_common: Arc,
+    /// The parsed Fighter.pac, shared between all fighters
+    pub moveset_common: sync::Arc<Arc>,
     pub moveset: Arc,
     pub motion: Arc,
+    /// The parsed Fit{}Final.pac, None when the fighter does not have one.
+    /// Holds the resources used during the final smash, use `Fighter::final_pac_scripts` to get its scripts.
+    pub final_pac: Option<Arc>,
     pub models: Vec<Arc>,
     pub kirby_hats: Vec<KirbyHat>,
     // TODO: Is there any reason to keep this now I can `mod_type`, any mods are going to be done by psa anyway...
     pub modded_by_psa: bool,
     pub mod_type: ModType,
     pub wiird_frame_speed_modifiers: Vec<WiiRDFrameSpeedModifier>,
+    /// Where each of the fighters files was loaded from and the version of brawllib_rs that parsed them.
+    pub provenance: Provenance,
+    /// The fighter that is controlled alongside this fighter e.g. Nana for Popo
+    pub follower: Option<Box<FollowerData>>,
+    /// The moveset the fighter transforms into during its final smash e.g. WarioMan for Wario, see `TRANSFORMATIONS`.
+    /// None when the fighter has no transformation or it was loaded standalone via `LoadConfig::standalone_transformations`.
+    pub transformation: Option<Box<Fighter>>,
+    /// Events of the common Fighter.pac scripts written to by the WiiRD codeset.
+    /// Only filled in when enabled by `LoadConfig::track_code_modifications`.
+    pub(crate) modified_events: Vec<ModifiedEvent>,
+    /// Replaced with the values from the mods BrawlEx configs when loaded via `BrawlMod`
+    pub(crate) slot_metadata: Option<SlotMetadata>,
+    pub(crate) load_detail: LoadDetail,
 }
 
+/// A fighter that is loaded as part of its leader rather than as a standalone fighter.
+/// The follower has its own moveset, attributes and models, but uses the leaders motion file if it doesnt have one.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
+pub struct FollowerData {
+    /// The cased_name of the leader
+    pub leader: String,
+    pub fighter: Fighter,
+}
+
+/// (leader, follower) pairs of cased names.
+/// A mod that reuses the follower system keeps these folder names.
+const FOLLOWERS: &[(&str, &str)] = &[("Popo", "Nana")];
+
+/// (base, transformation) pairs of cased names, for fighters whose final smash swaps in the moveset of another fighter folder.
+/// Samus and ZeroSuitSamus are not included as both are selectable fighters, they are transformations of each other.
+const TRANSFORMATIONS: &[(&str, &str)] = &[("Wario", "WarioMan"), ("Koopa", "GKoopa")];
+
+/// Records which file was used for a role and whether it came from the mod or from vanilla brawl.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct FileProvenance {
+    pub role: FileRole,
+    pub path: PathBuf,
+    pub from_mod: bool,
+    /// Hex encoded SHA-1 of the file as read from disk
+    pub sha1: String,
+    /// The file is a .pcs file that was decompressed before parsing
+    pub compressed: bool,
+}
+
+/// The purpose of a file in a fighter folder.
+/// Each role is resolved independently, so e.g. a mod can replace just the motion file and still use the vanilla moveset.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum FileRole {
+    /// Fit{}.pac
+    Moveset,
+    /// Fit{}MotionEtc.pac or Fit{}Motion.pac
+    Motion,
+    /// Fit{}Entry.pac
+    Entry,
+    /// Fit{}Final.pac
+    Final,
+    /// Fit{}00.pac, Fit{}01.pac, ... or the compressed Fit{}00.pcs, Fit{}01.pcs, ...
+    Costume (u8),
+}
+
+/// How much of each fighter is loaded.
+/// Files that are not needed are never read from disk, so loading only what is needed is much faster.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum LoadDetail {
+    /// Only the moveset, the motion and model files are not read.
+    /// Enough for anything that only needs scripts and attributes.
+    ScriptsOnly,
+    /// The moveset and motion files, the model files are not read.
+    ScriptsAndAnimations,
+    /// Every file.
+    /// If single_model is true then only the first model of the fighter and of each kirby hat is parsed.
+    Full { single_model: bool },
+}
+
+impl Default for LoadDetail {
+    fn default() -> LoadDetail {
+        LoadDetail::Full { single_model: false }
+    }
+}
+
+impl LoadDetail {
+    pub fn has_animations(&self) -> bool {
+        match self {
+            LoadDetail::ScriptsOnly => false,
+            LoadDetail::ScriptsAndAnimations | LoadDetail::Full { .. } => true,
+        }
+    }
+
+    pub fn has_models(&self) -> bool {
+        match self {
+            LoadDetail::ScriptsOnly | LoadDetail::ScriptsAndAnimations => false,
+            LoadDetail::Full { .. } => true,
+        }
+    }
+
+    fn single_model(&self) -> bool {
+        match self {
+            LoadDetail::Full { single_model } => *single_model,
+            _ => false,
+        }
+    }
+
+    /// Whether the file needs to be read from disk.
+    /// Files are identified by name: Fit{}Motion.pac and Fit{}MotionEtc.pac are animations and files ending in two digits e.g. Fit{}00.pac or Fit{}00.pcs are models.
+    fn reads_file(&self, file_name: &str) -> bool {
+        let file_name = file_name.to_lowercase();
+        let stem = file_name.trim_end_matches(".pac").trim_end_matches(".pcs");
+        if stem.ends_with("motion") || stem.ends_with("motionetc") {
+            self.has_animations()
+        } else if stem.starts_with("fit") && stem.len() > 2 && stem[stem.len() - 2..].chars().all(|x| x.is_ascii_digit()) {
+            self.has_models()
+        } else {
+            true
+        }
+    }
+}
+
+/// Returned when accessing data of a fighter that was excluded by its `LoadDetail`.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum NotLoaded {
+    Animations,
+    Models,
+}
+
+impl fmt::Display for NotLoaded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NotLoaded::Animations => write!(f, "The fighters animations were not loaded, load it with LoadDetail::ScriptsAndAnimations or LoadDetail::Full"),
+            NotLoaded::Models     => write!(f, "The fighters models were not loaded, load it with LoadDetail::Full"),
+        }
+    }
+}
+
+impl std::error::Error for NotLoaded { }
+
+/// Returned when a bone could not be found by its name.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BoneNameError {
+    NotLoaded (NotLoaded),
+    /// The fighter has no model to take the bones from
+    NoModel,
+    UnknownBone (String),
+}
+
+impl fmt::Display for BoneNameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BoneNameError::NotLoaded (not_loaded) => write!(f, "{}", not_loaded),
+            BoneNameError::NoModel                => write!(f, "The fighter has no model"),
+            BoneNameError::UnknownBone (name)     => write!(f, "The fighters model has no bone named {}", name),
+        }
+    }
+}
+
+impl std::error::Error for BoneNameError { }
+
+/// The outcome of loading every fighter folder.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
+pub struct FighterLoadResult {
+    pub fighters: Vec<Fighter>,
+    /// Fighters that were found but could not be loaded
+    pub failures: Vec<FighterFailure>,
+    /// Folders that were intentionally not loaded e.g. folders without a Fit{}.pac file or known unsupported fighters
+    pub skipped: Vec<String>,
+    /// Where the common Fighter.pac was loaded from.
+    /// None when loaded via `Fighter::load_detailed` as it is given an already parsed Fighter.pac
+    pub common_fighter: Option<CommonFighterProvenance>,
+}
+
+impl FighterLoadResult {
+    /// Combines the `Fighter::content_hash` of every loaded fighter, independent of the order of `fighters`.
+    /// Failures and skipped folders are not hashed.
+    pub fn roster_hash(&self) -> u64 {
+        content_hash::roster_hash(&self.fighters)
+    }
+}
+
+/// Records which Fighter.pac was used and whether the WiiRD codeset was applied to it.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct CommonFighterProvenance {
+    pub path: PathBuf,
+    pub from_mod: bool,
+    pub codes_applied: bool,
+    /// Hex encoded SHA-1 of the file as read from disk, before the codeset is applied
+    pub sha1: String,
+    /// The format revision detected from the dataCommon header.
+    /// None when the file has no dataCommon section, an unknown revision is recorded as `FormatRevision::Unknown` and reported as a `ParseWarning`.
+    pub revision: Option<FormatRevision>,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
+pub struct FighterFailure {
+    pub folder: String,
+    pub error: String,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct KirbyHat {
     pub moveset: Arc,
     pub models: Vec<Arc>,
 }
 
+/// How `Fighter::load_detailed_patched` loads the fighters
+#[derive(Clone, Copy)]
+pub(crate) struct PatchedLoadOptions<'a> {
+    pub(crate) detail: LoadDetail,
+    /// Scripts are truncated when they reach these limits
+    pub(crate) limits: ScriptLimits,
+    /// The WiiRD codeset applied to the files in `patches` before they are parsed
+    pub(crate) codeset: &'a [u8],
+    /// The cased name of the fighter, the file to patch and the address in RAM the file is loaded at
+    pub(crate) patches: &'a [(String, FileRole, RamAddress)],
+    /// Load transformations as standalone fighters instead of attaching them to their base fighter
+    pub(crate) standalone_transformations: bool,
+    /// Formats the paths of the files read, when they are recorded and logged
+    pub(crate) paths: &'a PathFormatter,
+}
+
 impl Fighter {
     /// Call this function to get Fighter structs that correspond to each fighters folder in the 'fighter' directory
     ///
@@ -56,57 +306,137 @@ impl Fighter {
     /// If single_model is true then only one model for each fighter is loaded, otherwise all models are loaded.
     /// It's much faster to only process one model so set this to true if you only need one.
     pub fn load(brawl_fighter_dir: ReadDir, mod_fighter_dir: Option<ReadDir>, common_fighter: &Arc, wii_memory: &WiiMemory, single_model: bool) -> Vec<Fighter> {
+        Fighter::load_detailed(brawl_fighter_dir, mod_fighter_dir, common_fighter, wii_memory, single_model).fighters
+    }
+
+    /// Same as `Fighter::load` but also reports which fighter folders failed to load or were skipped.
+    pub fn load_detailed(brawl_fighter_dir: ReadDir, mod_fighter_dir: Option<ReadDir>, common_fighter: &Arc, wii_memory: &WiiMemory, single_model: bool) -> FighterLoadResult {
+        Fighter::load_with_detail(brawl_fighter_dir, mod_fighter_dir, common_fighter, wii_memory, LoadDetail::Full { single_model })
+    }
+
+    /// Same as `Fighter::load_detailed` but only the files needed for `detail` are read and parsed.
+    pub fn load_with_detail(brawl_fighter_dir: ReadDir, mod_fighter_dir: Option<ReadDir>, common_fighter: &Arc, wii_memory: &WiiMemory, detail: LoadDetail) -> FighterLoadResult {
+        let mod_folders = mod_fighter_dir.map(fighter_folders).unwrap_or_default();
+        let options = PatchedLoadOptions { detail, limits: ScriptLimits::default(), codeset: &[], patches: &[], standalone_transformations: false, paths: &PathFormatter::default() };
+        Fighter::load_detailed_patched(fighter_folders(brawl_fighter_dir), mod_folders, common_fighter, wii_memory, &options, None)
+    }
+
+    /// Same as `Fighter::load_with_detail` but the brawl and mod fighter folders are already listed, and the fighters are loaded as configured by `options`.
+    ///
+    /// Once `cancel` is cancelled no more files are read or parsed and every fighter that was not finished is reported as a failure.
+    pub(crate) fn load_detailed_patched(
+        brawl_folders: Vec<FighterFolder>, mod_folders: Vec<FighterFolder>, common_fighter: &Arc, wii_memory: &WiiMemory, options: &PatchedLoadOptions, cancel: Option<&CancellationToken>
+    ) -> FighterLoadResult {
+        let PatchedLoadOptions { detail, limits, codeset, patches, standalone_transformations, paths } = *options;
         // TODO: Could probably make this faster by beginning processing of a fighter_data immediately after it is read from disk.
         // However it might actually slow things down because all the threads are reading from disk at once.
         // Is there a way to stagger the threads so the next thread starts when the previous finishes reading from disk?
         // Will need to benchmark any such changes.
-        let fighter_datas = fighter_datas(brawl_fighter_dir, mod_fighter_dir);
-            fighter_datas.par_iter()
-                .filter_map(|x| Fighter::load_single(x, &fighter_datas, common_fighter, single_model, wii_memory))
-                .collect()
+        let (mut fighter_datas, skipped) = fighter_datas(brawl_folders, mod_folders, detail, paths, cancel);
+        for (cased_name, role, ram_base) in patches {
+            let fighter_data = fighter_datas.iter_mut().find(|x| x.cased_name.eq_ignore_ascii_case(cased_name));
+            let file_name = fighter_data.as_ref().and_then(|x| x.resolve(*role)).map(|x| x.to_string());
+            match (fighter_data, file_name) {
+                (Some(fighter_data), Some(file_name)) => {
+                    phase_span!("patch_file", fighter = %cased_name, file = %file_name);
+                    // Files can be shared between fighters, so only this fighters copy is patched
+                    let data = fighter_data.data.get_mut(&file_name).unwrap();
+                    wiird_runner::process(codeset, sync::Arc::make_mut(data).as_mut_slice(), *ram_base);
+                }
+                _ => error!("Cannot apply the WiiRD codeset to {:?} of {}, the file does not exist", role, cased_name),
+            }
+        }
+        // attached after patching, so patches can still find the transformation by name
+        if !standalone_transformations {
+            attach_transformations(&mut fighter_datas);
+        }
+        let common_fighter = sync::Arc::new(common_fighter.clone());
+        let results: Vec<_> = fighter_datas.par_iter()
+            .map(|x| (x, Fighter::load_single_catch_panic(x, &fighter_datas, &common_fighter, detail, limits, wii_memory, cancel)))
+            .collect();
+
+        let mut fighters = vec!();
+        let mut failures = vec!();
+        for (fighter_data, result) in results {
+            match result {
+                Ok((fighter, fighter_failures)) => {
+                    fighters.push(fighter);
+                    failures.extend(fighter_failures);
+                }
+                Err(error) => {
+                    error!("Failed to load {}, {}", fighter_data.cased_name, error);
+                    failures.push(FighterFailure { folder: fighter_data.cased_name.clone(), error });
+                }
+            }
+        }
+
+        FighterLoadResult { fighters, failures, skipped, common_fighter: None }
+    }
+
+    /// The parsers panic on malformed files, so a corrupted fighter is caught here instead of taking down every other fighter with it.
+    fn load_single_catch_panic(fighter_data: &FighterData, other_fighters: &[FighterData], common_fighter: &sync::Arc<Arc>, detail: LoadDetail, limits: ScriptLimits, wii_memory: &WiiMemory, cancel: Option<&CancellationToken>) -> Result<(Fighter, Vec<FighterFailure>), String> {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| Fighter::load_single(fighter_data, other_fighters, common_fighter, detail, limits, wii_memory, cancel)));
+        match result {
+            Ok(result) => result,
+            Err(payload) => {
+                let message = payload.downcast_ref::<&str>().map(|x| x.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| String::from("unknown error"));
+                Err(format!("panicked while parsing: {}", message))
+            }
+        }
     }
 
-    fn load_single(fighter_data: &FighterData, other_fighters: &[FighterData], common_fighter: &Arc, single_model: bool, wii_memory: &WiiMemory) -> Option<Fighter> {
+    /// `cancel` is checked before parsing each file
+    ///
+    /// A transformation that fails to load does not fail the fighter, it is instead left out and returned as a failure.
+    fn load_single(fighter_data: &FighterData, other_fighters: &[FighterData], common_fighter: &sync::Arc<Arc>, detail: LoadDetail, limits: ScriptLimits, wii_memory: &WiiMemory, cancel: Option<&CancellationToken>) -> Result<(Fighter, Vec<FighterFailure>), String> {
+        check_cancelled(cancel)?;
+        phase_span!("parse_fighter", fighter = %fighter_data.cased_name, bytes = fighter_data.data.values().map(|x| x.len()).sum::<usize>());
         info!("Parsing fighter: {}", fighter_data.cased_name);
+        let mut budget = EventBudget::new(limits);
         let moveset_file_name = format!("Fit{}.pac", fighter_data.cased_name);
-        let moveset = if let Some(data) = fighter_data.data.get(&moveset_file_name) {
+        let moveset = if let Some(data) = fighter_data.resolve(FileRole::Moveset).and_then(|x| fighter_data.data.get(x)) {
             let data = FancySlice::new(data);
-            arc::arc(data, wii_memory, false)
+            arc::arc_cancellable(data, wii_memory, &mut budget, false, cancel)
         } else {
-            error!("Failed to load {}, missing moveset file: {}", fighter_data.cased_name, moveset_file_name);
-            return None;
+            return Err(format!("missing moveset file: {}", moveset_file_name));
         };
 
-        let moveset_common = common_fighter.clone();
+        let moveset_common = sync::Arc::clone(common_fighter);
 
         let psa_sequence = [0xfa, 0xde, 0xf0, 0x0d];
         let modded_by_psa = fighter_data.data.get(&moveset_file_name)
             .map(|a| a.windows(4).any(|b| b == psa_sequence))
             .unwrap_or(false);
 
-        let motion_etc_file_name = format!("Fit{}MotionEtc.pac", fighter_data.cased_name);
-        let motion_file_name = format!("Fit{}Motion.pac", fighter_data.cased_name);
-        let motion = if let Some(data) = fighter_data.data.get(&motion_etc_file_name) {
+        // TODO: I'm going to need better abstractions here as I cant read the Fit{}Etc file
+        // Currently I dont need that file at all (What does it even contain?)
+        // But when I do, I'll need to rethink how I abstract characters with and without combined Motion + Etc
+        check_cancelled(cancel)?;
+        let motion = if !detail.has_animations() {
+            // named like a real motion file so it is handled the same as an empty motion file
+            Arc { name: format!("Fit{}Motion", fighter_data.cased_name), children: vec!() }
+        } else if let Some(data) = fighter_data.resolve(FileRole::Motion).and_then(|x| fighter_data.data.get(x)) {
             let data = FancySlice::new(data);
-            arc::arc(data, wii_memory, false)
+            arc::arc_cancellable(data, wii_memory, &mut budget, false, cancel)
         } else {
-            if let Some(data) = fighter_data.data.get(&motion_file_name) {
-                // TODO: I'm going to need better abstractions here as I cant read the Fit{}Etc file
-                // Currently I dont need that file at all (What does it even contain?)
-                // But when I do, I'll need to rethink how I abstract characters with and without combined Motion + Etc
-                let data = FancySlice::new(data);
-                arc::arc(data, wii_memory, false)
-            } else {
-                error!("Failed to load {}, Missing motion file: {}", fighter_data.cased_name, motion_etc_file_name);
-                return None;
-            }
+            return Err(format!("missing motion file: Fit{}MotionEtc.pac", fighter_data.cased_name));
         };
 
+        check_cancelled(cancel)?;
+        let final_pac = fighter_data.resolve(FileRole::Final).and_then(|x| fighter_data.data.get(x)).map(|data| {
+            let data = FancySlice::new(data);
+            arc::arc_cancellable(data, wii_memory, &mut budget, false, cancel)
+        });
+
+        let single_model = detail.single_model();
         let mut models = vec!();
         for i in 0..100 {
-            if let Some(model_data) = fighter_data.data.get(&format!("Fit{}{:02}.pac", fighter_data.cased_name, i)) {
+            check_cancelled(cancel)?;
+            if let Some(model_data) = fighter_data.resolve(FileRole::Costume (i)).and_then(|x| fighter_data.data.get(x)) {
                 let data = FancySlice::new(model_data);
-                models.push(arc::arc(data, wii_memory, false));
+                models.push(arc::arc_cancellable(data, wii_memory, &mut budget, false, cancel));
                 if single_model {
                     break;
                 }
@@ -118,16 +448,18 @@ impl Fighter {
 
         let mut kirby_hats = vec!();
         for other_fighter in other_fighters {
+            check_cancelled(cancel)?;
             if let Some(moveset_data) = fighter_data.data.get(&format!("FitKirby{}.pac", other_fighter.cased_name)) {
                 info!("Parsing kirby hat: {}", other_fighter.cased_name);
                 let moveset_data = FancySlice::new(moveset_data);
-                let moveset = arc::arc(moveset_data, wii_memory, true);
+                let moveset = arc::arc_cancellable(moveset_data, wii_memory, &mut budget, true, cancel);
 
                 let mut models = vec!();
                 for i in 0..100 {
+                    check_cancelled(cancel)?;
                     if let Some(model_data) = fighter_data.data.get(&format!("FitKirby{}{:02}.pac", other_fighter.cased_name, i)) {
                         let data = FancySlice::new(model_data);
-                        models.push(arc::arc(data, wii_memory, true));
+                        models.push(arc::arc_cancellable(data, wii_memory, &mut budget, true, cancel));
                         if single_model {
                             break;
                         }
@@ -150,7 +482,7 @@ impl Fighter {
 
         let mut wiird_frame_speed_modifiers = vec!();
         let mut fighter_byte = 1;
-        let mut offset = 0x80581000;
+        let mut offset = RamAddress::new(wiird::FRAME_SPEED_MODIFIER_TABLE);
         let required_fighter_id = crate::fighter_maps::fighter_id(&fighter_data.cased_name);
         while fighter_byte != 0 {
             fighter_byte = wii_memory.read_u8(offset);
@@ -166,17 +498,81 @@ impl Fighter {
             offset += 8;
         }
 
-        Some(Fighter {
+        let mut failures = vec!();
+        let follower = match &fighter_data.follower {
+            Some(follower_data) => {
+                info!("Parsing follower: {}", follower_data.cased_name);
+                match Fighter::load_single(follower_data, other_fighters, common_fighter, detail, limits, wii_memory, cancel) {
+                    Ok((fighter, follower_failures)) => {
+                        failures.extend(follower_failures);
+                        Some(Box::new(FollowerData { leader: fighter_data.cased_name.clone(), fighter }))
+                    }
+                    Err(err) => return Err(format!("failed to load follower {}, {}", follower_data.cased_name, err)),
+                }
+            }
+            None => None
+        };
+
+        let transformation = match &fighter_data.transformation {
+            Some(transformation_data) => {
+                info!("Parsing transformation: {}", transformation_data.cased_name);
+                match Fighter::load_single_catch_panic(transformation_data, other_fighters, common_fighter, detail, limits, wii_memory, cancel) {
+                    Ok((fighter, transformation_failures)) => {
+                        failures.extend(transformation_failures);
+                        Some(Box::new(fighter))
+                    }
+                    // the fighter is still usable without its final smash
+                    Err(error) => {
+                        check_cancelled(cancel)?;
+                        let error = format!("failed to load as the transformation of {}, {}", fighter_data.cased_name, error);
+                        error!("Failed to load {}, {}", transformation_data.cased_name, error);
+                        failures.push(FighterFailure { folder: transformation_data.cased_name.clone(), error });
+                        None
+                    }
+                }
+            }
+            None => None
+        };
+
+        // the arcs may be incomplete when cancelled while parsing them
+        check_cancelled(cancel)?;
+
+        let memory_sections = moveset.memory_sections();
+        let fighter = Fighter {
             cased_name: fighter_data.cased_name.clone(),
             moveset_common,
             moveset,
             motion,
+            final_pac,
             models,
             kirby_hats,
             modded_by_psa,
             mod_type,
             wiird_frame_speed_modifiers,
-        })
+            provenance: Provenance::new(fighter_data.provenance(), memory_sections),
+            follower,
+            transformation,
+            modified_events: vec!(),
+            slot_metadata: SlotMetadata::vanilla(&fighter_data.cased_name),
+            load_detail: detail,
+        };
+        Ok((fighter, failures))
+    }
+
+    /// Returns the events of the common Fighter.pac scripts that the WiiRD codeset writes to, along with the code that writes to it.
+    /// The event is an index into the `Script::events` of the script.
+    ///
+    /// Always empty unless the fighter was loaded with `LoadConfig::track_code_modifications` enabled.
+    pub fn gct_modified_events(&self) -> Vec<(ScriptRef, usize, CodePath)> {
+        self.modified_events.iter().map(|x| (x.script.clone(), x.event, x.code.clone())).collect()
+    }
+
+    /// Returns the victory theme, announcer call and series icon of the fighter.
+    ///
+    /// When loaded via `BrawlMod` the values come from the mods BrawlEx configs, see the `slot_metadata` module for how configs are matched to fighters.
+    /// Otherwise only the built in values for vanilla fighters are known, None for fighters that are not in vanilla brawl.
+    pub fn slot_metadata(&self) -> Option<&SlotMetadata> {
+        self.slot_metadata.as_ref()
     }
 
     /// retrieves the ArcSakurai
@@ -192,6 +588,26 @@ impl Fighter {
         None
     }
 
+    pub(crate) fn get_fighter_sakurai_mut(&mut self) -> Option<&mut ArcSakurai> {
+        self.moveset.children.iter_mut().find_map(|sub_arc| match &mut sub_arc.data {
+            ArcChildData::Sakurai (sakurai) => Some(sakurai),
+            _ => None,
+        })
+    }
+
+    /// retrieves the problems that were worked around while parsing the fighters moveset
+    pub fn parse_warnings(&self) -> &[ParseWarning] {
+        self.get_fighter_sakurai().map(|x| x.warnings.as_slice()).unwrap_or(&[])
+    }
+
+    /// Lists every script of the fighters moveset that a WiiRD code redirected to RAM, along with the code that wrote it.
+    /// These scripts are what the codeset injected into the fighter, they are not in any of the fighters files.
+    ///
+    /// The common Fighter.pac scripts are shared by all fighters so are not included.
+    pub fn memory_sections(&self) -> &[MemorySection] {
+        &self.provenance.memory_sections
+    }
+
     /// retrieves the common ArcSakurai
     pub fn get_fighter_sakurai_common(&self) -> Option<&ArcSakurai> {
         for sub_arc in &self.moveset_common.children {
@@ -205,6 +621,146 @@ impl Fighter {
         None
     }
 
+    /// Returns the index of the subaction named `name`, or None if there is no such subaction.
+    /// Modded movesets can contain multiple subactions with the same name, in which case the lowest index is returned
+    /// and a warning is included in `Fighter::parse_warnings`. Use `Fighter::subactions_by_name` to get every match.
+    pub fn subaction_by_name(&self, name: &str) -> Option<usize> {
+        self.get_fighter_data()?.subaction_flags.iter().position(|x| x.name == name)
+    }
+
+    /// Returns the indexes of every subaction named `name` in ascending order.
+    pub fn subactions_by_name(&self, name: &str) -> Vec<usize> {
+        match self.get_fighter_data() {
+            Some(fighter_data) => fighter_data.subaction_flags.iter().enumerate()
+                .filter(|(_, x)| x.name == name)
+                .map(|(i, _)| i)
+                .collect(),
+            None => vec!(),
+        }
+    }
+
+    /// Returns the category of the subaction at `index`, or None if there is no such subaction.
+    pub fn subaction_category(&self, index: usize) -> Option<SubactionCategory> {
+        self.get_fighter_data()
+            .and_then(|x| x.subaction_flags.get(index))
+            .map(|x| SubactionCategory::from_name(&x.name))
+    }
+
+    /// Returns the indexes of all subactions in the given category.
+    pub fn subactions_in_category(&self, category: SubactionCategory) -> Vec<usize> {
+        match self.get_fighter_data() {
+            Some(fighter_data) => fighter_data.subaction_flags.iter().enumerate()
+                .filter(|(_, x)| SubactionCategory::from_name(&x.name) == category)
+                .map(|(i, _)| i)
+                .collect(),
+            None => vec!(),
+        }
+    }
+
+    /// Returns the indexes of all attack subactions.
+    pub fn attack_subactions(&self) -> Vec<usize> {
+        self.subactions_in_category(SubactionCategory::Attack)
+    }
+
+    /// Returns the indexes of all subactions that can occur during a match, i.e. excluding entry and victory subactions.
+    pub fn gameplay_subactions(&self) -> Vec<usize> {
+        match self.get_fighter_data() {
+            Some(fighter_data) => fighter_data.subaction_flags.iter().enumerate()
+                .filter(|(_, x)| SubactionCategory::from_name(&x.name).is_gameplay())
+                .map(|(i, _)| i)
+                .collect(),
+            None => vec!(),
+        }
+    }
+
+    /// Returns the x/y displacement of the character on each frame of the named subaction, relative to its position at the start of the subaction.
+    /// The displacement comes from the TransN bone of the subactions animation and is only applied when the subaction has the MOVES_CHARACTER flag,
+    /// otherwise the TransN bone only moves the model and every frame has a displacement of 0.
+    /// Returns None if the subaction or its animation does not exist and an error if the fighter was loaded without animations.
+    pub fn subaction_movement(&self, name: &str) -> Result<Option<Vec<(f32, f32)>>, NotLoaded> {
+        let animations = self.get_animations()?;
+        let fighter_data = match self.get_fighter_data() {
+            Some(fighter_data) => fighter_data,
+            None => return Ok(None),
+        };
+        let subaction_flags = match fighter_data.subaction_flags.iter().find(|x| x.name == name) {
+            Some(subaction_flags) => subaction_flags,
+            None => return Ok(None),
+        };
+        let chr0 = match animations.into_iter().find(|x| x.name == name) {
+            Some(chr0) => chr0,
+            None => return Ok(None),
+        };
+
+        // Without models the bone name is unknown, but it is nearly always TransN anyway
+        let trans_n_index = fighter_data.misc.bone_refs.trans_n;
+        let trans_n_name = self.get_bones().ok().flatten()
+            .and_then(|x| x.find_by_index(trans_n_index))
+            .map(|x| x.name.as_str())
+            .unwrap_or("TransN");
+
+        let translations = chr0.bone_translations(trans_n_name)
+            .unwrap_or_else(|| vec!(Vector3::new(0.0, 0.0, 0.0); chr0.num_frames as usize));
+        Ok(Some(character_movement(&translations, subaction_flags.animation_flags, fighter_data.attributes.size)))
+    }
+
+    /// Returns the landing data of each aerial.
+    /// This generates a HighLevelFighter, use `HighLevelFighter::aerial_data` instead if you already have one.
+    pub fn aerial_data(&self, l_cancel: bool) -> Vec<AerialData> {
+        HighLevelFighter::new(self).aerial_data(l_cancel)
+    }
+
+    /// Returns the grab boxes, active frames and reach of the standing, dash and pivot grabs.
+    /// This generates a HighLevelFighter, use `HighLevelFighter::grab_data` instead if you already have one.
+    pub fn grab_data(&self) -> Vec<GrabData> {
+        HighLevelFighter::new(self).grab_data()
+    }
+
+    /// Returns the LongtermAccess variables the fighter uses to keep state between actions, most used first.
+    /// Refer to `analysis::state_variables` for details.
+    pub fn state_variables(&self) -> Vec<StateVariable> {
+        analysis::state_variables(self)
+    }
+
+    /// Returns the cycles of scripts that call each other, classified by whether they can freeze the game.
+    /// Refer to `analysis::script_cycles` for details.
+    pub fn script_cycles(&self) -> Vec<ScriptCycle> {
+        analysis::script_cycles(self)
+    }
+
+    /// Returns the total frames, invincibility and first actionable frame of each ledge get up, roll, attack and jump subaction.
+    /// The options are ordered get up, roll, attack and jump, quick before slow, subactions the fighter does not have are left out.
+    /// Refer to `LedgeOption` for how each value is determined.
+    /// Returns an error if the fighter was loaded without animations, as the animations determine the length of each option.
+    pub fn ledge_options(&self) -> Result<Vec<LedgeOption>, NotLoaded> {
+        let common_entry_actions = self.get_fighter_data_common().map(|x| x.entry_actions.as_slice()).unwrap_or(&[]);
+        ledge_options::ledge_options(self, common_entry_actions)
+    }
+
+    /// Returns a hash of the scripts, attributes and other gameplay data of the fighter that is the same across loads and machines.
+    /// Where the files were loaded from is not hashed, refer to the `content_hash` module for exactly what is hashed.
+    pub fn content_hash(&self) -> u64 {
+        content_hash::fighter_hash(self)
+    }
+
+    /// Returns the weight, speeds, jumps, specials, grab range and fastest and strongest moves of the fighter.
+    /// Refer to `KitSummary` for how each value is determined.
+    pub fn kit_summary(&self) -> KitSummary {
+        KitSummary::new(self)
+    }
+
+    /// retrieves the held item bones, item throw strength and the subactions used for item swings
+    pub fn item_overrides(&self) -> Option<ItemOverrides> {
+        let fighter_data = self.get_fighter_data()?;
+        let names: Vec<&str> = fighter_data.subaction_flags.iter().map(|x| x.name.as_str()).collect();
+        Some(ItemOverrides::new(fighter_data.misc.item_bones.as_ref(), fighter_data.attributes.item_throw_strength, &names))
+    }
+
+    /// retrieves the main, gfx, sfx and other scripts of the subaction at `index`
+    pub fn get_subaction_scripts(&self, index: usize) -> Option<SubactionScripts<'_>> {
+        self.get_fighter_data().and_then(|x| x.subaction_scripts(index))
+    }
+
     /// retrieves the fighter data
     pub fn get_fighter_data(&self) -> Option<&ArcFighterData> {
         for sub_arc in &self.moveset.children {
@@ -224,19 +780,7 @@ impl Fighter {
 
     /// retrieves the fighter data common
     pub fn get_fighter_data_common(&self) -> Option<&ArcFighterDataCommon> {
-        for sub_arc in &self.moveset_common.children {
-            match &sub_arc.data {
-                &ArcChildData::Sakurai (ref data) => {
-                    for section in &data.sections {
-                        if let &SectionData::FighterDataCommon (ref fighter_data_ref) = &section.data {
-                            return Some(fighter_data_ref);
-                        }
-                    }
-                }
-                _ => { }
-            }
-        }
-        None
+        self.moveset_common.fighter_data_common()
     }
 
     /// retrieves the script sections from fighter data common
@@ -257,8 +801,17 @@ impl Fighter {
         scripts
     }
 
-    /// retrieves the bones from a character model
-    pub fn get_bones(&self) -> Option<&Bone> {
+    /// Returns how much of the fighter was loaded
+    pub fn load_detail(&self) -> LoadDetail {
+        self.load_detail
+    }
+
+    /// retrieves the bones from a character model.
+    /// Returns an error if the fighter was loaded without models and None if it has no model or the model has no bones.
+    pub fn get_bones(&self) -> Result<Option<&Bone>, NotLoaded> {
+        if !self.load_detail.has_models() {
+            return Err(NotLoaded::Models);
+        }
         if let Some(model) = self.models.get(0) {
             for sub_arc in model.children.iter() {
                 match &sub_arc.data {
@@ -276,7 +829,7 @@ impl Fighter {
 
                                         match &model_child.data {
                                             &BresChildData::Mdl0 (ref model) => {
-                                                return model.bones.as_ref();
+                                                return Ok(model.bones.as_ref());
                                             }
                                             _ => { }
                                         }
@@ -293,25 +846,104 @@ impl Fighter {
                 }
             }
         }
-        None
+        Ok(None)
+    }
+
+    /// Returns the bone of the fighters model with the name.
+    /// The name is compared case-insensitively, but a bone with exactly the same name is preferred.
+    pub fn bone_by_name(&self, name: &str) -> Result<&Bone, BoneNameError> {
+        find_bone(self.get_bones(), name)
+    }
+
+    /// Returns the index of the named bone as used by hitbox events and other script arguments.
+    /// Bones in the fighter datas bone references (TransN, HaveN and ThrowN) take their index from there, so they are found even without models.
+    /// Any other bone is looked up in the model, see `Fighter::bone_by_name`.
+    /// Returns None if the bone does not exist or the fighters models were not loaded, use `Fighter::bone_by_name` to find out why.
+    pub fn bone_index_by_name(&self, name: &str) -> Option<u16> {
+        self.script_bone_index(name).ok().map(|x| x as u16)
     }
 
-    /// retrieves the animations for the character model
-    pub fn get_animations(&self) -> Vec<&Chr0> {
+    fn script_bone_index(&self, name: &str) -> Result<i32, BoneNameError> {
+        match self.bone_refs().into_iter().find(|(ref_name, _)| ref_name.eq_ignore_ascii_case(name)) {
+            Some((_, index)) => Ok(index),
+            None             => self.bone_by_name(name).map(|x| x.index),
+        }
+    }
+
+    /// Returns the name of the bone referred to by a script bone index.
+    /// The name is taken from the model, falling back to the bone references of the fighter data when the models were not loaded.
+    /// Returns None if the bone does not exist.
+    pub fn bone_name(&self, index: u16) -> Option<&str> {
+        let index = get_bone_index(index as i32);
+        match self.get_bones() {
+            Ok(Some(bones)) => bones.find_by_index(index).map(|x| x.name.as_str()),
+            _ => self.bone_refs().into_iter().find(|(_, ref_index)| *ref_index == index).map(|(name, _)| name),
+        }
+    }
+
+    /// The bones the fighter data refers to by index, along with the name the bone has in the model.
+    fn bone_refs(&self) -> Vec<(&'static str, i32)> {
+        let mut refs = vec!();
+        if let Some(fighter_data) = self.get_fighter_data() {
+            refs.push(("TransN", fighter_data.misc.bone_refs.trans_n));
+            if let Some(item_bones) = &fighter_data.misc.item_bones {
+                refs.push(("HaveN", item_bones.have_n_bone));
+                refs.push(("ThrowN", item_bones.throw_n_bone));
+            }
+        }
+        refs
+    }
+
+    /// retrieves the animations for the character model.
+    /// Returns an error if the fighter was loaded without animations.
+    pub fn get_animations(&self) -> Result<Vec<&Chr0>, NotLoaded> {
+        Ok(Fighter::get_animations_fit_motion(self.fit_motion()?))
+    }
+
+    /// Retrieves the visibility animations for the character model, each has the same name as the CHR0 animation it is played alongside.
+    pub fn get_visibility_animations(&self) -> Result<Vec<&Vis0>, NotLoaded> {
+        Ok(Fighter::get_visibility_animations_fit_motion(self.fit_motion()?))
+    }
+
+    /// Returns the visibility tracks of the named animation, or None if animations are not loaded or the animation has no visibility animation.
+    pub fn visibility_tracks(&self, animation: &str) -> Option<&[VisibilityTrack]> {
+        self.get_visibility_animations().ok()?
+            .into_iter()
+            .find(|x| x.name == animation)
+            .map(|x| x.tracks.as_slice())
+    }
+
+    /// Returns whether the objects attached to the bone are visible on the frame of the named animation.
+    /// Returns None if animations are not loaded, the animation has no visibility animation or the visibility animation does not contain the bone.
+    pub fn bone_visible(&self, animation: &str, bone: &str, frame: usize) -> Option<bool> {
+        self.get_visibility_animations().ok()?
+            .into_iter()
+            .find(|x| x.name == animation)?
+            .bone_visible(bone, frame)
+    }
+
+    /// Returns the Fit{}Motion arc containing the animations
+    fn fit_motion(&self) -> Result<&Arc, NotLoaded> {
+        if !self.load_detail.has_animations() {
+            return Err(NotLoaded::Animations);
+        }
+
         // When checking the arc names, the characters name cannot be included
         // because modded characters name them inconsistently.
 
         if self.motion.name.ends_with("Motion") {
-            return Fighter::get_animations_fit_motion(&self.motion);
+            return Ok(&self.motion);
         }
         else if self.motion.name.ends_with("MotionEtc") {
             for sub_arc in &self.motion.children {
                 match &sub_arc.data {
-                    &ArcChildData::Arc (ref arc) => {
+                    ArcChildData::Arc (arc) => {
                         if arc.name.ends_with("Motion") {
-                            return Fighter::get_animations_fit_motion(arc);
+                            return Ok(arc);
                         }
                     }
+                    // The target of a redirect is also a child of this arc, so it is checked directly.
+                    ArcChildData::Redirect { .. } => { }
                     _ => panic!("Only expecting Arc at this level"),
                 }
             }
@@ -355,111 +987,440 @@ impl Fighter {
         }
         chr0s
     }
+
+    /// retrieves the visibility animations for the character model from the Fit{}Motion arc
+    pub fn get_visibility_animations_fit_motion(motion: &Arc) -> Vec<&Vis0> {
+        let mut vis0s: Vec<&Vis0> = vec!();
+        for sub_arc in &motion.children {
+            if let ArcChildData::Bres (bres) = &sub_arc.data {
+                for bres_child in &bres.children {
+                    if let BresChildData::Bres (children) = &bres_child.data {
+                        for bres_child in children {
+                            if let BresChildData::Vis0 (vis0) = &bres_child.data {
+                                vis0s.push(vis0);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        vis0s
+    }
+}
+
+fn find_bone<'a>(bones: Result<Option<&'a Bone>, NotLoaded>, name: &str) -> Result<&'a Bone, BoneNameError> {
+    bones.map_err(BoneNameError::NotLoaded)?
+        .ok_or(BoneNameError::NoModel)?
+        .find_by_name(name)
+        .ok_or_else(|| BoneNameError::UnknownBone (name.to_string()))
+}
+
+impl HitBoxArguments {
+    /// Creates a hitbox attached to the named bone of the fighter, `offset` is relative to the bone.
+    /// The bone is looked up in the same way as `Fighter::bone_index_by_name`.
+    /// The remaining values are those of a plain hitbox that hits grounded and aerial opponents, change them as needed.
+    pub fn with_bone_name(fighter: &Fighter, name: &str, hitbox_id: u8, damage: f32, size: f32, offset: Vector3<f32>) -> Result<HitBoxArguments, BoneNameError> {
+        let bone_index = fighter.script_bone_index(name)?;
+        Ok(HitBoxArguments {
+            bone_index:    bone_index as i16,
+            hitbox_id,
+            set_id:        0,
+            damage:        FloatValue::Constant (damage),
+            trajectory:    361,
+            wdsk:          0,
+            kbg:           100,
+            shield_damage: 0,
+            bkb:           0,
+            size,
+            x_offset:      offset.x,
+            y_offset:      offset.y,
+            z_offset:      offset.z,
+            tripping_rate: 0.0,
+            hitlag_mult:   1.0,
+            sdi_mult:      1.0,
+            effect:        HitBoxEffect::Normal,
+            unk1:          false,
+            sound_level:   1,
+            unk2:          false,
+            sound:         HitBoxSound::Punch,
+            unk3:          0,
+            ground:        true,
+            aerial:        true,
+            unk4:          0,
+            sse_type:      HitBoxSseType::None,
+            clang:         true,
+            unk5:          false,
+            direct:        true,
+            unk6:          0,
+        })
+    }
 }
 
-/// Returns the binary fighter data for all fighters
+/// Converts per frame TransN translations into per frame game x/y displacements.
+/// The game x-axis is equivalent to the model z-axis.
+fn character_movement(translations: &[Vector3<f32>], animation_flags: AnimationFlags, size: f32) -> Vec<(f32, f32)> {
+    if animation_flags.contains(AnimationFlags::MOVES_CHARACTER) {
+        let start = translations.first().cloned().unwrap_or(Vector3::new(0.0, 0.0, 0.0));
+        translations.iter().map(|x| ((x.z - start.z) * size, (x.y - start.y) * size)).collect()
+    } else {
+        vec!((0.0, 0.0); translations.len())
+    }
+}
+
+fn check_cancelled(cancel: Option<&CancellationToken>) -> Result<(), String> {
+    if cancel::is_cancelled(cancel) {
+        Err(Cancelled.to_string())
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns the binary fighter data for all fighters and the names of the folders that were skipped
 /// Replaces brawl fighter data with mod fighter data
-fn fighter_datas(brawl_fighter_dir: ReadDir, mod_fighter_dir: Option<ReadDir>) -> Vec<FighterData> {
+/// Stops reading once `cancel` is cancelled.
+fn fighter_datas(brawl_folders: Vec<FighterFolder>, mod_folders: Vec<FighterFolder>, detail: LoadDetail, paths: &PathFormatter, cancel: Option<&CancellationToken>) -> (Vec<FighterData>, Vec<String>) {
     let mut fighter_datas = vec!();
-    for fighter_path in brawl_fighter_dir {
-        let fighter_path = fighter_path.unwrap();
-        let file_type = fighter_path.file_type().unwrap();
-        if file_type.is_dir() {
-            if let Some(mut fighter_data) = fighter_data(&fighter_path.path()) {
+    let mut skipped = vec!();
+    for folder in &brawl_folders {
+        if cancel::is_cancelled(cancel) {
+            return (fighter_datas, skipped);
+        }
+        match fighter_data(folder, detail, paths) {
+            Ok(mut fighter_data) => {
                 fighter_data.read_from_vanilla = true;
                 fighter_datas.push(fighter_data);
             }
+            Err(folder) => skipped.push(folder),
         }
     }
 
-    if let Some(mod_fighter_dir) = mod_fighter_dir {
-        for fighter_path in mod_fighter_dir {
-            let fighter_path = fighter_path.unwrap();
-            if fighter_path.file_type().unwrap().is_dir() {
-                let fighter_path = fighter_path.path();
-                let dir_name = fighter_path.file_name().unwrap().to_str().unwrap().to_string();
+    for folder in &mod_folders {
+        if cancel::is_cancelled(cancel) {
+            break;
+        }
+        let dir_name = &folder.dir_name;
+
+        if let Some(fighter_data) = fighter_datas.iter_mut().find(|x| x.cased_name.to_lowercase() == dir_name.to_lowercase()) {
+            // fighter data already exists, overwrite and insert new files
+            for file in &folder.files {
+                // Mod files dont always match the casing of the vanilla files, so reuse the vanilla name when there is one.
+                let mut file_name = file.name.clone();
+                if !detail.reads_file(&file_name) {
+                    continue;
+                }
+                let (file_data, sha1, compressed) = match read_fighter_file(file, paths) {
+                    Some(file) => file,
+                    None => continue,
+                };
+
+                if let Some(existing) = fighter_data.data.keys().find(|x| x.to_lowercase() == file_name.to_lowercase()) {
+                    file_name = existing.clone();
+                }
 
-                if let Some(fighter_data) = fighter_datas.iter_mut().find(|x| x.cased_name.to_lowercase() == dir_name.to_lowercase()) {
-                    // fighter data already exists, overwrite and insert new files
-                    for data_path in fs::read_dir(&fighter_path).unwrap() {
-                        let data_path = data_path.unwrap().path();
-                        let mut file_data: Vec<u8> = vec!();
-                        File::open(&data_path).unwrap().read_to_end(&mut file_data).unwrap();
-                        fighter_data.data.insert(data_path.file_name().unwrap().to_str().unwrap().to_string(), file_data);
-                        fighter_data.read_from_mod = true;
+                fighter_data.data.insert(file_name.clone(), sync::Arc::new(file_data));
+                fighter_data.sources.insert(file_name, Source { path: paths.path(&file.path), from_mod: true, sha1, compressed });
+                fighter_data.read_from_mod = true;
+            }
+        }
+        else {
+            // fighter data doesnt exist yet, create it
+            match fighter_data(folder, detail, paths) {
+                Ok(mut fighter_data) => {
+                    fighter_data.read_from_mod = true;
+                    for source in fighter_data.sources.values_mut() {
+                        source.from_mod = true;
                     }
+                    // A vanilla folder may have been skipped only because the mod provides its moveset
+                    skipped.retain(|x| x.to_lowercase() != dir_name.to_lowercase());
+                    fighter_datas.push(fighter_data);
                 }
-                else {
-                    // fighter data doesnt exist yet, create it
-                    if let Some(mut fighter_data) = fighter_data(&fighter_path) {
-                        fighter_data.read_from_mod = true;
-                        fighter_datas.push(fighter_data);
+                Err(folder) => {
+                    if !skipped.iter().any(|x| x.to_lowercase() == folder.to_lowercase()) {
+                        skipped.push(folder);
                     }
                 }
             }
         }
     }
 
-    // copy missing warioman file from wario
-    if let Some(Some(wario_motion_etc)) = fighter_datas.iter()
-        .find(|x| x.cased_name == "Wario")
-        .map(|x| x.data.get("FitWarioMotionEtc.pac").cloned())
-    {
-        for fighter_data in &mut fighter_datas {
-            if fighter_data.cased_name == "WarioMan" {
-                fighter_data.data.insert(String::from("FitWarioManMotionEtc.pac"), wario_motion_etc);
-                // Just assume wariomans read_from_* is unaffected by this copy :/
-                break;
+    share_transformation_motions(&mut fighter_datas);
+
+    // attach followers to their leader
+    for (leader, follower) in FOLLOWERS {
+        if let Some(follower_index) = fighter_datas.iter().position(|x| &x.cased_name == follower) {
+            if let Some(leader_index) = fighter_datas.iter().position(|x| &x.cased_name == leader) {
+                let mut follower_data = fighter_datas.remove(follower_index);
+                let leader_index = if leader_index > follower_index { leader_index - 1 } else { leader_index };
+                let leader_data = &mut fighter_datas[leader_index];
+
+                if follower_data.resolve(FileRole::Motion).is_none() {
+                    if let Some(motion) = leader_data.resolve(FileRole::Motion).map(|x| x.to_string()) {
+                        let motion_name = format!("Fit{}MotionEtc.pac", follower_data.cased_name);
+                        follower_data.data.insert(motion_name.clone(), leader_data.data[&motion].clone());
+                        follower_data.sources.insert(motion_name, leader_data.sources[&motion].clone());
+                    }
+                }
+
+                leader_data.follower = Some(Box::new(follower_data));
+            }
+        }
+    }
+
+    skipped.sort();
+    (fighter_datas, skipped)
+}
+
+/// A transformation without a motion file uses the motion file of its base, see `TRANSFORMATIONS`.
+/// Done whether or not the transformation is attached to its base, so a standalone transformation also has animations.
+fn share_transformation_motions(fighter_datas: &mut [FighterData]) {
+    for (base, transformation) in TRANSFORMATIONS {
+        let motion = fighter_datas.iter()
+            .find(|x| &x.cased_name == base)
+            .and_then(|x| x.resolve(FileRole::Motion).map(|motion| (x.data[motion].clone(), x.sources[motion].clone())));
+        if let Some((data, source)) = motion {
+            for transformation_data in fighter_datas.iter_mut().filter(|x| &x.cased_name == transformation) {
+                if transformation_data.resolve(FileRole::Motion).is_none() {
+                    let motion_name = format!("Fit{}MotionEtc.pac", transformation_data.cased_name);
+                    transformation_data.data.insert(motion_name.clone(), data.clone());
+                    transformation_data.sources.insert(motion_name, source.clone());
+                }
             }
         }
     }
+}
 
-    fighter_datas
+/// Moves each transformation in `fighter_datas` into its base fighter, see `TRANSFORMATIONS`.
+/// A fighter that already has a transformation is never attached to another fighter, so transformations are at most one level deep.
+fn attach_transformations(fighter_datas: &mut Vec<FighterData>) {
+    for (base, transformation) in TRANSFORMATIONS {
+        if let Some(transformation_index) = fighter_datas.iter().position(|x| &x.cased_name == transformation && x.transformation.is_none()) {
+            if let Some(base_index) = fighter_datas.iter().position(|x| &x.cased_name == base && x.transformation.is_none()) {
+                let transformation_data = fighter_datas.remove(transformation_index);
+                let base_index = if base_index > transformation_index { base_index - 1 } else { base_index };
+                fighter_datas[base_index].transformation = Some(Box::new(transformation_data));
+            }
+        }
+    }
 }
 
-/// Returns the binary fighter data for each file in the passed dir
-fn fighter_data(fighter_path: &Path) -> Option<FighterData> {
-    let dir_name = fighter_path.file_name().unwrap().to_str().unwrap().to_string();
+/// Returns the binary fighter data for each file in the passed folder
+/// Returns Err(dir_name) if the folder is skipped
+/// Files not needed for `detail` are not read.
+fn fighter_data(folder: &FighterFolder, detail: LoadDetail, paths: &PathFormatter) -> Result<FighterData, String> {
+    let dir_name = folder.dir_name.clone();
     let mut cased_name: Option<String> = None;
 
-    for data_path in fs::read_dir(&fighter_path).unwrap() {
-        let data_name = data_path.unwrap().path().file_name().unwrap().to_str().unwrap().to_string();
-        if data_name.to_lowercase() == format!("Fit{}.pac", dir_name).to_lowercase() {
-            cased_name = Some(String::from(data_name.trim_end_matches(".pac").trim_start_matches("Fit")));
+    for file in &folder.files {
+        if file.name.to_lowercase() == format!("Fit{}.pac", dir_name).to_lowercase() {
+            cased_name = Some(String::from(file.name.trim_end_matches(".pac").trim_start_matches("Fit")));
         }
     }
 
     if let Some(cased_name) = cased_name {
         if cased_name == "ZakoBoy" || cased_name == "ZakoGirl" || cased_name == "ZakoChild" || cased_name == "ZakoBall" {
             error!("Can't load: {} (unfixed bug)", cased_name);
-            None
+            Err(dir_name)
         } else {
             let mut data = HashMap::new();
-            for data_path in fs::read_dir(&fighter_path).unwrap() {
-                let data_path = data_path.unwrap().path();
-                let mut file_data: Vec<u8> = vec!();
-                File::open(&data_path).unwrap().read_to_end(&mut file_data).unwrap();
-                data.insert(data_path.file_name().unwrap().to_str().unwrap().to_string(), file_data);
+            let mut sources = HashMap::new();
+            for file in &folder.files {
+                if !detail.reads_file(&file.name) {
+                    continue;
+                }
+                let (file_data, sha1, compressed) = match read_fighter_file(file, paths) {
+                    Some(file) => file,
+                    None => continue,
+                };
+                data.insert(file.name.clone(), sync::Arc::new(file_data));
+                sources.insert(file.name.clone(), Source { path: paths.path(&file.path), from_mod: false, sha1, compressed });
             }
-            Some(FighterData {
+            Ok(FighterData {
                 cased_name,
                 data,
+                sources,
                 // These fields get set later
                 read_from_vanilla: false,
                 read_from_mod: false,
+                follower: None,
+                transformation: None,
             })
         }
-    } else { None }
+    } else { Err(dir_name) }
+}
+
+/// Reads a file from a fighter folder, returning its data, the SHA-1 of the file on disk and whether it was compressed.
+/// .pcs files are decompressed so they can be used the same as the .pac file they stand in for.
+/// Returns None when a .pcs file cannot be decompressed.
+fn read_fighter_file(file: &FolderFile, paths: &PathFormatter) -> Option<(Vec<u8>, String, bool)> {
+    let (data, sha1) = match file.data {
+        FolderFileData::Disk => match provenance::read_hashed(&file.path) {
+            Ok(read) => read,
+            Err(err) => {
+                error!("Cannot read {}, {}", paths.display(&file.path), err);
+                return None;
+            }
+        }
+        FolderFileData::Provider (files, ref path) => match files.read(path) {
+            Ok(data) => {
+                let sha1 = provenance::sha1(&data);
+                (data, sha1)
+            }
+            Err(err) => {
+                error!("Cannot read {}, {}", paths.display(&file.path), err);
+                return None;
+            }
+        }
+    };
+    if file.path.extension().map(|x| x.eq_ignore_ascii_case("pcs")).unwrap_or(false) {
+        match compression::decompress(&data) {
+            Ok(data) => Some((data, sha1, true)),
+            Err(err) => {
+                error!("Cannot read {}, {}", paths.display(&file.path), err);
+                None
+            }
+        }
+    } else {
+        Some((data, sha1, false))
+    }
+}
+
+/// A fighter folder of the brawl dump or of a mod
+pub(crate) struct FighterFolder<'a> {
+    dir_name: String,
+    files: Vec<FolderFile<'a>>,
+}
+
+/// A file in a `FighterFolder`
+struct FolderFile<'a> {
+    name: String,
+    /// Where the file is read from, see `FileProvider::source_path`
+    path: PathBuf,
+    data: FolderFileData<'a>,
+}
+
+/// Where the contents of a `FolderFile` are read from
+enum FolderFileData<'a> {
+    /// The file at `FolderFile::path` on disk
+    Disk,
+    /// The file at the contained path of the `FileProvider`, read when needed
+    Provider (&'a dyn FileProvider, String),
+}
+
+/// Lists the fighter folders in `dir`, the files are not read until they are needed
+pub(crate) fn fighter_folders(dir: ReadDir) -> Vec<FighterFolder<'static>> {
+    let mut folders = vec!();
+    for entry in dir {
+        let entry = entry.unwrap();
+        if entry.file_type().unwrap().is_dir() {
+            let path = entry.path();
+            let files = fs::read_dir(&path).unwrap().map(|x| {
+                let path = x.unwrap().path();
+                FolderFile { name: path.file_name().unwrap().to_str().unwrap().to_string(), path, data: FolderFileData::Disk }
+            }).collect();
+            folders.push(FighterFolder { dir_name: path.file_name().unwrap().to_str().unwrap().to_string(), files });
+        }
+    }
+    folders
+}
+
+/// Groups the files in the fighter folder of `files` into fighter folders, the files are not read until they are needed
+pub(crate) fn provider_fighter_folders(files: &dyn FileProvider) -> Result<Vec<FighterFolder<'_>>, Error> {
+    let relative_paths = files.files_in("fighter")?;
+    Ok(group_fighter_folders(relative_paths.iter().map(|relative| {
+        let path = format!("fighter/{}", relative);
+        let name = relative.rsplit('/').next().unwrap_or("").to_string();
+        (relative.as_str(), FolderFile { name, path: files.source_path(&path), data: FolderFileData::Provider (files, path) })
+    })))
+}
+
+/// Groups files by their path relative to the fighter folder into fighter folders
+fn group_fighter_folders<'a, 'b>(files: impl Iterator<Item=(&'b str, FolderFile<'a>)>) -> Vec<FighterFolder<'a>> {
+    let mut folders: Vec<FighterFolder> = vec!();
+    for (path, file) in files {
+        // files directly in the fighter folder e.g. Fighter.pac and files in subfolders of a fighter folder are not part of a fighter
+        let dir_name = match path.split('/').collect::<Vec<_>>()[..] {
+            [dir_name, _] => dir_name,
+            _ => continue,
+        };
+        match folders.iter_mut().find(|x| x.dir_name.eq_ignore_ascii_case(dir_name)) {
+            Some(folder) => folder.files.push(file),
+            None => folders.push(FighterFolder { dir_name: dir_name.to_string(), files: vec!(file) }),
+        }
+    }
+    folders
 }
 
 struct FighterData {
     cased_name: String,
-    data: HashMap<String, Vec<u8>>,
+    /// Files that are used by multiple fighters e.g. FitWarioMotionEtc.pac are shared instead of copied
+    data: HashMap<String, sync::Arc<Vec<u8>>>,
+    /// Where each file in `data` was read from
+    sources: HashMap<String, Source>,
     read_from_vanilla: bool,
     read_from_mod: bool,
+    follower: Option<Box<FighterData>>,
+    transformation: Option<Box<FighterData>>,
+}
+
+#[derive(Clone)]
+struct Source {
+    path: PathBuf,
+    from_mod: bool,
+    /// SHA-1 of the file on disk, before decompression
+    sha1: String,
+    compressed: bool,
+}
+
+impl FighterData {
+    /// Returns the file names that can fill the role, in order of preference
+    fn role_file_names(&self, role: FileRole) -> Vec<String> {
+        let name = &self.cased_name;
+        match role {
+            FileRole::Moveset     => vec!(format!("Fit{}.pac", name)),
+            FileRole::Motion      => vec!(format!("Fit{}MotionEtc.pac", name), format!("Fit{}Motion.pac", name)),
+            FileRole::Entry       => vec!(format!("Fit{}Entry.pac", name)),
+            FileRole::Final       => vec!(format!("Fit{}Final.pac", name)),
+            FileRole::Costume (i) => vec!(format!("Fit{}{:02}.pac", name, i), format!("Fit{}{:02}.pcs", name, i)),
+        }
+    }
+
+    /// Returns the name of the file to use for the role.
+    /// A file provided by the mod is always used over a vanilla file, even if the vanilla file is otherwise preferred.
+    /// e.g. A mod providing only Fit{}Motion.pac takes priority over the vanilla Fit{}MotionEtc.pac
+    /// File names are compared case insensitively.
+    fn resolve(&self, role: FileRole) -> Option<&str> {
+        let names: Vec<String> = self.role_file_names(role).iter().map(|x| x.to_lowercase()).collect();
+        let candidates: Vec<&String> = names.iter()
+            .filter_map(|name| self.data.keys().find(|x| &x.to_lowercase() == name))
+            .collect();
+
+        candidates.iter()
+            .find(|x| self.sources.get(**x).map(|x| x.from_mod).unwrap_or(false))
+            .or_else(|| candidates.first())
+            .map(|x| x.as_str())
+    }
+
+    fn provenance(&self) -> Vec<FileProvenance> {
+        let mut roles = vec!(FileRole::Moveset, FileRole::Motion, FileRole::Entry, FileRole::Final);
+        for i in 0..100 {
+            if self.resolve(FileRole::Costume (i)).is_none() {
+                break;
+            }
+            roles.push(FileRole::Costume (i));
+        }
+
+        let mut provenance = vec!();
+        for role in roles {
+            if let Some(source) = self.resolve(role).and_then(|x| self.sources.get(x)) {
+                provenance.push(FileProvenance { role, path: source.path.clone(), from_mod: source.from_mod, sha1: source.sha1.clone(), compressed: source.compressed });
+            }
+        }
+        provenance
+    }
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub enum ModType {
     /// Original brawl fighter.
     /// All .pac files are unmodified from brawl.
@@ -472,3 +1433,486 @@ pub enum ModType {
     /// Although it can reference custom coding of a cloned character somehow.
     ModFromScratch,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use crate::script::DataSource;
+    use crate::test_util::{TestFighterBuilder, damage};
+    use crate::mdl0::bones::test_bone;
+
+    #[test]
+    fn bone_names() {
+        let bones = test_bone("TopN", 0, vec!(
+            test_bone("TransN", 1, vec!(
+                test_bone("RHaveN", 2, vec!()),
+            )),
+        ));
+        let bone = find_bone(Ok(Some(&bones)), "rhaven").unwrap();
+        assert_eq!(bone.index, 2);
+        assert_eq!(bones.find_by_index(bone.index).unwrap().name, "RHaveN");
+        // indices offset by 400 refer to the same bone
+        assert_eq!(bones.find_by_index(get_bone_index(402)).unwrap().name, "RHaveN");
+
+        assert_eq!(find_bone(Ok(Some(&bones)), "LHaveN").err(), Some(BoneNameError::UnknownBone ("LHaveN".to_string())));
+        assert_eq!(find_bone(Ok(None), "RHaveN").err(), Some(BoneNameError::NoModel));
+    }
+
+    #[test]
+    fn hitbox_with_bone_name() {
+        let fighter = TestFighterBuilder::new("Test").build();
+        let result = HitBoxArguments::with_bone_name(&fighter, "RHaveN", 0, 5.0, 3.0, Vector3::new(0.0, 2.0, 1.0));
+        assert_eq!(result.err(), Some(BoneNameError::NotLoaded (NotLoaded::Models)));
+        assert_eq!(fighter.bone_index_by_name("RHaveN"), None);
+
+        // bones in the bone references are found without a model
+        let trans_n = fighter.get_fighter_data().unwrap().misc.bone_refs.trans_n;
+        assert_eq!(fighter.bone_index_by_name("transn"), Some(trans_n as u16));
+        assert_eq!(fighter.bone_name(trans_n as u16), Some("TransN"));
+        let hitbox = HitBoxArguments::with_bone_name(&fighter, "TransN", 0, 5.0, 3.0, Vector3::new(0.0, 2.0, 1.0)).unwrap();
+        assert_eq!(hitbox.bone_index, trans_n as i16);
+    }
+
+    #[test]
+    fn subaction_by_duplicate_name() {
+        let fighter = TestFighterBuilder::new("Test")
+            .subaction("Wait1", |s| s)
+            .subaction("AttackS3S", |s| s)
+            .subaction("Wait1", |s| s.sync_timer(1.0))
+            .build();
+        assert_eq!(fighter.subaction_by_name("Wait1"), Some(0));
+        assert_eq!(fighter.subactions_by_name("Wait1"), vec!(0, 2));
+        assert_eq!(fighter.subaction_by_name("AttackS3S"), Some(1));
+        assert_eq!(fighter.subaction_by_name("Run"), None);
+        assert!(fighter.subactions_by_name("Run").is_empty());
+
+        let warnings = fighter.parse_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].section, "data/subaction_names");
+    }
+
+    fn write_files(dir: &Path, names: &[&str]) {
+        fs::create_dir_all(dir).unwrap();
+        for name in names {
+            fs::write(dir.join(name), name.as_bytes()).unwrap();
+        }
+    }
+
+    fn provenance(mod_files: &[&str]) -> Vec<FileProvenance> {
+        let brawl = tempfile::tempdir().unwrap();
+        let modded = tempfile::tempdir().unwrap();
+        write_files(&brawl.path().join("mario"), &["FitMario.pac", "FitMarioMotionEtc.pac", "FitMario00.pac"]);
+        write_files(&modded.path().join("mario"), mod_files);
+
+        let (fighter_datas, _) = fighter_datas(fighter_folders(fs::read_dir(brawl.path()).unwrap()), fighter_folders(fs::read_dir(modded.path()).unwrap()), LoadDetail::default(), &PathFormatter::default(), None);
+        assert_eq!(fighter_datas.len(), 1);
+        fighter_datas[0].provenance()
+    }
+
+    fn from_mod(provenance: &[FileProvenance], role: FileRole) -> bool {
+        provenance.iter().find(|x| x.role == role).unwrap().from_mod
+    }
+
+    #[test]
+    fn motion_only_override() {
+        let provenance = provenance(&["FitMarioMotionEtc.pac"]);
+        assert!(!from_mod(&provenance, FileRole::Moveset));
+        assert!(from_mod(&provenance, FileRole::Motion));
+        assert!(!from_mod(&provenance, FileRole::Costume (0)));
+    }
+
+    #[test]
+    fn motion_only_override_without_etc() {
+        let provenance = provenance(&["fitmariomotion.pac"]);
+        let motion = provenance.iter().find(|x| x.role == FileRole::Motion).unwrap();
+        assert!(motion.from_mod);
+        assert!(motion.path.ends_with("fitmariomotion.pac"));
+    }
+
+    /// Loads a mario folder containing the given costume files, returning the data and provenance of the first costume
+    fn costume(brawl_files: &[(&str, Vec<u8>)], mod_files: &[(&str, Vec<u8>)]) -> (Vec<u8>, Vec<FileProvenance>) {
+        let brawl = tempfile::tempdir().unwrap();
+        let modded = tempfile::tempdir().unwrap();
+        let mario = brawl.path().join("mario");
+        write_files(&mario, &["FitMario.pac", "FitMarioMotionEtc.pac"]);
+        for (name, data) in brawl_files {
+            fs::write(mario.join(name), data).unwrap();
+        }
+        fs::create_dir_all(modded.path().join("mario")).unwrap();
+        for (name, data) in mod_files {
+            fs::write(modded.path().join("mario").join(name), data).unwrap();
+        }
+
+        let (fighter_datas, _) = fighter_datas(fighter_folders(fs::read_dir(brawl.path()).unwrap()), fighter_folders(fs::read_dir(modded.path()).unwrap()), LoadDetail::default(), &PathFormatter::default(), None);
+        let fighter_data = &fighter_datas[0];
+        let data = fighter_data.resolve(FileRole::Costume (0)).map(|x| fighter_data.data[x].to_vec()).unwrap_or_default();
+        (data, fighter_data.provenance())
+    }
+
+    fn costumes(provenance: &[FileProvenance]) -> Vec<&FileProvenance> {
+        provenance.iter().filter(|x| matches!(x.role, FileRole::Costume (_))).collect()
+    }
+
+    #[test]
+    fn costume_pcs_only() {
+        let (data, provenance) = costume(&[("FitMario00.pcs", compression::lz77_literals(b"costume 00"))], &[]);
+        assert_eq!(data, b"costume 00");
+        let costumes = costumes(&provenance);
+        assert_eq!(costumes.len(), 1);
+        assert_eq!(costumes[0].role, FileRole::Costume (0));
+        assert!(costumes[0].compressed);
+        assert!(costumes[0].path.ends_with("FitMario00.pcs"));
+    }
+
+    #[test]
+    fn costume_pac_only() {
+        let (data, provenance) = costume(&[("FitMario00.pac", b"costume 00".to_vec())], &[]);
+        assert_eq!(data, b"costume 00");
+        let costumes = costumes(&provenance);
+        assert_eq!(costumes.len(), 1);
+        assert!(!costumes[0].compressed);
+        assert!(costumes[0].path.ends_with("FitMario00.pac"));
+    }
+
+    #[test]
+    fn costume_pac_and_pcs() {
+        let (data, provenance) = costume(&[
+            ("FitMario00.pac", b"pac".to_vec()),
+            ("FitMario00.pcs", compression::lz77_literals(b"pcs")),
+        ], &[]);
+        assert_eq!(data, b"pac");
+        let costumes = costumes(&provenance);
+        assert_eq!(costumes.len(), 1);
+        assert!(!costumes[0].compressed);
+    }
+
+    #[test]
+    fn costume_pcs_from_mod() {
+        let (data, provenance) = costume(
+            &[("FitMario00.pac", b"vanilla".to_vec())],
+            &[("FitMario00.pcs", compression::lz77_literals(b"modded"))],
+        );
+        assert_eq!(data, b"modded");
+        let costumes = costumes(&provenance);
+        assert_eq!(costumes.len(), 1);
+        assert!(costumes[0].from_mod);
+        assert!(costumes[0].compressed);
+    }
+
+    #[test]
+    fn costume_pcs_malformed() {
+        let (data, provenance) = costume(&[("FitMario00.pcs", b"not compressed".to_vec())], &[]);
+        assert!(data.is_empty());
+        assert!(costumes(&provenance).is_empty());
+    }
+
+    #[test]
+    fn character_movement_flag() {
+        let translations: Vec<_> = (0..5).map(|x| Vector3::new(0.0, 0.0, x as f32)).collect();
+
+        let movement = character_movement(&translations, AnimationFlags::MOVES_CHARACTER, 1.0);
+        assert_eq!(movement, vec!((0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (3.0, 0.0), (4.0, 0.0)));
+
+        let movement = character_movement(&translations, AnimationFlags::MOVES_CHARACTER, 2.0);
+        assert_eq!(movement[4], (8.0, 0.0));
+
+        let movement = character_movement(&translations, AnimationFlags::NONE, 1.0);
+        assert_eq!(movement, vec!((0.0, 0.0); 5));
+    }
+
+    #[test]
+    fn moveset_only_override() {
+        let provenance = provenance(&["FitMario.pac"]);
+        assert!(from_mod(&provenance, FileRole::Moveset));
+        assert!(!from_mod(&provenance, FileRole::Motion));
+        assert!(!from_mod(&provenance, FileRole::Costume (0)));
+    }
+
+    #[test]
+    fn load_detailed_reports_failures() {
+        let empty_arc = {
+            let mut data = vec!(0; 0x40);
+            data[..3].copy_from_slice(b"ARC");
+            data
+        };
+        let brawl = tempfile::tempdir().unwrap();
+        let mario = brawl.path().join("mario");
+        fs::create_dir_all(&mario).unwrap();
+        fs::write(mario.join("FitMario.pac"), &empty_arc).unwrap();
+        fs::write(mario.join("FitMarioMotionEtc.pac"), &empty_arc).unwrap();
+        let luigi = brawl.path().join("luigi");
+        fs::create_dir_all(&luigi).unwrap();
+        fs::write(luigi.join("FitLuigi.pac"), [0x41, 0x52]).unwrap();
+        fs::write(luigi.join("FitLuigiMotionEtc.pac"), &empty_arc).unwrap();
+        let peach = brawl.path().join("peach");
+        fs::create_dir_all(&peach).unwrap();
+        fs::write(peach.join("FitPeach.pac"), &empty_arc).unwrap();
+        write_files(&brawl.path().join("result"), &["other.pac"]);
+
+        let common_fighter = Arc { name: String::new(), children: vec!() };
+        let result = Fighter::load_detailed(fs::read_dir(brawl.path()).unwrap(), None, &common_fighter, &WiiMemory::new(), true);
+
+        assert_eq!(result.fighters.len(), 1);
+        assert_eq!(result.fighters[0].cased_name, "Mario");
+
+        let mut failed: Vec<&str> = result.failures.iter().map(|x| x.folder.as_str()).collect();
+        failed.sort();
+        assert_eq!(failed, vec!("Luigi", "Peach"));
+        let peach = result.failures.iter().find(|x| x.folder == "Peach").unwrap();
+        assert_eq!(peach.error, "missing motion file: FitPeachMotionEtc.pac");
+
+        assert_eq!(result.skipped, vec!(String::from("result")));
+    }
+
+    #[test]
+    fn memory_sourced_subaction() {
+        let mut moveset = TestFighterBuilder::new("Mario").subaction("Wait1", |s| s.sync_timer(1.0)).moveset_pac();
+        // FitMario.pac + 0x80 is the start of the moveset data
+        let ram_base: u32 = 0x80F9FBA0;
+        let subaction_main_start = u32::from_be_bytes([moveset[0xB0], moveset[0xB1], moveset[0xB2], moveset[0xB3]]);
+        let script_address: u32 = 0x80500000;
+
+        let mut codeset = vec!();
+        // string write the script to RAM
+        codeset.extend(&(0x06000000 | script_address & 0x1FFFFFF).to_be_bytes());
+        codeset.extend(&16_u32.to_be_bytes());
+        codeset.extend(&[0x0C, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        codeset.extend(&[0; 8]);
+        // redirect subaction 0 to the written script
+        codeset.extend(&(0x04000000 | (ram_base + 0x80 + subaction_main_start) & 0x1FFFFFF).to_be_bytes());
+        codeset.extend(&script_address.to_be_bytes());
+        codeset.extend(&[0xF0, 0, 0, 0, 0, 0, 0, 0]);
+        let wii_memory = wiird_runner::process(&codeset, &mut moveset, RamAddress::new(ram_base));
+        assert_eq!(wii_memory.writer(RamAddress::new(script_address + 8)), Some(0));
+
+        let empty_arc = {
+            let mut data = vec!(0; 0x40);
+            data[..3].copy_from_slice(b"ARC");
+            data
+        };
+        let brawl = tempfile::tempdir().unwrap();
+        let mario = brawl.path().join("mario");
+        fs::create_dir_all(&mario).unwrap();
+        fs::write(mario.join("FitMario.pac"), &moveset).unwrap();
+        fs::write(mario.join("FitMarioMotionEtc.pac"), &empty_arc).unwrap();
+
+        let common_fighter = Arc { name: String::new(), children: vec!() };
+        let result = Fighter::load_detailed(fs::read_dir(brawl.path()).unwrap(), None, &common_fighter, &wii_memory, true);
+        let fighter = &result.fighters[0];
+
+        let script = &fighter.get_fighter_data().unwrap().subaction_main[0];
+        assert_eq!(script.source, DataSource::WiiMemory { address: script_address, code_offset: Some(0) });
+        assert_eq!(script.events.len(), 1);
+        assert_eq!(script.events[0].namespace, 0x0C);
+        assert_eq!(fighter.get_fighter_data().unwrap().subaction_gfx[0].source, DataSource::File { offset: fighter.get_fighter_data().unwrap().subaction_gfx[0].offset as u32 });
+
+        let expected = vec!(MemorySection { location: String::from("subaction_main[0x0]"), address: script_address, code_offset: Some(0) });
+        assert_eq!(fighter.memory_sections(), expected.as_slice());
+        assert_eq!(fighter.moveset.memory_sections(), expected);
+        assert_eq!(fighter.provenance.memory_sections, expected);
+    }
+
+    /// Creates a moveset arc containing only a fighter data section with the passed walk_init_vel attribute
+    fn moveset_arc(walk_init_vel: f32) -> Vec<u8> {
+        TestFighterBuilder::new("Test").attribute_f32(0x00, walk_init_vel).moveset_pac()
+    }
+
+    #[test]
+    fn load_follower() {
+        let brawl = tempfile::tempdir().unwrap();
+        let popo = brawl.path().join("popo");
+        fs::create_dir_all(&popo).unwrap();
+        fs::write(popo.join("FitPopo.pac"), moveset_arc(1.0)).unwrap();
+        fs::write(popo.join("FitPopoMotionEtc.pac"), moveset_arc(0.0)).unwrap();
+        let nana = brawl.path().join("nana");
+        fs::create_dir_all(&nana).unwrap();
+        fs::write(nana.join("FitNana.pac"), moveset_arc(2.0)).unwrap();
+
+        let common_fighter = Arc { name: String::new(), children: vec!() };
+        let result = Fighter::load_detailed(fs::read_dir(brawl.path()).unwrap(), None, &common_fighter, &WiiMemory::new(), true);
+        assert!(result.failures.is_empty());
+        assert_eq!(result.fighters.len(), 1);
+
+        let leader = &result.fighters[0];
+        assert_eq!(leader.cased_name, "Popo");
+        assert_eq!(leader.get_fighter_data().unwrap().attributes.walk_init_vel, 1.0);
+
+        let follower = leader.follower.as_ref().unwrap();
+        assert_eq!(follower.leader, "Popo");
+        assert_eq!(follower.fighter.cased_name, "Nana");
+        assert_eq!(follower.fighter.get_fighter_data().unwrap().attributes.walk_init_vel, 2.0);
+        let motion = follower.fighter.provenance.files.iter().find(|x| x.role == FileRole::Motion).unwrap();
+        assert!(motion.path.ends_with("popo/FitPopoMotionEtc.pac"));
+    }
+
+    #[test]
+    fn unreadable_file_skipped() {
+        let brawl = tempfile::tempdir().unwrap();
+        let dir = brawl.path().join("test");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("FitTest.pac"), moveset_arc(1.0)).unwrap();
+        // opening a directory succeeds but reading from it fails
+        fs::create_dir_all(dir.join("FitTestMotionEtc.pac")).unwrap();
+
+        let paths = PathFormatter::new(true, brawl.path(), None);
+        let (fighter_datas, skipped) = fighter_datas(fighter_folders(fs::read_dir(brawl.path()).unwrap()), vec!(), LoadDetail::default(), &paths, None);
+        assert!(skipped.is_empty());
+        assert_eq!(fighter_datas.len(), 1);
+        assert!(fighter_datas[0].data.contains_key("FitTest.pac"));
+        assert!(!fighter_datas[0].data.contains_key("FitTestMotionEtc.pac"));
+        assert_eq!(fighter_datas[0].sources["FitTest.pac"].path, Path::new("<brawl>/test/FitTest.pac"));
+    }
+
+    #[test]
+    fn shared_files() {
+        let brawl = tempfile::tempdir().unwrap();
+        for name in &["Wario", "WarioMan"] {
+            let dir = brawl.path().join(name.to_lowercase());
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join(format!("Fit{}.pac", name)), moveset_arc(1.0)).unwrap();
+        }
+        fs::write(brawl.path().join("wario/FitWarioMotionEtc.pac"), moveset_arc(0.0)).unwrap();
+
+        let (fighter_datas, _) = fighter_datas(fighter_folders(fs::read_dir(brawl.path()).unwrap()), vec!(), LoadDetail::default(), &PathFormatter::default(), None);
+        let motion = |name: &str| {
+            let fighter_data = fighter_datas.iter().find(|x| x.cased_name == name).unwrap();
+            fighter_data.data[fighter_data.resolve(FileRole::Motion).unwrap()].clone()
+        };
+        assert!(sync::Arc::ptr_eq(&motion("Wario"), &motion("WarioMan")));
+
+        let common_fighter = Arc { name: String::new(), children: vec!() };
+        let result = Fighter::load_detailed(fs::read_dir(brawl.path()).unwrap(), None, &common_fighter, &WiiMemory::new(), true);
+        assert_eq!(result.fighters.len(), 1);
+        let transformation = result.fighters[0].transformation.as_ref().unwrap();
+        assert!(sync::Arc::ptr_eq(&result.fighters[0].moveset_common, &transformation.moveset_common));
+    }
+
+    #[test]
+    fn load_transformation() {
+        let brawl = tempfile::tempdir().unwrap();
+        for (name, walk_init_vel) in &[("Koopa", 1.0), ("GKoopa", 2.0)] {
+            let dir = brawl.path().join(name.to_lowercase());
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join(format!("Fit{}.pac", name)), moveset_arc(*walk_init_vel)).unwrap();
+        }
+        fs::write(brawl.path().join("koopa/FitKoopaMotionEtc.pac"), moveset_arc(0.0)).unwrap();
+
+        let common_fighter = Arc { name: String::new(), children: vec!() };
+        let result = Fighter::load_detailed(fs::read_dir(brawl.path()).unwrap(), None, &common_fighter, &WiiMemory::new(), true);
+        assert!(result.failures.is_empty());
+        assert_eq!(result.fighters.len(), 1);
+
+        let base = &result.fighters[0];
+        assert_eq!(base.cased_name, "Koopa");
+        let transformation = base.transformation.as_ref().unwrap();
+        assert_eq!(transformation.cased_name, "GKoopa");
+        assert_eq!(transformation.get_fighter_data().unwrap().attributes.walk_init_vel, 2.0);
+        assert!(transformation.transformation.is_none());
+        let motion = transformation.provenance.files.iter().find(|x| x.role == FileRole::Motion).unwrap();
+        assert!(motion.path.ends_with("koopa/FitKoopaMotionEtc.pac"));
+
+        let folders = fighter_folders(fs::read_dir(brawl.path()).unwrap());
+        let options = PatchedLoadOptions {
+            detail: LoadDetail::ScriptsOnly,
+            limits: ScriptLimits::default(),
+            codeset: &[],
+            patches: &[],
+            standalone_transformations: true,
+            paths: &PathFormatter::default(),
+        };
+        let result = Fighter::load_detailed_patched(folders, vec!(), &common_fighter, &WiiMemory::new(), &options, None);
+        let mut names: Vec<&str> = result.fighters.iter().map(|x| x.cased_name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!("GKoopa", "Koopa"));
+        assert!(result.fighters.iter().all(|x| x.transformation.is_none()));
+    }
+
+    #[test]
+    fn load_broken_transformation() {
+        let brawl = tempfile::tempdir().unwrap();
+        for name in &["koopa", "gkoopa"] {
+            fs::create_dir_all(brawl.path().join(name)).unwrap();
+        }
+        fs::write(brawl.path().join("koopa/FitKoopa.pac"), moveset_arc(1.0)).unwrap();
+        fs::write(brawl.path().join("koopa/FitKoopaMotionEtc.pac"), moveset_arc(0.0)).unwrap();
+        fs::write(brawl.path().join("gkoopa/FitGKoopa.pac"), [0x41, 0x52]).unwrap();
+
+        let common_fighter = Arc { name: String::new(), children: vec!() };
+        let result = Fighter::load_detailed(fs::read_dir(brawl.path()).unwrap(), None, &common_fighter, &WiiMemory::new(), true);
+        assert_eq!(result.fighters.len(), 1);
+        assert_eq!(result.fighters[0].cased_name, "Koopa");
+        assert!(result.fighters[0].transformation.is_none());
+        assert_eq!(result.failures.len(), 1);
+        assert_eq!(result.failures[0].folder, "GKoopa");
+        assert!(result.failures[0].error.starts_with("failed to load as the transformation of Koopa, panicked while parsing"), "{}", result.failures[0].error);
+    }
+
+    #[test]
+    fn load_detail_files() {
+        let files = ["FitMario.pac", "FitMarioMotionEtc.pac", "fitmariomotion.pac", "FitMario00.pac", "FitKirbyMario01.pac", "FitMarioEntry.pac"];
+        let read = |detail: LoadDetail| -> Vec<&str> { files.iter().cloned().filter(|x| detail.reads_file(x)).collect() };
+        assert_eq!(read(LoadDetail::ScriptsOnly), vec!("FitMario.pac", "FitMarioEntry.pac"));
+        assert_eq!(read(LoadDetail::ScriptsAndAnimations), vec!("FitMario.pac", "FitMarioMotionEtc.pac", "fitmariomotion.pac", "FitMarioEntry.pac"));
+        assert_eq!(read(LoadDetail::Full { single_model: true }), files.to_vec());
+    }
+
+    #[test]
+    fn load_scripts_only() {
+        let moveset = TestFighterBuilder::new("Test")
+            .attribute_f32(0x00, 1.5)
+            .subaction("Wait1", |s| s.sync_timer(10.0))
+            .subaction("Attack11", |s| s.async_timer(3.0).hitbox(damage(3)).sync_timer(2.0).terminate_collisions())
+            .moveset_pac();
+        let brawl = tempfile::tempdir().unwrap();
+        let mario = brawl.path().join("mario");
+        fs::create_dir_all(&mario).unwrap();
+        fs::write(mario.join("FitMario.pac"), &moveset).unwrap();
+        fs::write(mario.join("FitMarioMotionEtc.pac"), &moveset).unwrap();
+        let common_fighter = Arc { name: String::new(), children: vec!() };
+        let load = |detail| {
+            let mut result = Fighter::load_with_detail(fs::read_dir(brawl.path()).unwrap(), None, &common_fighter, &WiiMemory::new(), detail);
+            assert!(result.failures.is_empty(), "{:?}", result.failures);
+            result.fighters.remove(0)
+        };
+
+        // the script data is the same no matter how much else is loaded
+        let full = load(LoadDetail::Full { single_model: false });
+        let scripts_only = load(LoadDetail::ScriptsOnly);
+        assert_eq!(format!("{:?}", scripts_only.get_fighter_data()), format!("{:?}", full.get_fighter_data()));
+        assert_eq!(scripts_only.load_detail(), LoadDetail::ScriptsOnly);
+
+        // data that was not loaded is an error instead of being empty
+        assert_eq!(scripts_only.get_animations().err(), Some(NotLoaded::Animations));
+        assert_eq!(scripts_only.get_bones().err(), Some(NotLoaded::Models));
+        assert_eq!(scripts_only.subaction_movement("Wait1").err(), Some(NotLoaded::Animations));
+        assert!(matches!(full.get_bones(), Ok(None)));
+        assert_eq!(load(LoadDetail::ScriptsAndAnimations).get_bones().err(), Some(NotLoaded::Models));
+
+        // the motion and model files are never read, so they can be unparseable
+        fs::write(mario.join("FitMarioMotionEtc.pac"), [0x41, 0x52]).unwrap();
+        fs::write(mario.join("FitMario00.pac"), [0x41, 0x52]).unwrap();
+        let scripts_only = load(LoadDetail::ScriptsOnly);
+        assert_eq!(scripts_only.provenance.files.iter().map(|x| x.role).collect::<Vec<_>>(), vec!(FileRole::Moveset));
+        assert_eq!(scripts_only.get_fighter_data().unwrap().attributes.walk_init_vel, 1.5);
+        let failures = Fighter::load_with_detail(fs::read_dir(brawl.path()).unwrap(), None, &common_fighter, &WiiMemory::new(), LoadDetail::ScriptsAndAnimations).failures;
+        assert_eq!(failures.len(), 1);
+    }
+
+    #[cfg(all(feature = "serde-full", feature = "serde_json"))]
+    #[test]
+    fn serde_round_trip() {
+        let fighter = TestFighterBuilder::new("Test")
+            .attribute_f32(0x00, 1.5)
+            .attribute_i32(0x30, 3)
+            .subaction("Wait1", |s| s.sync_timer(10.0))
+            .subaction("Attack11", |s| s.async_timer(3.0).hitbox(damage(3).angle(361)).sync_timer(2.0).terminate_collisions())
+            .appended_subaction("AttackS3S", |s| s.async_timer(5.0).hitbox(damage(9).bone(2)).delete_hitbox(0))
+            .action(|s| s.sync_timer(1.0), |s| s)
+            .build();
+
+        let json = serde_json::to_string(&fighter).unwrap();
+        let deserialized: Fighter = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&deserialized).unwrap(), json);
+    }
+}
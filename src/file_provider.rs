@@ -0,0 +1,249 @@
+//! Where the files of the brawl dump and the mod are read from.
+//!
+//! Brawl is read from a dump folder or a disc image and a mod is read from its pf folder or from memory, see `BrawlMod::from_memory`.
+//! `BrawlMod` reads all of them through `FileProvider`, so the loaders do not depend on where the files are stored.
+//!
+//! Paths are relative to the root of the brawl dump or the mods pf folder and separated by `/` e.g. `fighter/mario/FitMario.pac`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use failure::Error;
+use failure::bail;
+
+use crate::memory_files::{self, MemoryFiles};
+
+pub(crate) trait FileProvider: Sync {
+    /// Returns the path of every file inside of `dir` including files in its subfolders, relative to `dir`.
+    /// A missing `dir` contains no files.
+    fn files_in(&self, dir: &str) -> Result<Vec<String>, Error>;
+
+    /// Returns true if `dir` exists
+    fn is_dir(&self, dir: &str) -> bool {
+        self.files_in(dir).map(|x| !x.is_empty()).unwrap_or(false)
+    }
+
+    /// Returns true if there is a file at `path`
+    fn contains(&self, path: &str) -> bool;
+
+    /// Returns the contents of the file at `path`, the error does not include the path
+    fn read(&self, path: &str) -> Result<Vec<u8>, Error>;
+
+    /// Returns where the file at `path` is read from, recorded in provenance and written to errors once formatted by a `PathFormatter`
+    fn source_path(&self, path: &str) -> PathBuf;
+}
+
+impl<T: FileProvider + ?Sized> FileProvider for &T {
+    fn files_in(&self, dir: &str) -> Result<Vec<String>, Error> {
+        (**self).files_in(dir)
+    }
+
+    fn is_dir(&self, dir: &str) -> bool {
+        (**self).is_dir(dir)
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        (**self).contains(path)
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>, Error> {
+        (**self).read(path)
+    }
+
+    fn source_path(&self, path: &str) -> PathBuf {
+        (**self).source_path(path)
+    }
+}
+
+/// The files in a folder on disk e.g. a brawl dump or a mods pf folder
+pub(crate) struct DirFiles {
+    root: PathBuf,
+}
+
+impl DirFiles {
+    pub(crate) fn new(root: &Path) -> DirFiles {
+        DirFiles { root: root.to_path_buf() }
+    }
+}
+
+impl FileProvider for DirFiles {
+    fn files_in(&self, dir: &str) -> Result<Vec<String>, Error> {
+        let mut files = vec!();
+        let dir = self.root.join(dir);
+        if dir.is_dir() {
+            find_files(&dir, "", &mut files)?;
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    fn is_dir(&self, dir: &str) -> bool {
+        self.root.join(dir).is_dir()
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.root.join(path).is_file()
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>, Error> {
+        Ok(fs::read(self.root.join(path))?)
+    }
+
+    fn source_path(&self, path: &str) -> PathBuf {
+        self.root.join(path)
+    }
+}
+
+/// Adds the path of every file in `dir` and its subfolders to `found`, prefixed with `relative`
+fn find_files(dir: &Path, relative: &str, found: &mut Vec<String>) -> Result<(), Error> {
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        let name = format!("{}{}", relative, entry.file_name().to_string_lossy());
+        if path.is_dir() {
+            find_files(&path, &format!("{}/", name), found)?;
+        } else {
+            found.push(name);
+        }
+    }
+    Ok(())
+}
+
+impl FileProvider for MemoryFiles {
+    fn files_in(&self, dir: &str) -> Result<Vec<String>, Error> {
+        Ok(MemoryFiles::files_in(self, dir).map(|(relative, _)| relative.to_string()).collect())
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.get(path).is_some()
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>, Error> {
+        match self.get(path) {
+            Some(file) => Ok(file.data.clone()),
+            None => bail!("The file is not held in memory"),
+        }
+    }
+
+    fn source_path(&self, path: &str) -> PathBuf {
+        match self.get(path) {
+            Some(file) => file.provenance_path(),
+            None => PathBuf::from(memory_files::normalize_path(path)),
+        }
+    }
+}
+
+/// A brawl disc image, files are only read from the image when they are needed.
+/// Shared between the threads loading fighters, only one file is read at a time.
+#[cfg(feature = "disc")]
+pub(crate) struct BrawlDisc {
+    disc: std::sync::Mutex<crate::disc::Disc>,
+    /// The path of every file on the disc
+    paths: Vec<String>,
+}
+
+/// Without the disc feature a `BrawlDisc` cannot be opened, so one never exists
+#[cfg(not(feature = "disc"))]
+pub(crate) enum BrawlDisc { }
+
+impl BrawlDisc {
+    #[cfg(feature = "disc")]
+    pub(crate) fn open(path: &Path) -> Result<BrawlDisc, Error> {
+        let disc = crate::disc::Disc::open(path)?;
+        let paths = disc.files().iter().map(|x| x.path.clone()).collect();
+        Ok(BrawlDisc { disc: std::sync::Mutex::new(disc), paths })
+    }
+
+    #[cfg(not(feature = "disc"))]
+    pub(crate) fn open(path: &Path) -> Result<BrawlDisc, Error> {
+        bail!("Cannot load brawl from the disc image {:?}, brawllib_rs must be built with the disc feature", path)
+    }
+
+    /// Returns the path of the file as it is stored on the disc, the path is compared case insensitively
+    #[cfg(feature = "disc")]
+    fn find(&self, path: &str) -> Option<&str> {
+        self.paths.iter().find(|x| x.eq_ignore_ascii_case(path)).map(|x| x.as_str())
+    }
+}
+
+#[cfg(feature = "disc")]
+impl FileProvider for BrawlDisc {
+    fn files_in(&self, dir: &str) -> Result<Vec<String>, Error> {
+        let prefix = format!("{}/", dir.trim_end_matches('/'));
+        Ok(self.paths.iter()
+            .filter(|x| x.get(..prefix.len()).map(|x| x.eq_ignore_ascii_case(&prefix)).unwrap_or(false))
+            .map(|x| x[prefix.len()..].to_string())
+            .collect())
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.find(path).is_some()
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>, Error> {
+        self.disc.lock().unwrap().read_file(path)
+    }
+
+    fn source_path(&self, path: &str) -> PathBuf {
+        PathBuf::from(self.find(path).unwrap_or(path))
+    }
+}
+
+#[cfg(not(feature = "disc"))]
+impl FileProvider for BrawlDisc {
+    fn files_in(&self, _dir: &str) -> Result<Vec<String>, Error> {
+        match *self { }
+    }
+
+    fn contains(&self, _path: &str) -> bool {
+        match *self { }
+    }
+
+    fn read(&self, _path: &str) -> Result<Vec<u8>, Error> {
+        match *self { }
+    }
+
+    fn source_path(&self, _path: &str) -> PathBuf {
+        match *self { }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn dir_files() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("stage/melee/nested")).unwrap();
+        fs::write(root.path().join("stage/melee/STGA.pac"), [1]).unwrap();
+        fs::write(root.path().join("stage/melee/nested/STGB.pac"), [2]).unwrap();
+        let files = DirFiles::new(root.path());
+
+        assert_eq!(files.files_in("stage").unwrap(), vec!("melee/STGA.pac", "melee/nested/STGB.pac"));
+        assert!(files.files_in("fighter").unwrap().is_empty());
+        assert!(files.is_dir("stage/melee"));
+        assert!(!files.is_dir("fighter"));
+        assert!(files.contains("stage/melee/STGA.pac"));
+        assert!(!files.contains("stage/melee"));
+        assert_eq!(files.read("stage/melee/nested/STGB.pac").unwrap(), vec!(2));
+        assert!(files.read("stage/melee/STGC.pac").is_err());
+        assert_eq!(files.source_path("stage/melee/STGA.pac"), root.path().join("stage/melee/STGA.pac"));
+    }
+
+    #[test]
+    fn memory_files() {
+        let mut map = HashMap::new();
+        map.insert(String::from("pf\\stage\\melee\\STGA.pac"), vec!(1));
+        let files = MemoryFiles::new(map);
+        let files: &dyn FileProvider = &files;
+
+        assert_eq!(files.files_in("Stage/Melee").unwrap(), vec!("STGA.pac"));
+        assert!(files.is_dir("stage"));
+        assert!(!files.is_dir("fighter"));
+        assert!(files.contains("stage/melee/stga.pac"));
+        assert_eq!(files.read("stage/melee/stga.pac").unwrap(), vec!(1));
+        assert!(files.read("stage/melee/STGB.pac").is_err());
+        assert_eq!(files.source_path("stage/melee/stga.pac"), Path::new("stage/melee/STGA.pac"));
+    }
+}
@@ -0,0 +1,252 @@
+use crate::arc::{Arc, ArcChildData};
+use crate::fighter::Fighter;
+use crate::sakurai::{SectionData, SectionScript};
+use crate::script::Requirement;
+use crate::script_ast::{ScriptAst, Block, EventAst, Expression};
+
+/// A subaction that is involved in the fighters final smash.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct FinalSmashSubaction {
+    pub index: usize,
+    pub name: String,
+    pub reasons: Vec<FinalSmashReason>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum FinalSmashReason {
+    /// One of the subactions scripts checks for the HasSmashBall requirement
+    ChecksSmashBall,
+    /// One of the subactions scripts enters or exits the final smash state
+    FinalSmashState,
+    /// The subaction is changed to by an action, subaction or script of Fit{}Final.pac, only when the fighter has the smash ball
+    GatedBySmashBall,
+}
+
+impl Fighter {
+    /// Returns the subactions that are involved in the fighters final smash.
+    /// This is determined by scanning the subaction and action scripts for HasSmashBall requirements and the final smash state events.
+    /// The scripts of Fit{}Final.pac are scanned too, they are returned by `Fighter::final_pac_scripts`.
+    pub fn final_smash_subactions(&self) -> Vec<FinalSmashSubaction> {
+        let fighter_data = match self.get_fighter_data() {
+            Some(fighter_data) => fighter_data,
+            None => return vec!(),
+        };
+
+        let mut reasons: Vec<Vec<FinalSmashReason>> = vec!(vec!(); fighter_data.subaction_flags.len());
+        let mut add_reason = |index: usize, reason: FinalSmashReason| {
+            if let Some(reasons) = reasons.get_mut(index) {
+                if !reasons.contains(&reason) {
+                    reasons.push(reason);
+                }
+            }
+        };
+
+        for index in 0..fighter_data.subaction_flags.len() {
+            if let Some(scripts) = fighter_data.subaction_scripts(index) {
+                for (_, script) in scripts.iter() {
+                    let scan = scan_script(&ScriptAst::new(script));
+                    if scan.checks_smash_ball {
+                        add_reason(index, FinalSmashReason::ChecksSmashBall);
+                    }
+                    if scan.final_smash_state {
+                        add_reason(index, FinalSmashReason::FinalSmashState);
+                    }
+                    for gated in scan.gated_subactions {
+                        add_reason(gated as usize, FinalSmashReason::GatedBySmashBall);
+                    }
+                }
+            }
+        }
+
+        let final_pac_scripts = self.final_pac_scripts().into_iter().map(|x| &x.script);
+        for script in fighter_data.entry_actions.iter().chain(fighter_data.exit_actions.iter()).chain(final_pac_scripts) {
+            for gated in scan_script(&ScriptAst::new(script)).gated_subactions {
+                add_reason(gated as usize, FinalSmashReason::GatedBySmashBall);
+            }
+        }
+
+        reasons.into_iter().enumerate()
+            .filter(|(_, reasons)| !reasons.is_empty())
+            .map(|(index, reasons)| FinalSmashSubaction {
+                index,
+                name: fighter_data.subaction_flags[index].name.clone(),
+                reasons,
+            })
+            .collect()
+    }
+
+    /// Returns the section scripts of Fit{}Final.pac, including those in nested ARC files.
+    /// Empty when the fighter has no Fit{}Final.pac.
+    pub fn final_pac_scripts(&self) -> Vec<&SectionScript> {
+        let mut scripts = vec!();
+        if let Some(final_pac) = &self.final_pac {
+            arc_section_scripts(final_pac, &mut scripts);
+        }
+        scripts
+    }
+}
+
+fn arc_section_scripts<'a>(arc: &'a Arc, scripts: &mut Vec<&'a SectionScript>) {
+    for child in &arc.children {
+        match &child.data {
+            ArcChildData::Arc (arc) => arc_section_scripts(arc, scripts),
+            ArcChildData::Sakurai (sakurai) => {
+                for section in &sakurai.sections {
+                    if let SectionData::Script (script) = &section.data {
+                        scripts.push(script);
+                    }
+                }
+            }
+            _ => { }
+        }
+    }
+}
+
+#[derive(Default)]
+struct ScriptScan {
+    checks_smash_ball: bool,
+    final_smash_state: bool,
+    /// Subactions changed to only when the fighter has the smash ball
+    gated_subactions: Vec<i32>,
+}
+
+fn scan_script(script: &ScriptAst) -> ScriptScan {
+    let mut scan = ScriptScan::default();
+    scan_block(&script.block, false, &mut scan);
+    scan
+}
+
+fn scan_block(block: &Block, gated: bool, scan: &mut ScriptScan) {
+    for event in &block.events {
+        match event {
+            EventAst::IfStatement (if_statement) => {
+                let smash_ball = smash_ball_test(&if_statement.test);
+                if smash_ball.is_some() {
+                    scan.checks_smash_ball = true;
+                }
+                scan_block(&if_statement.then_branch, gated || smash_ball == Some(true), scan);
+                if let Some(else_branch) = &if_statement.else_branch {
+                    scan_block(else_branch, gated || smash_ball == Some(false), scan);
+                }
+            }
+            EventAst::IfStatementAnd (test) |
+            EventAst::IfStatementOr (test) |
+            EventAst::PreviousInterruptAddRequirement { test } |
+            EventAst::InterruptAddRequirement { test, .. } if smash_ball_test(test).is_some() => scan.checks_smash_ball = true,
+            EventAst::CreateInterrupt (interrupt) if smash_ball_test(&interrupt.test).is_some() => scan.checks_smash_ball = true,
+            EventAst::ForLoop (for_loop) => scan_block(&for_loop.block, gated, scan),
+            EventAst::FinalSmashEnter |
+            EventAst::FinalSmashExit => scan.final_smash_state = true,
+            EventAst::ChangeSubaction (index) |
+            EventAst::ChangeSubactionRestartFrame (index) if gated && !scan.gated_subactions.contains(index) => scan.gated_subactions.push(*index),
+            _ => { }
+        }
+    }
+}
+
+/// Returns Some(true) if the expression passes only with the smash ball, Some(false) if it passes only without the smash ball
+/// and None if the expression does not depend on the smash ball in a known way.
+fn smash_ball_test(test: &Expression) -> Option<bool> {
+    match test {
+        Expression::Nullary (Requirement::HasSmashBall) => Some(true),
+        Expression::Not (inner) => smash_ball_test(inner).map(|x| !x),
+        _ => if test.requirements().iter().any(|x| matches!(x, Requirement::HasSmashBall)) {
+            // depends on the smash ball but combined with other requirements
+            Some(true)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arc;
+    use crate::script_ast::IfStatement;
+    use crate::test_util::{TestFighterBuilder, arc_blob};
+    use crate::wii_memory::WiiMemory;
+
+    use fancy_slice::FancySlice;
+
+    /// A Fit{}Final.pac containing a script that changes to subaction 1 when the fighter has the smash ball
+    fn final_pac() -> Vec<u8> {
+        let mut data = vec!();
+        for value in &[0x20 + 0x40, 0x40, 0, 1, 0, 0, 0, 0] {
+            data.extend(&u32::to_be_bytes(*value));
+        }
+        data.extend(&[0; 8]);
+        // script at 0x8: If HasSmashBall, Change Subaction 1, End If
+        data.extend(&[0x00, 0x0A, 0x01, 0x00, 0x00, 0x00, 0x00, 0x28]);
+        data.extend(&[0x04, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x30]);
+        data.extend(&[0x00, 0x0F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        data.extend(&[0; 8]);
+        // arguments at 0x28
+        for value in &[6, 0x2717, 0, 1, 3, 1] {
+            data.extend(&u32::to_be_bytes(*value));
+        }
+        // section
+        for value in &[0x8, 0x0] {
+            data.extend(&u32::to_be_bytes(*value));
+        }
+        data.extend(b"gameAnimCmd_Final\0");
+        arc_blob("FitTestFinal", &[(-1, &data)])
+    }
+
+    #[test]
+    fn final_pac_scripts() {
+        let mut fighter = TestFighterBuilder::new("Test")
+            .subaction("Wait1", |s| s)
+            .subaction("FinalStart", |s| s)
+            .build();
+        assert!(fighter.final_pac_scripts().is_empty());
+        assert!(fighter.final_smash_subactions().is_empty());
+
+        fighter.final_pac = Some(arc::arc(FancySlice::new(&final_pac()), &WiiMemory::new(), false));
+        let names: Vec<&str> = fighter.final_pac_scripts().iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, vec!("gameAnimCmd_Final"));
+        assert_eq!(fighter.final_smash_subactions(), vec!(FinalSmashSubaction {
+            index: 1,
+            name: String::from("FinalStart"),
+            reasons: vec!(FinalSmashReason::GatedBySmashBall),
+        }));
+    }
+
+    #[test]
+    fn smash_ball_gated_script() {
+        let script = ScriptAst {
+            block: Block { events: vec!(
+                EventAst::ChangeSubaction (1),
+                EventAst::IfStatement (IfStatement {
+                    test: Expression::Nullary (Requirement::HasSmashBall),
+                    then_branch: Block { events: vec!(EventAst::ChangeSubaction (10)) },
+                    else_branch: Some(Box::new(Block { events: vec!(EventAst::ChangeSubaction (11)) })),
+                }),
+                EventAst::IfStatement (IfStatement {
+                    test: Expression::Not (Box::new(Expression::Nullary (Requirement::HasSmashBall))),
+                    then_branch: Block { events: vec!(EventAst::ChangeSubaction (12)) },
+                    else_branch: Some(Box::new(Block { events: vec!(EventAst::ChangeSubaction (13)) })),
+                }),
+            )},
+            offset: 0,
+        };
+        let scan = scan_script(&script);
+        assert!(scan.checks_smash_ball);
+        assert!(!scan.final_smash_state);
+        assert_eq!(scan.gated_subactions, vec!(10, 13));
+    }
+
+    #[test]
+    fn ungated_script() {
+        let script = ScriptAst {
+            block: Block { events: vec!(EventAst::FinalSmashEnter, EventAst::ChangeSubaction (1)) },
+            offset: 0,
+        };
+        let scan = scan_script(&script);
+        assert!(!scan.checks_smash_ball);
+        assert!(scan.final_smash_state);
+        assert!(scan.gated_subactions.is_empty());
+    }
+}
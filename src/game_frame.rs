@@ -0,0 +1,120 @@
+//! Frame values that accumulate and compare the same way as the game.
+//!
+//! The game keeps the timer of a script as an f32 and adds the frame speed to it once every game frame.
+//! Frame speeds such as 0.9 are not exactly representable, so the error accumulates and the timer can fall just short of a value it would reach with exact arithmetic.
+//! e.g. 20 frames at 0.9 speed leave the timer just below 18, so an Asynchronous Timer 18 waits one more frame than `18 / 0.9` suggests.
+//! Calculating frames by dividing the wait by the speed disagrees with frames measured in game, so everything that converts timers to game frames goes through `GameFrame`.
+//!
+//! The rules followed are:
+//! *   The frame speed is added to the timer after the frame speed modifiers for the frame are applied and before the scripts run.
+//! *   A wait ends on the first game frame where the timer is not less than the target.
+//! *   Synchronous Timer N targets the timer value at the event plus N, calculated in f32.
+//! *   Frames converted to integers e.g. for the Current Frame variable and WiiRD frame speed modifiers are truncated, not rounded.
+
+use std::fmt;
+
+/// A timer value or frame number, stored in the same representation as the game.
+///
+/// Use `as_f32` to display it, arithmetic on the returned value will not match the game.
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct GameFrame (f32);
+
+impl GameFrame {
+    pub const ZERO: GameFrame = GameFrame (0.0);
+
+    pub fn new(value: f32) -> GameFrame {
+        GameFrame (value)
+    }
+
+    /// Returns the timer after one game frame at `speed`
+    pub fn advance(self, speed: f32) -> GameFrame {
+        GameFrame (self.0 + speed)
+    }
+
+    /// Returns the target of a Synchronous Timer of `frames` started at this timer value
+    pub fn offset(self, frames: f32) -> GameFrame {
+        GameFrame (self.0 + frames)
+    }
+
+    /// Returns true when a wait for `target` has ended
+    pub fn reached(self, target: GameFrame) -> bool {
+        self.0 >= target.0
+    }
+
+    /// Returns the number of game frames until the timer reaches `target` at a constant `speed`, accumulating one frame at a time as the game does.
+    /// Returns None when the timer never reaches the target e.g. the speed is 0 or too small to change the timer.
+    pub fn frames_until(self, target: GameFrame, speed: f32) -> Option<u32> {
+        self.advance_until(target, speed).map(|(_, frames)| frames)
+    }
+
+    /// Same as `frames_until` but also returns the timer on the game frame the target is reached.
+    pub fn advance_until(self, target: GameFrame, speed: f32) -> Option<(GameFrame, u32)> {
+        let mut timer = self;
+        let mut frames = 0;
+        while !timer.reached(target) {
+            let next = timer.advance(speed);
+            if next.0 <= timer.0 || next.0.is_nan() {
+                return None;
+            }
+            timer = next;
+            frames += 1;
+        }
+        Some((timer, frames))
+    }
+
+    /// Converts to an integer frame the way the game does, by truncating
+    pub fn truncate(self) -> i32 {
+        self.0 as i32
+    }
+
+    /// Returns the value for display
+    pub fn as_f32(self) -> f32 {
+        self.0
+    }
+}
+
+impl fmt::Display for GameFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accumulation_falls_short() {
+        let mut timer = GameFrame::ZERO;
+        for _ in 0..20 {
+            timer = timer.advance(0.9);
+        }
+        assert!(!timer.reached(GameFrame::new(18.0)));
+        assert!(timer.advance(0.9).reached(GameFrame::new(18.0)));
+
+        assert_eq!(GameFrame::ZERO.frames_until(GameFrame::new(18.0), 0.9), Some(21));
+        assert_eq!(GameFrame::ZERO.frames_until(GameFrame::new(7.0), 0.7), Some(11));
+        assert_eq!(GameFrame::ZERO.frames_until(GameFrame::new(12.0), 1.2), Some(11));
+        // exactly representable speeds match division
+        assert_eq!(GameFrame::ZERO.frames_until(GameFrame::new(9.0), 0.5), Some(18));
+        assert_eq!(GameFrame::ZERO.frames_until(GameFrame::new(9.0), 1.0), Some(9));
+
+        let (timer, frames) = GameFrame::ZERO.advance_until(GameFrame::new(18.0), 0.9).unwrap();
+        assert_eq!(frames, 21);
+        assert!(timer.reached(GameFrame::new(18.0)) && timer.as_f32() > 18.0);
+    }
+
+    #[test]
+    fn never_reached() {
+        assert_eq!(GameFrame::ZERO.frames_until(GameFrame::new(1.0), 0.0), None);
+        assert_eq!(GameFrame::new(1e8).frames_until(GameFrame::new(2e8), 1.0), None);
+        assert_eq!(GameFrame::new(5.0).frames_until(GameFrame::new(1.0), 0.0), Some(0));
+    }
+
+    #[test]
+    fn truncate() {
+        assert_eq!(GameFrame::new(4.9999995).truncate(), 4);
+        assert_eq!(GameFrame::new(5.0).truncate(), 5);
+    }
+}
@@ -0,0 +1,309 @@
+use std::ops::Range;
+
+use cgmath::Vector3;
+
+use crate::high_level_fighter::{HighLevelFighter, HighLevelFrame, HighLevelSubaction, CollisionBoxValues};
+use crate::script_ast::{Block, EventAst, GrabBoxArguments};
+
+/// The grab boxes and reach of a single grab.
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct GrabData {
+    pub grab: Grab,
+    pub subaction: String,
+    /// Every grab collision event in the main script, in the order they appear, including those inside if statements and loops.
+    pub grab_boxes: Vec<GrabBoxArguments>,
+    /// Frames where at least one grab box is active.
+    /// Frames start at 1 and the end of each range is exclusive.
+    pub active_frames: Vec<Range<f32>>,
+    /// How far in front of the fighters starting position the furthest grab box reaches on the first active frame.
+    /// The grab boxes are positioned with the bone transforms of that frame, so this includes any movement of TransN.
+    /// None when no grab box is ever active.
+    pub reach: Option<f32>,
+    /// How far the largest grab box reaches from the bone it is attached to, ignoring its vertical offset.
+    /// Unlike `reach` this does not depend on the animation, `KitSummary::grab_range` is this value for the standing grab.
+    /// None when the script creates no grab boxes.
+    pub bone_range: Option<f32>,
+    /// Where the ThrowN bone, that a grabbed opponent is held at, is on the first active frame.
+    /// Relative to the fighters starting position, in the same space as the hitbox positions.
+    /// None when no grab box is ever active or the fighter has no ThrowN bone.
+    pub throw_n: Option<Vector3<f32>>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum Grab {
+    Standing,
+    Dash,
+    Pivot,
+}
+
+impl Grab {
+    pub const ALL: [Grab; 3] = [Grab::Standing, Grab::Dash, Grab::Pivot];
+
+    pub fn subaction_name(&self) -> &'static str {
+        match self {
+            Grab::Standing => "Catch",
+            Grab::Dash     => "CatchDash",
+            Grab::Pivot    => "CatchTurn",
+        }
+    }
+}
+
+impl HighLevelFighter {
+    /// Returns the grab data of each grab the fighter has.
+    pub fn grab_data(&self) -> Vec<GrabData> {
+        let mut grabs = vec!();
+        for grab in Grab::ALL.iter().cloned() {
+            if let Some(subaction) = self.subactions.iter().find(|x| x.name == grab.subaction_name()) {
+                let grab_boxes = grab_boxes(&subaction.scripts.script_main.block);
+                let first_active = subaction.frames.iter().find(|x| is_grab_active(x));
+
+                grabs.push(GrabData {
+                    grab,
+                    subaction:     subaction.name.clone(),
+                    bone_range:    bone_range(&grab_boxes),
+                    grab_boxes,
+                    active_frames: active_frames(subaction),
+                    reach:         subaction.frames.iter().find_map(frame_reach),
+                    throw_n:       first_active.and_then(|frame| frame.throw_n.map(|x| x + Vector3::new(0.0, frame.y_pos, frame.x_pos))),
+                });
+            }
+        }
+        grabs
+    }
+}
+
+/// Returns every grab box created by the block, including those inside if statements and loops
+pub(crate) fn grab_boxes(block: &Block) -> Vec<GrabBoxArguments> {
+    let mut grab_boxes = vec!();
+    block_grab_boxes(block, &mut grab_boxes);
+    grab_boxes
+}
+
+/// Returns the furthest horizontal reach of the grab boxes from the bones they are attached to, None when there are no grab boxes
+pub(crate) fn bone_range(grab_boxes: &[GrabBoxArguments]) -> Option<f32> {
+    grab_boxes.iter()
+        .map(|x| x.x_offset.hypot(x.z_offset) + x.size)
+        .fold(None, |range: Option<f32>, x| Some(range.map(|range| range.max(x)).unwrap_or(x)))
+}
+
+fn block_grab_boxes(block: &Block, grab_boxes: &mut Vec<GrabBoxArguments>) {
+    for event in &block.events {
+        match event {
+            EventAst::CreateGrabBox (args) => grab_boxes.push(args.clone()),
+            EventAst::IfStatement (if_statement) => {
+                block_grab_boxes(&if_statement.then_branch, grab_boxes);
+                if let Some(else_branch) = &if_statement.else_branch {
+                    block_grab_boxes(else_branch, grab_boxes);
+                }
+            }
+            EventAst::ForLoop (for_loop) => block_grab_boxes(&for_loop.block, grab_boxes),
+            _ => { }
+        }
+    }
+}
+
+fn is_grab_active(frame: &HighLevelFrame) -> bool {
+    frame.hit_boxes.iter().any(|x| matches!(x.next_values, CollisionBoxValues::Grab (_)))
+}
+
+/// Returns the furthest horizontal point of the frames grab boxes, None when the frame has no grab boxes
+fn frame_reach(frame: &HighLevelFrame) -> Option<f32> {
+    frame.hit_boxes.iter()
+        .filter(|x| matches!(x.next_values, CollisionBoxValues::Grab (_)))
+        .map(|x| frame.x_pos + x.next_pos.z + x.next_size)
+        .fold(None, |reach: Option<f32>, x| Some(reach.map(|reach| reach.max(x)).unwrap_or(x)))
+}
+
+fn active_frames(subaction: &HighLevelSubaction) -> Vec<Range<f32>> {
+    let mut windows = vec!();
+    let mut start = None;
+    for (i, frame) in subaction.frames.iter().enumerate() {
+        let frame_number = i as f32 + 1.0;
+        match (start, is_grab_active(frame)) {
+            (None, true)          => start = Some(frame_number),
+            (Some(window), false) => {
+                windows.push(window..frame_number);
+                start = None;
+            }
+            _ => { }
+        }
+    }
+    if let Some(window) = start {
+        windows.push(window..subaction.frames.len() as f32 + 1.0);
+    }
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_frame::GameFrame;
+    use crate::high_level_fighter::{BoneTransforms, HighLevelHitBox, HighLevelScripts, ECB, gen_hit_boxes};
+    use crate::sakurai::fighter_data::AnimationFlags;
+    use crate::script_ast::{EdgeSlide, GrabTarget, ScriptAst};
+    use crate::script_runner::{ScriptCollisionBox, VelModify};
+    use crate::subaction_environment::Environment;
+    use crate::test_util::TestFighterBuilder;
+    use cgmath::{Matrix4, SquareMatrix};
+
+    const HAND_BONE: i32 = 5;
+
+    fn grab_box() -> GrabBoxArguments {
+        GrabBoxArguments {
+            hitbox_id:  0,
+            bone_index: HAND_BONE,
+            size:       3.0,
+            x_offset:   0.0,
+            y_offset:   1.0,
+            z_offset:   4.0,
+            set_action: 0x39,
+            target:     GrabTarget::GroundedOnly,
+            unk:        None,
+        }
+    }
+
+    /// TransN has moved 2 units forward and the hand is 3 units in front of and 8 units above TransN
+    fn bones() -> BoneTransforms {
+        let trans_n = Matrix4::from_translation(Vector3::new(0.0, 0.0, 2.0));
+        BoneTransforms {
+            index:            1,
+            transform_normal: trans_n,
+            transform_hitbox: Matrix4::identity(),
+            children:         vec!(BoneTransforms {
+                index:            HAND_BONE,
+                transform_normal: trans_n * Matrix4::from_translation(Vector3::new(0.0, 8.0, 3.0)),
+                transform_hitbox: Matrix4::identity(),
+                children:         vec!(),
+            }),
+        }
+    }
+
+    fn frame(grab_active: bool, x_pos: f32) -> HighLevelFrame {
+        let collision_box = ScriptCollisionBox {
+            bone_index:  HAND_BONE as i16,
+            hitbox_id:   0,
+            x_offset:    grab_box().x_offset,
+            y_offset:    grab_box().y_offset,
+            z_offset:    grab_box().z_offset,
+            size:        grab_box().size,
+            values:      CollisionBoxValues::from_grabbox(&grab_box()),
+            interpolate: false,
+        };
+        let hit_boxes = if grab_active {
+            gen_hit_boxes(&bones(), &[collision_box]).into_iter().map(|x| HighLevelHitBox {
+                hitbox_id:   x.hitbox_id,
+                bone_name:   None,
+                interpolate: false,
+                prev_pos:    None,
+                prev_size:   None,
+                prev_values: None,
+                next_pos:    x.position,
+                next_size:   x.size,
+                next_values: x.values,
+            }).collect()
+        } else {
+            vec!()
+        };
+
+        HighLevelFrame {
+            timer:                 GameFrame::ZERO,
+            hurt_boxes:            vec!(),
+            hit_boxes,
+            ledge_grab_box:        None,
+            throw_n:               if grab_active { Some(Vector3::new(0.0, 9.0, 5.0)) } else { None },
+            x_pos,
+            y_pos:                 0.0,
+            interruptible:         false,
+            edge_slide:            EdgeSlide::SlideOff,
+            reverse_direction:     false,
+            airbourne:             false,
+            landing_lag:           false,
+            ecb:                   ECB { left: -3.0, right: 3.0, top: 14.0, bottom: 0.0, transn_x: 0.0, transn_y: 0.0 },
+            hitbox_sets_rehit:     [false; 10],
+            slope_contour_stand:   None,
+            slope_contour_full:    None,
+            rumble:                None,
+            rumble_loop:           None,
+            grab_interrupt_damage: None,
+            throw:                 None,
+            effects:               vec!(),
+            color_flash:           None,
+            sword_glow:            None,
+            concurrent_loops:      vec!(),
+            x_vel_modify:          VelModify::None,
+            y_vel_modify:          VelModify::None,
+            movement_effects:      vec!(),
+            x_vel_temp:            0.0,
+            y_vel_temp:            0.0,
+        }
+    }
+
+    /// A standing grab that is active on frames 7 and 8, the fighter has slid forward 0.5 units by frame 7
+    fn catch() -> HighLevelSubaction {
+        let script = |events| ScriptAst { block: Block { events }, offset: 0 };
+        HighLevelSubaction {
+            name:            "Catch".into(),
+            iasa:            None,
+            frames:          (1..=30).map(|i| frame(i == 7 || i == 8, if i >= 7 { 0.5 } else { 0.0 })).collect(),
+            landing_lag:     None,
+            animation_flags: AnimationFlags::NONE,
+            scripts:         HighLevelScripts {
+                script_main:  script(vec!(
+                    EventAst::SyncWait (6.0),
+                    EventAst::CreateGrabBox (grab_box()),
+                    EventAst::SyncWait (2.0),
+                    EventAst::DeleteAllGrabBoxes,
+                )),
+                script_gfx:   script(vec!()),
+                script_sfx:   script(vec!()),
+                script_other: script(vec!()),
+            },
+            bad_interrupts:  false,
+            caveats:         vec!(),
+            environment:     Environment::Unknown,
+        }
+    }
+
+    #[test]
+    fn standing_grab_reach() {
+        let mut fighter = HighLevelFighter::new(&TestFighterBuilder::new("Test").build());
+        fighter.subactions = vec!(catch());
+
+        let grabs = fighter.grab_data();
+        assert_eq!(grabs.len(), 1);
+        let grab = &grabs[0];
+        assert_eq!(grab.grab, Grab::Standing);
+        assert_eq!(grab.subaction, "Catch");
+        assert_eq!(grab.grab_boxes.len(), 1);
+        assert_eq!(grab.grab_boxes[0].bone_index, HAND_BONE);
+        assert_eq!(grab.grab_boxes[0].size, 3.0);
+        assert_eq!(grab.active_frames, vec!(7.0..9.0));
+
+        // 0.5 slide + 2 TransN + 3 hand + 4 offset + 3 size
+        let reach = grab.reach.unwrap();
+        assert!((reach - 12.5).abs() < 0.001, "reach was {}", reach);
+        // 4 offset + 3 size, the hand bone is not included
+        assert_eq!(grab.bone_range, Some(7.0));
+        // the opponent is held 0.5 units further forward than ThrowN as the fighter has slid forward
+        assert_eq!(grab.throw_n, Some(Vector3::new(0.0, 9.0, 5.5)));
+    }
+
+    #[test]
+    fn no_grab_boxes() {
+        let mut fighter = HighLevelFighter::new(&TestFighterBuilder::new("Test").build());
+        let mut catch_dash = catch();
+        catch_dash.name = "CatchDash".into();
+        catch_dash.frames = (1..=10).map(|_| frame(false, 0.0)).collect();
+        fighter.subactions = vec!(catch_dash);
+
+        let grabs = fighter.grab_data();
+        assert_eq!(grabs.len(), 1);
+        assert_eq!(grabs[0].grab, Grab::Dash);
+        assert_eq!(grabs[0].active_frames, vec!());
+        assert_eq!(grabs[0].reach, None);
+        assert_eq!(grabs[0].bone_range, Some(7.0));
+        assert_eq!(grabs[0].throw_n, None);
+    }
+}
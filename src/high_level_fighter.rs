@@ -1,12 +1,19 @@
+use std::sync;
+
 use cgmath::{Point3, Vector3, Matrix4, SquareMatrix, InnerSpace, Transform};
 use rayon::prelude::*;
 
 use crate::chr0::Chr0;
-use crate::fighter::Fighter;
+use crate::arc::Arc;
+use crate::fighter::{Fighter, NotLoaded};
 use crate::mdl0::bones::Bone;
-use crate::sakurai::{SectionScript, ExternalSubroutine};
-use crate::sakurai::fighter_data::misc_section::{HurtBox, BoneRefs};
-use crate::sakurai::fighter_data::{FighterAttributes, AnimationFlags};
+use crate::game_frame::GameFrame;
+use crate::movement::MovementEffect;
+use crate::sakurai::ExternalSubroutine;
+use crate::script::Script;
+use crate::subaction_environment::Environment;
+use crate::sakurai::fighter_data::misc_section::{HurtBox, BoneRefs, Crawl, Glide, MultiJump};
+use crate::sakurai::fighter_data::{ArcFighterData, FighterAttributes, AnimationFlags, ActionFlags};
 use crate::script_ast::{
     ScriptAst,
     HitBoxArguments,
@@ -20,22 +27,35 @@ use crate::script_ast::{
     HitBoxSseType,
     GrabTarget,
     LedgeGrabEnable,
+    ColorFlash,
+    SwordGlow,
 };
-use crate::script_runner::{ScriptRunner, ChangeSubaction, ScriptCollisionBox, VelModify};
+use crate::script_runner::{ScriptRunner, ChangeSubaction, ScriptCollisionBox, ScriptEffect, ConcurrentLoop, VelModify, TimelineCaveat};
 use crate::init_hack_script::init_hack_script;
 
 /// The HighLevelFighter stores processed Fighter data in a format that is easy to read from.
 /// If brawllib_rs eventually implements the ability to modify character files via modifying Fighter and its children, then HighLevelFighter WILL NOT support that.
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct HighLevelFighter {
     pub name:                     String,
     pub internal_name:            String,
     pub attributes:               FighterAttributes,
+    /// None when the fighter cannot crawl
+    pub crawl:                    Option<Crawl>,
+    /// None when the fighter cannot glide
+    pub glide:                    Option<Glide>,
+    /// None when the fighter does not have multiple aerial jumps
+    pub multi_jump:               Option<MultiJump>,
     pub actions:                  Vec<HighLevelAction>,
     pub subactions:               Vec<HighLevelSubaction>,
     pub scripts_fragment_fighter: Vec<ScriptAst>,
-    pub scripts_fragment_common:  Vec<ScriptAst>,
+    /// Shared with every other `HighLevelFighter` created with the same `CommonScripts`
+    pub scripts_fragment_common:  Vec<sync::Arc<ScriptAst>>,
     pub scripts_section:          Vec<SectionScriptAst>,
+    /// The data the fighter was loaded without, see `LoadDetail`.
+    /// Without models there are no subactions, as the hitboxes and hurtboxes are positioned by the bones.
+    pub not_loaded:               Vec<NotLoaded>,
 }
 
 impl HighLevelFighter {
@@ -45,14 +65,41 @@ impl HighLevelFighter {
     // However it may be ineffecient due to overhead of spawning threads for every action.
     // Will need to benchmark any such changes.
     pub fn new(fighter: &Fighter) -> HighLevelFighter {
+        HighLevelFighter::new_with_common(fighter, &CommonScripts::new(fighter))
+    }
+
+    /// Same as `new` but the scripts of the common Fighter.pac are taken from `common` instead of being processed again.
+    /// `common` must have been created from a fighter with the same `Fighter::moveset_common`, use `CommonScripts::for_fighters` to create them.
+    pub fn new_with_common(fighter: &Fighter, common: &CommonScripts) -> HighLevelFighter {
+        let (mut high_level_fighter, generator) = HighLevelFighter::without_subactions(fighter, common);
+        // TODO: After fixing a bug, where a huge amount of needless work was being done, parallelizing this doesnt get us as much.
+        // It might be better for the caller of HighLevelFighter::new() to do the parallelization.
+        high_level_fighter.subactions = (0..generator.len()).into_par_iter().map(|i| generator.subaction(i)).collect();
+        high_level_fighter
+    }
+
+    /// Same as `new_with_common` but the returned `HighLevelFighter` has no subactions,
+    /// they are instead generated one at a time by the returned `SubactionGenerator`.
+    pub(crate) fn without_subactions<'a>(fighter: &'a Fighter, common: &'a CommonScripts) -> (HighLevelFighter, SubactionGenerator<'a>) {
         info!("Generating HighLevelFighter for {}", fighter.cased_name);
         let fighter_sakurai = fighter.get_fighter_sakurai().unwrap();
-        let fighter_sakurai_common = fighter.get_fighter_sakurai_common().unwrap();
         let fighter_data = fighter.get_fighter_data().unwrap();
-        let fighter_data_common = fighter.get_fighter_data_common().unwrap();
-        let fighter_data_common_scripts = fighter.get_fighter_data_common_scripts();
         let attributes = fighter_data.attributes.clone();
-        let fighter_animations = fighter.get_animations();
+        let mut not_loaded = vec!();
+        let fighter_animations = match fighter.get_animations() {
+            Ok(animations) => animations,
+            Err(err) => {
+                not_loaded.push(err);
+                vec!()
+            }
+        };
+        let first_bone = match fighter.get_bones() {
+            Ok(first_bone) => first_bone,
+            Err(err) => {
+                not_loaded.push(err);
+                None
+            }
+        };
 
         let fragment_scripts_fighter: Vec<_> = fighter_sakurai.fragment_scripts.iter().map(|x| ScriptAst::new(x)).collect();
         let subaction_main:           Vec<_> = fighter_data.subaction_main  .iter().map(|x| ScriptAst::new(x)).collect();
@@ -60,45 +107,14 @@ impl HighLevelFighter {
         let subaction_sfx:            Vec<_> = fighter_data.subaction_sfx   .iter().map(|x| ScriptAst::new(x)).collect();
         let subaction_other:          Vec<_> = fighter_data.subaction_other .iter().map(|x| ScriptAst::new(x)).collect();
 
-        let fragment_scripts_common: Vec<_> = fighter_sakurai_common.fragment_scripts.iter().map(|x| ScriptAst::new(x)).collect();
-
-        let scripts_section: Vec<_> = fighter_data_common_scripts.iter().map(|x| SectionScriptAst::new(x, &fighter_sakurai.external_subroutines)).collect();
-
-        let entry_actions_common: Vec<_> = fighter_data_common.entry_actions.iter().map(|x| ScriptAst::new(x)).collect();
-        let entry_actions:        Vec<_> = fighter_data       .entry_actions.iter().map(|x| ScriptAst::new(x)).collect();
-        let exit_actions_common:  Vec<_> = fighter_data_common.exit_actions .iter().map(|x| ScriptAst::new(x)).collect();
-        let exit_actions:         Vec<_> = fighter_data       .exit_actions .iter().map(|x| ScriptAst::new(x)).collect();
-
-        let mut fighter_scripts = vec!();
-        for script in fragment_scripts_fighter.iter()
-            .chain(subaction_main.iter())
-            .chain(subaction_gfx.iter())
-            .chain(subaction_sfx.iter())
-            .chain(subaction_other.iter())
-            .chain(entry_actions.iter())
-            .chain(exit_actions.iter())
-        {
-            fighter_scripts.push(script);
-        }
+        let fragment_scripts_common = common.fragments.clone();
 
-        let mut common_scripts = vec!();
-        for script in fragment_scripts_common.iter()
-            .chain(scripts_section.iter().map(|x| &x.script))
-            .chain(entry_actions_common.iter())
-            .chain(exit_actions_common.iter())
-        {
-            common_scripts.push(script);
-        }
+        let scripts_section: Vec<_> = common.sections.iter().map(|(name, script)| SectionScriptAst::new(name, script, &fighter_sakurai.external_subroutines)).collect();
 
-        let mut subaction_scripts = vec!();
-        for i in 0..subaction_main.len() {
-            subaction_scripts.push(HighLevelScripts {
-                script_main:  subaction_main[i].clone(),
-                script_gfx:   subaction_gfx[i].clone(),
-                script_sfx:   subaction_sfx[i].clone(),
-                script_other: subaction_other[i].clone(),
-            });
-        }
+        let entry_actions_common = &common.entry_actions;
+        let entry_actions:        Vec<_> = fighter_data.entry_actions.iter().map(|x| sync::Arc::new(ScriptAst::new(x))).collect();
+        let exit_actions_common  = &common.exit_actions;
+        let exit_actions:         Vec<_> = fighter_data.exit_actions .iter().map(|x| sync::Arc::new(ScriptAst::new(x))).collect();
 
         let mut actions = vec!();
         for i in 0..entry_actions_common.len() {
@@ -106,17 +122,17 @@ impl HighLevelFighter {
 
             let entry_action_override = fighter_data.entry_action_overrides.iter().find(|x| x.action_id == i as u32);
             let (script_entry, script_entry_common) = if let Some(action_override) = entry_action_override {
-                (ScriptAst::new(&action_override.script), false)
+                (sync::Arc::new(ScriptAst::new(&action_override.script)), false)
             } else {
                 (entry_actions_common[i].clone(), true)
             };
             let exit_action_override = fighter_data.exit_action_overrides.iter().find(|x| x.action_id == i as u32);
             let (script_exit, script_exit_common) = if let Some(action_override) = exit_action_override {
-                (ScriptAst::new(&action_override.script), false)
+                (sync::Arc::new(ScriptAst::new(&action_override.script)), false)
             } else {
                 (exit_actions_common[i].clone(), true)
             };
-            actions.push(HighLevelAction { name, script_entry, script_exit, script_entry_common, script_exit_common });
+            actions.push(HighLevelAction { name, script_entry, script_exit, script_entry_common, script_exit_common, flags: None });
         }
 
         for i in 0..entry_actions.len() {
@@ -126,293 +142,48 @@ impl HighLevelFighter {
                 script_entry_common: false,
                 script_exit:         exit_actions[i].clone(),
                 script_exit_common:  false,
+                flags:               fighter_data.action_flags.get(i).cloned(),
             });
         }
 
-        let subactions = if let Some(first_bone) = fighter.get_bones() {
-            // TODO: After fixing a bug, where a huge amount of needless work was being done, parallelizing this doesnt get us as much.
-            // It might be better for the caller of HighLevelFighter::new() to do the parallelization.
-            subaction_scripts.into_par_iter().enumerate().map(|(i, scripts)| {
-                let subaction_flags = &fighter_data.subaction_flags[i];
-                let actual_name = subaction_flags.name.clone();
-
-                // create a unique name for this subaction
-                let mut count = 0;
-                for j in 0..i {
-                    if fighter_data.subaction_flags[j].name == actual_name {
-                        count += 1;
-                    }
-                }
-                let name = if count == 0 {
-                    actual_name.clone()
-                } else {
-                    format!("{}_{}", actual_name, count)
-                };
-
-                let animation_flags = subaction_flags.animation_flags.clone();
-
-                let chr0 = fighter_animations.iter().find(|x| x.name == actual_name);
-                let subaction_scripts = vec!(&scripts.script_main, &scripts.script_gfx, &scripts.script_sfx, &scripts.script_other);
-                let init_hack_script = init_hack_script(&fighter.cased_name, &actual_name);
-
-                let mut frames: Vec<HighLevelFrame> = vec!();
-                let mut prev_animation_xyz_offset = Vector3::new(0.0, 0.0, 0.0);
-                let mut script_runner = ScriptRunner::new(i, &fighter.wiird_frame_speed_modifiers, &subaction_scripts, &fighter_scripts, &common_scripts, &scripts_section, &init_hack_script, &fighter_data, actual_name.clone());
-                let mut iasa = None;
-                let mut prev_hit_boxes: Option<Vec<PositionHitBox>> = None;
-
-                if let Some(chr0) = chr0 {
-                    let num_frames = match actual_name.as_ref() {
-                        "JumpSquat"    => attributes.jump_squat_frames as f32,
-                        "LandingAirN"  => attributes.nair_landing_lag,
-                        "LandingAirF"  => attributes.fair_landing_lag,
-                        "LandingAirB"  => attributes.bair_landing_lag,
-                        "LandingAirHi" => attributes.uair_landing_lag,
-                        "LandingAirLw" => attributes.dair_landing_lag,
-                        "LandingLight" => attributes.light_landing_lag, // TODO: This needs +1 do the others?!?!?
-                        "LandingHeavy" => attributes.normal_landing_lag,
-                        _              => chr0.num_frames as f32
-                    };
-
-                    let mut x_vel = 0.0;
-                    let mut y_vel = 0.0;
-
-                    let mut x_pos = 0.0;
-                    let mut y_pos = 0.0;
-
-                    while script_runner.animation_index < num_frames {
-                        let chr0_frame_index = script_runner.animation_index * chr0.num_frames as f32 / num_frames; // map frame count between [0, chr0.num_frames]
-                        let (animation_xyz_offset, frame_bones) = HighLevelFighter::transform_bones(
-                            &first_bone,
-                            &fighter_data.misc.bone_refs,
-                            Matrix4::<f32>::identity(),
-                            Matrix4::<f32>::identity(),
-                            chr0,
-                            chr0_frame_index as i32,
-                            animation_flags,
-                            fighter_data.attributes.size
-                        );
-                        let animation_xyz_offset = animation_xyz_offset.unwrap_or(Vector3::new(0.0, 0.0, 0.0));
-                        // TODO: should DisableMovement affect xyz_offset from transform_bones?????
-                        // script runner x-axis is equivalent to model z-axis
-
-                        let animation_xyz_velocity = animation_xyz_offset - prev_animation_xyz_offset;
-                        prev_animation_xyz_offset = animation_xyz_offset;
-
-                        let x_vel_modify = script_runner.x_vel_modify.clone();
-                        let y_vel_modify = script_runner.y_vel_modify.clone();
-
-                        let x_vel_temp = animation_xyz_velocity.z;
-                        let y_vel_temp = animation_xyz_velocity.y;
-
-                        match x_vel_modify {
-                            VelModify::Set (vel) => x_vel = vel,
-                            VelModify::Add (vel) => x_vel += vel,
-                            VelModify::None      => { }
-                        }
-
-                        match y_vel_modify {
-                            VelModify::Set (vel) => y_vel = vel,
-                            VelModify::Add (vel) => y_vel += vel,
-                            VelModify::None      => { }
-                        }
-
-                        x_pos += x_vel + x_vel_temp;
-                        y_pos += y_vel + y_vel_temp;
-
-                        let hurt_boxes = gen_hurt_boxes(&frame_bones, &fighter_data.misc.hurt_boxes, &script_runner, fighter_data.attributes.size);
-                        let hit_boxes: Vec<_> = script_runner.hitboxes.iter().filter(|x| x.is_some()).map(|x| x.clone().unwrap()).collect();
-                        let hit_boxes = gen_hit_boxes(&frame_bones, &hit_boxes);
-                        let mut hl_hit_boxes = vec!();
-                        for next in &hit_boxes {
-                            let mut prev_pos = None;
-                            let mut prev_size = None;
-                            let mut prev_values = None;
-                            if next.interpolate {
-                                if let &Some(ref prev_hit_boxes) = &prev_hit_boxes {
-                                    for prev_hit_box in prev_hit_boxes {
-                                        if prev_hit_box.hitbox_id == next.hitbox_id {
-                                            // A bit hacky, but we need to undo the movement that occured this frame to get the correct hitbox interpolation
-                                            prev_pos = Some(prev_hit_box.position - Vector3::new(0.0, y_vel, x_vel));
-                                            prev_size = Some(prev_hit_box.size);
-                                            prev_values = Some(prev_hit_box.values.clone());
-                                        }
-                                    }
-                                }
-                            }
-                            hl_hit_boxes.push(HighLevelHitBox {
-                                hitbox_id: next.hitbox_id,
-
-                                prev_pos,
-                                prev_size,
-                                prev_values,
-
-                                next_pos:    next.position,
-                                next_size:   next.size,
-                                next_values: next.values.clone(),
-                            });
-                        }
-                        hl_hit_boxes.sort_by_key(|x| x.hitbox_id);
-
-                        let mut option_ecb = None;
-                        for misc_ecb in &fighter_data.misc.ecbs {
-                            let min_ecb = ECB {
-                                // This implementation is just a guess from my observations that:
-                                // *    The higher the min_width the higher the right ecb point.
-                                // *    The higher the min_width the lower the left ecb point.
-                                // *    When further than all bones, both points move equally far apart.
-                                // *    When further than all bones, actions that affect the ecb horizontally no longer affect the ecb e.g. marth jab
-                                left:     -misc_ecb.min_width / 2.0, // TODO: Should I divide by 2.0 here?
-                                right:    misc_ecb.min_width / 2.0, // TODO: Should I divide by 2.0 here?
-                                top:      -10000.0,
-                                bottom:   10000.0,
-                                transn_x: 0.0,
-                                transn_y: 0.0,
-                            };
-                            let mut ecb = gen_ecb(&frame_bones, &misc_ecb.bones, &fighter_data.misc.bone_refs, min_ecb);
-
-                            // This implementation is just a guess from my observations that:
-                            // *    The higher the min_height the higher the top ecb point.
-                            // *    The higher the min_height the lower the bottom ecb point, capping out at transN.
-                            // *    Actions such as crouching, lower the height of the top ecb point.
-                            let middle_y = (ecb.top + ecb.bottom) / 2.0;
-                            let new_top    = middle_y + misc_ecb.min_height / 2.0;
-                            let new_bottom = middle_y - misc_ecb.min_height / 2.0;
-                            if new_top > ecb.top {
-                                ecb.top = new_top;
-                            }
-                            if new_bottom < ecb.bottom {
-                                ecb.bottom = new_bottom;
-                            }
-                            if ecb.bottom < ecb.transn_y {
-                                ecb.bottom = ecb.transn_y
-                            }
-
-                            option_ecb = Some(ecb);
-                        }
-                        let ecb = option_ecb.unwrap();
-
-                        let weight_dependent_speed = match actual_name.as_ref() {
-                            "ThrowLw" => attributes.weight_dependent_throw_down,
-                            "ThrowHi" => attributes.weight_dependent_throw_up,
-                            "ThrowF" => attributes.weight_dependent_throw_forward,
-                            "ThrowB" => attributes.weight_dependent_throw_backward,
-                            _        => false,
-                        };
-
-                        let mut throw = None;
-                        if let Some(ref specify_throw) = script_runner.throw {
-                            if script_runner.throw_activate {
-                                throw = Some(HighLevelThrow {
-                                    damage:      specify_throw.damage,
-                                    trajectory:  specify_throw.trajectory,
-                                    kbg:         specify_throw.kbg,
-                                    wdsk:        specify_throw.wdsk,
-                                    bkb:         specify_throw.bkb,
-                                    effect:      specify_throw.effect.clone(),
-                                    sfx:         specify_throw.sfx.clone(),
-                                    grab_target: specify_throw.grab_target.clone(),
-                                    i_frames:    specify_throw.i_frames,
-                                    weight_dependent_speed,
-                                });
-                            }
-                        }
-
-                        let ledge_grab_box = if script_runner.ledge_grab_enable.enabled() {
-                            // The first misc.ledge_grabs entry seems to be used for everything, not sure what the other entries are for.
-                            if let Some(ledge_grab_box) = fighter_data.misc.ledge_grab_boxes.get(0) {
-                                let left = if let LedgeGrabEnable::EnableInFrontAndBehind = script_runner.ledge_grab_enable {
-                                    ecb.left - ledge_grab_box.x_padding
-                                } else {
-                                    ledge_grab_box.x_left
-                                };
-
-                                Some(Extent {
-                                    left,
-                                    right:  ecb.right + ledge_grab_box.x_padding,
-                                    up:     ledge_grab_box.y + ledge_grab_box.height,
-                                    down:   ledge_grab_box.y,
-                                })
-                            } else {
-                                None
-                            }
-                        } else {
-                            None
-                        };
-
-                        frames.push(HighLevelFrame {
-                            throw,
-                            ecb,
-                            x_pos,
-                            y_pos,
-                            x_vel_modify,
-                            y_vel_modify,
-                            x_vel_temp,
-                            y_vel_temp,
-                            ledge_grab_box,
-                            hurt_boxes,
-                            hit_boxes:             hl_hit_boxes,
-                            interruptible:         script_runner.interruptible,
-                            landing_lag:           script_runner.landing_lag,
-                            edge_slide:            script_runner.edge_slide.clone(),
-                            reverse_direction:     script_runner.reverse_direction.clone(),
-                            airbourne:             script_runner.airbourne,
-                            hitbox_sets_rehit:     script_runner.hitbox_sets_rehit,
-                            slope_contour_stand:   script_runner.slope_contour_stand,
-                            slope_contour_full:    script_runner.slope_contour_full,
-                            rumble:                script_runner.rumble,
-                            rumble_loop:           script_runner.rumble_loop,
-                            grab_interrupt_damage: script_runner.grab_interrupt_damage,
-                        });
-
-                        if iasa.is_none() && script_runner.interruptible {
-                            iasa = Some(script_runner.frame_count)
-                        }
-
-                        script_runner.step();
-                        prev_hit_boxes = Some(hit_boxes);
-
-                        if let ChangeSubaction::Continue = script_runner.change_subaction { } else { break }
-                    }
-                }
-
-                if iasa.is_none() {
-                    iasa = match actual_name.as_ref() {
-                        "LandingAirN"  | "LandingAirF" |
-                        "LandingAirB"  | "LandingAirHi" |
-                        "LandingAirLw" | "LandingLight" |
-                        "LandingHeavy" | "LandingFallSpecial"
-                          => Some(script_runner.frame_count),
-                        _ => None
-                    }
-                };
-
-                let landing_lag = match actual_name.as_ref() {
-                    "AttackAirN"  => Some(attributes.nair_landing_lag),
-                    "AttackAirF"  => Some(attributes.fair_landing_lag),
-                    "AttackAirB"  => Some(attributes.bair_landing_lag),
-                    "AttackAirHi" => Some(attributes.uair_landing_lag),
-                    "AttackAirLw" => Some(attributes.dair_landing_lag),
-                    _             => None,
-                };
-
-                let bad_interrupts = script_runner.bad_interrupts.len() > 0;
-
-                HighLevelSubaction { name, iasa, landing_lag, frames, animation_flags, scripts, bad_interrupts }
-            }).collect()
-        } else {
-            vec!()
-        };
-
-        HighLevelFighter {
+        let high_level_fighter = HighLevelFighter {
             internal_name:            fighter.cased_name.clone(),
             name:                     crate::fighter_maps::fighter_name(&fighter.cased_name),
-            scripts_fragment_fighter: fragment_scripts_fighter,
+            scripts_fragment_fighter: fragment_scripts_fighter.clone(),
             scripts_fragment_common:  fragment_scripts_common,
-            scripts_section,
+            scripts_section:          scripts_section.clone(),
             attributes,
+            crawl:                    fighter_data.misc.crawl.clone(),
+            glide:                    fighter_data.misc.glide.clone(),
+            multi_jump:               fighter_data.misc.multi_jump.clone(),
             actions,
-            subactions,
+            subactions:               vec!(),
+            not_loaded,
+        };
+        let generator = SubactionGenerator {
+            fighter,
+            fighter_data,
+            common,
+            fighter_animations,
+            first_bone,
+            fragment_scripts_fighter,
+            subaction_main,
+            subaction_gfx,
+            subaction_sfx,
+            subaction_other,
+            entry_actions,
+            exit_actions,
+            scripts_section,
+            environments: fighter.subaction_environments(),
+        };
+        (high_level_fighter, generator)
+    }
+
+    /// Returns the fragment script at `offset` in the file specified by `source`
+    pub fn fragment_script(&self, source: ScriptSource, offset: i32) -> Option<&ScriptAst> {
+        match source {
+            ScriptSource::Fighter => self.scripts_fragment_fighter.binary_search_by_key(&offset, |x| x.offset).ok().map(|i| &self.scripts_fragment_fighter[i]),
+            ScriptSource::CommonPac => self.scripts_fragment_common.binary_search_by_key(&offset, |x| x.offset).ok().map(|i| self.scripts_fragment_common[i].as_ref()),
         }
     }
 
@@ -482,17 +253,23 @@ pub struct BoneTransforms {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct HighLevelAction {
     pub name:         String,
-    pub script_entry: ScriptAst,
-    pub script_exit:  ScriptAst,
+    /// Shared with other `HighLevelFighter`s when it is from the common Fighter.pac
+    pub script_entry: sync::Arc<ScriptAst>,
+    /// Shared with other `HighLevelFighter`s when it is from the common Fighter.pac
+    pub script_exit:  sync::Arc<ScriptAst>,
     /// This is needed to determine where Goto/Subroutine events are pointing
     pub script_entry_common: bool,
     /// This is needed to determine where Goto/Subroutine events are pointing
     pub script_exit_common: bool,
+    /// Only fighter specific actions have flags stored in the fighters moveset
+    pub flags: Option<ActionFlags>,
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct HighLevelSubaction {
     pub name:            String,
     pub iasa:            Option<usize>,
@@ -502,9 +279,21 @@ pub struct HighLevelSubaction {
     pub scripts:         HighLevelScripts,
     /// A hack where bad interrupts are ignored was used to process this subaction
     pub bad_interrupts:  bool,
+    /// Control flow in the scripts that could not be followed, the frames are inaccurate from the first caveat onwards
+    pub caveats:         Vec<TimelineCaveat>,
+    /// Whether the subaction is performed on the ground, in the air or both, see `Fighter::subaction_environment`
+    pub environment:     Environment,
 }
 
 impl HighLevelSubaction {
+    /// Returns every velocity change and air/ground transition made by the scripts, along with the index of the frame it occured on.
+    /// Compare the summaries of two versions of a subaction with `movement::movement_changes`.
+    pub fn movement_effects(&self) -> Vec<(usize, MovementEffect)> {
+        self.frames.iter().enumerate()
+            .flat_map(|(i, frame)| frame.movement_effects.iter().map(move |effect| (i, effect.clone())))
+            .collect()
+    }
+
     /// Furthest point of a hitbox, starting from the bps
     /// Furthest values across all frames
     pub fn hit_box_extent(&self) -> Extent {
@@ -570,6 +359,7 @@ impl HighLevelSubaction {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct HighLevelScripts {
     pub script_main:  ScriptAst,
     pub script_gfx:   ScriptAst,
@@ -578,6 +368,7 @@ pub struct HighLevelScripts {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct HighLevelThrow {
     // TODO: I imagine the bone is used to determine the location the character is thrown from.
     // Transform the bone into an xy offset.
@@ -594,10 +385,18 @@ pub struct HighLevelThrow {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct HighLevelFrame {
+    /// The script timer the scripts ran against on this frame, affected by frame speed modifiers.
+    /// Use `GameFrame::reached` to compare it with a timer value, comparing `GameFrame::as_f32` values will not match the game.
+    pub timer:                 GameFrame,
     pub hurt_boxes:            Vec<HighLevelHurtBox>,
     pub hit_boxes:             Vec<HighLevelHitBox>,
     pub ledge_grab_box:        Option<Extent>,
+    /// Position of the ThrowN bone, which grabbed opponents and thrown items are attached to.
+    /// Positioned in the same space as the hitboxes, so it does not include `x_pos` or `y_pos`.
+    /// None when the fighter data does not refer to a ThrowN bone.
+    pub throw_n:               Option<Vector3<f32>>,
     pub x_pos:                 f32,
     pub y_pos:                 f32,
     pub interruptible:         bool,
@@ -613,10 +412,20 @@ pub struct HighLevelFrame {
     pub rumble_loop:           Option<(i32, i32)>,
     pub grab_interrupt_damage: Option<i32>,
     pub throw:                 Option<HighLevelThrow>,
+    /// Sound and graphic events that occured on this frame, tagged with the script they came from
+    pub effects:               Vec<ScriptEffect>,
+    /// The flash color the fighter is tinted with on this frame, see `EventAst::color_flash`
+    pub color_flash:           Option<ColorFlash>,
+    /// The sword glow drawn on this frame
+    pub sword_glow:            Option<SwordGlow>,
+    /// Concurrent loops started by CallEveryFrame that are active at the end of this frame, the loops are run on the following frames until removed.
+    pub concurrent_loops:      Vec<ConcurrentLoop>,
     /// Affects the next frames velocity
     pub x_vel_modify: VelModify,
     /// Affects the next frames velocity
     pub y_vel_modify: VelModify,
+    /// Velocity changes and air/ground transitions made by the scripts on this frame
+    pub movement_effects: Vec<MovementEffect>,
     /// Does not affect the next frames velocity
     pub x_vel_temp: f32,
     /// Does not affect the next frames velocity
@@ -624,6 +433,11 @@ pub struct HighLevelFrame {
 }
 
 impl HighLevelFrame {
+    /// Returns the area covered by every hitbox on this frame, positioned relative to where the fighter started the subaction
+    pub fn frame_hitboxes(&self) -> Vec<FrameHitbox> {
+        self.hit_boxes.iter().map(|x| x.frame_hitbox(self.x_pos, self.y_pos)).collect()
+    }
+
     /// Furthest point of a hitbox, starting from the bps
     pub fn hit_box_extent(&self) -> Extent {
         let mut extent = Extent::new();
@@ -760,6 +574,7 @@ impl HighLevelFrame {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct Extent {
     pub left:  f32,
     pub right: f32,
@@ -794,6 +609,7 @@ impl Extent {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct HighLevelHurtBox {
     pub bone_matrix: Matrix4<f32>,
     pub hurt_box: HurtBox,
@@ -801,6 +617,7 @@ pub struct HighLevelHurtBox {
 }
 
 #[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum CollisionBoxValues {
     Hit (HitBoxValues),
     Grab (GrabBoxValues),
@@ -918,6 +735,7 @@ impl CollisionBoxValues {
 }
 
 #[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct GrabBoxValues {
     pub hitbox_id:  i32,
     pub size:       f32,
@@ -927,6 +745,7 @@ pub struct GrabBoxValues {
 }
 
 #[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct HitBoxValues {
     pub hitbox_id:            u8,
     pub set_id:               u8,
@@ -1021,17 +840,23 @@ impl HitBoxValues {
 }
 
 #[derive(Clone, Debug)]
-struct PositionHitBox {
-    pub hitbox_id:   u8,
-    pub position:    Point3<f32>,
-    pub size:        f32,
-    pub interpolate: bool,
-    pub values:      CollisionBoxValues,
+pub(crate) struct PositionHitBox {
+    pub(crate) hitbox_id:   u8,
+    pub(crate) bone_index:  i32,
+    pub(crate) position:    Point3<f32>,
+    pub(crate) size:        f32,
+    pub(crate) interpolate: bool,
+    pub(crate) values:      CollisionBoxValues,
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct HighLevelHitBox {
     pub hitbox_id: u8,
+    /// The name of the bone the hitbox is attached to, None if the bone is not in the model
+    pub bone_name: Option<String>,
+    /// The hitbox hits everything between its position on the previous frame and the current frame
+    pub interpolate: bool,
 
     /// This value doesnt take into account the distance travelled by the character that HighLevelFighter doesnt know about e.g. due to velocity from previous subaction
     pub prev_pos:    Option<Point3<f32>>,
@@ -1043,7 +868,37 @@ pub struct HighLevelHitBox {
     pub next_values: CollisionBoxValues,
 }
 
+impl HighLevelHitBox {
+    /// Returns the position of the hitbox relative to where the fighter started the subaction, for a frame where the fighter is at `x_pos`, `y_pos`.
+    pub fn frame_hitbox(&self, x_pos: f32, y_pos: f32) -> FrameHitbox {
+        let offset = Vector3::new(0.0, y_pos, x_pos);
+        FrameHitbox {
+            hitbox_id:    self.hitbox_id,
+            center:       self.next_pos + offset,
+            radius:       self.next_size,
+            prev_center:  self.prev_pos.map(|x| x + offset),
+            interpolated: self.interpolate,
+        }
+    }
+}
+
+/// The area a hitbox covers on a frame, independent of how it is rendered.
+///
+/// An interpolated hitbox covers the capsule from `prev_center` to `center`.
+/// On the first frame a hitbox is active it is at its spawn position and does not sweep, so `prev_center` is None even when `interpolated` is true.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct FrameHitbox {
+    pub hitbox_id:    u8,
+    pub center:       Point3<f32>,
+    pub radius:       f32,
+    /// The center of the hitbox on the previous frame, only set for interpolated hitboxes that were also active on the previous frame
+    pub prev_center:  Option<Point3<f32>>,
+    pub interpolated: bool,
+}
+
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct ECB {
     pub left:     f32,
     pub right:    f32,
@@ -1084,6 +939,15 @@ fn gen_ecb(bone: &BoneTransforms, ecb_bones: &[i32], bone_refs: &BoneRefs, mut e
     ecb
 }
 
+/// Returns the position of the bone with the given index, None when the bone is not in the tree
+fn bone_position(bone: &BoneTransforms, index: i32) -> Option<Vector3<f32>> {
+    if bone.index == index {
+        Some(bone.transform_normal.w.truncate())
+    } else {
+        bone.children.iter().find_map(|x| bone_position(x, index))
+    }
+}
+
 fn gen_hurt_boxes(bone: &BoneTransforms, hurt_boxes: &[HurtBox], script_runner: &ScriptRunner, size: f32) -> Vec<HighLevelHurtBox> {
     let hurtbox_state_all = &script_runner.hurtbox_state_all;
     let hurtbox_states    = &script_runner.hurtbox_states;
@@ -1120,7 +984,45 @@ fn gen_hurt_boxes(bone: &BoneTransforms, hurt_boxes: &[HurtBox], script_runner:
     hl_hurt_boxes
 }
 
-fn gen_hit_boxes(bone: &BoneTransforms, hit_boxes: &[ScriptCollisionBox]) -> Vec<PositionHitBox> {
+/// Creates the `HighLevelHitBox`es of a frame, sorted by hitbox id.
+/// Interpolated hitboxes that also existed on the previous frame are given their previous position, moved back by `movement` so that it is relative to the current frame.
+/// The bone names are taken from `bones` when it is available.
+pub(crate) fn interpolate_hit_boxes(prev_hit_boxes: Option<&[PositionHitBox]>, hit_boxes: &[PositionHitBox], movement: Vector3<f32>, bones: Option<&Bone>) -> Vec<HighLevelHitBox> {
+    let mut hl_hit_boxes = vec!();
+    for next in hit_boxes {
+        let mut prev_pos = None;
+        let mut prev_size = None;
+        let mut prev_values = None;
+        if next.interpolate {
+            if let Some(prev_hit_boxes) = prev_hit_boxes {
+                for prev_hit_box in prev_hit_boxes {
+                    if prev_hit_box.hitbox_id == next.hitbox_id {
+                        prev_pos = Some(prev_hit_box.position - movement);
+                        prev_size = Some(prev_hit_box.size);
+                        prev_values = Some(prev_hit_box.values.clone());
+                    }
+                }
+            }
+        }
+        hl_hit_boxes.push(HighLevelHitBox {
+            hitbox_id:   next.hitbox_id,
+            bone_name:   bones.and_then(|x| x.find_by_index(next.bone_index)).map(|x| x.name.clone()),
+            interpolate: next.interpolate,
+
+            prev_pos,
+            prev_size,
+            prev_values,
+
+            next_pos:    next.position,
+            next_size:   next.size,
+            next_values: next.values.clone(),
+        });
+    }
+    hl_hit_boxes.sort_by_key(|x| x.hitbox_id);
+    hl_hit_boxes
+}
+
+pub(crate) fn gen_hit_boxes(bone: &BoneTransforms, hit_boxes: &[ScriptCollisionBox]) -> Vec<PositionHitBox> {
     let mut pos_hit_boxes = vec!();
     for hit_box in hit_boxes.iter() {
         if bone.index == get_bone_index(hit_box.bone_index as i32) {
@@ -1134,6 +1036,7 @@ fn gen_hit_boxes(bone: &BoneTransforms, hit_boxes: &[ScriptCollisionBox]) -> Vec
 
             pos_hit_boxes.push(PositionHitBox {
                 hitbox_id:   hit_box.hitbox_id,
+                bone_index:  bone.index,
                 size:        hit_box.size,
                 values:      hit_box.values.clone(),
                 interpolate: hit_box.interpolate,
@@ -1160,18 +1063,518 @@ pub fn get_bone_index(index: i32) -> i32 {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct SectionScriptAst {
     pub name:    String,
-    pub script:  ScriptAst,
+    /// Shared with other `HighLevelFighter`s, the section scripts are all from the common Fighter.pac
+    pub script:  sync::Arc<ScriptAst>,
     pub callers: Vec<i32>,
 }
 
 impl SectionScriptAst {
-    fn new(section_script: &SectionScript, external_subroutines: &[ExternalSubroutine]) -> SectionScriptAst {
+    fn new(name: &str, script: &sync::Arc<ScriptAst>, external_subroutines: &[ExternalSubroutine]) -> SectionScriptAst {
         SectionScriptAst {
-            name:    section_script.name.clone(),
-            script:  ScriptAst::new(&section_script.script),
-            callers: external_subroutines.iter().find(|x| x.name == section_script.name).map(|x| x.offsets.clone()).unwrap_or_default(),
+            name:    name.to_string(),
+            script:  script.clone(),
+            callers: external_subroutines.iter().find(|x| x.name == name).map(|x| x.offsets.clone()).unwrap_or_default(),
         }
     }
 }
+
+/// Generates the subactions of a `HighLevelFighter` one at a time, created by `HighLevelFighter::without_subactions`.
+pub(crate) struct SubactionGenerator<'a> {
+    fighter:                  &'a Fighter,
+    fighter_data:             &'a ArcFighterData,
+    common:                   &'a CommonScripts,
+    fighter_animations:       Vec<&'a Chr0>,
+    /// Subactions are only generated when the fighter has a model
+    first_bone:               Option<&'a Bone>,
+    fragment_scripts_fighter: Vec<ScriptAst>,
+    subaction_main:           Vec<ScriptAst>,
+    subaction_gfx:            Vec<ScriptAst>,
+    subaction_sfx:            Vec<ScriptAst>,
+    subaction_other:          Vec<ScriptAst>,
+    entry_actions:            Vec<sync::Arc<ScriptAst>>,
+    exit_actions:             Vec<sync::Arc<ScriptAst>>,
+    scripts_section:          Vec<SectionScriptAst>,
+    environments:             Vec<Environment>,
+}
+
+impl SubactionGenerator<'_> {
+    /// The number of subactions of the `HighLevelFighter`
+    pub(crate) fn len(&self) -> usize {
+        match self.first_bone {
+            // subaction_flags is empty when it could not be parsed
+            Some(_) => self.subaction_main.len().min(self.fighter_data.subaction_flags.len()),
+            None    => 0,
+        }
+    }
+
+    /// Generates the subaction at `i`, which must be less than `len`
+    pub(crate) fn subaction(&self, i: usize) -> HighLevelSubaction {
+        let fighter = self.fighter;
+        let fighter_data = self.fighter_data;
+        let fighter_animations = &self.fighter_animations;
+        let attributes = &fighter_data.attributes;
+        let scripts_section = &self.scripts_section;
+        let first_bone = self.first_bone.unwrap();
+
+        let mut fighter_scripts = vec!();
+        for script in self.fragment_scripts_fighter.iter()
+            .chain(self.subaction_main.iter())
+            .chain(self.subaction_gfx.iter())
+            .chain(self.subaction_sfx.iter())
+            .chain(self.subaction_other.iter())
+            .chain(self.entry_actions.iter().map(|x| x.as_ref()))
+            .chain(self.exit_actions.iter().map(|x| x.as_ref()))
+        {
+            fighter_scripts.push(script);
+        }
+
+        let mut common_scripts = vec!();
+        for script in self.common.fragments.iter()
+            .chain(scripts_section.iter().map(|x| &x.script))
+            .chain(self.common.entry_actions.iter())
+            .chain(self.common.exit_actions.iter())
+        {
+            common_scripts.push(script.as_ref());
+        }
+
+        let scripts = HighLevelScripts {
+            script_main:  self.subaction_main[i].clone(),
+            script_gfx:   self.subaction_gfx[i].clone(),
+            script_sfx:   self.subaction_sfx[i].clone(),
+            script_other: self.subaction_other[i].clone(),
+        };
+
+        let subaction_flags = &fighter_data.subaction_flags[i];
+        let actual_name = subaction_flags.name.clone();
+
+        // create a unique name for this subaction
+        let mut count = 0;
+        for j in 0..i {
+            if fighter_data.subaction_flags[j].name == actual_name {
+                count += 1;
+            }
+        }
+        let name = if count == 0 {
+            actual_name.clone()
+        } else {
+            format!("{}_{}", actual_name, count)
+        };
+
+        let animation_flags = subaction_flags.animation_flags;
+
+        let chr0 = fighter_animations.iter().find(|x| x.name == actual_name);
+        let subaction_scripts = vec!(&scripts.script_main, &scripts.script_gfx, &scripts.script_sfx, &scripts.script_other);
+        let init_hack_script = init_hack_script(&fighter.cased_name, &actual_name);
+
+        let mut frames: Vec<HighLevelFrame> = vec!();
+        let mut prev_animation_xyz_offset = Vector3::new(0.0, 0.0, 0.0);
+        let mut script_runner = ScriptRunner::new(i, &fighter.wiird_frame_speed_modifiers, &subaction_scripts, &fighter_scripts, &common_scripts, scripts_section, &init_hack_script, fighter_data, actual_name.clone());
+        let mut iasa = None;
+        let mut prev_hit_boxes: Option<Vec<PositionHitBox>> = None;
+
+        if let Some(chr0) = chr0 {
+            let num_frames = match actual_name.as_ref() {
+                "JumpSquat"    => attributes.jump_squat_frames as f32,
+                "LandingAirN"  => attributes.nair_landing_lag,
+                "LandingAirF"  => attributes.fair_landing_lag,
+                "LandingAirB"  => attributes.bair_landing_lag,
+                "LandingAirHi" => attributes.uair_landing_lag,
+                "LandingAirLw" => attributes.dair_landing_lag,
+                "LandingLight" => attributes.light_landing_lag, // TODO: This needs +1 do the others?!?!?
+                "LandingHeavy" => attributes.normal_landing_lag,
+                _              => chr0.num_frames as f32
+            };
+
+            let mut x_vel = 0.0;
+            let mut y_vel = 0.0;
+
+            let mut x_pos = 0.0;
+            let mut y_pos = 0.0;
+
+            while script_runner.animation_index < num_frames {
+                let chr0_frame_index = script_runner.animation_index * chr0.num_frames as f32 / num_frames; // map frame count between [0, chr0.num_frames]
+                let (animation_xyz_offset, frame_bones) = HighLevelFighter::transform_bones(
+                    first_bone,
+                    &fighter_data.misc.bone_refs,
+                    Matrix4::<f32>::identity(),
+                    Matrix4::<f32>::identity(),
+                    chr0,
+                    chr0_frame_index as i32,
+                    animation_flags,
+                    fighter_data.attributes.size
+                );
+                let animation_xyz_offset = animation_xyz_offset.unwrap_or(Vector3::new(0.0, 0.0, 0.0));
+                // TODO: should DisableMovement affect xyz_offset from transform_bones?????
+                // script runner x-axis is equivalent to model z-axis
+
+                let animation_xyz_velocity = animation_xyz_offset - prev_animation_xyz_offset;
+                prev_animation_xyz_offset = animation_xyz_offset;
+
+                let x_vel_modify = script_runner.x_vel_modify.clone();
+                let y_vel_modify = script_runner.y_vel_modify.clone();
+
+                let x_vel_temp = animation_xyz_velocity.z;
+                let y_vel_temp = animation_xyz_velocity.y;
+
+                match x_vel_modify {
+                    VelModify::Set (vel) => x_vel = vel,
+                    VelModify::Add (vel) => x_vel += vel,
+                    VelModify::None      => { }
+                }
+
+                match y_vel_modify {
+                    VelModify::Set (vel) => y_vel = vel,
+                    VelModify::Add (vel) => y_vel += vel,
+                    VelModify::None      => { }
+                }
+
+                x_pos += x_vel + x_vel_temp;
+                y_pos += y_vel + y_vel_temp;
+
+                let hurt_boxes = gen_hurt_boxes(&frame_bones, &fighter_data.misc.hurt_boxes, &script_runner, fighter_data.attributes.size);
+                let hit_boxes: Vec<_> = script_runner.hitboxes.iter().filter_map(|x| x.clone()).collect();
+                let hit_boxes = gen_hit_boxes(&frame_bones, &hit_boxes);
+                // A bit hacky, but we need to undo the movement that occured this frame to get the correct hitbox interpolation
+                let hl_hit_boxes = interpolate_hit_boxes(prev_hit_boxes.as_deref(), &hit_boxes, Vector3::new(0.0, y_vel, x_vel), Some(first_bone));
+
+                let mut option_ecb = None;
+                for misc_ecb in &fighter_data.misc.ecbs {
+                    let min_ecb = ECB {
+                        // This implementation is just a guess from my observations that:
+                        // *    The higher the min_width the higher the right ecb point.
+                        // *    The higher the min_width the lower the left ecb point.
+                        // *    When further than all bones, both points move equally far apart.
+                        // *    When further than all bones, actions that affect the ecb horizontally no longer affect the ecb e.g. marth jab
+                        left:     -misc_ecb.min_width / 2.0, // TODO: Should I divide by 2.0 here?
+                        right:    misc_ecb.min_width / 2.0, // TODO: Should I divide by 2.0 here?
+                        top:      -10000.0,
+                        bottom:   10000.0,
+                        transn_x: 0.0,
+                        transn_y: 0.0,
+                    };
+                    let mut ecb = gen_ecb(&frame_bones, &misc_ecb.bones, &fighter_data.misc.bone_refs, min_ecb);
+
+                    // This implementation is just a guess from my observations that:
+                    // *    The higher the min_height the higher the top ecb point.
+                    // *    The higher the min_height the lower the bottom ecb point, capping out at transN.
+                    // *    Actions such as crouching, lower the height of the top ecb point.
+                    let middle_y = (ecb.top + ecb.bottom) / 2.0;
+                    let new_top    = middle_y + misc_ecb.min_height / 2.0;
+                    let new_bottom = middle_y - misc_ecb.min_height / 2.0;
+                    if new_top > ecb.top {
+                        ecb.top = new_top;
+                    }
+                    if new_bottom < ecb.bottom {
+                        ecb.bottom = new_bottom;
+                    }
+                    if ecb.bottom < ecb.transn_y {
+                        ecb.bottom = ecb.transn_y
+                    }
+
+                    option_ecb = Some(ecb);
+                }
+                let ecb = option_ecb.unwrap();
+
+                let weight_dependent_speed = match actual_name.as_ref() {
+                    "ThrowLw" => attributes.weight_dependent_throw_down,
+                    "ThrowHi" => attributes.weight_dependent_throw_up,
+                    "ThrowF" => attributes.weight_dependent_throw_forward,
+                    "ThrowB" => attributes.weight_dependent_throw_backward,
+                    _        => false,
+                };
+
+                let mut throw = None;
+                if let Some(ref specify_throw) = script_runner.throw {
+                    if script_runner.throw_activate {
+                        throw = Some(HighLevelThrow {
+                            damage:      specify_throw.damage,
+                            trajectory:  specify_throw.trajectory,
+                            kbg:         specify_throw.kbg,
+                            wdsk:        specify_throw.wdsk,
+                            bkb:         specify_throw.bkb,
+                            effect:      specify_throw.effect.clone(),
+                            sfx:         specify_throw.sfx.clone(),
+                            grab_target: specify_throw.grab_target.clone(),
+                            i_frames:    specify_throw.i_frames,
+                            weight_dependent_speed,
+                        });
+                    }
+                }
+
+                let ledge_grab_box = if script_runner.ledge_grab_enable.enabled() {
+                    // The first misc.ledge_grabs entry seems to be used for everything, not sure what the other entries are for.
+                    if let Some(ledge_grab_box) = fighter_data.misc.ledge_grab_boxes.first() {
+                        let left = if let LedgeGrabEnable::EnableInFrontAndBehind = script_runner.ledge_grab_enable {
+                            ecb.left - ledge_grab_box.x_padding
+                        } else {
+                            ledge_grab_box.x_left
+                        };
+
+                        Some(Extent {
+                            left,
+                            right:  ecb.right + ledge_grab_box.x_padding,
+                            up:     ledge_grab_box.y + ledge_grab_box.height,
+                            down:   ledge_grab_box.y,
+                        })
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                };
+
+                let throw_n = fighter_data.misc.item_bones.as_ref().and_then(|x| bone_position(&frame_bones, x.throw_n_bone));
+
+                frames.push(HighLevelFrame {
+                    timer:                 script_runner.frame_index,
+                    throw_n,
+                    throw,
+                    ecb,
+                    x_pos,
+                    y_pos,
+                    x_vel_modify,
+                    y_vel_modify,
+                    movement_effects:      script_runner.movement_effects.clone(),
+                    x_vel_temp,
+                    y_vel_temp,
+                    ledge_grab_box,
+                    hurt_boxes,
+                    hit_boxes:             hl_hit_boxes,
+                    interruptible:         script_runner.interruptible,
+                    landing_lag:           script_runner.landing_lag,
+                    edge_slide:            script_runner.edge_slide.clone(),
+                    reverse_direction:     script_runner.reverse_direction,
+                    airbourne:             script_runner.airbourne,
+                    hitbox_sets_rehit:     script_runner.hitbox_sets_rehit,
+                    slope_contour_stand:   script_runner.slope_contour_stand,
+                    slope_contour_full:    script_runner.slope_contour_full,
+                    rumble:                script_runner.rumble,
+                    rumble_loop:           script_runner.rumble_loop,
+                    grab_interrupt_damage: script_runner.grab_interrupt_damage,
+                    effects:               script_runner.effects.clone(),
+                    color_flash:           script_runner.color_flash,
+                    sword_glow:            script_runner.sword_glow.clone(),
+                    concurrent_loops:      script_runner.concurrent_loops(),
+                });
+
+                if iasa.is_none() && script_runner.interruptible {
+                    iasa = Some(script_runner.frame_count)
+                }
+
+                script_runner.step();
+                prev_hit_boxes = Some(hit_boxes);
+
+                if let ChangeSubaction::Continue = script_runner.change_subaction { } else { break }
+            }
+        }
+
+        if iasa.is_none() {
+            iasa = match actual_name.as_ref() {
+                "LandingAirN"  | "LandingAirF" |
+                "LandingAirB"  | "LandingAirHi" |
+                "LandingAirLw" | "LandingLight" |
+                "LandingHeavy" | "LandingFallSpecial"
+                  => Some(script_runner.frame_count),
+                _ => None
+            }
+        };
+
+        let landing_lag = match actual_name.as_ref() {
+            "AttackAirN"  => Some(attributes.nair_landing_lag),
+            "AttackAirF"  => Some(attributes.fair_landing_lag),
+            "AttackAirB"  => Some(attributes.bair_landing_lag),
+            "AttackAirHi" => Some(attributes.uair_landing_lag),
+            "AttackAirLw" => Some(attributes.dair_landing_lag),
+            _             => None,
+        };
+
+        let bad_interrupts = !script_runner.bad_interrupts.is_empty();
+        let caveats = script_runner.caveats.clone();
+        let environment = self.environments.get(i).cloned().unwrap_or(Environment::Unknown);
+
+        HighLevelSubaction { name, iasa, landing_lag, frames, animation_flags, scripts, bad_interrupts, caveats, environment }
+    }
+}
+
+/// The processed scripts of a common Fighter.pac.
+///
+/// Every fighter uses the same Fighter.pac, so these are processed once and shared between the `HighLevelFighter` of each fighter.
+/// The scripts are behind an `Arc` so scripts from the common Fighter.pac can be recognized across fighters with `sync::Arc::ptr_eq`.
+#[derive(Clone, Debug)]
+pub struct CommonScripts {
+    /// The `Fighter::moveset_common` these scripts were created from
+    source:            sync::Arc<Arc>,
+    /// Sorted by offset
+    pub fragments:     Vec<sync::Arc<ScriptAst>>,
+    /// The script sections and their names
+    pub sections:      Vec<(String, sync::Arc<ScriptAst>)>,
+    pub entry_actions: Vec<sync::Arc<ScriptAst>>,
+    pub exit_actions:  Vec<sync::Arc<ScriptAst>>,
+}
+
+impl CommonScripts {
+    /// Processes the scripts of the fighters `moveset_common`.
+    /// The scripts are empty if the common Fighter.pac could not be parsed.
+    pub fn new(fighter: &Fighter) -> CommonScripts {
+        let arc = |scripts: &[Script]| scripts.iter().map(|x| sync::Arc::new(ScriptAst::new(x))).collect();
+        let fragments = fighter.get_fighter_sakurai_common().map(|x| arc(&x.fragment_scripts)).unwrap_or_default();
+        let sections = fighter.get_fighter_data_common_scripts().iter().map(|x| (x.name.clone(), sync::Arc::new(ScriptAst::new(&x.script)))).collect();
+        let (entry_actions, exit_actions) = match fighter.get_fighter_data_common() {
+            Some(data) => (arc(&data.entry_actions), arc(&data.exit_actions)),
+            None       => (vec!(), vec!()),
+        };
+        CommonScripts { source: fighter.moveset_common.clone(), fragments, sections, entry_actions, exit_actions }
+    }
+
+    /// Returns the `CommonScripts` of each fighter in `fighters`.
+    /// Fighters that share a `Fighter::moveset_common`, e.g. all fighters loaded by the same `BrawlMod::load_fighters`, share the same `CommonScripts`.
+    pub fn for_fighters(fighters: &[Fighter]) -> Vec<sync::Arc<CommonScripts>> {
+        let mut unique: Vec<sync::Arc<CommonScripts>> = vec!();
+        let mut result = vec!();
+        for fighter in fighters {
+            let common = match unique.iter().find(|x| x.is_source_of(fighter)) {
+                Some(common) => common.clone(),
+                None => {
+                    let common = sync::Arc::new(CommonScripts::new(fighter));
+                    unique.push(common.clone());
+                    common
+                }
+            };
+            result.push(common);
+        }
+        result
+    }
+
+    /// Returns true if these scripts were created from the `moveset_common` of the fighter
+    pub fn is_source_of(&self, fighter: &Fighter) -> bool {
+        sync::Arc::ptr_eq(&self.source, &fighter.moveset_common)
+    }
+
+    /// Returns the fragment script at `offset` in the common Fighter.pac
+    pub fn fragment_script(&self, offset: i32) -> Option<&sync::Arc<ScriptAst>> {
+        self.fragments.binary_search_by_key(&offset, |x| x.offset).ok().map(|i| &self.fragments[i])
+    }
+}
+
+/// Which file a script is stored in
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum ScriptSource {
+    /// The fighters own Fit{}.pac
+    Fighter,
+    /// The common Fighter.pac shared by all fighters
+    CommonPac,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::{Argument, Offset};
+    use crate::fighter::LoadDetail;
+    use crate::test_util::TestFighterBuilder;
+    use crate::mdl0::bones::test_bone;
+
+    /// A moveset containing a fragment, standing in for the common Fighter.pac
+    fn common_fighter() -> (Arc, i32) {
+        let builder = |fragment_offset| TestFighterBuilder::new("Common")
+            .subaction("Wait1", |s| s
+                .event(0x0D, 0x00, vec!(Argument::Value (1), Argument::Offset (Offset { offset: fragment_offset, origin: 0 })))
+                .event(0x00, 0x02, vec!())
+            )
+            .build();
+        let fragment_offset = builder(0).get_fighter_data().unwrap().subaction_main[0].offset + 8;
+        (builder(fragment_offset).moveset, fragment_offset)
+    }
+
+    #[test]
+    fn common_scripts_shared() {
+        let (common, fragment_offset) = common_fighter();
+        let common = sync::Arc::new(common);
+        let fighter = |name: &str, moveset_common: &sync::Arc<Arc>| {
+            let mut fighter = TestFighterBuilder::new(name).build();
+            fighter.moveset_common = moveset_common.clone();
+            fighter
+        };
+        let fighters = vec!(
+            fighter("Mario", &common),
+            fighter("Luigi", &common),
+            fighter("Peach", &sync::Arc::new((*common).clone())),
+        );
+
+        let scripts = CommonScripts::for_fighters(&fighters);
+        assert_eq!(scripts.len(), 3);
+        assert!(sync::Arc::ptr_eq(&scripts[0], &scripts[1]));
+        assert!(!sync::Arc::ptr_eq(&scripts[0], &scripts[2]));
+        assert!(scripts[0].is_source_of(&fighters[1]));
+        assert!(!scripts[0].is_source_of(&fighters[2]));
+
+        let mario = scripts[0].fragment_script(fragment_offset).unwrap();
+        let luigi = scripts[1].fragment_script(fragment_offset).unwrap();
+        let peach = scripts[2].fragment_script(fragment_offset).unwrap();
+        assert!(sync::Arc::ptr_eq(mario, luigi));
+        assert!(!sync::Arc::ptr_eq(mario, peach));
+        assert_eq!(mario.offset, fragment_offset);
+        assert!(scripts[0].fragment_script(fragment_offset + 8).is_none());
+    }
+
+    #[test]
+    fn not_loaded() {
+        let mut fighter = TestFighterBuilder::new("Mario").subaction("Wait1", |s| s.sync_timer(1.0)).build();
+        let high_level_fighter = HighLevelFighter::new(&fighter);
+        assert_eq!(high_level_fighter.not_loaded, vec!(NotLoaded::Animations, NotLoaded::Models));
+        assert!(high_level_fighter.subactions.is_empty());
+
+        fighter.load_detail = LoadDetail::ScriptsAndAnimations;
+        assert_eq!(HighLevelFighter::new(&fighter).not_loaded, vec!(NotLoaded::Models));
+    }
+
+    #[test]
+    fn interpolated_hitbox_sweep() {
+        let collision_box = ScriptCollisionBox {
+            bone_index:  0,
+            hitbox_id:   0,
+            x_offset:    0.0,
+            y_offset:    1.0,
+            z_offset:    0.0,
+            size:        2.0,
+            values:      CollisionBoxValues::Grab (GrabBoxValues { hitbox_id: 0, size: 2.0, set_action: 0, target: GrabTarget::AerialAndGrounded, unk: None }),
+            interpolate: true,
+        };
+        // the bone moves 2 units forward every frame while the fighter stays in place
+        let bone = |frame: usize| BoneTransforms {
+            index:            0,
+            transform_normal: Matrix4::from_translation(Vector3::new(0.0, 0.0, frame as f32 * 2.0)),
+            transform_hitbox: Matrix4::identity(),
+            children:         vec!(),
+        };
+
+        let mut prev_hit_boxes: Option<Vec<PositionHitBox>> = None;
+        for frame in 0..4 {
+            let hit_boxes = gen_hit_boxes(&bone(frame), std::slice::from_ref(&collision_box));
+            let hl_hit_boxes = interpolate_hit_boxes(prev_hit_boxes.as_deref(), &hit_boxes, Vector3::new(0.0, 0.0, 0.0), None);
+            assert_eq!(hl_hit_boxes[0].bone_name, None);
+            let frame_hitbox = hl_hit_boxes[0].frame_hitbox(0.0, 0.0);
+            assert!(frame_hitbox.interpolated);
+            assert_eq!(frame_hitbox.center, Point3::new(0.0, 1.0, frame as f32 * 2.0));
+            if frame == 0 {
+                // spawned this frame, so there is no sweep
+                assert_eq!(frame_hitbox.prev_center, None);
+            } else {
+                assert_eq!(frame_hitbox.prev_center, Some(frame_hitbox.center - Vector3::new(0.0, 0.0, 2.0)));
+            }
+            prev_hit_boxes = Some(hit_boxes);
+        }
+
+        // hitboxes that are not interpolated never sweep
+        let collision_box = ScriptCollisionBox { interpolate: false, ..collision_box };
+        let prev = gen_hit_boxes(&bone(0), std::slice::from_ref(&collision_box));
+        let model_bones = test_bone("TopN", 0, vec!());
+        let hl_hit_boxes = interpolate_hit_boxes(Some(&prev), &gen_hit_boxes(&bone(1), &[collision_box]), Vector3::new(0.0, 0.0, 0.0), Some(&model_bones));
+        assert_eq!(hl_hit_boxes[0].bone_name.as_deref(), Some("TopN"));
+        assert_eq!(hl_hit_boxes[0].frame_hitbox(3.0, 0.0).prev_center, None);
+        assert_eq!(hl_hit_boxes[0].frame_hitbox(3.0, 0.0).center, Point3::new(0.0, 1.0, 5.0));
+    }
+}
@@ -0,0 +1,303 @@
+//! The interrupt groups checked during each action, for finding which inputs can cancel a subaction.
+//!
+//! The game checks the interrupts of a fighter in groups e.g. `InterruptType::GroundJump` holds the interrupts that start a jump.
+//! Which groups are checked during an action is decided by the Enable, Disable and Clear Interrupt Group events in the entry script of the action.
+//! So universal cancels like jump cancelling out of shield come from the entry actions of the common Fighter.pac, not from the subaction scripts.
+//! The interrupts inside each group are hardcoded in the game and are not read.
+//!
+//! An action is a member of a group when its entry script enables the group and does not unconditionally disable or clear it afterwards.
+//! Groups enabled inside an If statement are included, as they may be enabled.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::analysis;
+use crate::fighter::Fighter;
+use crate::sakurai::fighter_data_common::ArcFighterDataCommon;
+use crate::script::{normalize_timers, Script};
+use crate::script_ast::{Block, EventAst, Expression, InterruptType, ScriptAst};
+use crate::subaction_invokers::{InvokerRef, InvokerScript, FIGHTER_ACTIONS_START};
+
+/// The actions that check a group of interrupts.
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct InterruptGroup {
+    pub id: InterruptType,
+    /// The actions whose entry script enables the group, in ascending order
+    pub actions: Vec<u16>,
+    /// Requirements that entry scripts add to interrupts in the group, with the Interrupt Add Requirement event
+    pub requirements: Vec<GroupRequirement>,
+}
+
+/// An extra requirement an action adds to an interrupt of a group.
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct GroupRequirement {
+    /// The action whose entry script adds the requirement
+    pub action: u16,
+    pub interrupt_id: i32,
+    pub test: Expression,
+}
+
+/// A way for a subaction to be cancelled into another action.
+/// All frames start at 1.
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum SubactionTransition {
+    /// A Change Action event in the main script of the subaction, checked from the frame of the event
+    ChangeAction { action: i32, frame: f32 },
+    /// An interrupt group enabled by an action that changes to the subaction.
+    /// Checked from the first Allow Interrupts event of the subaction, or from the first frame when the subaction has none.
+    InterruptGroup { group: InterruptType, action: u16, frame: f32 },
+}
+
+impl ArcFighterDataCommon {
+    /// Returns the interrupt groups enabled by the entry scripts of the common actions.
+    /// Refer to the `interrupt_groups` module documentation for how membership is determined.
+    pub fn interrupt_groups(&self) -> Vec<InterruptGroup> {
+        let actions: Vec<(u16, &Script)> = self.entry_actions.iter().enumerate().map(|(i, x)| (i as u16, x)).collect();
+        interrupt_groups(&actions)
+    }
+}
+
+impl Fighter {
+    /// Returns the interrupt groups enabled by the entry scripts of every action of the fighter.
+    /// The fighters entry action overrides replace the common entry scripts they override.
+    pub fn interrupt_groups(&self) -> Vec<InterruptGroup> {
+        let mut actions: BTreeMap<u16, &Script> = BTreeMap::new();
+        if let Some(common) = self.get_fighter_data_common() {
+            for (i, script) in common.entry_actions.iter().enumerate() {
+                actions.insert(i as u16, script);
+            }
+        }
+        if let Some(fighter_data) = self.get_fighter_data() {
+            for action_override in &fighter_data.entry_action_overrides {
+                actions.insert(action_override.action_id as u16, &action_override.script);
+            }
+            for (i, script) in fighter_data.entry_actions.iter().enumerate() {
+                actions.insert((FIGHTER_ACTIONS_START + i) as u16, script);
+            }
+        }
+        let actions: Vec<(u16, &Script)> = actions.into_iter().collect();
+        interrupt_groups(&actions)
+    }
+
+    /// Returns every way the subaction at `index` can be cancelled, combining the Change Action events of the subaction
+    /// with the interrupt groups of the actions that change to the subaction, ordered by frame.
+    pub fn subaction_transitions(&self, index: usize) -> Vec<SubactionTransition> {
+        match self.get_fighter_data().and_then(|x| x.subaction_main.get(index)) {
+            Some(script) => transitions(script, &self.subaction_invokers(index), &groups_by_action(&self.interrupt_groups())),
+            None => vec!(),
+        }
+    }
+
+    /// Returns the transitions of every subaction, indexed by subaction.
+    /// The interrupt groups and invokers are only found once, so use this instead of calling `Fighter::subaction_transitions` for every subaction.
+    pub fn subaction_transition_index(&self) -> Vec<Vec<SubactionTransition>> {
+        let fighter_data = match self.get_fighter_data() {
+            Some(fighter_data) => fighter_data,
+            None => return vec!(),
+        };
+        let groups = groups_by_action(&self.interrupt_groups());
+        self.subaction_invoker_index().iter().zip(fighter_data.subaction_main.iter())
+            .map(|(invokers, script)| transitions(script, invokers, &groups))
+            .collect()
+    }
+
+    /// Returns the first frame the subaction at `index` can be cancelled into a grounded or aerial jump by an interrupt group, starting at 1.
+    /// Returns None when no action that changes to the subaction enables a jump group.
+    pub fn jump_cancel_frame(&self, index: usize) -> Option<f32> {
+        self.subaction_transitions(index).into_iter().find_map(|x| match x {
+            SubactionTransition::InterruptGroup { group: InterruptType::GroundJump, frame, .. } |
+            SubactionTransition::InterruptGroup { group: InterruptType::AirJump, frame, .. } => Some(frame),
+            _ => None,
+        })
+    }
+}
+
+/// Returns the groups enabled by each action
+fn groups_by_action(groups: &[InterruptGroup]) -> HashMap<u16, Vec<InterruptType>> {
+    let mut by_action: HashMap<u16, Vec<InterruptType>> = HashMap::new();
+    for group in groups {
+        for action in &group.actions {
+            by_action.entry(*action).or_default().push(group.id.clone());
+        }
+    }
+    by_action
+}
+
+/// Returns the transitions of the subaction with the main script `script` that is changed to by `invokers`
+fn transitions(script: &Script, invokers: &[InvokerRef], groups: &HashMap<u16, Vec<InterruptType>>) -> Vec<SubactionTransition> {
+    let mut transitions = vec!();
+    for timed_event in normalize_timers(script).events {
+        if let Some(EventAst::CreateInterrupt (interrupt)) = EventAst::from_event(timed_event.event) {
+            transitions.push(SubactionTransition::ChangeAction { action: interrupt.action, frame: timed_event.frame.as_f32() });
+        }
+    }
+
+    let frame = analysis::first_interrupt(script).unwrap_or(1.0);
+    let mut actions: Vec<u16> = invokers.iter().filter_map(|x| match x.script {
+        InvokerScript::EntryAction (action) |
+        InvokerScript::EntryActionOverride (action) => Some(action as u16),
+        _ => None,
+    }).collect();
+    actions.sort();
+    actions.dedup();
+    for action in actions {
+        for group in groups.get(&action).into_iter().flatten() {
+            transitions.push(SubactionTransition::InterruptGroup { group: group.clone(), action, frame });
+        }
+    }
+
+    transitions.sort_by(|a, b| a.frame().total_cmp(&b.frame()));
+    transitions
+}
+
+impl SubactionTransition {
+    /// The first frame the transition can occur on
+    pub fn frame(&self) -> f32 {
+        match self {
+            SubactionTransition::ChangeAction { frame, .. } => *frame,
+            SubactionTransition::InterruptGroup { frame, .. } => *frame,
+        }
+    }
+}
+
+/// `actions` must be in ascending order
+fn interrupt_groups(actions: &[(u16, &Script)]) -> Vec<InterruptGroup> {
+    let mut groups: Vec<InterruptGroup> = vec!();
+    for (action, script) in actions {
+        let mut enabled = vec!();
+        let mut requirements = vec!();
+        block_groups(&ScriptAst::new(script).block, true, &mut enabled, &mut requirements);
+
+        for id in enabled {
+            group_mut(&mut groups, &id).actions.push(*action);
+        }
+        for (id, interrupt_id, test) in requirements {
+            group_mut(&mut groups, &id).requirements.push(GroupRequirement { action: *action, interrupt_id, test });
+        }
+    }
+    groups
+}
+
+fn group_mut<'a>(groups: &'a mut Vec<InterruptGroup>, id: &InterruptType) -> &'a mut InterruptGroup {
+    match groups.iter().position(|x| x.id == *id) {
+        Some(i) => &mut groups[i],
+        None => {
+            groups.push(InterruptGroup { id: id.clone(), actions: vec!(), requirements: vec!() });
+            groups.last_mut().unwrap()
+        }
+    }
+}
+
+/// Adds the groups enabled by the block to `enabled` and the requirements added to groups to `requirements`.
+/// `unconditional` is true when the block always runs.
+fn block_groups(block: &Block, unconditional: bool, enabled: &mut Vec<InterruptType>, requirements: &mut Vec<(InterruptType, i32, Expression)>) {
+    for event in &block.events {
+        match event {
+            EventAst::EnableInterruptGroup (id) if !enabled.contains(id) => enabled.push(id.clone()),
+            EventAst::DisableInterruptGroup (id) |
+            EventAst::ClearInterruptGroup (id) if unconditional => enabled.retain(|x| x != id),
+            EventAst::InterruptAddRequirement { interrupt_type, interrupt_id, test } => requirements.push((interrupt_type.clone(), *interrupt_id, test.clone())),
+            EventAst::ForLoop (for_loop) => block_groups(&for_loop.block, unconditional, enabled, requirements),
+            EventAst::IfStatement (if_statement) => {
+                block_groups(&if_statement.then_branch, false, enabled, requirements);
+                if let Some(else_branch) = &if_statement.else_branch {
+                    block_groups(else_branch, false, enabled, requirements);
+                }
+            }
+            _ => { }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::{Argument, Requirement};
+    use crate::test_util::{ScriptBuilder, TestFighterBuilder, damage};
+
+    fn change_subaction(script: ScriptBuilder, subaction: i32) -> ScriptBuilder {
+        script.event(0x04, 0x00, vec!(Argument::Value (subaction)))
+    }
+
+    fn enable_group(script: ScriptBuilder, group: i32) -> ScriptBuilder {
+        script.event(0x02, 0x0A, vec!(Argument::Value (group)))
+    }
+
+    fn fighter() -> Fighter {
+        TestFighterBuilder::new("Test")
+            .subaction("Wait1", |s| s.sync_timer(10.0))
+            .subaction("GuardOn", |s| s.async_timer(4.0).event(0x64, 0x00, vec!()))
+            .subaction("AttackS4S", |s| s.async_timer(7.0).hitbox(damage(14)).sync_timer(3.0).terminate_collisions()
+                .event(0x02, 0x01, vec!(Argument::Value (0x0E), Argument::Requirement { flip: false, ty: Requirement::AnimationEnd })))
+            // 0x112: a shield that can be jump cancelled
+            .action(|s| enable_group(enable_group(change_subaction(s, 1), 0x06), 0x07)
+                .event(0x02, 0x05, vec!(Argument::Value (0x07), Argument::Value (2), Argument::Requirement { flip: true, ty: Requirement::AnimationEnd })), |s| s)
+            // 0x113: the jump group is cleared again
+            .action(|s| change_subaction(enable_group(enable_group(s, 0x07), 0x04), 2).event(0x02, 0x0C, vec!(Argument::Value (0x07))), |s| s)
+            .build()
+    }
+
+    #[test]
+    fn groups_from_entry_actions() {
+        let groups = fighter().interrupt_groups();
+        let ids: Vec<_> = groups.iter().map(|x| (x.id.clone(), x.actions.clone())).collect();
+        assert_eq!(ids, vec!(
+            (InterruptType::GroundGuard,  vec!(0x112)),
+            (InterruptType::GroundJump,   vec!(0x112)),
+            (InterruptType::GroundAttack, vec!(0x113)),
+        ));
+
+        let requirements = &groups[1].requirements;
+        assert_eq!(requirements.len(), 1);
+        assert_eq!((requirements[0].action, requirements[0].interrupt_id), (0x112, 2));
+    }
+
+    #[test]
+    fn jump_cancel_transition() {
+        let fighter = fighter();
+        let transitions = fighter.subaction_transitions(1);
+        assert_eq!(transitions.len(), 2);
+        match &transitions[1] {
+            SubactionTransition::InterruptGroup { group, action, frame } => assert_eq!((group, *action, *frame), (&InterruptType::GroundJump, 0x112, 5.0)),
+            transition => panic!("Unexpected transition {:?}", transition),
+        }
+        assert_eq!(fighter.jump_cancel_frame(1), Some(5.0));
+
+        // the forward smash only has the attack group and its own change action
+        let transitions = fighter.subaction_transitions(2);
+        assert_eq!(transitions.len(), 2);
+        match &transitions[0] {
+            SubactionTransition::InterruptGroup { group, action, frame } => assert_eq!((group, *action, *frame), (&InterruptType::GroundAttack, 0x113, 1.0)),
+            transition => panic!("Unexpected transition {:?}", transition),
+        }
+        match &transitions[1] {
+            SubactionTransition::ChangeAction { action, frame } => assert_eq!((*action, *frame), (0x0E, 11.0)),
+            transition => panic!("Unexpected transition {:?}", transition),
+        }
+        assert_eq!(fighter.jump_cancel_frame(2), None);
+        assert_eq!(fighter.jump_cancel_frame(0), None);
+    }
+
+    #[test]
+    fn transition_index() {
+        let fighter = fighter();
+        let index = fighter.subaction_transition_index();
+        assert_eq!(index.len(), 3);
+        for (i, transitions) in index.iter().enumerate() {
+            let expected: Vec<f32> = fighter.subaction_transitions(i).iter().map(|x| x.frame()).collect();
+            assert_eq!(transitions.iter().map(|x| x.frame()).collect::<Vec<_>>(), expected);
+        }
+        assert!(index[0].is_empty());
+    }
+
+    #[test]
+    fn change_action_without_valid_requirement() {
+        // a Change Action with an unknown requirement type is not a transition
+        let fighter = TestFighterBuilder::new("Test")
+            .subaction("Wait1", |s| s.event(0x02, 0x01, vec!(Argument::Value (0x0E), Argument::Value (0))))
+            .build();
+        assert!(fighter.subaction_transitions(0).is_empty());
+    }
+}
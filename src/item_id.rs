@@ -0,0 +1,226 @@
+//! The ids of the generic items, as used by the item events of fighter scripts.
+
+/// A generic item, e.g. the Beam Sword or a Bob-omb.
+///
+/// Covers the vanilla item table, ids outside of it are kept as `Unknown`.
+/// Pokémon and assist trophy summons are chosen by the Poké Ball and Assist Trophy items and do not have their own item ids here.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum ItemId {
+    AssistTrophy,
+    FranklinBadge,
+    BananaPeel,
+    Barrel,
+    BeamSword,
+    Bill,
+    BobOmb,
+    Crate,
+    Bumper,
+    Capsule,
+    RollingCrate,
+    Cd,
+    GooeyBomb,
+    CrackerLauncher,
+    CrackerLauncherShot,
+    Coin,
+    SuperspicyCurry,
+    SuperspicyCurryShot,
+    DekuNut,
+    MrSaturn,
+    DragoonPart,
+    DragoonSet,
+    DragoonSight,
+    Trophy,
+    FireFlower,
+    FireFlowerShot,
+    Freezie,
+    GoldenHammer,
+    GreenShell,
+    Hammer,
+    HammerHead,
+    Fan,
+    HeartContainer,
+    HomeRunBat,
+    PartyBall,
+    ManaphyHeart,
+    MaximTomato,
+    PoisonMushroom,
+    SuperMushroom,
+    MetalBox,
+    Hothead,
+    Pitfall,
+    PokeBall,
+    BlastBox,
+    RayGun,
+    RayGunShot,
+    Lipstick,
+    LipstickFlower,
+    LipstickShot,
+    Sandbag,
+    ScrewAttack,
+    Sticker,
+    MotionSensorBomb,
+    Timer,
+    SmartBomb,
+    SmashBall,
+    SmokeBall,
+    Spring,
+    StarRod,
+    StarRodShot,
+    SoccerBall,
+    SuperScope,
+    SuperScopeShot,
+    Superstar,
+    Food,
+    TeamHealer,
+    Lightning,
+    Unira,
+    BunnyHood,
+    Warpstar,
+    SubspaceTrophy,
+    Key,
+    TrophyStand,
+    StockBall,
+    GreenGreensApple,
+    Sidestepper,
+    Shellcreeper,
+    Pellet,
+    SummitVegetable,
+    HomeRunSandbag,
+    Unknown (i32),
+}
+
+impl ItemId {
+    pub fn new(value: i32) -> ItemId {
+        ITEMS.iter()
+            .find(|(id, _, _)| *id == value)
+            .map(|(_, item, _)| *item)
+            .unwrap_or(ItemId::Unknown (value))
+    }
+
+    /// The id stored in item events
+    pub fn value(&self) -> i32 {
+        match self {
+            ItemId::Unknown (value) => *value,
+            item => ITEMS.iter().find(|(_, x, _)| x == item).map(|(id, _, _)| *id).unwrap(),
+        }
+    }
+
+    /// The name of the item as shown in game, unknown items are named by their id e.g. `Item 0x80`
+    pub fn display_name(&self) -> String {
+        match self {
+            ItemId::Unknown (value) => format!("Item 0x{:X}", value),
+            item => ITEMS.iter().find(|(_, x, _)| x == item).map(|(_, _, name)| name.to_string()).unwrap(),
+        }
+    }
+}
+
+/// Every known item with its id and display name, ordered by id
+const ITEMS: &[(i32, ItemId, &str)] = &[
+    (0x00, ItemId::AssistTrophy,        "Assist Trophy"),
+    (0x01, ItemId::FranklinBadge,       "Franklin Badge"),
+    (0x02, ItemId::BananaPeel,          "Banana Peel"),
+    (0x03, ItemId::Barrel,              "Barrel"),
+    (0x04, ItemId::BeamSword,           "Beam Sword"),
+    (0x05, ItemId::Bill,                "Bill"),
+    (0x06, ItemId::BobOmb,              "Bob-omb"),
+    (0x07, ItemId::Crate,               "Crate"),
+    (0x08, ItemId::Bumper,              "Bumper"),
+    (0x09, ItemId::Capsule,             "Capsule"),
+    (0x0A, ItemId::RollingCrate,        "Rolling Crate"),
+    (0x0B, ItemId::Cd,                  "CD"),
+    (0x0C, ItemId::GooeyBomb,           "Gooey Bomb"),
+    (0x0D, ItemId::CrackerLauncher,     "Cracker Launcher"),
+    (0x0E, ItemId::CrackerLauncherShot, "Cracker Launcher Shot"),
+    (0x0F, ItemId::Coin,                "Coin"),
+    (0x10, ItemId::SuperspicyCurry,     "Superspicy Curry"),
+    (0x11, ItemId::SuperspicyCurryShot, "Superspicy Curry Shot"),
+    (0x12, ItemId::DekuNut,             "Deku Nut"),
+    (0x13, ItemId::MrSaturn,            "Mr. Saturn"),
+    (0x14, ItemId::DragoonPart,         "Dragoon Part"),
+    (0x15, ItemId::DragoonSet,          "Dragoon Set"),
+    (0x16, ItemId::DragoonSight,        "Dragoon Sight"),
+    (0x17, ItemId::Trophy,              "Trophy"),
+    (0x18, ItemId::FireFlower,          "Fire Flower"),
+    (0x19, ItemId::FireFlowerShot,      "Fire Flower Shot"),
+    (0x1A, ItemId::Freezie,             "Freezie"),
+    (0x1B, ItemId::GoldenHammer,        "Golden Hammer"),
+    (0x1C, ItemId::GreenShell,          "Green Shell"),
+    (0x1D, ItemId::Hammer,              "Hammer"),
+    (0x1E, ItemId::HammerHead,          "Hammer Head"),
+    (0x1F, ItemId::Fan,                 "Fan"),
+    (0x20, ItemId::HeartContainer,      "Heart Container"),
+    (0x21, ItemId::HomeRunBat,          "Home-Run Bat"),
+    (0x22, ItemId::PartyBall,           "Party Ball"),
+    (0x23, ItemId::ManaphyHeart,        "Manaphy Heart"),
+    (0x24, ItemId::MaximTomato,         "Maxim Tomato"),
+    (0x25, ItemId::PoisonMushroom,      "Poison Mushroom"),
+    (0x26, ItemId::SuperMushroom,       "Super Mushroom"),
+    (0x27, ItemId::MetalBox,            "Metal Box"),
+    (0x28, ItemId::Hothead,             "Hothead"),
+    (0x29, ItemId::Pitfall,             "Pitfall"),
+    (0x2A, ItemId::PokeBall,            "Poké Ball"),
+    (0x2B, ItemId::BlastBox,            "Blast Box"),
+    (0x2C, ItemId::RayGun,              "Ray Gun"),
+    (0x2D, ItemId::RayGunShot,          "Ray Gun Shot"),
+    (0x2E, ItemId::Lipstick,            "Lip's Stick"),
+    (0x2F, ItemId::LipstickFlower,      "Lip's Stick Flower"),
+    (0x30, ItemId::LipstickShot,        "Lip's Stick Shot"),
+    (0x31, ItemId::Sandbag,             "Sandbag"),
+    (0x32, ItemId::ScrewAttack,         "Screw Attack"),
+    (0x33, ItemId::Sticker,             "Sticker"),
+    (0x34, ItemId::MotionSensorBomb,    "Motion-Sensor Bomb"),
+    (0x35, ItemId::Timer,               "Timer"),
+    (0x36, ItemId::SmartBomb,           "Smart Bomb"),
+    (0x37, ItemId::SmashBall,           "Smash Ball"),
+    (0x38, ItemId::SmokeBall,           "Smoke Ball"),
+    (0x39, ItemId::Spring,              "Spring"),
+    (0x3A, ItemId::StarRod,             "Star Rod"),
+    (0x3B, ItemId::StarRodShot,         "Star Rod Shot"),
+    (0x3C, ItemId::SoccerBall,          "Soccer Ball"),
+    (0x3D, ItemId::SuperScope,          "Super Scope"),
+    (0x3E, ItemId::SuperScopeShot,      "Super Scope Shot"),
+    (0x3F, ItemId::Superstar,           "Superstar"),
+    (0x40, ItemId::Food,                "Food"),
+    (0x41, ItemId::TeamHealer,          "Team Healer"),
+    (0x42, ItemId::Lightning,           "Lightning"),
+    (0x43, ItemId::Unira,               "Unira"),
+    (0x44, ItemId::BunnyHood,           "Bunny Hood"),
+    (0x45, ItemId::Warpstar,            "Warp Star"),
+    (0x46, ItemId::SubspaceTrophy,      "Trophy (Subspace Emissary)"),
+    (0x47, ItemId::Key,                 "Key"),
+    (0x48, ItemId::TrophyStand,         "Trophy Stand"),
+    (0x49, ItemId::StockBall,           "Stock Ball"),
+    (0x4A, ItemId::GreenGreensApple,    "Apple (Green Greens)"),
+    (0x4B, ItemId::Sidestepper,         "Sidestepper"),
+    (0x4C, ItemId::Shellcreeper,        "Shellcreeper"),
+    (0x4D, ItemId::Pellet,              "Pellet"),
+    (0x4E, ItemId::SummitVegetable,     "Vegetable (Summit)"),
+    (0x4F, ItemId::HomeRunSandbag,      "Sandbag (Home-Run Contest)"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_duplicate_ids() {
+        for (i, (id, item, name)) in ITEMS.iter().enumerate() {
+            for (other_id, other_item, other_name) in &ITEMS[i + 1..] {
+                assert_ne!(id, other_id, "{} and {} share an id", name, other_name);
+                assert_ne!(item, other_item, "{:?} is listed twice", item);
+            }
+            assert_eq!(ItemId::new(*id), *item);
+            assert_eq!(item.value(), *id);
+        }
+    }
+
+    #[test]
+    fn unknown_ids() {
+        assert_eq!(ItemId::new(0x04), ItemId::BeamSword);
+        assert_eq!(ItemId::BeamSword.display_name(), "Beam Sword");
+        assert_eq!(ItemId::new(0x400), ItemId::Unknown (0x400));
+        assert_eq!(ItemId::Unknown (0x400).value(), 0x400);
+        assert_eq!(ItemId::Unknown (0x400).display_name(), "Item 0x400");
+    }
+}
@@ -0,0 +1,110 @@
+use crate::sakurai::fighter_data::misc_section::ItemBones;
+
+/// Per fighter data that changes how generic items behave when used by the fighter.
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct ItemOverrides {
+    /// The bone held items are attached to
+    pub held_item_bone: Option<i32>,
+    /// The bone items are attached to while being thrown
+    pub throw_item_bone: Option<i32>,
+    /// Entries of the item bones table that are not understood yet
+    pub unknown: Vec<i32>,
+    /// Strength multiplier applied to thrown items
+    pub item_throw_strength: f32,
+    /// The subactions this fighter uses for each item swing.
+    pub swing_subactions: Vec<ItemSwingSubaction>,
+}
+
+impl ItemOverrides {
+    pub(crate) fn new(item_bones: Option<&ItemBones>, item_throw_strength: f32, subaction_names: &[&str]) -> ItemOverrides {
+        let mut swing_subactions = vec!();
+        for (subaction_index, name) in subaction_names.iter().enumerate() {
+            if let Some(swing) = ItemSwing::from_subaction_name(name) {
+                swing_subactions.push(ItemSwingSubaction { swing, subaction_index });
+            }
+        }
+
+        ItemOverrides {
+            held_item_bone:  item_bones.map(|x| x.have_n_bone),
+            throw_item_bone: item_bones.map(|x| x.throw_n_bone),
+            unknown:         item_bones.map(|x| x.unknown.clone()).unwrap_or_default(),
+            item_throw_strength,
+            swing_subactions,
+        }
+    }
+
+    /// Returns the index of the subaction used for the swing
+    pub fn subaction_for(&self, swing: &ItemSwing) -> Option<usize> {
+        self.swing_subactions.iter().find(|x| &x.swing == swing).map(|x| x.subaction_index)
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct ItemSwingSubaction {
+    pub swing: ItemSwing,
+    pub subaction_index: usize,
+}
+
+/// The attacks a fighter can perform while holding a battering item e.g. a beam sword.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum ItemSwing {
+    /// Swing1
+    Jab,
+    /// Swing3
+    Tilt,
+    /// Swing4
+    Smash,
+    /// Swing4Start
+    SmashStart,
+    /// Swing42
+    Smash2,
+    /// Swing4Bat
+    HomeRunBatSmash,
+    /// SwingDash
+    DashAttack,
+    /// A swing subaction that isnt recognized, contains the subaction name
+    Unknown (String),
+}
+
+impl ItemSwing {
+    pub fn from_subaction_name(name: &str) -> Option<ItemSwing> {
+        if !name.starts_with("Swing") {
+            return None;
+        }
+
+        Some(match name {
+            "Swing1"      => ItemSwing::Jab,
+            "Swing3"      => ItemSwing::Tilt,
+            "Swing4"      => ItemSwing::Smash,
+            "Swing4Start" => ItemSwing::SmashStart,
+            "Swing42"     => ItemSwing::Smash2,
+            "Swing4Bat"   => ItemSwing::HomeRunBatSmash,
+            "SwingDash"   => ItemSwing::DashAttack,
+            _             => ItemSwing::Unknown (name.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swing_mapping() {
+        let item_bones = ItemBones { have_n_bone: 0x2a, throw_n_bone: 0x2b, unknown: vec!(7) };
+        let names = ["Wait1", "Swing1", "Swing4Bat", "SwingUnknown", "Swing4"];
+        let overrides = ItemOverrides::new(Some(&item_bones), 1.0, &names);
+
+        assert_eq!(overrides.held_item_bone, Some(0x2a));
+        assert_eq!(overrides.throw_item_bone, Some(0x2b));
+        assert_eq!(overrides.unknown, vec!(7));
+        assert_eq!(overrides.subaction_for(&ItemSwing::Jab), Some(1));
+        assert_eq!(overrides.subaction_for(&ItemSwing::HomeRunBatSmash), Some(2));
+        assert_eq!(overrides.subaction_for(&ItemSwing::Unknown (String::from("SwingUnknown"))), Some(3));
+        assert_eq!(overrides.subaction_for(&ItemSwing::Smash), Some(4));
+        assert_eq!(overrides.subaction_for(&ItemSwing::DashAttack), None);
+    }
+}
@@ -0,0 +1,269 @@
+//! The headline numbers of a fighter, as shown on a character select or roster screen.
+
+use crate::aerial_data::Aerial;
+use crate::analysis::{self, MoveName};
+use crate::fighter::Fighter;
+use crate::grab_data;
+use crate::sakurai::fighter_data::ArcFighterData;
+use crate::script::{Argument, Script, normalize_timers};
+use crate::script_ast::ScriptAst;
+
+/// Every move considered for `KitSummary::fastest_move` and `KitSummary::strongest_move`, in the order ties are resolved
+const ATTACKS: &[MoveName] = &[
+    MoveName::Jab,
+    MoveName::ForwardTilt,
+    MoveName::UpTilt,
+    MoveName::DownTilt,
+    MoveName::ForwardSmash,
+    MoveName::UpSmash,
+    MoveName::DownSmash,
+    MoveName::DashAttack,
+    MoveName::Aerial (Aerial::Nair),
+    MoveName::Aerial (Aerial::Fair),
+    MoveName::Aerial (Aerial::Bair),
+    MoveName::Aerial (Aerial::Uair),
+    MoveName::Aerial (Aerial::Dair),
+    MoveName::NeutralSpecial,
+    MoveName::SideSpecial,
+    MoveName::UpSpecial,
+    MoveName::DownSpecial,
+];
+
+const SPECIALS: &[MoveName] = &[
+    MoveName::NeutralSpecial,
+    MoveName::SideSpecial,
+    MoveName::UpSpecial,
+    MoveName::DownSpecial,
+];
+
+/// Headline stats of a fighter, see `Fighter::kit_summary`.
+///
+/// Every value that could not be determined is None rather than 0.
+/// The attribute values are None only when the fighter has no fighter data.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct KitSummary {
+    pub weight: Option<f32>,
+    /// Maximum walking speed
+    pub walk_speed: Option<f32>,
+    /// Maximum running speed
+    pub run_speed: Option<f32>,
+    /// Maximum horizontal speed in the air
+    pub air_speed: Option<f32>,
+    pub fall_speed: Option<f32>,
+    pub fastfall_speed: Option<f32>,
+    /// Total number of jumps, including the jump from the ground
+    pub jumps: Option<u32>,
+    /// The specials the fighter has, in the order neutral, side, up, down.
+    /// Specials the fighter lacks are left out.
+    pub specials: Option<Vec<SpecialSummary>>,
+    /// How far the largest grab box of the grab reaches from the bone it is attached to, ignoring its vertical offset.
+    /// None when the fighter has no grab or the grab creates no grab boxes.
+    pub grab_range: Option<f32>,
+    /// The move with the earliest hitbox, None when no move creates a hitbox
+    pub fastest_move: Option<MoveSummary>,
+    /// The move with the highest damage hitbox, None when no move creates a hitbox
+    pub strongest_move: Option<MoveSummary>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct SpecialSummary {
+    pub special: MoveName,
+    /// The subaction implementing the special
+    pub subaction: String,
+}
+
+/// A move that creates at least one hitbox.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct MoveSummary {
+    pub move_name: MoveName,
+    pub subaction: String,
+    /// The first frame a hitbox is active, starting at 1
+    pub startup: f32,
+    /// The highest damage of any hitbox in the move
+    pub damage: f32,
+}
+
+impl KitSummary {
+    /// Values are calculated from the attributes and main subaction scripts in the same way as `analysis::roster_table`.
+    /// No `HighLevelFighter` is generated, so this is cheap enough to call for every fighter in a roster.
+    pub fn new(fighter: &Fighter) -> KitSummary {
+        let fighter_data = match fighter.get_fighter_data() {
+            Some(fighter_data) => fighter_data,
+            None => return KitSummary {
+                weight:         None,
+                walk_speed:     None,
+                run_speed:      None,
+                air_speed:      None,
+                fall_speed:     None,
+                fastfall_speed: None,
+                jumps:          None,
+                specials:       None,
+                grab_range:     None,
+                fastest_move:   None,
+                strongest_move: None,
+            }
+        };
+        let attributes = &fighter_data.attributes;
+
+        let specials = SPECIALS.iter()
+            .filter_map(|special| find_move(fighter_data, *special).map(|(subaction, _)| SpecialSummary { special: *special, subaction }))
+            .collect();
+
+        let grab_range = find_move(fighter_data, MoveName::Grab)
+            .and_then(|(_, script)| script)
+            .and_then(grab_range);
+
+        let moves: Vec<MoveSummary> = ATTACKS.iter().filter_map(|move_name| {
+            let (subaction, script) = find_move(fighter_data, *move_name)?;
+            let (startup, damage) = hitbox_summary(script?)?;
+            Some(MoveSummary { move_name: *move_name, subaction, startup, damage })
+        }).collect();
+
+        let mut fastest_move: Option<&MoveSummary> = None;
+        let mut strongest_move: Option<&MoveSummary> = None;
+        for summary in &moves {
+            if fastest_move.map(|x| summary.startup < x.startup).unwrap_or(true) {
+                fastest_move = Some(summary);
+            }
+            if strongest_move.map(|x| summary.damage > x.damage).unwrap_or(true) {
+                strongest_move = Some(summary);
+            }
+        }
+
+        KitSummary {
+            weight:         Some(attributes.weight),
+            walk_speed:     Some(attributes.walk_max_vel),
+            run_speed:      Some(attributes.dash_run_term_vel),
+            air_speed:      Some(attributes.air_x_term_vel),
+            fall_speed:     Some(attributes.term_vel),
+            fastfall_speed: Some(attributes.fastfall_velocity),
+            jumps:          Some(attributes.num_jumps),
+            specials:       Some(specials),
+            grab_range,
+            fastest_move:   fastest_move.cloned(),
+            strongest_move: strongest_move.cloned(),
+        }
+    }
+}
+
+/// Returns the name and main script of the first subaction implementing the move
+pub(crate) fn find_move(fighter_data: &ArcFighterData, move_name: MoveName) -> Option<(String, Option<&Script>)> {
+    move_name.subaction_names().iter().find_map(|name| {
+        fighter_data.subaction_flags.iter().position(|x| x.name == *name)
+            .map(|index| (fighter_data.subaction_flags[index].name.clone(), fighter_data.subaction_main.get(index)))
+    })
+}
+
+/// Returns the first frame a hitbox is created on and the highest damage of any hitbox in the script
+fn hitbox_summary(script: &Script) -> Option<(f32, f32)> {
+    let (startup, _) = analysis::first_hitboxes(script)?;
+    let damage = normalize_timers(script).events.into_iter()
+        .filter(|x| x.event.namespace == 0x06 && (x.event.code == 0x00 || x.event.code == 0x15))
+        .filter_map(|x| match x.event.arguments.get(1) {
+            Some(Argument::Value (damage)) => Some(*damage as f32),
+            _ => None,
+        })
+        .fold(0.0, f32::max);
+    Some((startup, damage))
+}
+
+/// Returns the furthest horizontal reach of the grab boxes created by the script
+fn grab_range(script: &Script) -> Option<f32> {
+    grab_data::bone_range(&grab_data::grab_boxes(&ScriptAst::new(script).block))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{TestFighterBuilder, damage};
+    use crate::script::FixedPoint;
+
+    fn grab_box(size: f32, x: f32, z: f32) -> Vec<Argument> {
+        vec!(
+            Argument::Value (0),
+            Argument::Value (0),
+            Argument::Scalar (FixedPoint::from_frames(size)),
+            Argument::Scalar (FixedPoint::from_frames(x)),
+            Argument::Scalar (FixedPoint::from_frames(0.0)),
+            Argument::Scalar (FixedPoint::from_frames(z)),
+            Argument::Value (0x34),
+            Argument::Value (1),
+        )
+    }
+
+    #[test]
+    fn full_kit() {
+        let fighter = TestFighterBuilder::new("Full")
+            .attribute_f32(0x08, 1.1)
+            .attribute_f32(0x1c, 1.8)
+            .attribute_i32(0x60, 3)
+            .attribute_f32(0x68, 1.6)
+            .attribute_f32(0x7c, 1.0)
+            .attribute_f32(0x84, 2.4)
+            .attribute_f32(0xb0, 98.0)
+            .subaction("Attack11", |s| s.async_timer(3.0).hitbox(damage(3)).sync_timer(2.0).terminate_collisions())
+            .subaction("AttackS4S", |s| s.async_timer(14.0).hitbox(damage(14)).sync_timer(2.0).hitbox(damage(18)).terminate_collisions())
+            .subaction("AttackLw3", |s| s.async_timer(3.0).hitbox(damage(9)).terminate_collisions())
+            .subaction("Catch", |s| s.async_timer(7.0)
+                .event(0x06, 0x0A, grab_box(3.0, 0.0, 8.0))
+                .event(0x06, 0x0A, grab_box(4.0, 3.0, 4.0))
+            )
+            .subaction("SpecialN", |s| s.async_timer(20.0).hitbox(damage(5)))
+            // only the start of the up special exists
+            .subaction("SpecialHiStart", |s| s)
+            .build();
+
+        let summary = fighter.kit_summary();
+        assert_eq!(summary.weight, Some(98.0));
+        assert_eq!(summary.walk_speed, Some(1.1));
+        assert_eq!(summary.run_speed, Some(1.8));
+        assert_eq!(summary.air_speed, Some(1.0));
+        assert_eq!(summary.fall_speed, Some(1.6));
+        assert_eq!(summary.fastfall_speed, Some(2.4));
+        assert_eq!(summary.jumps, Some(3));
+        assert_eq!(summary.specials, Some(vec!(
+            SpecialSummary { special: MoveName::NeutralSpecial, subaction: "SpecialN".into() },
+            SpecialSummary { special: MoveName::UpSpecial, subaction: "SpecialHiStart".into() },
+        )));
+        assert_eq!(summary.grab_range, Some(11.0));
+
+        // jab and dtilt are both active on frame 4, the jab comes first
+        assert_eq!(summary.fastest_move, Some(MoveSummary { move_name: MoveName::Jab, subaction: "Attack11".into(), startup: 4.0, damage: 3.0 }));
+        // the later hitbox of the smash is the strongest
+        assert_eq!(summary.strongest_move, Some(MoveSummary { move_name: MoveName::ForwardSmash, subaction: "AttackS4S".into(), startup: 15.0, damage: 18.0 }));
+    }
+
+    #[test]
+    fn missing_moves() {
+        // has a grab and specials but none of them create hitboxes or grab boxes
+        let fighter = TestFighterBuilder::new("Empty")
+            .subaction("Wait1", |s| s.sync_timer(10.0))
+            .subaction("Catch", |s| s.async_timer(7.0))
+            .subaction("SpecialLw", |s| s.sync_timer(30.0))
+            .build();
+
+        let summary = fighter.kit_summary();
+        assert_eq!(summary.specials, Some(vec!(SpecialSummary { special: MoveName::DownSpecial, subaction: "SpecialLw".into() })));
+        assert_eq!(summary.grab_range, None);
+        assert_eq!(summary.fastest_move, None);
+        assert_eq!(summary.strongest_move, None);
+        // attributes are read even when 0
+        assert_eq!(summary.weight, Some(0.0));
+
+        let fighter = TestFighterBuilder::new("NoGrab").build();
+        assert_eq!(fighter.kit_summary().grab_range, None);
+        assert_eq!(fighter.kit_summary().specials, Some(vec!()));
+
+        // without a moveset nothing can be determined
+        let mut fighter = TestFighterBuilder::new("NoMoveset").build();
+        fighter.moveset.children.clear();
+        let summary = fighter.kit_summary();
+        assert_eq!(summary.weight, None);
+        assert_eq!(summary.jumps, None);
+        assert_eq!(summary.specials, None);
+        assert_eq!(summary.fastest_move, None);
+    }
+}
@@ -0,0 +1,234 @@
+//! Frame data of the options a fighter has while hanging from a ledge.
+//!
+//! Each option is a subaction of the fighter played in a common action, e.g. the subaction `CliffClimbQuick` is played in the action `CliffClimb`.
+//! The entry script of the action lives in the common Fighter.pac unless the fighter overrides it and can change the state of the fighter,
+//! so it is run alongside the subaction scripts for the whole subaction.
+
+use std::ops::RangeInclusive;
+
+use crate::fighter::{Fighter, NotLoaded};
+use crate::script::Script;
+use crate::script_ast::{Block, ScriptAst};
+use crate::script_runner::{ScriptRunner, ChangeSubaction};
+
+/// Scripts that never end are cut off after this many frames when there is no animation to take the length from.
+const MAX_FRAMES: usize = 1000;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum LedgeOptionKind {
+    GetUp,
+    Roll,
+    Attack,
+    Jump,
+}
+
+/// The ledge subactions, the speed of the option and the common action each subaction is played in.
+/// The quick subactions are used below 100% damage, the slow subactions at 100% and above.
+const LEDGE_SUBACTIONS: &[(&str, LedgeOptionKind, bool, usize)] = &[
+    ("CliffClimbQuick",  LedgeOptionKind::GetUp,  false, 0x77),
+    ("CliffClimbSlow",   LedgeOptionKind::GetUp,  true,  0x77),
+    ("CliffEscapeQuick", LedgeOptionKind::Roll,   false, 0x78),
+    ("CliffEscapeSlow",  LedgeOptionKind::Roll,   true,  0x78),
+    ("CliffAttackQuick", LedgeOptionKind::Attack, false, 0x76),
+    ("CliffAttackSlow",  LedgeOptionKind::Attack, true,  0x76),
+    ("CliffJumpQuick1",  LedgeOptionKind::Jump,   false, 0x79),
+    ("CliffJumpQuick2",  LedgeOptionKind::Jump,   false, 0x7A),
+    ("CliffJumpSlow1",   LedgeOptionKind::Jump,   true,  0x79),
+    ("CliffJumpSlow2",   LedgeOptionKind::Jump,   true,  0x7A),
+];
+
+/// The frame data of a single ledge option subaction, see `Fighter::ledge_options`.
+/// Frames start at 1.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct LedgeOption {
+    pub kind:      LedgeOptionKind,
+    /// True for the option used at 100% damage and above
+    pub slow:      bool,
+    pub subaction: String,
+    /// The common action the subaction is played in
+    pub action:    String,
+    /// True when the fighter replaces the Fighter.pac entry script of `action` with its own
+    pub action_overridden: bool,
+    /// The length of the animation, or when the subaction has no animation, the frame the scripts end on.
+    /// Subactions that change to another subaction end on the frame they change.
+    pub total_frames: usize,
+    /// The frames the whole body is invincible or intangible
+    pub invincible: Vec<RangeInclusive<usize>>,
+    /// The first frame the fighter can interrupt the subaction, None when the fighter can only act after the subaction ends
+    pub first_actionable_frame: Option<usize>,
+}
+
+pub(crate) fn ledge_options(fighter: &Fighter, common_entry_actions: &[Script]) -> Result<Vec<LedgeOption>, NotLoaded> {
+    let animations = fighter.get_animations()?;
+    let fighter_data = match fighter.get_fighter_data() {
+        Some(fighter_data) => fighter_data,
+        None => return Ok(vec!()),
+    };
+
+    let fragment_scripts: Vec<ScriptAst> = fighter.get_fighter_sakurai().map(|x| x.fragment_scripts.iter().map(ScriptAst::new).collect()).unwrap_or_default();
+    let fragment_scripts_common: Vec<ScriptAst> = fighter.get_fighter_sakurai_common().map(|x| x.fragment_scripts.iter().map(ScriptAst::new).collect()).unwrap_or_default();
+    let fighter_scripts: Vec<&ScriptAst> = fragment_scripts.iter().collect();
+    let common_scripts: Vec<&ScriptAst> = fragment_scripts_common.iter().collect();
+
+    let mut options = vec!();
+    for (name, kind, slow, action_id) in LEDGE_SUBACTIONS {
+        let index = match fighter_data.subaction_flags.iter().position(|x| x.name == *name) {
+            Some(index) => index,
+            None => continue,
+        };
+        let subaction_scripts = match fighter_data.subaction_scripts(index) {
+            Some(scripts) => scripts,
+            None => continue,
+        };
+        let subaction_scripts = [
+            ScriptAst::new(subaction_scripts.main),
+            ScriptAst::new(subaction_scripts.gfx),
+            ScriptAst::new(subaction_scripts.sfx),
+            ScriptAst::new(subaction_scripts.other),
+        ];
+        let subaction_scripts: Vec<&ScriptAst> = subaction_scripts.iter().collect();
+
+        let action_override = fighter_data.entry_action_overrides.iter().find(|x| x.action_id as usize == *action_id);
+        let entry_script = match action_override {
+            Some(action_override) => Some(&action_override.script),
+            None                  => common_entry_actions.get(*action_id),
+        };
+        let entry_script = entry_script.map(ScriptAst::new);
+
+        let num_frames = animations.iter().find(|x| x.name == *name).map(|x| x.num_frames as f32);
+        let runner = match &entry_script {
+            Some(entry_script) => ScriptRunner::new_in_action(entry_script, index, &fighter.wiird_frame_speed_modifiers, &subaction_scripts, &fighter_scripts, &common_scripts, &[], fighter_data, name.to_string()),
+            None               => ScriptRunner::new(index, &fighter.wiird_frame_speed_modifiers, &subaction_scripts, &fighter_scripts, &common_scripts, &[], &Block { events: vec!() }, fighter_data, name.to_string()),
+        };
+        let (total_frames, invincible, first_actionable_frame) = run(runner, num_frames);
+
+        options.push(LedgeOption {
+            kind:              *kind,
+            slow:              *slow,
+            subaction:         name.to_string(),
+            action:            crate::action_names::action_name(*action_id),
+            action_overridden: action_override.is_some(),
+            total_frames,
+            invincible,
+            first_actionable_frame,
+        });
+    }
+    Ok(options)
+}
+
+/// Steps through the subaction, returning the total frames, the invincible windows and the first actionable frame
+fn run(mut runner: ScriptRunner, num_frames: Option<f32>) -> (usize, Vec<RangeInclusive<usize>>, Option<usize>) {
+    let mut invincible: Vec<RangeInclusive<usize>> = vec!();
+    let mut first_actionable_frame = None;
+    let mut frame = 0;
+    loop {
+        frame += 1;
+        let state = &runner.hurtbox_state_all;
+        if state.is_invincible() || state.is_intangible() {
+            match invincible.last_mut() {
+                Some(window) if *window.end() == frame - 1 => *window = *window.start()..=frame,
+                _ => invincible.push(frame..=frame),
+            }
+        }
+        if first_actionable_frame.is_none() && runner.interruptible {
+            first_actionable_frame = Some(frame);
+        }
+
+        runner.step();
+        if let ChangeSubaction::Continue = runner.change_subaction { } else { break }
+        let ended = match num_frames {
+            Some(num_frames) => runner.animation_index >= num_frames,
+            None => runner.call_stacks.iter().all(|x| x.calls.is_empty()) || frame >= MAX_FRAMES,
+        };
+        if ended {
+            break;
+        }
+    }
+    (frame, invincible, first_actionable_frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fighter::LoadDetail;
+    use crate::script::Argument;
+    use crate::test_util::{TestFighterBuilder, ScriptBuilder};
+
+    fn hurtbox_state(script: ScriptBuilder, state: i32) -> ScriptBuilder {
+        script.event(0x06, 0x05, vec!(Argument::Value (state)))
+    }
+
+    /// The fighter has no animations, so the options end with their scripts
+    fn fighter() -> Fighter {
+        let mut fighter = scripts_only_fighter();
+        fighter.load_detail = LoadDetail::ScriptsAndAnimations;
+        fighter
+    }
+
+    fn scripts_only_fighter() -> Fighter {
+        TestFighterBuilder::new("Test")
+            .subaction("Wait1", |s| s.sync_timer(10.0))
+            .subaction("CliffClimbQuick", |s| {
+                let s = hurtbox_state(s.sync_timer(5.0), 2).sync_timer(20.0);
+                hurtbox_state(s, 0).sync_timer(5.0).event(0x64, 0x00, vec!()).sync_timer(5.0)
+            })
+            .subaction("CliffAttackQuick", |s| hurtbox_state(s.sync_timer(9.0), 0).sync_timer(30.0))
+            .subaction("CliffEscapeSlow", |s| {
+                let s = hurtbox_state(s, 1).sync_timer(3.0);
+                let s = hurtbox_state(s, 0).sync_timer(2.0);
+                hurtbox_state(s, 3).sync_timer(4.0).sync_timer(10.0)
+            })
+            .build()
+    }
+
+    #[test]
+    fn subaction_windows() {
+        let options = ledge_options(&fighter(), &[]).unwrap();
+        assert_eq!(options.len(), 3);
+
+        let climb = &options[0];
+        assert_eq!((climb.kind, climb.slow, climb.subaction.as_str(), climb.action.as_str()), (LedgeOptionKind::GetUp, false, "CliffClimbQuick", "CliffClimb"));
+        assert_eq!(climb.total_frames, 35);
+        assert_eq!(climb.invincible, vec!(6..=25));
+        assert_eq!(climb.first_actionable_frame, Some(31));
+        assert!(!climb.action_overridden);
+
+        let roll = &options[1];
+        assert_eq!((roll.kind, roll.slow), (LedgeOptionKind::Roll, true));
+        assert_eq!(roll.invincible, vec!(1..=3, 6..=19));
+        assert_eq!(roll.first_actionable_frame, None);
+
+        // without an entry script the attack starts vulnerable
+        let attack = &options[2];
+        assert_eq!(attack.kind, LedgeOptionKind::Attack);
+        assert_eq!(attack.total_frames, 39);
+        assert!(attack.invincible.is_empty());
+    }
+
+    #[test]
+    fn common_action_entry_script() {
+        let mut common_entry_actions: Vec<Script> = (0..0x112).map(|_| ScriptBuilder::new().build()).collect();
+        // the common CliffAttack action makes the fighter intangible, the subaction turns it off on frame 10
+        common_entry_actions[0x76] = hurtbox_state(ScriptBuilder::new(), 2).sync_timer(1.0).event(0x64, 0x00, vec!()).build();
+        // the common CliffClimb action allows interrupts immediately, overriding the subactions interrupt frame
+        common_entry_actions[0x77] = ScriptBuilder::new().event(0x64, 0x00, vec!()).build();
+
+        let options = ledge_options(&fighter(), &common_entry_actions).unwrap();
+
+        let climb = &options[0];
+        assert_eq!(climb.first_actionable_frame, Some(1));
+        assert_eq!(climb.invincible, vec!(6..=25));
+
+        let attack = &options[2];
+        assert_eq!(attack.invincible, vec!(1..=9));
+        // the entry script continues after its first timer
+        assert_eq!(attack.first_actionable_frame, Some(2));
+    }
+
+    #[test]
+    fn animations_not_loaded() {
+        assert_eq!(ledge_options(&scripts_only_fighter(), &[]), Err(NotLoaded::Animations));
+    }
+}
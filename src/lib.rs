@@ -22,33 +22,90 @@
 //!     println!("Hurtboxes on the 4th frame of 'Run' action {:#?}", hl_fighter.subactions.iter().find(|x| x.name == "Run").unwrap().frames[4].hurt_boxes);
 //! }
 //! ```
+//!
+//! The most commonly used types can be imported at once with `use brawllib_rs::prelude::*;`
 
 #[macro_use] extern crate serde_derive;
 #[macro_use] extern crate bitflags;
+// The log macros come from `tracing` when it is enabled, they take the same arguments
+#[cfg(not(feature = "tracing"))]
 #[macro_use] extern crate log;
+#[cfg(feature = "tracing")]
+#[macro_use] extern crate tracing;
+
+#[macro_use] mod trace;
 
+pub mod address;
+pub mod aerial_data;
+pub mod analysis;
 pub mod arc;
 pub mod brawl_mod;
+pub mod brawlbox_text;
 pub mod bres;
+pub mod calc;
+pub mod cancel;
 pub mod chr0;
+pub mod code_modifications;
+pub mod compression;
+pub mod content_hash;
+#[cfg(feature = "disc")]
+pub mod disc;
+pub mod edit;
+pub mod effect_pac;
+pub mod event_decoder;
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod fighter;
+pub mod final_smash;
+pub mod game_frame;
+pub mod grab_data;
 pub mod high_level_fighter;
-pub mod math;
+pub mod interrupt_groups;
+pub mod item_id;
+pub mod item_overrides;
+pub mod kit_summary;
+pub mod ledge_options;
 pub mod mbox;
 pub mod mdl0;
+pub mod motion;
+pub mod movement;
+pub mod msbin;
 pub mod plt0;
+pub mod prelude;
+pub mod provenance;
 pub mod renderer;
-pub mod resources;
+pub mod report;
 pub mod sakurai;
 pub mod script;
 pub mod script_ast;
+pub mod script_diff;
 pub mod script_runner;
+pub mod simulate;
+pub mod slot_metadata;
+pub mod space_report;
+pub mod stage;
+pub mod subaction_category;
+pub mod subaction_environment;
+pub mod subaction_invokers;
+pub mod symbols;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
 pub mod user_data;
+pub mod validation;
+pub mod vis0;
 pub mod wii_memory;
 pub mod wii_texture_formats;
 pub mod wiird;
+pub mod wiird_debugger;
 pub mod wiird_runner;
 mod util;
+mod math;
+mod resources;
 mod action_names;
 mod fighter_maps;
 mod init_hack_script;
+mod ppc;
+mod script_assembler;
+mod memory_files;
+mod file_provider;
@@ -18,6 +18,7 @@ pub fn mbox(data: FancySlice) -> MBox {
 
 // named MBox because Box is used in std lib
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct MBox {
     min: Vector3<f32>,
     max: Vector3<f32>,
@@ -170,6 +170,7 @@ fn bone_siblings(data_root: FancySlice, offset: i32) -> Vec<Bone> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Bone {
     pub name: String,
     header_len: i32,
@@ -200,9 +201,32 @@ impl Bone {
     pub(crate) fn gen_transform_rot_only(&self) -> Matrix4<f32> {
         math::gen_transform(Vector3::new(1.0, 1.0, 1.0), self.rot, Vector3::new(0.0, 0.0, 0.0))
     }
+
+    /// Returns this bone or the descendant with the index
+    pub fn find_by_index(&self, index: i32) -> Option<&Bone> {
+        if self.index == index {
+            return Some(self);
+        }
+        self.children.iter().filter_map(|x| x.find_by_index(index)).next()
+    }
+
+    /// Returns this bone or the descendant with the name.
+    /// The name is compared case-insensitively, but a bone with exactly the same name is preferred.
+    pub fn find_by_name(&self, name: &str) -> Option<&Bone> {
+        self.find(&|x| x.name == name)
+            .or_else(|| self.find(&|x| x.name.eq_ignore_ascii_case(name)))
+    }
+
+    fn find(&self, predicate: &dyn Fn(&Bone) -> bool) -> Option<&Bone> {
+        if predicate(self) {
+            return Some(self);
+        }
+        self.children.iter().filter_map(|x| x.find(predicate)).next()
+    }
 }
 
 bitflags! {
+    #[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
     pub struct BoneFlags: u32 {
         /// Needs to match what this bones transformation does. Identity matrix.
         const NO_TRANSFORM          = 0x1;
@@ -225,6 +249,7 @@ bitflags! {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub enum BoneBillboard {
     Off,
     Standard,
@@ -234,3 +259,64 @@ pub enum BoneBillboard {
     Y,
     YPerspective,
 }
+
+/// Creates a bone with an identity transform for tests that only need the bone hierarchy
+#[cfg(test)]
+pub(crate) fn test_bone(name: &str, index: i32, children: Vec<Bone>) -> Bone {
+    let scale = Vector3::new(1.0, 1.0, 1.0);
+    let rot = Vector3::new(0.0, 0.0, 0.0);
+    let translate = Vector3::new(0.0, 0.0, 0.0);
+    Bone {
+        name:             name.to_string(),
+        header_len:       0,
+        mdl0_offset:      0,
+        string_offset:    0,
+        index,
+        node_id:          index,
+        flags:            BoneFlags::empty(),
+        billboard:        BoneBillboard::Off,
+        bb_index:         0,
+        scale,
+        rot,
+        translate,
+        extents:          mbox::mbox(FancySlice::new(&[0; 0x18])),
+        user_data_offset: 0,
+        transform:        math::gen_transform(scale, rot, translate),
+        transform_inv:    math::gen_transform(scale, rot, translate),
+        children,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skeleton() -> Bone {
+        test_bone("TopN", 0, vec!(
+            test_bone("TransN", 1, vec!(
+                test_bone("HaveN", 2, vec!()),
+                test_bone("RHaveN", 3, vec!()),
+                test_bone("rhaven", 4, vec!()),
+            )),
+        ))
+    }
+
+    #[test]
+    fn find_by_name() {
+        let bones = skeleton();
+        assert_eq!(bones.find_by_name("HaveN").unwrap().index, 2);
+        assert_eq!(bones.find_by_name("haven").unwrap().index, 2);
+        // an exact match is preferred over an earlier case-insensitive match
+        assert_eq!(bones.find_by_name("rhaven").unwrap().index, 4);
+        assert_eq!(bones.find_by_name("RHAVEN").unwrap().index, 3);
+        assert!(bones.find_by_name("LHaveN").is_none());
+    }
+
+    #[test]
+    fn find_by_index() {
+        let bones = skeleton();
+        assert_eq!(bones.find_by_index(3).unwrap().name, "RHaveN");
+        assert_eq!(bones.find_by_index(0).unwrap().name, "TopN");
+        assert!(bones.find_by_index(5).is_none());
+    }
+}
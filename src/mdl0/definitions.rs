@@ -4,6 +4,7 @@ use crate::resources::Resource;
 use crate::resources;
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Definitions {
     pub values: Vec<Definition>,
 }
@@ -69,12 +70,14 @@ impl Definition {
 
 const DEFINITION_SIZE: usize = 0x8;
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Definition {
     pub name: String,
     pub draw_calls: Vec<DrawCall>,
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct DrawCall {
     pub material: u16,
     pub object: u16,
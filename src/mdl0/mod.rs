@@ -133,6 +133,7 @@ pub fn mdl0(data: FancySlice) -> Mdl0 {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Mdl0 {
     pub name: String,
     version: i32,
@@ -233,6 +234,7 @@ impl Mdl0 {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Mdl0Props {
     header_len: u32,
     mdl0offset: i32,
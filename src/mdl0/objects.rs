@@ -79,6 +79,7 @@ pub(crate) fn objects(data: FancySlice, resources: Vec<Resource>) -> Vec<Object>
 
 const _OBJECT_SIZE: usize = 0x64;
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Object {
     pub single_bind_node_id: Option<u32>,
 
@@ -214,6 +215,7 @@ impl Object {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub enum XFDataFormat {
     None,
     Direct,
@@ -234,6 +236,7 @@ impl XFDataFormat {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub enum XFNormalType {
     None,
     XYZ,
@@ -254,6 +257,7 @@ impl XFNormalType {
 // TODO: This needs a better name but not sure how its used.
 // Brawlbox calls it flag which is even worse
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub enum Modifier {
     None,
     ChangeCurrentMatrix,
@@ -23,6 +23,7 @@ pub(crate) fn palettes(data: FancySlice, resources: Vec<Resource>) -> Vec<Palett
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Palette {
     pub name: String,
     pub references: Vec<PaletteRef>,
@@ -30,6 +31,7 @@ pub struct Palette {
 
 const PALETTE_REF_SIZE: usize = 0x8;
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct PaletteRef {
     pub material_offset: i32,
     pub reference_offset: i32,
@@ -22,6 +22,7 @@ pub(crate) fn textures(data: FancySlice, resources: Vec<Resource>) -> Vec<Textur
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Texture {
     pub name: String,
     pub references: Vec<TextureRef>,
@@ -29,6 +30,7 @@ pub struct Texture {
 
 const TEXTURE_REF_SIZE: usize = 0x8;
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct TextureRef {
     pub material_offset: i32,
     pub reference_offset: i32,
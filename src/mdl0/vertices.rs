@@ -52,6 +52,7 @@ pub(crate) fn vertices(data: FancySlice, resources: Vec<Resource>) -> Vec<Vertic
 
 const _VERTICES_SIZE: usize = 0x40;
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Vertices {
     pub name: String,
     pub data: Vec<u8>,
@@ -67,6 +68,7 @@ pub struct Vertices {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub enum VertexComponentType {
     U8,
     I8,
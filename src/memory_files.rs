@@ -0,0 +1,120 @@
+//! Mod files held in memory instead of on disk, see `BrawlMod::from_memory`.
+//!
+//! Paths are relative to the mods pf folder e.g. `fighter/mario/FitMario.pac`.
+//! Like the Wii's SD card, paths are compared case insensitively and either `/` or `\` may separate the folders.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// A file of `MemoryFiles`
+pub(crate) struct MemoryFile {
+    /// The normalized path, see `normalize_path`
+    pub path: String,
+    pub data: Vec<u8>,
+}
+
+impl MemoryFile {
+    /// The name of the file without its folders
+    pub fn name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or("")
+    }
+
+    /// The path reported in the provenance of anything read from the file
+    pub fn provenance_path(&self) -> PathBuf {
+        PathBuf::from(&self.path)
+    }
+}
+
+/// Files of a mod held in memory, keyed by their path relative to the mods pf folder.
+pub(crate) struct MemoryFiles {
+    /// Sorted by path
+    files: Vec<MemoryFile>,
+}
+
+impl MemoryFiles {
+    /// When multiple paths are the same after normalization the path that sorts last is used, so the result does not depend on the order of the map.
+    pub fn new(files: HashMap<String, Vec<u8>>) -> MemoryFiles {
+        let mut files: Vec<(String, Vec<u8>)> = files.into_iter().collect();
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut result: Vec<MemoryFile> = vec!();
+        for (path, data) in files {
+            let path = normalize_path(&path);
+            result.retain(|x| !x.path.eq_ignore_ascii_case(&path));
+            result.push(MemoryFile { path, data });
+        }
+        result.sort_by_key(|x| x.path.to_lowercase());
+        MemoryFiles { files: result }
+    }
+
+    /// Returns the file at the pf relative `path`
+    pub fn get(&self, path: &str) -> Option<&MemoryFile> {
+        let path = normalize_path(path);
+        self.files.iter().find(|x| x.path.eq_ignore_ascii_case(&path))
+    }
+
+    /// Returns every file inside of the pf relative `dir` including files in its subfolders, along with the path of the file relative to `dir`
+    pub fn files_in<'a>(&'a self, dir: &str) -> impl Iterator<Item=(&'a str, &'a MemoryFile)> {
+        let dir = normalize_path(dir);
+        self.files.iter().filter_map(move |file| {
+            let (prefix, relative) = (file.path.get(..dir.len())?, file.path.get(dir.len()..)?);
+            if prefix.eq_ignore_ascii_case(&dir) {
+                relative.strip_prefix('/').map(|relative| (relative, file))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns the WiiRD codeset: the first file named RSBE01.gct in any folder.
+    /// The codes folder is not in the pf folder, so the codeset is the only file that is not found by its path.
+    pub fn codeset(&self) -> Option<&MemoryFile> {
+        self.files.iter().find(|x| x.name().eq_ignore_ascii_case("RSBE01.gct"))
+    }
+}
+
+/// Converts `path` to a pf relative path separated by `/`.
+/// Backslashes, empty folders and `.` folders are removed, as is a leading `pf` folder in case the key was written relative to the mod folder instead.
+/// The case of the path is kept.
+pub(crate) fn normalize_path(path: &str) -> String {
+    let mut components: Vec<&str> = path.split(['/', '\\'])
+        .filter(|x| !x.is_empty() && *x != ".")
+        .collect();
+    if components.len() > 1 && components[0].eq_ignore_ascii_case("pf") {
+        components.remove(0);
+    }
+    components.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize() {
+        assert_eq!(normalize_path("fighter/mario/FitMario.pac"), "fighter/mario/FitMario.pac");
+        assert_eq!(normalize_path("\\fighter\\Mario\\.\\FitMario.pac"), "fighter/Mario/FitMario.pac");
+        assert_eq!(normalize_path("pf/fighter//mario/FitMario.pac"), "fighter/mario/FitMario.pac");
+        assert_eq!(normalize_path("./Fighter.pac"), "Fighter.pac");
+    }
+
+    #[test]
+    fn lookup() {
+        let mut map = HashMap::new();
+        map.insert(String::from("fighter/Mario/FitMario.pac"), vec!(1));
+        map.insert(String::from("fighter\\mario\\fitmario.pac"), vec!(2));
+        map.insert(String::from("fighter/luigi/FitLuigi.pac"), vec!(3));
+        map.insert(String::from("../codes/RSBE01.gct"), vec!(4));
+        let files = MemoryFiles::new(map);
+
+        // the key that sorts last is used
+        assert_eq!(files.get("FIGHTER/MARIO/FITMARIO.PAC").unwrap().data, vec!(2));
+        assert_eq!(files.get("fighter/mario/FitMario.pac").unwrap().name(), "fitmario.pac");
+        assert!(files.get("fighter/mario").is_none());
+
+        let fighter: Vec<&str> = files.files_in("Fighter").map(|(path, _)| path).collect();
+        assert_eq!(fighter, vec!("luigi/FitLuigi.pac", "mario/fitmario.pac"));
+        assert_eq!(files.files_in("fight").count(), 0);
+        assert_eq!(files.codeset().unwrap().data, vec!(4));
+    }
+}
@@ -0,0 +1,431 @@
+//! Modifying the animations in a motion pac e.g. FitMarioMotionEtc.pac
+//!
+//! The motion pac is not modified, the modified file is returned instead.
+
+use std::fmt;
+
+use fancy_slice::FancySlice;
+
+use crate::arc::{Arc, ArcChildData};
+use crate::chr0::Chr0;
+use crate::resources;
+use crate::util;
+
+/// Errors from replacing an animation
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReplaceAnimationError {
+    /// No BRES in the arc or its nested arcs contains a CHR0 with this name
+    AnimationNotFound (String),
+    /// The bone has a linear track without a value for every frame of the animation
+    MismatchedLinearTrack (String),
+}
+
+impl fmt::Display for ReplaceAnimationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplaceAnimationError::AnimationNotFound (name) =>
+                write!(f, "The motion pac does not contain an animation named {}", name),
+            ReplaceAnimationError::MismatchedLinearTrack (bone) =>
+                write!(f, "The linear track of bone {} does not have a value for every frame of the animation", bone),
+        }
+    }
+}
+
+impl std::error::Error for ReplaceAnimationError { }
+
+/// Returns `motion_file` with the keyframes of the CHR0 animation `name` replaced with `animation`.
+/// `motion_pac` is the arc parsed from `motion_file`.
+/// If multiple BRES contain an animation with that name only the first is replaced.
+///
+/// The animation is encoded with `Chr0::compile`, so its tracks may be stored in a different format than the original.
+/// When the new animation fits in the space of the old one it is written in place, otherwise it is appended to the end of the BRES and the old space is left unused.
+pub fn replace_animation(motion_pac: &Arc, motion_file: &[u8], name: &str, animation: &Chr0) -> Result<Vec<u8>, ReplaceAnimationError> {
+    if let Some(bone) = animation.mismatched_linear_track() {
+        return Err(ReplaceAnimationError::MismatchedLinearTrack (bone.to_string()));
+    }
+
+    let mut replaced = false;
+    let output = motion_pac.compile_replacing(Some(motion_file), &mut |child, raw_data| match child.data {
+        ArcChildData::Bres (_) if !replaced => {
+            let bres = replace_in_bres(raw_data, name, animation);
+            replaced = bres.is_some();
+            Some(bres.unwrap_or_else(|| raw_data.to_vec()))
+        }
+        // everything else is copied as is, so only the replaced animation changes
+        _ => Some(raw_data.to_vec()),
+    });
+
+    if replaced {
+        Ok(output)
+    } else {
+        Err(ReplaceAnimationError::AnimationNotFound (name.to_string()))
+    }
+}
+
+const BRES_SIZE_OFFSET: usize = 0x8;
+const BRES_ROOT_OFFSET: usize = 0xc;
+const ROOT_HEADER_SIZE: usize = 0x8;
+
+/// Returns the BRES with the animation replaced, or None if the BRES does not contain the animation
+fn replace_in_bres(raw_data: &[u8], name: &str, animation: &Chr0) -> Option<Vec<u8>> {
+    let data = FancySlice::new(raw_data);
+    let root_group = data.u16_be(BRES_ROOT_OFFSET) as usize + ROOT_HEADER_SIZE;
+    let entry = find_chr0(data, root_group, name)?;
+
+    let old_size = data.u32_be(entry.chr0_offset + 4) as usize;
+    let mut output = raw_data.to_vec();
+    let chr0 = animation.compile(-(entry.chr0_offset as i32));
+    if chr0.len() <= old_size {
+        output[entry.chr0_offset .. entry.chr0_offset + chr0.len()].copy_from_slice(&chr0);
+        for byte in &mut output[entry.chr0_offset + chr0.len() .. entry.chr0_offset + old_size] {
+            *byte = 0;
+        }
+    } else {
+        let bres_size = data.u32_be(BRES_SIZE_OFFSET) as usize;
+        output.resize(bres_size, 0x00);
+        output.resize(output.len() + (0x20 - output.len() % 0x20) % 0x20, 0x00);
+
+        let chr0_offset = output.len();
+        output.extend(animation.compile(-(chr0_offset as i32)));
+
+        let data_offset = (chr0_offset - entry.group_offset) as i32;
+        output[entry.entry_offset + 0xc .. entry.entry_offset + 0x10].copy_from_slice(&data_offset.to_be_bytes());
+        let bres_size = output.len() as u32;
+        output[BRES_SIZE_OFFSET .. BRES_SIZE_OFFSET + 4].copy_from_slice(&bres_size.to_be_bytes());
+    }
+    Some(output)
+}
+
+/// The location of a CHR0 and the resource entry pointing at it, all offsets are from the start of the BRES
+struct Chr0Entry {
+    group_offset: usize,
+    entry_offset: usize,
+    chr0_offset:  usize,
+}
+
+/// Searches the resource group at `group_offset` and the groups it contains for a CHR0 named `name`
+fn find_chr0(data: FancySlice, group_offset: usize, name: &str) -> Option<Chr0Entry> {
+    let num_children = data.i32_be(group_offset + 4);
+    for i in 1..=num_children.max(0) as usize { // the first child is a dummy so we skip it.
+        let entry_offset = group_offset + resources::RESOURCE_HEADER_SIZE + resources::RESOURCE_SIZE * i;
+        let string_offset = data.i32_be(entry_offset + 0x8);
+        let target_offset = (group_offset as i32 + data.i32_be(entry_offset + 0xc)) as usize;
+
+        match util::parse_tag(data.relative_slice(target_offset ..)).as_ref() {
+            "CHR0" if data.str(group_offset + string_offset as usize).ok() == Some(name) =>
+                return Some(Chr0Entry { group_offset, entry_offset, chr0_offset: target_offset }),
+            // a nested resource group, see `bres::bres_group`
+            "" => {
+                if let Some(entry) = find_chr0(data, target_offset, name) {
+                    return Some(entry);
+                }
+            }
+            _ => { }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arc;
+    use crate::chr0::{Chr0Child, Interpolated12Entry, Interpolated12Header, Keyframe, KeyframeHolder, Linear2Header};
+    use crate::fighter::Fighter;
+    use crate::test_util::arc_blob;
+    use crate::wii_memory::WiiMemory;
+
+    /// Builds a BRES containing an AnimationData(NW4R) group of `animations`, like the BRES in a motion pac
+    fn bres_blob(animations: &[Chr0]) -> Vec<u8> {
+        let group_name = "AnimationData(NW4R)";
+        let root_group = 0x10 + ROOT_HEADER_SIZE;
+        let root_group_size = resources::RESOURCE_HEADER_SIZE + resources::RESOURCE_SIZE * 2;
+        let animation_group = root_group + root_group_size;
+        let animation_group_size = resources::RESOURCE_HEADER_SIZE + resources::RESOURCE_SIZE * (animations.len() + 1);
+
+        let mut strings = vec!();
+        let mut string_offsets = vec!();
+        let strings_offset = animation_group + animation_group_size;
+        for name in std::iter::once(group_name).chain(animations.iter().map(|x| x.name.as_str())) {
+            strings.extend(&(name.len() as u32).to_be_bytes());
+            string_offsets.push(strings_offset + strings.len());
+            strings.extend(name.as_bytes());
+            strings.push(0);
+            while strings.len() % 4 != 0 {
+                strings.push(0);
+            }
+        }
+
+        let mut chr0_offsets = vec!();
+        let mut chr0_data = vec!();
+        let strings_end = strings_offset + strings.len();
+        let chr0s_offset = strings_end + (0x20 - strings_end % 0x20) % 0x20;
+        for animation in animations {
+            let offset = chr0s_offset + chr0_data.len();
+            chr0_offsets.push(offset);
+            chr0_data.extend(animation.compile(-(offset as i32)));
+            while chr0_data.len() % 0x20 != 0 {
+                chr0_data.push(0);
+            }
+        }
+
+        let mut data = vec!();
+        data.extend(b"bres");
+        data.extend(&0xFEFFu16.to_be_bytes());
+        data.extend(&0u16.to_be_bytes()); // version
+        data.extend(&((chr0s_offset + chr0_data.len()) as u32).to_be_bytes());
+        data.extend(&0x10u16.to_be_bytes());
+        data.extend(&((animations.len() + 1) as u16).to_be_bytes());
+        data.extend(b"root");
+        data.extend(&((chr0s_offset - 0x10) as i32).to_be_bytes());
+
+        let group = |data: &mut Vec<u8>, group_offset: usize, entries: &[(&str, usize, usize)]| {
+            let names: Vec<&str> = entries.iter().map(|x| x.0).collect();
+            let tree = resources::resource_tree(&names);
+            data.extend(&((resources::RESOURCE_HEADER_SIZE + resources::RESOURCE_SIZE * (entries.len() + 1)) as i32).to_be_bytes());
+            data.extend(&(entries.len() as i32).to_be_bytes());
+            for (i, tree_entry) in tree.iter().enumerate() {
+                data.extend(&tree_entry.id.to_be_bytes());
+                data.extend(&0u16.to_be_bytes());
+                data.extend(&tree_entry.left_index.to_be_bytes());
+                data.extend(&tree_entry.right_index.to_be_bytes());
+                let (string_offset, data_offset) = if i == 0 {
+                    (0, 0)
+                } else {
+                    let (_, string, target) = entries[i - 1];
+                    ((string - group_offset) as i32, (target - group_offset) as i32)
+                };
+                data.extend(&string_offset.to_be_bytes());
+                data.extend(&data_offset.to_be_bytes());
+            }
+        };
+        group(&mut data, root_group, &[(group_name, string_offsets[0], animation_group)]);
+        let entries: Vec<_> = animations.iter().enumerate().map(|(i, x)| (x.name.as_str(), string_offsets[i + 1], chr0_offsets[i])).collect();
+        group(&mut data, animation_group, &entries);
+
+        data.extend(strings);
+        data.resize(chr0s_offset, 0);
+        data.extend(chr0_data);
+        data
+    }
+
+    fn interpolated(keys: &[(f32, f32)]) -> Keyframe {
+        Keyframe::Interpolated12 (Interpolated12Header {
+            num_frames:  keys.len() as u16,
+            unk:         0,
+            frame_scale: 0.05,
+            children:    keys.iter().map(|(frame_index, value)| Interpolated12Entry { frame_index: *frame_index, value: *value, tangent: 0.0 }).collect(),
+        })
+    }
+
+    fn wait() -> Chr0 {
+        Chr0::new("Wait1".into(), 21, true, vec!(
+            Chr0Child::new("TransN".into(),
+                KeyframeHolder::None,
+                KeyframeHolder::None,
+                KeyframeHolder::Individual {
+                    x: Keyframe::Fixed (0.0),
+                    y: interpolated(&[(0.0, 0.0), (10.0, 2.0), (20.0, 0.0)]),
+                    z: Keyframe::Fixed (1.5),
+                },
+            ),
+            Chr0Child::new("HipN".into(),
+                KeyframeHolder::Isotropic (Keyframe::Fixed (1.0)),
+                KeyframeHolder::Individual {
+                    x: Keyframe::Linear2 (Linear2Header { step: 0.5, base: -5.0, children_steps: (0..21).collect() }),
+                    y: Keyframe::Fixed (0.0),
+                    z: Keyframe::Linear4 ((0..21).map(|x| x as f32 * 2.0).collect()),
+                },
+                KeyframeHolder::None,
+            ),
+        ))
+    }
+
+    fn attack() -> Chr0 {
+        Chr0::new("AttackS4S".into(), 40, false, vec!(
+            Chr0Child::new("TransN".into(), KeyframeHolder::None, KeyframeHolder::None, KeyframeHolder::Isotropic (interpolated(&[(0.0, 0.0), (39.0, 4.0)]))),
+        ))
+    }
+
+    fn motion_file() -> Vec<u8> {
+        arc_blob("FitTestMotionEtc", &[(-1, &bres_blob(&[wait(), attack()])), (-1, b"EFLS")])
+    }
+
+    fn animation<'a>(motion: &'a Arc, name: &str) -> &'a Chr0 {
+        Fighter::get_animations_fit_motion(motion).into_iter().find(|x| x.name == name).unwrap()
+    }
+
+    fn parse(motion_file: &[u8]) -> Arc {
+        arc::arc(FancySlice::new(motion_file), &WiiMemory::new(), false)
+    }
+
+    /// Asserts the animations have the same value for every track on every frame, the keyframes may be stored in different formats
+    fn assert_same(actual: &Chr0, expected: &Chr0) {
+        assert_eq!(actual.name, expected.name);
+        assert_eq!(actual.num_frames, expected.num_frames);
+        assert_eq!(actual.loop_value, expected.loop_value);
+        assert_eq!(actual.children.len(), expected.children.len());
+        let num_frames = expected.num_frames as i32;
+        for (actual, expected) in actual.children.iter().zip(expected.children.iter()) {
+            assert_eq!(actual.name, expected.name);
+            let tracks = [(&actual.scale, &expected.scale), (&actual.rot, &expected.rot), (&actual.translation, &expected.translation)];
+            for (actual_track, expected_track) in tracks.iter() {
+                assert_eq!(actual_track.keyframes().len(), expected_track.keyframes().len(), "{}", actual.name);
+                for frame in 0..num_frames {
+                    let difference = actual_track.get_value(false, frame, 0.0) - expected_track.get_value(false, frame, 0.0);
+                    assert!(difference.x.abs() < 0.001 && difference.y.abs() < 0.001 && difference.z.abs() < 0.001,
+                        "{} differs by {:?} on frame {}", actual.name, difference, frame);
+                }
+            }
+        }
+    }
+
+    /// The frames of the keys of an interpolated keyframe
+    fn key_frames(keyframe: &Keyframe) -> Vec<f32> {
+        match keyframe {
+            Keyframe::Interpolated4 (header)  => header.children.iter().map(|x| x.frame_index() as f32).collect(),
+            Keyframe::Interpolated6 (header)  => header.children.iter().map(|x| x.frame_index() as f32).collect(),
+            Keyframe::Interpolated12 (header) => header.children.iter().map(|x| x.frame_index).collect(),
+            keyframe => panic!("Expected an interpolated keyframe, was {:?}", keyframe),
+        }
+    }
+
+    #[test]
+    fn identity_round_trip() {
+        let file = motion_file();
+        let motion = parse(&file);
+        let original = animation(&motion, "Wait1").clone();
+        let attack = animation(&motion, "AttackS4S").clone();
+
+        let output = replace_animation(&motion, &file, "Wait1", &original).unwrap();
+        let rebuilt = parse(&output);
+        // the re-encoded animation fits in place
+        assert_eq!(rebuilt.children[0].raw_size(), motion.children[0].raw_size());
+
+        assert_same(animation(&rebuilt, "Wait1"), &original);
+        assert_same(animation(&rebuilt, "AttackS4S"), &attack);
+        assert_eq!(rebuilt.children[1].raw_data(&output), b"EFLS");
+    }
+
+    #[test]
+    fn stretch_to_double_frames() {
+        let file = motion_file();
+        let motion = parse(&file);
+        let attack = animation(&motion, "AttackS4S").clone();
+        let mut stretched = wait();
+        stretched.num_frames = 41;
+        for child in &mut stretched.children {
+            for holder in [&mut child.scale, &mut child.rot, &mut child.translation].iter_mut() {
+                if let KeyframeHolder::Individual { x, y, z } = holder {
+                    for keyframe in [x, y, z].iter_mut() {
+                        match keyframe {
+                            Keyframe::Interpolated12 (header) => {
+                                for entry in &mut header.children {
+                                    entry.frame_index *= 2.0;
+                                }
+                            }
+                            Keyframe::Linear2 (header) => {
+                                header.children_steps = (0..41).map(|x| x / 2).collect();
+                            }
+                            Keyframe::Linear4 (values) => {
+                                *values = (0..41).map(|x| (x / 2) as f32 * 2.0).collect();
+                            }
+                            _ => { }
+                        }
+                    }
+                }
+            }
+        }
+
+        let output = replace_animation(&motion, &file, "Wait1", &stretched).unwrap();
+        let rebuilt = parse(&output);
+        // the longer linear tracks no longer fit, so the animation is moved to the end
+        assert!(rebuilt.children[0].raw_size() > motion.children[0].raw_size());
+
+        let wait = animation(&rebuilt, "Wait1");
+        assert_same(wait, &stretched);
+        match &wait.children[0].translation {
+            KeyframeHolder::Individual { y, .. } => assert_eq!(key_frames(y), vec!(0.0, 20.0, 40.0)),
+            _ => panic!("Expected individual translation"),
+        }
+        let translations = wait.bone_translations("TransN").unwrap();
+        assert_eq!(translations.len(), 41);
+        assert!((translations[20].y - 2.0).abs() < 0.001);
+        assert_eq!(translations[40].z, 1.5);
+
+        // the other animation in the BRES is untouched
+        assert_same(animation(&rebuilt, "AttackS4S"), &attack);
+    }
+
+    #[test]
+    fn mixed_formats_are_converted() {
+        let file = motion_file();
+        let motion = parse(&file);
+        let mut mixed = animation(&motion, "AttackS4S").clone();
+        mixed.children[0].translation = KeyframeHolder::Individual {
+            x: Keyframe::Linear4 ((0..40).map(|x| x as f32).collect()),
+            y: interpolated(&[(0.0, 1.0), (39.0, 3.0)]),
+            z: Keyframe::Fixed (0.0),
+        };
+        let output = replace_animation(&motion, &file, "AttackS4S", &mixed).unwrap();
+        let rebuilt = parse(&output);
+        let attack = animation(&rebuilt, "AttackS4S");
+        match &attack.children[0].translation {
+            KeyframeHolder::Individual { x, y: _, z: Keyframe::Fixed (_) } => assert_eq!(key_frames(x).len(), 40),
+            other => panic!("Expected the linear translation to be converted to an interpolated format, was {:?}", other),
+        }
+        assert_same(attack, &mixed);
+    }
+
+    #[test]
+    fn formats_selected_per_track() {
+        let file = motion_file();
+        let motion = parse(&file);
+        let mut tracks = animation(&motion, "AttackS4S").clone();
+        tracks.children = vec!(
+            Chr0Child::new("TransN".into(),
+                // a key between frames needs Interpolated12
+                KeyframeHolder::Isotropic (interpolated(&[(0.0, 1.0), (10.5, 2.0), (39.0, 1.0)])),
+                // linear rotation that fits in 8 bit steps
+                KeyframeHolder::Individual {
+                    x: Keyframe::Linear4 ((0..40).map(|x| (x % 4) as f32 * 85.0).collect()),
+                    y: Keyframe::Fixed (0.0),
+                    z: Keyframe::Linear4 (vec!(0.0; 40)),
+                },
+                // fits in the 12 bit steps of Interpolated4, the x value never changes
+                KeyframeHolder::Individual {
+                    x: interpolated(&[(0.0, 1.5), (39.0, 1.5)]),
+                    y: interpolated(&[(0.0, 0.0), (20.0, 4.0), (39.0, 0.0)]),
+                    z: interpolated(&[(0.0, 0.0), (39.0, 3.0)]),
+                },
+            ),
+            Chr0Child::new("HipN".into(),
+                KeyframeHolder::None,
+                KeyframeHolder::None,
+                // 1/16 of the range is not a multiple of a 12 bit step, but is within the tolerance of a 16 bit step
+                KeyframeHolder::Isotropic (interpolated(&[(0.0, 0.0), (20.0, 1.0), (39.0, 16.0)])),
+            ),
+        );
+        let output = replace_animation(&motion, &file, "AttackS4S", &tracks).unwrap();
+        let rebuilt = parse(&output);
+        let attack = animation(&rebuilt, "AttackS4S");
+        assert_same(attack, &tracks);
+
+        let trans = &attack.children[0];
+        assert!(matches!(trans.scale, KeyframeHolder::Isotropic (Keyframe::Interpolated12 (_))));
+        assert!(matches!(trans.rot, KeyframeHolder::Individual { x: Keyframe::Linear1 (_), y: Keyframe::Fixed (_), z: Keyframe::Fixed (_) }));
+        assert!(matches!(trans.translation, KeyframeHolder::Individual { x: Keyframe::Fixed (_), y: Keyframe::Interpolated4 (_), z: Keyframe::Interpolated4 (_) }));
+        assert!(matches!(attack.children[1].translation, KeyframeHolder::Isotropic (Keyframe::Interpolated6 (_))));
+    }
+
+    #[test]
+    fn errors() {
+        let file = motion_file();
+        let motion = parse(&file);
+        assert_eq!(replace_animation(&motion, &file, "Run", &wait()), Err(ReplaceAnimationError::AnimationNotFound ("Run".into())));
+
+        let mut short = wait();
+        short.num_frames = 30;
+        assert_eq!(replace_animation(&motion, &file, "Wait1", &short), Err(ReplaceAnimationError::MismatchedLinearTrack ("HipN".into())));
+    }
+}
@@ -0,0 +1,159 @@
+//! Velocity changes and air/ground transitions imposed by the subaction scripts.
+//!
+//! Mods retune recoveries and other movement almost entirely through these events, so they are listed per frame in `HighLevelFrame::movement_effects`.
+//! `HighLevelSubaction::movement_effects` summarizes a whole subaction and `movement_changes` compares the summaries of two versions of a subaction.
+
+use std::fmt;
+
+use crate::script_ast::DisableMovement;
+
+/// A change to the fighters movement made by a script event.
+/// Velocities are in units per frame, the x axis points in the direction the fighter is facing.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum MovementEffect {
+    /// Set the horizontal velocity
+    SetXVelocity (f32),
+    /// Add to the horizontal velocity
+    AddXVelocity (f32),
+    /// Set the vertical velocity
+    SetYVelocity (f32),
+    /// Add to the vertical velocity
+    AddYVelocity (f32),
+    /// Set the vertical velocity and acceleration to 0
+    ResetVerticalVelocity,
+    /// Disable or enable a type of movement
+    DisableMovement (DisableMovement),
+    /// Return to normal physics
+    NormalizePhysics,
+    /// Move the fighter into the air or onto the ground
+    SetAirGround { airbourne: bool },
+}
+
+impl MovementEffect {
+    /// Returns the name of the kind of effect, effects of the same kind are compared by `movement_changes`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MovementEffect::SetXVelocity (_)        => "set x momentum",
+            MovementEffect::AddXVelocity (_)        => "add x momentum",
+            MovementEffect::SetYVelocity (_)        => "set y momentum",
+            MovementEffect::AddYVelocity (_)        => "add y momentum",
+            MovementEffect::ResetVerticalVelocity   => "reset y momentum",
+            MovementEffect::DisableMovement (_)     => "disable movement",
+            MovementEffect::NormalizePhysics        => "normalize physics",
+            MovementEffect::SetAirGround { .. }     => "set air/ground",
+        }
+    }
+
+    fn value(&self) -> String {
+        match self {
+            MovementEffect::SetXVelocity (value) |
+            MovementEffect::AddXVelocity (value) |
+            MovementEffect::SetYVelocity (value) |
+            MovementEffect::AddYVelocity (value)   => value.to_string(),
+            MovementEffect::DisableMovement (value) => format!("{:?}", value),
+            MovementEffect::SetAirGround { airbourne: true }  => String::from("air"),
+            MovementEffect::SetAirGround { airbourne: false } => String::from("ground"),
+            MovementEffect::ResetVerticalVelocity |
+            MovementEffect::NormalizePhysics       => String::new(),
+        }
+    }
+}
+
+impl fmt::Display for MovementEffect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = self.value();
+        if value.is_empty() {
+            write!(f, "{}", self.kind())
+        } else {
+            write!(f, "{} {}", self.kind(), value)
+        }
+    }
+}
+
+/// A difference between two movement summaries, see `movement_changes`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum MovementChange {
+    Added   { frame: usize, effect: MovementEffect },
+    Removed { frame: usize, effect: MovementEffect },
+    Changed { old_frame: usize, old: MovementEffect, new_frame: usize, new: MovementEffect },
+}
+
+impl fmt::Display for MovementChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MovementChange::Added { frame, effect } => write!(f, "added {} at frame {}", effect, frame),
+            MovementChange::Removed { frame, effect } => write!(f, "removed {} at frame {}", effect, frame),
+            MovementChange::Changed { old_frame, old, new_frame, new } => {
+                if old == new {
+                    write!(f, "{} moved from frame {} → {}", old, old_frame, new_frame)
+                } else if old_frame == new_frame {
+                    write!(f, "{} {} → {} at frame {}", old.kind(), old.value(), new.value(), new_frame)
+                } else {
+                    write!(f, "{} {} → {} at frame {} → {}", old.kind(), old.value(), new.value(), old_frame, new_frame)
+                }
+            }
+        }
+    }
+}
+
+/// Compares two summaries returned by `HighLevelSubaction::movement_effects`.
+/// The nth effect of each kind in `old` is paired with the nth effect of the same kind in `new`, unpaired effects are added or removed.
+/// Changes are ordered by frame.
+pub fn movement_changes(old: &[(usize, MovementEffect)], new: &[(usize, MovementEffect)]) -> Vec<MovementChange> {
+    let mut changes = vec!();
+    let mut new_used = vec!(false; new.len());
+    for (old_frame, old_effect) in old {
+        let paired = new.iter().enumerate().find(|(i, (_, new_effect))| !new_used[*i] && new_effect.kind() == old_effect.kind());
+        match paired {
+            Some((i, (new_frame, new_effect))) => {
+                new_used[i] = true;
+                if old_frame != new_frame || old_effect != new_effect {
+                    changes.push(MovementChange::Changed { old_frame: *old_frame, old: old_effect.clone(), new_frame: *new_frame, new: new_effect.clone() });
+                }
+            }
+            None => changes.push(MovementChange::Removed { frame: *old_frame, effect: old_effect.clone() }),
+        }
+    }
+    for ((frame, effect), used) in new.iter().zip(new_used) {
+        if !used {
+            changes.push(MovementChange::Added { frame: *frame, effect: effect.clone() });
+        }
+    }
+    changes.sort_by_key(|x| match x {
+        MovementChange::Added { frame, .. } |
+        MovementChange::Removed { frame, .. } |
+        MovementChange::Changed { new_frame: frame, .. } => *frame,
+    });
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes() {
+        let old = vec!(
+            (0, MovementEffect::SetAirGround { airbourne: true }),
+            (18, MovementEffect::SetXVelocity (2.7)),
+            (20, MovementEffect::SetYVelocity (1.0)),
+            (25, MovementEffect::ResetVerticalVelocity),
+        );
+        let new = vec!(
+            (0, MovementEffect::SetAirGround { airbourne: true }),
+            (18, MovementEffect::SetXVelocity (3.1)),
+            (22, MovementEffect::SetYVelocity (1.0)),
+            (30, MovementEffect::AddYVelocity (-0.5)),
+        );
+        let changes: Vec<String> = movement_changes(&old, &new).iter().map(|x| x.to_string()).collect();
+        assert_eq!(changes, vec!(
+            "set x momentum 2.7 → 3.1 at frame 18",
+            "set y momentum 1 moved from frame 20 → 22",
+            "removed reset y momentum at frame 25",
+            "added add y momentum -0.5 at frame 30",
+        ));
+        assert!(movement_changes(&old, &old).is_empty());
+    }
+}
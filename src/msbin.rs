@@ -0,0 +1,260 @@
+//! Parses the MSBIN string tables used for menu text e.g. character and trophy names.
+//!
+//! # Format
+//!
+//! *   4 bytes - big endian number of strings
+//! *   4 bytes per string + 4 bytes - big endian offsets from the start of the file, string `i` spans from offset `i` to offset `i + 1`
+//! *   rest    - UTF-16BE strings, each optionally terminated by a null character
+//!
+//! Strings can contain escape sequences for formatting e.g. text color.
+//! An escape sequence is the character 0x001A, followed by a character containing the number of parameter characters, followed by the parameters.
+
+use std::path::{Path, PathBuf};
+
+use failure::Error;
+use failure::bail;
+
+use fancy_slice::FancySlice;
+
+use crate::file_provider::FileProvider;
+use crate::provenance::PathFormatter;
+
+const ESCAPE: u16 = 0x001A;
+
+/// What to do with the escape sequences found in strings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextEscapes {
+    /// Keep the escape sequences in the string as is.
+    Preserve,
+    /// Remove the escape sequences, leaving just the displayed text.
+    Strip,
+}
+
+/// A parsed MSBIN file
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct MenuText {
+    /// Path of the file relative to the root of the brawl dump or the mods pf folder
+    pub path: PathBuf,
+    pub from_mod: bool,
+    /// (index, string) pairs
+    pub strings: Vec<(u32, String)>,
+}
+
+impl MenuText {
+    /// Returns the string at `index`
+    pub fn get(&self, index: u32) -> Option<&str> {
+        self.strings.iter().find(|x| x.0 == index).map(|x| x.1.as_str())
+    }
+
+    /// Returns the display name of the fighter slot `slot_id` e.g. 0x00 for Mario, see `fighter_maps::fighter_id`.
+    /// `self` must be a name table, where the string at each index is the name of the slot with that id.
+    /// Empty strings are treated as unnamed slots.
+    pub fn slot_name(&self, slot_id: u8) -> Option<&str> {
+        self.get(slot_id as u32).filter(|x| !x.is_empty())
+    }
+}
+
+pub fn msbin(data: FancySlice, escapes: TextEscapes) -> Result<Vec<(u32, String)>, Error> {
+    if data.len() < 4 {
+        bail!("Not an msbin file: File size is less than 4 bytes");
+    }
+    let count = data.u32_be(0) as usize;
+    let offsets_end = 4 + (count + 1) * 4;
+    if offsets_end > data.len() {
+        bail!("Not an msbin file: {} strings do not fit in {} bytes", count, data.len());
+    }
+
+    let mut strings = vec!();
+    for i in 0..count {
+        let start = data.u32_be(4 + i * 4) as usize;
+        let end = data.u32_be(8 + i * 4) as usize;
+        if start > end || end > data.len() {
+            bail!("String {} has an invalid range 0x{:x}..0x{:x}", i, start, end);
+        }
+
+        let mut characters = vec!();
+        for j in 0..(end - start) / 2 {
+            characters.push(data.u16_be(start + j * 2));
+        }
+        strings.push((i as u32, decode(&characters, escapes)?));
+    }
+    Ok(strings)
+}
+
+fn decode(characters: &[u16], escapes: TextEscapes) -> Result<String, Error> {
+    let mut result = vec!();
+    let mut i = 0;
+    while i < characters.len() {
+        let character = characters[i];
+        if character == 0 {
+            break;
+        }
+        else if character == ESCAPE {
+            let parameters = characters.get(i + 1).cloned().unwrap_or(0) as usize;
+            let sequence_end = (i + 2 + parameters).min(characters.len());
+            if escapes == TextEscapes::Preserve {
+                result.extend_from_slice(&characters[i..sequence_end]);
+            }
+            i = sequence_end;
+        }
+        else {
+            result.push(character);
+            i += 1;
+        }
+    }
+
+    match String::from_utf16(&result) {
+        Ok(string) => Ok(string),
+        Err(err) => bail!("Invalid UTF-16 string: {}", err),
+    }
+}
+
+/// Parses every .msbin file in the menu related folders of `brawl_files`.
+/// Files in `mod_files` replace the brawl file with the same relative path.
+pub(crate) fn load_menu_text(brawl_files: &dyn FileProvider, mod_files: Option<&dyn FileProvider>, escapes: TextEscapes, paths: &PathFormatter) -> Result<Vec<MenuText>, Error> {
+    // the relative path, where the file is read from and whether it is from the mod
+    let mut files: Vec<(String, &dyn FileProvider, bool)> = vec!();
+    for (provider, from_mod) in [(Some(brawl_files), false), (mod_files, true)] {
+        if let Some(provider) = provider {
+            for dir in MENU_DIRS {
+                let found = match provider.files_in(dir) {
+                    Ok(found) => found,
+                    Err(err) => bail!("Cannot read directory {}: {}", paths.display(&provider.source_path(dir)), err),
+                };
+                for relative in found.iter().filter(|x| is_msbin(Path::new(x))) {
+                    replace_file(&mut files, (format!("{}/{}", dir, relative), provider, from_mod));
+                }
+            }
+        }
+    }
+    files.sort_by(|a, b| Path::new(&a.0).cmp(Path::new(&b.0)));
+
+    let mut menu_texts = vec!();
+    for (relative, provider, from_mod) in files {
+        let path = provider.source_path(&relative);
+        let data = match provider.read(&relative) {
+            Ok(data) => data,
+            Err(err) => bail!("Cannot read {}: {}", paths.display(&path), err),
+        };
+        match msbin(FancySlice::new(&data), escapes) {
+            Ok(strings) => menu_texts.push(MenuText { path: PathBuf::from(relative), from_mod, strings }),
+            Err(err) => error!("Failed to parse {}: {}", paths.display(&path), err),
+        }
+    }
+    Ok(menu_texts)
+}
+
+/// Adds the file, replacing any file at the same relative path
+pub(crate) fn replace_file<'a>(files: &mut Vec<(String, &'a dyn FileProvider, bool)>, file: (String, &'a dyn FileProvider, bool)) {
+    let relative_lower = file.0.to_lowercase();
+    files.retain(|x| x.0.to_lowercase() != relative_lower);
+    files.push(file);
+}
+
+fn is_msbin(path: &Path) -> bool {
+    path.extension().map(|x| x.to_string_lossy().to_lowercase() == "msbin").unwrap_or(false)
+}
+
+const MENU_DIRS: &[&str] = &["system", "menu", "menu2", "toy"];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_provider::DirFiles;
+    use std::fs;
+
+    fn msbin_blob(strings: &[&[u16]]) -> Vec<u8> {
+        let mut data = vec!();
+        data.extend(&(strings.len() as u32).to_be_bytes());
+        let mut offset = 4 + (strings.len() as u32 + 1) * 4;
+        for string in strings {
+            data.extend(&offset.to_be_bytes());
+            offset += string.len() as u32 * 2;
+        }
+        data.extend(&offset.to_be_bytes());
+        for string in strings {
+            for character in string.iter() {
+                data.extend(&character.to_be_bytes());
+            }
+        }
+        data
+    }
+
+    fn utf16(string: &str) -> Vec<u16> {
+        string.encode_utf16().collect()
+    }
+
+    #[test]
+    fn parse_msbin() {
+        let mario = utf16("Mario\0");
+        let mut colored = utf16("Dr. ");
+        colored.extend(&[ESCAPE, 2, 0x0003, 0x00FF]);
+        colored.extend(utf16("Mario"));
+        let data = msbin_blob(&[&mario, &colored]);
+
+        let strings = msbin(FancySlice::new(&data), TextEscapes::Strip).unwrap();
+        assert_eq!(strings, vec!((0, String::from("Mario")), (1, String::from("Dr. Mario"))));
+
+        let strings = msbin(FancySlice::new(&data), TextEscapes::Preserve).unwrap();
+        assert_eq!(strings[1].1, "Dr. \u{1A}\u{2}\u{3}\u{FF}Mario");
+    }
+
+    #[test]
+    fn slot_names() {
+        let mario = utf16("Mario");
+        let donkey = utf16("Donkey Kong\0");
+        let data = msbin_blob(&[&mario, &donkey, &[]]);
+        let names = MenuText {
+            path: PathBuf::from("menu/names.msbin"),
+            from_mod: false,
+            strings: msbin(FancySlice::new(&data), TextEscapes::Strip).unwrap(),
+        };
+
+        assert_eq!(names.slot_name(crate::fighter_maps::fighter_id("Mario")), Some("Mario"));
+        assert_eq!(names.slot_name(crate::fighter_maps::fighter_id("Donkey")), Some("Donkey Kong"));
+        assert_eq!(names.slot_name(2), None);
+        assert_eq!(names.slot_name(0x2A), None);
+    }
+
+    #[test]
+    fn invalid_msbin() {
+        assert!(msbin(FancySlice::new(&[0, 0]), TextEscapes::Strip).is_err());
+        assert!(msbin(FancySlice::new(&[0, 0, 0, 5, 0, 0, 0, 0]), TextEscapes::Strip).is_err());
+    }
+
+    #[test]
+    fn mod_overrides_brawl() {
+        let brawl = tempfile::tempdir().unwrap();
+        let modded = tempfile::tempdir().unwrap();
+        let name = utf16("Mario");
+        let mod_name = utf16("Dr. Mario");
+        let other = utf16("Luigi");
+        fs::create_dir_all(brawl.path().join("toy/fig")).unwrap();
+        fs::create_dir_all(modded.path().join("toy/fig")).unwrap();
+        fs::write(brawl.path().join("toy/fig/ty_fig_name_list.msbin"), msbin_blob(&[&name])).unwrap();
+        fs::write(brawl.path().join("toy/fig/other.msbin"), msbin_blob(&[&other])).unwrap();
+        fs::write(modded.path().join("toy/fig/ty_fig_name_list.msbin"), msbin_blob(&[&mod_name])).unwrap();
+
+        let menu_texts = load_menu_text(&DirFiles::new(brawl.path()), Some(&DirFiles::new(modded.path())), TextEscapes::Strip, &PathFormatter::default()).unwrap();
+        assert_eq!(menu_texts.len(), 2);
+        assert_eq!(menu_texts[0].path, Path::new("toy/fig/other.msbin"));
+        assert!(!menu_texts[0].from_mod);
+        assert_eq!(menu_texts[1].get(0), Some("Dr. Mario"));
+        assert!(menu_texts[1].from_mod);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_file_redacted() {
+        let brawl = tempfile::tempdir().unwrap();
+        let modded = tempfile::tempdir().unwrap();
+        fs::create_dir_all(modded.path().join("toy/fig")).unwrap();
+        std::os::unix::fs::symlink(modded.path().join("missing"), modded.path().join("toy/fig/broken.msbin")).unwrap();
+
+        let paths = PathFormatter::new(true, brawl.path(), Some(modded.path()));
+        let message = load_menu_text(&DirFiles::new(brawl.path()), Some(&DirFiles::new(modded.path())), TextEscapes::Strip, &paths).unwrap_err().to_string();
+        assert!(message.contains(&Path::new("<mod[0]>").join("toy/fig/broken.msbin").display().to_string()), "{}", message);
+        assert!(!message.contains(&modded.path().display().to_string()), "{}", message);
+    }
+}
@@ -46,6 +46,7 @@ pub(crate) fn plt0(data: FancySlice) -> Plt0 {
 
 const PLT0_HEADER_SIZE: usize = 0x40;
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Plt0 {
     pub name:         String,
     pub pixel_format: WiiPaletteFormat,
@@ -0,0 +1,163 @@
+//! Decodes the small subset of PowerPC used by ASM codes that only write constants to memory.
+//! This is nowhere near a full disassembler, anything outside the subset is reported as unsupported.
+
+use byteorder::{BigEndian, ByteOrder};
+
+/// A decoded instruction from the supported subset
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Instruction {
+    /// `li` when `a == 0`
+    Addi { d: usize, a: usize, simm: i16 },
+    /// `lis` when `a == 0`
+    Addis { d: usize, a: usize, simm: i16 },
+    /// `nop` when every field is 0
+    Ori { a: usize, s: usize, uimm: u16 },
+    Store { size: StoreSize, s: usize, a: usize, offset: i16 },
+    /// Any b, bc, bclr or bcctr instruction, `blr` is kept separate as it just returns from the code
+    Branch (u32),
+    Blr,
+    /// Used as a placeholder for the branch back at the end of C2 codes
+    Zero,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum StoreSize {
+    Byte,
+    Half,
+    Word,
+}
+
+impl Instruction {
+    pub fn decode(word: u32) -> Option<Instruction> {
+        let rd = (word >> 21) as usize & 0x1F;
+        let ra = (word >> 16) as usize & 0x1F;
+        let imm = word as u16;
+        let store = |size| Some(Instruction::Store { size, s: rd, a: ra, offset: imm as i16 });
+        match word >> 26 {
+            _ if word == 0 => Some(Instruction::Zero),
+            _ if word == 0x4E800020 => Some(Instruction::Blr),
+            14 => Some(Instruction::Addi { d: rd, a: ra, simm: imm as i16 }),
+            15 => Some(Instruction::Addis { d: rd, a: ra, simm: imm as i16 }),
+            24 => Some(Instruction::Ori { s: rd, a: ra, uimm: imm }),
+            36 => store(StoreSize::Word),
+            38 => store(StoreSize::Byte),
+            44 => store(StoreSize::Half),
+            16 | 18 => Some(Instruction::Branch (word)),
+            19 if matches!((word >> 1) & 0x3FF, 16 | 528) => Some(Instruction::Branch (word)),
+            _ => None,
+        }
+    }
+
+    pub fn disassemble(&self) -> String {
+        match *self {
+            Instruction::Addi  { d, a: 0, simm } => format!("li r{}, {}", d, simm),
+            Instruction::Addi  { d, a, simm }    => format!("addi r{}, r{}, {}", d, a, simm),
+            Instruction::Addis { d, a: 0, simm } => format!("lis r{}, 0x{:x}", d, simm as u16),
+            Instruction::Addis { d, a, simm }    => format!("addis r{}, r{}, 0x{:x}", d, a, simm as u16),
+            Instruction::Ori   { a: 0, s: 0, uimm: 0 } => String::from("nop"),
+            Instruction::Ori   { a, s, uimm }    => format!("ori r{}, r{}, 0x{:x}", a, s, uimm),
+            Instruction::Store { size, s, a, offset } => {
+                let name = match size {
+                    StoreSize::Byte => "stb",
+                    StoreSize::Half => "sth",
+                    StoreSize::Word => "stw",
+                };
+                format!("{} r{}, {}(r{})", name, s, offset, a)
+            }
+            Instruction::Branch (word) => match word >> 26 {
+                18 => {
+                    // sign extend the 24 bit word offset
+                    let target = ((word & 0x03FF_FFFC) << 6) as i32 >> 6;
+                    let link = if word & 1 != 0 { "l" } else { "" };
+                    let absolute = if word & 2 != 0 { "a" } else { "" };
+                    format!("b{}{} {}", link, absolute, target)
+                }
+                16 => format!("bc {}, {}, {}", (word >> 21) & 0x1F, (word >> 16) & 0x1F, (word & 0xFFFC) as i16),
+                _ if (word >> 1) & 0x3FF == 16 => format!("bclr {}, {}", (word >> 21) & 0x1F, (word >> 16) & 0x1F),
+                _ => format!("bcctr {}, {}", (word >> 21) & 0x1F, (word >> 16) & 0x1F),
+            }
+            Instruction::Blr => String::from("blr"),
+            Instruction::Zero => String::from(".long 0x0"),
+        }
+    }
+}
+
+/// Returns one line per instruction or None if any instruction is outside the supported subset.
+pub(crate) fn disassemble(data: &[u8]) -> Option<Vec<String>> {
+    data.chunks_exact(4)
+        .map(|word| Instruction::decode(BigEndian::read_u32(word)).map(|x| x.disassemble()))
+        .collect()
+}
+
+/// A memory write performed by interpreted instructions
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Write {
+    pub address: u32,
+    pub size: StoreSize,
+    pub value: u32,
+}
+
+/// Returns the writes performed by the instructions up to the first blr.
+///
+/// Returns None when the result cannot be known without emulating the game:
+/// an instruction is outside the subset, a branch is taken or a register is read before a constant is loaded into it.
+pub(crate) fn interpret(data: &[u8]) -> Option<Vec<Write>> {
+    let mut registers: [Option<u32>; 32] = [None; 32];
+    let mut writes = vec!();
+    // rA of 0 means the literal value 0 for addi, addis and the stores
+    let base = |registers: &[Option<u32>; 32], a: usize| if a == 0 { Some(0) } else { registers[a] };
+
+    for word in data.chunks_exact(4) {
+        match Instruction::decode(BigEndian::read_u32(word))? {
+            Instruction::Addi { d, a, simm } => {
+                registers[d] = Some(base(&registers, a)?.wrapping_add(simm as i32 as u32));
+            }
+            Instruction::Addis { d, a, simm } => {
+                registers[d] = Some(base(&registers, a)?.wrapping_add((simm as i32 as u32) << 16));
+            }
+            Instruction::Ori { a: 0, s: 0, uimm: 0 } => { }
+            Instruction::Ori { a, s, uimm } => {
+                registers[a] = Some(registers[s]? | uimm as u32);
+            }
+            Instruction::Store { size, s, a, offset } => {
+                let address = base(&registers, a)?.wrapping_add(offset as i32 as u32);
+                writes.push(Write { address, size, value: registers[s]? });
+            }
+            Instruction::Blr => return Some(writes),
+            Instruction::Branch (_) | Instruction::Zero => return None,
+        }
+    }
+    Some(writes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instructions(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|x| x.to_be_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn disassemble_subset() {
+        let data = instructions(&[0x3C6080F9, 0x38800005, 0x6084FFFF, 0x9083FC30, 0x48000008, 0x4E800020, 0x00000000]);
+        assert_eq!(disassemble(&data).unwrap(), vec!(
+            "lis r3, 0x80f9",
+            "li r4, 5",
+            "ori r4, r4, 0xffff",
+            "stw r4, -976(r3)",
+            "b 8",
+            "blr",
+            ".long 0x0",
+        ));
+
+        // mflr r0
+        assert_eq!(disassemble(&instructions(&[0x7C0802A6])), None);
+    }
+
+    #[test]
+    fn interpret_untracked_register() {
+        // stw r4, 0(r3) without loading r3
+        assert_eq!(interpret(&instructions(&[0x38800001, 0x90830000])), None);
+    }
+}
@@ -0,0 +1,113 @@
+//! The types most tools built on brawllib_rs need, for glob importing:
+//!
+//! ```rust
+//! use brawllib_rs::prelude::*;
+//! ```
+//!
+//! Everything re-exported here is part of the stable api, removing or renaming any of it is a breaking change.
+//! `arc::Arc` is not included as it would conflict with `std::sync::Arc`, import it from `brawllib_rs::arc` instead.
+
+pub use crate::address::RamAddress;
+pub use crate::brawl_mod::{BrawlMod, LoadConfig, CommonFighterCodes, PatchTarget, PatchFile, ModLayoutError};
+pub use crate::fighter::{Fighter, FighterLoadResult, FileRole, FileProvenance, LoadDetail};
+pub use crate::high_level_fighter::{HighLevelFighter, HighLevelAction, HighLevelSubaction, HighLevelHitBox};
+pub use crate::sakurai::fighter_data::{ArcFighterData, FighterAttributes, ActionFlags};
+pub use crate::script::{Script, Event, Argument, FixedPoint, Requirement, Variable};
+pub use crate::script_ast::{ScriptAst, EventAst, Expression, HitBoxArguments, SpecialHitBoxArguments};
+pub use crate::wii_memory::WiiMemory;
+pub use crate::wiird::{WiiRDBlock, WiiRDCode};
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    /// Lists every `pub` item and field reachable through `pub mod` declarations, one per line.
+    ///
+    /// This is a textual approximation of the public api, it is only used to make changes to the public api visible in review.
+    fn public_api() -> String {
+        let src = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut lines = vec!();
+        collect_module(&src.join("lib.rs"), "brawllib_rs", &mut lines);
+        lines.sort();
+        lines.dedup();
+        let mut api = lines.join("\n");
+        api.push('\n');
+        api
+    }
+
+    fn collect_module(path: &Path, module: &str, lines: &mut Vec<String>) {
+        let source = fs::read_to_string(path).unwrap();
+        let dir = if path.file_name().unwrap() == "lib.rs" || path.file_name().unwrap() == "mod.rs" {
+            path.parent().unwrap().to_path_buf()
+        } else {
+            path.with_extension("")
+        };
+
+        // the struct, enum or impl type that indented items belong to
+        let mut parent = String::new();
+        for raw_line in source.lines() {
+            let line = raw_line.trim();
+            if line.starts_with("#[cfg(test)]") {
+                break;
+            }
+            let indented = raw_line.starts_with(' ');
+            if !indented && !line.is_empty() {
+                parent = item_name(line).unwrap_or_default();
+            }
+            if !line.starts_with("pub ") {
+                continue;
+            }
+            let owner = if indented && !parent.is_empty() { format!("{}::{}", module, parent) } else { module.to_string() };
+            let declaration = line.trim_start_matches("pub ").trim_end_matches(['{', ' ']);
+            let declaration = declaration.trim_start_matches("unsafe ").trim_start_matches("extern \"C\" ").trim_start_matches("async ");
+            let declaration = declaration.strip_prefix("const ").filter(|x| x.starts_with("fn ")).unwrap_or(declaration);
+            let mut words = declaration.split(|c: char| c.is_whitespace() || c == '(' || c == '<' || c == ':' || c == ';');
+            let kind = words.next().unwrap_or("");
+            let name = words.find(|x| !x.is_empty()).unwrap_or("");
+            match kind {
+                "mod" if line.ends_with(';') => {
+                    let child = if dir.join(format!("{}.rs", name)).exists() {
+                        dir.join(format!("{}.rs", name))
+                    } else {
+                        dir.join(name).join("mod.rs")
+                    };
+                    lines.push(format!("{} mod {}", module, name));
+                    collect_module(&child, &format!("{}::{}", module, name), lines);
+                }
+                "fn" | "struct" | "enum" | "trait" | "type" | "const" | "static" | "mod" => lines.push(format!("{} {} {}", owner, kind, name)),
+                "use" => lines.push(format!("{} use {}", owner, declaration.trim_start_matches("use ").trim_end_matches(';'))),
+                _ => lines.push(format!("{} field {}", owner, declaration.trim_end_matches(','))),
+            }
+        }
+    }
+
+    /// The name of the type declared or implemented by a top level line
+    fn item_name(line: &str) -> Option<String> {
+        let line = line.trim_start_matches("pub ");
+        let rest = if line.starts_with("struct ") || line.starts_with("enum ") || line.starts_with("trait ") {
+            line.split_once(' ')?.1
+        } else if line.starts_with("impl") {
+            let rest = line.trim_start_matches("impl").trim_start();
+            let rest = if rest.starts_with('<') { rest.split_once("> ")?.1 } else { rest };
+            rest.rsplit(" for ").next()?
+        } else {
+            return None;
+        };
+        rest.split(|c: char| c.is_whitespace() || c == '<' || c == '(' || c == '{' || c == ';').next().map(|x| x.to_string())
+    }
+
+    /// Fails when the public api changed without updating public_api.txt.
+    /// Run with BRAWLLIB_UPDATE_PUBLIC_API=1 to update it, then check the diff for breaking changes.
+    #[test]
+    fn public_api_snapshot() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("public_api.txt");
+        let api = public_api();
+        if std::env::var("BRAWLLIB_UPDATE_PUBLIC_API").is_ok() {
+            fs::write(&path, &api).unwrap();
+            return;
+        }
+        let snapshot = fs::read_to_string(&path).unwrap_or_default();
+        assert!(snapshot == api, "The public api changed, run the tests with BRAWLLIB_UPDATE_PUBLIC_API=1 to update public_api.txt and review the diff for breaking changes");
+    }
+}
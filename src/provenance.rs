@@ -0,0 +1,264 @@
+//! Records which inputs a `Fighter` was parsed from, so published data can be traced back to the exact mod build and brawllib_rs version that produced it.
+//!
+//! Files are hashed with SHA-1 while they are read, the hash is of the file on disk before any WiiRD codes are applied to it.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sha1_smol::Sha1;
+
+use crate::brawl_mod::{BrawlMod, LoadConfig};
+use crate::fighter::{FileProvenance, CommonFighterProvenance};
+use crate::sakurai::MemorySection;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct Provenance {
+    /// The version of brawllib_rs that parsed the fighter
+    pub crate_version: String,
+    /// None when loaded via `Fighter::load` instead of `BrawlMod`
+    pub load_config: Option<LoadConfig>,
+    /// Seconds since the unix epoch when the fighter was parsed
+    pub timestamp: u64,
+    /// The fighters own files
+    pub files: Vec<FileProvenance>,
+    /// None when loaded via `Fighter::load` as it is given an already parsed Fighter.pac
+    pub common_fighter: Option<CommonFighterProvenance>,
+    /// The WiiRD codeset of the mod, None when loading vanilla brawl
+    pub codeset: Option<CodesetProvenance>,
+    /// Scripts of the fighters moveset that the codeset redirected to RAM, so they were not read from `files`
+    pub memory_sections: Vec<MemorySection>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct CodesetProvenance {
+    pub path: PathBuf,
+    /// Hex encoded SHA-1 of the entire file including the header
+    pub sha1: String,
+}
+
+impl Provenance {
+    pub(crate) fn new(files: Vec<FileProvenance>, memory_sections: Vec<MemorySection>) -> Provenance {
+        Provenance {
+            crate_version:  env!("CARGO_PKG_VERSION").to_string(),
+            load_config:    None,
+            timestamp:      SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0),
+            files,
+            common_fighter: None,
+            codeset:        None,
+            memory_sections,
+        }
+    }
+
+    /// Returns true if loading the fighter from `brawl_mod` now would use the same files with the same contents.
+    ///
+    /// Every recorded file is hashed again, and vanilla files are checked to not have been replaced by a file added to the mod since.
+    /// A file added to the mod for a role that was previously missing e.g. a new costume is not detected.
+    /// Redacted paths, see `LoadConfig::redact_paths`, are resolved against the paths of `brawl_mod`.
+    pub fn matches_files(&self, brawl_mod: &BrawlMod) -> bool {
+        let paths = brawl_mod.path_formatter(true);
+        let mod_fighter_path = match brawl_mod.mod_fighter_path() {
+            Ok(path) => path,
+            Err(_) => return false,
+        };
+        let replaced_by_mod = |path: &Path| -> bool {
+            let mod_fighter_path = match &mod_fighter_path {
+                Some(path) => path,
+                None => return false,
+            };
+            let file_name = path.file_name().map(|x| x.to_string_lossy().to_lowercase());
+            // Fighter.pac sits directly in the fighter folder, every other file is in a folder for the fighter
+            let dir = match path.parent().and_then(|x| x.file_name()) {
+                Some(dir) if dir.to_string_lossy().eq_ignore_ascii_case("fighter") => Some(mod_fighter_path.clone()),
+                Some(dir) => find_case_insensitive(mod_fighter_path, &dir.to_string_lossy()),
+                None => None,
+            };
+            match (dir, file_name) {
+                (Some(dir), Some(file_name)) => find_case_insensitive(&dir, &file_name).is_some(),
+                _ => false,
+            }
+        };
+
+        for file in &self.files {
+            let path = paths.resolve(&file.path);
+            if sha1_file(&path).ok().as_ref() != Some(&file.sha1) || (!file.from_mod && replaced_by_mod(&path)) {
+                return false;
+            }
+        }
+
+        if let Some(common_fighter) = &self.common_fighter {
+            let path = paths.resolve(&common_fighter.path);
+            if sha1_file(&path).ok().as_ref() != Some(&common_fighter.sha1) || (!common_fighter.from_mod && replaced_by_mod(&path)) {
+                return false;
+            }
+        }
+
+        match (&self.codeset, brawl_mod.wiird_codeset_path()) {
+            (Some(codeset), Ok(path)) => path == paths.resolve(&codeset.path) && sha1_file(&path).ok().as_ref() == Some(&codeset.sha1),
+            (None, Ok(_)) | (Some(_), Err(_)) => false,
+            (None, Err(_)) => true,
+        }
+    }
+}
+
+/// Formats the paths of files read from a brawl dump or mod, for error messages and provenance.
+///
+/// When redacting, paths within the brawl dump or mod are made relative to it and prefixed with a label for the root
+/// e.g. `<mod[0]>/projectm/pf/fighter/Fighter.pac`, so errors and provenance can be published without revealing where the files are on disk.
+/// Absolute paths outside of both roots are reduced to their file name.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PathFormatter {
+    /// The label and path of each root, empty when paths are not redacted
+    roots: Vec<(&'static str, PathBuf)>,
+}
+
+impl PathFormatter {
+    pub(crate) fn new(redact: bool, brawl_path: &Path, mod_path: Option<&Path>) -> PathFormatter {
+        let mut roots = vec!();
+        if redact {
+            // the mod is checked first in case it is stored within the brawl dump
+            if let Some(mod_path) = mod_path {
+                roots.push(("mod[0]", mod_path.to_path_buf()));
+            }
+            roots.push(("brawl", brawl_path.to_path_buf()));
+        }
+        PathFormatter { roots }
+    }
+
+    /// Returns the path as it should be recorded
+    pub(crate) fn path(&self, path: &Path) -> PathBuf {
+        if self.roots.is_empty() {
+            return path.to_path_buf();
+        }
+        for (label, root) in &self.roots {
+            if let Ok(relative) = path.strip_prefix(root) {
+                return Path::new(&format!("<{}>", label)).join(relative);
+            }
+        }
+        if path.is_relative() {
+            // e.g. the files of a mod held in memory
+            path.to_path_buf()
+        } else {
+            Path::new("<external>").join(path.file_name().unwrap_or_default())
+        }
+    }
+
+    /// Returns the path as it should be written in an error message
+    pub(crate) fn display(&self, path: &Path) -> String {
+        self.path(path).display().to_string()
+    }
+
+    /// Returns the path on disk of a path returned by `PathFormatter::path`, paths that were not redacted are returned as is.
+    pub(crate) fn resolve(&self, path: &Path) -> PathBuf {
+        for (label, root) in &self.roots {
+            if let Ok(relative) = path.strip_prefix(format!("<{}>", label)) {
+                return root.join(relative);
+            }
+        }
+        path.to_path_buf()
+    }
+}
+
+/// Reads the entire file, hashing it as it is read.
+/// Returns the file contents and the hex encoded SHA-1.
+pub(crate) fn read_hashed(path: &Path) -> io::Result<(Vec<u8>, String)> {
+    let mut file = File::open(path)?;
+    let mut data = vec!();
+    let mut hasher = Sha1::new();
+    let mut chunk = [0; 0x10000];
+    loop {
+        let len = file.read(&mut chunk)?;
+        if len == 0 {
+            break;
+        }
+        hasher.update(&chunk[..len]);
+        data.extend_from_slice(&chunk[..len]);
+    }
+    Ok((data, hasher.digest().to_string()))
+}
+
+/// Returns the hex encoded SHA-1 of a file that is already in memory
+pub(crate) fn sha1(data: &[u8]) -> String {
+    Sha1::from(data).digest().to_string()
+}
+
+fn sha1_file(path: &Path) -> io::Result<String> {
+    read_hashed(path).map(|(_, sha1)| sha1)
+}
+
+fn find_case_insensitive(dir: &Path, name: &str) -> Option<PathBuf> {
+    dir.read_dir().ok()?
+        .flatten()
+        .map(|x| x.path())
+        .find(|x| x.file_name().map(|x| x.to_string_lossy().eq_ignore_ascii_case(name)).unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_hashed_matches_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        let data: Vec<u8> = (0..0x25000).map(|x| x as u8).collect();
+        std::fs::write(&path, &data).unwrap();
+
+        let (read, sha1) = read_hashed(&path).unwrap();
+        assert_eq!(read, data);
+        assert_eq!(sha1, Sha1::from(&data).digest().to_string());
+        assert_eq!(read_hashed(&dir.path().join("missing")).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn path_formatter_roots() {
+        let brawl = Path::new("/home/user/brawl");
+        let sd = Path::new("/home/user/brawl/sd");
+        let paths = PathFormatter::new(true, brawl, Some(sd));
+
+        // the mod is stored within the brawl dump, but is still labelled as the mod
+        assert_eq!(paths.path(&sd.join("codes/RSBE01.gct")), Path::new("<mod[0]>/codes/RSBE01.gct"));
+        assert_eq!(paths.path(&brawl.join("fighter/Fighter.pac")), Path::new("<brawl>/fighter/Fighter.pac"));
+        assert_eq!(paths.path(Path::new("/tmp/other/FitMario.pac")), Path::new("<external>/FitMario.pac"));
+        assert_eq!(paths.path(Path::new("fighter/mario/FitMario.pac")), Path::new("fighter/mario/FitMario.pac"));
+        assert!(!paths.display(&sd.join("codes/RSBE01.gct")).contains("/home/user"));
+
+        for path in &[sd.join("codes/RSBE01.gct"), brawl.join("fighter/Fighter.pac")] {
+            assert_eq!(&paths.resolve(&paths.path(path)), path);
+        }
+
+        // not redacting leaves every path as is
+        let paths = PathFormatter::new(false, brawl, Some(sd));
+        assert_eq!(paths.path(&sd.join("codes/RSBE01.gct")), sd.join("codes/RSBE01.gct"));
+        assert_eq!(paths.resolve(Path::new("<brawl>/fighter/Fighter.pac")), Path::new("<brawl>/fighter/Fighter.pac"));
+    }
+
+    #[cfg(all(feature = "serde-full", feature = "serde_json"))]
+    #[test]
+    fn provenance_serde() {
+        use crate::fighter::FileRole;
+
+        let mut provenance = Provenance::new(vec!(FileProvenance {
+            role:       FileRole::Moveset,
+            path:       PathBuf::from("fighter/mario/FitMario.pac"),
+            from_mod:   false,
+            sha1:       "da39a3ee5e6b4b0d3255bfef95601890afd80709".to_string(),
+            compressed: false,
+        }), vec!(MemorySection {
+            location:    String::from("subaction_main[0x0]"),
+            address:     0x80500000,
+            code_offset: Some(0x18),
+        }));
+        provenance.load_config = Some(LoadConfig::default());
+        provenance.codeset = Some(CodesetProvenance {
+            path: PathBuf::from("codes/RSBE01.gct"),
+            sha1: "da39a3ee5e6b4b0d3255bfef95601890afd80709".to_string(),
+        });
+
+        let json = serde_json::to_string(&provenance).unwrap();
+        assert_eq!(serde_json::from_str::<Provenance>(&json).unwrap(), provenance);
+    }
+}
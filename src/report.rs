@@ -0,0 +1,530 @@
+//! Release notes comparing two versions of a mod, see `mod_changelog`.
+//!
+//! Fighters are compared through the headline attributes of `KitSummary` and the move metrics of `analysis::roster_table`,
+//! so each change is a statement a player understands e.g. `fsmash damage 14→13` rather than the events that were edited.
+//! Codes are compared as a whole, a code that is edited shows up as changed when it still writes to the same address.
+//! The addresses codes write to are described with a `SymbolTable`.
+//!
+//! Stage parameters are compared word by word as the meaning of most of them is unknown, see the `stage` module.
+
+use std::fmt;
+use std::fmt::Write;
+
+use failure::Error;
+
+use crate::address::RamAddress;
+use crate::aerial_data::Aerial;
+use crate::analysis::{self, MoveName, Metric, RosterTable};
+use crate::brawl_mod::{BrawlMod, LoadConfig};
+use crate::export::markdown::SECTIONS;
+use crate::fighter::{Fighter, LoadDetail};
+use crate::stage::StageData;
+use crate::symbols::SymbolTable;
+use crate::wiird;
+
+/// The metrics compared for every move, landing lag is only compared for aerials
+const METRICS: &[Metric] = &[Metric::Startup, Metric::Damage, Metric::Faf, Metric::LandingLag];
+
+/// The differences between two versions of a mod, see `mod_changelog`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct Changelog {
+    /// Fighters only in the new version, sorted by name
+    pub added_fighters: Vec<String>,
+    /// Fighters only in the old version, sorted by name
+    pub removed_fighters: Vec<String>,
+    /// Fighters in both versions with at least one change, sorted by name
+    pub fighters: Vec<FighterChanges>,
+    /// Codes of the WiiRD codeset that were added, removed or changed
+    pub codes: Vec<CodeChange>,
+    /// Stage .pac files only in the new version, sorted by name
+    pub added_stages: Vec<String>,
+    /// Stage .pac files only in the old version, sorted by name
+    pub removed_stages: Vec<String>,
+    /// Stages in both versions with at least one changed parameter, sorted by name
+    pub stages: Vec<StageChanges>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct FighterChanges {
+    pub fighter: String,
+    pub changes: Vec<StatChange>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct StageChanges {
+    /// The file name of the stage .pac e.g. `STGBATTLEFIELD.pac`
+    pub stage: String,
+    pub changes: Vec<StageParamChange>,
+}
+
+/// A word of a STDT file whose value differs between the versions.
+/// The value is None when the STDT file is shorter or missing in that version.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct StageParamChange {
+    /// Index of the STDT file in `StageData::tables`
+    pub table: usize,
+    /// Offset of the word from the start of the STDT file
+    pub offset: usize,
+    pub old: Option<u32>,
+    pub new: Option<u32>,
+}
+
+/// A stat whose value differs between the versions.
+/// The value is None when it could not be determined e.g. the fighter lacks the move in that version.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct StatChange {
+    pub stat: Stat,
+    pub old: Option<f32>,
+    pub new: Option<f32>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum Stat {
+    Weight,
+    WalkSpeed,
+    RunSpeed,
+    AirSpeed,
+    FallSpeed,
+    FastfallSpeed,
+    Jumps,
+    Move { move_name: MoveName, metric: Metric },
+}
+
+impl fmt::Display for Stat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stat::Weight        => write!(f, "weight"),
+            Stat::WalkSpeed     => write!(f, "walk speed"),
+            Stat::RunSpeed      => write!(f, "run speed"),
+            Stat::AirSpeed      => write!(f, "air speed"),
+            Stat::FallSpeed     => write!(f, "fall speed"),
+            Stat::FastfallSpeed => write!(f, "fast fall speed"),
+            Stat::Jumps         => write!(f, "jumps"),
+            Stat::Move { move_name, metric } => write!(f, "{} {}", move_short_name(*move_name), metric_name(*metric)),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum CodeChangeKind {
+    Added,
+    Removed,
+    /// The code was replaced by a code writing to the same address
+    Changed,
+}
+
+/// A code at the root of the codeset, including the codes nested in it.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct CodeChange {
+    pub kind: CodeChangeKind,
+    /// The first address the code writes to, None when it writes nothing or the address is only known at runtime
+    pub address: Option<u32>,
+    /// The name of the symbol at `address`
+    pub description: Option<String>,
+    /// The bytes of the code in the old codeset, None for added codes
+    pub old: Option<Vec<u8>>,
+    /// The bytes of the code in the new codeset, None for removed codes
+    pub new: Option<Vec<u8>>,
+}
+
+/// Compares the fighters, WiiRD codeset and stage parameters of two versions of a mod.
+///
+/// Either version may be vanilla brawl, which has no codeset.
+/// Only scripts and attributes are loaded, so stats taken from animations are not compared.
+/// The addresses written by codes are described with `SymbolTable::builtin`.
+pub fn mod_changelog(old: &BrawlMod, new: &BrawlMod) -> Result<Changelog, Error> {
+    mod_changelog_with_symbols(old, new, &SymbolTable::builtin())
+}
+
+/// Same as `mod_changelog` but describes the addresses written by codes with `symbols`.
+pub fn mod_changelog_with_symbols(old: &BrawlMod, new: &BrawlMod, symbols: &SymbolTable) -> Result<Changelog, Error> {
+    let config = LoadConfig { detail: LoadDetail::ScriptsOnly, ..LoadConfig::default() };
+    let old_fighters = old.load_fighters_with_config(&config)?.fighters;
+    let new_fighters = new.load_fighters_with_config(&config)?.fighters;
+
+    let mut added_fighters: Vec<String> = new_fighters.iter()
+        .filter(|x| !old_fighters.iter().any(|old| old.cased_name == x.cased_name))
+        .map(|x| x.cased_name.clone())
+        .collect();
+    let mut removed_fighters: Vec<String> = old_fighters.iter()
+        .filter(|x| !new_fighters.iter().any(|new| new.cased_name == x.cased_name))
+        .map(|x| x.cased_name.clone())
+        .collect();
+    added_fighters.sort();
+    removed_fighters.sort();
+
+    let old_stats = stats(&old_fighters);
+    let mut fighters: Vec<FighterChanges> = stats(&new_fighters).into_iter().filter_map(|(fighter, new_stats)| {
+        let (_, old_stats) = old_stats.iter().find(|(name, _)| *name == fighter)?;
+        let changes: Vec<StatChange> = old_stats.iter().zip(new_stats)
+            .filter(|((_, old), (_, new))| old != new)
+            .map(|((stat, old), (_, new))| StatChange { stat: *stat, old: *old, new })
+            .collect();
+        if changes.is_empty() { None } else { Some(FighterChanges { fighter, changes }) }
+    }).collect();
+    fighters.sort_by(|a, b| a.fighter.cmp(&b.fighter));
+
+    let codes = code_changes(&codeset(old)?, &codeset(new)?, symbols);
+
+    let old_stages = old.load_stage_data()?;
+    let new_stages = new.load_stage_data()?;
+    let mut added_stages: Vec<String> = new_stages.iter()
+        .filter(|x| !old_stages.iter().any(|old| old.name().eq_ignore_ascii_case(&x.name())))
+        .map(|x| x.name())
+        .collect();
+    let mut removed_stages: Vec<String> = old_stages.iter()
+        .filter(|x| !new_stages.iter().any(|new| new.name().eq_ignore_ascii_case(&x.name())))
+        .map(|x| x.name())
+        .collect();
+    added_stages.sort();
+    removed_stages.sort();
+
+    let mut stages: Vec<StageChanges> = new_stages.iter().filter_map(|new| {
+        let old = old_stages.iter().find(|old| old.name().eq_ignore_ascii_case(&new.name()))?;
+        let changes = stage_changes(old, new);
+        if changes.is_empty() { None } else { Some(StageChanges { stage: new.name(), changes }) }
+    }).collect();
+    stages.sort_by(|a, b| a.stage.cmp(&b.stage));
+
+    Ok(Changelog { added_fighters, removed_fighters, fighters, codes, added_stages, removed_stages, stages })
+}
+
+impl Changelog {
+    /// Renders the changelog as GitHub flavored markdown, sections without changes are left out.
+    pub fn to_markdown(&self) -> String {
+        let mut page = String::new();
+        writeln!(page, "# Changelog").unwrap();
+
+        if !self.added_fighters.is_empty() || !self.removed_fighters.is_empty() || !self.fighters.is_empty() {
+            writeln!(page).unwrap();
+            writeln!(page, "## Fighters").unwrap();
+            writeln!(page).unwrap();
+            for fighter in &self.added_fighters {
+                writeln!(page, "- Added {}", fighter).unwrap();
+            }
+            for fighter in &self.removed_fighters {
+                writeln!(page, "- Removed {}", fighter).unwrap();
+            }
+            for fighter in &self.fighters {
+                let changes: Vec<String> = fighter.changes.iter()
+                    .map(|x| format!("{} {}→{}", x.stat, num(x.old), num(x.new)))
+                    .collect();
+                writeln!(page, "- {}: {}", fighter.fighter, changes.join(", ")).unwrap();
+            }
+        }
+
+        if !self.codes.is_empty() {
+            writeln!(page).unwrap();
+            writeln!(page, "## Codes").unwrap();
+            writeln!(page).unwrap();
+            for code in &self.codes {
+                let kind = match code.kind {
+                    CodeChangeKind::Added   => "Added",
+                    CodeChangeKind::Removed => "Removed",
+                    CodeChangeKind::Changed => "Changed",
+                };
+                write!(page, "- {} code", kind).unwrap();
+                if let Some(address) = code.address {
+                    write!(page, " writing to 0x{:08x}", address).unwrap();
+                }
+                if let Some(description) = &code.description {
+                    write!(page, " ({})", description).unwrap();
+                }
+                match (&code.old, &code.new) {
+                    (Some(old), Some(new)) => writeln!(page, ": `{}` → `{}`", hex(old), hex(new)).unwrap(),
+                    (Some(code), None) |
+                    (None, Some(code))     => writeln!(page, ": `{}`", hex(code)).unwrap(),
+                    (None, None)           => writeln!(page).unwrap(),
+                }
+            }
+        }
+
+        if !self.added_stages.is_empty() || !self.removed_stages.is_empty() || !self.stages.is_empty() {
+            writeln!(page).unwrap();
+            writeln!(page, "## Stages").unwrap();
+            writeln!(page).unwrap();
+            for stage in &self.added_stages {
+                writeln!(page, "- Added {}", stage).unwrap();
+            }
+            for stage in &self.removed_stages {
+                writeln!(page, "- Removed {}", stage).unwrap();
+            }
+            for stage in &self.stages {
+                let changes: Vec<String> = stage.changes.iter()
+                    .map(|x| format!("STDT {} 0x{:x} {}→{}", x.table, x.offset, word(x.old), word(x.new)))
+                    .collect();
+                writeln!(page, "- {}: {}", stage.stage, changes.join(", ")).unwrap();
+            }
+        }
+
+        if self.added_fighters.is_empty() && self.removed_fighters.is_empty() && self.fighters.is_empty() && self.codes.is_empty()
+            && self.added_stages.is_empty() && self.removed_stages.is_empty() && self.stages.is_empty()
+        {
+            writeln!(page).unwrap();
+            writeln!(page, "No changes.").unwrap();
+        }
+        page
+    }
+}
+
+/// The name of a fighter and the value of each of its stats
+type FighterStats = (String, Vec<(Stat, Option<f32>)>);
+
+/// Every stat of each fighter, the stats are in the same order for every fighter
+fn stats(fighters: &[Fighter]) -> Vec<FighterStats> {
+    let tables: Vec<RosterTable> = SECTIONS.iter()
+        .flat_map(|(_, moves)| moves.iter())
+        .flat_map(|move_name| METRICS.iter()
+            .filter(move |metric| **metric != Metric::LandingLag || matches!(move_name, MoveName::Aerial (_)))
+            .map(move |metric| analysis::roster_table(fighters, *move_name, *metric)))
+        .collect();
+
+    fighters.iter().map(|fighter| {
+        let summary = fighter.kit_summary();
+        let mut stats = vec!(
+            (Stat::Weight,        summary.weight),
+            (Stat::WalkSpeed,     summary.walk_speed),
+            (Stat::RunSpeed,      summary.run_speed),
+            (Stat::AirSpeed,      summary.air_speed),
+            (Stat::FallSpeed,     summary.fall_speed),
+            (Stat::FastfallSpeed, summary.fastfall_speed),
+            (Stat::Jumps,         summary.jumps.map(|x| x as f32)),
+        );
+        for table in &tables {
+            let value = table.rows.iter().find(|x| x.fighter == fighter.cased_name).and_then(|x| x.value);
+            stats.push((Stat::Move { move_name: table.move_name, metric: table.metric }, value));
+        }
+        (fighter.cased_name.clone(), stats)
+    }).collect()
+}
+
+/// Every word that differs between the STDT files of the two versions of a stage
+fn stage_changes(old: &StageData, new: &StageData) -> Vec<StageParamChange> {
+    let mut changes = vec!();
+    for table in 0..old.tables.len().max(new.tables.len()) {
+        let old_words = old.tables.get(table).map(|x| x.as_slice()).unwrap_or_default();
+        let new_words = new.tables.get(table).map(|x| x.as_slice()).unwrap_or_default();
+        for i in 0..old_words.len().max(new_words.len()) {
+            let old = old_words.get(i).cloned();
+            let new = new_words.get(i).cloned();
+            if old != new {
+                changes.push(StageParamChange { table, offset: i * 4, old, new });
+            }
+        }
+    }
+    changes
+}
+
+/// The codeset without its header, empty for vanilla brawl
+fn codeset(brawl_mod: &BrawlMod) -> Result<Vec<u8>, Error> {
+    if brawl_mod.is_mod() {
+        brawl_mod.load_wiird_codeset_raw()
+    } else {
+        Ok(vec!())
+    }
+}
+
+fn code_changes(old: &[u8], new: &[u8], symbols: &SymbolTable) -> Vec<CodeChange> {
+    let mut old_codes = root_codes(old);
+    let mut new_codes = root_codes(new);
+
+    // codes in both codesets are unchanged
+    old_codes.retain(|(old, _)| match new_codes.iter().position(|(new, _)| new == old) {
+        Some(i) => {
+            new_codes.remove(i);
+            false
+        }
+        None => true,
+    });
+
+    let mut changes = vec!();
+    for (old, address) in old_codes {
+        let description = address.and_then(|x| symbols.resolve_address(RamAddress::new(x))).map(|x| x.to_string());
+        match address.and_then(|address| new_codes.iter().position(|x| x.1 == Some(address))) {
+            Some(i) => {
+                let (new, _) = new_codes.remove(i);
+                changes.push(CodeChange { kind: CodeChangeKind::Changed, address, description, old: Some(old), new: Some(new) });
+            }
+            None => changes.push(CodeChange { kind: CodeChangeKind::Removed, address, description, old: Some(old), new: None }),
+        }
+    }
+    for (new, address) in new_codes {
+        let description = address.and_then(|x| symbols.resolve_address(RamAddress::new(x))).map(|x| x.to_string());
+        changes.push(CodeChange { kind: CodeChangeKind::Added, address, description, old: None, new: Some(new) });
+    }
+    changes
+}
+
+/// The bytes of each code at the root of the codeset and the first address it writes to
+fn root_codes(data: &[u8]) -> Vec<(Vec<u8>, Option<u32>)> {
+    let (block, spans) = wiird::wiird_codes_with_spans(data);
+    let targets = wiird::resolve_targets(&block);
+    spans.into_iter()
+        .filter(|(path, span)| path.0.len() == 1 && !span.is_empty())
+        .map(|(path, span)| {
+            let address = targets.iter().find(|(target, _)| target.0[0] == path.0[0]).and_then(|(_, address)| *address);
+            (data[span].to_vec(), address)
+        })
+        .collect()
+}
+
+fn move_short_name(move_name: MoveName) -> &'static str {
+    match move_name {
+        MoveName::Jab            => "jab",
+        MoveName::ForwardTilt    => "ftilt",
+        MoveName::UpTilt         => "utilt",
+        MoveName::DownTilt       => "dtilt",
+        MoveName::ForwardSmash   => "fsmash",
+        MoveName::UpSmash        => "usmash",
+        MoveName::DownSmash      => "dsmash",
+        MoveName::DashAttack     => "dash attack",
+        MoveName::Aerial (Aerial::Nair) => "nair",
+        MoveName::Aerial (Aerial::Fair) => "fair",
+        MoveName::Aerial (Aerial::Bair) => "bair",
+        MoveName::Aerial (Aerial::Uair) => "uair",
+        MoveName::Aerial (Aerial::Dair) => "dair",
+        MoveName::Grab           => "grab",
+        MoveName::NeutralSpecial => "neutral special",
+        MoveName::SideSpecial    => "side special",
+        MoveName::UpSpecial      => "up special",
+        MoveName::DownSpecial    => "down special",
+    }
+}
+
+fn metric_name(metric: Metric) -> &'static str {
+    match metric {
+        Metric::Startup     => "startup",
+        Metric::TotalFrames => "total frames",
+        Metric::Damage      => "damage",
+        Metric::Faf         => "FAF",
+        Metric::LandingLag  => "landing lag",
+    }
+}
+
+fn num(value: Option<f32>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => String::from("none"),
+    }
+}
+
+fn word(value: Option<u32>) -> String {
+    match value {
+        Some(value) => format!("0x{:08x}", value),
+        None => String::from("none"),
+    }
+}
+
+/// Formats a code as its 32 bit words
+fn hex(code: &[u8]) -> String {
+    let words: Vec<String> = code.chunks(4).map(|word| word.iter().map(|x| format!("{:02X}", x)).collect()).collect();
+    words.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::test_util::{TestFighterBuilder, arc_blob, damage, gct, write_fighter};
+
+    /// The fair can be interrupted on the frame after `fair_interrupt`
+    fn marth(weight: f32, fsmash_damage: i32, fair_interrupt: f32) -> TestFighterBuilder {
+        TestFighterBuilder::new("Marth")
+            .attribute_f32(0xB0, weight)
+            .subaction("AttackS4S", |s| s.sync_timer(9.0).hitbox(damage(fsmash_damage)).sync_timer(30.0).event(0x64, 0x00, vec!()))
+            .subaction("AttackAirF", |s| s.sync_timer(4.0).hitbox(damage(9)).async_timer(fair_interrupt).event(0x64, 0x00, vec!()))
+    }
+
+    /// A mod with the given fighters and a codeset of 32 bit writes
+    fn sd_card(fighters: &[(&str, TestFighterBuilder)], writes: &[(u32, u32)]) -> tempfile::TempDir {
+        let sd = tempfile::tempdir().unwrap();
+        let fighter_dir = sd.path().join("projectm/pf/fighter");
+        fs::create_dir_all(&fighter_dir).unwrap();
+        for (name, builder) in fighters {
+            write_fighter(&fighter_dir, name, &builder.moveset_pac()).unwrap();
+        }
+
+        let writes: Vec<(u32, [u8; 4])> = writes.iter().map(|(address, value)| (*address, value.to_be_bytes())).collect();
+        fs::create_dir_all(sd.path().join("codes")).unwrap();
+        fs::write(sd.path().join("codes/RSBE01.gct"), gct(&writes)).unwrap();
+        sd
+    }
+
+    /// Writes a stage .pac containing a STDT file with `values` to the stage folder of `root`
+    fn write_stage(root: &std::path::Path, name: &str, values: &[u32]) {
+        let mut stdt = b"STDT".to_vec();
+        for value in [1, 0, 0, 0].iter().chain(values) {
+            stdt.extend(&value.to_be_bytes());
+        }
+        fs::create_dir_all(root.join("stage/melee")).unwrap();
+        fs::write(root.join("stage/melee").join(name), arc_blob(name, &[(-1, &stdt)])).unwrap();
+    }
+
+    #[test]
+    fn changelog_markdown() {
+        let brawl = tempfile::tempdir().unwrap();
+        let fighter_dir = brawl.path().join("fighter");
+        fs::create_dir_all(&fighter_dir).unwrap();
+        fs::write(fighter_dir.join("Fighter.pac"), TestFighterBuilder::new("Common").moveset_pac()).unwrap();
+        write_fighter(&fighter_dir, "Marth", &marth(90.0, 15, 30.0).moveset_pac()).unwrap();
+        write_fighter(&fighter_dir, "Luigi", &TestFighterBuilder::new("Luigi").attribute_f32(0xB0, 100.0).moveset_pac()).unwrap();
+
+        let old = sd_card(
+            &[("Marth", marth(90.0, 14, 33.0)), ("Roy", TestFighterBuilder::new("Roy"))],
+            &[(0x80581000, 0x0A010000), (0x80401000, 1)],
+        );
+        let new = sd_card(
+            &[("Marth", marth(92.5, 13, 35.0)), ("Knuckles", TestFighterBuilder::new("Knuckles"))],
+            &[(0x80581000, 0x0A020000), (0x80402000, 2)],
+        );
+        write_stage(brawl.path(), "STGBATTLEFIELD.pac", &[10, 20]);
+        write_stage(brawl.path(), "STGFINAL.pac", &[1]);
+        write_stage(&old.path().join("projectm/pf"), "STGBATTLEFIELD.pac", &[10, 21]);
+        write_stage(&old.path().join("projectm/pf"), "STGDOLPIC.pac", &[5]);
+        write_stage(&new.path().join("projectm/pf"), "STGBATTLEFIELD.pac", &[11, 21, 30]);
+
+        let changelog = mod_changelog(&BrawlMod::new(brawl.path(), Some(old.path())), &BrawlMod::new(brawl.path(), Some(new.path()))).unwrap();
+        assert_eq!(changelog.to_markdown(), "\
+# Changelog
+
+## Fighters
+
+- Added Knuckles
+- Removed Roy
+- Marth: weight 90→92.5, fsmash damage 14→13, fair FAF 34→36
+
+## Codes
+
+- Changed code writing to 0x80581000 (frame speed modifier table): `04581000 0A010000` → `04581000 0A020000`
+- Removed code writing to 0x80401000: `04401000 00000001`
+- Added code writing to 0x80402000: `04402000 00000002`
+
+## Stages
+
+- Removed STGDOLPIC.pac
+- STGBATTLEFIELD.pac: STDT 0 0x14 0x0000000a→0x0000000b, STDT 0 0x1c none→0x0000001e
+");
+
+        // comparing a version against itself finds nothing
+        let changelog = mod_changelog(&BrawlMod::new(brawl.path(), Some(old.path())), &BrawlMod::new(brawl.path(), Some(old.path()))).unwrap();
+        assert_eq!(changelog.to_markdown(), "# Changelog\n\nNo changes.\n");
+    }
+
+    #[test]
+    fn code_descriptions_from_symbols() {
+        let old = gct(&[]);
+        let new = gct(&[(0x80581000, [0; 4]), (0x80402000, [0; 4])]);
+        let symbols = SymbolTable::parse("0x80402000 Custom table").unwrap();
+        let changes = code_changes(&old[8..], &new[8..], &symbols);
+        let descriptions: Vec<Option<&str>> = changes.iter().map(|x| x.description.as_deref()).collect();
+        assert_eq!(descriptions, vec!(None, Some("Custom table")));
+    }
+}
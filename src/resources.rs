@@ -30,8 +30,133 @@ pub(crate) const RESOURCE_HEADER_SIZE: usize = 0x8;
 
 pub(crate) const RESOURCE_SIZE: usize = 0x10;
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Resource {
     flag:            u16,
     pub data_offset: i32,
     pub string:      String,
 }
+
+/// The position of an entry in the search tree of a resource group, which the game uses to find resources by name.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ResourceTreeEntry {
+    pub id:          u16,
+    pub left_index:  u16,
+    pub right_index: u16,
+}
+
+/// Builds the search tree for a resource group containing `names`, in the same order.
+/// The first entry is the dummy root entry, so entry `i + 1` belongs to `names[i]`.
+///
+/// The tree is a patricia tree where each id is the index of the bit that is tested to decide between the left and right branches:
+/// the index of the byte in the upper bits and the index of the bit within that byte in the lower 3 bits.
+pub(crate) fn resource_tree(names: &[&str]) -> Vec<ResourceTreeEntry> {
+    let mut entries = vec!(ResourceTreeEntry { id: 0xFFFF, left_index: 0, right_index: 0 });
+    for (i, name) in names.iter().enumerate() {
+        let name = name.as_bytes();
+        let index = (i + 1) as u16;
+        let mut entry = ResourceTreeEntry {
+            id:          tree_id(&[], name),
+            left_index:  index,
+            right_index: index,
+        };
+
+        let mut prev = 0;
+        let mut current = entries[0].left_index as usize;
+        let mut is_right = false;
+        while entry.id <= entries[current].id && entries[current].id < entries[prev].id {
+            if entry.id == entries[current].id {
+                let current_name = names[current - 1].as_bytes();
+                entry.id = tree_id(current_name, name);
+                if tree_bit(current_name, entry.id) {
+                    entry.left_index = index;
+                    entry.right_index = current as u16;
+                } else {
+                    entry.left_index = current as u16;
+                    entry.right_index = index;
+                }
+            }
+            prev = current;
+            is_right = tree_bit(name, entries[current].id);
+            current = if is_right { entries[current].right_index } else { entries[current].left_index } as usize;
+        }
+
+        let current_name = if current == 0 { &[][..] } else { names[current - 1].as_bytes() };
+        if current_name.len() == name.len() && tree_bit(current_name, entry.id) {
+            entry.right_index = current as u16;
+        } else {
+            entry.left_index = current as u16;
+        }
+
+        if is_right {
+            entries[prev].right_index = index;
+        } else {
+            entries[prev].left_index = index;
+        }
+        entries.push(entry);
+    }
+    entries
+}
+
+/// Returns the id of the first bit, searching from the end of `name`, where `name` differs from `other`
+fn tree_id(other: &[u8], name: &[u8]) -> u16 {
+    if other.len() < name.len() {
+        let last = name.len() - 1;
+        return (last as u16) << 3 | highest_bit(name[last]);
+    }
+    for i in (0..name.len()).rev() {
+        let difference = other[i] ^ name[i];
+        if difference != 0 {
+            return (i as u16) << 3 | highest_bit(difference);
+        }
+    }
+    0xFFFF
+}
+
+fn tree_bit(name: &[u8], id: u16) -> bool {
+    let byte = (id >> 3) as usize;
+    byte < name.len() && (name[byte] >> (id & 7)) & 1 != 0
+}
+
+fn highest_bit(value: u8) -> u16 {
+    7 - value.leading_zeros().min(7) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Finds a name the way the game does, by following the tree from the root
+    fn find(entries: &[ResourceTreeEntry], names: &[&str], name: &str) -> Option<usize> {
+        let mut prev = 0;
+        let mut current = entries[0].left_index as usize;
+        while entries[current].id < entries[prev].id {
+            prev = current;
+            current = if tree_bit(name.as_bytes(), entries[current].id) {
+                entries[current].right_index
+            } else {
+                entries[current].left_index
+            } as usize;
+        }
+        if current != 0 && names[current - 1] == name { Some(current) } else { None }
+    }
+
+    #[test]
+    fn tree_finds_every_name() {
+        let names = ["TransN", "XRotN", "YRotN", "HipN", "LLegJ", "RLegJ", "LKneeJ", "RKneeJ", "LFootJ", "RFootJ", "WaistN", "BustN", "NeckN", "HeadN", "LShoulderN", "RShoulderN", "a", "ab", "abc"];
+        let entries = resource_tree(&names);
+        assert_eq!(entries.len(), names.len() + 1);
+        for (i, name) in names.iter().enumerate() {
+            assert_eq!(find(&entries, &names, name), Some(i + 1), "{}", name);
+        }
+        assert_eq!(find(&entries, &names, "TopN"), None);
+    }
+
+    #[test]
+    fn tree_single_name() {
+        assert_eq!(resource_tree(&["Wait1"]), vec!(
+            ResourceTreeEntry { id: 0xFFFF, left_index: 1, right_index: 0 },
+            ResourceTreeEntry { id: 4 << 3 | 5, left_index: 0, right_index: 1 },
+        ));
+    }
+}
@@ -10,7 +10,7 @@ pub fn misc_section(data: FancySlice, parent_data: FancySlice) -> MiscSection {
     let ledge_grab_list       = util::list_offset(data.relative_fancy_slice(0x14..));
     let unk7_list             = util::list_offset(data.relative_fancy_slice(0x1c..));
     let bone_refs_offset      = data.i32_be(0x24);
-    let item_bones            = data.i32_be(0x28);
+    let item_bones_offset     = data.i32_be(0x28);
     let sound_data_offset     = data.i32_be(0x2c);
     let unk12_offset          = data.i32_be(0x30);
     let multi_jump_offset     = data.i32_be(0x34);
@@ -57,6 +57,24 @@ pub fn misc_section(data: FancySlice, parent_data: FancySlice) -> MiscSection {
         unk9:    parent_data.i32_be(bone_refs_offset as usize + 0x24),
     };
 
+    let item_bones = if item_bones_offset == 0 {
+        None
+    } else {
+        Some(item_bones(parent_data.relative_fancy_slice(item_bones_offset as usize ..), parent_data))
+    };
+
+    let multi_jump = if multi_jump_offset == 0 {
+        None
+    } else {
+        Some(multi_jump(parent_data.relative_fancy_slice(multi_jump_offset as usize ..), parent_data))
+    };
+
+    let glide = if glide_offset == 0 {
+        None
+    } else {
+        Some(glide(parent_data.relative_fancy_slice(glide_offset as usize ..)))
+    };
+
     let crawl = if crawl_offset == 0 {
         None
     } else {
@@ -109,8 +127,8 @@ pub fn misc_section(data: FancySlice, parent_data: FancySlice) -> MiscSection {
         item_bones,
         sound_data_offset,
         unk12_offset,
-        multi_jump_offset,
-        glide_offset,
+        multi_jump,
+        glide,
         crawl,
         ecbs,
         tether,
@@ -118,6 +136,72 @@ pub fn misc_section(data: FancySlice, parent_data: FancySlice) -> MiscSection {
     }
 }
 
+impl MiscSection {
+    /// Used in place of a misc section that could not be parsed
+    pub(crate) fn empty() -> MiscSection {
+        MiscSection {
+            final_smash_auras: vec!(),
+            hurt_boxes:        vec!(),
+            ledge_grab_boxes:  vec!(),
+            unk7s:             vec!(),
+            bone_refs:         BoneRefs { unk0: 0, unk1: 0, unk2: 0, unk3: 0, trans_n: 0, unk5: 0, unk6: 0, unk7: 0, unk8: 0, unk9: 0 },
+            item_bones:        None,
+            sound_data_offset: 0,
+            unk12_offset:      0,
+            multi_jump:        None,
+            glide:             None,
+            crawl:             None,
+            ecbs:              vec!(),
+            tether:            None,
+            unk18_offset:      0,
+        }
+    }
+}
+
+fn item_bones(data: FancySlice, parent_data: FancySlice) -> ItemBones {
+    let have_n_bone  = data.i32_be(0x00);
+    let throw_n_bone = data.i32_be(0x04);
+    let unknown_list = util::list_offset(data.relative_fancy_slice(0x08..));
+
+    let mut unknown = vec!();
+    for i in 0..unknown_list.count {
+        unknown.push(parent_data.i32_be(unknown_list.start_offset as usize + i as usize * 4));
+    }
+
+    ItemBones { have_n_bone, throw_n_bone, unknown }
+}
+
+fn multi_jump(data: FancySlice, parent_data: FancySlice) -> MultiJump {
+    let unk0             = data.f32_be(0x00);
+    let unk1             = data.f32_be(0x04);
+    let horizontal_boost = data.f32_be(0x08);
+    let jump_height_list = util::list_offset(data.relative_fancy_slice(0x0c..));
+    let unknown_list     = util::list_offset(data.relative_fancy_slice(0x14..));
+    let turn_frames      = data.f32_be(0x1c);
+
+    let f32_list = |list: util::ListOffset| (0..list.count)
+        .map(|i| parent_data.f32_be(list.start_offset as usize + i as usize * 4))
+        .collect();
+
+    MultiJump {
+        unk0,
+        unk1,
+        horizontal_boost,
+        jump_heights: f32_list(jump_height_list),
+        unknown: f32_list(unknown_list),
+        turn_frames,
+    }
+}
+
+fn glide(data: FancySlice) -> Glide {
+    let mut values = [0.0; GLIDE_VALUE_COUNT];
+    for (i, value) in values.iter_mut().enumerate() {
+        *value = data.f32_be(i * 4);
+    }
+    let unk = data.i32_be(GLIDE_VALUE_COUNT * 4);
+    Glide { values, unk }
+}
+
 fn final_smash_aura(data: FancySlice) -> FinalSmashAura {
     let bone_index = data.i32_be(0x00);
     let x          = data.f32_be(0x04);
@@ -198,17 +282,18 @@ fn unk7(data: FancySlice) -> Unk7 {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct MiscSection {
     pub final_smash_auras: Vec<FinalSmashAura>,
     pub hurt_boxes: Vec<HurtBox>,
     pub ledge_grab_boxes: Vec<LedgeGrabBox>,
     pub unk7s: Vec<Unk7>,
     pub bone_refs: BoneRefs,
-    item_bones: i32,
+    pub item_bones: Option<ItemBones>,
     sound_data_offset: i32,
     unk12_offset: i32,
-    multi_jump_offset: i32,
-    glide_offset: i32,
+    pub multi_jump: Option<MultiJump>,
+    pub glide: Option<Glide>,
     pub crawl: Option<Crawl>,
     pub ecbs: Vec<ECB>,
     pub tether: Option<Tether>,
@@ -217,6 +302,7 @@ pub struct MiscSection {
 
 pub const FINAL_SMASH_AURA_SIZE: usize = 0x14;
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct FinalSmashAura {
     pub bone_index: i32,
     pub x: f32,
@@ -227,6 +313,7 @@ pub struct FinalSmashAura {
 
 pub const HURTBOX_SIZE: usize = 0x20;
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct HurtBox {
     pub offset: Vector3<f32>,
     pub stretch: Vector3<f32>,
@@ -239,6 +326,7 @@ pub struct HurtBox {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum HurtBoxZone {
     Low,
     Middle,
@@ -260,6 +348,7 @@ pub enum HurtBoxZone {
 /// Note: left is behind the fighter and right is in front of the fighter
 pub const LEDGE_GRAB_SIZE: usize = 0x10;
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct LedgeGrabBox {
     pub x_left: f32,
     pub y: f32,
@@ -269,6 +358,7 @@ pub struct LedgeGrabBox {
 
 pub const UNK7_SIZE: usize = 0x20;
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Unk7 {
     unk1: u8,
     unk2: u8,
@@ -290,6 +380,7 @@ pub struct Unk7 {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct BoneRefs {
     pub unk0:    i32,
     pub unk1:    i32,
@@ -304,14 +395,52 @@ pub struct BoneRefs {
 }
 
 
+/// The bones items are attached to while held and while being thrown
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
+pub struct ItemBones {
+    pub have_n_bone: i32,
+    pub throw_n_bone: i32,
+    /// Entries of a list that follows the bones, its meaning is unknown so the raw values are kept.
+    pub unknown: Vec<i32>,
+}
+
+/// Only present for fighters that can crawl
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct Crawl {
     pub forward: f32,
     pub backward: f32,
 }
 
+/// Only present for fighters with more than one aerial jump.
+/// The layout and names follow BrawlBox's multijump node.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct MultiJump {
+    pub unk0: f32,
+    pub unk1: f32,
+    pub horizontal_boost: f32,
+    /// The height of each aerial jump, mods lower these to make later jumps decay
+    pub jump_heights: Vec<f32>,
+    /// Entries of a list that follows the jump heights, its meaning is unknown so the raw values are kept.
+    pub unknown: Vec<f32>,
+    pub turn_frames: f32,
+}
+
+pub const GLIDE_VALUE_COUNT: usize = 0x15;
+/// Only present for fighters that can glide.
+/// None of the values have been documented yet so they are kept raw in the order they are stored in the file.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct Glide {
+    pub values: [f32; GLIDE_VALUE_COUNT],
+    pub unk: i32,
+}
+
 pub const ECB_SIZE: usize = 0x4; // TODO
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 /// TODO: Currently just ECB type 0, maybe change to enum or maybe change the fields to Options
 pub struct ECB {
     pub bones:      Vec<i32>,
@@ -321,7 +450,96 @@ pub struct ECB {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Tether {
     pub num_hang_frame: i32,
     unk1: f32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_item_bones() {
+        let data: Vec<u8> = vec!(
+            0x00, 0x00, 0x00, 0x2a, // have_n_bone
+            0x00, 0x00, 0x00, 0x2b, // throw_n_bone
+            0x00, 0x00, 0x00, 0x10, // unknown list offset
+            0x00, 0x00, 0x00, 0x02, // unknown list count
+            0x00, 0x00, 0x00, 0x07,
+            0xff, 0xff, 0xff, 0xff,
+        );
+        let data = FancySlice::new(&data);
+        let item_bones = item_bones(data, data);
+        assert_eq!(item_bones.have_n_bone, 0x2a);
+        assert_eq!(item_bones.throw_n_bone, 0x2b);
+        assert_eq!(item_bones.unknown, vec!(7, -1));
+    }
+
+    /// A misc section header with every offset and list set to 0, followed by zeroed parent data
+    fn empty_misc_data() -> (Vec<u8>, Vec<u8>) {
+        (vec!(0; 0x4c), vec!(0; 0x100))
+    }
+
+    #[test]
+    fn parse_glide() {
+        let (mut data, mut parent_data) = empty_misc_data();
+        data[0x38..0x3c].copy_from_slice(&0x80i32.to_be_bytes());
+        for i in 0..GLIDE_VALUE_COUNT {
+            let offset = 0x80 + i * 4;
+            parent_data[offset..offset + 4].copy_from_slice(&(i as f32 * 0.5).to_be_bytes());
+        }
+        let unk_offset = 0x80 + GLIDE_VALUE_COUNT * 4;
+        parent_data[unk_offset..unk_offset + 4].copy_from_slice(&7i32.to_be_bytes());
+
+        let misc = misc_section(FancySlice::new(&data), FancySlice::new(&parent_data));
+        let glide = misc.glide.unwrap();
+        assert_eq!(glide.values[0], 0.0);
+        assert_eq!(glide.values[3], 1.5);
+        assert_eq!(glide.values[GLIDE_VALUE_COUNT - 1], 10.0);
+        assert_eq!(glide.unk, 7);
+        assert!(misc.crawl.is_none());
+        assert!(misc.multi_jump.is_none());
+    }
+
+    #[test]
+    fn parse_multi_jump() {
+        let (mut data, mut parent_data) = empty_misc_data();
+        data[0x34..0x38].copy_from_slice(&0x80i32.to_be_bytes());
+        let block: Vec<u8> = vec!(
+            0x3f, 0x80, 0x00, 0x00, // unk0 = 1.0
+            0x40, 0x00, 0x00, 0x00, // unk1 = 2.0
+            0x3f, 0x00, 0x00, 0x00, // horizontal_boost = 0.5
+            0x00, 0x00, 0x00, 0xa0, // jump heights list offset
+            0x00, 0x00, 0x00, 0x02, // jump heights list count
+            0x00, 0x00, 0x00, 0x00, // unknown list offset
+            0x00, 0x00, 0x00, 0x00, // unknown list count
+            0x41, 0x20, 0x00, 0x00, // turn_frames = 10.0
+            0x40, 0x40, 0x00, 0x00, // jump height 3.0
+            0x40, 0x00, 0x00, 0x00, // jump height 2.0
+        );
+        parent_data[0x80..0x80 + 0x20].copy_from_slice(&block[..0x20]);
+        parent_data[0xa0..0xa8].copy_from_slice(&block[0x20..]);
+
+        let misc = misc_section(FancySlice::new(&data), FancySlice::new(&parent_data));
+        assert_eq!(misc.multi_jump, Some(MultiJump {
+            unk0: 1.0,
+            unk1: 2.0,
+            horizontal_boost: 0.5,
+            jump_heights: vec!(3.0, 2.0),
+            unknown: vec!(),
+            turn_frames: 10.0,
+        }));
+        assert!(misc.glide.is_none());
+    }
+
+    #[test]
+    fn no_optional_blocks() {
+        let (data, parent_data) = empty_misc_data();
+        let misc = misc_section(FancySlice::new(&data), FancySlice::new(&parent_data));
+        assert!(misc.crawl.is_none());
+        assert!(misc.glide.is_none());
+        assert!(misc.multi_jump.is_none());
+    }
+}
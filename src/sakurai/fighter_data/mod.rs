@@ -1,36 +1,47 @@
 pub mod misc_section;
 
-use crate::script::Script;
-use crate::script;
+use crate::sakurai::ParseWarning;
+use crate::script::{DataSource, Script, ScriptParser};
 use crate::util;
-use crate::wii_memory::WiiMemory;
 use misc_section::MiscSection;
 
 use fancy_slice::FancySlice;
 
-pub(crate) fn arc_fighter_data(parent_data: FancySlice, data: FancySlice, wii_memory: &WiiMemory) -> ArcFighterData {
-    let subaction_flags_start        = data.i32_be(0);
-    let model_visibility_start       = data.i32_be(4);
-    let attribute_start              = data.i32_be(8);
+pub(crate) fn arc_fighter_data(parent_data: FancySlice, data: FancySlice, parser: &mut ScriptParser, warnings: &mut Vec<ParseWarning>) -> ArcFighterData {
+    // Offsets outside of the moveset are replaced with 0 so the section is treated as missing.
+    let mut check_offset = |name: &str, offset: i32| {
+        if offset < 0 || offset as usize >= parent_data.len() {
+            let message = format!("The offset 0x{:x} is outside of the moveset (size 0x{:x}), skipped the section", offset, parent_data.len());
+            error!("data/{}: {}", name, message);
+            warnings.push(ParseWarning { section: format!("data/{}", name), message, script_offset: None });
+            0
+        } else {
+            offset
+        }
+    };
+
+    let subaction_flags_start        = check_offset("subaction_flags", data.i32_be(0));
+    let model_visibility_start       = check_offset("model_visibility", data.i32_be(4));
+    let attribute_start              = check_offset("attributes", data.i32_be(8));
     let sse_attribute_start          = data.i32_be(12);
-    let misc_section_offset          = data.i32_be(16);
+    let misc_section_offset          = check_offset("misc", data.i32_be(16));
     let common_action_flags_start    = data.i32_be(20);
-    let action_flags_start           = data.i32_be(24);
+    let action_flags_start           = check_offset("action_flags", data.i32_be(24));
     let _unknown0                    = data.i32_be(28);
     let action_interrupts            = data.i32_be(32);
-    let entry_actions_start          = data.i32_be(36);
-    let exit_actions_start           = data.i32_be(40);
+    let entry_actions_start          = check_offset("entry_actions", data.i32_be(36));
+    let exit_actions_start           = check_offset("exit_actions", data.i32_be(40));
     let action_pre_start             = data.i32_be(44);
-    let subaction_main_start         = data.i32_be(48);
-    let subaction_gfx_start          = data.i32_be(52);
-    let subaction_sfx_start          = data.i32_be(56);
-    let subaction_other_start        = data.i32_be(60);
+    let subaction_main_start         = check_offset("subaction_main", data.i32_be(48));
+    let subaction_gfx_start          = check_offset("subaction_gfx", data.i32_be(52));
+    let subaction_sfx_start          = check_offset("subaction_sfx", data.i32_be(56));
+    let subaction_other_start        = check_offset("subaction_other", data.i32_be(60));
     let anchored_item_positions      = data.i32_be(64);
     let gooey_bomb_positions         = data.i32_be(68);
     let bone_ref1                    = data.i32_be(72);
     let bone_ref2                    = data.i32_be(76);
-    let entry_action_overrides_start = data.i32_be(80);
-    let exit_action_overrides_start  = data.i32_be(84);
+    let entry_action_overrides_start = check_offset("entry_action_overrides", data.i32_be(80));
+    let exit_action_overrides_start  = check_offset("exit_action_overrides", data.i32_be(84));
     let _unknown1                    = data.i32_be(88);
     let samus_arm_cannon_positions   = data.i32_be(92);
     let _unknown2                    = data.i32_be(96);
@@ -39,37 +50,67 @@ pub(crate) fn arc_fighter_data(parent_data: FancySlice, data: FancySlice, wii_me
     let flags1                       = data.u32_be(116);
     let flags2                       = data.i32_be(120);
 
-    let sizes = get_sizes(data);
+    let sizes = get_sizes(data, parent_data.len());
+    let size_of = |offset: i32| sizes.iter().find(|x| x.offset == offset as usize).map(|x| x.size).unwrap_or(0);
+
+    let subaction_main_num = size_of(subaction_main_start) / 4; // divide by integer size
 
-    let subaction_flags_num = sizes.iter().find(|x| x.offset == subaction_flags_start as usize).unwrap().size / SUB_ACTION_FLAGS_SIZE;
-    let subaction_flags = subaction_flags(parent_data, parent_data.relative_fancy_slice(subaction_flags_start as usize ..), subaction_flags_num);
+    let subaction_flags = if subaction_flags_start == 0 {
+        vec!()
+    } else {
+        let subaction_flags_num = size_of(subaction_flags_start) / SUB_ACTION_FLAGS_SIZE;
+        let flags = subaction_flags(parent_data, parent_data.relative_fancy_slice(subaction_flags_start as usize ..), subaction_flags_num, subaction_main_num);
+        for message in flags.warnings {
+            error!("data/subaction_names: {}", message);
+            warnings.push(ParseWarning { section: "data/subaction_names".to_string(), message, script_offset: None });
+        }
+        flags.flags
+    };
 
-    let model_visibility = model_visibility(parent_data, model_visibility_start);
+    let model_visibility = if model_visibility_start == 0 {
+        ModelVisibility { references: vec!(), defaults: vec!() }
+    } else {
+        model_visibility(parent_data, model_visibility_start)
+    };
 
-    let action_flags_num = sizes.iter().find(|x| x.offset == action_flags_start as usize).unwrap().size / ACTION_FLAGS_SIZE;
+    let action_flags_num = size_of(action_flags_start) / ACTION_FLAGS_SIZE;
     let action_flags = action_flags(parent_data.relative_fancy_slice(action_flags_start as usize ..), action_flags_num);
 
-    let entry_actions_num = sizes.iter().find(|x| x.offset == entry_actions_start as usize).unwrap().size / 4; // divide by integer size
-    let entry_actions = script::scripts(parent_data.relative_fancy_slice(..), parent_data.relative_fancy_slice(entry_actions_start as usize ..), entry_actions_num, wii_memory);
-    let exit_actions = script::scripts(parent_data.relative_fancy_slice(..), parent_data.relative_fancy_slice(exit_actions_start as usize ..), entry_actions_num, wii_memory);
+    // A skipped script table is replaced with empty scripts so that it still lines up with the other tables
+    let mut scripts = |start: i32, num: usize| if start == 0 {
+        vec!(Script { events: vec!(), offset: 0, source: DataSource::File { offset: 0 } }; num)
+    } else {
+        parser.scripts(parent_data.relative_fancy_slice(..), parent_data.relative_fancy_slice(start as usize ..), num)
+    };
+
+    let entry_actions_num = size_of(entry_actions_start) / 4; // divide by integer size
+    let entry_actions = scripts(entry_actions_start, entry_actions_num);
+    let exit_actions = scripts(exit_actions_start, entry_actions_num);
 
-    let subaction_main_num = sizes.iter().find(|x| x.offset == subaction_main_start as usize).unwrap().size / 4; // divide by integer size
-    let subaction_main = script::scripts(parent_data.relative_fancy_slice(..), parent_data.relative_fancy_slice(subaction_main_start as usize ..), subaction_main_num, wii_memory);
-    let subaction_gfx = script::scripts(parent_data.relative_fancy_slice(..), parent_data.relative_fancy_slice(subaction_gfx_start as usize ..), subaction_main_num, wii_memory);
-    let subaction_sfx = script::scripts(parent_data.relative_fancy_slice(..), parent_data.relative_fancy_slice(subaction_sfx_start as usize ..), subaction_main_num, wii_memory);
-    let subaction_other = script::scripts(parent_data.relative_fancy_slice(..), parent_data.relative_fancy_slice(subaction_other_start as usize ..), subaction_main_num, wii_memory);
+    let subaction_main = scripts(subaction_main_start, subaction_main_num);
+    let subaction_gfx = scripts(subaction_gfx_start, subaction_main_num);
+    let subaction_sfx = scripts(subaction_sfx_start, subaction_main_num);
+    let subaction_other = scripts(subaction_other_start, subaction_main_num);
 
-    let attributes = fighter_attributes(parent_data.relative_fancy_slice(attribute_start as usize ..));
-    let misc = misc_section::misc_section(parent_data.relative_fancy_slice(misc_section_offset as usize ..), parent_data);
+    let attributes = if attribute_start == 0 {
+        fighter_attributes(FancySlice::new(&[0; FIGHTER_ATTRIBUTES_SIZE]))
+    } else {
+        fighter_attributes(parent_data.relative_fancy_slice(attribute_start as usize ..))
+    };
+    let misc = if misc_section_offset == 0 {
+        misc_section::MiscSection::empty()
+    } else {
+        misc_section::misc_section(parent_data.relative_fancy_slice(misc_section_offset as usize ..), parent_data)
+    };
 
     let entry_action_overrides = if entry_action_overrides_start != 0 {
-        action_overrides(parent_data.relative_fancy_slice(..), parent_data.relative_fancy_slice(entry_action_overrides_start as usize ..), wii_memory)
+        action_overrides(parent_data.relative_fancy_slice(..), parent_data.relative_fancy_slice(entry_action_overrides_start as usize ..), parser)
     } else {
         vec!()
     };
 
     let exit_action_overrides = if exit_action_overrides_start != 0 {
-        action_overrides(parent_data.relative_fancy_slice(..), parent_data.relative_fancy_slice(exit_action_overrides_start as usize ..), wii_memory)
+        action_overrides(parent_data.relative_fancy_slice(..), parent_data.relative_fancy_slice(exit_action_overrides_start as usize ..), parser)
     } else {
         vec!()
     };
@@ -104,6 +145,7 @@ pub(crate) fn arc_fighter_data(parent_data: FancySlice, data: FancySlice, wii_me
     }
 }
 
+const FIGHTER_ATTRIBUTES_SIZE: usize = 0x2e0;
 fn fighter_attributes(data: FancySlice) -> FighterAttributes {
     FighterAttributes {
         walk_init_vel:                     data.f32_be(0x00),
@@ -198,8 +240,9 @@ fn fighter_attributes(data: FancySlice) -> FighterAttributes {
     }
 }
 
-const _ARC_FIGHTER_DATA_HEADER_SIZE: usize = 0x7c;
+pub(crate) const ARC_FIGHTER_DATA_HEADER_SIZE: usize = 0x7c;
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct ArcFighterData {
     pub subaction_flags: Vec<SubactionFlags>,
     pub attributes: FighterAttributes,
@@ -229,7 +272,56 @@ pub struct ArcFighterData {
     flags2: i32,
 }
 
+impl ArcFighterData {
+    /// Returns the main, gfx, sfx and other scripts of the subaction at `index`
+    pub fn subaction_scripts(&self, index: usize) -> Option<SubactionScripts<'_>> {
+        Some(SubactionScripts {
+            main:  self.subaction_main.get(index)?,
+            gfx:   self.subaction_gfx.get(index)?,
+            sfx:   self.subaction_sfx.get(index)?,
+            other: self.subaction_other.get(index)?,
+        })
+    }
+}
+
+/// The four scripts that are run in parallel for every subaction
+#[derive(Clone, Debug)]
+pub struct SubactionScripts<'a> {
+    pub main:  &'a Script,
+    pub gfx:   &'a Script,
+    pub sfx:   &'a Script,
+    pub other: &'a Script,
+}
+
+impl<'a> SubactionScripts<'a> {
+    /// Returns each script paired with the collection it belongs to
+    pub fn iter(&self) -> impl Iterator<Item=(ScriptCollection, &'a Script)> {
+        vec!(
+            (ScriptCollection::Main,  self.main),
+            (ScriptCollection::Gfx,   self.gfx),
+            (ScriptCollection::Sfx,   self.sfx),
+            (ScriptCollection::Other, self.other),
+        ).into_iter()
+    }
+}
+
+/// Identifies which of the four subaction script collections a script belongs to
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum ScriptCollection {
+    Main,
+    Gfx,
+    Sfx,
+    Other,
+}
+
+impl ScriptCollection {
+    /// The collections in the order they are stored and run in
+    pub const ALL: [ScriptCollection; 4] = [ScriptCollection::Main, ScriptCollection::Gfx, ScriptCollection::Sfx, ScriptCollection::Other];
+}
+
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct FighterAttributes {
     pub walk_init_vel: f32,
     pub walk_acc: f32,
@@ -324,6 +416,7 @@ pub struct FighterAttributes {
 
 bitflags! {
     #[derive(Serialize)]
+    #[cfg_attr(feature = "serde-full", derive(Deserialize))]
     pub struct AnimationFlags: u8 {
         const NONE                      = 0x0;
         const NO_OUT_TRANSITION         = 0x1;
@@ -337,8 +430,26 @@ bitflags! {
     }
 }
 
-fn subaction_flags(parent_data: FancySlice, data: FancySlice, num: usize) -> Vec<SubactionFlags> {
-    let mut result = vec!();
+/// The subaction flags along with the problems found in the subaction names
+struct ParsedSubactionFlags {
+    flags:    Vec<SubactionFlags>,
+    warnings: Vec<String>,
+}
+
+/// A name read from the subaction name string table
+struct SubactionName {
+    /// Offset of the first byte of the name
+    start: usize,
+    /// Offset of the null terminator
+    end:   usize,
+    name:  String,
+}
+
+/// `named_count` is the number of subactions that have scripts, only those are expected to have a name.
+fn subaction_flags(parent_data: FancySlice, data: FancySlice, num: usize, named_count: usize) -> ParsedSubactionFlags {
+    let mut flags = vec!();
+    let mut names: Vec<SubactionName> = vec!();
+    let mut warnings = vec!();
     let num = num + 1;
     for i in 0..num {
         let in_translation_time = data.u8(i * SUB_ACTION_FLAGS_SIZE + 0);
@@ -348,22 +459,87 @@ fn subaction_flags(parent_data: FancySlice, data: FancySlice, num: usize) -> Vec
 
         let animation_flags = AnimationFlags::from_bits(animation_flags_int).unwrap();
         let name = if string_offset == 0 {
+            if i < named_count {
+                warnings.push(format!("Subaction 0x{:x} has no name", i));
+            }
             String::new()
         } else {
-            parent_data.str(string_offset as usize).unwrap().to_string()
+            match subaction_name(parent_data, string_offset) {
+                Ok(name) => {
+                    let text = name.name.clone();
+                    if text.contains(|c: char| c.is_control() || c == char::REPLACEMENT_CHARACTER) {
+                        warnings.push(format!("The name of subaction 0x{:x} contains bytes that are not text, it was read as {:?}", i, text));
+                    }
+                    names.push(name);
+                    text
+                }
+                Err(message) => {
+                    warnings.push(format!("Subaction 0x{:x}: {}", i, message));
+                    String::new()
+                }
+            }
         };
 
-        result.push(SubactionFlags {
+        flags.push(SubactionFlags {
             in_translation_time,
             animation_flags,
             name,
         });
     }
-    result
+
+    for (i, subaction) in flags.iter().enumerate() {
+        if subaction.name.is_empty() {
+            continue;
+        }
+        if let Some(first) = flags[..i].iter().position(|x| x.name == subaction.name) {
+            warnings.push(format!("Subaction 0x{:x} has the same name {:?} as subaction 0x{:x}, lookups by name find subaction 0x{:x}", i, subaction.name, first, first));
+        }
+    }
+
+    // Names are stored next to each other, so any string between the first and last name that no subaction uses is left over junk.
+    // Names can share bytes e.g. a name pointing into the middle of another name, those are not orphans.
+    if let (Some(table_start), Some(table_end)) = (names.iter().map(|x| x.start).min(), names.iter().map(|x| x.end).max()) {
+        let mut offset = table_start;
+        while offset < table_end {
+            if parent_data.u8(offset) == 0 {
+                offset += 1;
+                continue;
+            }
+            match names.iter().find(|x| x.start <= offset && offset < x.end) {
+                Some(name) => offset = name.end,
+                None => {
+                    let orphan = subaction_name(parent_data, offset as i32).unwrap();
+                    warnings.push(format!("The string {:?} at 0x{:x} is in the subaction names but no subaction uses it", orphan.name, offset));
+                    offset = orphan.end;
+                }
+            }
+        }
+    }
+
+    ParsedSubactionFlags { flags, warnings }
+}
+
+/// Reads the null terminated name at `offset`.
+/// Bytes that are not valid utf8 are replaced with U+FFFD.
+fn subaction_name(parent_data: FancySlice, offset: i32) -> Result<SubactionName, String> {
+    if offset < 0 || offset as usize >= parent_data.len() {
+        return Err(format!("The name offset 0x{:x} is outside of the moveset (size 0x{:x})", offset, parent_data.len()));
+    }
+    let start = offset as usize;
+    let bytes = parent_data.relative_slice(start..);
+    match bytes.iter().position(|x| *x == 0) {
+        Some(length) => Ok(SubactionName {
+            start,
+            end: start + length,
+            name: String::from_utf8_lossy(&bytes[..length]).into_owned(),
+        }),
+        None => Err(format!("The name at 0x{:x} is not terminated before the end of the moveset", offset)),
+    }
 }
 
 const SUB_ACTION_FLAGS_SIZE: usize = 0x8;
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct SubactionFlags {
     pub in_translation_time: u8,
     pub animation_flags:     AnimationFlags,
@@ -435,6 +611,7 @@ fn model_visibility(parent_data: FancySlice, model_visibility_start: i32) -> Mod
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct ModelVisibility {
     pub references: Vec<VisibilityReference>,
     pub defaults:   Vec<VisibilityDefault>,
@@ -442,17 +619,20 @@ pub struct ModelVisibility {
 
 const VISIBILITY_REFERENCE_SIZE: usize = 0x4;
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct VisibilityReference {
     pub bone_switches: Vec<VisibilityBoneSwitch>,
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct VisibilityBoneSwitch {
     pub groups: Vec<VisibilityGroup>,
 }
 
 /// Enabling a `VisibilityGroup` will disable all other groups in the same `VisibilityBoneSwitch`
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct VisibilityGroup {
     pub bones: Vec<i32>,
 }
@@ -461,6 +641,7 @@ const VISIBILITY_DEFAULT_SIZE: usize = 0x8;
 /// Enables the `VisibilityGroup` with the matching `switch_index` and `group_index` for all `VisibilityReferences`s.
 /// When a new subaction is started, everything is set invisible and then all `VisibilityDefault`s are run.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct VisibilityDefault {
     pub switch_index: i32,
     pub group_index: i32,
@@ -480,7 +661,10 @@ fn action_flags(data: FancySlice, num: usize) -> Vec<ActionFlags> {
 }
 
 const ACTION_FLAGS_SIZE: usize = 0x10;
-#[derive(Clone, Debug)]
+/// The flags entry of a fighter specific action.
+/// The meaning of the bits is undocumented, so the words are kept raw and individual bits are read with `bit`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct ActionFlags {
     pub flag1: u32,
     pub flag2: u32,
@@ -488,24 +672,63 @@ pub struct ActionFlags {
     pub flag4: u32,
 }
 
+impl ActionFlags {
+    pub fn words(&self) -> [u32; 4] {
+        [self.flag1, self.flag2, self.flag3, self.flag4]
+    }
+
+    /// Returns true when `bit` of the `word`th flags word is set.
+    /// `word` is 0 for `flag1` up to 3 for `flag4`, `bit` 0 is the least significant bit.
+    pub fn bit(&self, word: usize, bit: u32) -> bool {
+        self.words()[word] & (1 << bit) != 0
+    }
+
+    /// Returns every bit that is set differently in `other`, ordered by word and then by bit.
+    pub fn changed_bits(&self, other: &ActionFlags) -> Vec<ActionFlagsBitChange> {
+        let mut changes = vec!();
+        for (word, (old, new)) in self.words().iter().zip(other.words().iter()).enumerate() {
+            let changed = old ^ new;
+            for bit in 0..32 {
+                if changed & (1 << bit) != 0 {
+                    changes.push(ActionFlagsBitChange { word, bit, new_value: new & (1 << bit) != 0 });
+                }
+            }
+        }
+        changes
+    }
+}
+
+/// A single bit that differs between two `ActionFlags`.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct ActionFlagsBitChange {
+    /// 0 for `flag1` up to 3 for `flag4`
+    pub word: usize,
+    /// 0 is the least significant bit
+    pub bit: u32,
+    /// The value of the bit in the compared against flags
+    pub new_value: bool,
+}
+
 struct OffsetSizePair {
     offset: usize,
     size: usize,
 }
 
-fn get_sizes(data: FancySlice) -> Vec<OffsetSizePair> {
+/// Offsets outside of the moveset are ignored, `len` is the size of the moveset.
+fn get_sizes(data: FancySlice, len: usize) -> Vec<OffsetSizePair> {
     let mut pairs = vec!();
     for i in 0..27 {
-        let offset = data.i32_be(i * 4) as usize;
-        if offset != 0 {
-            pairs.push(OffsetSizePair { offset, size: 0 });
+        let offset = data.i32_be(i * 4);
+        if offset > 0 && (offset as usize) < len {
+            pairs.push(OffsetSizePair { offset: offset as usize, size: 0 });
         }
     }
 
     pairs.sort_by_key(|x| x.offset);
 
     // fill in size for most elements
-    for i in 0..pairs.len()-1 {
+    for i in 0..pairs.len().saturating_sub(1) {
         pairs[i].size = pairs[i + 1].offset - pairs[i].offset
     }
 
@@ -515,12 +738,12 @@ fn get_sizes(data: FancySlice) -> Vec<OffsetSizePair> {
     pairs
 }
 
-fn action_overrides(parent_data: FancySlice, data: FancySlice, wii_memory: &WiiMemory) -> Vec<ActionOverride> {
+fn action_overrides(parent_data: FancySlice, data: FancySlice, parser: &mut ScriptParser) -> Vec<ActionOverride> {
     let mut overrides = vec!();
     for i in 0..10 {
         let action_id = data.u32_be(i * OVERRIDE_SIZE);
         let offset = data.u32_be(i * OVERRIDE_SIZE + 4);
-        let script = script::new_script(parent_data.relative_fancy_slice(..), offset, wii_memory);
+        let script = parser.script(parent_data.relative_fancy_slice(..), offset);
 
         if action_id == !0u32 {
             break;
@@ -532,7 +755,113 @@ fn action_overrides(parent_data: FancySlice, data: FancySlice, wii_memory: &WiiM
 
 const OVERRIDE_SIZE: usize = 0x8;
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct ActionOverride {
     pub action_id: u32,
     pub script: Script,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates subaction flags pointing at `name_offsets` followed by the string table `names`, which starts at 0x40.
+    /// Returns the parsed flags
+    fn parse_names(name_offsets: &[i32], names: &[u8]) -> ParsedSubactionFlags {
+        let mut data = vec!();
+        for offset in name_offsets {
+            data.extend(&[0; 4]);
+            data.extend(&offset.to_be_bytes());
+        }
+        data.resize(0x40, 0);
+        data.extend(names);
+        // the parser reads one entry more than the given number
+        subaction_flags(FancySlice::new(&data), FancySlice::new(&data), name_offsets.len() - 1, name_offsets.len())
+    }
+
+    fn names(parsed: &ParsedSubactionFlags) -> Vec<&str> {
+        parsed.flags.iter().map(|x| x.name.as_str()).collect()
+    }
+
+    #[test]
+    fn valid_subaction_names() {
+        let parsed = parse_names(&[0x40, 0x46, 0x4B], b"Wait1\0Walk\0Run\0");
+        assert_eq!(names(&parsed), vec!("Wait1", "Walk", "Run"));
+        assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+
+        // a name that is the end of another name is not an orphan
+        let parsed = parse_names(&[0x40, 0x44], b"Wait1\0");
+        assert_eq!(names(&parsed), vec!("Wait1", "1"));
+        assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+    }
+
+    #[test]
+    fn duplicate_subaction_name() {
+        let parsed = parse_names(&[0x40, 0x46, 0x4B], b"Wait1\0Walk\0Wait1\0");
+        assert_eq!(names(&parsed), vec!("Wait1", "Walk", "Wait1"));
+        assert_eq!(parsed.warnings, vec!(
+            "Subaction 0x2 has the same name \"Wait1\" as subaction 0x0, lookups by name find subaction 0x0".to_string()
+        ));
+    }
+
+    #[test]
+    fn out_of_range_subaction_name() {
+        let parsed = parse_names(&[0x40, 0x1000, -4, 0], b"Wait1\0");
+        assert_eq!(names(&parsed), vec!("Wait1", "", "", ""));
+        assert_eq!(parsed.warnings, vec!(
+            "Subaction 0x1: The name offset 0x1000 is outside of the moveset (size 0x46)".to_string(),
+            "Subaction 0x2: The name offset 0xfffffffc is outside of the moveset (size 0x46)".to_string(),
+            "Subaction 0x3 has no name".to_string(),
+        ));
+
+        // The string runs off the end of the moveset
+        let parsed = parse_names(&[0x40], b"Wait1");
+        assert_eq!(names(&parsed), vec!(""));
+        assert_eq!(parsed.warnings, vec!("Subaction 0x0: The name at 0x40 is not terminated before the end of the moveset".to_string()));
+    }
+
+    #[test]
+    fn non_utf8_subaction_name() {
+        let parsed = parse_names(&[0x40, 0x46], b"Wa\xFFt1\0Walk\0");
+        assert_eq!(names(&parsed), vec!("Wa\u{FFFD}t1", "Walk"));
+        assert_eq!(parsed.warnings, vec!(
+            "The name of subaction 0x0 contains bytes that are not text, it was read as \"Wa\u{FFFD}t1\"".to_string()
+        ));
+    }
+
+    #[test]
+    fn orphaned_subaction_name() {
+        let parsed = parse_names(&[0x40, 0x50], b"Wait1\0\0Junk\0\0\0\0\0Walk\0");
+        assert_eq!(names(&parsed), vec!("Wait1", "Walk"));
+        assert_eq!(parsed.warnings, vec!(
+            "The string \"Junk\" at 0x47 is in the subaction names but no subaction uses it".to_string()
+        ));
+    }
+
+    #[test]
+    fn parse_action_flags() {
+        let data: Vec<u8> = vec!(
+            0x00, 0x00, 0x00, 0x01, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x12, 0x34, 0x56, 0x78,
+            0x00, 0x00, 0x00, 0x03, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x12, 0x34, 0x56, 0x78,
+        );
+        let flags = action_flags(FancySlice::new(&data), 2);
+        assert_eq!(flags[0].words(), [0x1, 0x80000000, 0x0, 0x12345678]);
+
+        assert!(flags[0].bit(0, 0));
+        assert!(!flags[0].bit(0, 1));
+        assert!(flags[1].bit(0, 1));
+        assert!(flags[0].bit(1, 31));
+        assert!(!flags[0].bit(2, 8));
+        assert!(flags[1].bit(2, 8));
+
+        assert_eq!(flags[0].changed_bits(&flags[0]), vec!());
+        assert_eq!(flags[0].changed_bits(&flags[1]), vec!(
+            ActionFlagsBitChange { word: 0, bit: 1, new_value: true },
+            ActionFlagsBitChange { word: 2, bit: 8, new_value: true },
+        ));
+        assert_eq!(flags[1].changed_bits(&flags[0]), vec!(
+            ActionFlagsBitChange { word: 0, bit: 1, new_value: false },
+            ActionFlagsBitChange { word: 2, bit: 8, new_value: false },
+        ));
+    }
+}
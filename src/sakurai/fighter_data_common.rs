@@ -1,11 +1,27 @@
 use fancy_slice::FancySlice;
 
-use crate::script::Script;
-use crate::script;
+use crate::sakurai::ParseWarning;
+use crate::script::{Script, ScriptParser};
 use crate::util;
-use crate::wii_memory::WiiMemory;
 
-pub(crate) fn arc_fighter_data_common(parent_data: FancySlice, data: FancySlice, wii_memory: &WiiMemory) -> ArcFighterDataCommon {
+/// `header_size` is the space available for the header, None when it cannot be determined.
+///
+/// When the header size does not match any known revision a `ParseWarning` is reported and the header is parsed with the layout of the closest header size.
+pub(crate) fn arc_fighter_data_common(parent_data: FancySlice, data: FancySlice, header_size: Option<usize>, parser: &mut ScriptParser, warnings: &mut Vec<ParseWarning>) -> ArcFighterDataCommon {
+    let (layout, revision) = match header_size {
+        Some(header_size) => match detect_layout(LAYOUTS, header_size) {
+            Some(layout) => (layout, layout.revision),
+            None => {
+                let layout = closest_layout(LAYOUTS, header_size);
+                let message = format!("the header size 0x{:x} does not match any known Fighter.pac revision, parsed it with the 0x{:x} byte header of {:?}", header_size, layout.header_size, layout.revision);
+                error!("dataCommon: {}", message);
+                warnings.push(ParseWarning { section: "dataCommon".into(), message, script_offset: None });
+                (layout, FormatRevision::Unknown { header_size })
+            }
+        }
+        None => (&LAYOUTS[0], LAYOUTS[0].revision),
+    };
+
     let global_ics           = data.i32_be(0x00);
     let global_ics_sse       = data.i32_be(0x04);
     let ics                  = data.i32_be(0x08);
@@ -33,11 +49,11 @@ pub(crate) fn arc_fighter_data_common(parent_data: FancySlice, data: FancySlice,
     let unk15                = data.i32_be(0x60);
     let unk16                = data.i32_be(0x64);
 
-    let sizes = get_sizes(data);
+    let sizes = get_sizes(data, layout);
 
     let entry_actions_num = sizes.iter().find(|x| x.offset == entry_actions_start as usize).unwrap().size / 4; // divide by integer size
-    let entry_actions = script::scripts(parent_data, parent_data.relative_fancy_slice(entry_actions_start as usize ..), entry_actions_num, wii_memory);
-    let exit_actions = script::scripts(parent_data, parent_data.relative_fancy_slice(exit_actions_start as usize ..), entry_actions_num, wii_memory);
+    let entry_actions = parser.scripts(parent_data, parent_data.relative_fancy_slice(entry_actions_start as usize ..), entry_actions_num);
+    let exit_actions = parser.scripts(parent_data, parent_data.relative_fancy_slice(exit_actions_start as usize ..), entry_actions_num);
 
     let leg_bones_left_list = util::list_offset(parent_data.relative_fancy_slice(leg_bones as usize..));
     let mut leg_bones_left = vec!();
@@ -54,6 +70,7 @@ pub(crate) fn arc_fighter_data_common(parent_data: FancySlice, data: FancySlice,
     }
 
     ArcFighterDataCommon {
+        revision,
         global_ics,
         global_ics_sse,
         ics,
@@ -84,9 +101,56 @@ pub(crate) fn arc_fighter_data_common(parent_data: FancySlice, data: FancySlice,
     }
 }
 
-const _ARC_FIGHTER_DATA_COMMON_HEADER_SIZE: usize = 0x68;
+/// The number of bytes read as the header, when `header_size` bytes are available for it.
+/// All of them are claimed by the header when they do not match a known revision.
+pub(crate) fn read_header_size(header_size: Option<usize>) -> usize {
+    match header_size {
+        Some(header_size) => detect_layout(LAYOUTS, header_size).map(|x| x.header_size).unwrap_or(header_size),
+        None => LAYOUTS[0].header_size,
+    }
+}
+
+/// Returns the layout with a header of exactly `header_size` bytes
+fn detect_layout(layouts: &'static [Layout], header_size: usize) -> Option<&'static Layout> {
+    layouts.iter().find(|x| x.header_size == header_size)
+}
+
+/// Returns the layout whose header size is closest to `header_size`, the earlier layout on a tie
+fn closest_layout(layouts: &'static [Layout], header_size: usize) -> &'static Layout {
+    layouts.iter().min_by_key(|x| (x.header_size as isize - header_size as isize).abs()).unwrap()
+}
+
+/// The layout of the dataCommon header used by a revision of the game
+struct Layout {
+    revision: FormatRevision,
+    header_size: usize,
+}
+
+/// Every known layout, the first is used when the header size cannot be determined.
+///
+/// Only add layouts that have been checked against a dump of that revision,
+/// a header of any other size is reported as a `ParseWarning` and parsed with the closest layout.
+const LAYOUTS: &[Layout] = &[
+    Layout { revision: FormatRevision::Retail, header_size: 0x68 },
+];
+
+/// The revision of the common Fighter.pac format, identified by the size of the dataCommon header.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+#[non_exhaustive]
+pub enum FormatRevision {
+    /// The 0x68 byte header parsed by brawllib and BrawlBox
+    Retail,
+    /// A header size that does not match any known revision.
+    /// The header was parsed with the layout of the known revision with the closest header size, so its values may be wrong.
+    Unknown { header_size: usize },
+}
+
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct ArcFighterDataCommon {
+    /// The layout the header was parsed with
+    pub revision: FormatRevision,
     pub global_ics: i32,
     pub global_ics_sse: i32,
     pub ics: i32,
@@ -121,9 +185,9 @@ struct OffsetSizePair {
     size: usize,
 }
 
-fn get_sizes(data: FancySlice) -> Vec<OffsetSizePair> {
+fn get_sizes(data: FancySlice, layout: &Layout) -> Vec<OffsetSizePair> {
     let mut pairs = vec!();
-    for i in 0..26 {
+    for i in 0..layout.header_size / 4 {
         let offset = data.i32_be(i * 4) as usize;
         if offset != 0 {
             pairs.push(OffsetSizePair { offset, size: 0 });
@@ -145,3 +209,38 @@ fn get_sizes(data: FancySlice) -> Vec<OffsetSizePair> {
 
     pairs
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Retail and a stand-in for a second revision with a smaller header
+    const TWO_LAYOUTS: &[Layout] = &[
+        Layout { revision: FormatRevision::Retail, header_size: 0x68 },
+        Layout { revision: FormatRevision::Unknown { header_size: 0x60 }, header_size: 0x60 },
+    ];
+
+    #[test]
+    fn detect_layout_exact_size() {
+        assert_eq!(detect_layout(TWO_LAYOUTS, 0x68).map(|x| x.revision), Some(FormatRevision::Retail));
+        assert_eq!(detect_layout(TWO_LAYOUTS, 0x60).map(|x| x.revision), Some(FormatRevision::Unknown { header_size: 0x60 }));
+        assert!(detect_layout(TWO_LAYOUTS, 0x64).is_none());
+        assert!(detect_layout(LAYOUTS, 0x60).is_none());
+    }
+
+    #[test]
+    fn closest_layout_header_size() {
+        assert_eq!(closest_layout(TWO_LAYOUTS, 0x70).revision, FormatRevision::Retail);
+        assert_eq!(closest_layout(TWO_LAYOUTS, 0x5c).revision, FormatRevision::Unknown { header_size: 0x60 });
+        // a tie picks the earlier layout
+        assert_eq!(closest_layout(TWO_LAYOUTS, 0x64).revision, FormatRevision::Retail);
+        assert_eq!(closest_layout(LAYOUTS, 0x40).revision, FormatRevision::Retail);
+    }
+
+    #[test]
+    fn read_header_size_unknown_revision() {
+        assert_eq!(read_header_size(Some(0x68)), 0x68);
+        assert_eq!(read_header_size(None), 0x68);
+        assert_eq!(read_header_size(Some(0x70)), 0x70);
+    }
+}
@@ -8,5 +8,6 @@ pub(crate) fn arc_item_data(_parent_data: FancySlice, _data: FancySlice, _wii_me
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct ArcItemData {
 }
@@ -2,8 +2,9 @@ pub mod fighter_data;
 pub mod fighter_data_common;
 pub mod item_data;
 
-use crate::script::Script;
+use crate::script::{DataSource, EventBudget, Script, ScriptParser};
 use crate::script;
+use crate::space_report::script_byte_ranges;
 use crate::wii_memory::WiiMemory;
 
 use fighter_data::ArcFighterData;
@@ -12,7 +13,12 @@ use item_data::ArcItemData;
 
 use fancy_slice::FancySlice;
 
-pub(crate) fn arc_sakurai(data: FancySlice, wii_memory: &WiiMemory, item: bool) -> ArcSakurai {
+use std::fmt;
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// `budget` is shared with the other files of the fighter the moveset belongs to.
+pub(crate) fn arc_sakurai(data: FancySlice, wii_memory: &WiiMemory, budget: &mut EventBudget, item: bool) -> ArcSakurai {
     let size                      = data.i32_be(0x00);
     let lookup_entry_offset       = data.i32_be(0x04);
     let lookup_entry_count        = data.i32_be(0x08);
@@ -33,74 +39,94 @@ pub(crate) fn arc_sakurai(data: FancySlice, wii_memory: &WiiMemory, item: bool)
         lookup_entries.push(entry_offset);
     }
 
-    let mut external_subroutines = vec!();
-    for i in 0..external_subroutine_count {
-        let mut offsets = vec!();
-        let offset = external_subroutines_offset + i as usize * EXTERNAL_SUBROUTINE_SIZE;
-        let mut offset_linked_list = data.i32_be(offset);
-        let string_offset = data.i32_be(offset + 4);
-        let name = data.str(string_table_offset + string_offset as usize).unwrap().to_string();
-
-        // The offset_linked_list is a pointer to the offset argument used by a subroutine/goto call that is making an external call.
-        // However since the value in subroutine/goto offset argument has no purpose as its an external call, it is instead used to point to another value subroutine/goto offset argument.
-        // This forms a linked list between all the subroutine/goto offset arguments that make the same external call.
-        while offset_linked_list > 0 && offset_linked_list < size {
-            offsets.push(offset_linked_list);
-            offset_linked_list = data.i32_be(ARC_SAKURAI_HEADER_SIZE + offset_linked_list as usize);
-        }
+    let section_data_offsets: Vec<u32> = (0..section_count)
+        .map(|i| data.u32_be(sections_offset + i as usize * ARC_SAKURAI_SECTION_HEADER_SIZE))
+        .collect();
 
-        external_subroutines.push(ExternalSubroutine { name, offsets });
+    // scripts are stored in the data before the lookup entries
+    let mut parser = ScriptParser::new(wii_memory, budget);
+    if lookup_entry_offset > 0 {
+        parser = parser.with_data_end(lookup_entry_offset as usize);
     }
-
+    let mut warnings = vec!();
+    let mut section_overlaps = vec!();
     let mut sections = vec!();
     for i in 0..section_count {
         let offset = sections_offset + i as usize * ARC_SAKURAI_SECTION_HEADER_SIZE;
         let data_offset   = data.u32_be(offset);
         let string_offset = data.i32_be(offset + 4);
         let name = data.str(string_table_offset + string_offset as usize).unwrap().to_string();
+        phase_span!("sakurai_section", section = %name, offset = data_offset);
+
+        if data_offset as usize >= parent_data.len() {
+            let message = format!("The offset 0x{:x} is outside of the moveset (size 0x{:x}), skipped the section", data_offset, parent_data.len());
+            error!("{}: {}", name, message);
+            warnings.push(ParseWarning { section: name.clone(), message, script_offset: None });
+            sections.push(ArcSakuraiSection { name, range: None, data: SectionData::None });
+            continue;
+        }
+
+        let script_section = is_script_section(&name);
+        let header_size = section_header_size(data_offset, &section_data_offsets, lookup_entry_offset as u32);
+        let size = match name.as_str() {
+            _ if script_section => script::script_size(parent_data, data_offset),
+            "data" if !item     => Some(fighter_data::ARC_FIGHTER_DATA_HEADER_SIZE as u32),
+            "dataCommon"        => Some(fighter_data_common::read_header_size(header_size) as u32),
+            _                   => None,
+        };
+        let range = size.map(|size| data_offset .. data_offset + size);
+
+        if let Some(overlap) = range.as_ref().and_then(|range| section_overlap(&sections, &name, range)) {
+            let message = overlap.to_string();
+            error!("{}: {}", name, message);
+            warnings.push(ParseWarning { section: name.clone(), message, script_offset: None });
+            section_overlaps.push(overlap);
+            sections.push(ArcSakuraiSection { name, range, data: SectionData::None });
+            continue;
+        }
 
         let data = data.relative_fancy_slice(ARC_SAKURAI_HEADER_SIZE + data_offset as usize..);
         let mut section_data = match name.as_str() {
             "data" if item => SectionData::ItemData(item_data::arc_item_data(parent_data, data, wii_memory)),
-            "data"         => SectionData::FighterData(fighter_data::arc_fighter_data(parent_data, data, wii_memory)),
-            "dataCommon"   => SectionData::FighterDataCommon(fighter_data_common::arc_fighter_data_common(parent_data, data, wii_memory)),
+            "data"         => SectionData::FighterData(fighter_data::arc_fighter_data(parent_data, data, &mut parser, &mut warnings)),
+            "dataCommon"   => SectionData::FighterDataCommon(fighter_data_common::arc_fighter_data_common(parent_data, data, header_size, &mut parser, &mut warnings)),
             _              => SectionData::None
         };
 
-        if name.starts_with("gameAnimCmd_") || name.starts_with("effectAnimCmd_") || name.starts_with("statusAnimCmdGroup_") || name.starts_with("statusAnimCmdPre_") {
+        if script_section {
             section_data = SectionData::Script(SectionScript {
                 name:   name.clone(),
-                script: script::new_script(parent_data.relative_fancy_slice(..), data_offset, wii_memory),
+                script: parser.script(parent_data.relative_fancy_slice(..), data_offset),
             });
         }
-        sections.push(ArcSakuraiSection { name, data: section_data });
+        warnings.extend(parser.take_warnings(&name));
+        sections.push(ArcSakuraiSection { name, range, data: section_data });
     }
 
     // locate all script fragments called by subroutines etc.
-    let mut all_scripts = vec!();
-    let mut all_scripts_sub = vec!();
+    let mut all_scripts: Vec<&Script> = vec!();
     for section in &sections {
         match &section.data {
             SectionData::FighterData(data) => {
-                all_scripts.push(data.entry_actions.as_slice());
-                all_scripts.push(data.exit_actions.as_slice());
-                all_scripts.push(data.subaction_main.as_slice());
-                all_scripts.push(data.subaction_gfx.as_slice());
-                all_scripts.push(data.subaction_sfx.as_slice());
-                all_scripts.push(data.subaction_other.as_slice());
+                all_scripts.extend(data.entry_actions.iter());
+                all_scripts.extend(data.exit_actions.iter());
+                all_scripts.extend(data.subaction_main.iter());
+                all_scripts.extend(data.subaction_gfx.iter());
+                all_scripts.extend(data.subaction_sfx.iter());
+                all_scripts.extend(data.subaction_other.iter());
                 for override_script in &data.entry_action_overrides {
-                    all_scripts_sub.push(override_script.script.clone());
+                    all_scripts.push(&override_script.script);
                 }
                 for override_script in &data.exit_action_overrides {
-                    all_scripts_sub.push(override_script.script.clone());
+                    all_scripts.push(&override_script.script);
                 }
             }
             SectionData::FighterDataCommon(data_common) => {
-                all_scripts.push(data_common.entry_actions.as_slice());
-                all_scripts.push(data_common.exit_actions.as_slice());
+                all_scripts.extend(data_common.entry_actions.iter());
+                all_scripts.extend(data_common.exit_actions.iter());
             }
             SectionData::Script(script) => {
-                all_scripts_sub.push(script.script.clone());
+                all_scripts.push(&script.script);
             }
             SectionData::ItemData(_item_data) => {
                 // TODO
@@ -108,33 +134,256 @@ pub(crate) fn arc_sakurai(data: FancySlice, wii_memory: &WiiMemory, item: bool)
             _ => { }
         }
     }
-    all_scripts.push(all_scripts_sub.as_slice());
+
+    let appended_region = appended_region(size, parent_data.relative_slice(..), &all_scripts);
+    if let Some(region) = &appended_region {
+        info!("The moveset has data appended by legacy PSA at 0x{:x}..0x{:x}", region.start, region.end);
+    }
+    let data_end = match &appended_region {
+        Some(region) => region.end,
+        None         => size,
+    };
+
+    let mut external_subroutines = vec!();
+    for i in 0..external_subroutine_count {
+        let mut offsets = vec!();
+        let mut visited = HashSet::new();
+        let offset = external_subroutines_offset + i as usize * EXTERNAL_SUBROUTINE_SIZE;
+        let mut offset_linked_list = data.i32_be(offset);
+        let string_offset = data.i32_be(offset + 4);
+        let name = data.str(string_table_offset + string_offset as usize).unwrap().to_string();
+
+        // The offset_linked_list is a pointer to the offset argument used by a subroutine/goto call that is making an external call.
+        // However since the value in subroutine/goto offset argument has no purpose as its an external call, it is instead used to point to another value subroutine/goto offset argument.
+        // This forms a linked list between all the subroutine/goto offset arguments that make the same external call.
+        // A modified moveset may contain a list that loops back on itself, which would otherwise never end.
+        while offset_linked_list > 0 && offset_linked_list < data_end {
+            if !visited.insert(offset_linked_list) {
+                warnings.push(ParseWarning {
+                    section:       name.clone(),
+                    message:       format!("The calls to the external subroutine {} loop back to the call at 0x{:x}", name, offset_linked_list),
+                    script_offset: None,
+                });
+                break;
+            }
+            offsets.push(offset_linked_list);
+            offset_linked_list = data.i32_be(ARC_SAKURAI_HEADER_SIZE + offset_linked_list as usize);
+        }
+
+        external_subroutines.push(ExternalSubroutine { name, offsets });
+    }
 
     let ignore_origins: Vec<_> = external_subroutines.iter().flat_map(|x| x.offsets.iter().cloned()).collect();
-    let mut fragment_scripts = script::fragment_scripts(parent_data.relative_fancy_slice(..), all_scripts.as_slice(), ignore_origins.as_slice(), wii_memory);
-    fragment_scripts.sort_by_key(|x| x.offset);
+    let fragment_scripts = script::fragment_scripts(parent_data.relative_fancy_slice(..), all_scripts.as_slice(), ignore_origins.as_slice(), &mut parser);
+    warnings.extend(parser.take_warnings("fragment"));
+
+    ArcSakurai { lookup_entries, sections, section_overlaps, external_subroutines, fragment_scripts, appended_region, warnings }
+}
+
+/// The legacy PSA tool writes new scripts past the end of the original moveset data and updates the offsets to point at them,
+/// but leaves the size in the header unchanged.
+/// Returns the offsets of the appended data when a script is stored past the size in the header.
+/// The region ends at the end of the last event or argument of the appended scripts,
+/// `parent_data` may continue past the appended data e.g. with the padding or children following the moveset data.
+/// `size` is the size from the header, `parent_data` is the data after the header.
+fn appended_region(size: i32, parent_data: &[u8], scripts: &[&Script]) -> Option<Range<i32>> {
+    let start = size - ARC_SAKURAI_HEADER_SIZE as i32;
+    if start <= 0 || start as usize >= parent_data.len() {
+        return None;
+    }
+
+    let mut ranges = vec!();
+    for script in scripts {
+        if matches!(script.source, DataSource::File { .. }) && script.offset >= start {
+            script_byte_ranges(parent_data, script.offset, &mut ranges);
+        }
+    }
+    let end = ranges.iter().map(|x| x.end).max()?;
+    Some(start .. end as i32)
+}
+
+fn is_script_section(name: &str) -> bool {
+    name.starts_with("gameAnimCmd_") || name.starts_with("effectAnimCmd_") || name.starts_with("statusAnimCmdGroup_") || name.starts_with("statusAnimCmdPre_")
+}
+
+/// Returns the overlap between `range` of the section `name` and the first section in `sections` that it overlaps.
+/// Skipped sections are not checked, so the section declared first keeps the overlapping bytes.
+///
+/// Script sections starting at the same offset share a single script, this is not an overlap as both read the bytes the same way.
+fn section_overlap(sections: &[ArcSakuraiSection], name: &str, range: &Range<u32>) -> Option<SectionOverlap> {
+    sections.iter()
+        .filter(|x| !matches!(x.data, SectionData::None))
+        .filter_map(|first| {
+            let first_range = first.range.clone()?;
+            if first_range.start == range.start && is_script_section(&first.name) && is_script_section(name) {
+                return None;
+            }
+            let overlap = first_range.start.max(range.start) .. first_range.end.min(range.end);
+            if overlap.start < overlap.end {
+                Some(SectionOverlap {
+                    first:        first.name.clone(),
+                    first_range,
+                    second:       name.to_string(),
+                    second_range: range.clone(),
+                    overlap,
+                })
+            } else {
+                None
+            }
+        })
+        .next()
+}
 
-    ArcSakurai { lookup_entries, sections, external_subroutines, fragment_scripts }
+/// The space between a sections data and whatever follows it: the next section or the lookup entries at the end of the data.
+/// None when nothing follows it.
+fn section_header_size(data_offset: u32, section_data_offsets: &[u32], lookup_entry_offset: u32) -> Option<usize> {
+    section_data_offsets.iter()
+        .cloned()
+        .chain(std::iter::once(lookup_entry_offset))
+        .filter(|x| *x > data_offset)
+        .min()
+        .map(|x| (x - data_offset) as usize)
 }
 
 const ARC_SAKURAI_HEADER_SIZE: usize = 0x20;
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct ArcSakurai {
     lookup_entries:           Vec<i32>,
     pub sections:             Vec<ArcSakuraiSection>,
+    /// Sections that were skipped because they overlap a section declared before them, each is also reported in `warnings`.
+    pub section_overlaps:     Vec<SectionOverlap>,
     pub external_subroutines: Vec<ExternalSubroutine>,
+    /// Scripts called by Goto's, Subroutines etc. that are not otherwise referenced, sorted by offset.
     pub fragment_scripts:     Vec<Script>,
+    /// Offsets of the data appended past the size in the header by the legacy PSA tool, None for movesets without appended data.
+    /// The appended data is parsed the same as the rest of the moveset.
+    pub appended_region:      Option<Range<i32>>,
+    /// Problems that were worked around while parsing, the affected data is missing or incomplete.
+    pub warnings:             Vec<ParseWarning>,
+}
+
+impl ArcSakurai {
+    /// Returns the fragment script at `offset`
+    pub fn fragment_script(&self, offset: i32) -> Option<&Script> {
+        self.fragment_scripts.binary_search_by_key(&offset, |x| x.offset).ok().map(|i| &self.fragment_scripts[i])
+    }
+
+    /// Returns every script that was read from RAM written by the WiiRD codeset instead of from the moveset file.
+    pub fn memory_sections(&self) -> Vec<MemorySection> {
+        fn table<'a>(scripts: &mut Vec<(String, &'a Script)>, name: &str, table: &'a [Script]) {
+            scripts.extend(table.iter().enumerate().map(|(i, script)| (format!("{}[0x{:x}]", name, i), script)));
+        }
+
+        let mut scripts: Vec<(String, &Script)> = vec!();
+        for section in &self.sections {
+            match &section.data {
+                SectionData::FighterData(data) => {
+                    table(&mut scripts, "entry_actions",   &data.entry_actions);
+                    table(&mut scripts, "exit_actions",    &data.exit_actions);
+                    table(&mut scripts, "subaction_main",  &data.subaction_main);
+                    table(&mut scripts, "subaction_gfx",   &data.subaction_gfx);
+                    table(&mut scripts, "subaction_sfx",   &data.subaction_sfx);
+                    table(&mut scripts, "subaction_other", &data.subaction_other);
+                    for override_script in &data.entry_action_overrides {
+                        scripts.push((format!("entry_action_overrides[0x{:x}]", override_script.action_id), &override_script.script));
+                    }
+                    for override_script in &data.exit_action_overrides {
+                        scripts.push((format!("exit_action_overrides[0x{:x}]", override_script.action_id), &override_script.script));
+                    }
+                }
+                SectionData::FighterDataCommon(data_common) => {
+                    table(&mut scripts, "entry_actions", &data_common.entry_actions);
+                    table(&mut scripts, "exit_actions",  &data_common.exit_actions);
+                }
+                SectionData::Script(script) => {
+                    scripts.push((script.name.clone(), &script.script));
+                }
+                _ => { }
+            }
+        }
+        for script in &self.fragment_scripts {
+            scripts.push((String::from("fragment"), script));
+        }
+
+        scripts.into_iter().filter_map(|(location, script)| match script.source {
+            DataSource::WiiMemory { address, code_offset } => Some(MemorySection { location, address, code_offset }),
+            DataSource::File { .. } => None,
+        }).collect()
+    }
+}
+
+/// A problem found while parsing a moveset, that was recovered from by skipping the offending data.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
+pub struct ParseWarning {
+    /// The name of the section containing the problem
+    pub section: String,
+    pub message: String,
+    /// The offset of the script that was truncated, None when the problem is not in a script
+    pub script_offset: Option<i32>,
+}
+
+/// Two sections of the section table claiming the same bytes of the moveset, usually caused by hand editing the offsets of a mod.
+///
+/// The section declared first is parsed and the second is skipped.
+/// When the second section starts before the first it was declared out of order, which the message points out as the likely edit that broke the table.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct SectionOverlap {
+    /// The name of the section that was parsed
+    pub first:        String,
+    pub first_range:  Range<u32>,
+    /// The name of the section that was skipped
+    pub second:       String,
+    pub second_range: Range<u32>,
+    /// The bytes claimed by both sections
+    pub overlap:      Range<u32>,
+}
+
+impl SectionOverlap {
+    /// The second section was declared after the first but starts before it
+    pub fn out_of_order(&self) -> bool {
+        self.second_range.start < self.first_range.start
+    }
+}
+
+impl fmt::Display for SectionOverlap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The data 0x{:x}..0x{:x} overlaps 0x{:x}..0x{:x} of the section {} declared before it", self.second_range.start, self.second_range.end, self.overlap.start, self.overlap.end, self.first)?;
+        if self.out_of_order() {
+            write!(f, " (declared out of order, it starts before 0x{:x})", self.first_range.start)?;
+        }
+        write!(f, ", skipped the section")
+    }
+}
+
+/// A script that a WiiRD code redirected to RAM, see `ArcSakurai::memory_sections`
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct MemorySection {
+    /// Where the script is referenced from e.g. `subaction_main[0x1f]` or `fragment`
+    pub location: String,
+    /// The RAM address the script was read from
+    pub address: u32,
+    /// Byte offset within the codeset of the last code that wrote to `address`, None if no code wrote to it
+    pub code_offset: Option<usize>,
 }
 
 const ARC_SAKURAI_SECTION_HEADER_SIZE: usize = 0x8;
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct ArcSakuraiSection {
     /// TODO: Remove this field when all SectionData's are implemented
     pub name: String,
+    /// The bytes of the moveset read as the sections header or script, checked for overlaps with the other sections.
+    /// None when the section was skipped for being outside of the moveset or the size of its header is unknown.
+    pub range: Option<Range<u32>>,
     pub data: SectionData,
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub enum SectionData {
     ItemData (ArcItemData),
     FighterData (ArcFighterData),
@@ -144,6 +393,7 @@ pub enum SectionData {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct SectionScript {
     pub name:   String,
     pub script: Script,
@@ -151,7 +401,329 @@ pub struct SectionScript {
 
 const EXTERNAL_SUBROUTINE_SIZE: usize = 0x8;
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct ExternalSubroutine {
     pub name: String,
     pub offsets: Vec<i32>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fighter_data_common::FormatRevision;
+    use crate::script::ScriptLimits;
+
+    fn push_u32(data: &mut Vec<u8>, value: u32) {
+        data.extend_from_slice(&value.to_be_bytes());
+    }
+
+    #[test]
+    fn legacy_psa_appended_script() {
+        use crate::test_util::{TestFighterBuilder, damage};
+
+        let builder = TestFighterBuilder::new("Test")
+            .subaction("Wait1", |s| s.sync_timer(1.0))
+            .appended_subaction("AttackS3S", |s| s.hitbox(damage(7).id(2)));
+        let fighter = builder.build();
+
+        let sakurai = fighter.get_fighter_sakurai().unwrap();
+        let region = sakurai.appended_region.clone().unwrap();
+        let fighter_data = fighter.get_fighter_data().unwrap();
+        let appended = &fighter_data.subaction_main[1];
+        assert!(region.contains(&appended.offset));
+        assert_eq!(appended.events.len(), 1);
+        assert_eq!((appended.events[0].namespace, appended.events[0].code), (0x06, 0x00));
+        assert_eq!(appended.events[0].arguments[0], script::Argument::Value (2));
+        assert_eq!(appended.events[0].arguments[1], script::Argument::Value (7));
+        assert_eq!(fighter_data.subaction_main[0].events.len(), 1);
+
+        // the size in the header does not include the appended script
+        let pac = builder.moveset_pac();
+        let size = u32::from_be_bytes([pac[0x60], pac[0x61], pac[0x62], pac[0x63]]) as i32;
+        assert_eq!(region.start, size - ARC_SAKURAI_HEADER_SIZE as i32);
+
+        let standard = TestFighterBuilder::new("Test").subaction("AttackS3S", |s| s.hitbox(damage(7))).build();
+        assert_eq!(standard.get_fighter_sakurai().unwrap().appended_region, None);
+    }
+
+    #[test]
+    fn legacy_psa_appended_past_child_size() {
+        use crate::arc::{arc, ArcChildData};
+        use crate::test_util::{TestFighterBuilder, damage};
+
+        let builder = TestFighterBuilder::new("Test")
+            .subaction("Wait1", |s| s.sync_timer(1.0))
+            .appended_subaction("AttackS3S", |s| s.hitbox(damage(7)));
+        // the size of the arc child also excludes the appended data
+        let mut pac = builder.moveset_pac();
+        let size = [pac[0x60], pac[0x61], pac[0x62], pac[0x63]];
+        pac[0x44..0x48].copy_from_slice(&size);
+
+        let moveset = arc(FancySlice::new(&pac), &WiiMemory::new(), false);
+        let sakurai = match &moveset.children[0].data {
+            ArcChildData::Sakurai (sakurai) => sakurai,
+            _ => panic!("Expected moveset data"),
+        };
+        assert!(sakurai.appended_region.is_some());
+        let fighter_data = sakurai.sections.iter().find_map(|x| match &x.data {
+            SectionData::FighterData (data) => Some(data),
+            _ => None,
+        }).unwrap();
+        assert_eq!(fighter_data.subaction_main[1].events.len(), 1);
+        assert_eq!(fighter_data.subaction_main[1].events[0].arguments[1], script::Argument::Value (7));
+    }
+
+    #[test]
+    fn external_subroutine_linked_lists() {
+        let mut data = vec!();
+        // header
+        push_u32(&mut data, 0x40);       // size
+        push_u32(&mut data, 0x20);       // lookup entry offset
+        push_u32(&mut data, 0);          // lookup entry count
+        push_u32(&mut data, 0);          // section count
+        push_u32(&mut data, 2);          // external subroutine count
+        data.resize(ARC_SAKURAI_HEADER_SIZE, 0);
+
+        // offset arguments of the external calls, each pointing at the next call
+        data.resize(ARC_SAKURAI_HEADER_SIZE + 0x20, 0);
+        let mut set = |offset: usize, next: u32| data[ARC_SAKURAI_HEADER_SIZE + offset..][..4].copy_from_slice(&next.to_be_bytes());
+        set(0x04, 0x0C);
+        set(0x0C, 0x04);       // loops back to the first call
+        set(0x10, 0x14);
+        set(0x14, 0x18);
+        set(0x18, 0x7FFFFFF0); // points past the end of the data
+
+        // external subroutines
+        push_u32(&mut data, 0x04);
+        push_u32(&mut data, 0x00);
+        push_u32(&mut data, 0x10);
+        push_u32(&mut data, 0x07);
+
+        // string table
+        data.extend_from_slice(b"Cyclic\0Overlong\0");
+
+        let wii_memory = WiiMemory::new();
+        let sakurai = arc_sakurai(FancySlice::new(&data), &wii_memory, &mut EventBudget::new(ScriptLimits::default()), false);
+
+        assert_eq!(sakurai.external_subroutines[0].name, "Cyclic");
+        assert_eq!(sakurai.external_subroutines[0].offsets, vec!(0x04, 0x0C));
+        assert_eq!(sakurai.external_subroutines[1].name, "Overlong");
+        assert_eq!(sakurai.external_subroutines[1].offsets, vec!(0x10, 0x14, 0x18));
+        assert_eq!(sakurai.warnings.len(), 1);
+        assert_eq!(sakurai.warnings[0].section, "Cyclic");
+    }
+
+    #[test]
+    fn corrupted_section_offset() {
+        let mut data = vec!();
+        // header
+        push_u32(&mut data, 0x40);       // size
+        push_u32(&mut data, 0x10);       // lookup entry offset
+        push_u32(&mut data, 0);          // lookup entry count
+        push_u32(&mut data, 2);          // section count
+        push_u32(&mut data, 0);          // external subroutine count
+        data.resize(ARC_SAKURAI_HEADER_SIZE, 0);
+
+        // section data: a single empty script
+        push_u32(&mut data, 0);
+        push_u32(&mut data, 0);
+        push_u32(&mut data, 0);
+        push_u32(&mut data, 0);
+
+        // sections
+        push_u32(&mut data, 0x0);        // valid offset
+        push_u32(&mut data, 0x0);
+        push_u32(&mut data, 0xFFFFFFF0); // corrupted offset
+        push_u32(&mut data, 0xF);
+
+        // string table
+        data.extend_from_slice(b"gameAnimCmd_Ok\0");
+        data.extend_from_slice(b"gameAnimCmd_Bad\0");
+
+        let wii_memory = WiiMemory::new();
+        let sakurai = arc_sakurai(FancySlice::new(&data), &wii_memory, &mut EventBudget::new(ScriptLimits::default()), false);
+
+        assert_eq!(sakurai.warnings.len(), 1);
+        assert_eq!(sakurai.warnings[0].section, "gameAnimCmd_Bad");
+        assert_eq!(sakurai.sections.len(), 2);
+        match &sakurai.sections[0].data {
+            SectionData::Script (script) => assert_eq!(script.name, "gameAnimCmd_Ok"),
+            _ => panic!("Expected the valid section to be parsed"),
+        }
+        match &sakurai.sections[1].data {
+            SectionData::None => { }
+            _ => panic!("Expected the corrupted section to be skipped"),
+        }
+    }
+
+    #[test]
+    fn endless_section_script() {
+        let mut data = vec!();
+        // header
+        push_u32(&mut data, 0x228);      // size
+        push_u32(&mut data, 0x208);      // lookup entry offset
+        push_u32(&mut data, 0);          // lookup entry count
+        push_u32(&mut data, 1);          // section count
+        push_u32(&mut data, 0);          // external subroutine count
+        data.resize(ARC_SAKURAI_HEADER_SIZE + 0x8, 0);
+
+        // section data: events that never reach an end of script event
+        for _ in 0..0x40 {
+            data.extend_from_slice(&[0x04, 0x07, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        }
+
+        // sections
+        push_u32(&mut data, 0x8);
+        push_u32(&mut data, 0x0);
+
+        // string table
+        data.extend_from_slice(b"gameAnimCmd_Endless\0");
+
+        let wii_memory = WiiMemory::new();
+        let limits = ScriptLimits { max_events_per_script: 0x20, ..ScriptLimits::default() };
+        let sakurai = arc_sakurai(FancySlice::new(&data), &wii_memory, &mut EventBudget::new(limits), false);
+
+        match &sakurai.sections[0].data {
+            SectionData::Script (script) => assert_eq!(script.script.events.len(), 0x20),
+            _ => panic!("Expected the section to be parsed"),
+        }
+        assert_eq!(sakurai.warnings.len(), 1);
+        assert_eq!(sakurai.warnings[0].section, "gameAnimCmd_Endless");
+        assert_eq!(sakurai.warnings[0].script_offset, Some(0x8));
+    }
+
+    #[test]
+    fn overlapping_sections() {
+        let mut data = vec!();
+        // header
+        push_u32(&mut data, 0x60);       // size
+        push_u32(&mut data, 0x28);       // lookup entry offset
+        push_u32(&mut data, 0);          // lookup entry count
+        push_u32(&mut data, 3);          // section count
+        push_u32(&mut data, 0);          // external subroutine count
+        data.resize(ARC_SAKURAI_HEADER_SIZE + 0x8, 0);
+
+        // section data: a script of 3 events at 0x8..0x28
+        for _ in 0..3 {
+            data.extend_from_slice(&[0x04, 0x07, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        }
+        data.extend_from_slice(&[0; 8]);
+
+        // sections
+        push_u32(&mut data, 0x8);
+        push_u32(&mut data, 0x0);
+        push_u32(&mut data, 0x18);       // starts inside the first script
+        push_u32(&mut data, 0x12);
+        push_u32(&mut data, 0x8);        // shares the first script
+        push_u32(&mut data, 0x25);
+
+        // string table
+        data.extend_from_slice(b"gameAnimCmd_First\0");
+        data.extend_from_slice(b"gameAnimCmd_Second\0");
+        data.extend_from_slice(b"gameAnimCmd_Shared\0");
+
+        let wii_memory = WiiMemory::new();
+        let sakurai = arc_sakurai(FancySlice::new(&data), &wii_memory, &mut EventBudget::new(ScriptLimits::default()), false);
+
+        assert_eq!(sakurai.warnings.len(), 1);
+        assert_eq!(sakurai.warnings[0].section, "gameAnimCmd_Second");
+        assert!(sakurai.warnings[0].message.contains("gameAnimCmd_First"));
+        assert_eq!(sakurai.section_overlaps, vec!(SectionOverlap {
+            first:        String::from("gameAnimCmd_First"),
+            first_range:  0x8..0x28,
+            second:       String::from("gameAnimCmd_Second"),
+            second_range: 0x18..0x28,
+            overlap:      0x18..0x28,
+        }));
+        assert!(!sakurai.section_overlaps[0].out_of_order());
+
+        // the first declared section is parsed, the section overlapping it is skipped
+        let ranges: Vec<_> = sakurai.sections.iter().map(|x| x.range.clone()).collect();
+        assert_eq!(ranges, vec!(Some(0x8..0x28), Some(0x18..0x28), Some(0x8..0x28)));
+        match &sakurai.sections[0].data {
+            SectionData::Script (script) => assert_eq!(script.script.events.len(), 3),
+            _ => panic!("Expected the first section to be parsed"),
+        }
+        match &sakurai.sections[1].data {
+            SectionData::None => { }
+            _ => panic!("Expected the overlapping section to be skipped"),
+        }
+        match &sakurai.sections[2].data {
+            SectionData::Script (script) => assert_eq!(script.script.events.len(), 3),
+            _ => panic!("Expected the shared section to be parsed"),
+        }
+    }
+
+    /// A moveset containing only a dataCommon section whose header is `header_size` bytes long
+    fn data_common_moveset(header_size: u32) -> Vec<u8> {
+        let header_offset = 0x40;
+        let mut data = vec!();
+        // header
+        push_u32(&mut data, 0);                           // size
+        push_u32(&mut data, header_offset + header_size); // lookup entry offset
+        push_u32(&mut data, 0);                           // lookup entry count
+        push_u32(&mut data, 1);                           // section count
+        push_u32(&mut data, 0);                           // external subroutine count
+        data.resize(ARC_SAKURAI_HEADER_SIZE, 0);
+
+        // the data the dataCommon header points to, two empty entry and exit actions and empty leg bone lists
+        data.resize(ARC_SAKURAI_HEADER_SIZE + header_offset as usize, 0);
+
+        // dataCommon header
+        push_u32(&mut data, 0x10); // global_ics
+        push_u32(&mut data, 0x14); // global_ics_sse
+        push_u32(&mut data, 0x18); // ics
+        push_u32(&mut data, 0x1c); // ics_sse
+        push_u32(&mut data, 0x20); // entry_actions_start
+        push_u32(&mut data, 0x28); // exit_actions_start
+        data.resize(ARC_SAKURAI_HEADER_SIZE + header_offset as usize + 0x54, 0);
+        push_u32(&mut data, 0x30); // leg_bones
+        data.resize(ARC_SAKURAI_HEADER_SIZE + (header_offset + header_size) as usize, 0);
+
+        // sections
+        push_u32(&mut data, header_offset);
+        push_u32(&mut data, 0);
+
+        // string table
+        data.extend_from_slice(b"dataCommon\0");
+        data.resize(data.len() + 0x10, 0);
+        data
+    }
+
+    fn data_common(sakurai: &ArcSakurai) -> &ArcFighterDataCommon {
+        match &sakurai.sections[0].data {
+            SectionData::FighterDataCommon (data_common) => data_common,
+            _ => panic!("Expected the dataCommon section to be parsed"),
+        }
+    }
+
+    #[test]
+    fn data_common_retail_revision() {
+        let data = data_common_moveset(0x68);
+        let wii_memory = WiiMemory::new();
+        let sakurai = arc_sakurai(FancySlice::new(&data), &wii_memory, &mut EventBudget::new(ScriptLimits::default()), false);
+
+        let data_common = data_common(&sakurai);
+        assert_eq!(data_common.revision, FormatRevision::Retail);
+        assert_eq!(data_common.entry_actions.len(), 2);
+        assert_eq!(data_common.exit_actions.len(), 2);
+    }
+
+    #[test]
+    fn data_common_unknown_revision() {
+        let data = data_common_moveset(0x60);
+        let wii_memory = WiiMemory::new();
+        let sakurai = arc_sakurai(FancySlice::new(&data), &wii_memory, &mut EventBudget::new(ScriptLimits::default()), false);
+
+        // parsed with the closest layout
+        let data_common = data_common(&sakurai);
+        assert_eq!(data_common.revision, FormatRevision::Unknown { header_size: 0x60 });
+        assert_eq!(data_common.entry_actions.len(), 2);
+        assert_eq!(data_common.exit_actions.len(), 2);
+        assert_eq!(sakurai.sections[0].range, Some(0x40 .. 0xA0));
+        assert_eq!(sakurai.warnings.len(), 1);
+        assert_eq!(sakurai.warnings[0].section, "dataCommon");
+        assert!(sakurai.warnings[0].message.contains("0x60"));
+        assert!(sakurai.warnings[0].message.contains("Retail"));
+    }
+}
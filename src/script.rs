@@ -1,119 +1,220 @@
+use std::collections::HashSet;
+
 use fancy_slice::FancySlice;
 
 use crate::wii_memory::WiiMemory;
+use crate::address::RamAddress;
+use crate::game_frame::GameFrame;
+use crate::sakurai::ParseWarning;
+
+pub use crate::script_assembler::{assemble, disassemble, disassemble_event, disassemble_with_symbols, AssembleError, AssembleErrorKind};
 
-pub(crate) fn scripts(parent_data: FancySlice, offset_data: FancySlice, num: usize, wii_memory: &WiiMemory) -> Vec<Script> {
-    let mut result = vec!();
-    for i in 0..num {
-        let offset = offset_data.u32_be(i * 4);
-        result.push(new_script(parent_data, offset, wii_memory));
+/// Limits that stop a corrupted or malicious moveset from parsing into enormous scripts.
+/// A script that reaches a limit is truncated and a `ParseWarning` is reported for it.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct ScriptLimits {
+    /// The most events read from a single script
+    pub max_events_per_script: usize,
+    /// The most events read from all the moveset files of a single fighter including its Kirby hats, scripts read after the limit is reached are empty.
+    /// Followers and transformations are separate fighters with their own budget.
+    /// The common Fighter.pac is shared by every fighter, so it has its own budget.
+    pub max_events_per_fighter: usize,
+}
+
+impl Default for ScriptLimits {
+    fn default() -> ScriptLimits {
+        ScriptLimits {
+            max_events_per_script:  100_000,
+            max_events_per_fighter: 2_000_000,
+        }
     }
-    result
 }
 
-/// finds any scripts that are pointed to by Goto's and Subroutines but dont exist yet.
-pub(crate) fn fragment_scripts(parent_data: FancySlice, known_scripts: &[&[Script]], ignore_origins: &[i32], wii_memory: &WiiMemory) -> Vec<Script> {
-    let mut fragments: Vec<Script> = vec!();
-    for scripts in known_scripts.iter() {
-        for script in scripts.iter() {
-            for event in &script.events {
-                let mut found_offset = None;
-                if event.namespace == 0x00 && (event.code == 0x07 || event.code == 0x09) { // if the event is a subroutine or goto
-                    if let Some(Argument::Offset (Offset { offset, origin })) = event.arguments.get(0) {
-                        if !ignore_origins.contains(origin) {
-                            found_offset = Some(*offset);
-                        }
-                    }
+/// The events read so far towards `ScriptLimits::max_events_per_fighter`, shared by every file read for a single fighter.
+pub(crate) struct EventBudget {
+    limits:    ScriptLimits,
+    events:    usize,
+    /// Set once the budget has run out and the truncation has been reported, so it is only reported once
+    exhausted: bool,
+}
 
-                    if let Some(Argument::Value (offset)) = event.arguments.get(0) {
-                        found_offset = Some(*offset);
-                    }
-                }
-                if event.namespace == 0x0D && (event.code == 0x00 || event.code == 0x05) { // if the event is a CallEveryFrame or IndependentSubroutine
-                    if let Some(Argument::Offset (Offset { offset, origin })) = event.arguments.get(1) {
-                        if !ignore_origins.contains(origin) {
-                            found_offset = Some(*offset);
-                        }
-                    }
-                }
-                if let Some(offset) = found_offset {
-                    let mut is_action = false;
-                    'outer: for check_scripts in known_scripts.iter() {
-                        for check_script in check_scripts.iter() {
-                            if check_script.offset == offset {
-                                is_action = true;
-                                break 'outer;
-                            }
-                        }
-                    }
-                    let already_added = fragments.iter().any(|x| x.offset == offset);
+impl EventBudget {
+    pub(crate) fn new(limits: ScriptLimits) -> EventBudget {
+        EventBudget { limits, events: 0, exhausted: false }
+    }
+}
 
-                    if !is_action && !already_added {
-                        fragments.push(new_script(parent_data, offset as u32, wii_memory));
-                    }
+/// Reads the scripts of a moveset file, keeping track of the `ScriptLimits` across all of them.
+pub(crate) struct ScriptParser<'a> {
+    pub(crate) wii_memory: &'a WiiMemory,
+    budget:                &'a mut EventBudget,
+    /// The declared end of the data that scripts starting before it belong to, scripts are truncated when they reach it.
+    /// None when only the end of the parent data is known.
+    data_end:              Option<usize>,
+    /// Script offsets and messages of the scripts that were truncated since the last call to `take_warnings`
+    warnings:              Vec<(i32, String)>,
+}
+
+impl<'a> ScriptParser<'a> {
+    pub(crate) fn new(wii_memory: &'a WiiMemory, budget: &'a mut EventBudget) -> ScriptParser<'a> {
+        ScriptParser { wii_memory, budget, data_end: None, warnings: vec!() }
+    }
+
+    /// Scripts starting before `data_end` are truncated when they reach it, instead of at the end of the parent data.
+    /// Scripts starting at or after it e.g. appended by legacy PSA, can still continue to the end of the parent data.
+    pub(crate) fn with_data_end(mut self, data_end: usize) -> ScriptParser<'a> {
+        self.data_end = Some(data_end);
+        self
+    }
+
+    pub(crate) fn scripts(&mut self, parent_data: FancySlice, offset_data: FancySlice, num: usize) -> Vec<Script> {
+        let mut result = vec!();
+        for i in 0..num {
+            let offset = offset_data.u32_be(i * 4);
+            result.push(self.script(parent_data, offset));
+        }
+        result
+    }
+
+    pub(crate) fn script(&mut self, parent_data: FancySlice, offset: u32) -> Script {
+        let wii_memory = self.wii_memory;
+        let file_source = DataSource::File { offset };
+        let (buffer, source) = if offset == 0 || offset as i32 == -1 {
+            return Script { events: vec!(), offset: offset as i32, source: file_source }
+        } else if offset > 0 && offset < (parent_data.len() as u32) {
+            let end = match self.data_end {
+                Some(data_end) if (offset as usize) < data_end => data_end.min(parent_data.len()),
+                _ => parent_data.len(),
+            };
+            (parent_data.relative_fancy_slice(offset as usize .. end), file_source)
+        } else if offset < 0x8000_0000 {
+            return Script { events: vec!(), offset: offset as i32, source: file_source }
+        } else {
+            // A WiiRD code has redirected the script to data it wrote to RAM
+            let address = RamAddress::new(offset);
+            (wii_memory.fancy_slice_from(address), DataSource::WiiMemory { address: offset, code_offset: wii_memory.writer(address) })
+        };
+
+        let mut events = vec!();
+        let mut event_offset = 0;
+        loop {
+            if event_offset as usize + EVENT_SIZE > buffer.len() {
+                self.warn(offset, format!("The script continues past the end of its data at 0x{:x} without an end of script event, truncated it after {} events", offset as usize + buffer.len(), events.len()));
+                break;
+            }
+
+            let namespace     = buffer.u8    (event_offset as usize);
+            let code          = buffer.u8    (event_offset as usize + 1);
+            let num_arguments = buffer.u8    (event_offset as usize + 2);
+            let unk1          = buffer.u8    (event_offset as usize + 3);
+            let raw_id        = buffer.u32_be(event_offset as usize);
+
+            if code == 0 && namespace == 0 { // end of script
+                break
+            }
+
+            // Filler is counted too, so a script made of nothing but filler is still stopped
+            let limits = self.budget.limits;
+            if event_offset as usize / EVENT_SIZE >= limits.max_events_per_script {
+                self.warn(offset, format!("The script has more than {} events, truncated it", limits.max_events_per_script));
+                break;
+            }
+            if self.budget.events >= limits.max_events_per_fighter {
+                if !self.budget.exhausted {
+                    self.budget.exhausted = true;
+                    self.warn(offset, format!("The fighter has more than {} events, truncated this script and emptied all scripts read after it", limits.max_events_per_fighter));
                 }
+                break;
+            }
+            self.budget.events += 1;
+
+            // PSA fills empty space with these bytes:
+            // const long FADEDATA = 0xFADE0D8A; // Constant for the tag FADE0D8A representing the end of useable space.
+            // const long FADEFOOD = 0xFADEF00D; // Constant for the tag FADEF00D representing empty, useable space.
+            if raw_id != 0xFADEF00D && raw_id != 0xFADE0D8A {
+                let argument_offset = buffer.u32_be(event_offset as usize + 4);
+
+                let argument_buffer = if argument_offset as usize >= parent_data.len() {
+                    wii_memory.fancy_slice_from(RamAddress::new(argument_offset))
+                } else {
+                    parent_data.relative_fancy_slice(argument_offset as usize..)
+                };
+
+                let arguments = arguments(argument_buffer, argument_offset, num_arguments as usize);
+                events.push(Event {
+                    namespace,
+                    code,
+                    unk1,
+                    arguments,
+                });
             }
+
+            event_offset += EVENT_SIZE as u32;
         }
+        Script { events, offset: offset as i32, source }
     }
 
-    if fragments.len() > 0 {
-        // the fragment scripts may refer to their own fragment scripts
-        let mut all = known_scripts.to_vec();
-        all.push(&fragments);
-        let inner_fragments = fragment_scripts(parent_data, &all, ignore_origins, wii_memory);
-        fragments.extend(inner_fragments);
+    fn warn(&mut self, offset: u32, message: String) {
+        error!("Script at 0x{:x}: {}", offset, message);
+        self.warnings.push((offset as i32, message));
+    }
+
+    /// Returns a warning for each script truncated since the last call, reported as part of `section`
+    pub(crate) fn take_warnings(&mut self, section: &str) -> Vec<ParseWarning> {
+        self.warnings.drain(..).map(|(offset, message)| ParseWarning {
+            section:       section.to_string(),
+            message,
+            script_offset: Some(offset),
+        }).collect()
     }
-    fragments
 }
 
-pub fn new_script(parent_data: FancySlice, offset: u32, wii_memory: &WiiMemory) -> Script {
-    let buffer = if offset == 0 || offset as i32 == -1 {
-        return Script { events: vec!(), offset: offset as i32 }
-    } else if offset > 0 && offset < (parent_data.len() as u32) {
-        parent_data.relative_fancy_slice(offset as usize ..)
-    } else if offset < 0x8000_0000 {
-        return Script { events: vec!(), offset: offset as i32 }
-    } else {
-        wii_memory.fancy_slice_from(offset as usize)
-    };
+/// finds any scripts that are pointed to by Goto's and Subroutines but dont exist yet.
+/// Fragments may point at further fragments, these are also found.
+///
+/// The fragments are returned sorted by offset.
+pub(crate) fn fragment_scripts(parent_data: FancySlice, known_scripts: &[&Script], ignore_origins: &[i32], parser: &mut ScriptParser) -> Vec<Script> {
+    let ignore_origins: HashSet<i32> = ignore_origins.iter().cloned().collect();
+    let mut found: HashSet<i32> = known_scripts.iter().map(|x| x.offset).collect();
 
-    let mut events = vec!();
-    let mut event_offset = 0;
+    let mut fragments: Vec<Script> = vec!();
+    // known scripts are scanned first, then each fragment is scanned once after it is found
+    let mut scanned = 0;
     loop {
-        let namespace     = buffer.u8    (event_offset as usize);
-        let code          = buffer.u8    (event_offset as usize + 1);
-        let num_arguments = buffer.u8    (event_offset as usize + 2);
-        let unk1          = buffer.u8    (event_offset as usize + 3);
-        let raw_id        = buffer.u32_be(event_offset as usize);
-
-        if code == 0 && namespace == 0 { // end of script
-            break
-        }
-
-        // PSA fills empty space with these bytes:
-        // const long FADEDATA = 0xFADE0D8A; // Constant for the tag FADE0D8A representing the end of useable space.
-        // const long FADEFOOD = 0xFADEF00D; // Constant for the tag FADEF00D representing empty, useable space.
-        if raw_id != 0xFADEF00D && raw_id != 0xFADE0D8A {
-            let argument_offset = buffer.u32_be(event_offset as usize + 4);
+        let script = match known_scripts.get(scanned) {
+            Some(script) => *script,
+            None => match fragments.get(scanned - known_scripts.len()) {
+                Some(script) => script,
+                None => break,
+            }
+        };
+        scanned += 1;
 
-            let argument_buffer = if argument_offset as usize >= parent_data.len() {
-                wii_memory.fancy_slice_from(argument_offset as usize)
-            } else {
-                parent_data.relative_fancy_slice(argument_offset as usize..)
+        let mut new_offsets = vec!();
+        for event in &script.events {
+            let found_offset = match event.call() {
+                Some((_, Argument::Offset (Offset { offset, origin }))) if !ignore_origins.contains(origin) => Some(*offset),
+                Some((CallKind::Subroutine, Argument::Value (offset))) |
+                Some((CallKind::Goto,       Argument::Value (offset))) => Some(*offset),
+                _ => None,
             };
-
-            let arguments = arguments(argument_buffer, argument_offset, num_arguments as usize);
-            events.push(Event {
-                namespace,
-                code,
-                unk1,
-                arguments,
-            });
+            if let Some(offset) = found_offset {
+                if found.insert(offset) {
+                    new_offsets.push(offset);
+                }
+            }
         }
-
-        event_offset += EVENT_SIZE as u32;
+        fragments.extend(new_offsets.into_iter().map(|offset| parser.script(parent_data, offset as u32)));
     }
-    Script { events, offset: offset as i32 }
+
+    fragments.sort_by_key(|x| x.offset);
+    fragments
+}
+
+/// Reads the script at `offset`, it is truncated when it reaches the default `ScriptLimits`.
+pub fn new_script(parent_data: FancySlice, offset: u32, wii_memory: &WiiMemory) -> Script {
+    ScriptParser::new(wii_memory, &mut EventBudget::new(ScriptLimits::default())).script(parent_data, offset)
 }
 
 fn arguments(data: FancySlice, origin: u32, num_arguments: usize) -> Vec<Argument> {
@@ -131,7 +232,7 @@ fn arguments(data: FancySlice, origin: u32, num_arguments: usize) -> Vec<Argumen
 
         let argument = match ty {
             0 => Argument::Value (value),
-            1 => Argument::Scalar (value as f32 / 60000.0),
+            1 => Argument::Scalar (FixedPoint { raw: value }),
             2 => Argument::Offset (Offset { offset: value, origin: origin as i32 + argument_offset + 4}),
             3 => Argument::Bool (value == 1),
             4 => Argument::File (value),
@@ -156,14 +257,47 @@ fn arguments(data: FancySlice, origin: u32, num_arguments: usize) -> Vec<Argumen
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct Script {
     pub events: Vec<Event>,
     pub offset: i32,
+    /// Where the events were read from
+    pub source: DataSource,
+}
+
+/// Where the bytes of a script were read from.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum DataSource {
+    /// Read from the moveset file, `offset` is relative to the start of the moveset data.
+    /// Also used for null scripts and scripts that were not read from anywhere e.g. assembled scripts.
+    File { offset: u32 },
+    /// The script offset was a RAM address written into the moveset by the WiiRD codeset, so the script was read from `address` in RAM.
+    /// `code_offset` is the byte offset within the codeset of the last code that wrote to `address`, None if no code wrote to it.
+    WiiMemory { address: u32, code_offset: Option<usize> },
 }
 
 // Events are like lines of code in a script
 const EVENT_SIZE: usize = 0x8;
-#[derive(Serialize, Clone, Debug)]
+
+/// Returns the number of bytes of the script at `offset` in the file, including the end of script event.
+/// Scripts that continue to the end of the data take up the rest of the data.
+/// None when the script is not stored in the file.
+pub(crate) fn script_size(parent_data: FancySlice, offset: u32) -> Option<u32> {
+    if offset == 0 || offset as i32 == -1 || offset as usize >= parent_data.len() {
+        return None;
+    }
+    let mut event_offset = offset as usize;
+    while event_offset + EVENT_SIZE <= parent_data.len() {
+        event_offset += EVENT_SIZE;
+        if parent_data.u8(event_offset - EVENT_SIZE) == 0 && parent_data.u8(event_offset - EVENT_SIZE + 1) == 0 {
+            break;
+        }
+    }
+    Some(event_offset as u32 - offset)
+}
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct Event {
     pub namespace: u8,
     pub code: u8,
@@ -177,13 +311,464 @@ impl Event {
         assert!(num_args < 0x100);
         (self.namespace as u32) << 24 | (self.code as u32) << 16 | (num_args as u32) << 8
     }
+
+    /// Returns the kind of call and the argument containing the offset of the called script, if the event calls another script.
+    pub fn call(&self) -> Option<(CallKind, &Argument)> {
+        match (self.namespace, self.code) {
+            (0x00, 0x07) => self.arguments.first().map(|x| (CallKind::Subroutine, x)),
+            (0x00, 0x09) => self.arguments.first().map(|x| (CallKind::Goto, x)),
+            (0x0D, 0x00) => self.arguments.get(1).map(|x| (CallKind::Concurrent, x)),
+            (0x0D, 0x05) => self.arguments.get(1).map(|x| (CallKind::Independent, x)),
+            _ => None,
+        }
+    }
+
+    /// Same as `Event::call` but returns the offset of the called script, None when the argument is not an offset or value.
+    pub(crate) fn call_offset(&self) -> Option<(CallKind, i32)> {
+        match self.call() {
+            Some((kind, Argument::Offset (offset))) => Some((kind, offset.offset)),
+            Some((kind, Argument::Value (offset)))  => Some((kind, *offset)),
+            _ => None,
+        }
+    }
+
+    /// Returns how the event uses each of its arguments, indexed the same as `Event::arguments`.
+    ///
+    /// Events that take a varying number of arguments return the roles of the longest form, so there can be more roles than arguments.
+    /// Empty for events whose arguments have not been classified yet.
+    pub fn argument_roles(&self) -> &'static [ArgRole] {
+        ARGUMENT_ROLES.iter()
+            .find(|(namespace, code, _)| *namespace == self.namespace && *code == self.code)
+            .map(|(_, _, roles)| *roles)
+            .unwrap_or(&[])
+    }
+
+    /// Returns whether the event affects gameplay or is purely cosmetic, see `EventClass`
+    pub fn class(&self) -> EventClass {
+        EVENT_CLASSES.iter()
+            .find(|(namespace, code, _)| *namespace == self.namespace && *code == self.code)
+            .map(|(_, _, class)| *class)
+            .unwrap_or(EventClass::Unknown)
+    }
+}
+
+/// Whether an event changes how the fighter plays, see `Event::class`
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum EventClass {
+    /// Changes the state of the fighter or the match e.g. hitboxes, timers, momentum, interrupts and variables
+    Gameplay,
+    /// Only changes what is seen or heard e.g. graphics, sounds, rumble, screen tints and camera shake
+    Cosmetic,
+    /// Controls which events run e.g. if statements, loops and subroutines
+    ControlFlow,
+    /// The event is not in the table of known events
+    Unknown,
+}
+
+/// The class of each known event, keyed by namespace and code
+const EVENT_CLASSES: &[(u8, u8, EventClass)] = &[
+    // control flow, timers decide when everything else happens so they affect gameplay
+    (0x00, 0x01, EventClass::Gameplay),
+    (0x00, 0x02, EventClass::Gameplay),
+    (0x00, 0x04, EventClass::ControlFlow),
+    (0x00, 0x05, EventClass::ControlFlow),
+    (0x00, 0x07, EventClass::ControlFlow),
+    (0x00, 0x08, EventClass::ControlFlow),
+    (0x00, 0x09, EventClass::ControlFlow),
+    (0x00, 0x0A, EventClass::ControlFlow),
+    (0x00, 0x0B, EventClass::ControlFlow),
+    (0x00, 0x0C, EventClass::ControlFlow),
+    (0x00, 0x0D, EventClass::ControlFlow),
+    (0x00, 0x0E, EventClass::ControlFlow),
+    (0x00, 0x0F, EventClass::ControlFlow),
+    (0x00, 0x10, EventClass::ControlFlow),
+    (0x00, 0x11, EventClass::ControlFlow),
+    (0x00, 0x13, EventClass::ControlFlow),
+    (0x01, 0x01, EventClass::ControlFlow),
+    (0x0D, 0x00, EventClass::ControlFlow),
+    (0x0D, 0x01, EventClass::ControlFlow),
+    (0x0D, 0x05, EventClass::ControlFlow),
+    (0x0D, 0x06, EventClass::ControlFlow),
+    (0x0D, 0x07, EventClass::ControlFlow),
+
+    // interrupts and actions
+    (0x02, 0x00, EventClass::Gameplay),
+    (0x02, 0x01, EventClass::Gameplay),
+    (0x02, 0x04, EventClass::Gameplay),
+    (0x02, 0x05, EventClass::Gameplay),
+    (0x02, 0x06, EventClass::Gameplay),
+    (0x02, 0x08, EventClass::Gameplay),
+    (0x02, 0x09, EventClass::Gameplay),
+    (0x02, 0x0A, EventClass::Gameplay),
+    (0x02, 0x0B, EventClass::Gameplay),
+    (0x02, 0x0C, EventClass::Gameplay),
+    (0x64, 0x00, EventClass::Gameplay),
+    (0x64, 0x01, EventClass::Gameplay),
+
+    // subactions and animation
+    (0x04, 0x00, EventClass::Gameplay),
+    (0x04, 0x06, EventClass::Gameplay),
+    (0x04, 0x07, EventClass::Gameplay),
+    (0x04, 0x14, EventClass::Gameplay),
+    (0x05, 0x00, EventClass::Gameplay),
+    (0x05, 0x01, EventClass::Gameplay),
+    (0x05, 0x02, EventClass::Gameplay),
+    (0x05, 0x03, EventClass::Gameplay),
+    (0x05, 0x04, EventClass::Gameplay),
+    (0x05, 0x07, EventClass::Gameplay),
+    (0x05, 0x0D, EventClass::Gameplay),
+    (0x08, 0x00, EventClass::Gameplay),
+    (0x18, 0x00, EventClass::Gameplay),
+    (0x18, 0x01, EventClass::Gameplay),
+
+    // hitboxes, grabboxes and hurtboxes
+    (0x06, 0x00, EventClass::Gameplay),
+    (0x06, 0x01, EventClass::Gameplay),
+    (0x06, 0x02, EventClass::Gameplay),
+    (0x06, 0x03, EventClass::Gameplay),
+    (0x06, 0x04, EventClass::Gameplay),
+    (0x06, 0x05, EventClass::Gameplay),
+    (0x06, 0x06, EventClass::Gameplay),
+    (0x06, 0x08, EventClass::Gameplay),
+    (0x06, 0x0A, EventClass::Gameplay),
+    (0x06, 0x0C, EventClass::Gameplay),
+    (0x06, 0x0D, EventClass::Gameplay),
+    (0x06, 0x0E, EventClass::Gameplay),
+    (0x06, 0x0F, EventClass::Gameplay),
+    (0x06, 0x14, EventClass::Gameplay),
+    (0x06, 0x15, EventClass::Gameplay),
+    (0x06, 0x17, EventClass::Gameplay),
+    (0x06, 0x1B, EventClass::Gameplay),
+    (0x06, 0x2B, EventClass::Gameplay),
+    (0x1E, 0x00, EventClass::Gameplay),
+    (0x1E, 0x03, EventClass::Gameplay),
+
+    // controller
+    (0x07, 0x00, EventClass::Gameplay),
+    (0x07, 0x01, EventClass::Gameplay),
+    (0x07, 0x02, EventClass::Gameplay),
+    (0x07, 0x06, EventClass::Gameplay),
+    (0x07, 0x07, EventClass::Cosmetic),
+    (0x07, 0x0B, EventClass::Cosmetic),
+
+    // air/ground state and momentum
+    (0x0E, 0x00, EventClass::Gameplay),
+    (0x0E, 0x01, EventClass::Gameplay),
+    (0x0E, 0x02, EventClass::Gameplay),
+    (0x0E, 0x06, EventClass::Gameplay),
+    (0x0E, 0x07, EventClass::Gameplay),
+    (0x0E, 0x08, EventClass::Gameplay),
+    (0x17, 0x00, EventClass::Gameplay),
+
+    // articles and items, article visibility and the beam sword trail are only visual
+    (0x10, 0x00, EventClass::Gameplay),
+    (0x10, 0x01, EventClass::Gameplay),
+    (0x10, 0x02, EventClass::Gameplay),
+    (0x10, 0x03, EventClass::Gameplay),
+    (0x10, 0x05, EventClass::Cosmetic),
+    (0x1F, 0x00, EventClass::Gameplay),
+    (0x1F, 0x01, EventClass::Gameplay),
+    (0x1F, 0x02, EventClass::Gameplay),
+    (0x1F, 0x03, EventClass::Gameplay),
+    (0x1F, 0x04, EventClass::Gameplay),
+    (0x1F, 0x05, EventClass::Gameplay),
+    (0x1F, 0x06, EventClass::Gameplay),
+    (0x1F, 0x07, EventClass::Gameplay),
+    (0x1F, 0x08, EventClass::Gameplay),
+    (0x1F, 0x09, EventClass::Cosmetic),
+    (0x1F, 0x0A, EventClass::Gameplay),
+    (0x1F, 0x0C, EventClass::Cosmetic),
+    (0x1F, 0x0E, EventClass::Gameplay),
+
+    // fighter state
+    (0x0C, 0x06, EventClass::Gameplay),
+    (0x0C, 0x07, EventClass::Gameplay),
+    (0x0C, 0x08, EventClass::Gameplay),
+    (0x0C, 0x09, EventClass::Gameplay),
+    (0x0C, 0x23, EventClass::Gameplay), // time manipulation
+    (0x0C, 0x25, EventClass::Cosmetic),
+
+    // variables
+    (0x12, 0x00, EventClass::Gameplay),
+    (0x12, 0x01, EventClass::Gameplay),
+    (0x12, 0x02, EventClass::Gameplay),
+    (0x12, 0x03, EventClass::Gameplay),
+    (0x12, 0x04, EventClass::Gameplay),
+    (0x12, 0x06, EventClass::Gameplay),
+    (0x12, 0x07, EventClass::Gameplay),
+    (0x12, 0x08, EventClass::Gameplay),
+    (0x12, 0x0A, EventClass::Gameplay),
+    (0x12, 0x0B, EventClass::Gameplay),
+    (0x12, 0x0F, EventClass::Gameplay),
+    (0x12, 0x10, EventClass::Gameplay),
+
+    // sounds
+    (0x0A, 0x00, EventClass::Cosmetic),
+    (0x0A, 0x01, EventClass::Cosmetic),
+    (0x0A, 0x02, EventClass::Cosmetic),
+    (0x0A, 0x03, EventClass::Cosmetic),
+    (0x0A, 0x05, EventClass::Cosmetic),
+    (0x0A, 0x07, EventClass::Cosmetic),
+    (0x0A, 0x09, EventClass::Cosmetic),
+    (0x0A, 0x0A, EventClass::Cosmetic),
+    (0x0C, 0x0B, EventClass::Cosmetic),
+    (0x0C, 0x19, EventClass::Cosmetic),
+    (0x0C, 0x1D, EventClass::Cosmetic),
+    (0x0C, 0x1F, EventClass::Cosmetic),
+
+    // models, graphics, color and camera
+    (0x0B, 0x00, EventClass::Cosmetic),
+    (0x0B, 0x01, EventClass::Cosmetic),
+    (0x11, 0x00, EventClass::Cosmetic),
+    (0x11, 0x01, EventClass::Cosmetic),
+    (0x11, 0x02, EventClass::Cosmetic),
+    (0x11, 0x03, EventClass::Cosmetic),
+    (0x11, 0x05, EventClass::Cosmetic),
+    (0x11, 0x17, EventClass::Cosmetic),
+    (0x11, 0x18, EventClass::Cosmetic),
+    (0x11, 0x1A, EventClass::Cosmetic),
+    (0x11, 0x1B, EventClass::Cosmetic),
+    (0x14, 0x04, EventClass::Cosmetic),
+    (0x14, 0x07, EventClass::Cosmetic),
+    (0x1A, 0x00, EventClass::Cosmetic),
+    (0x1A, 0x04, EventClass::Cosmetic),
+    (0x1A, 0x08, EventClass::Cosmetic),
+    (0x21, 0x00, EventClass::Cosmetic),
+    (0x21, 0x01, EventClass::Cosmetic),
+    (0x21, 0x02, EventClass::Cosmetic),
+    (0x21, 0x05, EventClass::Cosmetic),
+    (0x21, 0x07, EventClass::Cosmetic),
+];
+
+/// How an event uses one of its arguments, see `Event::argument_roles`
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum ArgRole {
+    /// The value of the argument is used, if it is a variable the variable is read
+    Read,
+    /// The argument is a variable that the event sets
+    Write,
+    /// The argument is a variable that the event modifies based on its current value e.g. IntVariableAdd
+    ReadWrite,
+    /// The argument refers to the script or action the event goes to
+    Target,
+    /// The argument is a constant that controls what the event does e.g. a requirement or comparison operator
+    Immediate,
+}
+
+const CONDITION_ROLES: &[ArgRole] = &[ArgRole::Immediate, ArgRole::Read, ArgRole::Immediate, ArgRole::Read];
+
+/// The argument roles of each event, keyed by namespace and code
+const ARGUMENT_ROLES: &[(u8, u8, &[ArgRole])] = &[
+    // control flow
+    (0x00, 0x04, &[ArgRole::Read]),
+    (0x00, 0x07, &[ArgRole::Target]),
+    (0x00, 0x09, &[ArgRole::Target]),
+    (0x00, 0x0A, CONDITION_ROLES),
+    (0x00, 0x0B, CONDITION_ROLES),
+    (0x00, 0x0C, CONDITION_ROLES),
+    (0x00, 0x0D, CONDITION_ROLES),
+    (0x00, 0x10, &[ArgRole::Immediate, ArgRole::Read]),
+    (0x0D, 0x00, &[ArgRole::Immediate, ArgRole::Target]),
+    (0x0D, 0x05, &[ArgRole::Immediate, ArgRole::Target]),
+
+    // interrupts
+    (0x02, 0x00, &[ArgRole::Immediate, ArgRole::Target, ArgRole::Immediate, ArgRole::Read, ArgRole::Immediate, ArgRole::Read]),
+    (0x02, 0x01, &[ArgRole::Target, ArgRole::Immediate, ArgRole::Read, ArgRole::Immediate, ArgRole::Read]),
+    (0x02, 0x04, CONDITION_ROLES),
+    (0x02, 0x05, &[ArgRole::Immediate, ArgRole::Immediate, ArgRole::Immediate, ArgRole::Read, ArgRole::Immediate, ArgRole::Read]),
+
+    // variables
+    (0x12, 0x00, &[ArgRole::Read, ArgRole::Write]),
+    (0x12, 0x01, &[ArgRole::Read, ArgRole::ReadWrite]),
+    (0x12, 0x02, &[ArgRole::Read, ArgRole::ReadWrite]),
+    (0x12, 0x03, &[ArgRole::ReadWrite]),
+    (0x12, 0x04, &[ArgRole::ReadWrite]),
+    (0x12, 0x06, &[ArgRole::Read, ArgRole::Write]),
+    (0x12, 0x07, &[ArgRole::Read, ArgRole::ReadWrite]),
+    (0x12, 0x08, &[ArgRole::Read, ArgRole::ReadWrite]),
+    (0x12, 0x0F, &[ArgRole::Read, ArgRole::ReadWrite]),
+    (0x12, 0x10, &[ArgRole::Read, ArgRole::ReadWrite]),
+    (0x12, 0x0A, &[ArgRole::Write]),
+    (0x12, 0x0B, &[ArgRole::Write]),
+];
+
+/// How an event runs another script
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum CallKind {
+    /// Runs the script then continues after the event
+    Subroutine,
+    /// Continues in the script without returning
+    Goto,
+    /// Runs the script every frame alongside the caller until it is removed or the action ends.
+    /// Created by the CallEveryFrame event, known as "Concurrent Infinite Loop" in BrawlBox.
+    Concurrent,
+    /// Runs the script in its own thread that persists after the action ends, requires the Independent Subroutines code by Mawootad.
+    Independent,
+}
+
+/// The events of a script annotated with the frame they are executed on, returned by `normalize_timers`.
+#[derive(Clone, Debug)]
+pub struct TimedEvents<'a> {
+    pub events: Vec<TimedEvent<'a>>,
+    /// Unrolling the loops of the script reached `ScriptLimits::max_events_per_script`, so the events after the limit are not included.
+    pub truncated: bool,
+}
+
+/// An event annotated with the frame it is executed on.
+#[derive(Clone, Debug)]
+pub struct TimedEvent<'a> {
+    /// The game frame the event is executed on, the first frame of the script is frame 1.
+    pub frame: GameFrame,
+    pub event: &'a Event,
+    /// The event is in an infinite loop, `frame` is the frame of the first iteration.
+    pub repeating: bool,
+}
+
+/// Annotates the events of the script with the frame they are executed on.
+///
+/// # Convention
+///
+/// Frames start at 1, events before the first timer are executed on frame 1.
+/// This matches the frames of `HighLevelSubaction` and the `ScriptRunner`, where frame N is reached after N - 1 calls to `step`.
+///
+/// *   Asynchronous Timer N waits until the timer reaches N, so the following events are executed on frame N + 1.
+///     If the timer has already passed N it does nothing.
+/// *   Synchronous Timer N waits N timer frames from the current timer value.
+/// *   Frame speed modifiers change how many timer frames pass per game frame, so timers are converted to game frames.
+///     The timer is accumulated one game frame at a time following the rules of `GameFrame`, so the result matches the game when the speed is not exactly representable e.g. 0.9.
+///     When a wait ends between game frames the events are executed on the next game frame.
+/// *   Set Animation and Timer Frame sets the timer value.
+///
+/// Finite loops are unrolled.
+/// Every included event and every loop iteration counts towards the `ScriptLimits::max_events_per_script` of the default limits,
+/// once it is reached the remaining events are not included and `TimedEvents::truncated` is set.
+/// The body of an infinite loop is included once with `repeating` set, events after it are never reached so are not included.
+/// Events after a frame speed of 0 are never reached so are not included either.
+///
+/// If statements are not evaluated, all events in all branches are included in order.
+/// Gotos and subroutines are not followed.
+pub fn normalize_timers(script: &Script) -> TimedEvents<'_> {
+    normalize_timers_with_limits(script, &ScriptLimits::default())
+}
+
+/// Same as `normalize_timers` but loops are unrolled up to `limits.max_events_per_script` instead of the default limit.
+pub fn normalize_timers_with_limits<'a>(script: &'a Script, limits: &ScriptLimits) -> TimedEvents<'a> {
+    let mut state = TimerState {
+        timer: GameFrame::ZERO,
+        frames: 0,
+        speed: 1.0,
+        stopped: false,
+        remaining: limits.max_events_per_script,
+        truncated: false,
+    };
+    let mut events = vec!();
+    normalize_block(&script.events, &mut state, false, &mut events);
+    TimedEvents { events, truncated: state.truncated }
+}
+
+struct TimerState {
+    /// The value asynchronous timers compare against, affected by frame speed modifiers
+    timer: GameFrame,
+    /// Number of game frames that have passed
+    frames: u32,
+    speed: f32,
+    /// The remaining events can never be reached or the limit was reached
+    stopped: bool,
+    /// Events and loop iterations left before the limit is reached
+    remaining: usize,
+    truncated: bool,
+}
+
+impl TimerState {
+    fn wait_until(&mut self, timer: GameFrame) {
+        match self.timer.advance_until(timer, self.speed) {
+            Some((timer, frames)) => {
+                self.timer = timer;
+                self.frames += frames;
+            }
+            None => self.stopped = true,
+        }
+    }
+
+    /// Counts an event or loop iteration towards the limit, returns false and stops once the limit is reached
+    fn spend(&mut self) -> bool {
+        if self.remaining == 0 {
+            self.truncated = true;
+            self.stopped = true;
+            return false;
+        }
+        self.remaining -= 1;
+        true
+    }
+}
+
+fn normalize_block<'a>(events: &'a [Event], state: &mut TimerState, repeating: bool, timed_events: &mut Vec<TimedEvent<'a>>) {
+    let mut i = 0;
+    while i < events.len() && !state.stopped {
+        if !state.spend() {
+            break;
+        }
+        let event = &events[i];
+        timed_events.push(TimedEvent { frame: GameFrame::new(state.frames as f32 + 1.0), event, repeating });
+
+        match (event.namespace, event.code, event.arguments.first()) {
+            (0x00, 0x01, Some(&Argument::Scalar (value))) => {
+                let timer = state.timer.offset(value.as_frames());
+                state.wait_until(timer);
+            }
+            (0x00, 0x02, Some(&Argument::Scalar (value))) => state.wait_until(GameFrame::new(value.as_frames())),
+            (0x04, 0x07, Some(&Argument::Scalar (value))) => state.speed = value.as_frames(),
+            (0x04, 0x14, Some(&Argument::Scalar (value))) => state.timer = GameFrame::new(value.as_frames()),
+            (0x00, 0x04, Some(&Argument::Value (iterations))) => {
+                let end = loop_end(events, i).unwrap_or(events.len());
+                let block = &events[i + 1 .. end];
+                if iterations == -1 {
+                    normalize_block(block, state, true, timed_events);
+                    state.stopped = true;
+                } else {
+                    for _ in 0..iterations {
+                        if state.stopped || !state.spend() {
+                            break;
+                        }
+                        normalize_block(block, state, repeating, timed_events);
+                    }
+                }
+                i = end;
+            }
+            _ => { }
+        }
+        i += 1;
+    }
+}
+
+/// Returns the index of the event that ends the loop started at `start`
+fn loop_end(events: &[Event], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, event) in events.iter().enumerate().skip(start) {
+        match (event.namespace, event.code) {
+            (0x00, 0x04) => depth += 1,
+            (0x00, 0x05) => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => { }
+        }
+    }
+    None
 }
 
 const ARGUMENT_SIZE: usize = 0x8;
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+#[non_exhaustive]
 pub enum Argument {
     Value (i32),
-    Scalar (f32),
+    Scalar (FixedPoint),
     Offset (Offset),
     Bool (bool),
     File (i32),
@@ -192,26 +777,110 @@ pub enum Argument {
     Unknown (i32, i32)
 }
 
-#[derive(Serialize, Clone, Debug)]
+impl Argument {
+    /// Returns the (type, value) pair the argument is stored as
+    pub fn raw(&self) -> (i32, i32) {
+        match self {
+            Argument::Value (value)   => (0, *value),
+            Argument::Scalar (value)  => (1, value.raw),
+            Argument::Offset (offset) => (2, offset.offset),
+            Argument::Bool (value)    => (3, *value as i32),
+            Argument::File (value)    => (4, *value),
+            Argument::Unknown (ty, value) => (*ty, *value),
+            Argument::Variable (variable) => {
+                let value = (variable.memory_type.value() as u32) << 28 | (variable.data_type.value() as u32) << 24 | variable.address & 0x00FFFFFF;
+                (5, value as i32)
+            }
+            Argument::Requirement { ty, flip } => (6, (ty.id() | (*flip as u32) << 31) as i32),
+        }
+    }
+}
+
+/// The fixed point number stored by a Scalar argument.
+/// Nearly every event interprets it in frame units, see `FixedPoint::as_frames`, but the raw value is kept so it can be written back exactly.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+#[serde(into = "FixedPointSerde")]
+#[cfg_attr(feature = "serde-full", serde(from = "FixedPointSerde"))]
+pub struct FixedPoint {
+    pub raw: i32,
+}
+
+impl FixedPoint {
+    /// The divisor used by timers, sizes, offsets, velocities etc.
+    pub const FRAMES_DIVISOR: f32 = 60000.0;
+
+    /// Creates the fixed point number closest to `value` when interpreted in frame units.
+    pub fn from_frames(value: f32) -> FixedPoint {
+        FixedPoint::from_f32(value, FixedPoint::FRAMES_DIVISOR)
+    }
+
+    pub fn from_f32(value: f32, divisor: f32) -> FixedPoint {
+        FixedPoint { raw: (value as f64 * divisor as f64).round() as i32 }
+    }
+
+    /// The value as interpreted by nearly every event
+    pub fn as_frames(&self) -> f32 {
+        self.as_f32(FixedPoint::FRAMES_DIVISOR)
+    }
+
+    /// The value for events that take an angle e.g. hitbox angles and rotations.
+    /// Angles use the same scaling as frames.
+    pub fn as_degrees(&self) -> f32 {
+        self.as_f32(FixedPoint::FRAMES_DIVISOR)
+    }
+
+    /// The value for events that use a different scaling than `as_frames`
+    pub fn as_f32(&self, divisor: f32) -> f32 {
+        self.raw as f32 / divisor
+    }
+}
+
+/// Serialized with the value in frame units under `value`, as scalars were previously serialized, and the exact raw value under `raw`.
+/// Only `raw` is used when deserializing.
+#[derive(Serialize, Clone, Copy)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+struct FixedPointSerde {
+    value: f32,
+    raw:   i32,
+}
+
+impl From<FixedPoint> for FixedPointSerde {
+    fn from(fixed_point: FixedPoint) -> FixedPointSerde {
+        FixedPointSerde { value: fixed_point.as_frames(), raw: fixed_point.raw }
+    }
+}
+
+impl From<FixedPointSerde> for FixedPoint {
+    fn from(fixed_point: FixedPointSerde) -> FixedPoint {
+        FixedPoint { raw: fixed_point.raw }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct Variable {
     pub memory_type: VariableMemoryType,
     pub data_type: VariableDataType,
     pub address: u32,
 }
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct Offset {
     pub offset: i32,
     pub origin: i32,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub enum OffsetType {
     Internal (i32),
     External (String, ),
 }
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum VariableMemoryType {
     /// Known as IC in existing tools
     InternalConstant,
@@ -223,7 +892,7 @@ pub enum VariableMemoryType {
 }
 
 impl VariableMemoryType {
-    fn new(value: u8) -> VariableMemoryType {
+    pub(crate) fn new(value: u8) -> VariableMemoryType {
         match value {
             0 => VariableMemoryType::InternalConstant,
             1 => VariableMemoryType::LongtermAccess,
@@ -231,9 +900,20 @@ impl VariableMemoryType {
             _ => VariableMemoryType::Unknown (value),
         }
     }
+
+    /// The value the memory type is stored as
+    pub(crate) fn value(&self) -> u8 {
+        match self {
+            VariableMemoryType::InternalConstant => 0,
+            VariableMemoryType::LongtermAccess   => 1,
+            VariableMemoryType::RandomAccess     => 2,
+            VariableMemoryType::Unknown (value)  => *value,
+        }
+    }
 }
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum VariableDataType {
     /// Known as Basic in existing tools
     Int,
@@ -245,7 +925,7 @@ pub enum VariableDataType {
 }
 
 impl VariableDataType {
-    fn new(value: u8) -> VariableDataType {
+    pub(crate) fn new(value: u8) -> VariableDataType {
         match value {
             0 => VariableDataType::Int,
             1 => VariableDataType::Float,
@@ -253,9 +933,21 @@ impl VariableDataType {
             _ => VariableDataType::Unknown (value),
         }
     }
+
+    /// The value the data type is stored as
+    pub(crate) fn value(&self) -> u8 {
+        match self {
+            VariableDataType::Int             => 0,
+            VariableDataType::Float           => 1,
+            VariableDataType::Bool            => 2,
+            VariableDataType::Unknown (value) => *value,
+        }
+    }
 }
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+#[non_exhaustive]
 pub enum Requirement {
     CharacterExists,
     AnimationEnd,
@@ -332,72 +1024,445 @@ pub enum Requirement {
 impl Requirement {
     fn new(value: u32) -> Argument {
         let flip = value >> 31 == 1;
-        let ty = match value & 0xFFFF {
-            0x0000 => Requirement::CharacterExists,
-            0x0001 => Requirement::AnimationEnd,
-            0x0002 => Requirement::AnimationHasLooped,
-            0x0003 => Requirement::OnGround,
-            0x0004 => Requirement::InAir,
-            0x0005 => Requirement::HoldingALedge,
-            0x0006 => Requirement::OnAPassableFloor,
-            0x0007 => Requirement::Comparison,
-            0x0008 => Requirement::BoolIsTrue,
-            0x0009 => Requirement::FacingRight,
-            0x000A => Requirement::FacingLeft,
-            0x000B => Requirement::HitboxConnects,
-            0x000C => Requirement::TouchingAFloorWallOrCeiling,
-            0x000D => Requirement::IsThrowingSomeone,
-            0x000F => Requirement::ButtonTap,
-            0x0014 => Requirement::EnteringOrIsInHitLag,
-            0x0015 => Requirement::ArticleExists,
-            0x0016 => Requirement::IsOversteppingAnEdge,
-            0x0017 => Requirement::HasAFloorBelowThePlayer,
-            0x001B => Requirement::ChangeInAirGroundState,
-            0x001C => Requirement::ArticleAvailable,
-            0x001D => Requirement::CurrentTriggeredStatusID,
-            0x001F => Requirement::HoldingItem,
-            0x0020 => Requirement::HoldingItemOfType,
-            0x0021 => Requirement::LightItemIsInGrabRange,
-            0x0022 => Requirement::HeavyItemIsInGrabRange,
-            0x0023 => Requirement::ItemOfTypeIsInGrabbingRange,
-            0x0024 => Requirement::TurningWithItem,
-            0x002A => Requirement::InWater,
-            0x002B => Requirement::RollADie,
-            0x002C => Requirement::SubactionExists,
-            0x002E => Requirement::ButtonMashingOrStatusExpiredSleepBuryFreeze,
-            0x002F => Requirement::IsNotInDamagingLens,
-            0x0030 => Requirement::ButtonPress,
-            0x0031 => Requirement::ButtonRelease,
-            0x0032 => Requirement::ButtonHeld,
-            0x0033 => Requirement::ButtonNotPressed,
-            0x0034 => Requirement::StickDirectionPressed,
-            0x0035 => Requirement::StickDirectionNotPressed,
-            0x0037 => Requirement::IsBeingThrownBySomeone1,
-            0x0038 => Requirement::IsBeingThrownBySomeone2,
-            0x0039 => Requirement::HasntTethered3Times,
-            0x003a => Requirement::HasPassedOverAnEdgeForward,
-            0x003b => Requirement::HasPassedOverAnEdgeBackward,
-            0x003c => Requirement::IsHoldingSomeoneInGrab,
-            0x003d => Requirement::HitboxHasConnected,
-            0x0047 => Requirement::PickUpItem,
-            0x004C => Requirement::HitByCapeEffect,
-            0x004D => Requirement::SDIInput,
-            0x004E => Requirement::ShieldInputPress,
-            0x004f => Requirement::ShieldInputHeld,
-            0x0050 => Requirement::TauntInputPress,
-            0x0051 => Requirement::TauntInputHeld,
-            0x0060 => Requirement::ThreadIsNull,
-            0x00FF => Requirement::Always,
-            0x2711 => Requirement::InWalljump,
-            0x2712 => Requirement::InWallCling,
-            0x2713 => Requirement::InFootstoolRange,
-            0x2716 => Requirement::IsFallingOrHitDown,
-            0x2717 => Requirement::HasSmashBall,
-            0x2719 => Requirement::CanPickupAnotherItem,
-            0x271D => Requirement::FSmashShortcut,
-            0x2725 => Requirement::TapJumpOn,
-            v      => Requirement::Unknown (v),
-        };
+        let ty = Requirement::from_id(value & 0xFFFF);
         Argument::Requirement { ty, flip }
     }
+
+    pub(crate) fn from_id(id: u32) -> Requirement {
+        REQUIREMENTS.iter()
+            .find(|(x, _, _)| *x == id)
+            .map(|(_, _, requirement)| requirement.clone())
+            .unwrap_or(Requirement::Unknown (id))
+    }
+
+    /// The value the requirement is stored as, excluding the flip bit
+    pub fn id(&self) -> u32 {
+        match self {
+            Requirement::Unknown (id) => *id,
+            requirement => REQUIREMENTS.iter()
+                .find(|(_, _, x)| x == requirement)
+                .map(|(id, _, _)| *id)
+                .unwrap(),
+        }
+    }
+
+    /// The name of the variant, `Unknown` for requirements that are not named yet
+    pub fn name(&self) -> &'static str {
+        match self {
+            Requirement::Unknown (_) => "Unknown",
+            requirement => REQUIREMENTS.iter()
+                .find(|(_, _, x)| x == requirement)
+                .map(|(_, name, _)| *name)
+                .unwrap(),
+        }
+    }
+
+    /// Returns the named requirement matching the name returned by `Requirement::name`, ignoring case
+    pub fn from_name(name: &str) -> Option<Requirement> {
+        REQUIREMENTS.iter()
+            .find(|(_, x, _)| x.eq_ignore_ascii_case(name))
+            .map(|(_, _, requirement)| requirement.clone())
+    }
+}
+
+/// Every named requirement with its id and name.
+const REQUIREMENTS: &[(u32, &str, Requirement)] = &[
+    (0x0000, "CharacterExists",                             Requirement::CharacterExists),
+    (0x0001, "AnimationEnd",                                Requirement::AnimationEnd),
+    (0x0002, "AnimationHasLooped",                          Requirement::AnimationHasLooped),
+    (0x0003, "OnGround",                                    Requirement::OnGround),
+    (0x0004, "InAir",                                       Requirement::InAir),
+    (0x0005, "HoldingALedge",                               Requirement::HoldingALedge),
+    (0x0006, "OnAPassableFloor",                            Requirement::OnAPassableFloor),
+    (0x0007, "Comparison",                                  Requirement::Comparison),
+    (0x0008, "BoolIsTrue",                                  Requirement::BoolIsTrue),
+    (0x0009, "FacingRight",                                 Requirement::FacingRight),
+    (0x000A, "FacingLeft",                                  Requirement::FacingLeft),
+    (0x000B, "HitboxConnects",                              Requirement::HitboxConnects),
+    (0x000C, "TouchingAFloorWallOrCeiling",                 Requirement::TouchingAFloorWallOrCeiling),
+    (0x000D, "IsThrowingSomeone",                           Requirement::IsThrowingSomeone),
+    (0x000F, "ButtonTap",                                   Requirement::ButtonTap),
+    (0x0014, "EnteringOrIsInHitLag",                        Requirement::EnteringOrIsInHitLag),
+    (0x0015, "ArticleExists",                               Requirement::ArticleExists),
+    (0x0016, "IsOversteppingAnEdge",                        Requirement::IsOversteppingAnEdge),
+    (0x0017, "HasAFloorBelowThePlayer",                     Requirement::HasAFloorBelowThePlayer),
+    (0x001B, "ChangeInAirGroundState",                      Requirement::ChangeInAirGroundState),
+    (0x001C, "ArticleAvailable",                            Requirement::ArticleAvailable),
+    (0x001D, "CurrentTriggeredStatusID",                    Requirement::CurrentTriggeredStatusID),
+    (0x001F, "HoldingItem",                                 Requirement::HoldingItem),
+    (0x0020, "HoldingItemOfType",                           Requirement::HoldingItemOfType),
+    (0x0021, "LightItemIsInGrabRange",                      Requirement::LightItemIsInGrabRange),
+    (0x0022, "HeavyItemIsInGrabRange",                      Requirement::HeavyItemIsInGrabRange),
+    (0x0023, "ItemOfTypeIsInGrabbingRange",                 Requirement::ItemOfTypeIsInGrabbingRange),
+    (0x0024, "TurningWithItem",                             Requirement::TurningWithItem),
+    (0x002A, "InWater",                                     Requirement::InWater),
+    (0x002B, "RollADie",                                    Requirement::RollADie),
+    (0x002C, "SubactionExists",                             Requirement::SubactionExists),
+    (0x002E, "ButtonMashingOrStatusExpiredSleepBuryFreeze", Requirement::ButtonMashingOrStatusExpiredSleepBuryFreeze),
+    (0x002F, "IsNotInDamagingLens",                         Requirement::IsNotInDamagingLens),
+    (0x0030, "ButtonPress",                                 Requirement::ButtonPress),
+    (0x0031, "ButtonRelease",                               Requirement::ButtonRelease),
+    (0x0032, "ButtonHeld",                                  Requirement::ButtonHeld),
+    (0x0033, "ButtonNotPressed",                            Requirement::ButtonNotPressed),
+    (0x0034, "StickDirectionPressed",                       Requirement::StickDirectionPressed),
+    (0x0035, "StickDirectionNotPressed",                    Requirement::StickDirectionNotPressed),
+    (0x0037, "IsBeingThrownBySomeone1",                     Requirement::IsBeingThrownBySomeone1),
+    (0x0038, "IsBeingThrownBySomeone2",                     Requirement::IsBeingThrownBySomeone2),
+    (0x0039, "HasntTethered3Times",                         Requirement::HasntTethered3Times),
+    (0x003A, "HasPassedOverAnEdgeForward",                  Requirement::HasPassedOverAnEdgeForward),
+    (0x003B, "HasPassedOverAnEdgeBackward",                 Requirement::HasPassedOverAnEdgeBackward),
+    (0x003C, "IsHoldingSomeoneInGrab",                      Requirement::IsHoldingSomeoneInGrab),
+    (0x003D, "HitboxHasConnected",                          Requirement::HitboxHasConnected),
+    (0x0047, "PickUpItem",                                  Requirement::PickUpItem),
+    (0x004C, "HitByCapeEffect",                             Requirement::HitByCapeEffect),
+    (0x004D, "SDIInput",                                    Requirement::SDIInput),
+    (0x004E, "ShieldInputPress",                            Requirement::ShieldInputPress),
+    (0x004F, "ShieldInputHeld",                             Requirement::ShieldInputHeld),
+    (0x0050, "TauntInputPress",                             Requirement::TauntInputPress),
+    (0x0051, "TauntInputHeld",                              Requirement::TauntInputHeld),
+    (0x0060, "ThreadIsNull",                                Requirement::ThreadIsNull),
+    (0x00FF, "Always",                                      Requirement::Always),
+    (0x2711, "InWalljump",                                  Requirement::InWalljump),
+    (0x2712, "InWallCling",                                 Requirement::InWallCling),
+    (0x2713, "InFootstoolRange",                            Requirement::InFootstoolRange),
+    (0x2716, "IsFallingOrHitDown",                          Requirement::IsFallingOrHitDown),
+    (0x2717, "HasSmashBall",                                Requirement::HasSmashBall),
+    (0x2719, "CanPickupAnotherItem",                        Requirement::CanPickupAnotherItem),
+    (0x271D, "FSmashShortcut",                              Requirement::FSmashShortcut),
+    (0x2725, "TapJumpOn",                                   Requirement::TapJumpOn),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(namespace: u8, code: u8, arguments: Vec<Argument>) -> Event {
+        Event { namespace, code, unk1: 0, arguments }
+    }
+
+    fn sync_wait(frames: f32) -> Event { event(0x00, 0x01, vec!(Argument::Scalar (FixedPoint::from_frames(frames)))) }
+    fn async_wait(frames: f32) -> Event { event(0x00, 0x02, vec!(Argument::Scalar (FixedPoint::from_frames(frames)))) }
+    fn marker(id: i32) -> Event { event(0x06, 0x00, vec!(Argument::Value (id))) }
+
+    /// Returns the frames of the marker events
+    fn marker_frames(events: Vec<Event>) -> Vec<(i32, f32, bool)> {
+        let script = Script { events, offset: 0, source: DataSource::File { offset: 0 } };
+        normalize_timers(&script).events.iter().filter_map(|x| match (x.event.namespace, x.event.arguments.first()) {
+            (0x06, Some(&Argument::Value (id))) => Some((id, x.frame.as_f32(), x.repeating)),
+            _ => None
+        }).collect()
+    }
+
+    fn offset(offset: i32, origin: i32) -> Argument { Argument::Offset (Offset { offset, origin }) }
+    fn subroutine(target: i32) -> Event { event(0x00, 0x07, vec!(offset(target, 0))) }
+
+    /// Writes a script of Subroutine and Goto events at each `(offset, targets)` into `data`, a negative target is written as a Goto.
+    /// Arguments are written starting at `arguments_offset`.
+    fn write_scripts(data: &mut [u8], arguments_offset: usize, scripts: &[(usize, &[i32])]) {
+        let mut argument = arguments_offset;
+        for (offset, targets) in scripts {
+            for (i, target) in targets.iter().enumerate() {
+                let event = offset + i * EVENT_SIZE;
+                let code = if *target < 0 { 0x09 } else { 0x07 };
+                data[event .. event + 4].copy_from_slice(&[0x00, code, 0x01, 0x00]);
+                data[event + 4 .. event + 8].copy_from_slice(&(argument as u32).to_be_bytes());
+                data[argument .. argument + 4].copy_from_slice(&2_i32.to_be_bytes());
+                data[argument + 4 .. argument + 8].copy_from_slice(&target.abs().to_be_bytes());
+                argument += ARGUMENT_SIZE;
+            }
+        }
+    }
+
+    #[test]
+    fn fragment_script_offsets() {
+        let mut data = vec!(0; 0x400);
+        write_scripts(&mut data, 0x300, &[
+            (0x100, &[0x200, 0x140]),
+            (0x200, &[-0x100, -0x240]),
+        ]);
+        let known = Script { offset: 0x10, source: DataSource::File { offset: 0x10 }, events: vec!(
+            subroutine(0x100),
+            event(0x00, 0x09, vec!(offset(0x10, 0))),
+            event(0x00, 0x07, vec!(Argument::Value (0x180))),
+            event(0x0D, 0x00, vec!(Argument::Value (0), offset(0x140, 0))),
+            event(0x00, 0x07, vec!(offset(0x1C0, 0x999))),
+            subroutine(0x100),
+        )};
+
+        let fragments = fragment_scripts(FancySlice::new(&data), &[&known], &[0x999], &mut ScriptParser::new(&WiiMemory::new(), &mut EventBudget::new(ScriptLimits::default())));
+        let offsets: Vec<i32> = fragments.iter().map(|x| x.offset).collect();
+        assert_eq!(offsets, vec!(0x100, 0x140, 0x180, 0x200, 0x240));
+        assert_eq!(fragments[0].events.len(), 2);
+        assert_eq!(fragments[3].events.len(), 2);
+    }
+
+    /// Returns data filled with events that have no arguments and never end the script
+    fn endless_events(len: usize) -> Vec<u8> {
+        [0x04, 0x07, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00].iter().cloned().cycle().take(len).collect()
+    }
+
+    #[test]
+    fn script_limit() {
+        let data = endless_events(0x1000);
+        let wii_memory = WiiMemory::new();
+        let mut budget = EventBudget::new(ScriptLimits { max_events_per_script: 100, ..ScriptLimits::default() });
+        let mut parser = ScriptParser::new(&wii_memory, &mut budget);
+        let script = parser.script(FancySlice::new(&data), 0x10);
+        assert_eq!(script.events.len(), 100);
+
+        let warnings = parser.take_warnings("gameAnimCmd_Test");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].section, "gameAnimCmd_Test");
+        assert_eq!(warnings[0].script_offset, Some(0x10));
+        assert!(parser.take_warnings("gameAnimCmd_Test").is_empty());
+    }
+
+    #[test]
+    fn script_past_end_of_data() {
+        let data = endless_events(0x100);
+        let wii_memory = WiiMemory::new();
+        let mut budget = EventBudget::new(ScriptLimits::default());
+        let mut parser = ScriptParser::new(&wii_memory, &mut budget);
+        let script = parser.script(FancySlice::new(&data), 0x80);
+        assert_eq!(script.events.len(), 0x10);
+        let warnings = parser.take_warnings("data");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].script_offset, Some(0x80));
+    }
+
+    #[test]
+    fn script_past_declared_data_end() {
+        let data = endless_events(0x100);
+        let wii_memory = WiiMemory::new();
+        let mut budget = EventBudget::new(ScriptLimits::default());
+        let mut parser = ScriptParser::new(&wii_memory, &mut budget).with_data_end(0xC0);
+
+        // stops at the declared end even though the parent data continues
+        let script = parser.script(FancySlice::new(&data), 0x80);
+        assert_eq!(script.events.len(), 8);
+        let warnings = parser.take_warnings("data");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].script_offset, Some(0x80));
+        assert!(warnings[0].message.contains("0xc0"));
+
+        // a script after the declared end e.g. appended by PSA, continues to the end of the parent data
+        let script = parser.script(FancySlice::new(&data), 0xC0);
+        assert_eq!(script.events.len(), 8);
+        assert_eq!(parser.take_warnings("data")[0].script_offset, Some(0xC0));
+    }
+
+    #[test]
+    fn fighter_limit() {
+        let mut data = endless_events(0x1000);
+        // end the first script after 6 events
+        data[0x40 .. 0x48].copy_from_slice(&[0; 8]);
+        let wii_memory = WiiMemory::new();
+        let mut budget = EventBudget::new(ScriptLimits { max_events_per_script: 100, max_events_per_fighter: 10 });
+
+        // the budget is shared by every file of the fighter, each file has its own parser
+        let mut parser = ScriptParser::new(&wii_memory, &mut budget);
+        assert_eq!(parser.script(FancySlice::new(&data), 0x10).events.len(), 6);
+        assert!(parser.take_warnings("data").is_empty());
+
+        let mut parser = ScriptParser::new(&wii_memory, &mut budget);
+        let scripts: Vec<Script> = [0x100, 0x200, 0x300].iter().map(|x| parser.script(FancySlice::new(&data), *x)).collect();
+        assert_eq!(scripts[0].events.len(), 4);
+        assert_eq!(scripts[1].events.len(), 0);
+        assert_eq!(scripts[2].events.len(), 0);
+
+        let warnings = parser.take_warnings("data");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].script_offset, Some(0x100));
+    }
+
+    /// Run with `cargo test --release fragment_scripts_10k_events -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn fragment_scripts_10k_events() {
+        let count = 10_000;
+        let data = vec!(0; count * EVENT_SIZE + 0x10);
+        let known = Script { offset: 0, source: DataSource::File { offset: 0 }, events: (0..count).map(|i| subroutine(0x10 + (i * EVENT_SIZE) as i32)).collect() };
+
+        let start = std::time::Instant::now();
+        let fragments = fragment_scripts(FancySlice::new(&data), &[&known], &[], &mut ScriptParser::new(&WiiMemory::new(), &mut EventBudget::new(ScriptLimits::default())));
+        println!("fragment_scripts on {} events took {:?}", count, start.elapsed());
+        assert_eq!(fragments.len(), count);
+    }
+
+    #[test]
+    fn async_then_sync() {
+        let frames = marker_frames(vec!(marker(0), async_wait(10.0), marker(1), sync_wait(5.0), marker(2)));
+        assert_eq!(frames, vec!((0, 1.0, false), (1, 11.0, false), (2, 16.0, false)));
+    }
+
+    #[test]
+    fn async_already_passed() {
+        let frames = marker_frames(vec!(async_wait(10.0), async_wait(5.0), marker(0), async_wait(12.0), marker(1)));
+        assert_eq!(frames, vec!((0, 11.0, false), (1, 13.0, false)));
+    }
+
+    #[test]
+    fn frame_speed() {
+        let frames = marker_frames(vec!(
+            event(0x04, 0x07, vec!(Argument::Scalar (FixedPoint::from_frames(2.0)))),
+            sync_wait(10.0),
+            marker(0),
+            sync_wait(3.0),
+            marker(1),
+            event(0x04, 0x07, vec!(Argument::Scalar (FixedPoint::from_frames(0.0)))),
+            sync_wait(1.0),
+            marker(2),
+        ));
+        assert_eq!(frames, vec!((0, 6.0, false), (1, 8.0, false)));
+    }
+
+    #[test]
+    fn loops() {
+        let frames = marker_frames(vec!(
+            event(0x00, 0x04, vec!(Argument::Value (2))),
+            marker(0),
+            sync_wait(3.0),
+            event(0x00, 0x05, vec!()),
+            event(0x00, 0x04, vec!(Argument::Value (-1))),
+            marker(1),
+            sync_wait(1.0),
+            event(0x00, 0x05, vec!()),
+            marker(2),
+        ));
+        assert_eq!(frames, vec!((0, 1.0, false), (0, 4.0, false), (1, 7.0, true)));
+    }
+
+    #[test]
+    fn nested_loops_are_capped() {
+        let events = vec!(
+            event(0x00, 0x04, vec!(Argument::Value (i32::MAX))),
+            event(0x00, 0x04, vec!(Argument::Value (i32::MAX))),
+            marker(0),
+            event(0x00, 0x05, vec!()),
+            event(0x00, 0x05, vec!()),
+            marker(1),
+        );
+        let script = Script { events, offset: 0, source: DataSource::File { offset: 0 } };
+
+        let timed_events = normalize_timers(&script);
+        assert!(timed_events.truncated);
+        assert!(timed_events.events.len() <= ScriptLimits::default().max_events_per_script);
+        assert!(timed_events.events.iter().all(|x| x.event.arguments.first() != Some(&Argument::Value (1))));
+
+        // the two loop events and three iterations of the marker, each loop iteration also counts towards the limit
+        let limits = ScriptLimits { max_events_per_script: 9, max_events_per_fighter: 9 };
+        let timed_events = normalize_timers_with_limits(&script, &limits);
+        assert!(timed_events.truncated);
+        assert_eq!(timed_events.events.len(), 5);
+    }
+
+    #[test]
+    fn event_classes() {
+        assert_eq!(sync_wait(3.0).class(), EventClass::Gameplay);
+        assert_eq!(marker(0).class(), EventClass::Gameplay);
+        assert_eq!(event(0x0A, 0x00, vec!(Argument::Value (0x1234))).class(), EventClass::Cosmetic);
+        assert_eq!(event(0x11, 0x00, vec!()).class(), EventClass::Cosmetic);
+        assert_eq!(event(0x07, 0x07, vec!()).class(), EventClass::Cosmetic);
+        assert_eq!(event(0x00, 0x0A, vec!()).class(), EventClass::ControlFlow);
+        assert_eq!(event(0x99, 0x00, vec!()).class(), EventClass::Unknown);
+        assert_eq!(event(0x0E, 0x00, vec!(Argument::Value (0))).class(), EventClass::Gameplay);
+        assert_eq!(event(0x0C, 0x23, vec!()).class(), EventClass::Gameplay);
+    }
+
+    /// Every event that `ScriptAst` recognizes should have a class
+    #[test]
+    fn script_ast_events_classified() {
+        let mut checked = 0;
+        for line in include_str!("script_ast/mod.rs").lines().map(|x| x.trim_start()) {
+            // match arms of the form `(0x06, 0x00, ...) =>`
+            if !line.starts_with("(0x") || !line.contains("=>") || line.get(5..9) != Some(", 0x") {
+                continue;
+            }
+            let namespace = u8::from_str_radix(&line[3..5], 16).unwrap();
+            let code = u8::from_str_radix(&line[9..11], 16).unwrap();
+            assert_ne!(event(namespace, code, vec!()).class(), EventClass::Unknown, "0x{:02X} 0x{:02X} is not classified", namespace, code);
+            checked += 1;
+        }
+        assert!(checked > 100, "Only found {} events", checked);
+    }
+
+    #[test]
+    fn fixed_point_interpretations() {
+        let fixed_point = FixedPoint { raw: 90000 };
+        assert_eq!(fixed_point.as_frames(), 1.5);
+        assert_eq!(fixed_point.as_f32(100.0), 900.0);
+        assert_eq!(FixedPoint::from_frames(1.5), fixed_point);
+        assert_eq!(FixedPoint::from_f32(900.0, 100.0), fixed_point);
+        assert_eq!(FixedPoint { raw: 361 * 60000 }.as_degrees(), 361.0);
+    }
+
+    #[test]
+    fn fixed_point_raw_preserved() {
+        // a value that does not survive a round trip through f32 frame units
+        let raw = 0x7FFF_FFF1;
+        assert_ne!(FixedPoint::from_frames(FixedPoint { raw }.as_frames()).raw, raw);
+
+        let mut data = vec!(0; 8);
+        data.extend(&[0x00, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x18]);
+        data.extend(&[0; 8]);
+        data.extend(&1_i32.to_be_bytes());
+        data.extend(&raw.to_be_bytes());
+        let script = new_script(FancySlice::new(&data), 8, &WiiMemory::new());
+        match script.events[0].arguments[0] {
+            Argument::Scalar (fixed_point) => assert_eq!(fixed_point.raw, raw),
+            _ => panic!("Expected a scalar"),
+        }
+    }
+
+    #[cfg(all(feature = "serde-full", feature = "serde_json"))]
+    #[test]
+    fn fixed_point_serde() {
+        let json = serde_json::to_string(&Argument::Scalar (FixedPoint::from_frames(1.5))).unwrap();
+        assert_eq!(json, r#"{"Scalar":{"value":1.5,"raw":90000}}"#);
+        match serde_json::from_str(&json).unwrap() {
+            Argument::Scalar (fixed_point) => assert_eq!(fixed_point, FixedPoint::from_frames(1.5)),
+            _ => panic!("Expected a scalar"),
+        }
+
+        // a value that does not survive a round trip through f32 frame units
+        let argument = Argument::Scalar (FixedPoint { raw: 0x7FFF_FFF1 });
+        let json = serde_json::to_string(&argument).unwrap();
+        assert_eq!(json, r#"{"Scalar":{"value":35791.395,"raw":2147483633}}"#);
+        match serde_json::from_str(&json).unwrap() {
+            Argument::Scalar (fixed_point) => assert_eq!(fixed_point.raw, 0x7FFF_FFF1),
+            _ => panic!("Expected a scalar"),
+        }
+    }
+
+    #[cfg(feature = "export-compact")]
+    #[test]
+    fn fixed_point_compressed_fighter() {
+        use crate::fighter::Fighter;
+        use crate::test_util::TestFighterBuilder;
+
+        let fighter = TestFighterBuilder::new("Test")
+            .subaction("AttackS3S", |s| s
+                .async_timer(5.0)
+                .event(0x00, 0x02, vec!(Argument::Scalar (FixedPoint { raw: 0x7FFF_FFF1 })))
+            )
+            .build();
+        let decoded = Fighter::from_compressed(&fighter.to_compressed().unwrap()).unwrap();
+        let events = &decoded.get_fighter_data().unwrap().subaction_main[0].events;
+        assert_eq!(events[0].arguments, vec!(Argument::Scalar (FixedPoint::from_frames(5.0))));
+        assert_eq!(events[1].arguments, vec!(Argument::Scalar (FixedPoint { raw: 0x7FFF_FFF1 })));
+    }
+
+    #[test]
+    fn requirement_round_trip() {
+        let ids: HashSet<_> = REQUIREMENTS.iter().map(|(x, _, _)| x).collect();
+        assert_eq!(ids.len(), REQUIREMENTS.len());
+        for (id, name, requirement) in REQUIREMENTS {
+            assert_eq!(Requirement::from_id(*id), *requirement);
+            assert_eq!(requirement.id(), *id);
+            assert_eq!(requirement.name(), *name);
+            assert_eq!(Requirement::from_name(&name.to_lowercase()).as_ref(), Some(requirement));
+        }
+        assert_eq!(Requirement::Unknown (0x1234).id(), 0x1234);
+        assert_eq!(Requirement::from_name("Unknown"), None);
+    }
 }
@@ -0,0 +1,1311 @@
+//! A small text format for scripts, `disassemble` writes a script as text and `assemble` converts the text back into a script.
+//!
+//! Events are written one per line or separated by `;`, the event name is followed by its arguments:
+//! ```text
+//! AsyncTimer 14; Hitbox id=0 damage=12 angle=361 kbg=100 bkb=30 size=4.5 bone=16
+//! AsyncTimer 18
+//! TerminateCollisions // comments run until the end of the line
+//! ```
+//! *   Event names are case insensitive and common events have aliases e.g. `Hitbox` for `OffensiveCollision`.
+//! *   Arguments are given in order or by name, the two can not be mixed in one event.
+//!     Named arguments that are left out use their default value.
+//!     Some events pack several named fields into a single argument e.g. the bone, set id and id of a hitbox.
+//! *   The type of an argument is inferred from the event:
+//!     scalars are numbers in frame units, variables are written as `LA-Basic[3]` and requirements by name e.g. `OnGround` or `!OnGround` when flipped.
+//! *   The type can be given explicitly with a prefix:
+//!     `v:` value, `s:` scalar, `fixed:` raw fixed point scalar, `o:` offset, `b:` bool, `f:` file, `var:` variable, `req:` requirement and `type7:` for an unknown argument type 7.
+//! *   Events without a known signature are written as `raw ns=0x06 code=0x04 args=[v:0, s:1.5]`, optionally with `unk=` to set `Event::unk1`.
+//!     `event_decoder::EventDecoderRegistry::disassemble` additionally names the events of mods in a comment after the raw event.
+//!
+//! Offsets are stored relative to where the argument is in the moveset, which is not known until the script is written back to a file.
+//! So assembled offsets have an origin of -1.
+//!
+//! `disassemble_with_symbols` names the scripts called by Subroutine, Goto etc. in a comment e.g. `Subroutine 0x9E48 // -> @0x9E48 (KnockbackCheck)`.
+
+use std::fmt;
+
+use crate::event_decoder::{EventDecoder, DecodedEvent, FieldValue};
+use crate::script::{DataSource, Script, Event, Argument, FixedPoint, Offset, Variable, VariableMemoryType, VariableDataType, Requirement};
+use crate::symbols::SymbolTable;
+
+/// Converts the text format described in the module documentation into a script.
+pub fn assemble(text: &str) -> Result<Script, AssembleError> {
+    let mut events = vec!();
+    for (i, line) in text.lines().enumerate() {
+        for tokens in tokenize(line, i + 1)? {
+            events.push(assemble_event(&tokens, i + 1)?);
+        }
+    }
+    Ok(Script { events, offset: 0, source: DataSource::File { offset: 0 } })
+}
+
+/// Writes the script in the text format described in the module documentation, one event per line.
+pub fn disassemble(script: &Script) -> String {
+    let mut text = String::new();
+    for event in &script.events {
+        text.push_str(&disassemble_event(event));
+        text.push('\n');
+    }
+    text
+}
+
+/// Same as `disassemble` but events that call a script with a symbol in `symbols` are followed by a comment naming the script.
+pub fn disassemble_with_symbols(script: &Script, symbols: &SymbolTable) -> String {
+    let mut text = String::new();
+    for event in &script.events {
+        text.push_str(&disassemble_event(event));
+        if let Some((_, callee)) = event.call_offset() {
+            if symbols.resolve(callee).is_some() {
+                text.push_str(&format!(" // -> {}", symbols.format_offset(callee)));
+            }
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Writes a single event in the text format described in the module documentation.
+pub fn disassemble_event(event: &Event) -> String {
+    if let Some((_, text)) = find_signature(event) {
+        return text;
+    }
+    disassemble_raw(event)
+}
+
+/// Returns the signature the event is written with along with the written event.
+/// None when the event must be written raw.
+fn find_signature(event: &Event) -> Option<(&'static Signature, String)> {
+    if event.unk1 != 0 {
+        return None;
+    }
+    for signature in SIGNATURES.iter().filter(|x| x.matches(event)) {
+        let text = signature.disassemble(event);
+        // Packed fields might not cover every bit of an argument, so only use the signature if it reproduces the event exactly
+        let reassembled = tokenize(&text, 1).ok()
+            .and_then(|x| x.into_iter().next())
+            .and_then(|x| assemble_event(&x, 1).ok());
+        if let Some(reassembled) = reassembled {
+            if same_event(&reassembled, event) {
+                return Some((signature, text));
+            }
+        }
+    }
+    None
+}
+
+/// Writes the event as `raw`, which can represent any event.
+pub(crate) fn disassemble_raw(event: &Event) -> String {
+    let arguments: Vec<String> = event.arguments.iter().map(typed_text).collect();
+    let mut text = format!("raw ns=0x{:02X} code=0x{:02X} args=[{}]", event.namespace, event.code, arguments.join(", "));
+    if event.unk1 != 0 {
+        text.push_str(&format!(" unk=0x{:02X}", event.unk1));
+    }
+    text
+}
+
+/// Decodes the events with a known signature in a single namespace and code, these are the vanilla events of `EventDecoderRegistry`.
+pub(crate) struct SignatureDecoder {
+    namespace: u8,
+    code: u8,
+}
+
+impl SignatureDecoder {
+    /// One decoder for each namespace and code that has a signature
+    pub(crate) fn all() -> Vec<SignatureDecoder> {
+        let mut decoders: Vec<SignatureDecoder> = vec!();
+        for signature in SIGNATURES {
+            if !decoders.iter().any(|x| x.namespace == signature.namespace && x.code == signature.code) {
+                decoders.push(SignatureDecoder { namespace: signature.namespace, code: signature.code });
+            }
+        }
+        decoders
+    }
+
+    pub(crate) fn namespace(&self) -> u8 {
+        self.namespace
+    }
+
+    pub(crate) fn code(&self) -> u8 {
+        self.code
+    }
+}
+
+impl EventDecoder for SignatureDecoder {
+    fn decode(&self, event: &Event) -> Option<DecodedEvent> {
+        if event.namespace != self.namespace || event.code != self.code {
+            return None;
+        }
+        find_signature(event).map(|(signature, _)| signature.decode(event))
+    }
+}
+
+/// An error in the text given to `assemble`
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssembleError {
+    /// Starts at 1
+    pub line: usize,
+    /// Starts at 1
+    pub column: usize,
+    pub kind: AssembleErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AssembleErrorKind {
+    UnknownEvent (String),
+    UnknownField { event: String, field: String },
+    DuplicateField (String),
+    MissingField { event: String, field: String },
+    /// `min` and `max` count the fields of the event, which can differ from the number of arguments the event is stored with.
+    WrongArgumentCount { event: String, min: usize, max: usize, found: usize },
+    MixedArguments,
+    InvalidArgument { value: String, expected: &'static str },
+    UnclosedBracket,
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            AssembleErrorKind::UnknownEvent (name) =>
+                write!(f, "Unknown event {:?}, use `raw` for events without a name", name),
+            AssembleErrorKind::UnknownField { event, field } =>
+                write!(f, "{} has no field {:?}", event, field),
+            AssembleErrorKind::DuplicateField (field) =>
+                write!(f, "The field {:?} is given more than once", field),
+            AssembleErrorKind::MissingField { event, field } =>
+                write!(f, "{} requires the field {:?}", event, field),
+            AssembleErrorKind::WrongArgumentCount { event, min, max, found } if min == max =>
+                write!(f, "{} takes {} arguments but {} were given", event, min, found),
+            AssembleErrorKind::WrongArgumentCount { event, min, max, found } =>
+                write!(f, "{} takes {} to {} arguments but {} were given", event, min, max, found),
+            AssembleErrorKind::MixedArguments =>
+                write!(f, "Positional and named arguments can not be mixed"),
+            AssembleErrorKind::InvalidArgument { value, expected } =>
+                write!(f, "Expected {} but found {:?}", expected, value),
+            AssembleErrorKind::UnclosedBracket =>
+                write!(f, "Missing closing ]"),
+        }
+    }
+}
+
+impl std::error::Error for AssembleError { }
+
+struct Token<'a> {
+    text: &'a str,
+    column: usize,
+}
+
+/// Splits the line into statements and the statements into whitespace separated tokens.
+/// Whitespace and `;` within brackets do not split.
+fn tokenize(line: &str, line_number: usize) -> Result<Vec<Vec<Token<'_>>>, AssembleError> {
+    let line = match line.find("//") {
+        Some(comment) => &line[..comment],
+        None => line,
+    };
+
+    let mut statements = vec!();
+    let mut tokens = vec!();
+    // byte offset and column of the token being read
+    let mut start: Option<(usize, usize)> = None;
+    let mut depth = 0;
+    for (column, (offset, c)) in line.char_indices().enumerate() {
+        if depth == 0 && (c.is_whitespace() || c == ';') {
+            if let Some((start_offset, start_column)) = start.take() {
+                tokens.push(Token { text: &line[start_offset..offset], column: start_column });
+            }
+            if c == ';' && !tokens.is_empty() {
+                statements.push(std::mem::take(&mut tokens));
+            }
+        } else {
+            if start.is_none() {
+                start = Some((offset, column + 1));
+            }
+            match c {
+                '[' => depth += 1,
+                ']' if depth > 0 => depth -= 1,
+                _ => { }
+            }
+        }
+    }
+
+    if let Some((start_offset, start_column)) = start {
+        if depth > 0 {
+            return Err(AssembleError { line: line_number, column: start_column, kind: AssembleErrorKind::UnclosedBracket });
+        }
+        tokens.push(Token { text: &line[start_offset..], column: start_column });
+    }
+    if !tokens.is_empty() {
+        statements.push(tokens);
+    }
+    Ok(statements)
+}
+
+/// Splits `name=value` tokens
+fn split_named(text: &str) -> Option<(&str, &str)> {
+    let (name, value) = text.split_once('=')?;
+    if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        Some((name, value))
+    } else {
+        None
+    }
+}
+
+fn assemble_event(tokens: &[Token], line: usize) -> Result<Event, AssembleError> {
+    let error = |column, kind| AssembleError { line, column, kind };
+    let name = &tokens[0];
+    if name.text.eq_ignore_ascii_case("raw") {
+        return assemble_raw(tokens, line);
+    }
+    let signature = SIGNATURES.iter()
+        .find(|x| x.name.eq_ignore_ascii_case(name.text) || x.aliases.iter().any(|alias| alias.eq_ignore_ascii_case(name.text)))
+        .ok_or_else(|| error(name.column, AssembleErrorKind::UnknownEvent (name.text.to_string())))?;
+
+    // The value given for each field, along with its column
+    let mut given: Vec<Option<(&str, usize)>> = vec!(None; signature.fields.len());
+    let arguments = &tokens[1..];
+    let named = arguments.first().map(|x| split_named(x.text).is_some()).unwrap_or(false);
+    if let Some(mixed) = arguments.iter().find(|x| split_named(x.text).is_some() != named) {
+        return Err(error(mixed.column, AssembleErrorKind::MixedArguments));
+    }
+
+    if named {
+        for token in arguments {
+            let (field_name, value) = split_named(token.text).unwrap();
+            let index = signature.fields.iter().position(|x| x.name.eq_ignore_ascii_case(field_name))
+                .ok_or_else(|| error(token.column, AssembleErrorKind::UnknownField { event: signature.name.to_string(), field: field_name.to_string() }))?;
+            if given[index].is_some() {
+                return Err(error(token.column, AssembleErrorKind::DuplicateField (field_name.to_string())));
+            }
+            given[index] = Some((value, token.column + field_name.len() + 1));
+        }
+    } else {
+        for (i, token) in arguments.iter().enumerate() {
+            match given.get_mut(i) {
+                Some(field) => *field = Some((token.text, token.column)),
+                None => return Err(error(token.column, signature.wrong_argument_count(arguments.len()))),
+            }
+        }
+    }
+
+    let num_arguments = signature.fields.iter().zip(&given)
+        .filter(|(_, value)| value.is_some())
+        .map(|(field, _)| field.argument + 1)
+        .max()
+        .unwrap_or(0)
+        .max(signature.required);
+
+    // fill in defaults
+    for (field, value) in signature.fields.iter().zip(given.iter_mut()) {
+        if field.argument < num_arguments && value.is_none() {
+            match field.default {
+                Some(default) => *value = Some((default, name.column)),
+                None if named => return Err(error(name.column, AssembleErrorKind::MissingField { event: signature.name.to_string(), field: field.name.to_string() })),
+                None => return Err(error(name.column, signature.wrong_argument_count(arguments.len()))),
+            }
+        }
+    }
+
+    let mut event_arguments = vec!();
+    for (i, kind) in signature.arguments.iter().enumerate().take(num_arguments) {
+        let mut packed: u32 = 0;
+        for (field, value) in signature.fields.iter().zip(&given).filter(|(field, _)| field.argument == i) {
+            let (text, column) = value.unwrap();
+            let invalid = |expected| error(column, AssembleErrorKind::InvalidArgument { value: text.to_string(), expected });
+            if field.width == 32 {
+                event_arguments.push(parse_argument(*kind, text).ok_or_else(|| invalid(kind.description()))?);
+            } else {
+                packed |= parse_bits(text, field.width).ok_or_else(|| invalid("an integer that fits the field"))? << field.shift;
+            }
+        }
+        if event_arguments.len() == i {
+            event_arguments.push(Argument::Value (packed as i32));
+        }
+    }
+
+    Ok(Event {
+        namespace: signature.namespace,
+        code:      signature.code,
+        unk1:      0,
+        arguments: event_arguments,
+    })
+}
+
+fn assemble_raw(tokens: &[Token], line: usize) -> Result<Event, AssembleError> {
+    let error = |column, kind| AssembleError { line, column, kind };
+    let mut namespace = None;
+    let mut code = None;
+    let mut unk1 = 0;
+    let mut arguments = vec!();
+    for token in &tokens[1..] {
+        let (field, value) = split_named(token.text)
+            .ok_or_else(|| error(token.column, AssembleErrorKind::MixedArguments))?;
+        let column = token.column + field.len() + 1;
+        let invalid = |expected| error(column, AssembleErrorKind::InvalidArgument { value: value.to_string(), expected });
+        let byte = || parse_int(value).filter(|x| *x >= 0 && *x <= 0xFF).map(|x| x as u8).ok_or_else(|| invalid("a byte"));
+        match field.to_ascii_lowercase().as_str() {
+            "ns"   => namespace = Some(byte()?),
+            "code" => code = Some(byte()?),
+            "unk"  => unk1 = byte()?,
+            "args" => {
+                let list = value.strip_prefix('[').and_then(|x| x.strip_suffix(']'))
+                    .ok_or_else(|| invalid("a list of arguments e.g. [v:0, s:1.5]"))?;
+                arguments.clear();
+                let mut offset = 0;
+                for text in list.split(',') {
+                    let argument_column = column + 1 + list[..offset].chars().count() + (text.len() - text.trim_start().len());
+                    offset += text.len() + 1;
+                    let text = text.trim();
+                    if text.is_empty() && list.trim().is_empty() {
+                        break;
+                    }
+                    let argument = parse_argument(Kind::Any, text)
+                        .ok_or_else(|| error(argument_column, AssembleErrorKind::InvalidArgument { value: text.to_string(), expected: Kind::Any.description() }))?;
+                    arguments.push(argument);
+                }
+                if arguments.len() > 0xFF {
+                    return Err(error(column, AssembleErrorKind::WrongArgumentCount { event: "raw".to_string(), min: 0, max: 0xFF, found: arguments.len() }));
+                }
+            }
+            _ => return Err(error(token.column, AssembleErrorKind::UnknownField { event: "raw".to_string(), field: field.to_string() })),
+        }
+    }
+
+    let missing = |field: &str| error(tokens[0].column, AssembleErrorKind::MissingField { event: "raw".to_string(), field: field.to_string() });
+    Ok(Event {
+        namespace: namespace.ok_or_else(|| missing("ns"))?,
+        code:      code.ok_or_else(|| missing("code"))?,
+        unk1,
+        arguments,
+    })
+}
+
+fn same_event(a: &Event, b: &Event) -> bool {
+    a.namespace == b.namespace && a.code == b.code && a.unk1 == b.unk1 &&
+        a.arguments.len() == b.arguments.len() &&
+        a.arguments.iter().zip(&b.arguments).all(|(a, b)| same_argument(a, b))
+}
+
+/// Offsets are compared without their origin as it is lost when assembling
+fn same_argument(a: &Argument, b: &Argument) -> bool {
+    match (a, b) {
+        (Argument::Offset (a), Argument::Offset (b)) => a.offset == b.offset,
+        (a, b) => a == b,
+    }
+}
+
+/// Parses decimal or 0x prefixed hex integers, values up to u32::MAX are wrapped into an i32
+fn parse_int(text: &str) -> Option<i32> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None         => (false, text),
+    };
+    if !digits.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let value = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None      => digits.parse::<i64>().ok()?,
+    };
+    let value = if negative { -value } else { value };
+    if value >= i32::MIN as i64 && value <= u32::MAX as i64 {
+        Some(value as i32)
+    } else {
+        None
+    }
+}
+
+/// Parses an integer that fits in `width` bits as either a signed or unsigned number
+fn parse_bits(text: &str, width: u32) -> Option<u32> {
+    let value = parse_int(text)?;
+    let fits_unsigned = (value as u32) >> width == 0;
+    let fits_signed = value < 0 && value >= -(1 << (width - 1));
+    if fits_unsigned || fits_signed {
+        Some(value as u32 & ((1 << width) - 1))
+    } else {
+        None
+    }
+}
+
+fn parse_argument(kind: Kind, text: &str) -> Option<Argument> {
+    let argument = match text.split_once(':') {
+        Some((prefix, value)) => parse_typed(prefix, value)?,
+        None                  => parse_untyped(kind, text)?,
+    };
+    if kind.accepts(&argument) {
+        Some(argument)
+    } else {
+        None
+    }
+}
+
+fn parse_typed(prefix: &str, value: &str) -> Option<Argument> {
+    match prefix {
+        "v"     => Some(Argument::Value (parse_int(value)?)),
+        "s"     => parse_untyped(Kind::Scalar, value),
+        "fixed" => Some(Argument::Scalar (FixedPoint { raw: parse_int(value)? })),
+        "o"     => parse_untyped(Kind::Offset, value),
+        "b"     => parse_untyped(Kind::Bool, value),
+        "f"     => parse_untyped(Kind::File, value),
+        "var"   => parse_untyped(Kind::Variable, value),
+        "req"   => parse_untyped(Kind::Requirement, value),
+        _ => {
+            let ty = prefix.strip_prefix("type")?.parse().ok()?;
+            Some(Argument::Unknown (ty, parse_int(value)?))
+        }
+    }
+}
+
+fn parse_untyped(kind: Kind, text: &str) -> Option<Argument> {
+    match kind {
+        Kind::Value       => Some(Argument::Value (parse_int(text)?)),
+        Kind::Scalar      => text.parse::<f32>().ok().filter(|x| x.is_finite()).map(|x| Argument::Scalar (FixedPoint::from_frames(x))),
+        Kind::Offset      => Some(Argument::Offset (Offset { offset: parse_int(text)?, origin: -1 })),
+        Kind::Bool        => text.parse().ok().map(Argument::Bool),
+        Kind::File        => Some(Argument::File (parse_int(text)?)),
+        Kind::Variable    => parse_variable(text).map(Argument::Variable),
+        Kind::Requirement => parse_requirement(text),
+        Kind::ValueOrVariable if text.contains('[')  => parse_untyped(Kind::Variable, text),
+        Kind::ValueOrVariable                        => parse_untyped(Kind::Value, text),
+        Kind::ScalarOrVariable if text.contains('[') => parse_untyped(Kind::Variable, text),
+        Kind::ScalarOrVariable                       => parse_untyped(Kind::Scalar, text),
+        Kind::Any => if text == "true" || text == "false" {
+            parse_untyped(Kind::Bool, text)
+        } else if text.contains('.') {
+            parse_untyped(Kind::Scalar, text)
+        } else {
+            parse_untyped(Kind::ValueOrVariable, text)
+        }
+    }
+}
+
+fn parse_variable(text: &str) -> Option<Variable> {
+    let (head, address) = text.strip_suffix(']')?.split_once('[')?;
+    let (memory_type, data_type) = head.split_once('-')?;
+    let memory_type = match memory_type.to_ascii_uppercase().as_str() {
+        "IC" => 0,
+        "LA" => 1,
+        "RA" => 2,
+        other => other.parse().ok().filter(|x| *x < 0x10)?,
+    };
+    let data_type = match data_type.to_ascii_lowercase().as_str() {
+        "basic" => 0,
+        "float" => 1,
+        "bit"   => 2,
+        other => other.parse().ok().filter(|x| *x < 0x10)?,
+    };
+    let address = parse_int(address).filter(|x| *x >= 0 && *x <= 0xFFFFFF)? as u32;
+    Some(Variable {
+        memory_type: VariableMemoryType::new(memory_type),
+        data_type:   VariableDataType::new(data_type),
+        address,
+    })
+}
+
+fn parse_requirement(text: &str) -> Option<Argument> {
+    let (flip, name) = match text.strip_prefix('!') {
+        Some(name) => (true, name),
+        None       => (false, text),
+    };
+    let ty = match Requirement::from_name(name) {
+        Some(ty) => ty,
+        None => Requirement::from_id(parse_int(name).filter(|x| *x >= 0 && *x <= 0xFFFF)? as u32),
+    };
+    Some(Argument::Requirement { flip, ty })
+}
+
+/// The text of an argument without a type prefix, None if the argument can only be written with a type prefix
+pub(crate) fn untyped_text(argument: &Argument) -> Option<String> {
+    Some(match argument {
+        Argument::Value (value)  => value.to_string(),
+        Argument::Scalar (value) => value.as_frames().to_string(),
+        Argument::Offset (offset) if offset.offset < 0 => offset.offset.to_string(),
+        Argument::Offset (offset) => format!("0x{:X}", offset.offset),
+        Argument::Bool (value)   => value.to_string(),
+        Argument::File (value)   => value.to_string(),
+        Argument::Variable (variable) => {
+            let memory_type = match variable.memory_type {
+                VariableMemoryType::InternalConstant => "IC".to_string(),
+                VariableMemoryType::LongtermAccess   => "LA".to_string(),
+                VariableMemoryType::RandomAccess     => "RA".to_string(),
+                VariableMemoryType::Unknown (value)  => value.to_string(),
+            };
+            let data_type = match variable.data_type {
+                VariableDataType::Int             => "Basic".to_string(),
+                VariableDataType::Float           => "Float".to_string(),
+                VariableDataType::Bool            => "Bit".to_string(),
+                VariableDataType::Unknown (value) => value.to_string(),
+            };
+            format!("{}-{}[{}]", memory_type, data_type, variable.address)
+        }
+        Argument::Requirement { flip, ty } => {
+            let flip = if *flip { "!" } else { "" };
+            match ty {
+                Requirement::Unknown (id) => format!("{}0x{:X}", flip, id),
+                ty                        => format!("{}{}", flip, ty.name()),
+            }
+        }
+        Argument::Unknown (_, _) => return None,
+    })
+}
+
+/// The text of an argument with a type prefix, this is always parsed back into the same argument.
+fn typed_text(argument: &Argument) -> String {
+    match argument {
+        Argument::Value (value) => format!("v:{}", value),
+        Argument::Scalar (value) if FixedPoint::from_frames(value.as_frames()) == *value => format!("s:{}", value.as_frames()),
+        Argument::Scalar (value) => format!("fixed:{}", value.raw),
+        Argument::Offset (_)   => format!("o:{}",   untyped_text(argument).unwrap()),
+        Argument::Bool (_)     => format!("b:{}",   untyped_text(argument).unwrap()),
+        Argument::File (_)     => format!("f:{}",   untyped_text(argument).unwrap()),
+        Argument::Variable (_) => format!("var:{}", untyped_text(argument).unwrap()),
+        Argument::Requirement { .. } => format!("req:{}", untyped_text(argument).unwrap()),
+        Argument::Unknown (ty, value) => format!("type{}:{}", ty, value),
+    }
+}
+
+/// Uses the untyped text when it is parsed back into the same argument.
+fn argument_text(kind: Kind, argument: &Argument) -> String {
+    match untyped_text(argument) {
+        Some(text) if parse_untyped(kind, &text).map(|x| same_argument(&x, argument)).unwrap_or(false) => text,
+        _ => typed_text(argument),
+    }
+}
+
+/// The argument types an event accepts
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Value,
+    Scalar,
+    Offset,
+    Bool,
+    File,
+    Variable,
+    Requirement,
+    ValueOrVariable,
+    ScalarOrVariable,
+    Any,
+}
+
+impl Kind {
+    fn accepts(self, argument: &Argument) -> bool {
+        matches!((self, argument),
+            (Kind::Any, _) |
+            (Kind::Value,            Argument::Value (_)) |
+            (Kind::Scalar,           Argument::Scalar (_)) |
+            (Kind::Offset,           Argument::Offset (_)) |
+            (Kind::Bool,             Argument::Bool (_)) |
+            (Kind::File,             Argument::File (_)) |
+            (Kind::Variable,         Argument::Variable (_)) |
+            (Kind::Requirement,      Argument::Requirement { .. }) |
+            (Kind::ValueOrVariable,  Argument::Value (_)) |
+            (Kind::ValueOrVariable,  Argument::Variable (_)) |
+            (Kind::ScalarOrVariable, Argument::Scalar (_)) |
+            (Kind::ScalarOrVariable, Argument::Variable (_))
+        )
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Kind::Value            => "an integer",
+            Kind::Scalar           => "a number",
+            Kind::Offset           => "an offset",
+            Kind::Bool             => "true or false",
+            Kind::File             => "a file index",
+            Kind::Variable         => "a variable e.g. LA-Basic[3]",
+            Kind::Requirement      => "a requirement e.g. OnGround",
+            Kind::ValueOrVariable  => "an integer or a variable",
+            Kind::ScalarOrVariable => "a number or a variable",
+            Kind::Any              => "an argument e.g. v:0 or s:1.5",
+        }
+    }
+}
+
+/// A named part of an argument, most fields are the entire argument
+struct Field {
+    name: &'static str,
+    argument: usize,
+    shift: u32,
+    width: u32,
+    /// Fields without a default must be given
+    default: Option<&'static str>,
+}
+
+const fn field(name: &'static str, argument: usize) -> Field {
+    Field { name, argument, shift: 0, width: 32, default: None }
+}
+
+const fn field_or(name: &'static str, argument: usize, default: &'static str) -> Field {
+    Field { name, argument, shift: 0, width: 32, default: Some(default) }
+}
+
+/// A field stored in `width` bits of a Value argument, starting at bit `shift`
+const fn bits(name: &'static str, argument: usize, shift: u32, width: u32) -> Field {
+    Field { name, argument, shift, width, default: Some("0") }
+}
+
+struct Signature {
+    namespace: u8,
+    code: u8,
+    name: &'static str,
+    aliases: &'static [&'static str],
+    arguments: &'static [Kind],
+    /// Arguments after this many are optional and can be left off the end of the event
+    required: usize,
+    fields: &'static [Field],
+}
+
+const fn event(namespace: u8, code: u8, name: &'static str, arguments: &'static [Kind], fields: &'static [Field]) -> Signature {
+    Signature { namespace, code, name, aliases: &[], arguments, required: arguments.len(), fields }
+}
+
+impl Signature {
+    const fn aliases(mut self, aliases: &'static [&'static str]) -> Signature {
+        self.aliases = aliases;
+        self
+    }
+
+    const fn required(mut self, required: usize) -> Signature {
+        self.required = required;
+        self
+    }
+
+    fn matches(&self, event: &Event) -> bool {
+        self.namespace == event.namespace && self.code == event.code &&
+            event.arguments.len() >= self.required && event.arguments.len() <= self.arguments.len() &&
+            self.arguments.iter().zip(&event.arguments).all(|(kind, argument)| kind.accepts(argument))
+    }
+
+    fn wrong_argument_count(&self, found: usize) -> AssembleErrorKind {
+        let min = self.fields.iter().filter(|x| x.argument < self.required && x.default.is_none()).count();
+        AssembleErrorKind::WrongArgumentCount { event: self.name.to_string(), min, max: self.fields.len(), found }
+    }
+
+    /// Names every field of the event, the event must match the signature.
+    fn decode(&self, event: &Event) -> DecodedEvent {
+        let fields = self.fields.iter().filter(|x| x.argument < event.arguments.len()).map(|field| {
+            let argument = &event.arguments[field.argument];
+            let value = match argument {
+                Argument::Value (value) if field.width != 32 => FieldValue::Value (((*value as u32 >> field.shift) & ((1 << field.width) - 1)) as i32),
+                _ => FieldValue::new(argument),
+            };
+            (field.name.to_string(), value)
+        }).collect();
+        DecodedEvent { name: self.name.to_string(), fields }
+    }
+
+    /// Writes the event with this signature, the event must match the signature.
+    /// Fields are positional unless the signature has defaults, then only the fields that differ from their default are written.
+    fn disassemble(&self, event: &Event) -> String {
+        let named = self.fields.iter().any(|x| x.default.is_some());
+        let mut text = self.name.to_string();
+        for field in self.fields.iter().filter(|x| x.argument < event.arguments.len()) {
+            let argument = &event.arguments[field.argument];
+            let kind = self.arguments[field.argument];
+            let value = if field.width == 32 {
+                let is_default = field.default.and_then(|x| parse_argument(kind, x)).map(|x| same_argument(&x, argument)).unwrap_or(false);
+                if named && is_default {
+                    continue;
+                }
+                argument_text(kind, argument)
+            } else {
+                let value = match argument {
+                    Argument::Value (value) => (*value as u32 >> field.shift) & ((1 << field.width) - 1),
+                    _ => unreachable!("Packed fields are always in value arguments"),
+                };
+                if value == 0 {
+                    continue;
+                }
+                value.to_string()
+            };
+
+            text.push(' ');
+            if named {
+                text.push_str(field.name);
+                text.push('=');
+            }
+            text.push_str(&value);
+        }
+        text
+    }
+}
+
+const CONDITION: &[Kind] = &[Kind::Requirement, Kind::Any, Kind::Value, Kind::Any];
+const CONDITION_FIELDS: &[Field] = &[field("requirement", 0), field("value", 1), field("comparison", 2), field("compare_to", 3)];
+
+const CATCH: &[Kind] = &[Kind::Value, Kind::Value, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Value, Kind::Value, Kind::Value];
+const CATCH_FIELDS: &[Field] = &[
+    field("grabbox_id", 0),
+    field("bone", 1),
+    field("size", 2),
+    field("x_offset", 3),
+    field("y_offset", 4),
+    field("z_offset", 5),
+    field("set_action", 6),
+    field("target", 7),
+    field("unk", 8),
+];
+
+const HITBOX: &[Kind] = &[Kind::Value, Kind::ValueOrVariable, Kind::Value, Kind::Value, Kind::Value, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Value];
+const HITBOX_FIELDS: &[Field] = &[
+    bits("bone", 0, 16, 16),
+    bits("set_id", 0, 8, 8),
+    bits("id", 0, 0, 8),
+    field_or("damage", 1, "0"),
+    field_or("angle", 2, "0"),
+    bits("wdsk", 3, 16, 16),
+    bits("kbg", 3, 0, 16),
+    bits("shield_damage", 4, 16, 16),
+    bits("bkb", 4, 0, 16),
+    field_or("size", 5, "0"),
+    field_or("x_offset", 6, "0"),
+    field_or("y_offset", 7, "0"),
+    field_or("z_offset", 8, "0"),
+    field_or("tripping_rate", 9, "0"),
+    field_or("hitlag_mult", 10, "1"),
+    field_or("sdi_mult", 11, "1"),
+    // hits grounded and aerial opponents
+    field_or("flags", 12, "0x30000"),
+];
+
+/// Offsets and rotations are stored z, y, x
+const GRAPHIC: &[Kind] = &[Kind::Value, Kind::Value, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar,
+    Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Bool];
+const GRAPHIC_FIELDS: &[Field] = &[
+    field("graphic", 0),
+    field("bone", 1),
+    field_or("z_offset", 2, "0"),
+    field_or("y_offset", 3, "0"),
+    field_or("x_offset", 4, "0"),
+    field_or("z_rotation", 5, "0"),
+    field_or("y_rotation", 6, "0"),
+    field_or("x_rotation", 7, "0"),
+    field_or("scale", 8, "1"),
+    field_or("random_z_offset", 9, "0"),
+    field_or("random_y_offset", 10, "0"),
+    field_or("random_x_offset", 11, "0"),
+    field_or("random_z_rotation", 12, "0"),
+    field_or("random_y_rotation", 13, "0"),
+    field_or("random_x_rotation", 14, "0"),
+    field_or("terminate_with_animation", 15, "false"),
+];
+
+/// The graphic is packed with the index of the external effect file it comes from
+const EXTERNAL_GRAPHIC_FIELDS: &[Field] = &[
+    bits("file", 0, 16, 16),
+    bits("graphic", 0, 0, 16),
+    field("bone", 1),
+    field_or("z_offset", 2, "0"),
+    field_or("y_offset", 3, "0"),
+    field_or("x_offset", 4, "0"),
+    field_or("z_rotation", 5, "0"),
+    field_or("y_rotation", 6, "0"),
+    field_or("x_rotation", 7, "0"),
+    field_or("scale", 8, "1"),
+    field_or("random_z_offset", 9, "0"),
+    field_or("random_y_offset", 10, "0"),
+    field_or("random_x_offset", 11, "0"),
+    field_or("random_z_rotation", 12, "0"),
+    field_or("random_y_rotation", 13, "0"),
+    field_or("random_x_rotation", 14, "0"),
+    field_or("terminate_with_animation", 15, "false"),
+];
+
+/// The external graphic effects without randomization
+const EXTERNAL_GRAPHIC_SHORT: &[Kind] = &[Kind::Value, Kind::Value, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Bool];
+const EXTERNAL_GRAPHIC_SHORT_FIELDS: &[Field] = &[
+    bits("file", 0, 16, 16),
+    bits("graphic", 0, 0, 16),
+    field("bone", 1),
+    field_or("z_offset", 2, "0"),
+    field_or("y_offset", 3, "0"),
+    field_or("x_offset", 4, "0"),
+    field_or("z_rotation", 5, "0"),
+    field_or("y_rotation", 6, "0"),
+    field_or("x_rotation", 7, "0"),
+    field_or("scale", 8, "1"),
+    field_or("terminate_with_animation", 9, "false"),
+];
+
+/// The trail is drawn between two points, each offset from a bone, the glow graphic is then placed like a graphic effect
+const SWORD_GLOW: &[Kind] = &[Kind::Value, Kind::Value, Kind::Value, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Value, Kind::Scalar, Kind::Scalar, Kind::Scalar,
+    Kind::Bool, Kind::Value, Kind::Value, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar, Kind::Scalar];
+const SWORD_GLOW_FIELDS: &[Field] = &[
+    field("color", 0),
+    field("blur_length", 1),
+    field("point1_bone", 2),
+    field_or("point1_x_offset", 3, "0"),
+    field_or("point1_y_offset", 4, "0"),
+    field_or("point1_z_offset", 5, "0"),
+    field("point2_bone", 6),
+    field_or("point2_x_offset", 7, "0"),
+    field_or("point2_y_offset", 8, "0"),
+    field_or("point2_z_offset", 9, "0"),
+    field_or("delete_after_subaction", 10, "false"),
+    field("graphic_id", 11),
+    field("bone_id", 12),
+    field_or("x_offset", 13, "0"),
+    field_or("y_offset", 14, "0"),
+    field_or("z_offset", 15, "0"),
+    field_or("x_rotation", 16, "0"),
+    field_or("y_rotation", 17, "0"),
+    field_or("z_rotation", 18, "0"),
+    field("glow_length", 19),
+];
+
+/// Events with a known signature, an event is written with the first signature that matches it.
+const SIGNATURES: &[Signature] = &[
+    // control flow
+    event(0x00, 0x01, "SyncTimer",  &[Kind::Scalar], &[field("frames", 0)]).aliases(&["Wait"]),
+    event(0x00, 0x02, "Nop",        &[], &[]),
+    event(0x00, 0x02, "AsyncTimer", &[Kind::Scalar], &[field("frame", 0)]).aliases(&["FrameTimer"]),
+    event(0x00, 0x04, "SetLoop",    &[Kind::Value], &[field("iterations", 0)]).aliases(&["Loop"]),
+    event(0x00, 0x05, "ExecuteLoop", &[], &[]).aliases(&["EndLoop"]),
+    event(0x00, 0x07, "Subroutine", &[Kind::Offset], &[field("offset", 0)]).aliases(&["Call"]),
+    event(0x00, 0x08, "Return",     &[], &[]),
+    event(0x00, 0x09, "Goto",       &[Kind::Offset], &[field("offset", 0)]),
+    event(0x00, 0x0A, "If",         CONDITION, CONDITION_FIELDS).required(1),
+    event(0x00, 0x0B, "And",        CONDITION, CONDITION_FIELDS).required(1),
+    event(0x00, 0x0C, "Or",         CONDITION, CONDITION_FIELDS).required(1),
+    event(0x00, 0x0D, "ElseIf",     CONDITION, CONDITION_FIELDS).required(1),
+    event(0x00, 0x0E, "Else",       &[], &[]),
+    event(0x00, 0x0F, "EndIf",      &[], &[]),
+    event(0x00, 0x10, "Switch",     &[Kind::Value, Kind::Value], &[field("unk", 0), field("value", 1)]),
+    event(0x00, 0x11, "DefaultCase", &[], &[]),
+    event(0x00, 0x11, "Case",       &[Kind::Value], &[field("value", 0)]),
+    event(0x00, 0x13, "EndSwitch",  &[], &[]),
+    event(0x01, 0x01, "LoopRest",   &[], &[]),
+    event(0x0D, 0x00, "CallEveryFrame",                     &[Kind::Value, Kind::Offset], &[field("thread_id", 0), field("offset", 1)]).aliases(&["ConcurrentInfiniteLoop"]),
+    event(0x0D, 0x01, "RemoveCallEveryFrame",               &[Kind::Value], &[field("thread_id", 0)]).aliases(&["TerminateConcurrentInfiniteLoop"]),
+    event(0x0D, 0x05, "IndependentSubroutine",              &[Kind::Value, Kind::Offset], &[field("thread_id", 0), field("offset", 1)]),
+    event(0x0D, 0x06, "RemoveIndependentSubroutine",        &[Kind::Value], &[field("thread_id", 0)]),
+    event(0x0D, 0x07, "SetIndependentSubroutineThreadType", &[Kind::Value, Kind::Value], &[field("thread_id", 0), field("thread_type", 1)]),
+
+    // interrupts
+    event(0x02, 0x00, "ChangeActionStatus", &[Kind::Value, Kind::Value, Kind::Requirement, Kind::Any, Kind::Value, Kind::Any],
+        &[field("interrupt_id", 0), field("action", 1), field("requirement", 2), field("value", 3), field("comparison", 4), field("compare_to", 5)]).required(3).aliases(&["CreateInterrupt"]),
+    event(0x02, 0x01, "ChangeAction", &[Kind::Value, Kind::Requirement, Kind::Any, Kind::Value, Kind::Any],
+        &[field("action", 0), field("requirement", 1), field("value", 2), field("comparison", 3), field("compare_to", 4)]).required(2),
+    event(0x02, 0x04, "AdditionalRequirement", CONDITION, CONDITION_FIELDS).required(1).aliases(&["PreviousInterruptAddRequirement"]),
+    event(0x02, 0x05, "InterruptAddRequirement", &[Kind::Value, Kind::Value, Kind::Requirement, Kind::Any, Kind::Value, Kind::Any],
+        &[field("interrupt_type", 0), field("interrupt_id", 1), field("requirement", 2), field("value", 3), field("comparison", 4), field("compare_to", 5)]).required(3),
+    event(0x02, 0x06, "EnableInterrupt",       &[Kind::Value], &[field("interrupt_id", 0)]),
+    event(0x02, 0x08, "DisableInterrupt",      &[Kind::Value], &[field("interrupt_id", 0)]),
+    event(0x02, 0x09, "ToggleInterrupt",       &[Kind::Value, Kind::Value], &[field("interrupt_type", 0), field("interrupt_id", 1)]),
+    event(0x02, 0x0A, "EnableInterruptGroup",  &[Kind::Value], &[field("interrupt_type", 0)]),
+    event(0x02, 0x0B, "DisableInterruptGroup", &[Kind::Value], &[field("interrupt_type", 0)]),
+    event(0x02, 0x0C, "ClearInterruptGroup",   &[Kind::Value], &[field("interrupt_type", 0)]),
+    event(0x64, 0x00, "AllowInterrupts",       &[], &[]),
+    event(0x64, 0x01, "DisallowInterrupts",    &[], &[]),
+
+    // subactions and timing
+    event(0x04, 0x00, "ChangeSubaction",           &[Kind::Value, Kind::Bool], &[field("subaction", 0), field("pass_frame", 1)]).required(1),
+    event(0x04, 0x06, "SetAnimationFrame",         &[Kind::Scalar], &[field("frame", 0)]),
+    event(0x04, 0x07, "FrameSpeedModifier",        &[Kind::Scalar, Kind::Value], &[field("multiplier", 0), field("unk", 1)]).required(1),
+    event(0x04, 0x14, "SetAnimationAndTimerFrame", &[Kind::Scalar], &[field("frame", 0)]),
+
+    // misc state
+    event(0x0E, 0x00, "SetAirGround",     &[Kind::Value], &[field("state", 0)]),
+    event(0x08, 0x00, "SetEdgeSlide",     &[Kind::Value], &[field("state", 0)]),
+    event(0x05, 0x00, "ReverseDirection", &[], &[]),
+
+    // collisions
+    event(0x06, 0x00, "OffensiveCollision",  HITBOX, HITBOX_FIELDS).aliases(&["Hitbox", "CreateHitbox"]),
+    event(0x06, 0x2B, "ThrownCollision",     HITBOX, HITBOX_FIELDS).aliases(&["ThrownHitbox"]),
+    event(0x06, 0x04, "TerminateCollisions", &[], &[]).aliases(&["DeleteAllHitboxes"]),
+    event(0x06, 0x01, "ChangeHitboxDamage",  &[Kind::Value, Kind::Value], &[field("hitbox_id", 0), field("damage", 1)]),
+    event(0x06, 0x02, "ChangeHitboxSize",    &[Kind::Value, Kind::Value], &[field("hitbox_id", 0), field("size", 1)]),
+    event(0x06, 0x03, "DeleteHitbox",        &[Kind::Value], &[field("hitbox_id", 0)]),
+    event(0x06, 0x0A, "CatchCollision",      CATCH, CATCH_FIELDS).required(8).aliases(&["Grabbox", "CreateGrabbox"]),
+    event(0x06, 0x0C, "DeleteGrabbox",       &[Kind::Value], &[field("grabbox_id", 0)]),
+    event(0x06, 0x0D, "DeleteAllGrabboxes",  &[], &[]),
+    event(0x06, 0x05, "ChangeHurtboxStateAll",      &[Kind::Value], &[field("state", 0)]),
+    event(0x06, 0x08, "ChangeHurtboxStateSpecific", &[Kind::Value, Kind::Value], &[field("bone", 0), field("state", 1)]),
+    event(0x1E, 0x00, "Armor",     &[Kind::Value, Kind::Scalar], &[field("armor_type", 0), field("tolerance", 1)]),
+    event(0x1E, 0x03, "AddDamage", &[Kind::Scalar], &[field("damage", 0)]),
+
+    // controller
+    event(0x07, 0x07, "Rumble",     &[Kind::Value, Kind::Value], &[field("unk1", 0), field("unk2", 1)]),
+    event(0x07, 0x0B, "RumbleLoop", &[Kind::Value, Kind::Value], &[field("unk1", 0), field("unk2", 1)]),
+
+    // camera
+    event(0x1A, 0x00, "ScreenShake",   &[Kind::Value], &[field("magnitude", 0)]),
+    event(0x1A, 0x04, "CameraCloseup", &[Kind::Value, Kind::Value, Kind::Scalar, Kind::Scalar, Kind::Scalar],
+        &[field("zoom_time", 0), field("unk", 1), field("distance", 2), field("x_angle", 3), field("y_angle", 4)]),
+    event(0x1A, 0x08, "CameraNormal",  &[], &[]),
+
+    // graphics
+    event(0x0B, 0x00, "ModelChanger",  &[Kind::Value, Kind::Value], &[field("switch_index", 0), field("bone_group_index", 1)]),
+    event(0x0B, 0x01, "ModelChanger2", &[Kind::Value, Kind::Value], &[field("switch_index", 0), field("bone_group_index", 1)]),
+    event(0x11, 0x1A, "GraphicEffect",          GRAPHIC, GRAPHIC_FIELDS),
+    event(0x11, 0x1B, "GraphicEffect2",         GRAPHIC, GRAPHIC_FIELDS),
+    event(0x11, 0x00, "ExternalGraphicEffect",  GRAPHIC, EXTERNAL_GRAPHIC_FIELDS),
+    event(0x11, 0x01, "ExternalGraphicEffect2", EXTERNAL_GRAPHIC_SHORT, EXTERNAL_GRAPHIC_SHORT_FIELDS),
+    event(0x11, 0x02, "ExternalGraphicEffect3", EXTERNAL_GRAPHIC_SHORT, EXTERNAL_GRAPHIC_SHORT_FIELDS),
+    event(0x11, 0x03, "SwordGlow",              SWORD_GLOW, SWORD_GLOW_FIELDS),
+    event(0x11, 0x05, "DeleteSwordGlow",        &[Kind::Value], &[field("fade_time", 0)]),
+    event(0x11, 0x17, "LimitedScreenTint",      &[Kind::Value; 7],
+        &[field("transition_in_time", 0), field("red", 1), field("green", 2), field("blue", 3), field("alpha", 4), field("frame_count", 5), field("transition_out_time", 6)]),
+    event(0x11, 0x17, "UnlimitedScreenTint",    &[Kind::Value; 6],
+        &[field("tint_id", 0), field("transition_in_time", 1), field("red", 2), field("green", 3), field("blue", 4), field("alpha", 5)]),
+    event(0x11, 0x18, "EndUnlimitedScreenTint", &[Kind::Value, Kind::Value], &[field("tint_id", 0), field("transition_out_time", 1)]),
+    event(0x21, 0x00, "RemoveFlashEffect",      &[], &[]),
+    event(0x21, 0x01, "FlashEffectOverlay",     &[Kind::Value; 4], &[field("red", 0), field("green", 1), field("blue", 2), field("alpha", 3)]).aliases(&["ColorOverlay"]),
+    event(0x21, 0x02, "SetColorOfFlashEffectOverlay", &[Kind::Value; 5],
+        &[field("transition_time", 0), field("red", 1), field("green", 2), field("blue", 3), field("alpha", 4)]),
+    event(0x21, 0x05, "FlashEffectLight",       &[Kind::Value, Kind::Value, Kind::Value, Kind::Value, Kind::Scalar, Kind::Scalar],
+        &[field("red", 0), field("green", 1), field("blue", 2), field("alpha", 3), field("light_source_x", 4), field("light_source_y", 5)]),
+    event(0x21, 0x07, "SetColorOfFlashEffectLight", &[Kind::Value; 5],
+        &[field("transition_time", 0), field("red", 1), field("green", 2), field("blue", 3), field("alpha", 4)]),
+
+    // articles
+    event(0x10, 0x00, "GenerateArticle",   &[Kind::Value, Kind::Bool], &[field("article_id", 0), field("subaction_only", 1)]).required(1),
+    event(0x10, 0x01, "ArticleEvent",      &[Kind::Value], &[field("article_id", 0)]),
+    event(0x10, 0x02, "ArticleAnimation",  &[Kind::Value], &[field("article_id", 0)]),
+    event(0x10, 0x03, "ArticleRemove",     &[Kind::Value], &[field("article_id", 0)]),
+    event(0x10, 0x05, "ArticleVisibility", &[Kind::Value, Kind::Bool], &[field("article_id", 0), field("visibility", 1)]),
+    event(0x0C, 0x06, "FinalSmashEnter",   &[], &[]),
+    event(0x0C, 0x07, "FinalSmashExit",    &[], &[]),
+    event(0x0C, 0x08, "TerminateSelf",     &[], &[]),
+
+    // items
+    event(0x1F, 0x08, "GenerateItem", &[Kind::Value], &[field("item", 0)]).aliases(&["ItemCreate"]),
+
+    // movement
+    event(0x0E, 0x08, "SetVelocity",      &[Kind::Scalar, Kind::Scalar], &[field("x_vel", 0), field("y_vel", 1)]),
+    event(0x0E, 0x01, "AddVelocity",      &[Kind::ScalarOrVariable, Kind::ScalarOrVariable], &[field("x_vel", 0), field("y_vel", 1)]),
+    event(0x0E, 0x02, "ResetVerticalVelocityAndAcceleration", &[Kind::Value], &[field("reset", 0)]),
+    event(0x17, 0x00, "NormalizePhysics", &[], &[]),
+
+    // sound
+    event(0x0A, 0x00, "SoundEffect",          &[Kind::Value], &[field("sound", 0)]),
+    event(0x0A, 0x01, "SoundEffect2",         &[Kind::Value], &[field("sound", 0)]),
+    event(0x0A, 0x02, "SoundEffectTransient", &[Kind::Value], &[field("sound", 0)]),
+    event(0x0A, 0x03, "SoundEffectStop",      &[Kind::Value], &[field("sound", 0)]),
+
+    // variables
+    event(0x12, 0x00, "IntVariableSet",        &[Kind::Value, Kind::Variable], &[field("value", 0), field("variable", 1)]),
+    event(0x12, 0x01, "IntVariableAdd",        &[Kind::Value, Kind::Variable], &[field("value", 0), field("variable", 1)]),
+    event(0x12, 0x02, "IntVariableSubtract",   &[Kind::Value, Kind::Variable], &[field("value", 0), field("variable", 1)]),
+    event(0x12, 0x03, "IntVariableIncrement",  &[Kind::Variable], &[field("variable", 0)]),
+    event(0x12, 0x04, "IntVariableDecrement",  &[Kind::Variable], &[field("variable", 0)]),
+    event(0x12, 0x06, "FloatVariableSet",      &[Kind::ScalarOrVariable, Kind::Variable], &[field("value", 0), field("variable", 1)]),
+    event(0x12, 0x07, "FloatVariableAdd",      &[Kind::ScalarOrVariable, Kind::Variable], &[field("value", 0), field("variable", 1)]),
+    event(0x12, 0x08, "FloatVariableSubtract", &[Kind::ScalarOrVariable, Kind::Variable], &[field("value", 0), field("variable", 1)]),
+    event(0x12, 0x0F, "FloatVariableMultiply", &[Kind::ScalarOrVariable, Kind::Variable], &[field("value", 0), field("variable", 1)]),
+    event(0x12, 0x10, "FloatVariableDivide",   &[Kind::ScalarOrVariable, Kind::Variable], &[field("value", 0), field("variable", 1)]),
+    event(0x12, 0x0A, "BoolVariableSetTrue",   &[Kind::Variable], &[field("variable", 0)]),
+    event(0x12, 0x0B, "BoolVariableSetFalse",  &[Kind::Variable], &[field("variable", 0)]),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script_ast::{ScriptAst, EventAst, FloatValue, Rgba};
+    use cgmath::Vector3;
+    use crate::script::new_script;
+    use crate::test_util::{TestFighterBuilder, ScriptBuilder, damage};
+    use crate::wii_memory::WiiMemory;
+    use fancy_slice::FancySlice;
+
+    fn assert_round_trip(script: &Script) {
+        let text = disassemble(script);
+        let assembled = assemble(&text).unwrap();
+        assert_eq!(assembled.events.len(), script.events.len(), "{}", text);
+        for (assembled, event) in assembled.events.iter().zip(&script.events) {
+            assert!(same_event(assembled, event), "{}\n{:?}\n{:?}", text, assembled, event);
+        }
+    }
+
+    fn error(text: &str) -> AssembleError {
+        assemble(text).unwrap_err()
+    }
+
+    #[test]
+    fn fighter_script_round_trip() {
+        let fighter = TestFighterBuilder::new("Test")
+            .subaction("AttackS3S", |s| s
+                .async_timer(5.0)
+                .hitbox(damage(8).angle(361).bkb(30).kbg(100).size(4.5).offset(0.0, 2.0, -1.0).bone(3).id(1))
+                .sync_timer(2.5)
+                .delete_hitbox(1)
+                .event(0x99, 0x02, vec!(Argument::Value (3), Argument::Scalar (FixedPoint::from_frames(1.5))))
+                .terminate_collisions()
+            )
+            .build();
+        let script = &fighter.get_fighter_data().unwrap().subaction_main[0];
+        assert_round_trip(script);
+        assert_eq!(disassemble(script), "\
+AsyncTimer 5
+OffensiveCollision bone=3 id=1 damage=8 angle=361 kbg=100 bkb=30 size=4.5 y_offset=2 z_offset=-1
+SyncTimer 2.5
+DeleteHitbox 1
+raw ns=0x99 code=0x02 args=[v:3, s:1.5]
+TerminateCollisions
+");
+    }
+
+    #[test]
+    fn argument_types_round_trip() {
+        let variable = |memory_type, data_type, address| Argument::Variable (Variable { memory_type, data_type, address });
+        let mut script = ScriptBuilder::new()
+            .event(0x00, 0x0A, vec!(Argument::Requirement { flip: true, ty: Requirement::OnGround }))
+            .event(0x00, 0x0A, vec!(
+                Argument::Requirement { flip: false, ty: Requirement::Comparison },
+                variable(VariableMemoryType::LongtermAccess, VariableDataType::Int, 3),
+                Argument::Value (4),
+                Argument::Value (2),
+            ))
+            .event(0x00, 0x0B, vec!(Argument::Requirement { flip: false, ty: Requirement::Unknown (0x1234) }, Argument::Scalar (FixedPoint::from_frames(2.0))))
+            .event(0x00, 0x0F, vec!())
+            .event(0x00, 0x07, vec!(Argument::Offset (Offset { offset: 0x1A0, origin: 0x40 })))
+            .event(0x12, 0x06, vec!(variable(VariableMemoryType::InternalConstant, VariableDataType::Float, 20), variable(VariableMemoryType::RandomAccess, VariableDataType::Float, 5)))
+            .event(0x12, 0x0A, vec!(variable(VariableMemoryType::Unknown (7), VariableDataType::Unknown (9), 1)))
+            .event(0x00, 0x02, vec!(Argument::Scalar (FixedPoint { raw: 0x7FFF_FFF1 })))
+            .event(0x04, 0x00, vec!(Argument::Value (0x12), Argument::Bool (true)))
+            .event(0x0E, 0x01, vec!(Argument::File (2), Argument::Unknown (9, -5)))
+            .hitbox(damage(5).wdsk(-1))
+            .build();
+        script.events[3].unk1 = 1;
+        let mut hitbox = script.events.last().unwrap().clone();
+        hitbox.arguments[1] = variable(VariableMemoryType::LongtermAccess, VariableDataType::Int, 8);
+        script.events.push(hitbox);
+
+        assert_round_trip(&script);
+        let text = disassemble(&script);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "If !OnGround");
+        assert_eq!(lines[1], "If Comparison LA-Basic[3] 4 2");
+        assert_eq!(lines[2], "And 0x1234 s:2");
+        assert_eq!(lines[3], "raw ns=0x00 code=0x0F args=[] unk=0x01");
+        assert_eq!(lines[4], "Subroutine 0x1A0");
+        assert_eq!(lines[5], "FloatVariableSet IC-Float[20] RA-Float[5]");
+        assert_eq!(lines[6], "BoolVariableSetTrue 7-9[1]");
+        assert_eq!(lines[7], "AsyncTimer fixed:2147483633");
+        assert_eq!(lines[8], "ChangeSubaction 18 true");
+        assert_eq!(lines[9], "raw ns=0x0E code=0x01 args=[f:2, type9:-5]");
+        assert_eq!(lines[10], "OffensiveCollision damage=5 wdsk=65535 size=1");
+        assert_eq!(lines[11], "OffensiveCollision damage=LA-Basic[8] wdsk=65535 size=1");
+    }
+
+    #[test]
+    fn disassemble_symbols() {
+        let script = ScriptBuilder::new()
+            .event(0x00, 0x07, vec!(Argument::Offset (Offset { offset: 0x9E48, origin: 0x40 })))
+            .event(0x00, 0x09, vec!(Argument::Offset (Offset { offset: 0x1A0, origin: 0x48 })))
+            .event(0x0D, 0x00, vec!(Argument::Value (1), Argument::Offset (Offset { offset: 0x9E48, origin: 0x54 })))
+            .build();
+        let symbols = SymbolTable::parse("0x9E48 KnockbackCheck").unwrap();
+
+        let text = disassemble_with_symbols(&script, &symbols);
+        assert_eq!(text, "\
+Subroutine 0x9E48 // -> @0x9E48 (KnockbackCheck)
+Goto 0x1A0
+CallEveryFrame 1 0x9E48 // -> @0x9E48 (KnockbackCheck)
+");
+        // the symbols are comments, so the text still assembles to the same script
+        let assembled = assemble(&text).unwrap();
+        assert!(assembled.events.iter().zip(&script.events).all(|(a, b)| same_event(a, b)));
+    }
+
+    #[test]
+    fn camera_and_rumble_round_trip() {
+        let script = ScriptBuilder::new()
+            .event(0x07, 0x07, vec!(Argument::Value (0), Argument::Value (3)))
+            .event(0x1A, 0x00, vec!(Argument::Value (5)))
+            .event(0x1A, 0x04, vec!(Argument::Value (10), Argument::Value (0), Argument::Scalar (FixedPoint::from_frames(20.0)), Argument::Scalar (FixedPoint::from_frames(1.5)), Argument::Scalar (FixedPoint::from_frames(0.0))))
+            .event(0x1A, 0x08, vec!())
+            .build();
+
+        assert_round_trip(&script);
+        assert_eq!(disassemble(&script), "\
+Rumble 0 3
+ScreenShake 5
+CameraCloseup 10 0 20 1.5 0
+CameraNormal
+");
+        assert_eq!(assemble("screenShake magnitude=5").unwrap().events, script.events[1..2]);
+    }
+
+    #[test]
+    fn graphics_round_trip() {
+        let scalar = |x| Argument::Scalar (FixedPoint::from_frames(x));
+        let mut graphic = vec!(Argument::Value (0x0003_0007), Argument::Value (4), scalar(1.0), scalar(2.0), scalar(3.0));
+        graphic.extend((0..3).map(|_| scalar(0.0)));
+        graphic.push(scalar(1.0));
+        graphic.extend((0..6).map(|_| scalar(0.0)));
+        graphic.push(Argument::Bool (true));
+        let script = ScriptBuilder::new()
+            .event(0x11, 0x00, graphic.clone())
+            .event(0x11, 0x1A, graphic)
+            .event(0x11, 0x17, vec!(Argument::Value (1), Argument::Value (255), Argument::Value (0), Argument::Value (0), Argument::Value (128), Argument::Value (20), Argument::Value (5)))
+            .event(0x21, 0x02, vec!(Argument::Value (6), Argument::Value (255), Argument::Value (255), Argument::Value (255), Argument::Value (300)))
+            .event(0x21, 0x00, vec!())
+            .build();
+
+        assert_round_trip(&script);
+        assert_eq!(disassemble(&script), "\
+ExternalGraphicEffect file=3 graphic=7 bone=4 z_offset=1 y_offset=2 x_offset=3 terminate_with_animation=true
+GraphicEffect graphic=196615 bone=4 z_offset=1 y_offset=2 x_offset=3 terminate_with_animation=true
+LimitedScreenTint 1 255 0 0 128 20 5
+SetColorOfFlashEffectOverlay 6 255 255 255 300
+RemoveFlashEffect
+");
+
+        let events = ScriptAst::new(&script).block.events;
+        match &events[1] {
+            EventAst::GraphicEffect (graphic) => assert_eq!(graphic.offset(), Vector3::new(3.0, 2.0, 1.0)),
+            event => panic!("{:?}", event),
+        }
+        let flash = events[3].color_flash().unwrap();
+        assert_eq!(flash.rgba, Rgba { red: 255, green: 255, blue: 255, alpha: 255 });
+        assert_eq!(flash.frames, 6);
+        assert_eq!(Rgba::from_u32(0x11223344).to_u32(), 0x11223344);
+        assert_eq!(assemble("colorOverlay 255 0 0 128").unwrap().events[0].code, 0x01);
+    }
+
+    /// A sword glow event as it is stored in a moveset file, every argument has a distinct value so a shifted field is caught.
+    /// The layout follows the documented argument order: color, blur length, bone and x/y/z offset of both ends of the trail,
+    /// delete after subaction, then the glow graphic, its bone, x/y/z offset, x/y/z rotation and the glow length.
+    /// The values are not copied from a vanilla moveset, no vanilla files are available to the tests.
+    const SWORD_GLOW_EVENT: &[u8] = &[
+        // offset 0 is a null script, so the script starts at 0x8
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // SwordGlow with 20 arguments at 0x18, followed by the end of the script
+        0x11, 0x03, 0x14, 0x00, 0x00, 0x00, 0x00, 0x18,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, // color
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x06, // blur length
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x18, // point 1 bone
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x75, 0x30, // point 1 x offset
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0xEA, 0x60, // point 1 y offset
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x5F, 0x90, // point 1 z offset
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x19, // point 2 bone
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0xD4, 0xC0, // point 2 x offset
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x49, 0xF0, // point 2 y offset
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x0B, 0x71, 0xB0, // point 2 z offset
+        0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01, // delete after subaction
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, // graphic
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1A, // bone
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x3A, 0x98, // x offset
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0xAF, 0xC8, // y offset
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x06, 0x68, 0xA0, // z offset
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x09, 0x27, 0xC0, // x rotation
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x52, 0x65, 0xC0, // y rotation
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x1B, 0x77, 0x40, // z rotation
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x24, 0xF8, // glow length
+    ];
+
+    /// Pins the argument order of the sword glow by parsing `SWORD_GLOW_EVENT`
+    #[test]
+    fn sword_glow_fields() {
+        let mut script = new_script(FancySlice::new(SWORD_GLOW_EVENT), 0x8, &WiiMemory::new());
+        script.events.extend(ScriptBuilder::new().event(0x11, 0x05, vec!(Argument::Value (8))).build().events);
+
+        assert_round_trip(&script);
+        assert_eq!(disassemble(&script), "\
+SwordGlow color=3 blur_length=6 point1_bone=24 point1_x_offset=0.5 point1_y_offset=1 point1_z_offset=1.5 \
+point2_bone=25 point2_x_offset=2 point2_y_offset=2.5 point2_z_offset=12.5 delete_after_subaction=true graphic_id=2 bone_id=26 \
+x_offset=0.25 y_offset=0.75 z_offset=7 x_rotation=10 y_rotation=90 z_rotation=30 glow_length=1.25
+DeleteSwordGlow 8
+");
+
+        let glow = match &ScriptAst::new(&script).block.events[0] {
+            EventAst::SwordGlow (glow) => glow.clone(),
+            event => panic!("{:?}", event),
+        };
+        assert_eq!((glow.color, glow.blur_length), (3, 6));
+        assert_eq!((glow.point1_bone, glow.point1_x_offset, glow.point1_y_offset, glow.point1_z_offset), (24, 0.5, 1.0, 1.5));
+        assert_eq!((glow.point2_bone, glow.point2_x_offset, glow.point2_y_offset, glow.point2_z_offset), (25, 2.0, 2.5, 12.5));
+        assert!(glow.delete_after_subaction);
+        assert_eq!((glow.graphic_id, glow.bone_id), (2, 26));
+        assert_eq!((glow.x_offset, glow.y_offset, glow.z_offset), (0.25, 0.75, 7.0));
+        assert_eq!((glow.x_rotation, glow.y_rotation, glow.z_rotation), (10.0, 90.0, 30.0));
+        assert_eq!(glow.glow_length, 1.25);
+    }
+
+    #[test]
+    fn assemble_hitbox() {
+        let script = assemble("asyncTimer 14; hitbox id=0 damage=12 angle=361 kbg=100 bkb=30 size=4.5 bone=16; asyncTimer 18; terminateCollisions").unwrap();
+        let ast = ScriptAst::new(&script);
+        assert_eq!(ast.block.events.len(), 4);
+        assert!(matches!(ast.block.events[0], EventAst::AsyncWait (frame) if frame == 14.0));
+        match &ast.block.events[1] {
+            EventAst::CreateHitBox (hitbox) => {
+                assert!(matches!(hitbox.damage, FloatValue::Constant (damage) if damage == 12.0));
+                assert_eq!(hitbox.hitbox_id, 0);
+                assert_eq!(hitbox.trajectory, 361);
+                assert_eq!(hitbox.kbg, 100);
+                assert_eq!(hitbox.bkb, 30);
+                assert_eq!(hitbox.size, 4.5);
+                assert_eq!(hitbox.bone_index, 16);
+                assert_eq!(hitbox.hitlag_mult, 1.0);
+                assert!(hitbox.ground && hitbox.aerial);
+            }
+            _ => panic!("Expected a hitbox"),
+        }
+        assert!(matches!(ast.block.events[2], EventAst::AsyncWait (frame) if frame == 18.0));
+        assert!(matches!(ast.block.events[3], EventAst::DeleteAllHitBoxes));
+    }
+
+    #[test]
+    fn catch_collision_round_trip() {
+        let script = assemble("CatchCollision 0 5 3 0 1 4 57 1; Grabbox 1 5 2.5 0 1 6 57 3 0; DeleteAllGrabboxes").unwrap();
+        assert_round_trip(&script);
+        assert_eq!(disassemble(&script), "\
+CatchCollision 0 5 3 0 1 4 57 1
+CatchCollision 1 5 2.5 0 1 6 57 3 0
+DeleteAllGrabboxes
+");
+        let ast = ScriptAst::new(&script);
+        match &ast.block.events[0] {
+            EventAst::CreateGrabBox (grab_box) => {
+                assert_eq!(grab_box.bone_index, 5);
+                assert_eq!(grab_box.size, 3.0);
+                assert_eq!(grab_box.z_offset, 4.0);
+                assert_eq!(grab_box.set_action, 57);
+                assert_eq!(grab_box.unk, None);
+            }
+            _ => panic!("Expected a grab box"),
+        }
+        assert!(matches!(&ast.block.events[1], EventAst::CreateGrabBox (grab_box) if grab_box.unk == Some(0)));
+    }
+
+    #[test]
+    fn assemble_raw_and_comments() {
+        let script = assemble("// a comment\n  raw ns=6 code=4 args=[v:0, s:1.5] // another\n\nRAW ns=0x64 code=0 args=[] unk=2").unwrap();
+        assert_eq!(script.events.len(), 2);
+        assert_eq!(script.events[0], Event { namespace: 6, code: 4, unk1: 0, arguments: vec!(Argument::Value (0), Argument::Scalar (FixedPoint::from_frames(1.5))) });
+        assert_eq!(script.events[1], Event { namespace: 0x64, code: 0, unk1: 2, arguments: vec!() });
+    }
+
+    #[test]
+    fn assemble_errors() {
+        assert_eq!(error("AsyncTimer 1\n  Hitbx id=0"), AssembleError { line: 2, column: 3, kind: AssembleErrorKind::UnknownEvent ("Hitbx".to_string()) });
+        assert_eq!(error("SyncTimer 1 2"), AssembleError {
+            line: 1, column: 13,
+            kind: AssembleErrorKind::WrongArgumentCount { event: "SyncTimer".to_string(), min: 1, max: 1, found: 2 }
+        });
+        assert_eq!(error("Nop; SyncTimer"), AssembleError {
+            line: 1, column: 6,
+            kind: AssembleErrorKind::WrongArgumentCount { event: "SyncTimer".to_string(), min: 1, max: 1, found: 0 }
+        });
+        assert_eq!(error("If"), AssembleError {
+            line: 1, column: 1,
+            kind: AssembleErrorKind::WrongArgumentCount { event: "If".to_string(), min: 1, max: 4, found: 0 }
+        });
+        assert_eq!(error("SyncTimer abc").kind, AssembleErrorKind::InvalidArgument { value: "abc".to_string(), expected: "a number" });
+        assert_eq!(error("Hitbox id=300").kind, AssembleErrorKind::InvalidArgument { value: "300".to_string(), expected: "an integer that fits the field" });
+        assert_eq!(error("Hitbox id=1 angle=abc").column, 19);
+        assert_eq!(error("Hitbox id=1 angle=s:2").kind, AssembleErrorKind::InvalidArgument { value: "s:2".to_string(), expected: "an integer" });
+        assert_eq!(error("Hitbox id=1 power=2").kind, AssembleErrorKind::UnknownField { event: "OffensiveCollision".to_string(), field: "power".to_string() });
+        assert_eq!(error("Hitbox id=1 id=2").kind, AssembleErrorKind::DuplicateField ("id".to_string()));
+        assert_eq!(error("Hitbox id=1 2").kind, AssembleErrorKind::MixedArguments);
+        assert_eq!(error("If requirement=OnGround compare_to=2").kind, AssembleErrorKind::MissingField { event: "If".to_string(), field: "value".to_string() });
+        assert_eq!(error("raw code=4").kind, AssembleErrorKind::MissingField { event: "raw".to_string(), field: "ns".to_string() });
+        assert_eq!(error("raw ns=6 code=4 args=[v:0, x]"), AssembleError {
+            line: 1, column: 28,
+            kind: AssembleErrorKind::InvalidArgument { value: "x".to_string(), expected: "an argument e.g. v:0 or s:1.5" }
+        });
+        assert_eq!(error("raw ns=6 code=4 args=[v:0").kind, AssembleErrorKind::UnclosedBracket);
+    }
+}
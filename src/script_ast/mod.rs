@@ -1,15 +1,19 @@
-use crate::script::{Script, Event, Requirement, Argument, Offset};
+use crate::script::{Script, Event, Requirement, Argument, Offset, Variable};
 use crate::script;
+use crate::item_id::ItemId;
 
 use std::iter::Iterator;
 use std::slice;
 use std::f32;
 
+use cgmath::Vector3;
+
 pub mod variable_ast;
 
 use variable_ast::VariableAst;
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct ScriptAst {
     pub block:  Block,
     pub offset: i32,
@@ -31,11 +35,47 @@ impl ScriptAst {
     }
 }
 
+/// An `Argument` with the scalar converted to frame units, the interpretation used by the events handled here.
+/// This allows the arguments of an event to be matched on directly.
+#[derive(Clone, Copy, Debug)]
+enum DecodedArgument<'a> {
+    Value (i32),
+    Scalar (f32),
+    Offset (&'a Offset),
+    Bool (bool),
+    Variable (&'a Variable),
+    Requirement { flip: bool, ty: &'a Requirement },
+    /// Arguments that no event handled here uses
+    Other,
+}
+
+/// The number of arguments of the event with the most arguments handled here, later arguments are never matched on
+const MAX_DECODED_ARGUMENTS: usize = 20;
+
+impl DecodedArgument<'_> {
+    fn new(argument: &Argument) -> DecodedArgument<'_> {
+        match argument {
+            Argument::Value (value)            => DecodedArgument::Value (*value),
+            Argument::Scalar (value)           => DecodedArgument::Scalar (value.as_frames()),
+            Argument::Offset (offset)          => DecodedArgument::Offset (offset),
+            Argument::Bool (value)             => DecodedArgument::Bool (*value),
+            Argument::Variable (variable)      => DecodedArgument::Variable (variable),
+            Argument::Requirement { flip, ty } => DecodedArgument::Requirement { flip: *flip, ty },
+            Argument::File (_) |
+            Argument::Unknown (_, _)           => DecodedArgument::Other,
+        }
+    }
+}
+
 fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> ProcessedBlock {
     let mut event_asts = vec!();
     while let Some(event) = events.next() {
-        let args = &event.arguments;
-        use crate::script::Argument::*;
+        let mut decoded = [DecodedArgument::Other; MAX_DECODED_ARGUMENTS];
+        for (decoded, argument) in decoded.iter_mut().zip(event.arguments.iter()) {
+            *decoded = DecodedArgument::new(argument);
+        }
+        let args = &decoded[..event.arguments.len().min(MAX_DECODED_ARGUMENTS)];
+        use DecodedArgument::*;
         let event_ast = match (event.namespace, event.code, args.get(0), args.get(1), args.get(2)) {
             (0x00, 0x01, Some(&Scalar(v0)), None, None) => EventAst::SyncWait (v0),
             (0x00, 0x02, None,              None, None) => EventAst::Nop,
@@ -58,12 +98,12 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
             (0x00, 0x05, None, None, None) => { // End loop
                 return ProcessedBlock::EndForLoop (Block { events: event_asts })
             }
-            (0x00, 0x07, Some(&Offset(ref v0)), None, None) => EventAst::Subroutine (v0.clone()),
+            (0x00, 0x07, Some(&Offset(v0)), None, None) => EventAst::Subroutine (v0.clone()),
             (0x00, 0x07, Some(&Value (ref v0)), None, None) => EventAst::Subroutine (script::Offset { offset: *v0, origin: -1 }),
             (0x00, 0x08, None,                  None, None) => EventAst::Return,
-            (0x00, 0x09, Some(&Offset(ref v0)), None, None) => EventAst::Goto (v0.clone()),
+            (0x00, 0x09, Some(&Offset(v0)), None, None) => EventAst::Goto (v0.clone()),
             (0x00, 0x09, Some(&Value (ref v0)), None, None) => EventAst::Goto (script::Offset { offset: *v0, origin: -1 }),
-            (0x00, 0x0A, Some(&Requirement { ref ty, flip }), v1, v2) => { // If
+            (0x00, 0x0A, Some(&Requirement { ty, flip }), v1, v2) => { // If
                 if let Some(test) = Expression::from_args(ty, flip, v1, v2, args.get(3)) {
                     match process_block(events) {
                         ProcessedBlock::EndIf { then_branch } => {
@@ -94,7 +134,7 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
                     }
                 }
             }
-            (0x00, 0x0B, Some(&Requirement { ref ty, flip }), v1, v2) => { // And
+            (0x00, 0x0B, Some(&Requirement { ty, flip }), v1, v2) => { // And
                 // It is tempting to combine this event with the previous IfStatement event.
                 // However that is a terrible idea, as an And/Or will alter the execution of the
                 // current IfStatement even if other events have occured since the IfStatement.
@@ -112,14 +152,14 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
                     EventAst::Unknown (event.clone())
                 }
             }
-            (0x00, 0x0C, Some(&Requirement { ref ty, flip }), v1, v2) => { // Or
+            (0x00, 0x0C, Some(&Requirement { ty, flip }), v1, v2) => { // Or
                 if let Some(test) = Expression::from_args(ty, flip, v1, v2, args.get(3)) {
                     EventAst::IfStatementOr (test)
                 } else {
                     EventAst::Unknown (event.clone())
                 }
             }
-            (0x00, 0x0D, Some(&Requirement { ref ty, flip }), v1, v2) => { // Else If
+            (0x00, 0x0D, Some(&Requirement { ty, flip }), v1, v2) => { // Else If
                 if let Some(test) = Expression::from_args(ty, flip, v1, v2, args.get(3)) {
                     let event = match process_block(events) {
                         ProcessedBlock::EndIf { then_branch } => {
@@ -146,28 +186,28 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
             (0x00, 0x11, None,             None,                  None) => EventAst::DefaultCase,
             (0x00, 0x13, None,             None,                  None) => EventAst::EndSwitch,
             (0x01, 0x01, None,             None,                  None) => EventAst::LoopRest,
-            (0x0D, 0x00, Some(&Value(v0)), Some(&Offset(ref v1)), None) => EventAst::CallEveryFrame { thread_id: v0, offset: v1.clone() },
+            (0x0D, 0x00, Some(&Value(v0)), Some(&Offset(v1)), None) => EventAst::CallEveryFrame { thread_id: v0, offset: v1.clone() },
             (0x0D, 0x01, Some(&Value(v0)), None,                  None) => EventAst::RemoveCallEveryFrame { thread_id: v0 },
-            (0x0D, 0x05, Some(&Value(v0)), Some(&Offset(ref v1)), None) => EventAst::IndependentSubroutine { thread_id: v0, offset: v1.clone() },
+            (0x0D, 0x05, Some(&Value(v0)), Some(&Offset(v1)), None) => EventAst::IndependentSubroutine { thread_id: v0, offset: v1.clone() },
             (0x0D, 0x06, Some(&Value(v0)), None,                  None) => EventAst::RemoveIndependentSubroutine { thread_id: v0 },
             (0x0D, 0x07, Some(&Value(v0)), Some(&Value(v1)),      None) => EventAst::SetIndependentSubroutineThreadType { thread_id: v0, thread_type: v1 },
 
             // change action
-            (0x02, 0x00, Some(&Value(v0)), Some(&Value(v1)), Some(&Requirement { ref ty, flip })) => {
+            (0x02, 0x00, Some(&Value(v0)), Some(&Value(v1)), Some(&Requirement { ty, flip })) => {
                 if let Some(test) = Expression::from_args(ty, flip, args.get(3), args.get(4), args.get(5)) {
                     EventAst::CreateInterrupt (Interrupt { interrupt_id: Some(v0), action: v1, test })
                 } else {
                     EventAst::Unknown (event.clone())
                 }
             }
-            (0x02, 0x01, Some(&Value(v0)), Some(&Requirement { ref ty, flip }), v2) => {
+            (0x02, 0x01, Some(&Value(v0)), Some(&Requirement { ty, flip }), v2) => {
                 if let Some(test) = Expression::from_args(ty, flip, v2, args.get(3), args.get(4)) {
                     EventAst::CreateInterrupt (Interrupt { interrupt_id: None, action: v0, test })
                 } else {
                     EventAst::Unknown (event.clone())
                 }
             }
-            (0x02, 0x04, Some(&Requirement { ref ty, flip }), v1, v2) => {
+            (0x02, 0x04, Some(&Requirement { ty, flip }), v1, v2) => {
                 // It is tempting to combine this event with the previous CreateInterrupt event.
                 // However that is a terrible idea as a InterruptAddRequirement will
                 // modify the last CreateInterrupt regardless of any events in between.
@@ -180,7 +220,7 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
                     EventAst::Unknown (event.clone())
                 }
             }
-            (0x02, 0x05, Some(&Value(v0)), Some(&Value(v1)), Some(&Requirement { ref ty, flip })) => {
+            (0x02, 0x05, Some(&Value(v0)), Some(&Value(v1)), Some(&Requirement { ty, flip })) => {
                 if let Some(test) = Expression::from_args(ty, flip, args.get(3), args.get(4), args.get(5)) {
                     EventAst::InterruptAddRequirement { interrupt_type: InterruptType::new(v0), interrupt_id: v1, test }
                 } else {
@@ -217,7 +257,7 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
                     (Some(&Value(v3)), Some(&Value(v4)), Some(&Scalar(v5)), Some(&Scalar(v6)), Some(&Scalar(v7)), Some(&Scalar(v8)), Some(&Scalar(v9)), Some(&Scalar(v10)), Some(&Scalar(v11)), Some(&Value(v12))) => {
                         let damage = match v1 {
                             Value(constant)        => Some(FloatValue::Constant (*constant as f32)),
-                            Variable(ref variable) => Some(FloatValue::Variable (VariableAst::new(variable))),
+                            Variable(variable) => Some(FloatValue::Variable (VariableAst::new(variable))),
                             _ => None,
                         };
 
@@ -307,7 +347,7 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
                     (Some(&Value(v3)), Some(&Value(v4)), Some(&Scalar(v5)), Some(&Scalar(v6)), Some(&Scalar(v7)), Some(&Scalar(v8)), Some(&Scalar(v9)), Some(&Scalar(v10)), Some(&Scalar(v11)), Some(&Value(v12)), Some(&Value(v13)), Some(&Value(v14))) => {
                         let damage = match v1 {
                             Value(constant)        => Some(FloatValue::Constant (*constant as f32)),
-                            Variable(ref variable) => Some(FloatValue::Variable (VariableAst::new(variable))),
+                            Variable(variable) => Some(FloatValue::Variable (VariableAst::new(variable))),
                             _ => None,
                         };
 
@@ -458,8 +498,8 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
                     _ => EventAst::Unknown(event.clone())
                 }
             }
-            (0x06, 0x0F, Some(&Value(v0)), Some(&Value(v1)), Some(&Variable(ref v2))) => {
-                if let (Some(&Variable(ref v3)), Some(&Variable(ref v4))) = (args.get(3), args.get(4)) {
+            (0x06, 0x0F, Some(&Value(v0)), Some(&Value(v1)), Some(&Variable(v2))) => {
+                if let (Some(&Variable(v3)), Some(&Variable(v4))) = (args.get(3), args.get(4)) {
                     EventAst::ApplyThrow (ApplyThrow {
                         unk0: v0,
                         bone: v1,
@@ -474,7 +514,7 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
             (0x06, 0x14, Some(&Value(v0)), Some(v1), None) => {
                 let add_damage = match v1 {
                     Value(constant)        => Some(FloatValue::Constant (*constant as f32)),
-                    Variable(ref variable) => Some(FloatValue::Variable (VariableAst::new(variable))),
+                    Variable(variable) => Some(FloatValue::Variable (VariableAst::new(variable))),
                     _ => None,
                 };
                 if let Some(add_damage) = add_damage {
@@ -544,9 +584,9 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
             }
             (0x0E, 0x08, Some(&Scalar(v0)),       Some(&Scalar(v1)),       None) => EventAst::SetVelocity { x_vel: v0, y_vel: v1 },
             (0x0E, 0x01, Some(&Scalar(v0)),       Some(&Scalar(v1)),       None) => EventAst::AddVelocity { x_vel: FloatValue::Constant(v0),                   y_vel: FloatValue::Constant(v1) },
-            (0x0E, 0x01, Some(&Variable(ref v0)), Some(&Scalar(v1)),       None) => EventAst::AddVelocity { x_vel: FloatValue::Variable(VariableAst::new(v0)), y_vel: FloatValue::Constant(v1) },
-            (0x0E, 0x01, Some(&Scalar(v0)),       Some(&Variable(ref v1)), None) => EventAst::AddVelocity { x_vel: FloatValue::Constant(v0),                   y_vel: FloatValue::Variable(VariableAst::new(v1)) },
-            (0x0E, 0x01, Some(&Variable(ref v0)), Some(&Variable(ref v1)), None) => EventAst::AddVelocity { x_vel: FloatValue::Variable(VariableAst::new(v0)), y_vel: FloatValue::Variable(VariableAst::new(v1)) },
+            (0x0E, 0x01, Some(&Variable(v0)), Some(&Scalar(v1)),       None) => EventAst::AddVelocity { x_vel: FloatValue::Variable(VariableAst::new(v0)), y_vel: FloatValue::Constant(v1) },
+            (0x0E, 0x01, Some(&Scalar(v0)),       Some(&Variable(v1)), None) => EventAst::AddVelocity { x_vel: FloatValue::Constant(v0),                   y_vel: FloatValue::Variable(VariableAst::new(v1)) },
+            (0x0E, 0x01, Some(&Variable(v0)), Some(&Variable(v1)), None) => EventAst::AddVelocity { x_vel: FloatValue::Variable(VariableAst::new(v0)), y_vel: FloatValue::Variable(VariableAst::new(v1)) },
             (0x0E, 0x06, Some(&Value(v0)),        None,                    None) => EventAst::DisableMovement (DisableMovement::new(v0)),
             (0x0E, 0x07, Some(&Value(v0)),        None,                    None) => EventAst::DisableMovement2 (DisableMovement::new(v0)),
             (0x0E, 0x02, Some(&Value(v0)),        None,                    None) => EventAst::ResetVerticalVelocityAndAcceleration (v0 == 1),
@@ -567,23 +607,23 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
             (0x0C, 0x1F, None,             None, None) => EventAst::SoundVoiceEating,
 
             // Modify variables
-            (0x12, 0x00, Some(&Value(v0)),        Some(&Variable(ref v1)), None) => EventAst::IntVariableSet { value: v0, variable: VariableAst::new(v1) },
-            (0x12, 0x01, Some(&Value(v0)),        Some(&Variable(ref v1)), None) => EventAst::IntVariableAdd { value: v0, variable: VariableAst::new(v1) },
-            (0x12, 0x02, Some(&Value(v0)),        Some(&Variable(ref v1)), None) => EventAst::IntVariableSubtract { value: v0, variable: VariableAst::new(v1) },
-            (0x12, 0x03, Some(&Variable(ref v0)), None,                    None) => EventAst::IntVariableIncrement { variable: VariableAst::new(v0) },
-            (0x12, 0x04, Some(&Variable(ref v0)), None,                    None) => EventAst::IntVariableDecrement { variable: VariableAst::new(v0) },
-            (0x12, 0x06, Some(&Scalar(v0)),       Some(&Variable(ref v1)), None) => EventAst::FloatVariableSet      { value: FloatValue::Constant(v0),                   variable: VariableAst::new(v1) },
-            (0x12, 0x06, Some(&Variable(ref v0)), Some(&Variable(ref v1)), None) => EventAst::FloatVariableSet      { value: FloatValue::Variable(VariableAst::new(v0)), variable: VariableAst::new(v1) },
-            (0x12, 0x07, Some(&Scalar(v0)),       Some(&Variable(ref v1)), None) => EventAst::FloatVariableAdd      { value: FloatValue::Constant(v0),                   variable: VariableAst::new(v1) },
-            (0x12, 0x07, Some(&Variable(ref v0)), Some(&Variable(ref v1)), None) => EventAst::FloatVariableAdd      { value: FloatValue::Variable(VariableAst::new(v0)), variable: VariableAst::new(v1) },
-            (0x12, 0x08, Some(&Scalar(v0)),       Some(&Variable(ref v1)), None) => EventAst::FloatVariableSubtract { value: FloatValue::Constant(v0),                   variable: VariableAst::new(v1) },
-            (0x12, 0x08, Some(&Variable(ref v0)), Some(&Variable(ref v1)), None) => EventAst::FloatVariableSubtract { value: FloatValue::Variable(VariableAst::new(v0)), variable: VariableAst::new(v1) },
-            (0x12, 0x0F, Some(&Scalar(v0)),       Some(&Variable(ref v1)), None) => EventAst::FloatVariableMultiply { value: FloatValue::Constant(v0),                   variable: VariableAst::new(v1) },
-            (0x12, 0x0F, Some(&Variable(ref v0)), Some(&Variable(ref v1)), None) => EventAst::FloatVariableMultiply { value: FloatValue::Variable(VariableAst::new(v0)), variable: VariableAst::new(v1) },
-            (0x12, 0x10, Some(&Scalar(v0)),       Some(&Variable(ref v1)), None) => EventAst::FloatVariableDivide   { value: FloatValue::Constant(v0),                   variable: VariableAst::new(v1) },
-            (0x12, 0x10, Some(&Variable(ref v0)), Some(&Variable(ref v1)), None) => EventAst::FloatVariableDivide   { value: FloatValue::Variable(VariableAst::new(v0)), variable: VariableAst::new(v1) },
-            (0x12, 0x0A, Some(&Variable(ref v0)), None,                    None) => EventAst::BoolVariableSetTrue { variable: VariableAst::new(v0) },
-            (0x12, 0x0B, Some(&Variable(ref v0)), None,                    None) => EventAst::BoolVariableSetFalse { variable: VariableAst::new(v0) },
+            (0x12, 0x00, Some(&Value(v0)),        Some(&Variable(v1)), None) => EventAst::IntVariableSet { value: v0, variable: VariableAst::new(v1) },
+            (0x12, 0x01, Some(&Value(v0)),        Some(&Variable(v1)), None) => EventAst::IntVariableAdd { value: v0, variable: VariableAst::new(v1) },
+            (0x12, 0x02, Some(&Value(v0)),        Some(&Variable(v1)), None) => EventAst::IntVariableSubtract { value: v0, variable: VariableAst::new(v1) },
+            (0x12, 0x03, Some(&Variable(v0)), None,                    None) => EventAst::IntVariableIncrement { variable: VariableAst::new(v0) },
+            (0x12, 0x04, Some(&Variable(v0)), None,                    None) => EventAst::IntVariableDecrement { variable: VariableAst::new(v0) },
+            (0x12, 0x06, Some(&Scalar(v0)),       Some(&Variable(v1)), None) => EventAst::FloatVariableSet      { value: FloatValue::Constant(v0),                   variable: VariableAst::new(v1) },
+            (0x12, 0x06, Some(&Variable(v0)), Some(&Variable(v1)), None) => EventAst::FloatVariableSet      { value: FloatValue::Variable(VariableAst::new(v0)), variable: VariableAst::new(v1) },
+            (0x12, 0x07, Some(&Scalar(v0)),       Some(&Variable(v1)), None) => EventAst::FloatVariableAdd      { value: FloatValue::Constant(v0),                   variable: VariableAst::new(v1) },
+            (0x12, 0x07, Some(&Variable(v0)), Some(&Variable(v1)), None) => EventAst::FloatVariableAdd      { value: FloatValue::Variable(VariableAst::new(v0)), variable: VariableAst::new(v1) },
+            (0x12, 0x08, Some(&Scalar(v0)),       Some(&Variable(v1)), None) => EventAst::FloatVariableSubtract { value: FloatValue::Constant(v0),                   variable: VariableAst::new(v1) },
+            (0x12, 0x08, Some(&Variable(v0)), Some(&Variable(v1)), None) => EventAst::FloatVariableSubtract { value: FloatValue::Variable(VariableAst::new(v0)), variable: VariableAst::new(v1) },
+            (0x12, 0x0F, Some(&Scalar(v0)),       Some(&Variable(v1)), None) => EventAst::FloatVariableMultiply { value: FloatValue::Constant(v0),                   variable: VariableAst::new(v1) },
+            (0x12, 0x0F, Some(&Variable(v0)), Some(&Variable(v1)), None) => EventAst::FloatVariableMultiply { value: FloatValue::Variable(VariableAst::new(v0)), variable: VariableAst::new(v1) },
+            (0x12, 0x10, Some(&Scalar(v0)),       Some(&Variable(v1)), None) => EventAst::FloatVariableDivide   { value: FloatValue::Constant(v0),                   variable: VariableAst::new(v1) },
+            (0x12, 0x10, Some(&Variable(v0)), Some(&Variable(v1)), None) => EventAst::FloatVariableDivide   { value: FloatValue::Variable(VariableAst::new(v0)), variable: VariableAst::new(v1) },
+            (0x12, 0x0A, Some(&Variable(v0)), None,                    None) => EventAst::BoolVariableSetTrue { variable: VariableAst::new(v0) },
+            (0x12, 0x0B, Some(&Variable(v0)), None,                    None) => EventAst::BoolVariableSetFalse { variable: VariableAst::new(v0) },
 
             // graphics
             (0x0B, 0x00, Some(&Value(v0)), Some(&Value(v1)), None) => EventAst::ModelChanger { reference: 0, switch_index: v0, bone_group_index: v1 },
@@ -802,15 +842,15 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
                 } else {
                     EventAst::Unknown (event.clone())
                 },
-            (0x1F, 0x01, Some(&Variable(ref v0)), Some(&Variable(ref v1)), Some(&Variable(ref v2))) =>
-                if let (Some(&Variable(ref v3)), Some(&Variable(ref v4))) = (args.get(3), args.get(4)) {
+            (0x1F, 0x01, Some(&Variable(v0)), Some(&Variable(v1)), Some(&Variable(v2))) =>
+                if let (Some(&Variable(v3)), Some(&Variable(v4))) = (args.get(3), args.get(4)) {
                     EventAst::ItemThrow { unk1: VariableAst::new(v0), unk2: VariableAst::new(v1), unk3: VariableAst::new(v2), unk4: Some(VariableAst::new(v3)), unk5: Some(VariableAst::new(v4)) }
                 } else {
                     EventAst::Unknown (event.clone())
                 },
-            (0x1F, 0x0E, Some(&Variable(ref v0)), Some(&Variable(ref v1)), Some(&Variable(ref v2)))
+            (0x1F, 0x0E, Some(&Variable(v0)), Some(&Variable(v1)), Some(&Variable(v2)))
                 => EventAst::ItemThrow { unk1: VariableAst::new(v0), unk2: VariableAst::new(v1), unk3: VariableAst::new(v2), unk4: None, unk5: None },
-            (0x1F, 0x01, Some(&Scalar(v0)), Some(&Scalar(v1)), Some(&Variable(ref v2))) =>
+            (0x1F, 0x01, Some(&Scalar(v0)), Some(&Scalar(v1)), Some(&Variable(v2))) =>
                     EventAst::ItemThrow2 { unk1: v0, unk2: v1, unk3: VariableAst::new(v2) },
             (0x1F, 0x02, None,             None,              None) => EventAst::ItemDrop,
             (0x1F, 0x03, Some(&Value(v0)), None,              None) => EventAst::ItemConsume { unk: v0 },
@@ -818,7 +858,7 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
             (0x1F, 0x05, None,             None,              None) => EventAst::FireWeapon,
             (0x1F, 0x06, None,             None,              None) => EventAst::FireProjectile,
             (0x1F, 0x07, Some(&Value(v0)), None,              None) => EventAst::Item1F { unk: v0 },
-            (0x1F, 0x08, Some(&Value(v0)), None,              None) => EventAst::ItemCreate { unk: v0 },
+            (0x1F, 0x08, Some(&Value(v0)), None,              None) => EventAst::ItemCreate { item: ItemId::new(v0) },
             (0x1F, 0x09, Some(&Bool(v0)),  None,              None) => EventAst::ItemVisibility (v0),
             (0x1F, 0x0A, None,             None,              None) => EventAst::ItemDelete,
             (0x1F, 0x0C, Some(&Value(v0)), None,              None) => EventAst::BeamSwordTrail { unk: v0 },
@@ -845,16 +885,25 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
 }
 
 impl Expression {
-    fn from_args(requirement: &Requirement, flip: bool, v1: Option<&Argument>, v2: Option<&Argument>, v3: Option<&Argument>) -> Option<Expression> {
+    /// Returns the condition tested by an If, And, Or or Else If event, None when it cannot be represented as an expression
+    pub(crate) fn from_condition_event(event: &Event) -> Option<Expression> {
+        let args: Vec<DecodedArgument> = event.arguments.iter().map(DecodedArgument::new).collect();
+        match args.first() {
+            Some(DecodedArgument::Requirement { ty, flip }) => Expression::from_args(ty, *flip, args.get(1), args.get(2), args.get(3)),
+            _ => None,
+        }
+    }
+
+    fn from_args(requirement: &Requirement, flip: bool, v1: Option<&DecodedArgument>, v2: Option<&DecodedArgument>, v3: Option<&DecodedArgument>) -> Option<Expression> {
         let test = match (v1, v2, v3) {
             (None, None, None) => {
                 Expression::Nullary(requirement.clone())
             }
             (Some(v1), None, None) => {
                 let value = Box::new(match v1 {
-                    &Argument::Scalar(v1)       => Expression::Scalar(v1),
-                    &Argument::Variable(ref v1) => Expression::Variable(VariableAst::new(v1)),
-                    &Argument::Value(v1)        => Expression::Value(v1),
+                    DecodedArgument::Scalar(v1)   => Expression::Scalar(*v1),
+                    DecodedArgument::Variable(v1) => Expression::Variable(VariableAst::new(v1)),
+                    DecodedArgument::Value(v1)    => Expression::Value(*v1),
                     _ => {
                         error!("Unhandled expression case: value: {:?}", v1);
                         return None;
@@ -862,20 +911,20 @@ impl Expression {
                 });
                 Expression::Unary (UnaryExpression { requirement: requirement.clone(), value })
             }
-            (Some(v1), Some(&Argument::Value(v2)), Some(v3)) => {
+            (Some(v1), Some(&DecodedArgument::Value(v2)), Some(v3)) => {
                 let left = Box::new(match v1 {
-                    &Argument::Scalar(v1)       => Expression::Scalar(v1),
-                    &Argument::Variable(ref v1) => Expression::Variable(VariableAst::new(v1)),
-                    &Argument::Value(v1)        => Expression::Value(v1),
+                    DecodedArgument::Scalar(v1)   => Expression::Scalar(*v1),
+                    DecodedArgument::Variable(v1) => Expression::Variable(VariableAst::new(v1)),
+                    DecodedArgument::Value(v1)    => Expression::Value(*v1),
                     _ => {
                         error!("Unhandled expression case: left");
                         return None;
                     }
                 });
                 let right = Box::new(match v3 {
-                    &Argument::Scalar(v3)       => Expression::Scalar(v3),
-                    &Argument::Variable(ref v3) => Expression::Variable(VariableAst::new(v3)),
-                    &Argument::Value(v3)        => Expression::Value(v3),
+                    DecodedArgument::Scalar(v3)   => Expression::Scalar(*v3),
+                    DecodedArgument::Variable(v3) => Expression::Variable(VariableAst::new(v3)),
+                    DecodedArgument::Value(v3)    => Expression::Value(*v3),
                     _ => {
                         error!("Unhandled expression case: right");
                         return None;
@@ -900,6 +949,27 @@ impl Expression {
 
         Some(if flip { Expression::Not (Box::new(test)) } else { test })
     }
+
+    /// Returns every requirement used in the expression
+    pub fn requirements(&self) -> Vec<&Requirement> {
+        match self {
+            Expression::Nullary (requirement) => vec!(requirement),
+            Expression::Unary (unary) => {
+                let mut requirements = vec!(&unary.requirement);
+                requirements.extend(unary.value.requirements());
+                requirements
+            }
+            Expression::Binary (binary) => {
+                let mut requirements = binary.left.requirements();
+                requirements.extend(binary.right.requirements());
+                requirements
+            }
+            Expression::Not (expression) => expression.requirements(),
+            Expression::Variable (_) |
+            Expression::Value (_) |
+            Expression::Scalar (_) => vec!(),
+        }
+    }
 }
 
 enum ProcessedBlock {
@@ -910,6 +980,7 @@ enum ProcessedBlock {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum EventAst {
     ///Pause the current flow of events until the set time is reached. Synchronous timers count down when they are reached in the code.
     SyncWait (f32),
@@ -951,9 +1022,11 @@ pub enum EventAst {
     EndSwitch,
     /// Briefly return execution back to the system to prevent crashes during infinite loops.
     LoopRest,
-    /// Runs a subroutine once per frame for the current action.
+    /// Runs a subroutine once per frame for the current action, in parallel with the script that called it.
+    /// BrawlBox calls this "Concurrent Infinite Loop".
     CallEveryFrame { thread_id: i32, offset: Offset },
-    /// Stops the execution of a loop created with CallEveryFrame
+    /// Stops the execution of a loop created with CallEveryFrame.
+    /// BrawlBox calls this "Terminate Concurrent Infinite Loop".
     RemoveCallEveryFrame { thread_id: i32 },
     /// Runs a subroutine once, the subroutine will persist even after the end of the action.
     /// Requires the Independent Subroutines code by Mawootad.
@@ -1212,7 +1285,7 @@ pub enum EventAst {
     /// Used when firing a cracker launcher.
     Item1F { unk: i32 },
     /// Create an item in the characters hand.
-    ItemCreate { unk: i32 },
+    ItemCreate { item: ItemId },
     /// Determines the visibility of the currently held item.
     ItemVisibility (bool),
     /// Deletes the currently held item.
@@ -1223,30 +1296,63 @@ pub enum EventAst {
     Unknown (Event)
 }
 
+impl EventAst {
+    /// Converts a single event the same way as `ScriptAst::new`, for callers that walk the raw events e.g. with `script::normalize_timers`.
+    /// None for events that start, continue or end a block i.e. loops and If statements, as they can only be converted along with their block.
+    pub fn from_event(event: &Event) -> Option<EventAst> {
+        match (event.namespace, event.code) {
+            (0x00, 0x04) | (0x00, 0x05) | (0x00, 0x0A ..= 0x0F) => None,
+            _ => match process_block(&mut slice::from_ref(event).iter().peekable()) {
+                ProcessedBlock::Finished (block) => block.events.into_iter().next(),
+                _ => None,
+            }
+        }
+    }
+
+    /// Returns the color the flash event changes the character to.
+    /// Events that set a new flash effect take effect immediately, so have 0 `frames`.
+    pub fn color_flash(&self) -> Option<ColorFlash> {
+        match *self {
+            EventAst::FlashEffectOverlay { red, green, blue, alpha } |
+            EventAst::FlashEffectLight { red, green, blue, alpha, .. }
+                => Some(ColorFlash { rgba: Rgba::from_channels(red, green, blue, alpha), frames: 0 }),
+            EventAst::SetColorOfFlashEffectOverlay { transition_time, red, green, blue, alpha } |
+            EventAst::SetColorOfFlashEffectLight { transition_time, red, green, blue, alpha }
+                => Some(ColorFlash { rgba: Rgba::from_channels(red, green, blue, alpha), frames: transition_time }),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum FloatValue {
     Variable (VariableAst),
     Constant (f32),
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct Block {
     pub events: Vec<EventAst>
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct ForLoop {
     pub iterations: Iterations,
     pub block: Block,
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum Iterations {
     Finite (i32),
     Infinite
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct IfStatement {
     pub test: Expression,
     pub then_branch: Block,
@@ -1254,6 +1360,7 @@ pub struct IfStatement {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum Expression {
     Nullary  (Requirement),
     Unary    (UnaryExpression),
@@ -1265,6 +1372,7 @@ pub enum Expression {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct BinaryExpression {
     pub left: Box<Expression>,
     pub right: Box<Expression>,
@@ -1272,12 +1380,14 @@ pub struct BinaryExpression {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct UnaryExpression {
     pub requirement: Requirement,
     pub value: Box<Expression>,
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum ComparisonOperator {
     LessThan,
     LessThanOrEqual,
@@ -1305,6 +1415,7 @@ impl ComparisonOperator {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum EdgeSlide {
     SlideOff,
     StayOn,
@@ -1324,6 +1435,7 @@ impl EdgeSlide {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum HurtBoxState {
     Normal,
     Invincible,
@@ -1370,6 +1482,7 @@ impl HurtBoxState {
 }
 
 #[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum AngleFlip {
     AwayFromAttacker,
     AttackerDir,
@@ -1391,6 +1504,7 @@ impl AngleFlip {
 }
 
 #[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum HitBoxEffect {
     Normal,
     None,
@@ -1454,6 +1568,7 @@ impl HitBoxEffect {
 }
 
 #[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum HitBoxSound {
     Unique,
     Punch,
@@ -1511,6 +1626,7 @@ impl HitBoxSound {
 }
 
 #[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum HitBoxSseType {
     None,
     Head,
@@ -1575,6 +1691,7 @@ impl HitBoxSseType {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct HitBoxArguments {
     pub bone_index:         i16,
     pub hitbox_id:          u8,
@@ -1609,6 +1726,7 @@ pub struct HitBoxArguments {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct SpecialHitBoxArguments {
     pub hitbox_args:       HitBoxArguments,
     pub rehit_rate:        i32,
@@ -1656,6 +1774,7 @@ pub struct SpecialHitBoxArguments {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum DefensiveCollisionType {
     Block,
     Reflect,
@@ -1673,6 +1792,7 @@ impl DefensiveCollisionType {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum DefensiveCollisionDirection {
     Front,
     FrontAndBack,
@@ -1690,6 +1810,7 @@ impl DefensiveCollisionDirection {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct MoveHitBox {
     pub hitbox_id:    i32,
     pub new_bone:     i32,
@@ -1699,6 +1820,7 @@ pub struct MoveHitBox {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct GrabBoxArguments {
     pub hitbox_id:    i32,
     pub bone_index:   i32,
@@ -1712,6 +1834,7 @@ pub struct GrabBoxArguments {
 }
 
 #[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum GrabTarget {
     None,
     GroundedOnly,
@@ -1749,6 +1872,7 @@ impl GrabTarget {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct SpecifyThrow {
     /// ID of throw data. Seemingly, a "0" indicates this is the throw data, while a "1" indicates this is used if the opponent escapes during the throw. "2" has also been seen (by Light Arrow)."
     pub throw_use:   ThrowUse,
@@ -1771,6 +1895,7 @@ pub struct SpecifyThrow {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum ThrowUse {
     Throw,
     GrabInterrupt,
@@ -1788,6 +1913,7 @@ impl ThrowUse {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct ApplyThrow {
     pub unk0: i32,
     pub bone: i32,
@@ -1797,6 +1923,7 @@ pub struct ApplyThrow {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum LedgeGrabEnable {
     Disable,
     EnableInFront,
@@ -1825,6 +1952,7 @@ impl LedgeGrabEnable {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum ArmorType {
     None,
     SuperArmor,
@@ -1846,6 +1974,7 @@ impl ArmorType {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct SetOrAddVelocity {
     pub x_vel: f32,
     pub y_vel: f32,
@@ -1853,7 +1982,8 @@ pub struct SetOrAddVelocity {
     pub y_set: bool,
 }
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum DisableMovement {
     Enable,
     DisableVertical,
@@ -1873,6 +2003,7 @@ impl DisableMovement {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct GraphicEffect {
     pub graphic:                  i32,
     pub bone:                     i32,
@@ -1892,7 +2023,29 @@ pub struct GraphicEffect {
     pub terminate_with_animation: bool
 }
 
+impl GraphicEffect {
+    pub fn offset(&self) -> Vector3<f32> {
+        Vector3::new(self.x_offset, self.y_offset, self.z_offset)
+    }
+
+    /// Rotation in degrees around each axis
+    pub fn rotation(&self) -> Vector3<f32> {
+        Vector3::new(self.x_rotation, self.y_rotation, self.z_rotation)
+    }
+
+    /// The offset is randomly moved by up to this much along each axis
+    pub fn random_offset(&self) -> Vector3<f32> {
+        Vector3::new(self.random_x_offset, self.random_y_offset, self.random_z_offset)
+    }
+
+    /// The rotation is randomly changed by up to this many degrees around each axis
+    pub fn random_rotation(&self) -> Vector3<f32> {
+        Vector3::new(self.random_x_rotation, self.random_y_rotation, self.random_z_rotation)
+    }
+}
+
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct ExternalGraphicEffect {
     pub file:                     i16,
     pub graphic:                  i16,
@@ -1909,6 +2062,7 @@ pub struct ExternalGraphicEffect {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct ExternalGraphicEffectRandomize {
     pub random_x_offset:   f32,
     pub random_y_offset:   f32,
@@ -1919,6 +2073,7 @@ pub struct ExternalGraphicEffectRandomize {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct LimitedScreenTint {
     pub transition_in_time: i32,
     pub red: i32,
@@ -1930,6 +2085,7 @@ pub struct LimitedScreenTint {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct UnlimitedScreenTint {
     pub tint_id: i32,
     pub transition_in_time: i32,
@@ -1940,6 +2096,7 @@ pub struct UnlimitedScreenTint {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct SwordGlow {
     pub color:                  i32,
     pub blur_length:            i32,
@@ -1966,7 +2123,51 @@ pub struct SwordGlow {
     pub glow_length:            f32,
 }
 
+/// A color with 8 bits per channel.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct Rgba {
+    pub red:   u8,
+    pub green: u8,
+    pub blue:  u8,
+    pub alpha: u8,
+}
+
+impl Rgba {
+    /// Scripts store each channel in its own argument, values outside of 0-255 are clamped.
+    pub fn from_channels(red: i32, green: i32, blue: i32, alpha: i32) -> Rgba {
+        let channel = |value: i32| value.clamp(0, 0xFF) as u8;
+        Rgba {
+            red:   channel(red),
+            green: channel(green),
+            blue:  channel(blue),
+            alpha: channel(alpha),
+        }
+    }
+
+    /// Unpacks a color stored as 0xRRGGBBAA
+    pub fn from_u32(value: u32) -> Rgba {
+        let [red, green, blue, alpha] = value.to_be_bytes();
+        Rgba { red, green, blue, alpha }
+    }
+
+    /// Packs the color as 0xRRGGBBAA
+    pub fn to_u32(self) -> u32 {
+        u32::from_be_bytes([self.red, self.green, self.blue, self.alpha])
+    }
+}
+
+/// The color of a flash effect, see `EventAst::color_flash`.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct ColorFlash {
+    pub rgba:   Rgba,
+    /// Number of frames taken to transition from the previous color
+    pub frames: i32,
+}
+
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct AestheticWindEffect {
     pub unk1:    i32,
     pub unk2:    f32,
@@ -1982,13 +2183,15 @@ pub struct AestheticWindEffect {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct Interrupt {
     pub interrupt_id: Option<i32>,
     pub action:       i32,
     pub test:         Expression
 }
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum InterruptType {
     Main,
     GroundSpecial,
@@ -2043,6 +2246,7 @@ impl InterruptType {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct CameraCloseup {
     pub zoom_time: i32,
     pub unk:       i32,
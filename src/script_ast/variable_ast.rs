@@ -1,6 +1,7 @@
 use crate::script::{Variable, VariableMemoryType, VariableDataType};
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum VariableAst {
     /// Known as IC in existing tools
     InternalConstantInt (InternalConstantInt),
@@ -57,7 +58,9 @@ impl VariableAst {
     }
 }
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+#[non_exhaustive]
 pub enum InternalConstantInt {
     CurrentFrame,
     Damage,
@@ -93,11 +96,14 @@ pub enum InternalConstantInt {
     PreviousControlStickXAxisRelative,
     PreviousControlStickXAxisRelativeReverse,
     PreviousControlStickXAxisAbsolute,
+    #[deprecated(note = "no address is known to refer to this constant so parsing never produces it")]
     PreviousControlStickXAxisReverse,
+    #[deprecated(note = "no address is known to refer to this constant so parsing never produces it")]
     PreviousControlStickXAxisReverse2,
     PreviousControlStickYAxis,
     PreviousControlStickYAxisAbsolute,
     PreviousControlStickYAxisReverse,
+    #[deprecated(note = "no address is known to refer to this constant so parsing never produces it")]
     PreviousControlStickYAxis2,
     CrawlControlStickXOffsetMax,
     CrawlControlStickXOffsetMin,
@@ -125,64 +131,115 @@ pub enum InternalConstantInt {
 
 impl InternalConstantInt {
     fn new(address: u32) -> InternalConstantInt {
-        match address {
-           00000 => InternalConstantInt::CurrentFrame,
-           00002 => InternalConstantInt::Damage,
-           00003 => InternalConstantInt::CharacterXPosition,
-           00004 => InternalConstantInt::CharacterYPosition,
-           00008 => InternalConstantInt::CharacterDirection,
-           00009 => InternalConstantInt::CharacterDirectionOpposite,
-           00023 => InternalConstantInt::VerticalCharacterVelocity,
-           00024 => InternalConstantInt::CurrentFrameSpeed,
-           00028 => InternalConstantInt::HorizontalCharacterVelocity,
-           00038 => InternalConstantInt::Knockback,
-           00039 => InternalConstantInt::SurfaceTraction,
-           01000 => InternalConstantInt::XVelocity,
-           01005 => InternalConstantInt::LaunchSpeed,
-           01006 => InternalConstantInt::RightVelocity,
-           01007 => InternalConstantInt::LeftVelocity,
-           01008 => InternalConstantInt::UpVelocity,
-           01009 => InternalConstantInt::DownVelocity,
-           01010 => InternalConstantInt::ControlStickXAxis,
-           01011 => InternalConstantInt::ControlStickXAxisRelative,
-           01012 => InternalConstantInt::ControlStickXAxisRelativeReverse,
-           01013 => InternalConstantInt::ControlStickXAxisAbsolute,
-           01014 => InternalConstantInt::ControlStickXAxisReverse,
-           01017 => InternalConstantInt::ControlStickXAxisReverse2,
-           01018 => InternalConstantInt::ControlStickYAxis,
-           01019 => InternalConstantInt::ControlStickYAxisAbsolute,
-           01020 => InternalConstantInt::ControlStickYAxisReverse,
-           01021 => InternalConstantInt::ControlStickYAxis2,
-           01022 => InternalConstantInt::PreviousControlStickXAxis,
-           01023 => InternalConstantInt::PreviousControlStickXAxisRelative,
-           01024 => InternalConstantInt::PreviousControlStickXAxisRelativeReverse,
-           01025 => InternalConstantInt::PreviousControlStickXAxisAbsolute,
-           01026 => InternalConstantInt::PreviousControlStickYAxis,
-           01027 => InternalConstantInt::PreviousControlStickYAxisAbsolute,
-           01028 => InternalConstantInt::PreviousControlStickYAxisReverse,
-           03134 => InternalConstantInt::CrawlControlStickXOffsetMax,
-           03136 => InternalConstantInt::CrawlControlStickXOffsetMin,
-           20000 => InternalConstantInt::CurrentSubaction,
-           20001 => InternalConstantInt::CurrentAction,
-           20003 => InternalConstantInt::PreviousAction,
-           20009 => InternalConstantInt::HeldItem,
-           21004 => InternalConstantInt::EffectOfAttack,
-           21010 => InternalConstantInt::FramesSinceNormal,
-           21012 => InternalConstantInt::FramesSinceSpecial,
-           21014 => InternalConstantInt::FramesSinceJump,
-           21016 => InternalConstantInt::FramesSinceShield,
-           21018 => InternalConstantInt::FramesSinceShield2,
-           23001 => InternalConstantInt::TurnRunFrameTimer,
-           23002 => InternalConstantInt::JumpStartTimer,
-           23003 => InternalConstantInt::MaxJumpCount,
-           23004 => InternalConstantInt::GlideStartTimer,
-           23007 => InternalConstantInt::TermVelFrameTimer,
-            _    => InternalConstantInt::Address (address)
+        INTERNAL_CONSTANT_INTS.iter()
+            .find(|(x, _, _)| *x == address)
+            .map(|(_, _, constant)| constant.clone())
+            .unwrap_or(InternalConstantInt::Address (address))
+    }
+
+    /// The address used to refer to this constant in scripts.
+    /// None for the deprecated constants that no known address refers to.
+    pub fn address(&self) -> Option<u32> {
+        match self {
+            InternalConstantInt::Address (address) => Some(*address),
+            constant => INTERNAL_CONSTANT_INTS.iter()
+                .find(|(_, _, x)| x == constant)
+                .map(|(address, _, _)| *address),
+        }
+    }
+
+    /// The name of the variant, `Address` for constants that are not named yet
+    pub fn name(&self) -> &'static str {
+        match self {
+            InternalConstantInt::Address (_) => "Address",
+            constant => INTERNAL_CONSTANT_INTS.iter()
+                .map(|(_, name, x)| (name, x))
+                .chain(UNADDRESSED_INTERNAL_CONSTANT_INTS.iter().map(|(name, x)| (name, x)))
+                .find(|(_, x)| *x == constant)
+                .map(|(name, _)| *name)
+                .unwrap(),
         }
     }
+
+    /// Returns the named constant matching the name returned by `InternalConstantInt::name`
+    pub fn from_name(name: &str) -> Option<InternalConstantInt> {
+        INTERNAL_CONSTANT_INTS.iter()
+            .map(|(_, name, x)| (name, x))
+            .chain(UNADDRESSED_INTERNAL_CONSTANT_INTS.iter().map(|(name, x)| (name, x)))
+            .find(|(x, _)| **x == name)
+            .map(|(_, constant)| constant.clone())
+    }
 }
 
+/// Named constants that no known address refers to, kept so existing code matching on them still compiles.
+#[allow(deprecated)]
+const UNADDRESSED_INTERNAL_CONSTANT_INTS: &[(&str, InternalConstantInt)] = &[
+    ("PreviousControlStickXAxisReverse",  InternalConstantInt::PreviousControlStickXAxisReverse),
+    ("PreviousControlStickXAxisReverse2", InternalConstantInt::PreviousControlStickXAxisReverse2),
+    ("PreviousControlStickYAxis2",        InternalConstantInt::PreviousControlStickYAxis2),
+];
+
+/// Every named internal constant with its address and name.
+/// Addresses are decimal, matching how they are written in PSA and BrawlBox.
+///
+/// 1015, 1016 and the 3xxx addresses other than the crawl offsets are missing from the list of names this table was built from,
+/// so they stay `Address` until their meaning is confirmed against the game.
+const INTERNAL_CONSTANT_INTS: &[(u32, &str, InternalConstantInt)] = &[
+    (0,     "CurrentFrame",                             InternalConstantInt::CurrentFrame),
+    (2,     "Damage",                                   InternalConstantInt::Damage),
+    (3,     "CharacterXPosition",                       InternalConstantInt::CharacterXPosition),
+    (4,     "CharacterYPosition",                       InternalConstantInt::CharacterYPosition),
+    (8,     "CharacterDirection",                       InternalConstantInt::CharacterDirection),
+    (9,     "CharacterDirectionOpposite",               InternalConstantInt::CharacterDirectionOpposite),
+    (23,    "VerticalCharacterVelocity",                InternalConstantInt::VerticalCharacterVelocity),
+    (24,    "CurrentFrameSpeed",                        InternalConstantInt::CurrentFrameSpeed),
+    (28,    "HorizontalCharacterVelocity",              InternalConstantInt::HorizontalCharacterVelocity),
+    (38,    "Knockback",                                InternalConstantInt::Knockback),
+    (39,    "SurfaceTraction",                          InternalConstantInt::SurfaceTraction),
+    (1000,  "XVelocity",                                InternalConstantInt::XVelocity),
+    (1005,  "LaunchSpeed",                              InternalConstantInt::LaunchSpeed),
+    (1006,  "RightVelocity",                            InternalConstantInt::RightVelocity),
+    (1007,  "LeftVelocity",                             InternalConstantInt::LeftVelocity),
+    (1008,  "UpVelocity",                               InternalConstantInt::UpVelocity),
+    (1009,  "DownVelocity",                             InternalConstantInt::DownVelocity),
+    (1010,  "ControlStickXAxis",                        InternalConstantInt::ControlStickXAxis),
+    (1011,  "ControlStickXAxisRelative",                InternalConstantInt::ControlStickXAxisRelative),
+    (1012,  "ControlStickXAxisRelativeReverse",         InternalConstantInt::ControlStickXAxisRelativeReverse),
+    (1013,  "ControlStickXAxisAbsolute",                InternalConstantInt::ControlStickXAxisAbsolute),
+    (1014,  "ControlStickXAxisReverse",                 InternalConstantInt::ControlStickXAxisReverse),
+    (1017,  "ControlStickXAxisReverse2",                InternalConstantInt::ControlStickXAxisReverse2),
+    (1018,  "ControlStickYAxis",                        InternalConstantInt::ControlStickYAxis),
+    (1019,  "ControlStickYAxisAbsolute",                InternalConstantInt::ControlStickYAxisAbsolute),
+    (1020,  "ControlStickYAxisReverse",                 InternalConstantInt::ControlStickYAxisReverse),
+    (1021,  "ControlStickYAxis2",                       InternalConstantInt::ControlStickYAxis2),
+    (1022,  "PreviousControlStickXAxis",                InternalConstantInt::PreviousControlStickXAxis),
+    (1023,  "PreviousControlStickXAxisRelative",        InternalConstantInt::PreviousControlStickXAxisRelative),
+    (1024,  "PreviousControlStickXAxisRelativeReverse", InternalConstantInt::PreviousControlStickXAxisRelativeReverse),
+    (1025,  "PreviousControlStickXAxisAbsolute",        InternalConstantInt::PreviousControlStickXAxisAbsolute),
+    (1026,  "PreviousControlStickYAxis",                InternalConstantInt::PreviousControlStickYAxis),
+    (1027,  "PreviousControlStickYAxisAbsolute",        InternalConstantInt::PreviousControlStickYAxisAbsolute),
+    (1028,  "PreviousControlStickYAxisReverse",         InternalConstantInt::PreviousControlStickYAxisReverse),
+    (3134,  "CrawlControlStickXOffsetMax",              InternalConstantInt::CrawlControlStickXOffsetMax),
+    (3136,  "CrawlControlStickXOffsetMin",              InternalConstantInt::CrawlControlStickXOffsetMin),
+    (20000, "CurrentSubaction",                         InternalConstantInt::CurrentSubaction),
+    (20001, "CurrentAction",                            InternalConstantInt::CurrentAction),
+    (20003, "PreviousAction",                           InternalConstantInt::PreviousAction),
+    (20009, "HeldItem",                                 InternalConstantInt::HeldItem),
+    (21004, "EffectOfAttack",                           InternalConstantInt::EffectOfAttack),
+    (21010, "FramesSinceNormal",                        InternalConstantInt::FramesSinceNormal),
+    (21012, "FramesSinceSpecial",                       InternalConstantInt::FramesSinceSpecial),
+    (21014, "FramesSinceJump",                          InternalConstantInt::FramesSinceJump),
+    (21016, "FramesSinceShield",                        InternalConstantInt::FramesSinceShield),
+    (21018, "FramesSinceShield2",                       InternalConstantInt::FramesSinceShield2),
+    (23001, "TurnRunFrameTimer",                        InternalConstantInt::TurnRunFrameTimer),
+    (23002, "JumpStartTimer",                           InternalConstantInt::JumpStartTimer),
+    (23003, "MaxJumpCount",                             InternalConstantInt::MaxJumpCount),
+    (23004, "GlideStartTimer",                          InternalConstantInt::GlideStartTimer),
+    (23007, "TermVelFrameTimer",                        InternalConstantInt::TermVelFrameTimer),
+];
+
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum LongtermAccessInt {
     JumpsUsed,
     WallJumpCount,
@@ -214,10 +271,10 @@ pub enum LongtermAccessInt {
 impl LongtermAccessInt {
     fn new(address: u32) -> LongtermAccessInt {
         match address {
-           01 => LongtermAccessInt::JumpsUsed,
-           03 => LongtermAccessInt::WallJumpCount,
-           04 => LongtermAccessInt::WallJumpInterval,
-           05 => LongtermAccessInt::FootstoolCount,
+           1  => LongtermAccessInt::JumpsUsed,
+           3  => LongtermAccessInt::WallJumpCount,
+           4  => LongtermAccessInt::WallJumpInterval,
+           5  => LongtermAccessInt::FootstoolCount,
            13 => LongtermAccessInt::FallTime,
            20 => LongtermAccessInt::SwimTime,
            24 => LongtermAccessInt::LipStickRefresh,
@@ -244,6 +301,7 @@ impl LongtermAccessInt {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum LongtermAccessFloat {
     SpecialLandingLag,
     SpecialFallMobilityMultiplier,
@@ -267,6 +325,7 @@ impl LongtermAccessFloat {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum LongtermAccessBool {
     IsDead,
     CannotDie,
@@ -289,11 +348,11 @@ pub enum LongtermAccessBool {
 impl LongtermAccessBool {
     fn new(address: u32) -> LongtermAccessBool {
         match address {
-            00 => LongtermAccessBool::IsDead,
-            01 => LongtermAccessBool::CannotDie,
-            05 => LongtermAccessBool::AutomaticFootstool,
-            08 => LongtermAccessBool::HasFinal,
-            09 => LongtermAccessBool::HasFinalAura,
+            0  => LongtermAccessBool::IsDead,
+            1  => LongtermAccessBool::CannotDie,
+            5  => LongtermAccessBool::AutomaticFootstool,
+            8  => LongtermAccessBool::HasFinal,
+            9  => LongtermAccessBool::HasFinalAura,
             10 => LongtermAccessBool::HasCurry,
             11 => LongtermAccessBool::HasHammer,
             17 => LongtermAccessBool::HitByParalyze,
@@ -310,6 +369,7 @@ impl LongtermAccessBool {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum RandomAccessInt {
     ThrowDataParam1,
     ThrowDataParam2,
@@ -329,6 +389,7 @@ impl RandomAccessInt {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum RandomAccessFloat {
     EnableTurnWhenBelowZero,
     Address (u32),
@@ -344,6 +405,7 @@ impl RandomAccessFloat {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum RandomAccessBool {
     CharacterFloat,
     EnableFastFall,
@@ -361,9 +423,9 @@ pub enum RandomAccessBool {
 impl RandomAccessBool {
     fn new(address: u32) -> Self {
         match address {
-            00 => RandomAccessBool::CharacterFloat,
-            02 => RandomAccessBool::EnableFastFall,
-            06 => RandomAccessBool::Shorthop,
+            0  => RandomAccessBool::CharacterFloat,
+            2  => RandomAccessBool::EnableFastFall,
+            6  => RandomAccessBool::Shorthop,
             16 => RandomAccessBool::EnableActionTransition,
             18 => RandomAccessBool::SpecialsMovement,
             19 => RandomAccessBool::EnableGlide,
@@ -375,3 +437,45 @@ impl RandomAccessBool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn internal_constant_round_trip() {
+        for (address, name, constant) in INTERNAL_CONSTANT_INTS {
+            assert_eq!(&InternalConstantInt::new(*address), constant);
+            assert_eq!(constant.address(), Some(*address));
+            assert_eq!(constant.name(), *name);
+            assert_eq!(InternalConstantInt::from_name(name).as_ref(), Some(constant));
+            assert_eq!(format!("{:?}", constant), *name);
+        }
+
+        for (name, constant) in UNADDRESSED_INTERNAL_CONSTANT_INTS {
+            assert_eq!(constant.address(), None);
+            assert_eq!(constant.name(), *name);
+            assert_eq!(InternalConstantInt::from_name(name).as_ref(), Some(constant));
+            assert_eq!(format!("{:?}", constant), *name);
+        }
+
+        for address in [1015, 1016, 3000, 3135].iter() {
+            let unnamed = InternalConstantInt::new(*address);
+            assert_eq!(unnamed, InternalConstantInt::Address (*address));
+            assert_eq!(unnamed.address(), Some(*address));
+            assert_eq!(unnamed.name(), "Address");
+        }
+        assert_eq!(InternalConstantInt::from_name("Address"), None);
+    }
+
+    #[test]
+    fn internal_constants_are_unique() {
+        let addresses: HashSet<_> = INTERNAL_CONSTANT_INTS.iter().map(|(x, _, _)| x).collect();
+        let names:     HashSet<_> = INTERNAL_CONSTANT_INTS.iter().map(|(_, x, _)| x)
+            .chain(UNADDRESSED_INTERNAL_CONSTANT_INTS.iter().map(|(x, _)| x))
+            .collect();
+        assert_eq!(addresses.len(), INTERNAL_CONSTANT_INTS.len());
+        assert_eq!(names.len(),     INTERNAL_CONSTANT_INTS.len() + UNADDRESSED_INTERNAL_CONSTANT_INTS.len());
+    }
+}
@@ -0,0 +1,241 @@
+//! Event level differences between the scripts of two versions of a fighter.
+//!
+//! Events are matched by their id and arguments, so an event that only moved shows up as unchanged.
+//! Subroutine and goto events refer to scripts by offset, so they show up as changed when the called script moved, even when it is identical.
+
+use crate::fighter::Fighter;
+use crate::sakurai::fighter_data::{ActionFlagsBitChange, ScriptCollection};
+use crate::script::{Event, EventClass, Script};
+
+/// Controls which changes `diff_script` and `diff_fighter_scripts` report.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct DiffOptions {
+    /// Leave out changes to events that are `EventClass::Cosmetic` e.g. graphics, sounds and rumble
+    pub ignore_cosmetic: bool,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum EventChangeKind {
+    Added,
+    Removed,
+    /// The event was replaced by an event with the same id but different arguments
+    Changed,
+}
+
+/// A single event that differs between two versions of a script.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct EventChange {
+    pub kind: EventChangeKind,
+    /// Index of the event in the old script, None for added events
+    pub old_index: Option<usize>,
+    /// Index of the event in the new script, None for removed events
+    pub new_index: Option<usize>,
+    pub old: Option<Event>,
+    pub new: Option<Event>,
+    pub class: EventClass,
+}
+
+/// The changed events of a single subaction script.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct ScriptChanges {
+    pub subaction: String,
+    pub collection: ScriptCollection,
+    pub changes: Vec<EventChange>,
+}
+
+/// The bits of the flags of a fighter specific action that differ between two versions of a fighter.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct ActionFlagsChanges {
+    /// The id of the action, fighter specific actions start at 0x112
+    pub action: usize,
+    pub changes: Vec<ActionFlagsBitChange>,
+}
+
+/// Returns the events that differ between `old` and `new`, in the order they occur in the scripts.
+///
+/// An event removed and added at the same position with the same id is reported as `EventChangeKind::Changed`.
+pub fn diff_script(old: &Script, new: &Script, options: &DiffOptions) -> Vec<EventChange> {
+    let old = &old.events;
+    let new = &new.events;
+
+    // longest common subsequence, lengths[i][j] is the length for old[i..] and new[j..]
+    let mut lengths = vec!(vec!(0_usize; new.len() + 1); old.len() + 1);
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = vec!();
+    // the events removed and added since the last unchanged event
+    let mut removed: Vec<usize> = vec!();
+    let mut added: Vec<usize> = vec!();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            push_changes(&mut changes, old, new, &removed, &added);
+            removed.clear();
+            added.clear();
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lengths[i][j + 1] >= lengths[i + 1][j]) {
+            added.push(j);
+            j += 1;
+        } else {
+            removed.push(i);
+            i += 1;
+        }
+    }
+    push_changes(&mut changes, old, new, &removed, &added);
+
+    if options.ignore_cosmetic {
+        changes.retain(|x| x.class != EventClass::Cosmetic);
+    }
+    changes
+}
+
+/// Pairs up the events removed and added between two unchanged events
+fn push_changes(changes: &mut Vec<EventChange>, old: &[Event], new: &[Event], removed: &[usize], added: &[usize]) {
+    let paired = removed.iter().zip(added).take_while(|(i, j)| old[**i].namespace == new[**j].namespace && old[**i].code == new[**j].code).count();
+    for (i, j) in removed.iter().zip(added).take(paired) {
+        changes.push(EventChange {
+            kind:      EventChangeKind::Changed,
+            old_index: Some(*i),
+            new_index: Some(*j),
+            old:       Some(old[*i].clone()),
+            new:       Some(new[*j].clone()),
+            class:     old[*i].class(),
+        });
+    }
+    for i in &removed[paired..] {
+        changes.push(EventChange { kind: EventChangeKind::Removed, old_index: Some(*i), new_index: None, old: Some(old[*i].clone()), new: None, class: old[*i].class() });
+    }
+    for j in &added[paired..] {
+        changes.push(EventChange { kind: EventChangeKind::Added, old_index: None, new_index: Some(*j), old: None, new: Some(new[*j].clone()), class: new[*j].class() });
+    }
+}
+
+/// Compares the scripts of every subaction in both versions of the fighter, subactions are matched by name.
+/// Only scripts with at least one reported change are returned, in the order of the subactions of `new`.
+pub fn diff_fighter_scripts(old: &Fighter, new: &Fighter, options: &DiffOptions) -> Vec<ScriptChanges> {
+    let (old_data, new_data) = match (old.get_fighter_data(), new.get_fighter_data()) {
+        (Some(old_data), Some(new_data)) => (old_data, new_data),
+        _ => return vec!(),
+    };
+
+    let mut result = vec!();
+    for (new_index, flags) in new_data.subaction_flags.iter().enumerate() {
+        let old_index = match old_data.subaction_flags.iter().position(|x| x.name == flags.name) {
+            Some(index) => index,
+            None => continue,
+        };
+        let (old_scripts, new_scripts) = match (old_data.subaction_scripts(old_index), new_data.subaction_scripts(new_index)) {
+            (Some(old_scripts), Some(new_scripts)) => (old_scripts, new_scripts),
+            _ => continue,
+        };
+        for ((collection, old_script), (_, new_script)) in old_scripts.iter().zip(new_scripts.iter()) {
+            let changes = diff_script(old_script, new_script, options);
+            if !changes.is_empty() {
+                result.push(ScriptChanges { subaction: flags.name.clone(), collection, changes });
+            }
+        }
+    }
+    result
+}
+
+/// Compares the flags of every fighter specific action in both versions of the fighter, actions are matched by id.
+/// The meaning of the bits is undocumented, so every changed bit is reported as a raw `ActionFlagsBitChange`.
+/// Only actions with at least one changed bit are returned, actions only one version has are not compared.
+pub fn diff_action_flags(old: &Fighter, new: &Fighter) -> Vec<ActionFlagsChanges> {
+    let (old_data, new_data) = match (old.get_fighter_data(), new.get_fighter_data()) {
+        (Some(old_data), Some(new_data)) => (old_data, new_data),
+        _ => return vec!(),
+    };
+
+    old_data.action_flags.iter().zip(new_data.action_flags.iter()).enumerate()
+        .map(|(i, (old_flags, new_flags))| ActionFlagsChanges { action: 0x112 + i, changes: old_flags.changed_bits(new_flags) })
+        .filter(|x| !x.changes.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sakurai::SectionData;
+    use crate::sakurai::fighter_data::ActionFlags;
+    use crate::script::Argument;
+    use crate::test_util::{damage, ScriptBuilder, TestFighterBuilder};
+
+    fn sound(script: ScriptBuilder, id: i32) -> ScriptBuilder {
+        script.event(0x0A, 0x00, vec!(Argument::Value (id)))
+    }
+
+    #[test]
+    fn changed_added_removed() {
+        let old = sound(ScriptBuilder::new().sync_timer(5.0).hitbox(damage(10)), 1).sync_timer(2.0).build();
+        let new = ScriptBuilder::new().sync_timer(5.0).hitbox(damage(12)).sync_timer(2.0).terminate_collisions().build();
+
+        let changes = diff_script(&old, &new, &DiffOptions::default());
+        let kinds: Vec<_> = changes.iter().map(|x| (x.kind, x.old_index, x.new_index, x.class)).collect();
+        assert_eq!(kinds, vec!(
+            (EventChangeKind::Changed, Some(1), Some(1), EventClass::Gameplay),
+            (EventChangeKind::Removed, Some(2), None,    EventClass::Cosmetic),
+            (EventChangeKind::Added,   None,    Some(3), EventClass::Gameplay),
+        ));
+
+        let changes = diff_script(&old, &new, &DiffOptions { ignore_cosmetic: true });
+        assert_eq!(changes.len(), 2);
+        assert!(diff_script(&old, &old, &DiffOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn only_cosmetic_changes() {
+        let fighter = |sfx| TestFighterBuilder::new("Test")
+            .subaction("Wait1", |s| s.sync_timer(10.0))
+            .subaction("AttackS4S", |s| sound(s.sync_timer(5.0), sfx).hitbox(damage(14)).sync_timer(3.0))
+            .build();
+        let old = fighter(0x1F00);
+        let new = fighter(0x1F01);
+
+        let changes = diff_fighter_scripts(&old, &new, &DiffOptions::default());
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].subaction.as_str(), changes[0].collection), ("AttackS4S", ScriptCollection::Main));
+        assert_eq!(changes[0].changes[0].kind, EventChangeKind::Changed);
+
+        assert!(diff_fighter_scripts(&old, &new, &DiffOptions { ignore_cosmetic: true }).is_empty());
+    }
+
+    #[test]
+    fn action_flags_bits() {
+        let fighter = |flags: &[(u32, u32, u32, u32)]| {
+            let mut fighter = TestFighterBuilder::new("Test").subaction("Wait1", |s| s).build();
+            let data = fighter.get_fighter_sakurai_mut().unwrap().sections.iter_mut().find_map(|x| match &mut x.data {
+                SectionData::FighterData (data) => Some(data),
+                _ => None,
+            }).unwrap();
+            data.action_flags = flags.iter().map(|&(flag1, flag2, flag3, flag4)| ActionFlags { flag1, flag2, flag3, flag4 }).collect();
+            fighter
+        };
+        let old = fighter(&[(0, 0, 0, 0), (0x8000_0001, 0, 0, 0), (0, 0, 0, 0)]);
+        let new = fighter(&[(0, 0, 0, 0), (0x0000_0001, 0, 0x10, 0)]);
+
+        let changes = diff_action_flags(&old, &new);
+        assert_eq!(changes, vec!(ActionFlagsChanges {
+            action: 0x113,
+            changes: vec!(
+                ActionFlagsBitChange { word: 0, bit: 31, new_value: false },
+                ActionFlagsBitChange { word: 2, bit: 4,  new_value: true },
+            ),
+        }));
+        assert!(diff_action_flags(&old, &old).is_empty());
+    }
+}
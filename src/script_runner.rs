@@ -1,6 +1,6 @@
 use crate::high_level_fighter::{CollisionBoxValues, SectionScriptAst};
 use crate::high_level_fighter;
-use crate::sakurai::fighter_data::ArcFighterData;
+use crate::sakurai::fighter_data::{ArcFighterData, ScriptCollection};
 use crate::script::{Requirement, VariableDataType};
 use crate::script_ast::{
     ScriptAst,
@@ -22,8 +22,12 @@ use crate::script_ast::{
     SpecifyThrow,
     ThrowUse,
     FloatValue,
+    ColorFlash,
+    SwordGlow,
 };
 use crate::fighter::WiiRDFrameSpeedModifier;
+use crate::game_frame::GameFrame;
+use crate::movement::MovementEffect;
 use crate::script_ast::variable_ast::{
     VariableAst,
     InternalConstantInt,
@@ -33,17 +37,22 @@ use crate::script_ast::variable_ast::{
 
 use std::collections::HashMap;
 
+/// The most Subroutine calls that can be nested in a single call stack
+const MAX_SUBROUTINE_DEPTH: usize = 16;
+
 pub struct ScriptRunner<'a> {
     pub subaction_name:              String,
     pub wiird_frame_speed_modifiers: &'a [WiiRDFrameSpeedModifier],
     pub call_stacks:                 Vec<CallStack<'a>>,
+    /// The number of call stacks that persist between frames: the action script if any, followed by the main, gfx, sfx and other scripts
+    script_call_stacks:              usize,
     pub fighter_scripts:             &'a [&'a ScriptAst],
     pub common_scripts:              &'a [&'a ScriptAst],
     pub section_scripts:             &'a [SectionScriptAst],
     pub call_every_frame:            HashMap<i32, CallEveryFrame<'a>>,
     pub visited_gotos:               Vec<i32>,
     pub subaction_index:             usize,
-    pub frame_index:                 f32, // affected by frame speed modifiers
+    pub frame_index:                 GameFrame, // affected by frame speed modifiers
     pub animation_index:             f32, // affected by frame speed modifiers, usually in sync with frame_index but not always because some commands affect only animation_index
     pub frame_count:                 usize, // goes up by exactly 1 every frame, only used for external statistics like iasa
     pub interruptible:               bool,
@@ -65,6 +74,8 @@ pub struct ScriptRunner<'a> {
     pub x_vel_modify: VelModify,
     /// Reset to None before processing each frame
     pub y_vel_modify: VelModify,
+    /// Cleared before processing each frame
+    pub movement_effects: Vec<MovementEffect>,
     pub disable_movement:  DisableMovement,
     pub armor_type:        ArmorType,
     pub armor_tolerance:   f32,
@@ -88,6 +99,15 @@ pub struct ScriptRunner<'a> {
     pub throw:                 Option<SpecifyThrow>,
     /// Reset to false before processing each frame.
     pub throw_activate: bool,
+    /// Sound and graphic events run during the current frame.
+    /// Reset to empty before processing each frame.
+    pub effects: Vec<ScriptEffect>,
+    /// The flash color the fighter is tinted with, kept until a RemoveFlashEffect
+    pub color_flash: Option<ColorFlash>,
+    /// The sword glow currently drawn, kept until a DeleteSwordGlow
+    pub sword_glow: Option<SwordGlow>,
+    /// Control flow that could not be followed, the frames are inaccurate from the first caveat onwards.
+    pub caveats: Vec<TimelineCaveat>,
 
     // LongtermAccessInt
     pub jumps_used: i32,
@@ -168,13 +188,15 @@ pub struct ScriptRunner<'a> {
 
 pub struct CallStack<'a> {
     pub calls: Vec<Call<'a>>,
-    pub wait_until: f32,
+    pub wait_until: GameFrame,
+    pub collection: ScriptCollection,
 }
 
 pub struct Call<'a> {
     pub block: &'a Block,
     pub else_branch: Option<&'a Box<Block>>,
     pub index: usize,
+    /// The call was created by a Subroutine event, a Return resumes the call below it.
     pub subroutine: bool,
     pub external: bool,
     /// I have tested the `And` and `Or` events to have no effect outside of any if statement
@@ -264,8 +286,22 @@ impl<'a> ScriptRunner<'a> {
     /// The returned runner has completed the first frame.
     /// Calling `runner.step` will advance to frame 2 and then frame 3 and so on.
     pub fn new(subaction_index: usize, wiird_frame_speed_modifiers: &'a [WiiRDFrameSpeedModifier], subaction_scripts: &[&'a ScriptAst], fighter_scripts: &'a [&'a ScriptAst], common_scripts: &'a [&'a ScriptAst], section_scripts: &'a [SectionScriptAst], init_hack_script: &Block, fighter_data: &ArcFighterData, subaction_name: String) -> ScriptRunner<'a> {
+        ScriptRunner::start(None, subaction_index, wiird_frame_speed_modifiers, subaction_scripts, fighter_scripts, common_scripts, section_scripts, init_hack_script, fighter_data, subaction_name)
+    }
+
+    /// Same as `new` but also runs `action_script`, the entry script of the action the subaction is played in.
+    /// The action script runs before the subaction scripts on each frame, its effects are recorded as part of the main script.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_in_action(action_script: &'a ScriptAst, subaction_index: usize, wiird_frame_speed_modifiers: &'a [WiiRDFrameSpeedModifier], subaction_scripts: &[&'a ScriptAst], fighter_scripts: &'a [&'a ScriptAst], common_scripts: &'a [&'a ScriptAst], section_scripts: &'a [SectionScriptAst], fighter_data: &ArcFighterData, subaction_name: String) -> ScriptRunner<'a> {
+        ScriptRunner::start(Some(action_script), subaction_index, wiird_frame_speed_modifiers, subaction_scripts, fighter_scripts, common_scripts, section_scripts, &Block { events: vec!() }, fighter_data, subaction_name)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn start(action_script: Option<&'a ScriptAst>, subaction_index: usize, wiird_frame_speed_modifiers: &'a [WiiRDFrameSpeedModifier], subaction_scripts: &[&'a ScriptAst], fighter_scripts: &'a [&'a ScriptAst], common_scripts: &'a [&'a ScriptAst], section_scripts: &'a [SectionScriptAst], init_hack_script: &Block, fighter_data: &ArcFighterData, subaction_name: String) -> ScriptRunner<'a> {
         let mut call_stacks = vec!();
-        for script in subaction_scripts {
+        let scripts = action_script.map(|x| (x, ScriptCollection::Main)).into_iter()
+            .chain(subaction_scripts.iter().cloned().zip(ScriptCollection::ALL.iter().cloned()));
+        for (script, collection) in scripts {
             let calls = vec!(Call {
                 block: &script.block,
                 else_branch: None,
@@ -277,9 +313,11 @@ impl<'a> ScriptRunner<'a> {
             });
             call_stacks.push(CallStack {
                 calls,
-                wait_until: -1.0,
+                wait_until: GameFrame::new(-1.0),
+                collection,
             });
         }
+        let script_call_stacks = call_stacks.len();
 
         let ledge_grab_enable = match subaction_name.as_ref() {
             "JumpF" | "JumpB" | "JumpAerialF" | "JumpAerialB" |
@@ -402,6 +440,7 @@ impl<'a> ScriptRunner<'a> {
             subaction_name,
             wiird_frame_speed_modifiers,
             call_stacks,
+            script_call_stacks,
             fighter_scripts,
             common_scripts,
             section_scripts,
@@ -409,7 +448,7 @@ impl<'a> ScriptRunner<'a> {
             ledge_grab_enable,
             call_every_frame:      HashMap::new(),
             visited_gotos:         vec!(),
-            frame_index:           0.0,
+            frame_index:           GameFrame::ZERO,
             animation_index:       0.0,
             frame_count:           0,
             interruptible:         false,
@@ -424,6 +463,7 @@ impl<'a> ScriptRunner<'a> {
             y_vel:                 0.0,
             x_vel_modify:          VelModify::None,
             y_vel_modify:          VelModify::None,
+            movement_effects:      vec!(),
             disable_movement:      DisableMovement::Enable,
             armor_type:            ArmorType::None,
             armor_tolerance:       0.0,
@@ -442,6 +482,10 @@ impl<'a> ScriptRunner<'a> {
             grab_interrupt_damage: None,
             throw:                 None,
             throw_activate:        false,
+            effects:               vec!(),
+            color_flash:           None,
+            sword_glow:            None,
+            caveats:               vec!(),
             invisible_bones,
 
             // LongtermAccessInt
@@ -532,12 +576,21 @@ impl<'a> ScriptRunner<'a> {
         runner
     }
 
+    /// The concurrent loops that will run on the next frame, sorted by thread_id
+    pub fn concurrent_loops(&self) -> Vec<ConcurrentLoop> {
+        let mut loops: Vec<_> = self.call_every_frame.iter()
+            .map(|(thread_id, x)| ConcurrentLoop { thread_id: *thread_id, offset: x.offset, external: x.external, collection: x.collection })
+            .collect();
+        loops.sort_by_key(|x| x.thread_id);
+        loops
+    }
+
     /// Steps the main, gfx, sfx and other scripts by 1 game frame.
     pub fn step(&mut self) {
         let mut fsms = vec!();
         for fsm in self.wiird_frame_speed_modifiers {
             // TODO: Because we currently only operate at the subaction level, this is the best we can do.
-            if !fsm.action && fsm.action_subaction_id as usize == self.subaction_index && (self.frame_index as u16) >= fsm.frame as u16 {
+            if !fsm.action && fsm.action_subaction_id as usize == self.subaction_index && self.frame_index.truncate() as u16 >= fsm.frame as u16 {
                 fsms.push(fsm);
             }
         }
@@ -546,7 +599,7 @@ impl<'a> ScriptRunner<'a> {
         if let Some(fsm) = fsms.last() {
             self.frame_speed_modifier = fsm.frame_speed;
         }
-        self.frame_index += self.frame_speed_modifier;
+        self.frame_index = self.frame_index.advance(self.frame_speed_modifier);
         self.animation_index += self.frame_speed_modifier;
         self.frame_count += 1;
         self.step_script();
@@ -557,10 +610,12 @@ impl<'a> ScriptRunner<'a> {
             *rehit = false;
         }
         self.throw_activate = false;
+        self.effects.clear();
         self.rumble = None; // TODO: I guess rumble_loop shouldnt be reset?
         self.visited_gotos.clear();
         self.x_vel_modify = VelModify::None;
         self.y_vel_modify = VelModify::None;
+        self.movement_effects.clear();
         self.reverse_direction = false;
 
         // The hitbox existed last frame so should be interpolated.
@@ -591,15 +646,18 @@ impl<'a> ScriptRunner<'a> {
             });
             self.call_stacks.push(CallStack {
                 calls,
-                wait_until: -1.0,
+                wait_until: GameFrame::new(-1.0),
+                collection: call_every_frame.collection,
             });
         }
 
-        // run the main, gfx, sfx and other scripts
+        // run the action, main, gfx, sfx and other scripts
         for i in 0..self.call_stacks.len() {
+            // Each call stack is its own thread so may visit the same Goto destination as another call stack in the same frame.
+            self.visited_gotos.clear();
             while !self.call_stacks[i].calls.is_empty() { // reached the end of the script
                 // Handle wait events
-                if self.frame_index < self.call_stacks[i].wait_until {
+                if !self.frame_index.reached(self.call_stacks[i].wait_until) {
                     break;
                 }
 
@@ -610,6 +668,11 @@ impl<'a> ScriptRunner<'a> {
                     let external = self.call_stacks[i].calls.last().unwrap().external;
 
                     if self.call_stacks[i].calls.last().unwrap().execute {
+                        if is_effect(event) {
+                            let collection = self.call_stacks[i].collection;
+                            self.effects.push(ScriptEffect { collection, event: event.clone() });
+                        }
+
                         match self.step_event(event, external, self.fighter_scripts, self.common_scripts, self.section_scripts) {
                             StepEventResult::WaitUntil (value) => {
                                 self.call_stacks[i].wait_until = value;
@@ -630,21 +693,37 @@ impl<'a> ScriptRunner<'a> {
                                     self.call_stacks[i].calls.last_mut().unwrap().execute = true;
                                 }
                             }
-                            StepEventResult::Subroutine { block, external } => {
-                                self.call_stacks[i].calls.push(Call { block, else_branch: None, index: 0, subroutine: true, if_statement: false, execute: true, external });
+                            StepEventResult::Subroutine { block, external, offset } => {
+                                let depth = self.call_stacks[i].calls.iter().filter(|x| x.subroutine).count();
+                                if depth < MAX_SUBROUTINE_DEPTH {
+                                    self.call_stacks[i].calls.push(Call { block, else_branch: None, index: 0, subroutine: true, if_statement: false, execute: true, external });
+                                } else {
+                                    error!("Avoided Subroutine infinite recursion");
+                                    self.add_caveat(TimelineCaveat::SubroutineRecursion { frame: self.frame_count, offset });
+                                }
                             }
-                            StepEventResult::CallEveryFrame { block, thread_id, external } => {
-                                self.call_every_frame.insert(thread_id, CallEveryFrame { block, external });
+                            StepEventResult::CallEveryFrame { block, thread_id, offset, external } => {
+                                let collection = self.call_stacks[i].collection;
+                                self.call_every_frame.insert(thread_id, CallEveryFrame { block, offset, external, collection });
                             }
                             StepEventResult::Return => {
-                                let mut run = false;
-                                while run {
-                                    run = self.call_stacks[i].calls.pop().map(|x| !x.subroutine).unwrap_or(false);
+                                // Leave any loops and if statements of the current routine along with the routine itself.
+                                // When the routine was not called by a Subroutine there is nothing to return to, so the script ends.
+                                while let Some(call) = self.call_stacks[i].calls.pop() {
+                                    if call.subroutine {
+                                        break;
+                                    }
                                 }
                             }
                             StepEventResult::Goto { block, external } => {
-                                self.call_stacks[i].calls.pop();
-                                self.call_stacks[i].calls.push(Call { block, else_branch: None, index: 0, subroutine: false, if_statement: false, execute: true, external });
+                                // A Goto never comes back, so the current routine is replaced.
+                                // The routine keeps the place a Subroutine would Return to.
+                                let calls = &mut self.call_stacks[i].calls;
+                                while calls.len() > 1 && !calls.last().unwrap().subroutine {
+                                    calls.pop();
+                                }
+                                let subroutine = calls.pop().map(|x| x.subroutine).unwrap_or(false);
+                                calls.push(Call { block, else_branch: None, index: 0, subroutine, if_statement: false, execute: true, external });
                             }
                             StepEventResult::None => { }
                         }
@@ -676,7 +755,7 @@ impl<'a> ScriptRunner<'a> {
         }
 
         // CallEveryFrame call stacks only get one frame to complete so remove them now.
-        self.call_stacks.truncate(4); // keep main, gfx, sfx and other call stacks
+        self.call_stacks.truncate(self.script_call_stacks); // keep the action, main, gfx, sfx and other call stacks
 
         if self.frame_speed_modifier == 0.0 {
             self.change_subaction = ChangeSubaction::InfiniteLoop
@@ -697,14 +776,20 @@ impl<'a> ScriptRunner<'a> {
         }
     }
 
+    fn add_caveat(&mut self, caveat: TimelineCaveat) {
+        if !self.caveats.contains(&caveat) {
+            self.caveats.push(caveat);
+        }
+    }
+
     /// Returns the wait_until value
     fn step_event<'b>(&mut self, event: &'b EventAst, external: bool, fighter_scripts: &[&'b ScriptAst], common_scripts: &[&'b ScriptAst], section_scripts: &'b [SectionScriptAst]) -> StepEventResult<'b> {
         match event {
             &EventAst::SyncWait (ref value) => {
-                return StepEventResult::WaitUntil (self.frame_index + *value);
+                return StepEventResult::WaitUntil (self.frame_index.offset(*value));
             }
             &EventAst::AsyncWait (ref value) => {
-                return StepEventResult::WaitUntil (*value);
+                return StepEventResult::WaitUntil (GameFrame::new(*value));
             }
             &EventAst::ForLoop (ref for_loop) => {
                 match for_loop.iterations {
@@ -720,20 +805,22 @@ impl<'a> ScriptRunner<'a> {
             &EventAst::Subroutine (ref offset) => {
                 if !external {
                     if let Some(script) = section_scripts.iter().find(|x| x.callers.contains(&offset.origin)) {
-                        return StepEventResult::Subroutine { block: &script.script.block, external: true };
+                        return StepEventResult::Subroutine { block: &script.script.block, external: true, offset: offset.offset };
                     }
                 }
 
+                // Recursion is limited by MAX_SUBROUTINE_DEPTH instead of tracking visited subroutines like visited_gotos,
+                // as calling the same subroutine multiple times in a frame is common.
                 let all_scripts = if external { common_scripts } else { fighter_scripts };
-                // TODO: Maybe I should implement a protection similar to visited_gotos for subroutines.
-                // If that turns out to be a bad idea document why.
                 for script in all_scripts.iter() {
                     if script.offset == offset.offset {
                         if script.block.events.len() > 0 && &script.block.events[0] as *const _ == event as *const _ {
                             error!("Avoided hard Subroutine infinite loop (attempted to jump to the same location)");
+                            self.add_caveat(TimelineCaveat::SubroutineRecursion { frame: self.frame_count, offset: offset.offset });
+                            return StepEventResult::None;
                         }
                         else {
-                            return StepEventResult::Subroutine { block: &script.block, external };
+                            return StepEventResult::Subroutine { block: &script.block, external, offset: offset.offset };
                         }
                     }
                 }
@@ -759,7 +846,11 @@ impl<'a> ScriptRunner<'a> {
                     }
                     error!("Couldnt find Goto offset");
                 }
-                error!("Avoided Goto infinite loop");
+                else {
+                    // Looping back without waiting for a frame would hang the game.
+                    error!("Avoided Goto infinite loop");
+                    self.add_caveat(TimelineCaveat::GotoLoop { frame: self.frame_count, offset: offset.offset });
+                }
             }
             &EventAst::IfStatement (ref if_statement) => {
                 let then_branch = &if_statement.then_branch;
@@ -781,14 +872,14 @@ impl<'a> ScriptRunner<'a> {
             &EventAst::CallEveryFrame { thread_id, ref offset } => {
                 if !external {
                     if let Some(script) = section_scripts.iter().find(|x| x.callers.contains(&offset.origin)) {
-                        return StepEventResult::CallEveryFrame { thread_id, block: &script.script.block, external: true };
+                        return StepEventResult::CallEveryFrame { thread_id, block: &script.script.block, offset: offset.offset, external: true };
                     }
                 }
 
                 let all_scripts = if external { common_scripts } else { fighter_scripts };
                 for script in all_scripts.iter() {
                     if script.offset == offset.offset {
-                        return StepEventResult::CallEveryFrame { thread_id, block: &script.block, external };
+                        return StepEventResult::CallEveryFrame { thread_id, block: &script.block, offset: offset.offset, external };
                     }
                 }
             }
@@ -807,7 +898,7 @@ impl<'a> ScriptRunner<'a> {
             &EventAst::CreateInterrupt (ref interrupt) => {
                 // If the interrupt would succeed on the first frame then ignore it.
                 // This is a super hacky hack to get moves like DK's dash attack working.
-                if !(self.frame_index == 0.0 && self.evaluate_expression(&interrupt.test).unwrap_bool()) {
+                if !(self.frame_index == GameFrame::ZERO && self.evaluate_expression(&interrupt.test).unwrap_bool()) {
                     self.interrupts.push(interrupt.clone());
                 } else {
                     self.bad_interrupts.push(interrupt.clone());
@@ -841,7 +932,7 @@ impl<'a> ScriptRunner<'a> {
             }
             &EventAst::SetAnimationAndTimerFrame (v0) => {
                 self.animation_index = v0;
-                self.frame_index = v0;
+                self.frame_index = GameFrame::new(v0);
             }
             &EventAst::FrameSpeedModifier { multiplier, .. } => {
                 self.frame_speed_modifier = multiplier;
@@ -851,6 +942,7 @@ impl<'a> ScriptRunner<'a> {
             // misc state
             &EventAst::SetAirGround (v0) => {
                 self.airbourne = v0 == 0; // TODO: Seems like brawlbox is incomplete here e.g 36
+                self.movement_effects.push(MovementEffect::SetAirGround { airbourne: self.airbourne });
             }
             &EventAst::SetEdgeSlide (ref v0) => {
                 self.edge_slide = v0.clone();
@@ -1085,19 +1177,23 @@ impl<'a> ScriptRunner<'a> {
                 if values.x_set {
                     self.x_vel = values.x_vel;
                     self.x_vel_modify = VelModify::Set(values.x_vel);
+                    self.movement_effects.push(MovementEffect::SetXVelocity (values.x_vel));
                 }
                 else {
                     self.x_vel += values.x_vel;
                     self.x_vel_modify = VelModify::Add(self.x_vel_modify.value() + values.x_vel);
+                    self.push_add_velocity(values.x_vel, 0.0);
                 }
 
                 if values.y_set {
                     self.y_vel = values.y_vel;
                     self.y_vel_modify = VelModify::Set(values.y_vel);
+                    self.movement_effects.push(MovementEffect::SetYVelocity (values.y_vel));
                 }
                 else {
                     self.y_vel += values.y_vel;
                     self.y_vel_modify = VelModify::Add(self.y_vel_modify.value() + values.y_vel);
+                    self.push_add_velocity(0.0, values.y_vel);
                 }
             }
             &EventAst::SetVelocity { x_vel, y_vel } => {
@@ -1106,6 +1202,9 @@ impl<'a> ScriptRunner<'a> {
 
                 self.x_vel_modify = VelModify::Set(x_vel);
                 self.y_vel_modify = VelModify::Set(y_vel);
+
+                self.movement_effects.push(MovementEffect::SetXVelocity (x_vel));
+                self.movement_effects.push(MovementEffect::SetYVelocity (y_vel));
             }
             &EventAst::AddVelocity { ref x_vel, ref y_vel } => {
                 let x_vel = self.get_float_value(x_vel);
@@ -1116,9 +1215,12 @@ impl<'a> ScriptRunner<'a> {
 
                 self.x_vel_modify = VelModify::Add(self.x_vel_modify.value() + x_vel);
                 self.y_vel_modify = VelModify::Add(self.y_vel_modify.value() + y_vel);
+
+                self.push_add_velocity(x_vel, y_vel);
             }
             &EventAst::DisableMovement (ref disable_movement) => {
                 self.disable_movement = disable_movement.clone();
+                self.movement_effects.push(MovementEffect::DisableMovement (disable_movement.clone()));
             }
             &EventAst::DisableMovement2 (_) => { } // TODO: What!?!?
             &EventAst::ResetVerticalVelocityAndAcceleration (reset) => {
@@ -1126,9 +1228,12 @@ impl<'a> ScriptRunner<'a> {
                     self.y_vel = 0.0;
 
                     self.y_vel_modify = VelModify::Set(0.0);
+                    self.movement_effects.push(MovementEffect::ResetVerticalVelocity);
                 }
             }
-            &EventAst::NormalizePhysics => { } // TODO
+            &EventAst::NormalizePhysics => { // TODO
+                self.movement_effects.push(MovementEffect::NormalizePhysics);
+            }
 
             // sound
             &EventAst::SoundEffect1 (_) => { }
@@ -1211,8 +1316,12 @@ impl<'a> ScriptRunner<'a> {
             &EventAst::LimitedScreenTint (_) => { }
             &EventAst::UnlimitedScreenTint (_) => { }
             &EventAst::EndUnlimitedScreenTint { .. } => { }
-            &EventAst::SwordGlow (_) => { }
-            &EventAst::DeleteSwordGlow { .. } => { }
+            EventAst::SwordGlow (sword_glow) => {
+                self.sword_glow = Some(sword_glow.clone());
+            }
+            &EventAst::DeleteSwordGlow { .. } => {
+                self.sword_glow = None;
+            }
             &EventAst::AestheticWindEffect (_) => { }
             &EventAst::EndAestheticWindEffect { .. } => { }
             &EventAst::ScreenShake { .. } => { }
@@ -1222,11 +1331,15 @@ impl<'a> ScriptRunner<'a> {
             }
             &EventAst::CameraCloseup (_) => { }
             &EventAst::CameraNormal => { }
-            &EventAst::RemoveFlashEffect => { }
-            &EventAst::FlashEffectOverlay { .. } => { }
-            &EventAst::SetColorOfFlashEffectOverlay { .. } => { }
-            &EventAst::FlashEffectLight { .. } => { }
-            &EventAst::SetColorOfFlashEffectLight { .. } => { }
+            &EventAst::RemoveFlashEffect => {
+                self.color_flash = None;
+            }
+            &EventAst::FlashEffectOverlay { .. } |
+            &EventAst::SetColorOfFlashEffectOverlay { .. } |
+            &EventAst::FlashEffectLight { .. } |
+            &EventAst::SetColorOfFlashEffectLight { .. } => {
+                self.color_flash = event.color_flash();
+            }
 
             // items
             &EventAst::ItemPickup { .. } => { }
@@ -1371,6 +1484,16 @@ impl<'a> ScriptRunner<'a> {
         }
     }
 
+    /// Adding 0 does not change the velocity, so only nonzero values are listed
+    fn push_add_velocity(&mut self, x_vel: f32, y_vel: f32) {
+        if x_vel != 0.0 {
+            self.movement_effects.push(MovementEffect::AddXVelocity (x_vel));
+        }
+        if y_vel != 0.0 {
+            self.movement_effects.push(MovementEffect::AddYVelocity (y_vel));
+        }
+    }
+
     fn get_float_value(&self, value: &FloatValue) -> f32 {
         match value {
             FloatValue::Constant (value) => *value,
@@ -1398,7 +1521,7 @@ impl<'a> ScriptRunner<'a> {
 
     fn get_variable_int_inner(&self, variable: &VariableAst) -> i32 {
         match variable {
-            VariableAst::InternalConstantInt (InternalConstantInt::CurrentFrame) => self.frame_index as i32,
+            VariableAst::InternalConstantInt (InternalConstantInt::CurrentFrame) => self.frame_index.truncate(),
             VariableAst::InternalConstantInt (InternalConstantInt::CharacterDirection) => 1,
             VariableAst::InternalConstantInt (InternalConstantInt::CharacterDirectionOpposite) => -1,
             VariableAst::InternalConstantInt (InternalConstantInt::CurrentFrameSpeed) => self.frame_speed_modifier as i32,
@@ -1618,21 +1741,72 @@ impl<'a> ScriptRunner<'a> {
 }
 
 enum StepEventResult<'a> {
-    WaitUntil      (f32),
+    WaitUntil      (GameFrame),
     NewForLoop     { block: &'a Block, iterations: i32 },
     NewCall        { block: &'a Block },
     NewIfStatement { then_branch: &'a Block, else_branch: Option<&'a Box<Block>>, execute: bool },
     IfStatementDisableExecution,
     Goto           { block: &'a Block, external: bool },
-    Subroutine     { block: &'a Block, external: bool },
-    CallEveryFrame { block: &'a Block, external: bool, thread_id: i32 },
+    Subroutine     { block: &'a Block, external: bool, offset: i32 },
+    CallEveryFrame { block: &'a Block, external: bool, thread_id: i32, offset: i32 },
     Return,
     None
 }
 
 pub struct CallEveryFrame<'a> {
-    pub block:    &'a Block,
-    pub external: bool,
+    pub block:      &'a Block,
+    /// The offset of the script being run
+    pub offset:     i32,
+    pub external:   bool,
+    pub collection: ScriptCollection,
+}
+
+/// A concurrent loop created by a CallEveryFrame event that is running
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct ConcurrentLoop {
+    pub thread_id:  i32,
+    /// The offset of the script being run, compare with `ScriptAst::offset`
+    pub offset:     i32,
+    /// True when the script is in the common Fighter.pac
+    pub external:   bool,
+    /// The script collection that created the loop
+    pub collection: ScriptCollection,
+}
+
+/// Control flow that the game would not survive, the `ScriptRunner` skips the offending event instead of hanging.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum TimelineCaveat {
+    /// A Goto to `offset` jumped back to where the call stack had already been this frame without waiting.
+    /// Loops that wait at least one frame before each Goto are fine.
+    GotoLoop { frame: usize, offset: i32 },
+    /// A Subroutine to `offset` would have exceeded the maximum call depth or called itself.
+    SubroutineRecursion { frame: usize, offset: i32 },
+}
+
+/// A sound or graphic event and the script collection it was run from
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct ScriptEffect {
+    pub collection: ScriptCollection,
+    pub event:      EventAst,
+}
+
+fn is_effect(event: &EventAst) -> bool {
+    matches!(event,
+        EventAst::SoundEffect1 (_) | EventAst::SoundEffect2 (_) | EventAst::SoundEffectTransient (_) |
+        EventAst::SoundEffectStop (_) | EventAst::SoundEffectVictory (_) | EventAst::SoundEffectUnk (_) |
+        EventAst::SoundEffectOther1 (_) | EventAst::SoundEffectOther2 (_) |
+        EventAst::SoundVoiceLow | EventAst::SoundVoiceDamage | EventAst::SoundVoiceOttotto | EventAst::SoundVoiceEating |
+        EventAst::GraphicEffect (_) | EventAst::ExternalGraphicEffect (_) |
+        EventAst::LimitedScreenTint (_) | EventAst::UnlimitedScreenTint (_) | EventAst::EndUnlimitedScreenTint { .. } |
+        EventAst::SwordGlow (_) | EventAst::DeleteSwordGlow { .. } |
+        EventAst::AestheticWindEffect (_) | EventAst::EndAestheticWindEffect { .. } |
+        EventAst::ScreenShake { .. } |
+        EventAst::RemoveFlashEffect | EventAst::FlashEffectOverlay { .. } | EventAst::SetColorOfFlashEffectOverlay { .. } |
+        EventAst::FlashEffectLight { .. } | EventAst::SetColorOfFlashEffectLight { .. }
+    )
 }
 
 #[derive(Debug)]
@@ -1653,6 +1827,7 @@ impl ExprResult {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum VelModify {
     Set (f32),
     Add (f32),
@@ -1668,3 +1843,346 @@ impl VelModify {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::{Argument, FixedPoint, Offset};
+    use crate::test_util::{TestFighterBuilder, ScriptBuilder};
+    use crate::script_ast::{ForLoop, SetOrAddVelocity};
+
+    const ROUTINE_A: i32 = 0x100;
+    const ROUTINE_B: i32 = 0x200;
+
+    fn sound(id: i32) -> EventAst {
+        EventAst::SoundEffect1 (id)
+    }
+
+    fn offset(offset: i32) -> Offset {
+        Offset { offset, origin: 0 }
+    }
+
+    fn script(offset: i32, events: Vec<EventAst>) -> ScriptAst {
+        ScriptAst { block: Block { events }, offset }
+    }
+
+    /// Runs `main` for `frames` frames with `routines` available to Goto and Subroutine events.
+    /// Returns the ids of the sounds played on each frame and the caveats.
+    fn run(main: Vec<EventAst>, routines: Vec<ScriptAst>, frames: usize) -> (Vec<Vec<i32>>, Vec<TimelineCaveat>) {
+        let fighter = TestFighterBuilder::new("Test").subaction("Wait1", |s| s).build();
+        let fighter_data = fighter.get_fighter_data().unwrap();
+
+        let main = script(0, main);
+        let empty = script(0, vec!());
+        let routines: Vec<_> = routines.iter().collect();
+
+        let mut runner = ScriptRunner::new(0, &[], &[&main, &empty, &empty, &empty], &routines, &[], &[], &Block { events: vec!() }, fighter_data, "Wait1".into());
+        let mut sounds = vec!();
+        for _ in 0..frames {
+            sounds.push(runner.effects.iter().filter_map(|x| match x.event {
+                EventAst::SoundEffect1 (id) => Some(id),
+                _ => None,
+            }).collect());
+            runner.step();
+        }
+        (sounds, runner.caveats.clone())
+    }
+
+    #[test]
+    fn wiird_frame_speed_modifier_accumulates() {
+        // structured like Marth's fair under a 0.9x WiiRD frame speed modifier: an Asynchronous Timer followed by the hitboxes.
+        // Dividing 18 by 0.9 gives 20 game frames, but the accumulated timer only reaches 18 after 21.
+        let fighter = TestFighterBuilder::new("Marth").subaction("AttackAirF", |s| s).build();
+        let fighter_data = fighter.get_fighter_data().unwrap();
+        let fsms = [WiiRDFrameSpeedModifier { action: false, action_subaction_id: 0, frame: 0, frame_speed: 0.9 }];
+
+        let main = script(0, vec!(EventAst::AsyncWait (18.0), sound(1)));
+        let empty = script(0, vec!());
+        let mut runner = ScriptRunner::new(0, &fsms, &[&main, &empty, &empty, &empty], &[], &[], &[], &Block { events: vec!() }, fighter_data, "AttackAirF".into());
+        let mut first_sound = None;
+        for frame in 0..30 {
+            if first_sound.is_none() && runner.effects.iter().any(|x| matches!(x.event, EventAst::SoundEffect1 (1))) {
+                first_sound = Some(frame);
+            }
+            runner.step();
+        }
+        assert_eq!(first_sound, Some(21));
+        assert!(runner.frame_index.reached(GameFrame::new(18.0)));
+
+        // a modifier for another subaction does not apply
+        let fsms = [WiiRDFrameSpeedModifier { action: false, action_subaction_id: 1, frame: 0, frame_speed: 0.9 }];
+        let mut runner = ScriptRunner::new(0, &fsms, &[&main, &empty, &empty, &empty], &[], &[], &[], &Block { events: vec!() }, fighter_data, "AttackAirF".into());
+        for _ in 0..18 {
+            runner.step();
+        }
+        assert!(runner.effects.iter().any(|x| matches!(x.event, EventAst::SoundEffect1 (1))));
+    }
+
+    #[test]
+    fn gfx_state_persists_between_frames() {
+        let fighter = TestFighterBuilder::new("Test").subaction("Wait1", |s| s).build();
+        let fighter_data = fighter.get_fighter_data().unwrap();
+        let scalar = |x| Argument::Scalar (FixedPoint::from_frames(x));
+        let mut glow = vec!(Argument::Value (3), Argument::Value (6), Argument::Value (24));
+        glow.extend((0..3).map(|_| scalar(0.0)));
+        glow.push(Argument::Value (25));
+        glow.extend((0..3).map(|_| scalar(0.0)));
+        glow.extend(vec!(Argument::Bool (true), Argument::Value (2), Argument::Value (26)));
+        glow.extend((0..7).map(|_| scalar(1.0)));
+        let gfx = ScriptAst::new(&ScriptBuilder::new()
+            .event(0x11, 0x03, glow)
+            .event(0x21, 0x01, vec!(Argument::Value (255), Argument::Value (0), Argument::Value (0), Argument::Value (128)))
+            .event(0x00, 0x01, vec!(scalar(2.0)))
+            .event(0x21, 0x00, vec!())
+            .event(0x00, 0x01, vec!(scalar(1.0)))
+            .event(0x11, 0x05, vec!(Argument::Value (8)))
+            .build());
+
+        let empty = script(0, vec!());
+        let mut runner = ScriptRunner::new(0, &[], &[&empty, &gfx, &empty, &empty], &[], &[], &[], &Block { events: vec!() }, fighter_data, "Wait1".into());
+        let mut frames = vec!();
+        for _ in 0..4 {
+            frames.push((runner.color_flash.map(|x| x.rgba.red), runner.sword_glow.as_ref().map(|x| x.point1_bone)));
+            runner.step();
+        }
+        assert_eq!(frames, vec!((Some(255), Some(24)), (Some(255), Some(24)), (None, Some(24)), (None, None)));
+    }
+
+    #[test]
+    fn effects_tagged_with_collection() {
+        let fighter = TestFighterBuilder::new("Test").subaction("Wait1", |s| s).build();
+        let fighter_data = fighter.get_fighter_data().unwrap();
+
+        let main = script(0, vec!(sound(1)));
+        let gfx = script(0, vec!(EventAst::SyncWait (1.0), sound(3)));
+        let sfx = script(0, vec!(sound(2)));
+        let empty = script(0, vec!());
+        let mut runner = ScriptRunner::new(0, &[], &[&main, &gfx, &sfx, &empty], &[], &[], &[], &Block { events: vec!() }, fighter_data, "Wait1".into());
+        let mut frames = vec!();
+        for _ in 0..2 {
+            frames.push(runner.effects.iter().filter_map(|x| match x.event {
+                EventAst::SoundEffect1 (id) => Some((x.collection, id)),
+                _ => None,
+            }).collect::<Vec<_>>());
+            runner.step();
+        }
+        assert_eq!(frames, vec!(
+            vec!((ScriptCollection::Main, 1), (ScriptCollection::Sfx, 2)),
+            // an effect only in the gfx script is reported under Gfx, not Main
+            vec!((ScriptCollection::Gfx, 3)),
+        ));
+    }
+
+    #[test]
+    fn subroutine_returns_to_caller() {
+        let (sounds, caveats) = run(
+            vec!(sound(1), EventAst::Subroutine (offset(ROUTINE_A)), sound(5)),
+            vec!(script(ROUTINE_A, vec!(sound(2), EventAst::SyncWait (1.0), sound(3), EventAst::Return, sound(4)))),
+            3
+        );
+        assert_eq!(sounds, vec!(vec!(1, 2), vec!(3, 5), vec!()));
+        assert_eq!(caveats, vec!());
+
+        // Returning from inside a loop leaves the loop along with the subroutine
+        let for_loop = EventAst::ForLoop (ForLoop {
+            iterations: Iterations::Finite (3),
+            block: Block { events: vec!(sound(2), EventAst::Return) },
+        });
+        let (sounds, _) = run(
+            vec!(EventAst::Subroutine (offset(ROUTINE_A)), EventAst::Subroutine (offset(ROUTINE_A)), sound(5)),
+            vec!(script(ROUTINE_A, vec!(for_loop, sound(4)))),
+            1
+        );
+        assert_eq!(sounds, vec!(vec!(2, 2, 5)));
+    }
+
+    #[test]
+    fn frame_speed_accumulates_like_the_game() {
+        // 20 frames at 0.9 speed leave the timer just below 18, so the wait ends a frame later than 18 / 0.9 suggests
+        let (sounds, _) = run(
+            vec!(EventAst::FrameSpeedModifier { multiplier: 0.9, unk: 0 }, EventAst::AsyncWait (18.0), sound(1)),
+            vec!(),
+            23
+        );
+        let frame = sounds.iter().position(|x| !x.is_empty()).map(|x| x + 1);
+        assert_eq!(frame, Some(22));
+        assert_eq!(frame.map(|x| x as f32), analysis_frame(0.9, 18.0));
+    }
+
+    /// The frame normalize_timers gives to an event after an Asynchronous Timer at the frame speed
+    fn analysis_frame(speed: f32, timer: f32) -> Option<f32> {
+        use crate::script::{Argument, FixedPoint};
+        use crate::test_util::ScriptBuilder;
+
+        let script = ScriptBuilder::new()
+            .event(0x04, 0x07, vec!(Argument::Scalar (FixedPoint::from_frames(speed))))
+            .async_timer(timer)
+            .event(0x64, 0x00, vec!())
+            .build();
+        crate::analysis::first_interrupt(&script)
+    }
+
+    #[test]
+    fn goto_does_not_return() {
+        let (sounds, caveats) = run(
+            vec!(sound(1), EventAst::Goto (offset(ROUTINE_A)), sound(9)),
+            vec!(script(ROUTINE_A, vec!(sound(2), EventAst::SyncWait (1.0), sound(3)))),
+            3
+        );
+        assert_eq!(sounds, vec!(vec!(1, 2), vec!(3), vec!()));
+        assert_eq!(caveats, vec!());
+
+        // A routine entered by Goto returns to whatever called the routine containing the Goto
+        let (sounds, _) = run(
+            vec!(EventAst::Subroutine (offset(ROUTINE_B)), sound(5)),
+            vec!(
+                script(ROUTINE_A, vec!(sound(2), EventAst::Return, sound(3))),
+                script(ROUTINE_B, vec!(sound(1), EventAst::Goto (offset(ROUTINE_A)), sound(9))),
+            ),
+            1
+        );
+        assert_eq!(sounds, vec!(vec!(1, 2, 5)));
+    }
+
+    #[test]
+    fn return_on_empty_stack() {
+        let (sounds, caveats) = run(
+            vec!(sound(1), EventAst::Return, sound(2), EventAst::SyncWait (1.0), sound(3)),
+            vec!(),
+            2
+        );
+        assert_eq!(sounds, vec!(vec!(1), vec!()));
+        assert_eq!(caveats, vec!());
+
+        // a routine entered by Goto has nothing to return to
+        let (sounds, _) = run(
+            vec!(EventAst::Goto (offset(ROUTINE_A)), sound(9)),
+            vec!(script(ROUTINE_A, vec!(sound(1), EventAst::Return, sound(2)))),
+            2
+        );
+        assert_eq!(sounds, vec!(vec!(1), vec!()));
+    }
+
+    #[test]
+    fn frame_gated_recursion() {
+        // a loop that waits every iteration runs once per frame
+        let (sounds, caveats) = run(
+            vec!(EventAst::Goto (offset(ROUTINE_A))),
+            vec!(script(ROUTINE_A, vec!(sound(1), EventAst::SyncWait (1.0), EventAst::Goto (offset(ROUTINE_A))))),
+            4
+        );
+        assert_eq!(sounds, vec!(vec!(1), vec!(1), vec!(1), vec!(1)));
+        assert_eq!(caveats, vec!());
+
+        // without the wait the loop would never finish the frame
+        let (sounds, caveats) = run(
+            vec!(EventAst::Goto (offset(ROUTINE_A))),
+            vec!(script(ROUTINE_A, vec!(sound(1), EventAst::Goto (offset(ROUTINE_A))))),
+            2
+        );
+        assert_eq!(sounds, vec!(vec!(1), vec!()));
+        assert_eq!(caveats, vec!(TimelineCaveat::GotoLoop { frame: 0, offset: ROUTINE_A }));
+
+        // recursing subroutines stop at the maximum depth
+        let (sounds, caveats) = run(
+            vec!(EventAst::Subroutine (offset(ROUTINE_A)), sound(2)),
+            vec!(script(ROUTINE_A, vec!(sound(1), EventAst::Subroutine (offset(ROUTINE_A))))),
+            1
+        );
+        let mut expected = vec!(1; MAX_SUBROUTINE_DEPTH);
+        expected.push(2);
+        assert_eq!(sounds, vec!(expected));
+        assert_eq!(caveats, vec!(TimelineCaveat::SubroutineRecursion { frame: 0, offset: ROUTINE_A }));
+    }
+
+    #[test]
+    fn concurrent_loop_timeline() {
+        let fighter = |fragment_offset| TestFighterBuilder::new("Test")
+            .subaction("AttackS3S", |s| s
+                .async_timer(3.0)
+                .event(0x0D, 0x00, vec!(Argument::Value (1), Argument::Offset (Offset { offset: fragment_offset, origin: 0 })))
+                .async_timer(10.0)
+                .event(0x00, 0x02, vec!())
+            )
+            .build();
+        let fragment_offset = fighter(0).get_fighter_data().unwrap().subaction_main[0].offset + 3 * 8;
+        let fighter = fighter(fragment_offset);
+        let fighter_data = fighter.get_fighter_data().unwrap();
+        let sakurai = fighter.get_fighter_sakurai().unwrap();
+
+        let main = ScriptAst::new(&fighter_data.subaction_main[0]);
+        let gfx = ScriptAst::new(&fighter_data.subaction_gfx[0]);
+        let sfx = ScriptAst::new(&fighter_data.subaction_sfx[0]);
+        let other = ScriptAst::new(&fighter_data.subaction_other[0]);
+        let fragments: Vec<_> = sakurai.fragment_scripts.iter().map(ScriptAst::new).collect();
+        let fragments: Vec<_> = fragments.iter().collect();
+
+        let mut runner = ScriptRunner::new(0, &[], &[&main, &gfx, &sfx, &other], &fragments, &[], &[], &Block { events: vec!() }, fighter_data, "AttackS3S".into());
+        for frame in 0..8 {
+            let loops = runner.concurrent_loops();
+            if frame < 3 {
+                assert!(loops.is_empty(), "frame {}", frame);
+            } else {
+                assert_eq!(loops.len(), 1, "frame {}", frame);
+                assert_eq!(loops[0].offset, fragment_offset);
+                assert!(!loops[0].external);
+            }
+            runner.step();
+        }
+    }
+
+    #[test]
+    fn movement_events() {
+        let scalar = |value| Argument::Scalar (FixedPoint::from_frames(value));
+        let fighter = TestFighterBuilder::new("Test")
+            .subaction("SpecialS", |s| s
+                .event(0x0E, 0x00, vec!(Argument::Value (0)))
+                .async_timer(3.0)
+                .event(0x0E, 0x08, vec!(scalar(2.5), scalar(0.0), Argument::Value (1), Argument::Value (0)))
+                .event(0x0E, 0x06, vec!(Argument::Value (1)))
+                .event(0x0E, 0x07, vec!(Argument::Value (1)))
+                .sync_timer(2.0)
+                .event(0x0E, 0x08, vec!(scalar(-0.5), scalar(1.5)))
+                .event(0x0E, 0x01, vec!(scalar(0.25), scalar(0.0)))
+                .event(0x0E, 0x02, vec!(Argument::Value (1)))
+                .event(0x17, 0x00, vec!())
+            )
+            .build();
+        let fighter_data = fighter.get_fighter_data().unwrap();
+
+        let main = ScriptAst::new(&fighter_data.subaction_main[0]);
+        let decoded: Vec<_> = main.block.events.iter().filter(|x| !matches!(x, EventAst::AsyncWait (_) | EventAst::SyncWait (_))).collect();
+        assert!(matches!(decoded[0], EventAst::SetAirGround (0)));
+        assert!(matches!(decoded[1], EventAst::SetOrAddVelocity (SetOrAddVelocity { x_vel, y_vel, x_set: true, y_set: false }) if *x_vel == 2.5 && *y_vel == 0.0));
+        assert!(matches!(decoded[2], EventAst::DisableMovement (DisableMovement::DisableVertical)));
+        assert!(matches!(decoded[3], EventAst::DisableMovement2 (DisableMovement::DisableVertical)));
+        assert!(matches!(decoded[4], EventAst::SetVelocity { x_vel, y_vel } if *x_vel == -0.5 && *y_vel == 1.5));
+        assert!(matches!(decoded[5], EventAst::AddVelocity { x_vel: FloatValue::Constant (x_vel), y_vel: FloatValue::Constant (y_vel) } if *x_vel == 0.25 && *y_vel == 0.0));
+        assert!(matches!(decoded[6], EventAst::ResetVerticalVelocityAndAcceleration (true)));
+        assert!(matches!(decoded[7], EventAst::NormalizePhysics));
+
+        let empty = ScriptAst { block: Block { events: vec!() }, offset: 0 };
+        let mut runner = ScriptRunner::new(0, &[], &[&main, &empty, &empty, &empty], &[], &[], &[], &Block { events: vec!() }, fighter_data, "SpecialS".into());
+        let mut frames = vec!();
+        for _ in 0..7 {
+            frames.push(runner.movement_effects.clone());
+            runner.step();
+        }
+        assert_eq!(frames, vec!(
+            vec!(MovementEffect::SetAirGround { airbourne: true }),
+            vec!(),
+            vec!(),
+            // the async timer waits until frame 3
+            vec!(MovementEffect::SetXVelocity (2.5), MovementEffect::DisableMovement (DisableMovement::DisableVertical)),
+            vec!(),
+            vec!(
+                MovementEffect::SetXVelocity (-0.5),
+                MovementEffect::SetYVelocity (1.5),
+                MovementEffect::AddXVelocity (0.25),
+                MovementEffect::ResetVerticalVelocity,
+                MovementEffect::NormalizePhysics,
+            ),
+            vec!(),
+        ));
+    }
+}
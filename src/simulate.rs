@@ -0,0 +1,379 @@
+//! Simulates a short sequence of subactions e.g. "short hop, nair on frame 3, land on frame 14" as a single timeline.
+//!
+//! There is no physics, the caller decides on which frame each transition occurs.
+//! The frames of each subaction are taken from `HighLevelFighter`, so WiiRD frame speed modifiers are already applied.
+//! When the subaction changes, the new subaction starts from its first frame and nothing carries over,
+//! so hitboxes of the previous subaction are terminated.
+
+use std::fmt;
+
+use crate::aerial_data::Aerial;
+use crate::fighter::Fighter;
+use crate::game_frame::GameFrame;
+use crate::high_level_fighter::{HighLevelFighter, HighLevelFrame, HighLevelSubaction};
+
+/// A transition in a sequence, frames are indexes into `Timeline::frames`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum SimStep {
+    /// Starts the subaction named `name` on `start_frame`, regardless of whether the current subaction could be interrupted.
+    /// The first step must be a `Subaction`.
+    Subaction { name: String, start_frame: usize },
+    /// The fighter touches the ground on `frame`.
+    /// During an aerial the aerials landing subaction is used, lasting for the landing lag of the aerial,
+    /// unless the aerial is in an autocancel window in which case `LandingLight` is used like any other landing.
+    Land { frame: usize },
+    /// Interrupts the current subaction into `into` on `frame`.
+    /// Unlike `SimStep::Subaction` this fails when the current subaction is not interruptible on that frame.
+    Interrupt { frame: usize, into: String },
+}
+
+impl SimStep {
+    fn frame(&self) -> usize {
+        match self {
+            SimStep::Subaction { start_frame, .. } => *start_frame,
+            SimStep::Land { frame } => *frame,
+            SimStep::Interrupt { frame, .. } => *frame,
+        }
+    }
+}
+
+/// The frames of every subaction in the sequence, one after the other.
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct Timeline {
+    pub frames: Vec<TimelineFrame>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct TimelineFrame {
+    /// The name of the subaction the frame is from
+    pub subaction: String,
+    /// Index into the frames of the subaction, starting at 0 when the subaction begins
+    pub subaction_frame: usize,
+    pub frame: HighLevelFrame,
+}
+
+/// Why a sequence could not be simulated
+#[derive(Clone, Debug, PartialEq)]
+pub enum SimError {
+    /// The first step is not a `SimStep::Subaction` or there are no steps
+    NoStartingSubaction,
+    /// The step at `index` occurs on an earlier frame than the step before it
+    OutOfOrder { index: usize },
+    /// The fighter has no subaction with this name
+    UnknownSubaction (String),
+    /// The current subaction finished on `frame` before the next step occurred.
+    /// The game would transition to another subaction here e.g. `Fall` after an aerial, add a step for it.
+    SubactionEnded { subaction: String, frame: usize },
+    /// The current subaction is not interruptible on the frame of the `SimStep::Interrupt`
+    NotInterruptible { subaction: String, frame: usize },
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SimError::NoStartingSubaction => write!(f, "The first step must be a SimStep::Subaction"),
+            SimError::OutOfOrder { index } => write!(f, "Step {} occurs before the previous step", index),
+            SimError::UnknownSubaction (name) => write!(f, "There is no subaction named {}", name),
+            SimError::SubactionEnded { subaction, frame } => write!(f, "{} finished on frame {} before the next step occurred", subaction, frame),
+            SimError::NotInterruptible { subaction, frame } => write!(f, "{} is not interruptible on frame {}", subaction, frame),
+        }
+    }
+}
+
+impl std::error::Error for SimError { }
+
+/// Simulates the steps on the fighter, refer to the module documentation.
+/// This generates a HighLevelFighter, use `sequence_high_level` instead if you already have one.
+pub fn sequence(fighter: &Fighter, steps: &[SimStep]) -> Result<Timeline, SimError> {
+    sequence_high_level(&HighLevelFighter::new(fighter), steps)
+}
+
+/// Simulates the steps on the fighter, refer to the module documentation.
+/// The timeline ends when the subaction started by the last step finishes.
+pub fn sequence_high_level(fighter: &HighLevelFighter, steps: &[SimStep]) -> Result<Timeline, SimError> {
+    let first = match steps.first() {
+        Some(SimStep::Subaction { name, .. }) => name,
+        _ => return Err(SimError::NoStartingSubaction),
+    };
+    for (index, pair) in steps.windows(2).enumerate() {
+        if pair[1].frame() < pair[0].frame() {
+            return Err(SimError::OutOfOrder { index: index + 1 });
+        }
+    }
+
+    let mut timeline = Timeline { frames: vec!() };
+    let mut current = Playing::new(fighter, first)?;
+    for step in &steps[1..] {
+        // play the current subaction up until the step
+        let start = timeline.frames.len();
+        while timeline.frames.len() < step.frame() {
+            match current.next_frame() {
+                Some(frame) => timeline.frames.push(frame),
+                None => return Err(SimError::SubactionEnded { subaction: current.name, frame: timeline.frames.len() }),
+            }
+        }
+
+        current = match step {
+            SimStep::Subaction { name, .. } => Playing::new(fighter, name)?,
+            SimStep::Interrupt { into, frame } => {
+                let interruptible = timeline.frames[start..].last().map(|x| x.frame.interruptible).unwrap_or(false);
+                if !interruptible {
+                    return Err(SimError::NotInterruptible { subaction: current.name, frame: *frame });
+                }
+                Playing::new(fighter, into)?
+            }
+            SimStep::Land { .. } => current.land(fighter)?,
+        };
+    }
+
+    while let Some(frame) = current.next_frame() {
+        timeline.frames.push(frame);
+    }
+    Ok(timeline)
+}
+
+/// The subaction that is currently playing
+struct Playing<'a> {
+    name:      String,
+    subaction: &'a HighLevelSubaction,
+    /// The index into `subaction.frames` of each frame to play, landing subactions are stretched to the landing lag
+    frames:    Vec<usize>,
+    /// Index into `frames` of the next frame to play
+    next:      usize,
+}
+
+impl<'a> Playing<'a> {
+    fn new(fighter: &'a HighLevelFighter, name: &str) -> Result<Playing<'a>, SimError> {
+        let subaction = find_subaction(fighter, name)?;
+        Ok(Playing {
+            name:   name.to_string(),
+            frames: (0..subaction.frames.len()).collect(),
+            next:   0,
+            subaction,
+        })
+    }
+
+    fn next_frame(&mut self) -> Option<TimelineFrame> {
+        let subaction_frame = *self.frames.get(self.next)?;
+        self.next += 1;
+        Some(TimelineFrame {
+            subaction:       self.name.clone(),
+            subaction_frame: self.next - 1,
+            frame:           self.subaction.frames[subaction_frame].clone(),
+        })
+    }
+
+    /// Returns the landing subaction to play when landing on the next frame
+    fn land(self, fighter: &'a HighLevelFighter) -> Result<Playing<'a>, SimError> {
+        let aerial = Aerial::ALL.iter().find(|x| x.subaction_name() == self.name);
+        let aerial_data = aerial.and_then(|aerial| fighter.aerial_data(false).into_iter().find(|x| x.aerial == *aerial));
+        match (aerial, aerial_data) {
+            (Some(aerial), Some(aerial_data)) => {
+                // frames in autocancel windows start at 1
+                let landing_frame = self.next as f32;
+                if aerial_data.autocancel_windows.iter().any(|x| x.contains(&landing_frame)) {
+                    return Playing::new(fighter, "LandingLight");
+                }
+
+                let mut landing = Playing::new(fighter, aerial.landing_subaction_name())?;
+                let length = landing.subaction.frames.len();
+                // the landing subaction plays until its timer reaches the landing lag, the same as `HighLevelFighter` plays the subaction
+                let lag = GameFrame::ZERO.frames_until(GameFrame::new(aerial_data.landing_lag), 1.0).unwrap_or(0) as usize;
+                landing.frames = (0..lag).map(|i| i * length / lag).collect();
+                Ok(landing)
+            }
+            _ => Playing::new(fighter, "LandingLight"),
+        }
+    }
+}
+
+fn find_subaction<'a>(fighter: &'a HighLevelFighter, name: &str) -> Result<&'a HighLevelSubaction, SimError> {
+    fighter.subactions.iter().find(|x| x.name == name).ok_or_else(|| SimError::UnknownSubaction (name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::high_level_fighter::{HighLevelHitBox, HighLevelScripts, CollisionBoxValues, GrabBoxValues, ECB};
+    use crate::sakurai::fighter_data::AnimationFlags;
+    use crate::script_ast::{Block, EdgeSlide, GrabTarget, ScriptAst};
+    use crate::script_runner::VelModify;
+    use crate::subaction_environment::Environment;
+    use crate::test_util::TestFighterBuilder;
+    use cgmath::Point3;
+
+    fn hit_box() -> HighLevelHitBox {
+        HighLevelHitBox {
+            hitbox_id:   0,
+            bone_name:   None,
+            interpolate: false,
+            prev_pos:    None,
+            prev_size:   None,
+            prev_values: None,
+            next_pos:    Point3::new(0.0, 5.0, 5.0),
+            next_size:   3.0,
+            next_values: CollisionBoxValues::Grab (GrabBoxValues {
+                hitbox_id:  0,
+                size:       3.0,
+                set_action: 0,
+                target:     GrabTarget::AerialAndGrounded,
+                unk:        None,
+            }),
+        }
+    }
+
+    fn frame(hit_box: bool, landing_lag: bool, interruptible: bool) -> HighLevelFrame {
+        HighLevelFrame {
+            timer:                 GameFrame::ZERO,
+            hurt_boxes:            vec!(),
+            hit_boxes:             if hit_box { vec!(self::hit_box()) } else { vec!() },
+            ledge_grab_box:        None,
+            throw_n:               None,
+            x_pos:                 0.0,
+            y_pos:                 0.0,
+            interruptible,
+            edge_slide:            EdgeSlide::SlideOff,
+            reverse_direction:     false,
+            airbourne:             false,
+            landing_lag,
+            ecb:                   ECB { left: -3.0, right: 3.0, top: 14.0, bottom: 0.0, transn_x: 0.0, transn_y: 0.0 },
+            hitbox_sets_rehit:     [false; 10],
+            slope_contour_stand:   None,
+            slope_contour_full:    None,
+            rumble:                None,
+            rumble_loop:           None,
+            grab_interrupt_damage: None,
+            throw:                 None,
+            effects:               vec!(),
+            color_flash:           None,
+            sword_glow:            None,
+            concurrent_loops:      vec!(),
+            x_vel_modify:          VelModify::None,
+            y_vel_modify:          VelModify::None,
+            movement_effects:      vec!(),
+            x_vel_temp:            0.0,
+            y_vel_temp:            0.0,
+        }
+    }
+
+    fn subaction(name: &str, landing_lag: Option<f32>, frames: Vec<HighLevelFrame>) -> HighLevelSubaction {
+        let script = || ScriptAst { block: Block { events: vec!() }, offset: 0 };
+        HighLevelSubaction {
+            name:            name.into(),
+            iasa:            None,
+            frames,
+            landing_lag,
+            animation_flags: AnimationFlags::NONE,
+            scripts:         HighLevelScripts { script_main: script(), script_gfx: script(), script_sfx: script(), script_other: script() },
+            bad_interrupts:  false,
+            caveats:         vec!(),
+            environment:     Environment::Unknown,
+        }
+    }
+
+    /// A nair that is active on frames 5 and 6 and has landing lag from frame 3 until frame 15
+    fn fighter() -> HighLevelFighter {
+        let mut fighter = HighLevelFighter::new(&TestFighterBuilder::new("Test").build());
+        let nair = (1..=20).map(|i| frame(i == 5 || i == 6, (3..16).contains(&i), i >= 18)).collect();
+        fighter.subactions = vec!(
+            subaction("JumpSquat", None, (0..3).map(|_| frame(false, false, true)).collect()),
+            subaction("JumpF", None, (0..30).map(|_| frame(false, false, true)).collect()),
+            subaction("AttackAirN", Some(6.0), nair),
+            // the landing animation is longer than the landing lag, so it is sped up
+            subaction("LandingAirN", None, (0..12).map(|_| frame(false, false, false)).collect()),
+            subaction("LandingLight", None, (0..2).map(|_| frame(false, false, false)).collect()),
+        );
+        fighter
+    }
+
+    fn subactions(timeline: &Timeline) -> Vec<(&str, usize)> {
+        let mut subactions: Vec<(&str, usize)> = vec!();
+        for frame in &timeline.frames {
+            match subactions.last_mut() {
+                Some((name, count)) if *name == frame.subaction => *count += 1,
+                _ => subactions.push((&frame.subaction, 1)),
+            }
+        }
+        subactions
+    }
+
+    #[test]
+    fn aerial_interrupted_by_landing() {
+        let fighter = fighter();
+        let timeline = sequence_high_level(&fighter, &[
+            SimStep::Subaction { name: "JumpSquat".into(), start_frame: 0 },
+            SimStep::Interrupt { frame: 3, into: "JumpF".into() },
+            SimStep::Subaction { name: "AttackAirN".into(), start_frame: 5 },
+            SimStep::Land { frame: 10 },
+        ]).unwrap();
+
+        // the nair lasts 5 frames and is followed by 6 frames of landing lag
+        assert_eq!(subactions(&timeline), vec!(("JumpSquat", 3), ("JumpF", 2), ("AttackAirN", 5), ("LandingAirN", 6)));
+        assert_eq!(timeline.frames.len(), 16);
+        // hitboxes on the 5th and 6th frames of the nair end on landing
+        let active: Vec<usize> = timeline.frames.iter().enumerate().filter(|(_, x)| !x.frame.hit_boxes.is_empty()).map(|(i, _)| i).collect();
+        assert_eq!(active, vec!(9));
+        assert_eq!(timeline.frames[9].subaction_frame, 4);
+        assert_eq!(timeline.frames[15].subaction_frame, 5);
+    }
+
+    #[test]
+    fn autocancelled_landing() {
+        let fighter = fighter();
+        let timeline = sequence_high_level(&fighter, &[
+            SimStep::Subaction { name: "AttackAirN".into(), start_frame: 0 },
+            SimStep::Land { frame: 16 },
+        ]).unwrap();
+        assert_eq!(subactions(&timeline), vec!(("AttackAirN", 16), ("LandingLight", 2)));
+
+        // landing with anything other than an aerial is a normal landing
+        let timeline = sequence_high_level(&fighter, &[
+            SimStep::Subaction { name: "JumpF".into(), start_frame: 0 },
+            SimStep::Land { frame: 4 },
+        ]).unwrap();
+        assert_eq!(subactions(&timeline), vec!(("JumpF", 4), ("LandingLight", 2)));
+    }
+
+    #[test]
+    fn grounded_aerial_has_no_autocancel() {
+        let mut fighter = fighter();
+        assert_eq!(fighter.aerial_data(false)[0].autocancel_windows, vec!(1.0..3.0, 16.0..21.0));
+
+        fighter.subactions.iter_mut().find(|x| x.name == "AttackAirN").unwrap().environment = Environment::Grounded;
+        let aerial_data = fighter.aerial_data(false);
+        assert_eq!(aerial_data[0].environment, Environment::Grounded);
+        assert!(aerial_data[0].autocancel_windows.is_empty());
+    }
+
+    #[test]
+    fn invalid_sequences() {
+        let fighter = fighter();
+        assert_eq!(sequence_high_level(&fighter, &[]).unwrap_err(), SimError::NoStartingSubaction);
+        assert_eq!(sequence_high_level(&fighter, &[SimStep::Land { frame: 0 }]).unwrap_err(), SimError::NoStartingSubaction);
+        assert_eq!(sequence_high_level(&fighter, &[
+            SimStep::Subaction { name: "AttackAirN".into(), start_frame: 0 },
+            SimStep::Land { frame: 8 },
+            SimStep::Subaction { name: "JumpF".into(), start_frame: 7 },
+        ]).unwrap_err(), SimError::OutOfOrder { index: 2 });
+        assert_eq!(sequence_high_level(&fighter, &[
+            SimStep::Subaction { name: "AttackAirHi".into(), start_frame: 0 },
+        ]).unwrap_err(), SimError::UnknownSubaction ("AttackAirHi".into()));
+        assert_eq!(sequence_high_level(&fighter, &[
+            SimStep::Subaction { name: "AttackAirN".into(), start_frame: 0 },
+            SimStep::Land { frame: 25 },
+        ]).unwrap_err(), SimError::SubactionEnded { subaction: "AttackAirN".into(), frame: 20 });
+
+        // the nair is interruptible from frame 18
+        assert_eq!(sequence_high_level(&fighter, &[
+            SimStep::Subaction { name: "AttackAirN".into(), start_frame: 0 },
+            SimStep::Interrupt { frame: 17, into: "JumpF".into() },
+        ]).unwrap_err(), SimError::NotInterruptible { subaction: "AttackAirN".into(), frame: 17 });
+        assert!(sequence_high_level(&fighter, &[
+            SimStep::Subaction { name: "AttackAirN".into(), start_frame: 0 },
+            SimStep::Interrupt { frame: 18, into: "JumpF".into() },
+        ]).is_ok());
+    }
+}
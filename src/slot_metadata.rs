@@ -0,0 +1,298 @@
+//! Parses the metadata of each fighter slot: the victory theme, the announcer call and the series icon.
+//!
+//! Vanilla brawl keeps these in tables in the main executable, which is not part of a brawl dump.
+//! So for vanilla fighters only the series icon is known, it comes from a table built into this crate.
+//!
+//! Mods using BrawlEx store the metadata in config files in `pf/BrawlEx`, the files are named with the id they configure in uppercase hex e.g. `Slot2A.dat`.
+//! The configs of a fighter are all named with its fighter id.
+//! Vanilla fighters are matched by their known fighter id, other fighters are matched by the name in their FighterConfig.
+//!
+//! # Format
+//!
+//! All values are big endian, fields not listed are not used by this crate.
+//!
+//! `SlotConfig/SlotXX.dat`
+//! *   0x00 4 bytes - "SLTC"
+//! *   0x0C 4 bytes - song id of the victory theme
+//! *   0x10 4 bytes - sound effect id of the announcer call
+//!
+//! `CosmeticConfig/CosmeticXX.dat`
+//! *   0x00 4 bytes - "COSC"
+//! *   0x0A 1 byte  - series icon
+//!
+//! `FighterConfig/FighterXX.dat`
+//! *   0x00 4 bytes  - "FCFG"
+//! *   0x60 16 bytes - null terminated fighter name, as used in the fighter file names e.g. "Knuckles" for FitKnuckles.pac
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use failure::Error;
+use failure::bail;
+
+use fancy_slice::FancySlice;
+
+use crate::fighter_maps;
+use crate::file_provider::FileProvider;
+use crate::provenance::PathFormatter;
+
+/// The victory theme, announcer call and series icon of a fighter.
+/// Values that are unknown are None.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct SlotMetadata {
+    /// Song id of the theme played on the results screen
+    pub victory_theme: Option<u32>,
+    /// Sound effect id of the announcer calling the fighters name
+    pub announcer_call: Option<u32>,
+    /// Index of the series icon shown on the character select screen, in the order used by BrawlEx
+    pub series_icon: Option<u8>,
+    /// The BrawlEx config files the values were read from, empty when the values are built in
+    pub config_files: Vec<PathBuf>,
+}
+
+impl SlotMetadata {
+    /// The values known for a fighter of vanilla brawl, None if the fighter is not part of vanilla brawl
+    pub(crate) fn vanilla(cased_name: &str) -> Option<SlotMetadata> {
+        series_icon(cased_name).map(|series_icon| SlotMetadata {
+            victory_theme: None,
+            announcer_call: None,
+            series_icon: Some(series_icon),
+            config_files: vec!(),
+        })
+    }
+}
+
+struct SlotConfig {
+    victory_theme: u32,
+    announcer_call: u32,
+}
+
+/// The BrawlEx configs of a mod, keyed by the id in their file name
+#[derive(Default)]
+pub(crate) struct BrawlExConfigs {
+    slots: HashMap<u8, (PathBuf, SlotConfig)>,
+    cosmetics: HashMap<u8, (PathBuf, u8)>,
+    names: HashMap<u8, String>,
+}
+
+impl BrawlExConfigs {
+    /// Reads the configs in the BrawlEx folder of the mods `files`, configs that fail to parse are reported via `error!()` and skipped.
+    /// The paths of the configs are recorded and logged as formatted by `paths`.
+    pub(crate) fn load(files: &dyn FileProvider, paths: &PathFormatter) -> BrawlExConfigs {
+        BrawlExConfigs::parse(|dir, prefix| config_files(files, dir, prefix, paths))
+    }
+
+    /// `files` returns the config files in the folder of BrawlEx with the prefix, see `config_files`.
+    /// The returned paths are already formatted by the `PathFormatter` of the load, so they are logged as is.
+    fn parse<F>(files: F) -> BrawlExConfigs where F: Fn(&str, &str) -> Vec<(u8, PathBuf, Vec<u8>)> {
+        let mut configs = BrawlExConfigs::default();
+        for (id, path, data) in files("SlotConfig", "Slot") {
+            match slot_config(FancySlice::new(&data)) {
+                Ok(slot) => { configs.slots.insert(id, (path, slot)); }
+                Err(err) => error!("Failed to parse {}: {}", path.display(), err),
+            }
+        }
+        for (id, path, data) in files("CosmeticConfig", "Cosmetic") {
+            match cosmetic_config(FancySlice::new(&data)) {
+                Ok(series_icon) => { configs.cosmetics.insert(id, (path, series_icon)); }
+                Err(err) => error!("Failed to parse {}: {}", path.display(), err),
+            }
+        }
+        for (id, path, data) in files("FighterConfig", "Fighter") {
+            match fighter_config_name(FancySlice::new(&data)) {
+                Ok(name) => { configs.names.insert(id, name); }
+                Err(err) => error!("Failed to parse {}: {}", path.display(), err),
+            }
+        }
+        configs
+    }
+
+    /// Returns the metadata of the fighter, falling back to the vanilla values when the mod has no configs for it.
+    /// A series icon missing from the configs also falls back to the vanilla value.
+    pub(crate) fn slot_metadata(&self, cased_name: &str) -> Option<SlotMetadata> {
+        let id = self.names.iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(cased_name))
+            .map(|(id, _)| *id)
+            .or_else(|| Some(fighter_maps::fighter_id(cased_name)).filter(|x| *x != 0xFF));
+
+        let slot = id.and_then(|x| self.slots.get(&x));
+        let cosmetic = id.and_then(|x| self.cosmetics.get(&x));
+        if slot.is_none() && cosmetic.is_none() {
+            return SlotMetadata::vanilla(cased_name);
+        }
+
+        let mut config_files = vec!();
+        config_files.extend(slot.map(|x| x.0.clone()));
+        config_files.extend(cosmetic.map(|x| x.0.clone()));
+        Some(SlotMetadata {
+            victory_theme: slot.map(|x| x.1.victory_theme),
+            announcer_call: slot.map(|x| x.1.announcer_call),
+            series_icon: cosmetic.map(|x| x.1).or_else(|| series_icon(cased_name)),
+            config_files,
+        })
+    }
+}
+
+/// Returns the id, path formatted by `paths` and contents of every `{prefix}XX.dat` file in `BrawlEx/{dir}`, the directory not existing is not an error.
+fn config_files(files: &dyn FileProvider, dir: &str, prefix: &str, paths: &PathFormatter) -> Vec<(u8, PathBuf, Vec<u8>)> {
+    let dir = format!("BrawlEx/{}", dir);
+    let mut configs = vec!();
+    for relative in files.files_in(&dir).unwrap_or_default() {
+        if let Some(id) = config_id(&relative, prefix) {
+            let path = format!("{}/{}", dir, relative);
+            match files.read(&path) {
+                Ok(data) => configs.push((id, paths.path(&files.source_path(&path)), data)),
+                Err(err) => error!("Cannot read {}: {}", paths.display(&files.source_path(&path)), err),
+            }
+        }
+    }
+    configs
+}
+
+/// Returns the id of a `{prefix}XX.dat` file name
+fn config_id(file_name: &str, prefix: &str) -> Option<u8> {
+    file_name.to_lowercase().strip_prefix(&prefix.to_lowercase())
+        .and_then(|x| x.strip_suffix(".dat"))
+        .filter(|x| x.len() == 2)
+        .and_then(|x| u8::from_str_radix(x, 16).ok())
+}
+
+fn check_tag(data: &FancySlice, tag: &str, size: usize) -> Result<(), Error> {
+    if data.len() < size {
+        bail!("Not a {} file: File size is less than 0x{:x} bytes", tag, size);
+    }
+    if data.relative_slice(0..4) != tag.as_bytes() {
+        bail!("Not a {} file: Incorrect tag", tag);
+    }
+    Ok(())
+}
+
+fn slot_config(data: FancySlice) -> Result<SlotConfig, Error> {
+    check_tag(&data, "SLTC", 0x14)?;
+    Ok(SlotConfig {
+        victory_theme: data.u32_be(0x0C),
+        announcer_call: data.u32_be(0x10),
+    })
+}
+
+/// Returns the series icon
+fn cosmetic_config(data: FancySlice) -> Result<u8, Error> {
+    check_tag(&data, "COSC", 0x0B)?;
+    Ok(data.u8(0x0A))
+}
+
+/// Returns the fighter name
+fn fighter_config_name(data: FancySlice) -> Result<String, Error> {
+    check_tag(&data, "FCFG", FIGHTER_CONFIG_NAME_OFFSET + FIGHTER_CONFIG_NAME_SIZE)?;
+    let name = data.relative_slice(FIGHTER_CONFIG_NAME_OFFSET .. FIGHTER_CONFIG_NAME_OFFSET + FIGHTER_CONFIG_NAME_SIZE);
+    let name: Vec<u8> = name.iter().cloned().take_while(|x| *x != 0).collect();
+    match String::from_utf8(name) {
+        Ok(name) if !name.is_empty() => Ok(name),
+        _ => bail!("Invalid fighter name"),
+    }
+}
+
+const FIGHTER_CONFIG_NAME_OFFSET: usize = 0x60;
+const FIGHTER_CONFIG_NAME_SIZE: usize = 0x10;
+
+/// The series icon of each vanilla fighter, None for fighters without one
+fn series_icon(cased_name: &str) -> Option<u8> {
+    match cased_name {
+        "Mario" | "Luigi" | "Peach" | "Koopa" | "GKoopa"                 => Some(0),
+        "Donkey" | "Diddy"                                               => Some(1),
+        "Link" | "Zelda" | "Sheik" | "Ganon" | "ToonLink"                => Some(2),
+        "Samus" | "SZerosuit"                                            => Some(3),
+        "Yoshi"                                                          => Some(4),
+        "Kirby" | "Metaknight" | "Dedede"                                => Some(5),
+        "Fox" | "Falco" | "Wolf"                                         => Some(6),
+        "Pikachu" | "PokeTrainer" | "PokeZenigame" | "PokeFushigisou" |
+        "PokeLizardon" | "Lucario" | "Purin" | "Mewtwo"                  => Some(7),
+        "Captain"                                                        => Some(8),
+        "Ness" | "Lucas"                                                 => Some(9),
+        "Marth" | "Ike" | "Roy"                                          => Some(10),
+        "Popo" | "Nana"                                                  => Some(11),
+        "Pit"                                                            => Some(12),
+        "Wario" | "WarioMan"                                             => Some(13),
+        "Pikmin"                                                         => Some(14),
+        "GameWatch"                                                      => Some(15),
+        "Robot"                                                          => Some(16),
+        "Snake"                                                          => Some(17),
+        "Sonic"                                                          => Some(18),
+        _                                                                => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_provider::DirFiles;
+    use std::fs;
+
+    fn slot_dat(victory_theme: u32, announcer_call: u32) -> Vec<u8> {
+        let mut data = b"SLTC".to_vec();
+        data.resize(0x0C, 0);
+        data.extend(&victory_theme.to_be_bytes());
+        data.extend(&announcer_call.to_be_bytes());
+        data.resize(0x40, 0);
+        data
+    }
+
+    fn cosmetic_dat(series_icon: u8) -> Vec<u8> {
+        let mut data = b"COSC".to_vec();
+        data.resize(0x40, 0);
+        data[0x0A] = series_icon;
+        data
+    }
+
+    fn fighter_dat(name: &str) -> Vec<u8> {
+        let mut data = b"FCFG".to_vec();
+        data.resize(0x100, 0);
+        data[FIGHTER_CONFIG_NAME_OFFSET .. FIGHTER_CONFIG_NAME_OFFSET + name.len()].copy_from_slice(name.as_bytes());
+        data
+    }
+
+    #[test]
+    fn parse_configs() {
+        let slot = slot_config(FancySlice::new(&slot_dat(0x2710, 0x1F40))).unwrap();
+        assert_eq!((slot.victory_theme, slot.announcer_call), (0x2710, 0x1F40));
+        assert_eq!(cosmetic_config(FancySlice::new(&cosmetic_dat(18))).unwrap(), 18);
+        assert_eq!(fighter_config_name(FancySlice::new(&fighter_dat("Knuckles"))).unwrap(), "Knuckles");
+
+        assert!(slot_config(FancySlice::new(&cosmetic_dat(18))).is_err());
+        assert!(slot_config(FancySlice::new(b"SLTC")).is_err());
+        assert!(fighter_config_name(FancySlice::new(&fighter_dat(""))).is_err());
+    }
+
+    #[test]
+    fn load_configs() {
+        let pf = tempfile::tempdir().unwrap();
+        let brawl_ex = pf.path().join("BrawlEx");
+        for dir in &["SlotConfig", "CosmeticConfig", "FighterConfig"] {
+            fs::create_dir_all(brawl_ex.join(dir)).unwrap();
+        }
+        // a clone with every config
+        fs::write(brawl_ex.join("SlotConfig/Slot40.dat"), slot_dat(0x2710, 0x1F40)).unwrap();
+        fs::write(brawl_ex.join("CosmeticConfig/Cosmetic40.dat"), cosmetic_dat(18)).unwrap();
+        fs::write(brawl_ex.join("FighterConfig/Fighter40.dat"), fighter_dat("Knuckles")).unwrap();
+        // a vanilla fighter with a custom victory theme, matched by its fighter id
+        fs::write(brawl_ex.join("SlotConfig/Slot00.dat"), slot_dat(0x2711, 0x1F41)).unwrap();
+        // ignored files
+        fs::write(brawl_ex.join("SlotConfig/Slot41.dat"), b"garbage").unwrap();
+        fs::write(brawl_ex.join("SlotConfig/notes.txt"), b"SLTC").unwrap();
+
+        let configs = BrawlExConfigs::load(&DirFiles::new(pf.path()), &PathFormatter::default());
+        let knuckles = configs.slot_metadata("Knuckles").unwrap();
+        assert_eq!(knuckles.victory_theme, Some(0x2710));
+        assert_eq!(knuckles.announcer_call, Some(0x1F40));
+        assert_eq!(knuckles.series_icon, Some(18));
+        assert_eq!(knuckles.config_files, vec!(brawl_ex.join("SlotConfig/Slot40.dat"), brawl_ex.join("CosmeticConfig/Cosmetic40.dat")));
+
+        let mario = configs.slot_metadata("Mario").unwrap();
+        assert_eq!(mario.victory_theme, Some(0x2711));
+        assert_eq!(mario.series_icon, Some(0));
+
+        assert_eq!(configs.slot_metadata("Luigi"), SlotMetadata::vanilla("Luigi"));
+        assert_eq!(configs.slot_metadata("Unknown"), None);
+    }
+}
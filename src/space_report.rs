@@ -0,0 +1,333 @@
+use std::ops::Range;
+
+use crate::arc::{Arc, ArcChildData};
+use crate::fighter::{Fighter, FileRole};
+use crate::sakurai::SectionData;
+use crate::script::Script;
+
+/// How the bytes of a fighters files are used, for checking how close a mod is to the file size limits.
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct SpaceReport {
+    pub moveset: ArcSpace,
+    /// None when the moveset has no moveset data
+    pub moveset_data: Option<MovesetSpace>,
+    pub motion: ArcSpace,
+}
+
+/// How the bytes of an ARC file are used.
+/// `header_bytes + child_bytes + padding_bytes == file_size`
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct ArcSpace {
+    /// Whether the file was loaded from the mod, None when the provenance of the file is unknown
+    pub from_mod: Option<bool>,
+    /// Size of the file as written by `Arc::compile_with_original`
+    pub file_size: usize,
+    /// Bytes used by the ARC headers and child headers, including those of nested ARCs
+    pub header_bytes: usize,
+    /// Bytes used by the children, excluding nested ARCs which are counted by their contents
+    pub child_bytes: usize,
+    /// Bytes used to align the children
+    pub padding_bytes: usize,
+}
+
+/// How the bytes of the moveset data are used.
+/// `script_bytes + free_bytes + other_bytes == data_size`
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct MovesetSpace {
+    /// Size of the moveset data child of the moveset file
+    pub data_size: usize,
+    /// Bytes used by script events and their arguments.
+    /// Arguments shared by multiple events are only counted once.
+    pub script_bytes: usize,
+    /// Bytes marked as free space (FADEF00D) by PSA, that are not used by a script.
+    pub free_bytes: usize,
+    /// Bytes used by everything else e.g. section data, headers and string tables
+    pub other_bytes: usize,
+    /// The size of the largest contiguous block of free bytes
+    pub largest_free_block: usize,
+    /// How the data appended by the legacy PSA tool is used, None when the moveset has no appended data.
+    /// The appended bytes are also counted by the totals above.
+    pub appended: Option<AppendedSpace>,
+}
+
+/// How the bytes appended past the size in the moveset data header are used, see `ArcSakurai::appended_region`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct AppendedSpace {
+    /// Offset of the appended data from the start of the moveset data, the size of the original data
+    pub offset: usize,
+    pub size: usize,
+    pub script_bytes: usize,
+    pub free_bytes: usize,
+}
+
+impl Fighter {
+    /// Reports how the bytes of the moveset and motion files are used.
+    /// `moveset_file` is the data the moveset was parsed from, the fighter does not keep the bytes of its files.
+    ///
+    /// Only scripts stored in the moveset file are counted, scripts injected into memory by codes are not part of the file.
+    pub fn space_report(&self, moveset_file: &[u8]) -> SpaceReport {
+        let from_mod = |role| self.provenance.files.iter().find(|x| x.role == role).map(|x| x.from_mod);
+
+        let mut moveset_data = None;
+        for child in &self.moveset.children {
+            if let ArcChildData::Sakurai (sakurai) = &child.data {
+                let scripts = self.moveset_scripts();
+                let mut data = child.raw_data(moveset_file);
+                // data appended by the legacy PSA tool can continue past the size of the child
+                if let Some(region) = &sakurai.appended_region {
+                    let end = child.data_range.start + ARC_SAKURAI_HEADER_SIZE + region.end as usize;
+                    if end > child.data_range.end {
+                        data = moveset_file.get(child.data_range.start .. end).unwrap_or(data);
+                    }
+                }
+                moveset_data = Some(moveset_space(data, &scripts, sakurai.appended_region.clone()));
+            }
+        }
+
+        SpaceReport {
+            moveset: arc_space(&self.moveset, from_mod(FileRole::Moveset)),
+            moveset_data,
+            motion: arc_space(&self.motion, from_mod(FileRole::Motion)),
+        }
+    }
+
+    /// All scripts parsed from the moveset file
+    fn moveset_scripts(&self) -> Vec<&Script> {
+        let mut scripts = vec!();
+        if let Some(sakurai) = self.get_fighter_sakurai() {
+            for section in &sakurai.sections {
+                match &section.data {
+                    SectionData::FighterData (data) => {
+                        scripts.extend(data.entry_actions.iter());
+                        scripts.extend(data.exit_actions.iter());
+                        scripts.extend(data.subaction_main.iter());
+                        scripts.extend(data.subaction_gfx.iter());
+                        scripts.extend(data.subaction_sfx.iter());
+                        scripts.extend(data.subaction_other.iter());
+                        scripts.extend(data.entry_action_overrides.iter().map(|x| &x.script));
+                        scripts.extend(data.exit_action_overrides.iter().map(|x| &x.script));
+                    }
+                    SectionData::Script (section_script) => scripts.push(&section_script.script),
+                    _ => { }
+                }
+            }
+            scripts.extend(sakurai.fragment_scripts.iter());
+        }
+        scripts
+    }
+}
+
+fn arc_space(arc: &Arc, from_mod: Option<bool>) -> ArcSpace {
+    let mut space = ArcSpace { from_mod, file_size: 0, header_bytes: 0, child_bytes: 0, padding_bytes: 0 };
+    add_arc_space(arc, &mut space);
+    space.file_size = space.header_bytes + space.child_bytes + space.padding_bytes;
+    space
+}
+
+fn add_arc_space(arc: &Arc, space: &mut ArcSpace) {
+    space.header_bytes += ARC_HEADER_SIZE;
+    for child in &arc.children {
+        space.header_bytes += ARC_CHILD_HEADER_SIZE;
+        let size = match &child.data {
+            ArcChildData::Arc (nested) => {
+                let before = space.header_bytes + space.child_bytes + space.padding_bytes;
+                add_arc_space(nested, space);
+                space.header_bytes + space.child_bytes + space.padding_bytes - before
+            }
+            _ => {
+                space.child_bytes += child.raw_size();
+                child.raw_size()
+            }
+        };
+        space.padding_bytes += (ARC_CHILD_HEADER_SIZE - size % ARC_CHILD_HEADER_SIZE) % ARC_CHILD_HEADER_SIZE;
+    }
+}
+
+/// `data` is the moveset data including its header
+/// `appended_region` is relative to the end of the header
+fn moveset_space(data: &[u8], scripts: &[&Script], appended_region: Option<Range<i32>>) -> MovesetSpace {
+    let parent_data = &data[ARC_SAKURAI_HEADER_SIZE.min(data.len())..];
+
+    let mut script_ranges = vec!();
+    for script in scripts {
+        script_byte_ranges(parent_data, script.offset, &mut script_ranges);
+    }
+    let script_ranges = merge_ranges(script_ranges);
+
+    let mut free_ranges = vec!();
+    let mut offset = 0;
+    while offset + 4 <= parent_data.len() {
+        let word = u32::from_be_bytes([parent_data[offset], parent_data[offset + 1], parent_data[offset + 2], parent_data[offset + 3]]);
+        if word == FREE_SPACE && !script_ranges.iter().any(|x| x.contains(&offset)) {
+            free_ranges.push(offset + ARC_SAKURAI_HEADER_SIZE .. offset + ARC_SAKURAI_HEADER_SIZE + 4);
+        }
+        offset += 4;
+    }
+    let free_ranges = merge_ranges(free_ranges);
+
+    let script_bytes = script_ranges.iter().map(|x| x.len()).sum();
+    let free_bytes = free_ranges.iter().map(|x| x.len()).sum();
+    let appended = appended_region.map(|region| {
+        let start = region.start as usize;
+        let end = region.end as usize;
+        AppendedSpace {
+            offset:       start + ARC_SAKURAI_HEADER_SIZE,
+            size:         end - start,
+            script_bytes: overlap(&script_ranges, start .. end),
+            free_bytes:   overlap(&free_ranges, start + ARC_SAKURAI_HEADER_SIZE .. end + ARC_SAKURAI_HEADER_SIZE),
+        }
+    });
+    MovesetSpace {
+        data_size: data.len(),
+        script_bytes,
+        free_bytes,
+        other_bytes: data.len() - script_bytes - free_bytes,
+        largest_free_block: free_ranges.iter().map(|x| x.len()).max().unwrap_or(0),
+        appended,
+    }
+}
+
+/// Returns the number of bytes of `ranges` within `region`
+fn overlap(ranges: &[Range<usize>], region: Range<usize>) -> usize {
+    ranges.iter().map(|x| x.end.min(region.end).saturating_sub(x.start.max(region.start))).sum()
+}
+
+/// Adds the byte ranges of the events and arguments of the script at `offset` to `ranges`.
+/// Scripts that are empty or not stored in `parent_data` have no byte ranges.
+pub(crate) fn script_byte_ranges(parent_data: &[u8], offset: i32, ranges: &mut Vec<Range<usize>>) {
+    if offset <= 0 || offset as usize >= parent_data.len() {
+        return;
+    }
+    let read_u32 = |offset: usize| u32::from_be_bytes([parent_data[offset], parent_data[offset + 1], parent_data[offset + 2], parent_data[offset + 3]]);
+
+    let start = offset as usize;
+    let mut event_offset = start;
+    while event_offset + EVENT_SIZE <= parent_data.len() {
+        let namespace     = parent_data[event_offset];
+        let code          = parent_data[event_offset + 1];
+        let num_arguments = parent_data[event_offset + 2] as usize;
+        let raw_id        = read_u32(event_offset);
+        event_offset += EVENT_SIZE;
+
+        if code == 0 && namespace == 0 { // end of script
+            break;
+        }
+
+        if raw_id != FREE_SPACE && raw_id != END_OF_SPACE && num_arguments > 0 {
+            let argument_offset = read_u32(event_offset - 4) as usize;
+            let argument_end = argument_offset + num_arguments * ARGUMENT_SIZE;
+            if argument_end <= parent_data.len() {
+                ranges.push(argument_offset..argument_end);
+            }
+        }
+    }
+    ranges.push(start..event_offset);
+}
+
+/// Sorts the ranges and combines overlapping and adjacent ranges
+fn merge_ranges(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.sort_by_key(|x| x.start);
+    let mut merged: Vec<Range<usize>> = vec!();
+    for range in ranges {
+        if let Some(last) = merged.last_mut() {
+            if range.start <= last.end {
+                last.end = last.end.max(range.end);
+                continue;
+            }
+        }
+        merged.push(range);
+    }
+    merged
+}
+
+const ARC_HEADER_SIZE: usize = 0x40;
+const ARC_CHILD_HEADER_SIZE: usize = 0x20;
+const ARC_SAKURAI_HEADER_SIZE: usize = 0x20;
+const EVENT_SIZE: usize = 0x8;
+const ARGUMENT_SIZE: usize = 0x8;
+/// PSA fills empty, usable space with this value
+const FREE_SPACE: u32 = 0xFADEF00D;
+/// PSA marks the end of usable space with this value
+const END_OF_SPACE: u32 = 0xFADE0D8A;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::DataSource;
+    use crate::test_util::{TestFighterBuilder, damage};
+
+    #[test]
+    fn totals_add_up() {
+        let builder = TestFighterBuilder::new("TestChar")
+            .subaction("Wait1", |s| s)
+            .subaction("AttackAirN", |s| s
+                .async_timer(5.0)
+                .hitbox(damage(8).angle(361))
+                .terminate_collisions()
+            );
+        let fighter = builder.build();
+        let moveset_pac = builder.moveset_pac();
+        let report = fighter.space_report(&moveset_pac);
+
+        assert_eq!(report.moveset.file_size, moveset_pac.len());
+        assert_eq!(report.moveset.file_size, fighter.moveset.compile_with_original(&moveset_pac).len());
+        assert_eq!(report.moveset.header_bytes, ARC_HEADER_SIZE + ARC_CHILD_HEADER_SIZE);
+        assert_eq!(report.moveset.from_mod, None);
+
+        let data = report.moveset_data.unwrap();
+        assert_eq!(data.data_size, report.moveset.child_bytes);
+        assert_eq!(data.script_bytes + data.free_bytes + data.other_bytes, data.data_size);
+        // 3 events + the end of script event, 1 + 13 arguments
+        assert_eq!(data.script_bytes, 4 * EVENT_SIZE + 14 * ARGUMENT_SIZE);
+        assert_eq!(data.free_bytes, 0);
+
+        assert_eq!(report.motion.file_size, ARC_HEADER_SIZE);
+    }
+
+    #[test]
+    fn appended_script() {
+        let builder = TestFighterBuilder::new("TestChar")
+            .subaction("Wait1", |s| s.sync_timer(1.0))
+            .appended_subaction("AttackAirN", |s| s.hitbox(damage(8)));
+        let data = builder.build().space_report(&builder.moveset_pac()).moveset_data.unwrap();
+        let appended = data.appended.unwrap();
+        // 1 event + the end of script event, 13 arguments
+        assert_eq!(appended.size, 2 * EVENT_SIZE + 13 * ARGUMENT_SIZE);
+        assert_eq!(appended.script_bytes, appended.size);
+        assert_eq!(appended.free_bytes, 0);
+        assert_eq!(appended.offset + appended.size, data.data_size);
+        assert_eq!(data.script_bytes, 2 * EVENT_SIZE + ARGUMENT_SIZE + appended.script_bytes);
+    }
+
+    #[test]
+    fn shared_arguments_and_free_space() {
+        let mut data = vec!(0; ARC_SAKURAI_HEADER_SIZE);
+        // two scripts at 0x8 and 0x18 that each have one event using the same argument at 0x28
+        data.extend(&[0; 8]);
+        for _ in 0..2 {
+            data.extend(&[0x00, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x28]);
+            data.extend(&[0; 8]);
+        }
+        data.extend(&[0, 0, 0, 1, 0, 0, 0, 5]);
+        for _ in 0..3 {
+            data.extend(&FREE_SPACE.to_be_bytes());
+        }
+        data.extend(&[0; 4]);
+        data.extend(&FREE_SPACE.to_be_bytes());
+
+        let script = |offset| Script { events: vec!(), offset, source: DataSource::File { offset: offset as u32 } };
+        let space = moveset_space(&data, &[&script(0x8), &script(0x18), &script(0x18), &script(0)], None);
+        assert_eq!(space, MovesetSpace {
+            data_size: data.len(),
+            script_bytes: 0x20 + ARGUMENT_SIZE,
+            free_bytes: 16,
+            other_bytes: data.len() - 0x28 - 16,
+            largest_free_block: 12,
+            appended: None,
+        });
+    }
+}
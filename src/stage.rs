@@ -0,0 +1,159 @@
+//! Stage parameters, read from the STDT files in the stage .pac files e.g. `stage/melee/STGBATTLEFIELD.pac`.
+//!
+//! A STDT file holds the values a stage is tuned with e.g. the timing of its hazards.
+//! Which value is at which offset differs between stages and most of them are undocumented,
+//! so each STDT file is kept as its 32 bit big endian words, including its header, and compared word by word.
+
+use std::path::{Path, PathBuf};
+
+use failure::Error;
+use failure::bail;
+
+use crate::file_provider::FileProvider;
+use crate::msbin;
+use crate::provenance::PathFormatter;
+
+const STAGE_DIR: &str = "stage/melee";
+const ARC_HEADER_SIZE: usize = 0x40;
+const ARC_CHILD_HEADER_SIZE: usize = 0x20;
+
+/// The STDT files of a stage .pac
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct StageData {
+    /// Path of the file relative to the root of the brawl dump or the mods pf folder
+    pub path: PathBuf,
+    pub from_mod: bool,
+    /// The words of each STDT file in the order they are stored in the .pac, nested ARC files included
+    pub tables: Vec<Vec<u32>>,
+}
+
+impl StageData {
+    /// The file name of the stage .pac e.g. `STGBATTLEFIELD.pac`
+    pub fn name(&self) -> String {
+        self.path.file_name().map(|x| x.to_string_lossy().to_string()).unwrap_or_default()
+    }
+}
+
+/// Reads the stage .pac files of `brawl_files`, replacing them with the files from `mod_files` when present.
+pub(crate) fn load_stage_data(brawl_files: &dyn FileProvider, mod_files: Option<&dyn FileProvider>, paths: &PathFormatter) -> Result<Vec<StageData>, Error> {
+    // the relative path, where the file is read from and whether it is from the mod
+    let mut files: Vec<(String, &dyn FileProvider, bool)> = vec!();
+    for (provider, from_mod) in [(Some(brawl_files), false), (mod_files, true)] {
+        if let Some(provider) = provider {
+            let found = match provider.files_in(STAGE_DIR) {
+                Ok(found) => found,
+                Err(err) => bail!("Cannot read directory {}: {}", paths.display(&provider.source_path(STAGE_DIR)), err),
+            };
+            // stages are not in subfolders
+            for relative in found.iter().filter(|x| !x.contains('/') && is_pac(Path::new(x))) {
+                msbin::replace_file(&mut files, (format!("{}/{}", STAGE_DIR, relative), provider, from_mod));
+            }
+        }
+    }
+    files.sort_by(|a, b| Path::new(&a.0).cmp(Path::new(&b.0)));
+
+    let mut stages = vec!();
+    for (relative, provider, from_mod) in files {
+        let data = match provider.read(&relative) {
+            Ok(data) => data,
+            Err(err) => bail!("Cannot read {}: {}", paths.display(&provider.source_path(&relative)), err),
+        };
+        stages.push(StageData { path: PathBuf::from(relative), from_mod, tables: stdt_tables(&data) });
+    }
+    Ok(stages)
+}
+
+fn is_pac(path: &Path) -> bool {
+    path.extension().map(|x| x.to_string_lossy().to_lowercase() == "pac").unwrap_or(false)
+}
+
+/// Returns the words of every STDT file in the ARC file, including those in nested ARC files.
+/// Children that do not fit in the data are skipped.
+pub(crate) fn stdt_tables(data: &[u8]) -> Vec<Vec<u32>> {
+    let mut tables = vec!();
+    if data.len() < ARC_HEADER_SIZE || &data[..4] != b"ARC\0" {
+        return tables;
+    }
+
+    let count = u16::from_be_bytes([data[6], data[7]]) as usize;
+    let mut header_index = ARC_HEADER_SIZE;
+    for _ in 0..count {
+        let data_start = header_index + ARC_CHILD_HEADER_SIZE;
+        if data_start > data.len() {
+            break;
+        }
+        let size = i32::from_be_bytes([data[header_index + 4], data[header_index + 5], data[header_index + 6], data[header_index + 7]]).max(0) as usize;
+        let redirect_index = i16::from_be_bytes([data[header_index + 10], data[header_index + 11]]);
+        let child = &data[data_start .. (data_start + size).min(data.len())];
+        if redirect_index == -1 {
+            if child.starts_with(b"ARC\0") {
+                tables.extend(stdt_tables(child));
+            } else if child.starts_with(b"STDT") {
+                tables.push(child.chunks_exact(4).map(|x| u32::from_be_bytes([x[0], x[1], x[2], x[3]])).collect());
+            }
+        }
+        let end = data_start + size;
+        header_index = end + (ARC_CHILD_HEADER_SIZE - end % ARC_CHILD_HEADER_SIZE) % ARC_CHILD_HEADER_SIZE;
+    }
+    tables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_provider::DirFiles;
+    use crate::memory_files::MemoryFiles;
+    use crate::test_util::arc_blob;
+
+    use std::collections::HashMap;
+    use std::fs;
+
+    /// A STDT file containing the header and `values`
+    fn stdt(values: &[u32]) -> Vec<u8> {
+        let mut data = b"STDT".to_vec();
+        for value in [1, 0, 0, 0].iter().chain(values) {
+            data.extend(&value.to_be_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn tables_in_nested_arcs() {
+        let first = stdt(&[1.5_f32.to_bits(), 3]);
+        let second = stdt(&[7]);
+        let nested = arc_blob("nested", &[(-1, b"REFT"), (-1, &second)]);
+        let pac = arc_blob("STGTEST", &[(-1, &first), (-1, &nested), (0, &[])]);
+
+        assert_eq!(stdt_tables(&pac), vec!(
+            vec!(u32::from_be_bytes(*b"STDT"), 1, 0, 0, 0, 1.5_f32.to_bits(), 3),
+            vec!(u32::from_be_bytes(*b"STDT"), 1, 0, 0, 0, 7),
+        ));
+        assert!(stdt_tables(b"ARC").is_empty());
+        // a child larger than the file is cut off instead of panicking
+        assert_eq!(stdt_tables(&pac[..0x70]).len(), 1);
+    }
+
+    #[test]
+    fn mod_replaces_brawl_stage() {
+        let brawl = tempfile::tempdir().unwrap();
+        fs::create_dir_all(brawl.path().join("stage/melee")).unwrap();
+        fs::write(brawl.path().join("stage/melee/STGA.pac"), arc_blob("STGA", &[(-1, &stdt(&[1]))])).unwrap();
+        fs::write(brawl.path().join("stage/melee/STGB.pac"), arc_blob("STGB", &[(-1, &stdt(&[2]))])).unwrap();
+        // not stages
+        fs::create_dir_all(brawl.path().join("stage/melee/nested")).unwrap();
+        fs::write(brawl.path().join("stage/melee/nested/STGC.pac"), arc_blob("STGC", &[(-1, &stdt(&[4]))])).unwrap();
+        fs::write(brawl.path().join("stage/melee/STGA.rel"), [0]).unwrap();
+
+        let mut files = HashMap::new();
+        files.insert(String::from("stage/melee/stgb.pac"), arc_blob("STGB", &[(-1, &stdt(&[3]))]));
+        let memory = MemoryFiles::new(files);
+
+        let stages = load_stage_data(&DirFiles::new(brawl.path()), Some(&memory), &PathFormatter::default()).unwrap();
+        let stages: Vec<(String, bool, u32)> = stages.iter().map(|x| (x.name(), x.from_mod, *x.tables[0].last().unwrap())).collect();
+        assert_eq!(stages, vec!(
+            (String::from("STGA.pac"), false, 1),
+            (String::from("stgb.pac"), true, 3),
+        ));
+    }
+}
@@ -0,0 +1,154 @@
+/// A rough grouping of subactions, used to filter out subactions that are not relevant to e.g. frame data.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum SubactionCategory {
+    /// Standard attacks, specials, grabs, throws and final smashes
+    Attack,
+    /// Standing, walking, running, jumping, falling, landing, crouching, shielding and dodging
+    Movement,
+    /// Getting hit, knocked down, grabbed, thrown, buried, etc.
+    Damage,
+    /// Grabbing, hanging from and getting up from a ledge
+    Ledge,
+    /// Picking up, using and throwing items
+    Item,
+    /// Results screen animations
+    Victory,
+    /// Entry animations
+    Entry,
+    /// Taunts and anything else, including subactions added by mods that dont follow vanilla naming conventions
+    Misc,
+}
+
+impl SubactionCategory {
+    /// Categorizes a subaction from its name, following the vanilla naming conventions.
+    pub fn from_name(name: &str) -> SubactionCategory {
+        // Order matters, e.g. "ThrownF" must be checked before "Throw"
+        const CATEGORIES: &[(&str, SubactionCategory)] = &[
+            ("Entry",         SubactionCategory::Entry),
+            ("Win",           SubactionCategory::Victory),
+            ("Lose",          SubactionCategory::Victory),
+            ("Cliff",         SubactionCategory::Ledge),
+            ("Thrown",        SubactionCategory::Damage),
+            ("Capture",       SubactionCategory::Damage),
+            ("Damage",        SubactionCategory::Damage),
+            ("Down",          SubactionCategory::Damage),
+            ("Passive",       SubactionCategory::Damage),
+            ("Furafura",      SubactionCategory::Damage),
+            ("Bury",          SubactionCategory::Damage),
+            ("Swallowed",     SubactionCategory::Damage),
+            ("Miss",          SubactionCategory::Damage),
+            ("ShieldBreak",   SubactionCategory::Damage),
+            ("Item",          SubactionCategory::Item),
+            ("LightGet",      SubactionCategory::Item),
+            ("HeavyGet",      SubactionCategory::Item),
+            ("LightThrow",    SubactionCategory::Item),
+            ("HeavyThrow",    SubactionCategory::Item),
+            ("HeavyWalk",     SubactionCategory::Item),
+            ("Swing",         SubactionCategory::Item),
+            ("Hammer",        SubactionCategory::Item),
+            ("Attack",        SubactionCategory::Attack),
+            ("Special",       SubactionCategory::Attack),
+            ("Final",         SubactionCategory::Attack),
+            ("Catch",         SubactionCategory::Attack),
+            ("Throw",         SubactionCategory::Attack),
+            ("Wait",          SubactionCategory::Movement),
+            ("Walk",          SubactionCategory::Movement),
+            ("Dash",          SubactionCategory::Movement),
+            ("Run",           SubactionCategory::Movement),
+            ("Turn",          SubactionCategory::Movement),
+            ("Jump",          SubactionCategory::Movement),
+            ("Fall",          SubactionCategory::Movement),
+            ("Landing",       SubactionCategory::Movement),
+            ("Squat",         SubactionCategory::Movement),
+            ("Guard",         SubactionCategory::Movement),
+            ("Escape",        SubactionCategory::Movement),
+            ("Pass",          SubactionCategory::Movement),
+            ("Ottotto",       SubactionCategory::Movement),
+            ("Glide",         SubactionCategory::Movement),
+            ("Swim",          SubactionCategory::Movement),
+            ("Ladder",        SubactionCategory::Movement),
+            ("Stop",          SubactionCategory::Movement),
+        ];
+
+        for (prefix, category) in CATEGORIES {
+            if name.starts_with(prefix) {
+                return *category;
+            }
+        }
+        SubactionCategory::Misc
+    }
+
+    /// Returns false for subactions that never occur during a match, i.e. Victory and Entry.
+    pub fn is_gameplay(&self) -> bool {
+        !matches!(self, SubactionCategory::Victory | SubactionCategory::Entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_name() {
+        let names = [
+            ("EntryR",          SubactionCategory::Entry),
+            ("Win1",            SubactionCategory::Victory),
+            ("Lose",            SubactionCategory::Victory),
+            ("CliffCatch",      SubactionCategory::Ledge),
+            ("CliffAttack",     SubactionCategory::Ledge),
+            ("ThrownF",         SubactionCategory::Damage),
+            ("CaptureWait",     SubactionCategory::Damage),
+            ("DamageFlyHi",     SubactionCategory::Damage),
+            ("DownBoundU",      SubactionCategory::Damage),
+            ("Passive",         SubactionCategory::Damage),
+            ("PassiveStandF",   SubactionCategory::Damage),
+            ("FuraFura",        SubactionCategory::Misc),
+            ("Furafura",        SubactionCategory::Damage),
+            ("BuryWait",        SubactionCategory::Damage),
+            ("ShieldBreakFly",  SubactionCategory::Damage),
+            ("ItemScrewJump",   SubactionCategory::Item),
+            ("LightGet",        SubactionCategory::Item),
+            ("HeavyThrowF",     SubactionCategory::Item),
+            ("SwingS4",         SubactionCategory::Item),
+            ("HammerWalk",      SubactionCategory::Item),
+            ("Attack11",        SubactionCategory::Attack),
+            ("AttackAirF",      SubactionCategory::Attack),
+            ("SpecialHi",       SubactionCategory::Attack),
+            ("FinalStart",      SubactionCategory::Attack),
+            ("Catch",           SubactionCategory::Attack),
+            ("ThrowB",          SubactionCategory::Attack),
+            ("Wait1",           SubactionCategory::Movement),
+            ("WalkFast",        SubactionCategory::Movement),
+            ("Dash",            SubactionCategory::Movement),
+            ("RunBrake",        SubactionCategory::Movement),
+            ("TurnRun",         SubactionCategory::Movement),
+            ("JumpSquat",       SubactionCategory::Movement),
+            ("FallSpecial",     SubactionCategory::Movement),
+            ("LandingAirF",     SubactionCategory::Movement),
+            ("SquatWait",       SubactionCategory::Movement),
+            ("GuardOn",         SubactionCategory::Movement),
+            ("EscapeN",         SubactionCategory::Movement),
+            ("Pass",            SubactionCategory::Movement),
+            ("Ottotto",         SubactionCategory::Movement),
+            ("GlideStart",      SubactionCategory::Movement),
+            ("Swim",            SubactionCategory::Movement),
+            ("LadderUp",        SubactionCategory::Movement),
+            ("StopCeil",        SubactionCategory::Movement),
+            ("AppealHiR",       SubactionCategory::Misc),
+            ("",                SubactionCategory::Misc),
+            ("attack11",        SubactionCategory::Misc),
+        ];
+        for (name, category) in names.iter() {
+            assert_eq!(SubactionCategory::from_name(name), *category, "{}", name);
+        }
+    }
+
+    #[test]
+    fn is_gameplay() {
+        assert!(SubactionCategory::Attack.is_gameplay());
+        assert!(SubactionCategory::Misc.is_gameplay());
+        assert!(!SubactionCategory::Victory.is_gameplay());
+        assert!(!SubactionCategory::Entry.is_gameplay());
+    }
+}
@@ -0,0 +1,220 @@
+//! Classifies whether each subaction is performed on the ground, in the air or both.
+//!
+//! A subaction has no air/ground state of its own, it runs in whatever state the fighter is in when an action changes to it.
+//! So the environment comes from the Change Subaction events found by `Fighter::subaction_invokers`:
+//! *   An event inside an If statement checking On Ground or In Air, runs in that state.
+//! *   Otherwise an event in a common action runs in the state of that action, see `common_action_environment`.
+//! *   Events in the fighters own actions and the common script sections are unknown, as these are used both on the ground and in the air.
+//!
+//! Set Air/Ground events in the subactions main script move the fighter between the ground and the air, so such subactions are performed in both.
+
+use crate::fighter::Fighter;
+use crate::script::{Requirement, Script};
+use crate::script_ast::{BinaryExpression, ComparisonOperator, Expression};
+use crate::subaction_invokers::{InvokerRef, InvokerScript};
+
+/// Whether a subaction or action is performed on the ground, in the air or both.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum Environment {
+    Grounded,
+    Aerial,
+    Both,
+    Unknown,
+}
+
+impl Environment {
+    /// Returns the environment of something performed in both `self` and `other`.
+    /// Unknown is ignored as long as the other side is known.
+    pub fn combine(self, other: Environment) -> Environment {
+        match (self, other) {
+            (Environment::Unknown, x) | (x, Environment::Unknown) => x,
+            (a, b) if a == b => a,
+            _ => Environment::Both,
+        }
+    }
+
+    fn opposite(self) -> Environment {
+        match self {
+            Environment::Grounded => Environment::Aerial,
+            Environment::Aerial   => Environment::Grounded,
+            x                     => x,
+        }
+    }
+}
+
+impl Fighter {
+    /// Returns whether the subaction at `index` is performed on the ground, in the air or both, see the `subaction_environment` module documentation.
+    /// The environment is based on the known invocations, so a subaction that is also invoked by an unknown action may be performed in more environments than returned.
+    pub fn subaction_environment(&self, index: usize) -> Environment {
+        match self.get_fighter_data().and_then(|x| x.subaction_main.get(index)) {
+            Some(script) => environment(&self.subaction_invokers(index), script),
+            None => Environment::Unknown,
+        }
+    }
+
+    /// Returns the environment of every subaction, indexed by subaction.
+    /// All scripts are scanned once, so use this instead of calling `Fighter::subaction_environment` for every subaction.
+    pub fn subaction_environments(&self) -> Vec<Environment> {
+        let fighter_data = match self.get_fighter_data() {
+            Some(fighter_data) => fighter_data,
+            None => return vec!(),
+        };
+        self.subaction_invoker_index().iter().zip(fighter_data.subaction_main.iter())
+            .map(|(invokers, script)| environment(invokers, script))
+            .collect()
+    }
+}
+
+/// Returns the environment of a subaction from its invokers and main script
+fn environment(invokers: &[InvokerRef], script: &Script) -> Environment {
+    let environment = invokers.iter()
+        .map(invoker_environment)
+        .fold(Environment::Unknown, Environment::combine);
+    set_air_ground_environments(script).fold(environment, Environment::combine)
+}
+
+fn invoker_environment(invoker: &InvokerRef) -> Environment {
+    if let Some(environment) = invoker.requirements.iter().filter_map(requirement_environment).next() {
+        return environment;
+    }
+    match invoker.script {
+        InvokerScript::EntryAction (action) |
+        InvokerScript::ExitAction (action) |
+        InvokerScript::EntryActionOverride (action) |
+        InvokerScript::ExitActionOverride (action) => common_action_environment(action),
+        InvokerScript::CommonSection (_) => Environment::Unknown,
+    }
+}
+
+/// Returns the environment the expression requires the fighter to be in to pass, None if it does not depend on the environment.
+fn requirement_environment(expression: &Expression) -> Option<Environment> {
+    match expression {
+        Expression::Nullary (Requirement::OnGround) => Some(Environment::Grounded),
+        Expression::Nullary (Requirement::InAir)    => Some(Environment::Aerial),
+        Expression::Not (expression) => requirement_environment(expression).map(Environment::opposite),
+        Expression::Binary (BinaryExpression { left, right, operator: ComparisonOperator::And }) => {
+            match (requirement_environment(left), requirement_environment(right)) {
+                (Some(left), Some(right)) if left != right => None,
+                (left, right) => left.or(right),
+            }
+        }
+        Expression::Binary (BinaryExpression { left, right, operator: ComparisonOperator::Or }) => {
+            match (requirement_environment(left), requirement_environment(right)) {
+                (Some(left), Some(right)) if left == right => Some(left),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The environments that the Set Air/Ground events of the script put the fighter in
+fn set_air_ground_environments(script: &Script) -> impl Iterator<Item=Environment> + '_ {
+    script.events.iter().filter_map(|event| match (event.namespace, event.code, event.arguments.first()) {
+        (0x0E, 0x00, Some(crate::script::Argument::Value (0))) => Some(Environment::Aerial),
+        (0x0E, 0x00, Some(_))                                  => Some(Environment::Grounded),
+        _ => None,
+    })
+}
+
+/// Returns the environment of the common action at `index`.
+///
+/// Only actions that are always performed on the ground or always in the air are classified.
+/// Actions that can be performed in either e.g. item throws and tethers, or in neither e.g. hanging from a ledge or swimming, are Unknown.
+/// Fighter specific actions, starting at 0x112, are always Unknown.
+///
+/// Source: the indexes and names are those of `action_names::action_name`, the same table used to name `HighLevelAction`s.
+/// Each range is classified from what those names describe, it has not been checked against the games code,
+/// so an action that can unexpectedly be entered in the other environment would be misclassified.
+pub fn common_action_environment(index: usize) -> Environment {
+    match index {
+        0x000 ..= 0x00a => Environment::Grounded, // Wait to JumpSquat
+        0x00b ..= 0x010 => Environment::Aerial,   // Jump to FallSpecial
+        0x011 ..= 0x020 => Environment::Grounded, // Squat, landing, shield and rolls
+        0x021           => Environment::Aerial,   // EscapeAir
+        0x022 ..= 0x032 => Environment::Grounded, // Rebound and ground attacks
+        0x033           => Environment::Aerial,   // AerialAttack
+        0x034 ..= 0x03c => Environment::Grounded, // Grabs and throws
+        0x049           => Environment::Aerial,   // DamageFall
+        0x04d ..= 0x055 => Environment::Grounded, // Lying on the ground
+        0x059 ..= 0x05e => Environment::Grounded, // Dizzy and asleep
+        0x060 | 0x061   => Environment::Grounded, // Passive and PassiveStand
+        0x062 ..= 0x064 => Environment::Aerial,   // Wall and ceiling techs
+        0x065           => Environment::Grounded, // RunIntoWall
+        0x067 ..= 0x06c => Environment::Aerial,   // Walljump and wallcling
+        0x070           => Environment::Aerial,   // GetFootstooledAirStart
+        0x07c ..= 0x07d => Environment::Grounded, // Ottotto
+        0x084 | 0x085   => Environment::Aerial,   // GlideStart and GlideDirection
+        0x086           => Environment::Grounded, // GlideLanding
+        0x087 | 0x088   => Environment::Aerial,   // GlideAttack and GlideEnd
+        0x089 ..= 0x091 => Environment::Grounded, // Slip
+        0x0af ..= 0x0b1 => Environment::Aerial,   // ItemScrew
+        0x0c4           => Environment::Aerial,   // SpringJump
+        0x10c | 0x10d   => Environment::Grounded, // Appeal
+        0x10f | 0x110   => Environment::Grounded, // Victory and Loss
+        _               => Environment::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::Argument;
+    use crate::test_util::TestFighterBuilder;
+
+    fn requirement(ty: Requirement, flip: bool) -> Argument {
+        Argument::Requirement { flip, ty }
+    }
+
+    #[test]
+    fn common_actions() {
+        assert_eq!(common_action_environment(0x000), Environment::Grounded);
+        assert_eq!(common_action_environment(0x00e), Environment::Aerial);
+        assert_eq!(common_action_environment(0x033), Environment::Aerial);
+        assert_eq!(common_action_environment(0x074), Environment::Unknown);
+        assert_eq!(common_action_environment(0x112), Environment::Unknown);
+    }
+
+    #[test]
+    fn combine() {
+        assert_eq!(Environment::Unknown.combine(Environment::Aerial), Environment::Aerial);
+        assert_eq!(Environment::Grounded.combine(Environment::Grounded), Environment::Grounded);
+        assert_eq!(Environment::Grounded.combine(Environment::Aerial), Environment::Both);
+        assert_eq!(Environment::Both.combine(Environment::Unknown), Environment::Both);
+    }
+
+    #[test]
+    fn invoked_environments() {
+        let fighter = TestFighterBuilder::new("TestChar")
+            .subaction("SpecialN", |s| s)
+            .subaction("SpecialAirN", |s| s)
+            .subaction("SpecialS", |s| s)
+            .subaction("SpecialHi", |s| s.event(0x0E, 0x00, vec!(Argument::Value (0))))
+            .subaction("Unused", |s| s)
+            // grounded only and aerial only invocations
+            .action(|s| s
+                .event(0x00, 0x0A, vec!(requirement(Requirement::OnGround, false)))
+                .event(0x04, 0x00, vec!(Argument::Value (0)))
+                .event(0x00, 0x0E, vec!())
+                .event(0x04, 0x00, vec!(Argument::Value (1)))
+                .event(0x00, 0x0F, vec!())
+            , |s| s)
+            // not in the air and unconditional
+            .action(|s| s
+                .event(0x00, 0x0A, vec!(requirement(Requirement::InAir, true)))
+                .event(0x04, 0x00, vec!(Argument::Value (2)))
+                .event(0x00, 0x0F, vec!())
+                .event(0x04, 0x00, vec!(Argument::Value (3)))
+            , |s| s)
+            .build();
+
+        assert_eq!(fighter.subaction_environment(0), Environment::Grounded);
+        assert_eq!(fighter.subaction_environment(1), Environment::Aerial);
+        assert_eq!(fighter.subaction_environment(2), Environment::Grounded);
+        // invoked by a fighter specific action without a check, but goes into the air itself
+        assert_eq!(fighter.subaction_environment(3), Environment::Aerial);
+        assert_eq!(fighter.subaction_environment(4), Environment::Unknown);
+        assert_eq!(fighter.subaction_environment(99), Environment::Unknown);
+    }
+}
@@ -0,0 +1,272 @@
+//! Finds the scripts that change to each subaction, so tools can navigate from a subaction back to where the game uses it.
+//!
+//! The entry and exit scripts of every action and the script sections of the common Fighter.pac are scanned for Change Subaction events.
+//! Subactions that are only changed to by code e.g. the rel files of the fighter, or that are never used at all, have no invokers.
+//! Subroutines in the fighters own moveset are not followed, so a change made by a subroutine called from an action is not found.
+
+use crate::fighter::Fighter;
+use crate::script::{Argument, Script};
+use crate::script_ast::{BinaryExpression, ComparisonOperator, Expression};
+
+/// A script that contains a Change Subaction event.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum InvokerScript {
+    /// The entry script of the action at the index, common actions are below 0x112 and fighter specific actions start at 0x112
+    EntryAction (usize),
+    /// The exit script of the action at the index
+    ExitAction (usize),
+    /// The fighters replacement for the entry script of the common action at the index
+    EntryActionOverride (usize),
+    /// The fighters replacement for the exit script of the common action at the index
+    ExitActionOverride (usize),
+    /// A script section of the common Fighter.pac, identified by its name
+    CommonSection (String),
+}
+
+/// A Change Subaction event that changes to a subaction.
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct InvokerRef {
+    pub script: InvokerScript,
+    /// Index into `Script::events`
+    pub event: usize,
+    /// The conditions of the If statements the event is in, the subaction is only changed when all of them pass.
+    /// Else and Else If branches include the failed conditions of the earlier branches as `Expression::Not`.
+    /// Empty when the change is unconditional.
+    pub requirements: Vec<Expression>,
+    /// False when the event is also gated by conditions that could not be represented in `requirements` e.g. a switch case or an unknown requirement
+    pub requirements_complete: bool,
+}
+
+impl InvokerRef {
+    /// Returns true when the event is inside an If statement or switch case
+    pub fn is_conditional(&self) -> bool {
+        !self.requirements.is_empty() || !self.requirements_complete
+    }
+}
+
+impl Fighter {
+    /// Returns every Change Subaction event that changes to the subaction at `index`.
+    /// An empty list means the subaction is never changed to by a script, see the `subaction_invokers` module documentation.
+    pub fn subaction_invokers(&self, index: usize) -> Vec<InvokerRef> {
+        let mut invokers = vec!();
+        if self.get_fighter_data().map(|x| index >= x.subaction_main.len()).unwrap_or(true) {
+            return invokers;
+        }
+        for (invoker, script) in self.invoker_scripts() {
+            scan_script(&invoker, script, &mut |subaction, invoker_ref| {
+                if subaction == index {
+                    invokers.push(invoker_ref);
+                }
+            });
+        }
+        invokers
+    }
+
+    /// Returns the invokers of every subaction, indexed by subaction.
+    /// All scripts are scanned once, so use this instead of calling `Fighter::subaction_invokers` for every subaction.
+    pub fn subaction_invoker_index(&self) -> Vec<Vec<InvokerRef>> {
+        let subactions = match self.get_fighter_data() {
+            Some(fighter_data) => fighter_data.subaction_main.len(),
+            None => return vec!(),
+        };
+        let mut index = vec!(vec!(); subactions);
+        for (invoker, script) in self.invoker_scripts() {
+            scan_script(&invoker, script, &mut |subaction, invoker_ref| {
+                if let Some(invokers) = index.get_mut(subaction) {
+                    invokers.push(invoker_ref);
+                }
+            });
+        }
+        index
+    }
+
+    /// Returns every script that is scanned for Change Subaction events
+    fn invoker_scripts(&self) -> Vec<(InvokerScript, &Script)> {
+        let mut scripts: Vec<(InvokerScript, &Script)> = vec!();
+        let fighter_data = match self.get_fighter_data() {
+            Some(fighter_data) => fighter_data,
+            None => return scripts,
+        };
+
+        for (i, script) in fighter_data.entry_actions.iter().enumerate() {
+            scripts.push((InvokerScript::EntryAction (FIGHTER_ACTIONS_START + i), script));
+        }
+        for (i, script) in fighter_data.exit_actions.iter().enumerate() {
+            scripts.push((InvokerScript::ExitAction (FIGHTER_ACTIONS_START + i), script));
+        }
+        for action_override in &fighter_data.entry_action_overrides {
+            scripts.push((InvokerScript::EntryActionOverride (action_override.action_id as usize), &action_override.script));
+        }
+        for action_override in &fighter_data.exit_action_overrides {
+            scripts.push((InvokerScript::ExitActionOverride (action_override.action_id as usize), &action_override.script));
+        }
+        if let Some(common) = self.get_fighter_data_common() {
+            for (i, script) in common.entry_actions.iter().enumerate() {
+                scripts.push((InvokerScript::EntryAction (i), script));
+            }
+            for (i, script) in common.exit_actions.iter().enumerate() {
+                scripts.push((InvokerScript::ExitAction (i), script));
+            }
+        }
+        for section in self.get_fighter_data_common_scripts() {
+            scripts.push((InvokerScript::CommonSection (section.name.clone()), &section.script));
+        }
+        scripts
+    }
+}
+
+/// The index of the first action in the fighters own moveset, lower indexes are the common actions in Fighter.pac
+pub(crate) const FIGHTER_ACTIONS_START: usize = 0x112;
+
+/// An If statement that the scan is currently inside of
+struct IfBranch {
+    /// Conditions of the earlier branches, which must have failed for the current branch to run
+    failed: Vec<Option<Expression>>,
+    /// Condition of the current branch, None for an Else branch
+    test: Option<Option<Expression>>,
+}
+
+/// Calls `found` with the subaction index and invoker of every Change Subaction event in the script
+fn scan_script(invoker: &InvokerScript, script: &Script, found: &mut dyn FnMut(usize, InvokerRef)) {
+    let mut branches: Vec<IfBranch> = vec!();
+    let mut switch_depth = 0;
+    for (i, event) in script.events.iter().enumerate() {
+        match (event.namespace, event.code) {
+            (0x00, 0x0A) => branches.push(IfBranch { failed: vec!(), test: Some(Expression::from_condition_event(event)) }),
+            (0x00, 0x0B) | (0x00, 0x0C) => {
+                if let Some(IfBranch { test: Some(test), .. }) = branches.last_mut() {
+                    let operator = if event.code == 0x0B { ComparisonOperator::And } else { ComparisonOperator::Or };
+                    *test = match (test.take(), Expression::from_condition_event(event)) {
+                        (Some(left), Some(right)) => Some(Expression::Binary (BinaryExpression { left: Box::new(left), right: Box::new(right), operator })),
+                        _ => None,
+                    };
+                }
+            }
+            (0x00, 0x0D) | (0x00, 0x0E) => {
+                if let Some(branch) = branches.last_mut() {
+                    if let Some(test) = branch.test.take() {
+                        branch.failed.push(test);
+                    }
+                    if event.code == 0x0D {
+                        branch.test = Some(Expression::from_condition_event(event));
+                    }
+                }
+            }
+            (0x00, 0x0F) => { branches.pop(); }
+            (0x00, 0x10) => switch_depth += 1,
+            (0x00, 0x13) => switch_depth -= 1,
+            (0x04, 0x00) => {
+                if let Some(Argument::Value (subaction)) = event.arguments.first() {
+                    if *subaction >= 0 {
+                        let mut requirements = vec!();
+                        let mut requirements_complete = switch_depth <= 0;
+                        for branch in &branches {
+                            for failed in &branch.failed {
+                                match failed {
+                                    Some(failed) => requirements.push(Expression::Not (Box::new(failed.clone()))),
+                                    None => requirements_complete = false,
+                                }
+                            }
+                            match &branch.test {
+                                Some(Some(test)) => requirements.push(test.clone()),
+                                Some(None) => requirements_complete = false,
+                                None => { }
+                            }
+                        }
+                        found(*subaction as usize, InvokerRef { script: invoker.clone(), event: i, requirements, requirements_complete });
+                    }
+                }
+            }
+            _ => { }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::{Requirement, FixedPoint};
+    use crate::test_util::TestFighterBuilder;
+
+    fn requirement(ty: Requirement) -> Argument {
+        Argument::Requirement { flip: false, ty }
+    }
+
+    #[test]
+    fn conditional_entry_action() {
+        let mut builder = TestFighterBuilder::new("TestChar");
+        for i in 0..8 {
+            builder = builder.subaction(&format!("Subaction{}", i), |s| s);
+        }
+        let fighter = builder
+            .action(|s| s
+                .event(0x00, 0x0A, vec!(requirement(Requirement::AnimationEnd)))
+                .event(0x04, 0x00, vec!(Argument::Value (5)))
+                .event(0x00, 0x0F, vec!())
+            , |s| s)
+            .action(|s| s
+                .event(0x04, 0x00, vec!(Argument::Value (1), Argument::Bool (true)))
+                .event(0x00, 0x0A, vec!(requirement(Requirement::OnGround)))
+                .event(0x00, 0x0B, vec!(requirement(Requirement::AnimationEnd)))
+                .event(0x04, 0x00, vec!(Argument::Value (2)))
+                .event(0x00, 0x0E, vec!())
+                .event(0x00, 0x10, vec!(Argument::Value (0), Argument::Value (0)))
+                .event(0x00, 0x11, vec!(Argument::Value (1)))
+                .event(0x04, 0x00, vec!(Argument::Value (3)))
+                .event(0x00, 0x13, vec!())
+                .event(0x00, 0x0F, vec!())
+                .event(0x00, 0x01, vec!(Argument::Scalar (FixedPoint::from_frames(1.0))))
+            , |s| s
+                .event(0x04, 0x00, vec!(Argument::Value (1)))
+            )
+            .build();
+
+        let invokers = fighter.subaction_invokers(5);
+        assert_eq!(invokers.len(), 1);
+        assert_eq!(invokers[0].script, InvokerScript::EntryAction (0x112));
+        assert_eq!(invokers[0].event, 1);
+        assert!(invokers[0].requirements_complete);
+        assert_eq!(invokers[0].requirements.len(), 1);
+        assert!(matches!(invokers[0].requirements[0], Expression::Nullary (Requirement::AnimationEnd)));
+
+        let invokers = fighter.subaction_invokers(1);
+        assert_eq!(invokers.len(), 2);
+        assert_eq!(invokers[0].script, InvokerScript::EntryAction (0x113));
+        assert_eq!(invokers[0].event, 0);
+        assert!(!invokers[0].is_conditional());
+        assert_eq!(invokers[1].script, InvokerScript::ExitAction (0x113));
+
+        let invokers = fighter.subaction_invokers(2);
+        assert_eq!(invokers.len(), 1);
+        match &invokers[0].requirements[..] {
+            [Expression::Binary (BinaryExpression { left, right, operator: ComparisonOperator::And })] => {
+                assert!(matches!(**left, Expression::Nullary (Requirement::OnGround)));
+                assert!(matches!(**right, Expression::Nullary (Requirement::AnimationEnd)));
+            }
+            requirements => panic!("Unexpected requirements {:?}", requirements),
+        }
+
+        // in the else branch and a switch case
+        let invokers = fighter.subaction_invokers(3);
+        assert_eq!(invokers.len(), 1);
+        assert!(matches!(invokers[0].requirements[..], [Expression::Not (_)]));
+        assert!(!invokers[0].requirements_complete);
+        assert!(invokers[0].is_conditional());
+    }
+
+    #[test]
+    fn unreferenced() {
+        let fighter = TestFighterBuilder::new("TestChar")
+            .subaction("Wait1", |s| s)
+            .subaction("Unused", |s| s)
+            .action(|s| s.event(0x04, 0x00, vec!(Argument::Value (0))), |s| s)
+            .build();
+        assert!(fighter.subaction_invokers(1).is_empty());
+        assert!(fighter.subaction_invokers(99).is_empty());
+        let index = fighter.subaction_invoker_index();
+        assert_eq!(index.len(), 2);
+        assert_eq!(index[0].len(), 1);
+    }
+}
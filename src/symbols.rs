@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use failure::Error;
+use failure::bail;
+
+use crate::action_names::action_name;
+use crate::address::RamAddress;
+use crate::fighter::Fighter;
+use crate::wiird;
+
+/// Names of regions of RAM with a known purpose, keyed by their start address.
+pub const RAM_SYMBOLS: &[(RamAddress, &str)] = &[
+    (RamAddress::new(wiird::FRAME_SPEED_MODIFIER_TABLE), "frame speed modifier table"),
+];
+
+/// Wii RAM starts at this address, symbol files give every value below it as a script offset.
+const RAM_START: u32 = 0x8000_0000;
+
+/// Maps script offsets and RAM addresses to human readable names.
+/// The two are kept apart, so a script offset never resolves to the name of a RAM address or the other way around.
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable {
+    symbols:     HashMap<i32, String>,
+    ram_symbols: HashMap<RamAddress, String>,
+}
+
+impl SymbolTable {
+    /// Creates a table containing `RAM_SYMBOLS`
+    pub fn builtin() -> SymbolTable {
+        SymbolTable {
+            symbols:     HashMap::new(),
+            ram_symbols: RAM_SYMBOLS.iter().map(|(address, name)| (*address, name.to_string())).collect(),
+        }
+    }
+
+    /// Creates a table naming the scripts of the common Fighter.pac moveset the fighter was loaded with, along with `RAM_SYMBOLS`.
+    ///
+    /// The offsets of the common scripts differ between versions of Fighter.pac, so they are named from the loaded file instead of a fixed table:
+    /// *   section scripts by the name of their section
+    /// *   action entry and exit scripts by their action e.g. `Wait entry` and `Wait exit`
+    ///
+    /// When several of these share an offset, the first one in that order is used.
+    pub fn common(fighter: &Fighter) -> SymbolTable {
+        let mut table = SymbolTable::builtin();
+        let mut add = |offset: i32, name: String| {
+            // empty scripts have no offset
+            if offset > 0 {
+                table.symbols.entry(offset).or_insert(name);
+            }
+        };
+
+        for section in fighter.get_fighter_data_common_scripts() {
+            add(section.script.offset, section.name.clone());
+        }
+        if let Some(common) = fighter.get_fighter_data_common() {
+            for (i, script) in common.entry_actions.iter().enumerate() {
+                add(script.offset, format!("{} entry", action_name(i)));
+            }
+            for (i, script) in common.exit_actions.iter().enumerate() {
+                add(script.offset, format!("{} exit", action_name(i)));
+            }
+        }
+        table
+    }
+
+    /// Parses a symbol file.
+    ///
+    /// Each line contains a hex offset followed by a name e.g. `0x9E48 KnockbackCheck`, the `0x` prefix is optional.
+    /// Values of 0x80000000 and above are RAM addresses e.g. `0x80581000 frame speed modifier table`, smaller values are script offsets.
+    /// Empty lines and lines starting with `#` are ignored.
+    /// Repeating an offset with the same name is allowed, repeating an offset with a different name is an error.
+    pub fn parse(text: &str) -> Result<SymbolTable, Error> {
+        let mut table = SymbolTable::default();
+        for (i, line) in text.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut split = line.splitn(2, char::is_whitespace);
+            let offset_string = split.next().unwrap();
+            let name = match split.next().map(|x| x.trim()) {
+                Some(name) if !name.is_empty() => name,
+                _ => bail!("line {}: missing a name after the offset", line_number),
+            };
+
+            let hex = offset_string.trim_start_matches("0x").trim_start_matches("0X");
+            let value = match u32::from_str_radix(hex, 16) {
+                Ok(value) => value,
+                Err(_) => bail!("line {}: {:?} is not a hex offset", line_number, offset_string),
+            };
+
+            let existing = if value >= RAM_START {
+                table.ram_symbols.insert(RamAddress::new(value), name.to_string())
+            } else {
+                table.symbols.insert(value as i32, name.to_string())
+            };
+            if let Some(existing) = existing {
+                if existing != name {
+                    bail!("line {}: offset 0x{:x} is already named {:?}", line_number, value, existing);
+                }
+            }
+        }
+        Ok(table)
+    }
+
+    /// Loads a symbol file, refer to `SymbolTable::parse` for the format.
+    pub fn load(path: &Path) -> Result<SymbolTable, Error> {
+        match fs::read_to_string(path) {
+            Ok(text) => SymbolTable::parse(&text),
+            Err(err) => bail!("Cannot read symbol file {:?}: {}", path, err),
+        }
+    }
+
+    /// Adds the symbols from `other`, replacing existing symbols at the same offset or address.
+    pub fn extend(&mut self, other: SymbolTable) {
+        self.symbols.extend(other.symbols);
+        self.ram_symbols.extend(other.ram_symbols);
+    }
+
+    /// Returns the name of the symbol at `offset`
+    pub fn resolve(&self, offset: i32) -> Option<&str> {
+        self.symbols.get(&offset).map(|x| x.as_str())
+    }
+
+    /// Returns the name of the symbol at the RAM address
+    pub fn resolve_address(&self, address: RamAddress) -> Option<&str> {
+        self.ram_symbols.get(&address).map(|x| x.as_str())
+    }
+
+    /// Formats the offset for display, including the symbol name if there is one e.g. `@0x9E48 (KnockbackCheck)`
+    pub fn format_offset(&self, offset: i32) -> String {
+        match self.resolve(offset) {
+            Some(name) => format!("@0x{:X} ({})", offset, name),
+            None       => format!("@0x{:X}", offset),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len() + self.ram_symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty() && self.ram_symbols.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arc;
+    use crate::test_util::{TestFighterBuilder, arc_blob};
+    use crate::wii_memory::WiiMemory;
+
+    use fancy_slice::FancySlice;
+
+    use std::sync;
+
+    #[test]
+    fn parse_symbols() {
+        let table = SymbolTable::parse("
+            # comment
+            0x9E48 KnockbackCheck
+            1a2B   Fast Fall check
+            0x9E48 KnockbackCheck
+        ").unwrap();
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.resolve(0x9E48), Some("KnockbackCheck"));
+        assert_eq!(table.resolve(0x1A2B), Some("Fast Fall check"));
+        assert_eq!(table.resolve(0x1234), None);
+        assert_eq!(table.format_offset(0x9E48), "@0x9E48 (KnockbackCheck)");
+        assert_eq!(table.format_offset(0x1234), "@0x1234");
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(SymbolTable::parse("0x9E48 A\n0x9E48 B").is_err());
+        assert!(SymbolTable::parse("0xZZ Name").is_err());
+        assert!(SymbolTable::parse("0x9E48").is_err());
+    }
+
+    #[test]
+    fn builtin_addresses() {
+        let table = SymbolTable::builtin();
+        assert_eq!(table.resolve_address(RamAddress::new(0x80581000)), Some("frame speed modifier table"));
+        assert_eq!(table.resolve_address(RamAddress::new(0x80581008)), None);
+
+        let table = SymbolTable::parse("0x80402000 Custom").unwrap();
+        assert_eq!(table.resolve_address(RamAddress::new(0x80402000)), Some("Custom"));
+    }
+
+    #[test]
+    fn offsets_and_addresses_separate() {
+        let table = SymbolTable::parse("0x80402000 Address\n0x9E48 Offset").unwrap();
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.resolve(0x80402000_u32 as i32), None);
+        assert_eq!(table.resolve_address(RamAddress::new(0x80402000)), Some("Address"));
+        assert_eq!(table.resolve(0x9E48), Some("Offset"));
+        assert_eq!(table.resolve_address(RamAddress::new(0x9E48)), None);
+
+        // the same value is only a duplicate within its kind
+        assert!(SymbolTable::parse("0x80402000 A\n0x80402000 B").is_err());
+    }
+
+    #[test]
+    fn extend_replaces() {
+        let mut table = SymbolTable::parse("0x10 Old").unwrap();
+        table.extend(SymbolTable::parse("0x10 New\n0x20 Other").unwrap());
+        assert_eq!(table.resolve(0x10), Some("New"));
+        assert_eq!(table.len(), 2);
+    }
+
+    /// A Fighter.pac containing three section scripts, where the first two are the same script
+    fn common_moveset() -> Vec<u8> {
+        let mut data = vec!();
+        for value in &[0x48, 0x28, 0, 3, 0, 0, 0, 0] {
+            data.extend(&u32::to_be_bytes(*value));
+        }
+        data.resize(0x20 + 0x8, 0);
+        // two scripts of a single event at 0x8 and 0x18
+        for _ in 0..2 {
+            data.extend(&[0x04, 0x07, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+            data.extend(&[0; 8]);
+        }
+        // sections
+        for value in &[0x8, 0x0, 0x8, 0x12, 0x18, 0x23] {
+            data.extend(&u32::to_be_bytes(*value));
+        }
+        // string table
+        data.extend(b"gameAnimCmd_First\0gameAnimCmd_Same\0gameAnimCmd_Other\0");
+        data
+    }
+
+    #[test]
+    fn common_fighter_scripts() {
+        let pac = arc_blob("Fighter", &[(-1, &common_moveset())]);
+        let mut fighter = TestFighterBuilder::new("Test").build();
+        fighter.moveset_common = sync::Arc::new(arc::arc(FancySlice::new(&pac), &WiiMemory::new(), false));
+
+        let table = SymbolTable::common(&fighter);
+        assert_eq!(table.resolve(0x8), Some("gameAnimCmd_First"));
+        assert_eq!(table.resolve(0x18), Some("gameAnimCmd_Other"));
+        assert_eq!(table.format_offset(0x18), "@0x18 (gameAnimCmd_Other)");
+        assert_eq!(table.resolve_address(RamAddress::new(0x80581000)), Some("frame speed modifier table"));
+        assert_eq!(table.len(), 3);
+    }
+}
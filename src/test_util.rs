@@ -0,0 +1,575 @@
+//! Builds minimal fighters in memory for use in tests, without needing any game files.
+//!
+//! The builder encodes a moveset file the same way brawl stores it, which is then parsed by the regular parsers.
+//! So the resulting `Fighter` is exactly what would be loaded from a Fit{}.pac containing the same data.
+//!
+//! ```rust
+//! use brawllib_rs::test_util::{TestFighterBuilder, damage};
+//!
+//! let fighter = TestFighterBuilder::new("TestChar")
+//!     .subaction("AttackAirN", |s| s
+//!         .async_timer(5.0)
+//!         .hitbox(damage(8).angle(361))
+//!         .async_timer(10.0)
+//!         .terminate_collisions()
+//!     )
+//!     .build();
+//!
+//! let fighter_data = fighter.get_fighter_data().unwrap();
+//! assert_eq!(fighter_data.subaction_flags[0].name, "AttackAirN");
+//! assert_eq!(fighter_data.subaction_main[0].events.len(), 4);
+//! ```
+//!
+//! The built fighter has no motion, models or Fighter.pac, so it cannot be used to create a `HighLevelFighter`.
+//! It is marked as loaded with `LoadDetail::ScriptsOnly`, so the animation and model accessors return `NotLoaded`.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync;
+
+use crate::arc::Arc;
+use crate::arc;
+use crate::fighter::{Fighter, LoadDetail, ModType};
+use crate::provenance::Provenance;
+use crate::script::{Argument, DataSource, Event, FixedPoint, Script};
+use crate::wii_memory::WiiMemory;
+
+use fancy_slice::FancySlice;
+
+/// Builds a `Fighter` containing only a moveset with the specified subactions and attributes.
+#[derive(Clone, Debug)]
+pub struct TestFighterBuilder {
+    name: String,
+    subactions: Vec<(String, ScriptBuilder)>,
+    /// Index of the subaction and its main script, stored after the rest of the moveset data
+    appended: Vec<(usize, ScriptBuilder)>,
+    /// Entry and exit scripts of the fighter specific actions, starting at action 0x112
+    actions: Vec<(ScriptBuilder, ScriptBuilder)>,
+    attributes: Vec<u8>,
+}
+
+impl TestFighterBuilder {
+    pub fn new(name: &str) -> TestFighterBuilder {
+        TestFighterBuilder {
+            name:       name.to_string(),
+            subactions: vec!(),
+            appended:   vec!(),
+            actions:    vec!(),
+            attributes: vec!(0; ATTRIBUTES_SIZE),
+        }
+    }
+
+    /// Adds a subaction with the main script built by `script`.
+    /// Subactions are indexed in the order they are added.
+    pub fn subaction<F>(mut self, name: &str, script: F) -> TestFighterBuilder where F: FnOnce(ScriptBuilder) -> ScriptBuilder {
+        self.subactions.push((name.to_string(), script(ScriptBuilder::new())));
+        self
+    }
+
+    /// Adds a subaction whose main script is stored in the layout written by the legacy PSA tool:
+    /// after the end of the moveset data, without including it in the size in the header.
+    pub fn appended_subaction<F>(mut self, name: &str, script: F) -> TestFighterBuilder where F: FnOnce(ScriptBuilder) -> ScriptBuilder {
+        self.appended.push((self.subactions.len(), script(ScriptBuilder::new())));
+        self.subactions.push((name.to_string(), ScriptBuilder::new()));
+        self
+    }
+
+    /// Adds a fighter specific action with the entry and exit scripts built by `entry` and `exit`.
+    /// Actions are indexed in the order they are added, starting at 0x112.
+    pub fn action<F, G>(mut self, entry: F, exit: G) -> TestFighterBuilder
+        where F: FnOnce(ScriptBuilder) -> ScriptBuilder, G: FnOnce(ScriptBuilder) -> ScriptBuilder
+    {
+        self.actions.push((entry(ScriptBuilder::new()), exit(ScriptBuilder::new())));
+        self
+    }
+
+    /// Sets the float attribute at `offset` into the attributes section e.g. 0x00 for walk_init_vel
+    pub fn attribute_f32(mut self, offset: usize, value: f32) -> TestFighterBuilder {
+        self.attributes[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    /// Sets the integer attribute at `offset` into the attributes section e.g. 0x30 for jump_squat_frames
+    pub fn attribute_i32(mut self, offset: usize, value: i32) -> TestFighterBuilder {
+        self.attributes[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    /// Parses the moveset created by `TestFighterBuilder::moveset_pac` into a `Fighter`
+    pub fn build(&self) -> Fighter {
+        let moveset_pac = self.moveset_pac();
+        let moveset = arc::arc(FancySlice::new(&moveset_pac), &WiiMemory::new(), false);
+        let empty_arc = || Arc { name: String::new(), children: vec!() };
+
+        Fighter {
+            cased_name:                  self.name.clone(),
+            moveset_common:              sync::Arc::new(empty_arc()),
+            moveset,
+            motion:                      Arc { name: format!("Fit{}Motion", self.name), children: vec!() },
+            final_pac:                   None,
+            models:                      vec!(),
+            kirby_hats:                  vec!(),
+            modded_by_psa:               false,
+            mod_type:                    ModType::NotMod,
+            wiird_frame_speed_modifiers: vec!(),
+            provenance:                  Provenance::new(vec!(), vec!()),
+            follower:                    None,
+            transformation:              None,
+            modified_events:             vec!(),
+            slot_metadata:               None,
+            load_detail:                 LoadDetail::ScriptsOnly,
+        }
+    }
+
+    /// Returns the bytes of a Fit{}.pac file containing the moveset.
+    pub fn moveset_pac(&self) -> Vec<u8> {
+        let sakurai = self.sakurai();
+
+        let mut pac = vec!(0; ARC_HEADER_SIZE);
+        pac[..3].copy_from_slice(b"ARC");
+        pac[4..6].copy_from_slice(&0x0101_u16.to_be_bytes());
+        pac[6..8].copy_from_slice(&1_u16.to_be_bytes());
+        let name = format!("Fit{}", self.name);
+        pac[0x10..0x10 + name.len().min(0x2F)].copy_from_slice(&name.as_bytes()[..name.len().min(0x2F)]);
+
+        let mut child_header = vec!(0; ARC_CHILD_HEADER_SIZE);
+        child_header[4..8].copy_from_slice(&(sakurai.len() as u32).to_be_bytes());
+        child_header[10..12].copy_from_slice(&(-1_i16).to_be_bytes());
+        pac.extend(child_header);
+        pac.extend(sakurai);
+        let padding = (ARC_CHILD_HEADER_SIZE - pac.len() % ARC_CHILD_HEADER_SIZE) % ARC_CHILD_HEADER_SIZE;
+        pac.extend(vec!(0; padding));
+        pac
+    }
+
+    /// Creates the moveset data containing a single "data" section
+    fn sakurai(&self) -> Vec<u8> {
+        let moveset = self.fighter_data();
+
+        let mut sakurai = vec!();
+        let moveset_len = moveset.len() as u32;
+        for value in &[moveset_len, moveset_len, 0, 1, 0, 0, 0, 0] {
+            sakurai.extend(&value.to_be_bytes());
+        }
+        sakurai.extend(moveset);
+        // section table: the data section starts at offset 0 and is named by the first string
+        sakurai.extend(&[0; 8]);
+        sakurai.extend(b"data\0");
+        if self.appended.is_empty() {
+            return sakurai;
+        }
+
+        // appended scripts are detected by the size in the header, so it needs to be the exact size of the original data
+        let size = sakurai.len() as u32;
+        sakurai[..4].copy_from_slice(&size.to_be_bytes());
+
+        let header = SAKURAI_HEADER_SIZE + 0x30; // subaction main offset in the fighter data header
+        let subaction_main_start = u32::from_be_bytes([sakurai[header], sakurai[header + 1], sakurai[header + 2], sakurai[header + 3]]) as usize;
+        for (index, script) in &self.appended {
+            let mut appended = vec!();
+            let offset = script.encode(&mut appended, sakurai.len() - SAKURAI_HEADER_SIZE);
+            sakurai.extend(appended);
+            let entry = SAKURAI_HEADER_SIZE + subaction_main_start + index * 4;
+            sakurai[entry..entry + 4].copy_from_slice(&offset.to_be_bytes());
+        }
+        sakurai
+    }
+
+    /// Creates the moveset data, offsets are relative to the start of the returned data.
+    ///
+    /// Layout:
+    /// *   fighter data header
+    /// *   attributes
+    /// *   subaction flags
+    /// *   entry and exit action offset tables
+    /// *   subaction main, gfx, sfx and other offset tables
+    /// *   scripts and their arguments
+    /// *   subaction names
+    fn fighter_data(&self) -> Vec<u8> {
+        let subaction_count = self.subactions.len();
+        // The parser reads one more subaction flags entry than fits before the next section, so there is always at least one entry.
+        let flags_count = subaction_count.max(1);
+
+        let attributes_start = FIGHTER_DATA_HEADER_SIZE;
+        let subaction_flags_start = attributes_start + ATTRIBUTES_SIZE;
+        let action_count = self.actions.len();
+        let entry_actions_start = subaction_flags_start + flags_count * SUBACTION_FLAGS_SIZE;
+        let exit_actions_start = entry_actions_start + action_count * 4;
+        let subaction_main_start = exit_actions_start + action_count * 4;
+        let subaction_gfx_start = subaction_main_start + subaction_count * 4;
+        let subaction_sfx_start = subaction_gfx_start + subaction_count * 4;
+        let subaction_other_start = subaction_sfx_start + subaction_count * 4;
+        let scripts_start = subaction_other_start + subaction_count * 4;
+
+        let mut scripts = vec!();
+        let mut script_offsets = vec!();
+        for (_, script) in &self.subactions {
+            script_offsets.push(script.encode(&mut scripts, scripts_start));
+        }
+        let mut entry_action_offsets = vec!();
+        let mut exit_action_offsets = vec!();
+        for (entry, exit) in &self.actions {
+            entry_action_offsets.push(entry.encode(&mut scripts, scripts_start));
+            exit_action_offsets.push(exit.encode(&mut scripts, scripts_start));
+        }
+
+        let names_start = scripts_start + scripts.len();
+        let mut names = vec!();
+        let mut name_offsets = vec!();
+        for (name, _) in &self.subactions {
+            name_offsets.push((names_start + names.len()) as u32);
+            names.extend(name.as_bytes());
+            names.push(0);
+        }
+
+        let mut header = vec!(0; FIGHTER_DATA_HEADER_SIZE);
+        let mut set_header = |offset: usize, value: usize| header[offset..offset + 4].copy_from_slice(&(value as u32).to_be_bytes());
+        set_header(0x00, subaction_flags_start);
+        set_header(0x08, attributes_start);
+        // The size of each section is determined by the offset of the following section.
+        // This otherwise unused offset marks the last subaction flags entry, so that the extra entry read by the parser is the last entry.
+        set_header(0x1c, subaction_flags_start + (flags_count - 1) * SUBACTION_FLAGS_SIZE);
+        if action_count > 0 {
+            set_header(0x24, entry_actions_start);
+            set_header(0x28, exit_actions_start);
+        }
+        if subaction_count > 0 {
+            set_header(0x30, subaction_main_start);
+            set_header(0x34, subaction_gfx_start);
+            set_header(0x38, subaction_sfx_start);
+            set_header(0x3c, subaction_other_start);
+        }
+
+        let mut data = header;
+        data.extend(&self.attributes);
+        for i in 0..flags_count {
+            data.extend(&[0; 4]); // in translation time, animation flags and padding
+            data.extend(&name_offsets.get(i).cloned().unwrap_or(0).to_be_bytes());
+        }
+        for offset in entry_action_offsets.iter().chain(&exit_action_offsets) {
+            data.extend(&offset.to_be_bytes());
+        }
+        for offset in &script_offsets {
+            data.extend(&offset.to_be_bytes());
+        }
+        // gfx, sfx and other scripts are left empty
+        data.extend(vec!(0; subaction_count * 4 * 3));
+        data.extend(scripts);
+        data.extend(names);
+        data
+    }
+}
+
+/// Builds a script from a sequence of events.
+#[derive(Clone, Debug, Default)]
+pub struct ScriptBuilder {
+    events: Vec<Event>,
+}
+
+impl ScriptBuilder {
+    pub fn new() -> ScriptBuilder {
+        ScriptBuilder { events: vec!() }
+    }
+
+    /// Creates a standalone script, for testing functions that take a single script
+    pub fn build(self) -> Script {
+        Script { events: self.events, offset: 0, source: DataSource::File { offset: 0 } }
+    }
+
+    /// Adds an event with the specified id and arguments
+    pub fn event(mut self, namespace: u8, code: u8, arguments: Vec<Argument>) -> ScriptBuilder {
+        self.events.push(Event { namespace, code, unk1: 0, arguments });
+        self
+    }
+
+    /// Wait `frames` frames from when the event is reached
+    pub fn sync_timer(self, frames: f32) -> ScriptBuilder {
+        self.event(0x00, 0x01, vec!(Argument::Scalar (FixedPoint::from_frames(frames))))
+    }
+
+    /// Wait until frame `frame` of the subaction
+    pub fn async_timer(self, frame: f32) -> ScriptBuilder {
+        self.event(0x00, 0x02, vec!(Argument::Scalar (FixedPoint::from_frames(frame))))
+    }
+
+    pub fn hitbox(self, hitbox: HitBoxBuilder) -> ScriptBuilder {
+        let arguments = hitbox.arguments();
+        self.event(0x06, 0x00, arguments)
+    }
+
+    /// Creates a special hitbox with a rehit rate of 0 and all special flags cleared
+    pub fn special_hitbox(self, hitbox: HitBoxBuilder) -> ScriptBuilder {
+        let mut arguments = hitbox.arguments();
+        arguments.push(Argument::Value (0)); // rehit rate
+        arguments.push(Argument::Value (0)); // special flags
+        self.event(0x06, 0x15, arguments)
+    }
+
+    /// Removes the hitbox with the specified id
+    pub fn delete_hitbox(self, hitbox_id: i32) -> ScriptBuilder {
+        self.event(0x06, 0x03, vec!(Argument::Value (hitbox_id)))
+    }
+
+    /// Removes all hitboxes
+    pub fn terminate_collisions(self) -> ScriptBuilder {
+        self.event(0x06, 0x04, vec!())
+    }
+
+    /// Appends the encoded script to `output` and returns the offset of the script.
+    /// `start` is the offset of `output` in the moveset.
+    /// An empty script is stored as offset 0 like in brawl.
+    fn encode(&self, output: &mut Vec<u8>, start: usize) -> u32 {
+        if self.events.is_empty() {
+            return 0;
+        }
+
+        let script_offset = start + output.len();
+        let arguments_start = script_offset + (self.events.len() + 1) * EVENT_SIZE;
+        let mut events: Vec<u8> = vec!();
+        let mut arguments: Vec<u8> = vec!();
+        for event in &self.events {
+            let arguments_offset = if event.arguments.is_empty() { 0 } else { arguments_start + arguments.len() };
+            events.push(event.namespace);
+            events.push(event.code);
+            events.push(event.arguments.len() as u8);
+            events.push(event.unk1);
+            events.extend(&(arguments_offset as u32).to_be_bytes());
+
+            for argument in &event.arguments {
+                let (ty, value) = argument.raw();
+                arguments.extend(&ty.to_be_bytes());
+                arguments.extend(&value.to_be_bytes());
+            }
+        }
+        events.extend(&[0; EVENT_SIZE]); // end of script
+
+        output.extend(events);
+        output.extend(arguments);
+        script_offset as u32
+    }
+}
+
+/// Starts building a hitbox that deals `damage`
+pub fn damage(damage: i32) -> HitBoxBuilder {
+    HitBoxBuilder {
+        bone_index: 0,
+        set_id:     0,
+        hitbox_id:  0,
+        damage,
+        angle:      0,
+        wdsk:       0,
+        kbg:        0,
+        shield_damage: 0,
+        bkb:        0,
+        size:       1.0,
+        offset:     (0.0, 0.0, 0.0),
+        flags:      HITBOX_GROUND_AND_AERIAL,
+    }
+}
+
+/// Builds the arguments of a Create Hitbox event, start with `damage`.
+#[derive(Clone, Debug)]
+pub struct HitBoxBuilder {
+    bone_index:    i16,
+    set_id:        u8,
+    hitbox_id:     u8,
+    damage:        i32,
+    angle:         i32,
+    wdsk:          i16,
+    kbg:           i16,
+    shield_damage: i16,
+    bkb:           i16,
+    size:          f32,
+    offset:        (f32, f32, f32),
+    flags:         i32,
+}
+
+/// Hits grounded and aerial opponents
+const HITBOX_GROUND_AND_AERIAL: i32 = 0b11 << 16;
+
+impl HitBoxBuilder {
+    pub fn angle(mut self, angle: i32) -> HitBoxBuilder {
+        self.angle = angle;
+        self
+    }
+
+    pub fn bone(mut self, bone_index: i16) -> HitBoxBuilder {
+        self.bone_index = bone_index;
+        self
+    }
+
+    pub fn id(mut self, hitbox_id: u8) -> HitBoxBuilder {
+        self.hitbox_id = hitbox_id;
+        self
+    }
+
+    pub fn set_id(mut self, set_id: u8) -> HitBoxBuilder {
+        self.set_id = set_id;
+        self
+    }
+
+    pub fn bkb(mut self, bkb: i16) -> HitBoxBuilder {
+        self.bkb = bkb;
+        self
+    }
+
+    pub fn kbg(mut self, kbg: i16) -> HitBoxBuilder {
+        self.kbg = kbg;
+        self
+    }
+
+    pub fn wdsk(mut self, wdsk: i16) -> HitBoxBuilder {
+        self.wdsk = wdsk;
+        self
+    }
+
+    pub fn shield_damage(mut self, shield_damage: i16) -> HitBoxBuilder {
+        self.shield_damage = shield_damage;
+        self
+    }
+
+    pub fn size(mut self, size: f32) -> HitBoxBuilder {
+        self.size = size;
+        self
+    }
+
+    pub fn offset(mut self, x: f32, y: f32, z: f32) -> HitBoxBuilder {
+        self.offset = (x, y, z);
+        self
+    }
+
+    fn arguments(&self) -> Vec<Argument> {
+        vec!(
+            Argument::Value  ((self.bone_index as i32) << 16 | (self.set_id as i32) << 8 | self.hitbox_id as i32),
+            Argument::Value  (self.damage),
+            Argument::Value  (self.angle),
+            Argument::Value  ((self.wdsk as i32) << 16 | (self.kbg as u16) as i32),
+            Argument::Value  ((self.shield_damage as i32) << 16 | (self.bkb as u16) as i32),
+            Argument::Scalar (FixedPoint::from_frames(self.size)),
+            Argument::Scalar (FixedPoint::from_frames(self.offset.0)),
+            Argument::Scalar (FixedPoint::from_frames(self.offset.1)),
+            Argument::Scalar (FixedPoint::from_frames(self.offset.2)),
+            Argument::Scalar (FixedPoint::from_frames(0.0)), // tripping rate
+            Argument::Scalar (FixedPoint::from_frames(1.0)), // hitlag multiplier
+            Argument::Scalar (FixedPoint::from_frames(1.0)), // sdi multiplier
+            Argument::Value  (self.flags),
+        )
+    }
+}
+
+/// Returns a WiiRD codeset of 32 bit writes, each write is the address and the value written to it.
+pub fn gct(writes: &[(u32, [u8; 4])]) -> Vec<u8> {
+    let mut gct = vec!(0x00, 0xD0, 0xC0, 0xDE, 0x00, 0xD0, 0xC0, 0xDE);
+    for (address, value) in writes {
+        gct.extend(&(0x04000000 | address & 0x1FFFFFF).to_be_bytes());
+        gct.extend(value);
+    }
+    gct.extend(&[0xF0, 0, 0, 0, 0, 0, 0, 0]);
+    gct
+}
+
+/// Writes `moveset_pac` as both the Fit{}.pac and Fit{}MotionEtc.pac of the fighter in its own folder of `fighter_dir`
+pub fn write_fighter(fighter_dir: &Path, name: &str, moveset_pac: &[u8]) -> io::Result<()> {
+    let dir = fighter_dir.join(name.to_lowercase());
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(format!("Fit{}.pac", name)), moveset_pac)?;
+    fs::write(dir.join(format!("Fit{}MotionEtc.pac", name)), moveset_pac)
+}
+
+/// Returns the bytes of an ARC file named `name`.
+/// Each child is given as its redirect index, -1 when it is not a redirect, and its data.
+pub fn arc_blob(name: &str, children: &[(i16, &[u8])]) -> Vec<u8> {
+    let mut data = vec!();
+    data.extend(b"ARC\0");
+    data.extend(&u16::to_be_bytes(0x0101));
+    data.extend(&u16::to_be_bytes(children.len() as u16));
+    data.extend(&[0x00; 8]);
+    data.extend(name.bytes());
+    data.resize(ARC_HEADER_SIZE, 0x00);
+    for (i, (redirect_index, child)) in children.iter().enumerate() {
+        let start = data.len();
+        data.extend(&i16::to_be_bytes(3));
+        data.extend(&i16::to_be_bytes(i as i16));
+        data.extend(&i32::to_be_bytes(child.len() as i32));
+        data.push(1);
+        data.push(0);
+        data.extend(&i16::to_be_bytes(*redirect_index));
+        data.resize(start + ARC_CHILD_HEADER_SIZE, 0x00);
+        data.extend(child.iter());
+        let padding = (ARC_CHILD_HEADER_SIZE - data.len() % ARC_CHILD_HEADER_SIZE) % ARC_CHILD_HEADER_SIZE;
+        data.extend(vec!(0; padding));
+    }
+    data
+}
+
+const ARC_HEADER_SIZE: usize = 0x40;
+const ARC_CHILD_HEADER_SIZE: usize = 0x20;
+const SAKURAI_HEADER_SIZE: usize = 0x20;
+const FIGHTER_DATA_HEADER_SIZE: usize = 0x80;
+const ATTRIBUTES_SIZE: usize = 0x2e0;
+const SUBACTION_FLAGS_SIZE: usize = 0x8;
+const EVENT_SIZE: usize = 0x8;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script_ast::{ScriptAst, EventAst, FloatValue};
+
+    #[test]
+    fn round_trip() {
+        let builder = TestFighterBuilder::new("TestChar")
+            .attribute_f32(0x00, 1.5)
+            .attribute_i32(0x30, 7)
+            .subaction("Wait1", |s| s)
+            .subaction("AttackAirN", |s| s
+                .async_timer(5.0)
+                .hitbox(damage(8).angle(361).bkb(30).kbg(100).size(4.5).offset(0.0, 2.0, -1.0).bone(3).id(1))
+                .async_timer(10.0)
+                .terminate_collisions()
+            );
+        let fighter = builder.build();
+        assert_eq!(fighter.cased_name, "TestChar");
+
+        let fighter_data = fighter.get_fighter_data().unwrap();
+        assert!(fighter.get_fighter_sakurai().unwrap().warnings.is_empty());
+        assert_eq!(fighter_data.attributes.walk_init_vel, 1.5);
+        assert_eq!(fighter_data.attributes.jump_squat_frames, 7);
+        let names: Vec<&str> = fighter_data.subaction_flags.iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, vec!("Wait1", "AttackAirN"));
+        assert_eq!(fighter_data.subaction_main.len(), 2);
+        assert!(fighter_data.subaction_main[0].events.is_empty());
+        assert!(fighter_data.subaction_gfx[1].events.is_empty());
+
+        let script = ScriptAst::new(&fighter_data.subaction_main[1]);
+        assert_eq!(script.block.events.len(), 4);
+        assert!(matches!(script.block.events[0], EventAst::AsyncWait (frame) if frame == 5.0));
+        match &script.block.events[1] {
+            EventAst::CreateHitBox (hitbox) => {
+                assert!(matches!(hitbox.damage, FloatValue::Constant (damage) if damage == 8.0));
+                assert_eq!(hitbox.trajectory, 361);
+                assert_eq!(hitbox.bkb, 30);
+                assert_eq!(hitbox.kbg, 100);
+                assert_eq!(hitbox.size, 4.5);
+                assert_eq!(hitbox.y_offset, 2.0);
+                assert_eq!(hitbox.z_offset, -1.0);
+                assert_eq!(hitbox.bone_index, 3);
+                assert_eq!(hitbox.hitbox_id, 1);
+                assert!(hitbox.ground && hitbox.aerial);
+            }
+            _ => panic!("Expected a hitbox"),
+        }
+        assert!(matches!(script.block.events[3], EventAst::DeleteAllHitBoxes));
+
+        // the pac survives a rebuild by the arc writer
+        let pac = builder.moveset_pac();
+        assert_eq!(fighter.moveset.compile_with_original(&pac), pac);
+    }
+
+    #[test]
+    fn no_subactions() {
+        let fighter = TestFighterBuilder::new("Empty").build();
+        let fighter_data = fighter.get_fighter_data().unwrap();
+        assert!(fighter_data.subaction_main.is_empty());
+    }
+}
@@ -0,0 +1,105 @@
+//! Spans for each phase of loading, emitted when the `tracing` feature is enabled.
+//!
+//! Without the feature the spans compile to nothing and the crates log messages go to `log` as before.
+
+/// Enters a span with the given name and fields that lasts until the end of the enclosing block.
+/// The fields use the `tracing` field syntax, they are not evaluated when the `tracing` feature is disabled.
+macro_rules! phase_span {
+    ($name:expr $(, $($fields:tt)*)?) => {
+        #[cfg(feature = "tracing")]
+        let _span = ::tracing::info_span!($name $(, $($fields)*)?).entered();
+    };
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use crate::brawl_mod::BrawlMod;
+    use crate::test_util::TestFighterBuilder;
+
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use tracing::Dispatch;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+
+    /// The name and (field, value) pairs of a span
+    type CapturedSpan = (String, Vec<(String, String)>);
+
+    /// Records the name and fields of every span created while it is the default subscriber
+    struct SpanCapture {
+        spans:   Arc<Mutex<Vec<CapturedSpan>>>,
+        next_id: AtomicU64,
+    }
+
+    impl tracing::Subscriber for SpanCapture {
+        fn enabled(&self, _: &tracing::Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes) -> Id {
+            struct Fields (Vec<(String, String)>);
+            impl Visit for Fields {
+                fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                    self.0.push((field.name().to_string(), format!("{:?}", value)));
+                }
+            }
+
+            let mut fields = Fields (vec!());
+            span.record(&mut fields);
+            self.spans.lock().unwrap().push((span.metadata().name().to_string(), fields.0));
+            Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+        }
+
+        fn record(&self, _: &Id, _: &Record) { }
+        fn record_follows_from(&self, _: &Id, _: &Id) { }
+        fn event(&self, _: &tracing::Event) { }
+        fn enter(&self, _: &Id) { }
+        fn exit(&self, _: &Id) { }
+    }
+
+    #[test]
+    fn load_fighters_spans() {
+        let brawl = tempfile::tempdir().unwrap();
+        let fighter_dir = brawl.path().join("fighter");
+        fs::create_dir_all(&fighter_dir).unwrap();
+        // an empty ARC as the common Fighter.pac
+        let mut common_fighter = vec!(0; 0x40);
+        common_fighter[..3].copy_from_slice(b"ARC");
+        fs::write(fighter_dir.join("Fighter.pac"), common_fighter).unwrap();
+        let moveset = TestFighterBuilder::new("Test").moveset_pac();
+        for name in &["Test0", "Test1"] {
+            let dir = fighter_dir.join(name.to_lowercase());
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join(format!("Fit{}.pac", name)), &moveset).unwrap();
+            fs::write(dir.join(format!("Fit{}MotionEtc.pac", name)), &moveset).unwrap();
+        }
+
+        let spans = Arc::new(Mutex::new(vec!()));
+        let dispatch = Dispatch::new(SpanCapture { spans: spans.clone(), next_id: AtomicU64::new(0) });
+        // The fighters are parsed on rayon threads, so they are run in a pool whose threads also use the capturing subscriber
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .spawn_handler(|thread| {
+                let dispatch = dispatch.clone();
+                std::thread::spawn(move || tracing::dispatcher::with_default(&dispatch, || thread.run()));
+                Ok(())
+            })
+            .build()
+            .unwrap();
+        let result = tracing::dispatcher::with_default(&dispatch, ||
+            pool.install(|| BrawlMod::new(brawl.path(), None).load_fighters(true).unwrap())
+        );
+        assert_eq!(result.len(), 2);
+
+        let spans = spans.lock().unwrap();
+        let has_span = |name: &str, field: &str, value: &str|
+            spans.iter().any(|(x, fields)| x == name && fields.iter().any(|(x, y)| x == field && y == value));
+        assert!(spans.iter().any(|(name, _)| name == "load_fighters"));
+        assert!(has_span("parse_fighter", "fighter", "Test0"));
+        assert!(has_span("parse_fighter", "fighter", "Test1"));
+        assert!(has_span("arc", "name", "FitTest"));
+        assert!(spans.iter().any(|(name, fields)| name == "sakurai_section" && fields.iter().any(|(x, _)| x == "section")));
+    }
+}
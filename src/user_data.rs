@@ -1,10 +1,12 @@
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub struct UserData {
     pub name: String,
     pub value: UserDataValue,
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub enum UserDataValue {
     Int (i32),
     Float (f32),
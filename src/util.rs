@@ -16,8 +16,9 @@ pub(crate) struct ListOffset {
 
 pub fn parse_tag(data: &[u8]) -> String {
     let mut tag = String::new();
-    for j in 0..4 {
-        let byte = data[j] as char;
+    // the tag is cut short when the data ends, e.g. for an empty arc child at the end of the file
+    for byte in data.iter().take(4) {
+        let byte = *byte as char;
         if byte.is_ascii_graphic() {
             tag.push(byte);
         }
@@ -0,0 +1,253 @@
+use crate::effect_pac::{self, EffectPac};
+use crate::fighter::Fighter;
+use crate::sakurai::SectionData;
+use crate::sakurai::fighter_data::ScriptCollection;
+use crate::script::Script;
+use crate::script_ast::{ScriptAst, Block, EventAst};
+
+/// A script event that refers to a subaction or action that does not exist.
+/// Brawl crashes when such an event is executed.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct SubactionRefIssue {
+    /// The script containing the event
+    pub location: ScriptLocation,
+    /// Index of the event within each nested block, starting from the root block of the script.
+    /// e.g. `[3, 1]` is the second event in the block of the fourth event.
+    /// The events of an else branch are numbered after the events of the then branch.
+    pub event_path: Vec<usize>,
+    pub target: RefTarget,
+    /// The index that was referenced
+    pub index: i32,
+    /// The number of subactions or actions that exist
+    pub count: usize,
+}
+
+/// A graphic effect event that refers to an effect missing from its effect file.
+/// Brawl crashes when such an event is executed.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct GfxRefIssue {
+    /// The script containing the event
+    pub location: ScriptLocation,
+    /// Index of the event within each nested block, see `SubactionRefIssue::event_path`
+    pub event_path: Vec<usize>,
+    /// The id of the effect file that was referenced
+    pub file: i16,
+    /// The index of the effect that was referenced
+    pub graphic: i16,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum ScriptLocation {
+    Subaction { collection: ScriptCollection, index: usize },
+    EntryAction (usize),
+    ExitAction (usize),
+    /// The fighters replacement for the entry script of a common action, identified by the action id
+    EntryActionOverride (u32),
+    /// The fighters replacement for the exit script of a common action, identified by the action id
+    ExitActionOverride (u32),
+    /// A script called by a Goto/Subroutine, identified by its offset
+    Fragment (i32),
+    Section (String),
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum RefTarget {
+    Subaction,
+    Action,
+}
+
+impl Fighter {
+    /// Finds events in the fighters scripts that refer to a subaction or action that does not exist.
+    ///
+    /// Subaction references are compared against the number of subactions of this fighter.
+    /// Action references are compared against the number of common actions plus the number of actions of this fighter.
+    /// Article scripts are not checked as they are not parsed yet.
+    pub fn validate_subaction_references(&self) -> Vec<SubactionRefIssue> {
+        let fighter_data = match self.get_fighter_data() {
+            Some(fighter_data) => fighter_data,
+            None => return vec!(),
+        };
+        let subaction_count = fighter_data.subaction_flags.len();
+        let action_count = self.get_fighter_data_common()
+            .map(|x| x.entry_actions.len() + fighter_data.entry_actions.len());
+
+        let mut issues = vec!();
+        for (location, script) in fighter_scripts(self) {
+            let script_ast = ScriptAst::new(script);
+            script_reference_issues(location, &script_ast, subaction_count, action_count, &mut issues);
+        }
+        issues
+    }
+
+    /// Finds graphic effect events in the fighters scripts that refer to an effect missing from `effect_pac`.
+    ///
+    /// Only effects of the file identified by `EffectPac::file` are checked, effects of other files e.g. the common effect file are ignored.
+    /// Pass the effect file of the vanilla fighter to find mod scripts that use effects only present in the vanilla file.
+    pub fn validate_gfx_references(&self, effect_pac: &EffectPac) -> Vec<GfxRefIssue> {
+        let mut issues = vec!();
+        for (location, script) in fighter_scripts(self) {
+            let script_ast = ScriptAst::new(script);
+            script_gfx_issues(location, &script_ast, effect_pac, &mut issues);
+        }
+        issues
+    }
+}
+
+/// Every script of the fighter along with where it is found, empty when the fighter has no fighter data.
+pub(crate) fn fighter_scripts(fighter: &Fighter) -> Vec<(ScriptLocation, &Script)> {
+    let fighter_data = match fighter.get_fighter_data() {
+        Some(fighter_data) => fighter_data,
+        None => return vec!(),
+    };
+
+    let mut scripts: Vec<(ScriptLocation, &Script)> = vec!();
+    for collection in ScriptCollection::ALL.iter().cloned() {
+        let collection_scripts = match collection {
+            ScriptCollection::Main  => &fighter_data.subaction_main,
+            ScriptCollection::Gfx   => &fighter_data.subaction_gfx,
+            ScriptCollection::Sfx   => &fighter_data.subaction_sfx,
+            ScriptCollection::Other => &fighter_data.subaction_other,
+        };
+        for (index, script) in collection_scripts.iter().enumerate() {
+            scripts.push((ScriptLocation::Subaction { collection, index }, script));
+        }
+    }
+    for (index, script) in fighter_data.entry_actions.iter().enumerate() {
+        scripts.push((ScriptLocation::EntryAction (index), script));
+    }
+    for (index, script) in fighter_data.exit_actions.iter().enumerate() {
+        scripts.push((ScriptLocation::ExitAction (index), script));
+    }
+    for action_override in &fighter_data.entry_action_overrides {
+        scripts.push((ScriptLocation::EntryActionOverride (action_override.action_id), &action_override.script));
+    }
+    for action_override in &fighter_data.exit_action_overrides {
+        scripts.push((ScriptLocation::ExitActionOverride (action_override.action_id), &action_override.script));
+    }
+    if let Some(sakurai) = fighter.get_fighter_sakurai() {
+        for script in &sakurai.fragment_scripts {
+            scripts.push((ScriptLocation::Fragment (script.offset), script));
+        }
+        for section in &sakurai.sections {
+            if let SectionData::Script (section_script) = &section.data {
+                scripts.push((ScriptLocation::Section (section_script.name.clone()), &section_script.script));
+            }
+        }
+    }
+    scripts
+}
+
+/// `action_count` is None when the action count is unknown, in which case actions are not checked.
+fn script_reference_issues(location: ScriptLocation, script: &ScriptAst, subaction_count: usize, action_count: Option<usize>, issues: &mut Vec<SubactionRefIssue>) {
+    visit_events(&script.block, 0, &mut vec!(), &mut |event, path| {
+        let reference = match event {
+            EventAst::ChangeSubaction (index) |
+            EventAst::ChangeSubactionRestartFrame (index) => Some((RefTarget::Subaction, *index, Some(subaction_count))),
+            EventAst::CreateInterrupt (interrupt) => Some((RefTarget::Action, interrupt.action, action_count)),
+            _ => None,
+        };
+        if let Some((target, index, Some(count))) = reference {
+            if index < 0 || index as usize >= count {
+                issues.push(SubactionRefIssue { location: location.clone(), event_path: path.to_vec(), target, index, count });
+            }
+        }
+    });
+}
+
+fn script_gfx_issues(location: ScriptLocation, script: &ScriptAst, effect_pac: &EffectPac, issues: &mut Vec<GfxRefIssue>) {
+    visit_events(&script.block, 0, &mut vec!(), &mut |event, path| {
+        let (file, graphic) = match event {
+            EventAst::GraphicEffect (effect) => effect_pac::split_graphic_id(effect.graphic),
+            EventAst::ExternalGraphicEffect (effect) => (effect.file, effect.graphic),
+            _ => return,
+        };
+        if file == effect_pac.file && !effect_pac.contains(graphic) {
+            issues.push(GfxRefIssue { location: location.clone(), event_path: path.to_vec(), file, graphic });
+        }
+    });
+}
+
+/// Calls `f` with every event of the block and its nested blocks along with the path to the event, see `SubactionRefIssue::event_path`.
+/// `first_index` is the index used for the first event of the block in the event path
+fn visit_events<F>(block: &Block, first_index: usize, path: &mut Vec<usize>, f: &mut F) where F: FnMut(&EventAst, &[usize]) {
+    for (i, event) in block.events.iter().enumerate() {
+        path.push(first_index + i);
+        f(event, path);
+
+        match event {
+            EventAst::ForLoop (for_loop) => visit_events(&for_loop.block, 0, path, f),
+            EventAst::IfStatement (if_statement) => {
+                let then_branch = &if_statement.then_branch;
+                visit_events(then_branch, 0, path, f);
+                if let Some(else_branch) = &if_statement.else_branch {
+                    visit_events(else_branch, then_branch.events.len(), path, f);
+                }
+            }
+            _ => { }
+        }
+
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::{Argument, FixedPoint};
+    use crate::test_util::{TestFighterBuilder, ScriptBuilder};
+
+    fn graphic_effect(script: ScriptBuilder, code: u8, graphic: i32, scalars: usize) -> ScriptBuilder {
+        let mut arguments = vec!(Argument::Value (graphic), Argument::Value (0));
+        arguments.extend((0..scalars).map(|_| Argument::Scalar (FixedPoint::from_frames(0.0))));
+        arguments.push(Argument::Bool (false));
+        script.event(0x11, code, arguments)
+    }
+
+    #[test]
+    fn out_of_range_subaction() {
+        let script = ScriptAst {
+            block: Block { events: vec!(
+                EventAst::Nop,
+                EventAst::ChangeSubaction (3),
+                EventAst::ChangeSubactionRestartFrame (4),
+            )},
+            offset: 0,
+        };
+        let location = ScriptLocation::Subaction { collection: ScriptCollection::Main, index: 2 };
+        let mut issues = vec!();
+        script_reference_issues(location.clone(), &script, 4, None, &mut issues);
+
+        assert_eq!(issues, vec!(SubactionRefIssue {
+            location,
+            event_path: vec!(2),
+            target: RefTarget::Subaction,
+            index: 4,
+            count: 4,
+        }));
+    }
+
+    #[test]
+    fn missing_gfx_reference() {
+        let fighter = TestFighterBuilder::new("Test")
+            .subaction("Wait1", |s| graphic_effect(s, 0x01, 0x000C_0002, 7))
+            .subaction("AttackS4S", |s| {
+                let s = graphic_effect(s, 0x01, 0x000C_0001, 7);
+                let s = graphic_effect(s, 0x02, 0x000C_0007, 7);
+                // the common effect file is not checked
+                graphic_effect(s, 0x1A, 0x0000_0007, 13)
+            })
+            .build();
+
+        let effect_pac = EffectPac::new(0x0C, vec!(2, 0, 1));
+        assert_eq!(fighter.validate_gfx_references(&effect_pac), vec!(GfxRefIssue {
+            location: ScriptLocation::Subaction { collection: ScriptCollection::Main, index: 1 },
+            event_path: vec!(1),
+            file: 0x0C,
+            graphic: 7,
+        }));
+    }
+}
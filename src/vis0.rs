@@ -0,0 +1,209 @@
+use fancy_slice::FancySlice;
+
+use crate::resources;
+
+pub(crate) fn vis0(data: FancySlice) -> Vis0 {
+    let version = data.i32_be(0x8);
+    // Version 4 inserts a user data offset after the resources offset, brawl uses version 3.
+    let shift = if version == 4 { 4 } else { 0 };
+    let resources_offset = data.i32_be(0x10);
+    let string_offset    = data.i32_be(0x14 + shift);
+    let num_frames       = data.u16_be(0x1c + shift);
+    let loop_value       = data.i32_be(0x20 + shift);
+
+    let name = data.str(string_offset as usize).unwrap().to_string();
+
+    let mut tracks = vec!();
+    for resource in resources::resources(data.relative_fancy_slice(resources_offset as usize ..)) {
+        let child_data = data.relative_fancy_slice(resources_offset as usize + resource.data_offset as usize ..);
+        let flags = child_data.u32_be(4);
+
+        let values = if flags & VIS0_FLAG_CONSTANT != 0 {
+            VisibilityValues::Constant (flags & VIS0_FLAG_ENABLED != 0)
+        } else {
+            // One bit per frame, starting from the most significant bit of each big endian word
+            VisibilityValues::Keyframes ((0..num_frames as usize)
+                .map(|frame| child_data.u32_be(VIS0_CHILD_SIZE + frame / 32 * 4) & (0x8000_0000 >> (frame % 32)) != 0)
+                .collect())
+        };
+
+        tracks.push(VisibilityTrack {
+            bone: resource.string,
+            values,
+        });
+    }
+
+    Vis0 {
+        name,
+        num_frames,
+        loop_value: loop_value != 0,
+        tracks,
+    }
+}
+
+const VIS0_FLAG_ENABLED: u32 = 1;
+const VIS0_FLAG_CONSTANT: u32 = 2;
+
+/// A visibility animation, it has the same name as the CHR0 animation it is played alongside.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
+pub struct Vis0 {
+    pub name: String,
+    pub num_frames: u16,
+    pub loop_value: bool,
+    pub tracks: Vec<VisibilityTrack>,
+}
+
+impl Vis0 {
+    /// Returns whether the objects attached to the named bone are visible on the frame, or None if the animation does not contain the bone.
+    pub fn bone_visible(&self, bone_name: &str, frame: usize) -> Option<bool> {
+        self.tracks.iter().find(|x| x.bone == bone_name).map(|x| x.visible(self.loop_value, frame))
+    }
+}
+
+const VIS0_CHILD_SIZE: usize = 0x8;
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
+pub struct VisibilityTrack {
+    pub bone: String,
+    pub values: VisibilityValues,
+}
+
+impl VisibilityTrack {
+    /// Frames past the end of the track wrap around when `loop_value` is set, otherwise they keep the value of the last frame.
+    pub fn visible(&self, loop_value: bool, frame: usize) -> bool {
+        match &self.values {
+            VisibilityValues::Constant (visible) => *visible,
+            VisibilityValues::Keyframes (frames) => {
+                if frames.is_empty() {
+                    true
+                } else if loop_value {
+                    frames[frame % frames.len()]
+                } else {
+                    frames[frame.min(frames.len() - 1)]
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
+pub enum VisibilityValues {
+    /// The bone has this visibility for the whole animation
+    Constant (bool),
+    /// The visibility of the bone on each frame
+    Keyframes (Vec<bool>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a version 3 VIS0 containing the given tracks.
+    /// Each track is its bone name, its flags and the words of its bit array.
+    fn vis0_data(name: &str, num_frames: u16, loop_value: bool, tracks: &[(&str, u32, &[u32])]) -> Vec<u8> {
+        let header_size = 0x24;
+        let resources_size = resources::RESOURCE_HEADER_SIZE + resources::RESOURCE_SIZE * (tracks.len() + 1);
+        let entries_offset = header_size + resources_size;
+
+        let mut entries = vec!();
+        let mut entry_offsets = vec!();
+        for (_, flags, words) in tracks {
+            entry_offsets.push(entries_offset + entries.len());
+            entries.extend(&0i32.to_be_bytes()); // string offset, filled in below
+            entries.extend(&flags.to_be_bytes());
+            for word in words.iter() {
+                entries.extend(&word.to_be_bytes());
+            }
+        }
+
+        let strings_offset = entries_offset + entries.len();
+        let mut strings = vec!();
+        let mut string_offsets = vec!();
+        for string in std::iter::once(name).chain(tracks.iter().map(|x| x.0)) {
+            string_offsets.push(strings_offset + strings.len());
+            strings.extend(string.as_bytes());
+            strings.push(0);
+        }
+
+        let mut data = vec!();
+        data.extend(b"VIS0");
+        data.extend(&0i32.to_be_bytes()); // size
+        data.extend(&3i32.to_be_bytes()); // version
+        data.extend(&0i32.to_be_bytes()); // bres offset
+        data.extend(&(header_size as i32).to_be_bytes());
+        data.extend(&(string_offsets[0] as i32).to_be_bytes());
+        data.extend(&0i32.to_be_bytes()); // orig path offset
+        data.extend(&num_frames.to_be_bytes());
+        data.extend(&(tracks.len() as u16).to_be_bytes());
+        data.extend(&(loop_value as i32).to_be_bytes());
+
+        data.extend(&(resources_size as i32).to_be_bytes());
+        data.extend(&(tracks.len() as i32).to_be_bytes());
+        data.extend(&[0; resources::RESOURCE_SIZE]); // dummy child
+        for (i, entry_offset) in entry_offsets.iter().enumerate() {
+            data.extend(&[0; 8]); // id, flag, left index and right index
+            data.extend(&((string_offsets[i + 1] - header_size) as i32).to_be_bytes());
+            data.extend(&((entry_offset - header_size) as i32).to_be_bytes());
+        }
+
+        for (i, entry_offset) in entry_offsets.iter().enumerate() {
+            let string_offset = (string_offsets[i + 1] - entry_offset) as i32;
+            let index = entry_offset - entries_offset;
+            entries[index..index + 4].copy_from_slice(&string_offset.to_be_bytes());
+        }
+        data.extend(entries);
+        data.extend(strings);
+        data
+    }
+
+    #[test]
+    fn constant_and_keyframed_tracks() {
+        let data = vis0_data("AttackS4S", 36, false, &[
+            ("HaveN", VIS0_FLAG_CONSTANT, &[]),
+            ("SwordN", 0, &[0x0000_FFFF, 0xA000_0000]),
+        ]);
+        let vis0 = vis0(FancySlice::new(&data));
+
+        assert_eq!(vis0.name, "AttackS4S");
+        assert_eq!(vis0.num_frames, 36);
+        assert!(!vis0.loop_value);
+        assert_eq!(vis0.tracks.len(), 2);
+
+        assert_eq!(vis0.tracks[0].bone, "HaveN");
+        assert_eq!(vis0.tracks[0].values, VisibilityValues::Constant (false));
+
+        assert_eq!(vis0.tracks[1].bone, "SwordN");
+        let mut expected = vec!(false; 36);
+        for visible in &mut expected[16..32] {
+            *visible = true;
+        }
+        expected[32] = true;
+        expected[34] = true;
+        assert_eq!(vis0.tracks[1].values, VisibilityValues::Keyframes (expected));
+
+        assert_eq!(vis0.bone_visible("HaveN", 20), Some(false));
+        assert_eq!(vis0.bone_visible("SwordN", 15), Some(false));
+        assert_eq!(vis0.bone_visible("SwordN", 16), Some(true));
+        assert_eq!(vis0.bone_visible("SwordN", 34), Some(true));
+        assert_eq!(vis0.bone_visible("SwordN", 35), Some(false));
+        assert_eq!(vis0.bone_visible("SwordN", 100), Some(false));
+        assert_eq!(vis0.bone_visible("TopN", 0), None);
+    }
+
+    #[test]
+    fn constant_visible_and_looping() {
+        let data = vis0_data("Wait1", 4, true, &[
+            ("HaveN", VIS0_FLAG_CONSTANT | VIS0_FLAG_ENABLED, &[]),
+            ("SwordN", 0, &[0x4000_0000]),
+        ]);
+        let vis0 = vis0(FancySlice::new(&data));
+
+        assert!(vis0.loop_value);
+        assert_eq!(vis0.tracks[0].values, VisibilityValues::Constant (true));
+        assert_eq!(vis0.tracks[1].values, VisibilityValues::Keyframes (vec!(false, true, false, false)));
+        assert_eq!(vis0.bone_visible("SwordN", 5), Some(true));
+        assert_eq!(vis0.bone_visible("SwordN", 6), Some(false));
+    }
+}
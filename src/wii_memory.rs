@@ -1,9 +1,22 @@
 use byteorder::{BigEndian, ByteOrder};
 use fancy_slice::FancySlice;
 
+use crate::address::{LoadedRegion, RamAddress};
+
 pub struct WiiMemory {
     mem1: Vec<u8>,
     mem2: Vec<u8>,
+    writes: Vec<CodeWrite>,
+}
+
+/// A range of memory written to by a single WiiRD code.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct CodeWrite {
+    pub address: u32,
+    pub length: u32,
+    /// Byte offset of the code within the codeset
+    pub code_offset: usize,
 }
 
 impl WiiMemory {
@@ -11,10 +24,32 @@ impl WiiMemory {
         WiiMemory {
             mem1: vec!(0; 0x180_0000),
             mem2: vec!(0; 0x400_0000),
+            writes: vec!(),
         }
     }
 
-    pub fn write_u8(&mut self, address: usize, value: u8) {
+    /// Every write made by the codeset, in the order they were executed.
+    /// Only filled in by `wiird_runner::run`, writes made directly with the `write_*` methods are not recorded.
+    pub fn writes(&self) -> &[CodeWrite] {
+        &self.writes
+    }
+
+    /// Returns the codeset offset of the last code that wrote to `address`
+    pub fn writer(&self, address: RamAddress) -> Option<usize> {
+        let address = address.into_inner();
+        self.writes.iter().rev()
+            .find(|x| address >= x.address && address - x.address < x.length)
+            .map(|x| x.code_offset)
+    }
+
+    pub(crate) fn record_write(&mut self, address: u32, length: u32, code_offset: usize) {
+        if length > 0 {
+            self.writes.push(CodeWrite { address, length, code_offset });
+        }
+    }
+
+    pub fn write_u8(&mut self, address: RamAddress, value: u8) {
+        let address = address.into_inner() as usize;
         if address >= 0x8000_0000 && address < 0x8180_0000 {
             self.mem1[address - 0x8000_0000] = value;
         }
@@ -26,7 +61,8 @@ impl WiiMemory {
         }
     }
 
-    pub fn write_u16(&mut self, address: usize, value: u16) {
+    pub fn write_u16(&mut self, address: RamAddress, value: u16) {
+        let address = address.into_inner() as usize;
         if address >= 0x8000_0000 && address < 0x8180_0000 {
             BigEndian::write_u16(&mut self.mem1[address - 0x8000_0000 ..], value);
         }
@@ -38,7 +74,8 @@ impl WiiMemory {
         }
     }
 
-    pub fn write_u32(&mut self, address: usize, value: u32) {
+    pub fn write_u32(&mut self, address: RamAddress, value: u32) {
+        let address = address.into_inner() as usize;
         if address >= 0x8000_0000 && address < 0x8180_0000 {
             BigEndian::write_u32(&mut self.mem1[address - 0x8000_0000 ..], value);
         }
@@ -50,7 +87,8 @@ impl WiiMemory {
         }
     }
 
-    pub fn read_u8(&self, address: usize) -> u8 {
+    pub fn read_u8(&self, address: RamAddress) -> u8 {
+        let address = address.into_inner() as usize;
         if address >= 0x8000_0000 && address < 0x8180_0000 {
             return self.mem1[address - 0x8000_0000];
         } else if address >= 0x9000_0000 && address < 0x9400_0000 {
@@ -61,7 +99,8 @@ impl WiiMemory {
         }
     }
 
-    pub fn read_u16(&self, address: usize) -> u16 {
+    pub fn read_u16(&self, address: RamAddress) -> u16 {
+        let address = address.into_inner() as usize;
         if address >= 0x8000_0000 && address < 0x8180_0000 {
             BigEndian::read_u16(&self.mem1[address - 0x8000_0000 ..])
         } else if address >= 0x9000_0000 && address < 0x9400_0000 {
@@ -72,7 +111,8 @@ impl WiiMemory {
         }
     }
 
-    pub fn read_u32(&self, address: usize) -> u32 {
+    pub fn read_u32(&self, address: RamAddress) -> u32 {
+        let address = address.into_inner() as usize;
         if address >= 0x8000_0000 && address < 0x8180_0000 {
             BigEndian::read_u32(&self.mem1[address - 0x8000_0000 ..])
         } else if address >= 0x9000_0000 && address < 0x9400_0000 {
@@ -83,7 +123,8 @@ impl WiiMemory {
         }
     }
 
-    pub fn read_f32(&self, address: usize) -> f32 {
+    pub fn read_f32(&self, address: RamAddress) -> f32 {
+        let address = address.into_inner() as usize;
         if address >= 0x8000_0000 && address < 0x8180_0000 {
             BigEndian::read_f32(&self.mem1[address - 0x8000_0000 ..])
         } else if address >= 0x9000_0000 && address < 0x9400_0000 {
@@ -94,7 +135,8 @@ impl WiiMemory {
         }
     }
 
-    pub fn buffer_from(&self, address: usize) -> &[u8] {
+    pub fn buffer_from(&self, address: RamAddress) -> &[u8] {
+        let address = address.into_inner() as usize;
         if address >= 0x8000_0000 && address < 0x8180_0000 {
             &self.mem1[address - 0x8000_0000..]
         } else if address >= 0x9000_0000 && address < 0x9400_0000 {
@@ -104,7 +146,8 @@ impl WiiMemory {
             &[]
         }
     }
-    pub fn fancy_slice_from(&self, address: usize) -> FancySlice {
+    pub fn fancy_slice_from(&self, address: RamAddress) -> FancySlice<'_> {
+        let address = address.into_inner() as usize;
         let slice = if address >= 0x8000_0000 && address < 0x8180_0000 {
             &self.mem1[address - 0x8000_0000..]
         } else if address >= 0x9000_0000 && address < 0x9400_0000 {
@@ -116,3 +159,62 @@ impl WiiMemory {
         FancySlice::new(slice)
     }
 }
+
+/// Read access to memory that may only be partially known.
+///
+/// Used to statically evaluate WiiRD if statements, see `wiird::evaluate_condition`.
+/// Reads return None for addresses whose value is not known ahead of time.
+pub trait MemoryView {
+    /// Returns the byte at `address` or None if it is not known
+    fn read_u8(&self, address: RamAddress) -> Option<u8>;
+
+    /// Returns the big endian u16 at `address` or None if any of its bytes are not known
+    fn read_u16(&self, address: RamAddress) -> Option<u16> {
+        Some(u16::from_be_bytes([self.read_u8(address)?, self.read_u8(address.checked_add(1)?)?]))
+    }
+
+    /// Returns the big endian u32 at `address` or None if any of its bytes are not known
+    fn read_u32(&self, address: RamAddress) -> Option<u32> {
+        Some(u32::from_be_bytes([
+            self.read_u8(address)?,
+            self.read_u8(address.checked_add(1)?)?,
+            self.read_u8(address.checked_add(2)?)?,
+            self.read_u8(address.checked_add(3)?)?,
+        ]))
+    }
+}
+
+/// Treats every address in MEM1 and MEM2 as known, memory that was never written to reads as 0.
+/// This is only true of the memory simulated by `wiird_runner`, not of the memory of a running game.
+impl MemoryView for WiiMemory {
+    fn read_u8(&self, address: RamAddress) -> Option<u8> {
+        let address = address.into_inner() as usize;
+        if (0x8000_0000..0x8180_0000).contains(&address) {
+            Some(self.mem1[address - 0x8000_0000])
+        } else if (0x9000_0000..0x9400_0000).contains(&address) {
+            Some(self.mem2[address - 0x9000_0000])
+        } else {
+            None
+        }
+    }
+}
+
+/// A file loaded into RAM, only the addresses within the file are known.
+///
+/// For example the buffer passed to `wiird_runner::run`, so the rest of RAM which depends on the state of the game is treated as unknown.
+pub struct BufferView<'a> {
+    buffer: &'a [u8],
+    region: LoadedRegion,
+}
+
+impl<'a> BufferView<'a> {
+    pub fn new(buffer: &'a [u8], ram_base: RamAddress) -> BufferView<'a> {
+        BufferView { buffer, region: LoadedRegion::new(ram_base, buffer.len() as u32) }
+    }
+}
+
+impl<'a> MemoryView for BufferView<'a> {
+    fn read_u8(&self, address: RamAddress) -> Option<u8> {
+        self.region.to_file_offset(address).and_then(|x| self.buffer.get(x.into_inner() as usize).cloned())
+    }
+}
@@ -1,4 +1,5 @@
 #[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum WiiPixelFormat {
     I4,
     I8,
@@ -48,6 +49,7 @@ impl WiiPixelFormat {
 }
 
 #[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum WiiPixelFormat2 {
     TfI4,
     TfI8,
@@ -153,6 +155,7 @@ impl WiiPixelFormat2 {
 }
 
 #[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum WiiPaletteFormat {
     IA8,
     RGB565,
@@ -1,6 +1,7 @@
 use std::fs::File;
 use std::fs;
 use std::io::{Read, ErrorKind};
+use std::ops::Range;
 use std::path::Path;
 
 use byteorder::{BigEndian, ReadBytesExt};
@@ -8,6 +9,9 @@ use byteorder::{BigEndian, ReadBytesExt};
 use failure::Error;
 use failure::bail;
 
+use crate::address::{LoadedRegion, RamAddress};
+use crate::wii_memory::MemoryView;
+
 pub fn wiird_load_txt(codeset_path: &Path) -> Result<WiiRDBlock, Error> {
     match fs::read_to_string(codeset_path) {
         Ok(text) => {
@@ -68,26 +72,480 @@ pub fn wiird_load_gct(codeset_path: &Path) -> Result<WiiRDBlock, Error> {
     Ok(wiird_codes(&data[8..])) // Skip the header
 }
 
+/// Converts a text codeset into a gct codeset.
+///
+/// The text codeset consists of codes separated by blank lines.
+/// The first line of a code is its name, the following lines are either code lines or comments.
+/// Code lines prefixed with `*` are enabled, code lines without the prefix are disabled.
+/// Only enabled code lines are written to the gct.
+///
+/// Every enabled code line is written as is, even when the code type is not understood by `wiird_codes`.
+/// The returned report contains a warning for each of these codes.
+pub fn convert_txt_to_gct(txt_path: &Path, gct_path: &Path) -> Result<ConversionReport, Error> {
+    let text = match fs::read_to_string(txt_path) {
+        Ok(text) => text,
+        Err(err) => {
+            match err.kind() {
+                ErrorKind::InvalidData => {
+                    bail!("Failed to read WiiRD codeset {:?}: Please reencode the file as utf8.", txt_path);
+                }
+                _ => bail!("Cannot read WiiRD codeset {:?}: {:?}", txt_path, err),
+            }
+        }
+    };
+
+    let mut gct = GCT_HEADER.to_vec();
+    let mut report = ConversionReport { codes: vec!(), warnings: vec!() };
+    for code in txt_codes(&text)? {
+        if !code.enabled {
+            continue;
+        }
+
+        if let Some(code_type) = unmodeled_code_type(&code.data) {
+            report.warnings.push(format!(
+                "Code {:?} (lines {}-{}) contains code type 0x{:02x} which is not understood, it was passed through as is",
+                code.name, code.line_start, code.line_end, code_type
+            ));
+        }
+
+        gct.extend_from_slice(&code.data);
+        report.codes.push(ConvertedCode {
+            name:         code.name,
+            comments:     code.comments,
+            line_start:   code.line_start,
+            line_end:     code.line_end,
+            encoded_size: code.data.len(),
+        });
+    }
+    gct.extend_from_slice(&[0xF0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+
+    if let Err(err) = fs::write(gct_path, &gct) {
+        bail!("Cannot write WiiRD codeset {:?}: {}", gct_path, err);
+    }
+
+    Ok(report)
+}
+
+/// Describes the result of `convert_txt_to_gct`
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct ConversionReport {
+    /// Every code that was written to the gct
+    pub codes: Vec<ConvertedCode>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct ConvertedCode {
+    pub name: String,
+    /// Lines of the code that are neither the name nor code lines
+    pub comments: Vec<String>,
+    /// Line number of the name, starting at 1
+    pub line_start: usize,
+    /// Line number of the last line of the code, starting at 1
+    pub line_end: usize,
+    /// Number of bytes written to the gct
+    pub encoded_size: usize,
+}
+
+struct TxtCode {
+    name: String,
+    comments: Vec<String>,
+    enabled: bool,
+    line_start: usize,
+    line_end: usize,
+    data: Vec<u8>,
+}
+
+fn txt_codes(text: &str) -> Result<Vec<TxtCode>, Error> {
+    let mut codes = vec!();
+    let mut current: Option<TxtCode> = None;
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            codes.extend(current.take());
+            continue;
+        }
+
+        let enabled = trimmed.starts_with('*');
+        let hex_string = trimmed.replace("*", "").replace(" ", "");
+        let is_code_line = enabled || (hex_string.len() == 16 && hex_string.chars().all(|x| x.is_ascii_hexdigit()));
+
+        if let Some(code) = &mut current {
+            code.line_end = line_number;
+            if is_code_line {
+                if hex_string.chars().any(|x| !x.is_ascii_hexdigit()) {
+                    bail!("line {} contains a non-hex character in a code", line_number);
+                }
+                if hex_string.len() != 16 {
+                    bail!("line {} contains a code that does not have 16 digits", line_number);
+                }
+
+                if enabled {
+                    code.enabled = true;
+                    push_code_line(&hex_string, &mut code.data);
+                }
+            } else {
+                code.comments.push(trimmed.to_string());
+            }
+        } else if is_code_line {
+            bail!("line {} contains a code without a name", line_number);
+        } else {
+            current = Some(TxtCode {
+                name:       trimmed.to_string(),
+                comments:   vec!(),
+                enabled:    false,
+                line_start: line_number,
+                line_end:   line_number,
+                data:       vec!(),
+            });
+        }
+    }
+    codes.extend(current.take());
+
+    Ok(codes)
+}
+
+/// Appends the bytes of a code line to `data`, `hex_string` must consist of 16 hex digits.
+fn push_code_line(hex_string: &str, data: &mut Vec<u8>) {
+    let hex_chars: Vec<_> = hex_string.chars().collect();
+    for i in 0..8 {
+        let first  = hex_chars[i * 2    ].to_digit(16).unwrap() as u8;
+        let second = hex_chars[i * 2 + 1].to_digit(16).unwrap() as u8;
+        data.push((first << 4) | second);
+    }
+}
+
+/// A code from a Dolphin ini
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct NamedCode {
+    /// The name without the leading `$`, usually includes the author e.g. `Fix ledge grab [author]`
+    pub name: String,
+    /// The notes of the code, stored on lines starting with `*`
+    pub comments: Vec<String>,
+    pub enabled: bool,
+    pub data: Vec<u8>,
+}
+
+/// Reads the gecko codes of a Dolphin game ini e.g. `RSBE01.ini`.
+///
+/// Codes are read from the `[Gecko]` section.
+/// A code is enabled when its name is listed in the `[Gecko_Enabled]` section and not in the `[Gecko_Disabled]` section.
+/// Names are matched ignoring case and differences in whitespace.
+/// Malformed lines are skipped with a warning.
+pub fn load_dolphin_ini(path: &Path) -> Result<Vec<NamedCode>, Error> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) => {
+            match err.kind() {
+                ErrorKind::InvalidData => {
+                    bail!("Failed to read Dolphin ini {:?}: Please reencode the file as utf8.", path);
+                }
+                _ => bail!("Cannot read Dolphin ini {:?}: {:?}", path, err),
+            }
+        }
+    };
+    Ok(dolphin_ini_codes(&text))
+}
+
+fn dolphin_ini_codes(text: &str) -> Vec<NamedCode> {
+    let mut codes: Vec<NamedCode> = vec!();
+    let mut enabled_names = vec!();
+    let mut disabled_names = vec!();
+    let mut section = String::new();
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            section = trimmed[1..trimmed.len() - 1].to_lowercase();
+            continue;
+        }
+
+        match section.as_str() {
+            "gecko" => {
+                if let Some(name) = trimmed.strip_prefix('$') {
+                    codes.push(NamedCode {
+                        name:     name.trim().to_string(),
+                        comments: vec!(),
+                        enabled:  false,
+                        data:     vec!(),
+                    });
+                } else if let Some(code) = codes.last_mut() {
+                    if let Some(comment) = trimmed.strip_prefix('*') {
+                        code.comments.push(comment.trim().to_string());
+                    } else {
+                        let hex_string = trimmed.replace(" ", "");
+                        if hex_string.len() == 16 && hex_string.chars().all(|x| x.is_ascii_hexdigit()) {
+                            push_code_line(&hex_string, &mut code.data);
+                        } else {
+                            warn!("Skipped line {} of the Dolphin ini as it is not a code line of 16 hex digits", line_number);
+                        }
+                    }
+                } else {
+                    warn!("Skipped line {} of the Dolphin ini as it contains a code without a name", line_number);
+                }
+            }
+            "gecko_enabled" => if let Some(name) = trimmed.strip_prefix('$') {
+                enabled_names.push(normalize_code_name(name));
+            }
+            "gecko_disabled" => if let Some(name) = trimmed.strip_prefix('$') {
+                disabled_names.push(normalize_code_name(name));
+            }
+            _ => { }
+        }
+    }
+
+    for code in &mut codes {
+        let name = normalize_code_name(&code.name);
+        code.enabled = enabled_names.contains(&name) && !disabled_names.contains(&name);
+    }
+
+    codes
+}
+
+/// Dolphin and users are inconsistent with the case and whitespace of code names between sections
+fn normalize_code_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// Writes the codes to a Dolphin game ini.
+///
+/// When the file exists only its `[Gecko]` and `[Gecko_Enabled]` sections are replaced, all other sections are kept as they are.
+/// Enabled codes are also removed from the `[Gecko_Disabled]` section, otherwise Dolphin would still treat them as disabled.
+pub fn write_dolphin_ini(codes: &[NamedCode], path: &Path) -> Result<(), Error> {
+    let existing = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+        Err(err) => bail!("Cannot read Dolphin ini {:?}: {}", path, err),
+    };
+    if let Err(err) = fs::write(path, dolphin_ini_text(&existing, codes)?) {
+        bail!("Cannot write Dolphin ini {:?}: {}", path, err);
+    }
+    Ok(())
+}
+
+/// Returns `existing` with its gecko sections replaced by `codes`
+fn dolphin_ini_text(existing: &str, codes: &[NamedCode]) -> Result<String, Error> {
+    let mut gecko = String::from("[Gecko]\n");
+    for code in codes {
+        if code.data.len() % 8 != 0 {
+            bail!("Code {:?} has {} bytes of data which is not a whole number of code lines", code.name, code.data.len());
+        }
+
+        gecko.push_str(&format!("${}\n", code.name));
+        for line in code.data.chunks(8) {
+            gecko.push_str(&format!("{:02X}{:02X}{:02X}{:02X} {:02X}{:02X}{:02X}{:02X}\n", line[0], line[1], line[2], line[3], line[4], line[5], line[6], line[7]));
+        }
+        for comment in &code.comments {
+            gecko.push_str(&format!("*{}\n", comment));
+        }
+    }
+
+    let mut gecko_enabled = String::from("[Gecko_Enabled]\n");
+    for code in codes.iter().filter(|x| x.enabled) {
+        gecko_enabled.push_str(&format!("${}\n", code.name));
+    }
+    let enabled_names: Vec<String> = codes.iter().filter(|x| x.enabled).map(|x| normalize_code_name(&x.name)).collect();
+
+    let mut text = String::new();
+    let mut section = String::new();
+    let mut wrote_gecko = false;
+    let mut wrote_gecko_enabled = false;
+    for line in existing.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            section = trimmed[1..trimmed.len() - 1].to_lowercase();
+            match section.as_str() {
+                "gecko" if !wrote_gecko => {
+                    text.push_str(&gecko);
+                    wrote_gecko = true;
+                }
+                "gecko_enabled" if !wrote_gecko_enabled => {
+                    text.push_str(&gecko_enabled);
+                    wrote_gecko_enabled = true;
+                }
+                "gecko" | "gecko_enabled" => { }
+                _ => {
+                    text.push_str(line);
+                    text.push('\n');
+                }
+            }
+            continue;
+        }
+
+        let keep = match section.as_str() {
+            "gecko" | "gecko_enabled" => false,
+            "gecko_disabled" => match trimmed.strip_prefix('$') {
+                Some(name) => !enabled_names.contains(&normalize_code_name(name)),
+                None => true,
+            },
+            _ => true,
+        };
+        if keep {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+
+    if !wrote_gecko {
+        text.push_str(&gecko);
+    }
+    if !wrote_gecko_enabled {
+        text.push_str(&gecko_enabled);
+    }
+    Ok(text)
+}
+
+/// Concatenates the data of the enabled codes, the result can be passed to `wiird_codes` or `wiird_runner::process`.
+pub fn enabled_codes_data(codes: &[NamedCode]) -> Vec<u8> {
+    let mut data = vec!();
+    for code in codes.iter().filter(|x| x.enabled) {
+        data.extend_from_slice(&code.data);
+    }
+    data
+}
+
+/// Returns the first code type in data that `process_block` does not understand
+fn unmodeled_code_type(data: &[u8]) -> Option<u8> {
+    let mut offset = 0;
+    while offset + 8 <= data.len() {
+        let code = if data[offset] >= 0xF0 { data[offset] } else { data[offset] & 0b11101110 };
+        let value = (&data[offset + 4..]).read_u32::<BigEndian>().unwrap() as usize;
+        match code {
+            0x06 => offset += 8 + value + (8 - value % 8) % 8,
+            0x08 => offset += 16,
+            0xC0 | 0xC2 => offset += 8 + value * 8,
+            0x00 | 0x02 | 0x04 | 0x20 | 0x22 | 0x24 | 0x26 | 0x28 | 0x2A | 0x2C | 0x2E |
+            0x40 | 0x42 | 0x44 | 0x46 | 0x48 | 0x4A | 0x4C | 0x4E |
+            0x60 | 0x62 | 0x64 | 0x66 | 0x68 |
+            0x80 | 0x82 | 0x84 | 0x86 | 0x88 | 0x8A | 0x8C | 0xE0 | 0xE2 => offset += 8,
+            _ => return Some(data[offset]),
+        }
+    }
+    None
+}
+
 pub fn wiird_codes(data: &[u8]) -> WiiRDBlock {
+    wiird_codes_with_spans(data).0
+}
+
+/// Same as `wiird_codes` but also returns the bytes of `data` that each code was parsed from, including codes in nested blocks.
+///
+/// The span of an if statement includes its nested codes and the code that terminates it, a repeat includes its body and the codes that start and execute it.
+/// Codes that are not stored in the data have an empty span at the end of the code they were created for e.g. the ResetAddressHigh following an if statement, which is read from the terminating code.
+/// The F0 end of codes line and the data sections skipped by an unconditional goto are not part of any span,
+/// so otherwise the spans of the root block cover `data` in order without gaps or overlaps.
+pub fn wiird_codes_with_spans(data: &[u8]) -> (WiiRDBlock, Vec<(CodePath, Range<usize>)>) {
+    let (block, spans, _) = parse_codes(data);
+    (block, spans)
+}
+
+/// The codes of a gct codeset along with any data following its terminator, see `wiird_codes_with_trailing`.
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct WiiRDParseOutput {
+    pub block: WiiRDBlock,
+    /// Codesets that were appended after the terminator, each starting with its own gct header
+    pub concatenated: Vec<WiiRDBlock>,
+    /// The bytes following the terminator of the last codeset e.g. padding or a checksum added by a tool
+    pub trailing: Vec<u8>,
+}
+
+/// Same as `wiird_codes` but also returns what follows the F0 end of codes line.
+///
+/// If the data following the terminator starts with the gct header, it is parsed as another codeset and included in `concatenated`.
+/// `trailing` is empty when the codeset has no terminator or could not be parsed to the end.
+pub fn wiird_codes_with_trailing(data: &[u8]) -> WiiRDParseOutput {
+    let (block, _, end) = parse_codes(data);
+    let mut concatenated = vec!();
+    let mut trailing = end.map(|end| &data[end..]).unwrap_or_default();
+    while trailing.starts_with(&GCT_HEADER) {
+        let (block, _, end) = parse_codes(&trailing[GCT_HEADER.len()..]);
+        concatenated.push(block);
+        trailing = end.map(|end| &trailing[GCT_HEADER.len() + end..]).unwrap_or_default();
+    }
+    WiiRDParseOutput { block, concatenated, trailing: trailing.to_vec() }
+}
+
+/// The magic number at the start of every gct codeset
+const GCT_HEADER: [u8; 8] = [0x00, 0xD0, 0xC0, 0xDE, 0x00, 0xD0, 0xC0, 0xDE];
+
+/// The span of every code, as returned by `wiird_codes_with_spans`
+type CodePathSpans = Vec<(CodePath, Range<usize>)>;
+
+/// Returns the codes, the spans of the codes and the offset following the terminator
+fn parse_codes(data: &[u8]) -> (WiiRDBlock, CodePathSpans, Option<usize>) {
     // TODO: Extend the length of data to avoid panics due to out of bounds accesses.
 
-    if let ProcessedBlock::Finished(block) = process_block(data, false) {
-        block
+    if let ProcessedBlock::Finished (block, spans, end) = process_block(data, 0, false) {
+        let (block, spans) = structure_repeats(block, spans);
+        let mut paths = vec!();
+        flatten_spans(&spans, &mut vec!(), &mut paths);
+        (block, paths, end)
     } else {
         error!("A block in the script did not terminate, or a termination occured without a block.");
-        WiiRDBlock { codes: vec!() }
+        (WiiRDBlock { codes: vec!() }, vec!(), None)
+    }
+}
+
+/// The bytes a code was parsed from, parallel to the codes of a `WiiRDBlock`
+struct CodeSpan {
+    range: Range<usize>,
+    /// Spans of the codes in the block of an if statement or repeat
+    nested: Vec<CodeSpan>,
+}
+
+/// Gives the codes pushed since the last call the span `range`.
+/// The first gets `range` and `nested`, any others were created from the same bytes and get an empty span at its end.
+fn record_spans(codes: &[WiiRDCode], spans: &mut Vec<CodeSpan>, range: Range<usize>, nested: &mut Vec<CodeSpan>) {
+    let mut range = range;
+    while spans.len() < codes.len() {
+        spans.push(CodeSpan { range: range.clone(), nested: std::mem::take(nested) });
+        range = range.end .. range.end;
+    }
+}
+
+fn flatten_spans(spans: &[CodeSpan], path: &mut Vec<usize>, output: &mut Vec<(CodePath, Range<usize>)>) {
+    for (i, span) in spans.iter().enumerate() {
+        path.push(i);
+        output.push((CodePath (path.clone()), span.range.clone()));
+        flatten_spans(&span.nested, path, output);
+        path.pop();
     }
 }
 
-fn process_block(data: &[u8], is_nested: bool) -> ProcessedBlock {
+/// `start` is the offset of `data` in the data passed to `wiird_codes_with_spans`
+fn process_block(data: &[u8], start: usize, is_nested: bool) -> ProcessedBlock {
     let mut codes = vec!();
+    let mut spans = vec!();
+    let mut nested_spans = vec!();
     let mut offset = 0;
+    let mut code_start = 0;
+    let mut end = None;
     while offset < data.len() {
+        record_spans(&codes, &mut spans, start + code_start .. start + offset, &mut nested_spans);
+        code_start = offset;
+
         // Not every code type uses this, but its safe to just create these for if we need them.
         let use_base_address = data[offset] & 0b00010000 == 0;
         let address = (&data[offset ..]).read_u32::<BigEndian>().unwrap() & 0x1FFFFFF;
 
-        let code = data[offset] & 0b11101110;
+        if data[offset] == 0xF0 {
+            // End of codes, anything after this is not part of the codeset
+            end = Some((offset + 8).min(data.len()));
+            break;
+        }
+
+        // Bit 0x10 selects the pointer address instead of the base address e.g. 0x90 is 0x80 using the pointer address.
+        // Bit 0x01 is the 25th bit of the address for codes that take an address and is always unset for the other codes.
+        // F codes dont use the base address or pointer address bits
+        let code = if data[offset] >= 0xF0 { data[offset] } else { data[offset] & 0b11101110 };
         match code {
             0x00 => {
                 let value = data[offset + 7];
@@ -158,11 +616,12 @@ fn process_block(data: &[u8], is_nested: bool) -> ProcessedBlock {
                 };
                 offset += 8;
 
-                match process_block(&data[offset..], true) {
-                    ProcessedBlock::EndIf { count, then_branch, bytes_processed, reset_base_address_high, reset_pointer_address_high } => {
+                match process_block(&data[offset..], start + offset, true) {
+                    ProcessedBlock::EndIf { count, then_branch, spans: then_spans, bytes_processed, reset_base_address_high, reset_pointer_address_high } => {
                         offset += bytes_processed;
                         let else_branch = None;
                         codes.push(WiiRDCode::IfStatement { test, then_branch, else_branch, reset_base_address_high, reset_pointer_address_high });
+                        nested_spans = then_spans;
 
                         let count = match count {
                             EndIfCount::Infinite   => EndIfCount::Infinite,
@@ -174,8 +633,9 @@ fn process_block(data: &[u8], is_nested: bool) -> ProcessedBlock {
                         };
 
                         if multi_endif && is_nested {
+                            record_spans(&codes, &mut spans, start + code_start .. start + offset, &mut nested_spans);
                             let then_branch = WiiRDBlock { codes };
-                            return ProcessedBlock::EndIf { count, then_branch, bytes_processed: offset, reset_base_address_high, reset_pointer_address_high };
+                            return ProcessedBlock::EndIf { count, then_branch, spans, bytes_processed: offset, reset_base_address_high, reset_pointer_address_high };
                         }
                         else {
                             codes.push(WiiRDCode::ResetAddressHigh { reset_base_address_high, reset_pointer_address_high });
@@ -184,7 +644,7 @@ fn process_block(data: &[u8], is_nested: bool) -> ProcessedBlock {
                     _ => {
                         // Need to terminate as we have no idea how many bytes were meant to be processed
                         error!("IfStatement {} did not terminate", code);
-                        return ProcessedBlock::Finished (WiiRDBlock { codes: vec!() })
+                        return ProcessedBlock::Finished (WiiRDBlock { codes: vec!() }, vec!(), None)
                     }
                 };
             }
@@ -328,7 +788,7 @@ fn process_block(data: &[u8], is_nested: bool) -> ProcessedBlock {
             }
             0x60 => {
                 let count = (&data[offset + 2..]).read_u16::<BigEndian>().unwrap();
-                let block_id = data[offset + 7];
+                let block_id = data[offset + 7] & 0xF;
                 codes.push(WiiRDCode::SetRepeat { count, block_id });
                 offset += 8;
             }
@@ -344,7 +804,7 @@ fn process_block(data: &[u8], is_nested: bool) -> ProcessedBlock {
                     0x20 => JumpFlag::Always,
                     flag => {
                         error!("Unknown jump flag '{}' in return", flag);
-                        return ProcessedBlock::Finished (WiiRDBlock { codes });
+                        return ProcessedBlock::Finished (WiiRDBlock { codes }, spans, None);
                     }
                 };
                 let block_id = data[offset + 7] & 0xF;
@@ -358,7 +818,7 @@ fn process_block(data: &[u8], is_nested: bool) -> ProcessedBlock {
                     0x20 => JumpFlag::Always,
                     flag => {
                         error!("Unknown jump flag '{}' in goto", flag);
-                        return ProcessedBlock::Finished (WiiRDBlock { codes });
+                        return ProcessedBlock::Finished (WiiRDBlock { codes }, spans, None);
                     }
                 };
 
@@ -384,7 +844,7 @@ fn process_block(data: &[u8], is_nested: bool) -> ProcessedBlock {
                     0x20 => JumpFlag::Always,
                     flag => {
                         error!("Unknown jump flag '{}' in subroutine", flag);
-                        return ProcessedBlock::Finished (WiiRDBlock { codes });
+                        return ProcessedBlock::Finished (WiiRDBlock { codes }, spans, None);
                     }
                 };
                 let offset_lines = (&data[offset + 2..]).read_i16::<BigEndian>().unwrap();
@@ -408,19 +868,38 @@ fn process_block(data: &[u8], is_nested: bool) -> ProcessedBlock {
                 offset += 8;
             }
             0x82 => {
+                let value_size = data[offset + 1] >> 4;
+                let add_bool = data[offset + 1] & 1 != 0;
                 let register = data[offset + 3] & 0xF;
                 let mem_address = (&data[offset + 4..]).read_u32::<BigEndian>().unwrap();
-                codes.push(WiiRDCode::LoadGeckoRegister { register, mem_address });
+
+                let add_mem_address = match (add_bool, use_base_address) {
+                    (true, true)  => AddAddress::BaseAddress,
+                    (true, false) => AddAddress::PointerAddress,
+                    (false, _)    => AddAddress::None,
+                };
+
+                codes.push(WiiRDCode::LoadGeckoRegister { value_size, add_mem_address, register, mem_address });
                 offset += 8;
             }
             0x84 => {
+                let value_size = data[offset + 1] >> 4;
+                let add_bool = data[offset + 1] & 1 != 0;
+                let repeat = (&data[offset + 2..]).read_u16::<BigEndian>().unwrap() >> 4;
                 let register = data[offset + 3] & 0xF;
                 let mem_address = (&data[offset + 4..]).read_u32::<BigEndian>().unwrap();
-                codes.push(WiiRDCode::StoreGeckoRegister { register, mem_address });
+
+                let add_mem_address = match (add_bool, use_base_address) {
+                    (true, true)  => AddAddress::BaseAddress,
+                    (true, false) => AddAddress::PointerAddress,
+                    (false, _)    => AddAddress::None,
+                };
+
+                codes.push(WiiRDCode::StoreGeckoRegister { value_size, add_mem_address, repeat, register, mem_address });
                 offset += 8;
             }
             0x86 => {
-                let operation_byte = data[offset + 1] & 0xF0;
+                let operation_byte = data[offset + 1] >> 4;
                 let load_register  = data[offset + 1] & 0b00000001 != 0;
                 let load_value     = data[offset + 1] & 0b00000010 != 0;
                 let register       = data[offset + 3] & 0x0F;
@@ -432,7 +911,7 @@ fn process_block(data: &[u8], is_nested: bool) -> ProcessedBlock {
                 offset += 8;
             }
             0x88 => {
-                let operation_byte = data[offset + 1] & 0xF0;
+                let operation_byte = data[offset + 1] >> 4;
                 let load_register1 = data[offset + 1] & 0b00000001 != 0;
                 let load_register2 = data[offset + 1] & 0b00000010 != 0;
                 let register1      = data[offset + 3] & 0x0F;
@@ -445,7 +924,7 @@ fn process_block(data: &[u8], is_nested: bool) -> ProcessedBlock {
             }
             0x8A => {
                 let count           = (&data[offset + 1..]).read_u16::<BigEndian>().unwrap();
-                let source_register =   data[offset + 3] & 0xF0;
+                let source_register =   data[offset + 3] >> 4;
                 let dest_register   =   data[offset + 3] & 0x0F;
                 let dest_offset     = (&data[offset + 4..]).read_u32::<BigEndian>().unwrap();
 
@@ -460,7 +939,7 @@ fn process_block(data: &[u8], is_nested: bool) -> ProcessedBlock {
             }
             0x8C => {
                 let count           = (&data[offset + 1..]).read_u16::<BigEndian>().unwrap();
-                let source_register =   data[offset + 3] & 0xF0;
+                let source_register =   data[offset + 3] >> 4;
                 let dest_register   =   data[offset + 3] & 0x0F;
                 let source_offset   = (&data[offset + 4..]).read_u32::<BigEndian>().unwrap();
 
@@ -500,7 +979,7 @@ fn process_block(data: &[u8], is_nested: bool) -> ProcessedBlock {
                 offset += 8;
 
                 if is_nested {
-                    return ProcessedBlock::EndIf { count: EndIfCount::Infinite, then_branch: WiiRDBlock { codes }, bytes_processed: offset, reset_base_address_high, reset_pointer_address_high };
+                    return ProcessedBlock::EndIf { count: EndIfCount::Infinite, then_branch: WiiRDBlock { codes }, spans, bytes_processed: offset, reset_base_address_high, reset_pointer_address_high };
                 }
                 else {
                     codes.push(WiiRDCode::ResetAddressHigh { reset_base_address_high, reset_pointer_address_high });
@@ -519,45 +998,746 @@ fn process_block(data: &[u8], is_nested: bool) -> ProcessedBlock {
                 offset += 8;
                 if is_nested {
                     if count != 0 {
-                        return ProcessedBlock::EndIf { count: EndIfCount::Finite(count), then_branch: WiiRDBlock { codes }, bytes_processed: offset, reset_base_address_high, reset_pointer_address_high };
+                        record_spans(&codes, &mut spans, start + code_start .. start + offset, &mut nested_spans);
+                        return ProcessedBlock::EndIf { count: EndIfCount::Finite(count), then_branch: WiiRDBlock { codes }, spans, bytes_processed: offset, reset_base_address_high, reset_pointer_address_high };
                     }
                 }
                 else {
                     codes.push(WiiRDCode::ResetAddressHigh { reset_base_address_high, reset_pointer_address_high });
                 }
             }
-            0xF0 => {
-                // End of codes
-            }
             unknown => {
                 // Can't really continue processing because we dont know what the correct offset should be.
                 // Report an error and return what we have so far.
                 error!("Cannot process WiiRD code starting with 0x{:x}", unknown);
-                return ProcessedBlock::Finished (WiiRDBlock { codes });
+                return ProcessedBlock::Finished (WiiRDBlock { codes }, spans, None);
+            }
+        }
+    }
+
+    record_spans(&codes, &mut spans, start + code_start .. start + offset, &mut nested_spans);
+    ProcessedBlock::Finished (WiiRDBlock { codes }, spans, end)
+}
+
+/// Lifts each SetRepeat and its matching ExecuteRepeat into a WiiRDCode::Repeat containing the codes between them.
+/// A pair matches when both codes use the same block_id and are in the same block, otherwise the codes are left as is.
+/// `spans` are the spans of the codes in `block` and are restructured in the same way.
+fn structure_repeats(block: WiiRDBlock, spans: Vec<CodeSpan>) -> (WiiRDBlock, Vec<CodeSpan>) {
+    let mut codes = vec!();
+    let mut output_spans = vec!();
+    let mut input = block.codes;
+    let mut input_spans = spans;
+    while !input.is_empty() {
+        let span = input_spans.remove(0);
+        match input.remove(0) {
+            WiiRDCode::SetRepeat { count, block_id } => {
+                let end = input.iter().position(|x| match x {
+                    WiiRDCode::ExecuteRepeat { block_id: end_id } => *end_id == block_id,
+                    _ => false,
+                });
+                if let Some(end) = end {
+                    let body = WiiRDBlock { codes: input.drain(..end).collect() };
+                    let body_spans = input_spans.drain(..end).collect();
+                    input.remove(0); // the matching ExecuteRepeat
+                    let end_span = input_spans.remove(0);
+                    let (body, nested) = structure_repeats(body, body_spans);
+                    codes.push(WiiRDCode::Repeat { count, block_id, body });
+                    output_spans.push(CodeSpan { range: span.range.start .. end_span.range.end, nested });
+                } else {
+                    codes.push(WiiRDCode::SetRepeat { count, block_id });
+                    output_spans.push(span);
+                }
+            }
+            WiiRDCode::IfStatement { test, then_branch, else_branch, reset_base_address_high, reset_pointer_address_high } => {
+                // else branches are not created by the parser, so there are no spans for them
+                let end = span.range.end;
+                let (then_branch, nested) = structure_repeats(then_branch, span.nested);
+                codes.push(WiiRDCode::IfStatement {
+                    test,
+                    then_branch,
+                    else_branch: else_branch.map(|x| {
+                        let spans = (0..x.codes.len()).map(|_| CodeSpan { range: end .. end, nested: vec!() }).collect();
+                        Box::new(structure_repeats(*x, spans).0)
+                    }),
+                    reset_base_address_high,
+                    reset_pointer_address_high,
+                });
+                output_spans.push(CodeSpan { range: span.range, nested });
+            }
+            code => {
+                codes.push(code);
+                output_spans.push(span);
+            }
+        }
+    }
+    (WiiRDBlock { codes }, output_spans)
+}
+
+/// Computes the address a code accesses, in the same way as the code handler.
+///
+/// `address` is the 25 bit address stored in the code.
+/// Codes using the base address only take the upper 7 bits of the base address, codes using the pointer address add the full pointer address.
+pub fn code_address(use_base_address: bool, address: u32, base_address: u32, pointer_address: u32) -> u32 {
+    if use_base_address {
+        (base_address & 0xFE000000) + address
+    } else {
+        pointer_address.wrapping_add(address)
+    }
+}
+
+/// Statically evaluates the test of an if statement, returning None when the result is not known.
+///
+/// `base_address` and `pointer_address` are the values of the code handler when the if statement is run, None when they are not known.
+/// The result is not known when the tested address depends on an unknown base address or pointer address, or `memory` does not know the value at the address.
+///
+/// The 20 to 26 codes compare the u32 at the address against the value.
+/// The 28 to 2E codes clear the bits set in the mask from the u16 at the address and compare the result against the value.
+/// All comparisons are unsigned.
+pub fn evaluate_condition(test: &IfTest, base_address: Option<u32>, pointer_address: Option<u32>, memory: &dyn MemoryView) -> Option<bool> {
+    let state = AddressState { base_address, pointer_address };
+    let read_u32 = |use_base_address: bool, address: u32| state.target(use_base_address, address & 0xFFFFFFFE).and_then(|x| memory.read_u32(RamAddress::new(x)));
+    let read_u16 = |use_base_address: bool, address: u32, mask: u16| state.target(use_base_address, address & 0xFFFFFFFE).and_then(|x| memory.read_u16(RamAddress::new(x))).map(|x| x & !mask);
+    Some(match *test {
+        IfTest::IsEqual       { use_base_address, address, value } => read_u32(use_base_address, address)? == value,
+        IfTest::IsNotEqual    { use_base_address, address, value } => read_u32(use_base_address, address)? != value,
+        IfTest::IsGreaterThan { use_base_address, address, value } => read_u32(use_base_address, address)? >  value,
+        IfTest::IsLessThan    { use_base_address, address, value } => read_u32(use_base_address, address)? <  value,
+        IfTest::IsEqualMask       { use_base_address, address, lhs_mask, rhs_value } => read_u16(use_base_address, address, lhs_mask)? == rhs_value,
+        IfTest::IsNotEqualMask    { use_base_address, address, lhs_mask, rhs_value } => read_u16(use_base_address, address, lhs_mask)? != rhs_value,
+        IfTest::IsGreaterThanMask { use_base_address, address, lhs_mask, rhs_value } => read_u16(use_base_address, address, lhs_mask)? >  rhs_value,
+        IfTest::IsLessThanMask    { use_base_address, address, lhs_mask, rhs_value } => read_u16(use_base_address, address, lhs_mask)? <  rhs_value,
+    })
+}
+
+/// The base address the code handler starts with
+pub const DEFAULT_BASE_ADDRESS: u32 = 0x80000000;
+/// The pointer address the code handler starts with
+pub const DEFAULT_POINTER_ADDRESS: u32 = 0x80000000;
+
+/// Identifies a code in a `WiiRDBlock`.
+/// Index of the code within each nested block, starting from the root block.
+/// e.g. `[3, 1]` is the second code in the block of the fourth code.
+/// The codes of an else branch are numbered after the codes of the then branch.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct CodePath (pub Vec<usize>);
+
+/// Returns the address written to by every write code in the block.
+///
+/// The base address and pointer address are tracked through the block starting from their defaults, including the resets of their upper 16 bits by E0 and E2 codes.
+/// The address is None when it depends on values only known at runtime e.g. after loading the base address from memory,
+/// or after an if statement or repeat that sets it to different values.
+/// Gotos, subroutines and returns are not followed.
+///
+/// Write codes are 00, 02, 04, 06, 08 and C2.
+/// A code within a repeat is only reported once.
+pub fn resolve_targets(block: &WiiRDBlock) -> Vec<(CodePath, Option<u32>)> {
+    resolve_targets_inner(block, None)
+}
+
+/// Same as `resolve_targets` but if statements that `evaluate_condition` can evaluate against `memory` only have their taken branch followed.
+/// Codes in the branch that is never taken are not included.
+pub fn resolve_targets_with_memory(block: &WiiRDBlock, memory: &dyn MemoryView) -> Vec<(CodePath, Option<u32>)> {
+    resolve_targets_inner(block, Some(memory))
+}
+
+fn resolve_targets_inner(block: &WiiRDBlock, memory: Option<&dyn MemoryView>) -> Vec<(CodePath, Option<u32>)> {
+    resolve_all_targets(block, memory).writes
+}
+
+fn resolve_all_targets(block: &WiiRDBlock, memory: Option<&dyn MemoryView>) -> ResolvedTargets {
+    let mut state = AddressState {
+        base_address:    Some(DEFAULT_BASE_ADDRESS),
+        pointer_address: Some(DEFAULT_POINTER_ADDRESS),
+    };
+    let mut targets = ResolvedTargets::default();
+    resolve_block_targets(block, &[], 0, &mut state, memory, &mut targets);
+    targets
+}
+
+#[derive(Default)]
+struct ResolvedTargets {
+    /// The address written to by each write code
+    writes: Vec<(CodePath, Option<u32>)>,
+    /// The address tested by each if statement
+    if_tests: Vec<(CodePath, Option<u32>)>,
+}
+
+impl ResolvedTargets {
+    fn extend(&mut self, other: ResolvedTargets) {
+        self.writes.extend(other.writes);
+        self.if_tests.extend(other.if_tests);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct AddressState {
+    base_address: Option<u32>,
+    pointer_address: Option<u32>,
+}
+
+impl AddressState {
+    fn target(&self, use_base_address: bool, address: u32) -> Option<u32> {
+        match (self.base_address, self.pointer_address) {
+            (Some(base_address), _) if use_base_address => Some(code_address(true, address, base_address, 0)),
+            (_, Some(pointer_address)) if !use_base_address => Some(code_address(false, address, 0, pointer_address)),
+            _ => None,
+        }
+    }
+
+    fn reset_high(&mut self, reset_base_address_high: u16, reset_pointer_address_high: u16) {
+        if reset_base_address_high != 0 {
+            self.base_address = Some((reset_base_address_high as u32) << 16);
+        }
+        if reset_pointer_address_high != 0 {
+            self.pointer_address = Some((reset_pointer_address_high as u32) << 16);
+        }
+    }
+
+    /// Returns the value of 42 and 4A codes
+    fn set_value(&self, add: &AddAddress, add_gecko_register: Option<u8>, value: u32) -> Option<u32> {
+        if add_gecko_register.is_some() {
+            return None;
+        }
+        let addend = match add {
+            AddAddress::BaseAddress    => self.base_address,
+            AddAddress::PointerAddress => self.pointer_address,
+            AddAddress::None           => Some(0),
+        };
+        addend.map(|x| value.wrapping_add(x))
+    }
+
+    /// Keeps the addresses that are the same in both states
+    fn merge(&self, other: &AddressState) -> AddressState {
+        AddressState {
+            base_address:    if self.base_address    == other.base_address    { self.base_address }    else { None },
+            pointer_address: if self.pointer_address == other.pointer_address { self.pointer_address } else { None },
+        }
+    }
+}
+
+/// `first_index` is the index given to the first code of the block
+/// `memory` is used to skip branches of if statements that are never taken
+fn resolve_block_targets(block: &WiiRDBlock, path: &[usize], first_index: usize, state: &mut AddressState, memory: Option<&dyn MemoryView>, targets: &mut ResolvedTargets) {
+    for (i, code) in block.codes.iter().enumerate() {
+        let mut code_path = path.to_vec();
+        code_path.push(first_index + i);
+
+        match code {
+            WiiRDCode::WriteAndFill8  { use_base_address, address, .. } |
+            WiiRDCode::WriteAndFill16 { use_base_address, address, .. } |
+            WiiRDCode::WriteAndFill32 { use_base_address, address, .. } |
+            WiiRDCode::StringWrite    { use_base_address, address, .. } |
+            WiiRDCode::SerialWrite    { use_base_address, address, .. } |
+            WiiRDCode::InsertPPC      { use_base_address, address, .. } => {
+                targets.writes.push((CodePath (code_path), state.target(*use_base_address, *address)));
+            }
+            WiiRDCode::SetBaseAddress { add_result, add, add_gecko_register, value } => {
+                let value = state.set_value(add, *add_gecko_register, *value);
+                state.base_address = if *add_result {
+                    state.base_address.and_then(|x| value.map(|value| x.wrapping_add(value)))
+                } else {
+                    value
+                };
+            }
+            WiiRDCode::SetPointerAddress { add_result, add, add_gecko_register, value } => {
+                let value = state.set_value(add, *add_gecko_register, *value);
+                state.pointer_address = if *add_result {
+                    state.pointer_address.and_then(|x| value.map(|value| x.wrapping_add(value)))
+                } else {
+                    value
+                };
+            }
+            WiiRDCode::LoadBaseAddress { .. } |
+            WiiRDCode::SetBaseAddressToCodeLocation { .. } => state.base_address = None,
+            WiiRDCode::LoadPointerAddress { .. } |
+            WiiRDCode::SetPointerAddressToCodeLocation { .. } => state.pointer_address = None,
+            WiiRDCode::ResetAddressHigh { reset_base_address_high, reset_pointer_address_high } |
+            WiiRDCode::Else { reset_base_address_high, reset_pointer_address_high, .. } => {
+                state.reset_high(*reset_base_address_high, *reset_pointer_address_high);
+            }
+            WiiRDCode::IfStatement { test, then_branch, else_branch, reset_base_address_high, reset_pointer_address_high } => {
+                let result = memory.and_then(|memory| evaluate_condition(test, state.base_address, state.pointer_address, memory));
+                targets.if_tests.push((CodePath (code_path.clone()), state.target(test.use_base_address(), test.address())));
+                let mut then_state = *state;
+                if result != Some(false) {
+                    resolve_block_targets(then_branch, &code_path, 0, &mut then_state, memory, targets);
+                }
+                let mut else_state = *state;
+                if let (Some(else_branch), true) = (else_branch, result != Some(true)) {
+                    resolve_block_targets(else_branch, &code_path, then_branch.codes.len(), &mut else_state, memory, targets);
+                }
+                *state = match result {
+                    Some(true)  => then_state,
+                    Some(false) => else_state,
+                    None        => then_state.merge(&else_state),
+                };
+                state.reset_high(*reset_base_address_high, *reset_pointer_address_high);
+            }
+            WiiRDCode::Repeat { body, .. } => {
+                let before = *state;
+                let mut body_targets = ResolvedTargets::default();
+                resolve_block_targets(body, &code_path, 0, state, memory, &mut body_targets);
+                if *state != before {
+                    // Later iterations start from a different state, so only keep what is the same for every iteration
+                    let mut merged = before.merge(state);
+                    body_targets = ResolvedTargets::default();
+                    resolve_block_targets(body, &code_path, 0, &mut merged, memory, &mut body_targets);
+                    *state = before.merge(&merged);
+                }
+                targets.extend(body_targets);
+            }
+            _ => { }
+        }
+    }
+}
+
+/// Returns the range of addresses written to by every write code in the block, as resolved by `resolve_targets`.
+/// Codes with an unknown address are not included.
+///
+/// InsertPPC codes are included as writing the 4 byte branch at their address.
+pub fn write_ranges(block: &WiiRDBlock) -> Vec<(CodePath, Range<u32>)> {
+    target_ranges(block, resolve_targets(block))
+}
+
+/// Same as `write_ranges` but resolved by `resolve_targets_with_memory`, so codes in branches that are never taken are not included.
+pub fn write_ranges_with_memory(block: &WiiRDBlock, memory: &dyn MemoryView) -> Vec<(CodePath, Range<u32>)> {
+    target_ranges(block, resolve_targets_with_memory(block, memory))
+}
+
+fn target_ranges(block: &WiiRDBlock, targets: Vec<(CodePath, Option<u32>)>) -> Vec<(CodePath, Range<u32>)> {
+    targets.into_iter().filter_map(|(path, address)| {
+        let address = address?;
+        let len = match block.code(&path)? {
+            WiiRDCode::WriteAndFill8  { length, .. } => *length,
+            WiiRDCode::WriteAndFill16 { length, .. } => *length * 2,
+            WiiRDCode::WriteAndFill32 { .. }         => 4,
+            WiiRDCode::StringWrite    { values, .. } => values.len() as u32,
+            WiiRDCode::SerialWrite    { value_size, count, address_increment, .. } => {
+                let size = match value_size >> 4 {
+                    0 => 1,
+                    1 => 2,
+                    _ => 4,
+                };
+                (*count as u32 - 1) * *address_increment as u32 + size
+            }
+            WiiRDCode::InsertPPC { .. } => 4,
+            _ => return None,
+        };
+        Some((path, address .. address.wrapping_add(len)))
+    }).collect()
+}
+
+impl WiiRDBlock {
+    /// Returns the code identified by `path`
+    pub fn code(&self, path: &CodePath) -> Option<&WiiRDCode> {
+        code_at(self, 0, &path.0)
+    }
+}
+
+/// `first_index` is the index given to the first code of the block
+fn code_at<'a>(block: &'a WiiRDBlock, first_index: usize, path: &[usize]) -> Option<&'a WiiRDCode> {
+    let (index, rest) = path.split_first()?;
+    let code = block.codes.get(index.checked_sub(first_index)?)?;
+    if rest.is_empty() {
+        return Some(code);
+    }
+
+    match code {
+        WiiRDCode::IfStatement { then_branch, else_branch, .. } => {
+            if rest[0] < then_branch.codes.len() {
+                code_at(then_branch, 0, rest)
+            } else {
+                code_at(else_branch.as_ref()?, then_branch.codes.len(), rest)
+            }
+        }
+        WiiRDCode::Repeat { body, .. } => code_at(body, 0, rest),
+        _ => None,
+    }
+}
+
+/// The start of the table read by the frame speed modifier engine.
+/// Each 8 byte entry is the fighter id, the frame, the action or subaction id and the frame speed, the table ends at an entry with a fighter id of 0.
+pub(crate) const FRAME_SPEED_MODIFIER_TABLE: u32 = 0x80581000;
+
+/// A region of RAM holding data that only affects a single fighter.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct FighterRegion {
+    /// The id of the fighter as returned by `fighter_maps::fighter_id`
+    pub fighter_id: u32,
+    pub range: Range<u32>,
+}
+
+/// A table in RAM where each entry starts with the id of the fighter it applies to.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct FighterTable {
+    pub range: Range<u32>,
+    pub entry_size: u32,
+}
+
+/// The addresses used by `codes_for_fighter` to attribute codes to fighters.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct FighterAddressMap {
+    pub regions: Vec<FighterRegion>,
+    pub tables: Vec<FighterTable>,
+    /// Addresses of a u32 holding a fighter id
+    pub fighter_id_addresses: Vec<u32>,
+}
+
+impl FighterAddressMap {
+    /// The addresses known to brawllib without knowing where the fighters are loaded, this is only the frame speed modifier table.
+    ///
+    /// Brawl allocates the data of each fighter, including its fighter id, at runtime.
+    /// Use `FighterAddressMap::with_loaded_files` to add the regions of fighter files loaded at a known location
+    /// and add the fighter id addresses used by a codeset to attribute more of its codes.
+    pub fn builtin() -> FighterAddressMap {
+        FighterAddressMap {
+            regions: vec!(),
+            tables: vec!(
+                // 0x200 entries is more than any known codeset fills
+                FighterTable { range: FRAME_SPEED_MODIFIER_TABLE .. FRAME_SPEED_MODIFIER_TABLE + 0x1000, entry_size: 8 },
+            ),
+            fighter_id_addresses: vec!(),
+        }
+    }
+
+    /// `FighterAddressMap::builtin` with a region for each fighter file loaded at a known location,
+    /// e.g. the files a mod is loaded with as `PatchTarget`s.
+    /// Each file is given with the id of its fighter as returned by `fighter_maps::fighter_id`.
+    pub fn with_loaded_files(files: &[(u32, LoadedRegion)]) -> FighterAddressMap {
+        let mut map = FighterAddressMap::builtin();
+        for (fighter_id, region) in files {
+            let start = region.ram_base.into_inner();
+            map.regions.push(FighterRegion { fighter_id: *fighter_id, range: start .. start + region.len });
+        }
+        map
+    }
+
+    /// Returns true if the write of `code` to `range` only affects the fighter
+    fn write_is_for_fighter(&self, code: &WiiRDCode, range: &Range<u32>, fighter_id: u32) -> bool {
+        let in_region = self.regions.iter().any(|x| x.fighter_id == fighter_id && x.range.start <= range.start && range.end <= x.range.end);
+        let in_table = self.tables.iter().any(|x| {
+            x.range.start <= range.start && range.end <= x.range.end
+                && (range.start - x.range.start).checked_rem(x.entry_size) == Some(0)
+                && first_written_byte(code).map(u32::from) == Some(fighter_id)
+        });
+        in_region || in_table
+    }
+
+    /// Returns true if `test` of an if statement, reading from `address`, checks for the fighter.
+    /// 16 bit tests compare against the lower half of the fighter id.
+    fn test_is_for_fighter(&self, test: &IfTest, address: u32, fighter_id: u32) -> bool {
+        self.fighter_id_addresses.iter().any(|id_address| match *test {
+            IfTest::IsEqual     { value, .. }                => address == *id_address && value == fighter_id,
+            IfTest::IsEqualMask { lhs_mask, rhs_value, .. } => address == *id_address + 2 && lhs_mask & 0xFF == 0 && fighter_id <= 0xFFFF && u32::from(rhs_value) == fighter_id,
+            _ => false,
+        })
+    }
+}
+
+/// The first byte written by the code, None if it is not known
+fn first_written_byte(code: &WiiRDCode) -> Option<u8> {
+    match code {
+        WiiRDCode::WriteAndFill8  { value, .. } => Some(*value),
+        WiiRDCode::WriteAndFill16 { value, .. } => Some((value >> 8) as u8),
+        WiiRDCode::WriteAndFill32 { value, .. } => Some((value >> 24) as u8),
+        WiiRDCode::StringWrite    { values, .. } => values.first().cloned(),
+        _ => None,
+    }
+}
+
+/// Returns the codes of the block that only affect the fighter, as attributed by the addresses in `FighterAddressMap::builtin`.
+/// `fighter_id` is the id returned by `fighter_maps::fighter_id`.
+pub fn codes_for_fighter(block: &WiiRDBlock, fighter_id: u32) -> WiiRDBlock {
+    codes_for_fighter_with_map(block, fighter_id, &FighterAddressMap::builtin())
+}
+
+/// Same as `codes_for_fighter` but attributes codes using the addresses in `map`.
+///
+/// A code is attributed to the fighter when:
+/// *   it writes within a region of the fighter
+/// *   it writes an entry of a table starting with the fighter id
+/// *   it is an if statement testing that a fighter id address is equal to the fighter id, the whole if statement including its else branch is attributed.
+///
+/// Codes are selected from the root of the block, so an if statement or repeat is included whole when any code within it is attributed.
+/// Every code setting the base address or pointer address since the previously selected code is also included, in order,
+/// so a selected code still writes to the same address even when unselected codes sit between it and its setup codes.
+/// Codes that cannot be attributed are not included.
+pub fn codes_for_fighter_with_map(block: &WiiRDBlock, fighter_id: u32, map: &FighterAddressMap) -> WiiRDBlock {
+    let targets = resolve_all_targets(block, None);
+    let mut attributed = vec!(false; block.codes.len());
+
+    let writes = target_ranges(block, targets.writes);
+    for (path, range) in &writes {
+        if let Some(code) = block.code(path) {
+            if map.write_is_for_fighter(code, range, fighter_id) {
+                attributed[path.0[0]] = true;
+            }
+        }
+    }
+    for (path, address) in &targets.if_tests {
+        if let (Some(WiiRDCode::IfStatement { test, .. }), Some(address)) = (block.code(path), address) {
+            if map.test_is_for_fighter(test, *address, fighter_id) {
+                attributed[path.0[0]] = true;
+            }
+        }
+    }
+
+    let mut codes = vec!();
+    let mut pending_setup = vec!();
+    for (code, attributed) in block.codes.iter().zip(attributed) {
+        if attributed {
+            codes.append(&mut pending_setup);
+            codes.push(code.clone());
+        } else if is_address_setup(code) {
+            pending_setup.push(code.clone());
+        }
+    }
+    WiiRDBlock { codes }
+}
+
+fn is_address_setup(code: &WiiRDCode) -> bool {
+    matches!(code,
+        WiiRDCode::LoadBaseAddress { .. } |
+        WiiRDCode::SetBaseAddress { .. } |
+        WiiRDCode::SetBaseAddressToCodeLocation { .. } |
+        WiiRDCode::LoadPointerAddress { .. } |
+        WiiRDCode::SetPointerAddress { .. } |
+        WiiRDCode::SetPointerAddressToCodeLocation { .. } |
+        WiiRDCode::ResetAddressHigh { .. }
+    )
+}
+
+/// The codes that read or write a gecko register or block id.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct ResourceUse {
+    pub reads: Vec<CodePath>,
+    pub writes: Vec<CodePath>,
+}
+
+impl ResourceUse {
+    pub fn is_used(&self) -> bool {
+        !self.reads.is_empty() || !self.writes.is_empty()
+    }
+}
+
+/// A register is written by 80, 82, 86 and 88 codes and read by every code that takes a register as an operand.
+pub type RegisterUse = ResourceUse;
+
+/// A block id is written by 60 and 68 codes and read by 62 and 64 codes.
+pub type BlockIdUse = ResourceUse;
+
+/// The gecko registers and block ids used by a `WiiRDBlock`, indexed by register/block id.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct ResourceUsage {
+    pub registers: [RegisterUse; 16],
+    pub block_ids: [BlockIdUse; 16],
+}
+
+/// Returns which codes in the block use each gecko register and block id.
+///
+/// Both branches of if statements and the bodies of repeats are included.
+/// Gotos, subroutines and returns are not followed.
+pub fn resource_usage(block: &WiiRDBlock) -> ResourceUsage {
+    let mut usage = ResourceUsage::default();
+    block_resource_usage(block, &[], 0, &mut usage);
+    usage
+}
+
+/// `first_index` is the index given to the first code of the block
+fn block_resource_usage(block: &WiiRDBlock, path: &[usize], first_index: usize, usage: &mut ResourceUsage) {
+    for (i, code) in block.codes.iter().enumerate() {
+        let mut code_path = path.to_vec();
+        code_path.push(first_index + i);
+
+        let mut register_reads = vec!();
+        let mut register_writes = vec!();
+        let mut block_id_reads = vec!();
+        let mut block_id_writes = vec!();
+        match code {
+            WiiRDCode::LoadBaseAddress    { add_mem_address_gecko_register, .. } |
+            WiiRDCode::StoreBaseAddress   { add_mem_address_gecko_register, .. } |
+            WiiRDCode::LoadPointerAddress { add_mem_address_gecko_register, .. } |
+            WiiRDCode::StorePointerAddress { add_mem_address_gecko_register, .. } => {
+                register_reads.extend(add_mem_address_gecko_register);
+            }
+            WiiRDCode::SetBaseAddress    { add_gecko_register, .. } |
+            WiiRDCode::SetPointerAddress { add_gecko_register, .. } => {
+                register_reads.extend(add_gecko_register);
+            }
+            WiiRDCode::SetGeckoRegister { add_result, register, .. } => {
+                if *add_result {
+                    register_reads.push(*register);
+                }
+                register_writes.push(*register);
+            }
+            WiiRDCode::LoadGeckoRegister { register, .. } => register_writes.push(*register),
+            WiiRDCode::StoreGeckoRegister { register, .. } => register_reads.push(*register),
+            WiiRDCode::OperationGeckoRegisterDirectValue { load_register, register, .. } => {
+                register_reads.push(*register);
+                // when loading, the result is stored at the address in the register instead of the register itself
+                if !*load_register {
+                    register_writes.push(*register);
+                }
+            }
+            WiiRDCode::OperationGeckoRegister { load_register1, register1, register2, .. } => {
+                register_reads.push(*register1);
+                register_reads.push(*register2);
+                if !*load_register1 {
+                    register_writes.push(*register1);
+                }
+            }
+            WiiRDCode::MemoryCopy1 { source_register, dest_register, .. } => {
+                register_reads.push(*source_register);
+                register_reads.extend(dest_register);
+            }
+            WiiRDCode::MemoryCopy2 { source_register, dest_register, .. } => {
+                register_reads.extend(source_register);
+                register_reads.push(*dest_register);
+            }
+            WiiRDCode::SetRepeat     { block_id, .. } |
+            WiiRDCode::Subroutine    { block_id, .. } => block_id_writes.push(*block_id),
+            WiiRDCode::ExecuteRepeat { block_id }     |
+            WiiRDCode::Return        { block_id, .. } => block_id_reads.push(*block_id),
+            WiiRDCode::Repeat { block_id, body, .. } => {
+                block_id_writes.push(*block_id);
+                block_id_reads.push(*block_id);
+                block_resource_usage(body, &code_path, 0, usage);
+            }
+            WiiRDCode::IfStatement { then_branch, else_branch, .. } => {
+                block_resource_usage(then_branch, &code_path, 0, usage);
+                if let Some(else_branch) = else_branch {
+                    block_resource_usage(else_branch, &code_path, then_branch.codes.len(), usage);
+                }
+            }
+            _ => { }
+        }
+
+        let code_path = CodePath (code_path);
+        let uses = [
+            (&mut usage.registers, register_reads, register_writes),
+            (&mut usage.block_ids, block_id_reads, block_id_writes),
+        ];
+        for (resources, reads, writes) in uses {
+            for id in reads {
+                if let Some(resource) = resources.get_mut(id as usize) {
+                    resource.reads.push(code_path.clone());
+                }
+            }
+            for id in writes {
+                if let Some(resource) = resources.get_mut(id as usize) {
+                    resource.writes.push(code_path.clone());
+                }
             }
         }
     }
+}
+
+/// A gecko register or block id.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum MergeResource {
+    GeckoRegister (u8),
+    BlockId (u8),
+}
+
+/// A gecko register or block id that is used by two code groups.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct MergeConflict {
+    pub resource: MergeResource,
+    /// How the first group uses the resource, paths are relative to the first groups block
+    pub a: ResourceUse,
+    /// How the second group uses the resource, paths are relative to the second groups block
+    pub b: ResourceUse,
+}
+
+/// Returns the gecko registers and block ids that are used by both `a` and `b` and written by at least one of them.
+///
+/// `a` and `b` are expected to be independently authored code groups e.g. two codes that are being merged into the same gct.
+/// Resources that are only read by both groups are not conflicts.
+pub fn check_merge_conflicts(a: &WiiRDBlock, b: &WiiRDBlock) -> Vec<MergeConflict> {
+    merge_conflicts(&resource_usage(a), &resource_usage(b))
+}
+
+fn merge_conflicts(a: &ResourceUsage, b: &ResourceUsage) -> Vec<MergeConflict> {
+    let mut conflicts = vec!();
+    let resources = [
+        (&a.registers, &b.registers, MergeResource::GeckoRegister as fn(u8) -> MergeResource),
+        (&a.block_ids, &b.block_ids, MergeResource::BlockId),
+    ];
+    for (a_uses, b_uses, resource) in resources {
+        for (i, (a_use, b_use)) in a_uses.iter().zip(b_uses.iter()).enumerate() {
+            if a_use.is_used() && b_use.is_used() && (!a_use.writes.is_empty() || !b_use.writes.is_empty()) {
+                conflicts.push(MergeConflict {
+                    resource: resource(i as u8),
+                    a:        a_use.clone(),
+                    b:        b_use.clone(),
+                });
+            }
+        }
+    }
+    conflicts
+}
 
-    ProcessedBlock::Finished(WiiRDBlock { codes })
+/// A `MergeConflict` between two named codes.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct NamedMergeConflict {
+    pub a_name: String,
+    pub b_name: String,
+    pub conflict: MergeConflict,
+}
+
+/// Runs `check_merge_conflicts` on every pair of enabled codes, treating each code as an independently authored group.
+pub fn check_named_code_conflicts(codes: &[NamedCode]) -> Vec<NamedMergeConflict> {
+    let codes: Vec<_> = codes.iter()
+        .filter(|x| x.enabled)
+        .map(|x| (&x.name, resource_usage(&wiird_codes(&x.data))))
+        .collect();
+
+    let mut conflicts = vec!();
+    for (i, (a_name, a)) in codes.iter().enumerate() {
+        for (b_name, b) in &codes[i + 1..] {
+            for conflict in merge_conflicts(a, b) {
+                conflicts.push(NamedMergeConflict {
+                    a_name: a_name.to_string(),
+                    b_name: b_name.to_string(),
+                    conflict,
+                });
+            }
+        }
+    }
+    conflicts
 }
 
 enum ProcessedBlock {
-    Finished     (WiiRDBlock),
-    EndIf        { count: EndIfCount, then_branch: WiiRDBlock, bytes_processed: usize, reset_base_address_high: u16, reset_pointer_address_high: u16 },
+    /// The offset following the F0 end of codes line is included when the block was ended by one
+    Finished     (WiiRDBlock, Vec<CodeSpan>, Option<usize>),
+    EndIf        { count: EndIfCount, then_branch: WiiRDBlock, spans: Vec<CodeSpan>, bytes_processed: usize, reset_base_address_high: u16, reset_pointer_address_high: u16 },
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Serialize, Deserialize))]
 pub enum EndIfCount {
     Infinite,
     Finite (u8),
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub struct WiiRDBlock {
     pub codes: Vec<WiiRDCode>,
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+#[non_exhaustive]
 pub enum WiiRDCode {
     /// 00
     WriteAndFill8 { use_base_address: bool, address: u32, value: u8, length: u32 },
@@ -589,7 +1769,7 @@ pub enum WiiRDCode {
     SetBaseAddressToCodeLocation { address_offset: i16 },
     /// 48
     LoadPointerAddress { add_result: bool, add_mem_address: AddAddress, add_mem_address_gecko_register: Option<u8>, mem_address: u32 },
-    /// 48
+    /// 4A
     SetPointerAddress { add_result: bool, add: AddAddress, add_gecko_register: Option<u8>, value: u32 },
     /// 4C
     StorePointerAddress { add_mem_address: AddAddress, add_mem_address_gecko_register: Option<u8>, mem_address: u32 },
@@ -602,6 +1782,10 @@ pub enum WiiRDCode {
     SetRepeat { count: u16, block_id: u8 },
     /// 62
     ExecuteRepeat { block_id: u8 },
+    /// 60 and 62
+    /// A SetRepeat and its matching ExecuteRepeat, body is executed count + 1 times.
+    /// SetRepeat and ExecuteRepeat are only used when no match could be found.
+    Repeat { count: u16, block_id: u8, body: WiiRDBlock },
     /// 64
     Return { flag: JumpFlag, block_id: u8 },
     /// 66
@@ -610,19 +1794,27 @@ pub enum WiiRDCode {
     /// 68
     /// The code handler stores the next code address in block_id, then it jumps to (next line of code + offset_lines)
     Subroutine { flag: JumpFlag, offset_lines: i16, block_id: u8 },
-    /// 80
+    /// 80 or 90
+    /// 90 adds the pointer address instead of the base address
     SetGeckoRegister { add_result: bool, add: AddAddress, register: u8, value: u32 },
-    /// 82
-    LoadGeckoRegister { register: u8, mem_address: u32 },
-    /// 84
-    StoreGeckoRegister { register: u8, mem_address: u32 },
+    /// 82 or 92
+    /// value_size is 0 for 8 bit, 1 for 16 bit and 2 for 32 bit
+    LoadGeckoRegister { value_size: u8, add_mem_address: AddAddress, register: u8, mem_address: u32 },
+    /// 84 or 94
+    /// value_size is 0 for 8 bit, 1 for 16 bit and 2 for 32 bit.
+    /// The register is stored repeat + 1 times to consecutive addresses.
+    StoreGeckoRegister { value_size: u8, add_mem_address: AddAddress, repeat: u16, register: u8, mem_address: u32 },
     /// 86
     OperationGeckoRegisterDirectValue { operation: GeckoOperation, load_register: bool, load_value: bool, register: u8, value: u32 },
     /// 88
     OperationGeckoRegister { operation: GeckoOperation, load_register1: bool, load_register2: bool, register1: u8, register2: u8 },
-    /// 8A
+    /// 8A or 9A
+    /// Copies count bytes from the address in source_register to the address in dest_register + dest_offset.
+    /// When dest_register is None the base address or pointer address is used instead.
     MemoryCopy1 { use_base_address: bool, count: u16, source_register: u8, dest_register: Option<u8>, dest_offset: u32 },
-    /// 8C
+    /// 8C or 9C
+    /// Copies count bytes from the address in source_register + source_offset to the address in dest_register.
+    /// When source_register is None the base address or pointer address is used instead.
     MemoryCopy2 { use_base_address: bool, count: u16, source_register: Option<u8>, dest_register: u8, source_offset: u32 },
     /// C0
     ExecutePPC { instruction_data: Vec<u8> },
@@ -638,6 +1830,7 @@ pub enum WiiRDCode {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum IfTest {
     IsEqual { use_base_address: bool, address: u32, value: u32 },
     IsNotEqual { use_base_address: bool, address: u32, value: u32 },
@@ -649,7 +1842,38 @@ pub enum IfTest {
     IsLessThanMask { use_base_address: bool, address: u32, lhs_mask: u16, rhs_value: u16 },
 }
 
+impl IfTest {
+    pub(crate) fn use_base_address(&self) -> bool {
+        match *self {
+            IfTest::IsEqual           { use_base_address, .. } |
+            IfTest::IsNotEqual        { use_base_address, .. } |
+            IfTest::IsGreaterThan     { use_base_address, .. } |
+            IfTest::IsLessThan        { use_base_address, .. } |
+            IfTest::IsEqualMask       { use_base_address, .. } |
+            IfTest::IsNotEqualMask    { use_base_address, .. } |
+            IfTest::IsGreaterThanMask { use_base_address, .. } |
+            IfTest::IsLessThanMask    { use_base_address, .. } => use_base_address,
+        }
+    }
+
+    /// The address of the tested value, with the lowest bit cleared as it is by the code handler
+    pub(crate) fn address(&self) -> u32 {
+        let address = match *self {
+            IfTest::IsEqual           { address, .. } |
+            IfTest::IsNotEqual        { address, .. } |
+            IfTest::IsGreaterThan     { address, .. } |
+            IfTest::IsLessThan        { address, .. } |
+            IfTest::IsEqualMask       { address, .. } |
+            IfTest::IsNotEqualMask    { address, .. } |
+            IfTest::IsGreaterThanMask { address, .. } |
+            IfTest::IsLessThanMask    { address, .. } => address,
+        };
+        address & 0xFFFFFFFE
+    }
+}
+
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum JumpFlag {
     WhenTrue,
     WhenFalse,
@@ -657,6 +1881,7 @@ pub enum JumpFlag {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum AddAddress {
     BaseAddress,
     PointerAddress,
@@ -664,6 +1889,7 @@ pub enum AddAddress {
 }
 
 #[derive(Serialize, Clone, Debug)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
 pub enum GeckoOperation {
     Add,
     Mul,
@@ -697,3 +1923,616 @@ impl GeckoOperation {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::RamAddress;
+    use crate::wii_memory::{BufferView, WiiMemory};
+
+    const CODESET: &str = "RSBE01
+Super Smash Bros. Brawl
+
+Write fighter value [author]
+* 04581000 00000001
+* 2057A000 00000002
+* 04581004 00000003
+* E0000000 80008000
+Sets some values
+
+Disabled code
+04581008 00000004
+
+Checksum code
+* F2000000 00000001
+* 04581010 00000005
+";
+
+    #[test]
+    fn txt_to_gct_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let txt_path = dir.path().join("codes.txt");
+        let gct_path = dir.path().join("RSBE01.gct");
+        fs::write(&txt_path, CODESET).unwrap();
+
+        let report = convert_txt_to_gct(&txt_path, &gct_path).unwrap();
+        assert_eq!(report.codes.len(), 2);
+        assert_eq!(report.codes[0].name, "Write fighter value [author]");
+        assert_eq!(report.codes[0].comments, vec!("Sets some values"));
+        assert_eq!((report.codes[0].line_start, report.codes[0].line_end), (4, 9));
+        assert_eq!(report.codes[0].encoded_size, 32);
+        assert_eq!(report.codes[1].name, "Checksum code");
+        assert_eq!(report.codes[1].encoded_size, 16);
+        assert_eq!(report.warnings.len(), 1);
+
+        // The unknown code is passed through untouched
+        let gct = fs::read(&gct_path).unwrap();
+        assert_eq!(gct.len(), 8 + 32 + 16 + 8);
+        assert_eq!(&gct[40..48], &[0xF2, 0, 0, 0, 0, 0, 0, 1]);
+
+        let from_gct = wiird_load_gct(&gct_path).unwrap();
+        let from_txt = wiird_load_txt(&txt_path).unwrap();
+        assert_eq!(format!("{:?}", from_gct), format!("{:?}", from_txt));
+    }
+
+    const DOLPHIN_INI: &str = "[Core]
+CPUThread = True
+[Gecko]
+$Write fighter value [author]
+04581004 00000003
+*Sets some values
+$Disabled code
+04581008 00000004
+$Fix [Bug] (v1.2)  [Other Author]
+04581010 00000005
+2057A000 00000002
+$Listed as disabled
+0458100C 00000006
+[Gecko_Enabled]
+$write fighter VALUE [author]
+$Fix [Bug] (v1.2) [Other Author]
+$Listed as disabled
+[Gecko_Disabled]
+$Listed as disabled
+";
+
+    #[test]
+    fn load_dolphin_ini_codes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("RSBE01.ini");
+        fs::write(&path, DOLPHIN_INI).unwrap();
+
+        let codes = load_dolphin_ini(&path).unwrap();
+        let names: Vec<_> = codes.iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, vec!("Write fighter value [author]", "Disabled code", "Fix [Bug] (v1.2)  [Other Author]", "Listed as disabled"));
+        let enabled: Vec<_> = codes.iter().map(|x| x.enabled).collect();
+        assert_eq!(enabled, vec!(true, false, true, false));
+
+        assert_eq!(codes[0].comments, vec!("Sets some values"));
+        assert_eq!(codes[0].data, vec!(0x04, 0x58, 0x10, 0x04, 0x00, 0x00, 0x00, 0x03));
+        assert_eq!(codes[2].data.len(), 16);
+
+        assert_eq!(enabled_codes_data(&codes).len(), 24);
+    }
+
+    #[test]
+    fn dolphin_ini_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("RSBE01.ini");
+        fs::write(&path, DOLPHIN_INI).unwrap();
+        let codes = load_dolphin_ini(&path).unwrap();
+
+        let out_path = dir.path().join("out.ini");
+        write_dolphin_ini(&codes, &out_path).unwrap();
+        assert_eq!(load_dolphin_ini(&out_path).unwrap(), codes);
+        assert!(fs::read_to_string(&out_path).unwrap().contains("[Gecko_Enabled]\n$Write fighter value [author]\n$Fix [Bug] (v1.2)  [Other Author]\n"));
+    }
+
+    #[test]
+    fn dolphin_ini_keeps_other_sections() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("RSBE01.ini");
+        fs::write(&path, DOLPHIN_INI.replace("[Gecko_Disabled]", "[Video_Settings]\nSafeTextureCacheColorSamples = 512\n[Gecko_Disabled]")).unwrap();
+        let mut codes = load_dolphin_ini(&path).unwrap();
+        codes.truncate(2);
+        codes[1].enabled = true;
+        codes.push(NamedCode { name: "New code".into(), comments: vec!(), enabled: true, data: vec!(0x04, 0x58, 0x10, 0x20, 0, 0, 0, 1) });
+        codes.push(NamedCode { name: "Listed as disabled".into(), comments: vec!(), enabled: true, data: vec!() });
+
+        write_dolphin_ini(&codes, &path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("[Core]\nCPUThread = True\n[Gecko]\n$Write fighter value [author]\n"));
+        assert!(text.contains("[Video_Settings]\nSafeTextureCacheColorSamples = 512\n[Gecko_Disabled]\n"));
+        assert!(!text.contains("Fix [Bug]"));
+        assert_eq!(load_dolphin_ini(&path).unwrap(), codes);
+    }
+
+    #[test]
+    fn dolphin_ini_invalid_lines_are_skipped() {
+        let codes = dolphin_ini_codes("[Gecko]\n04581000 00000003\n$Code\n0458100 00000003\n04581004 00000003\n[Gecko_Enabled]\n$Code\n");
+        assert_eq!(codes, vec!(NamedCode { name: "Code".into(), comments: vec!(), enabled: true, data: vec!(0x04, 0x58, 0x10, 0x04, 0, 0, 0, 3) }));
+    }
+
+    fn codes(lines: &[(u32, u32)]) -> WiiRDBlock {
+        let mut data = vec!();
+        for (left, right) in lines {
+            data.extend(&left.to_be_bytes());
+            data.extend(&right.to_be_bytes());
+        }
+        wiird_codes(&data)
+    }
+
+    #[test]
+    fn code_spans() {
+        let lines: [(u32, u32); 15] = [
+            (0x04581000, 0x00000001),
+            (0x06581100, 0x00000005), // string write of 5 bytes, padded to a full line
+            (0x48656C6C, 0x6F000000),
+            (0x20500000, 0x00000011), // if
+            (0x04001000, 0x00000001),
+            (0x20500004, 0x00000001), // nested if
+            (0x04002000, 0x00000001),
+            (0xE2000002, 0x00000000), // terminates both ifs
+            (0x60000002, 0x00000000), // repeat
+            (0x04003000, 0x00000001),
+            (0x62000000, 0x00000000),
+            (0xC2004000, 0x00000001), // insert one line of instructions
+            (0x60000000, 0x00000000),
+            (0xE0000000, 0x80008000),
+            (0xF0000000, 0x00000000),
+        ];
+        let mut data = vec!();
+        for (left, right) in &lines {
+            data.extend(&left.to_be_bytes());
+            data.extend(&right.to_be_bytes());
+        }
+        let (block, spans) = wiird_codes_with_spans(&data);
+
+        let path = |path: &[usize]| CodePath (path.to_vec());
+        assert_eq!(spans, vec!(
+            (path(&[0]),       0..8),
+            (path(&[1]),       8..24),
+            (path(&[2]),       24..64),
+            (path(&[2, 0]),    32..40),
+            (path(&[2, 1]),    40..64),
+            (path(&[2, 1, 0]), 48..56),
+            (path(&[3]),       64..64), // created from the terminator of the ifs
+            (path(&[4]),       64..88),
+            (path(&[4, 0]),    72..80),
+            (path(&[5]),       88..104),
+            (path(&[6]),       104..112),
+        ));
+        for (path, _) in &spans {
+            assert!(block.code(path).is_some());
+        }
+        assert!(matches!(block.code(&path(&[2, 1, 0])), Some(WiiRDCode::WriteAndFill32 { address: 0x2000, .. })));
+        assert!(matches!(block.code(&path(&[4])), Some(WiiRDCode::Repeat { .. })));
+
+        // the root codes cover everything but the end of codes line
+        let mut end = 0;
+        for (path, span) in spans.iter().filter(|(path, _)| path.0.len() == 1) {
+            assert_eq!(span.start, end, "gap or overlap before {:?}", path);
+            end = span.end;
+        }
+        assert_eq!(end, data.len() - 8);
+
+        assert_eq!(wiird_codes(&data).codes.len(), 7);
+    }
+
+    #[test]
+    fn trailing_data() {
+        let lines: [(u32, u32); 3] = [
+            (0x04581000, 0x00000001),
+            (0xF0000000, 0x00000000),
+            (0x04581004, 0x00000002), // would be a valid code if the terminator was ignored
+        ];
+        let mut data = vec!();
+        for (left, right) in &lines {
+            data.extend(&left.to_be_bytes());
+            data.extend(&right.to_be_bytes());
+        }
+        data.extend(&[0xDE, 0xAD]);
+
+        let output = wiird_codes_with_trailing(&data);
+        assert_eq!(output.block.codes.len(), 1);
+        assert!(matches!(output.block.codes[0], WiiRDCode::WriteAndFill32 { address: 0x581000, .. }));
+        assert!(output.concatenated.is_empty());
+        assert_eq!(output.trailing, vec!(0x04, 0x58, 0x10, 0x04, 0x00, 0x00, 0x00, 0x02, 0xDE, 0xAD));
+        assert_eq!(wiird_codes(&data).codes.len(), 1);
+
+        // no terminator
+        let output = wiird_codes_with_trailing(&data[..8]);
+        assert_eq!(output.block.codes.len(), 1);
+        assert!(output.trailing.is_empty());
+    }
+
+    #[test]
+    fn concatenated_gct() {
+        let mut data = vec!();
+        for (left, right) in &[(0x04581000_u32, 0x00000001_u32), (0xF0000000, 0x00000000)] {
+            data.extend(&left.to_be_bytes());
+            data.extend(&right.to_be_bytes());
+        }
+        data.extend(&GCT_HEADER);
+        for (left, right) in &[(0x04581004_u32, 0x00000002_u32), (0x04581008, 0x00000003), (0xF0000000, 0x00000000)] {
+            data.extend(&left.to_be_bytes());
+            data.extend(&right.to_be_bytes());
+        }
+        data.extend(&[0; 4]);
+
+        let output = wiird_codes_with_trailing(&data);
+        assert_eq!(output.block.codes.len(), 1);
+        assert_eq!(output.concatenated.len(), 1);
+        assert_eq!(output.concatenated[0].codes.len(), 2);
+        assert!(matches!(output.concatenated[0].codes[1], WiiRDCode::WriteAndFill32 { address: 0x581008, value: 3, .. }));
+        assert_eq!(output.trailing, vec!(0; 4));
+    }
+
+    fn target_addresses(lines: &[(u32, u32)]) -> Vec<Option<u32>> {
+        resolve_targets(&codes(lines)).into_iter().map(|(_, target)| target).collect()
+    }
+
+    #[test]
+    fn resolve_targets_defaults() {
+        let targets = resolve_targets(&codes(&[
+            (0x04001234, 0x00000001), // ba write
+            (0x05001234, 0x00000001), // ba write using the 25th address bit
+            (0x14001234, 0x00000001), // po write
+        ]));
+        assert_eq!(targets, vec!(
+            (CodePath (vec!(0)), Some(0x80001234)),
+            (CodePath (vec!(1)), Some(0x81001234)),
+            (CodePath (vec!(2)), Some(0x80001234)),
+        ));
+    }
+
+    #[test]
+    fn resolve_targets_full_terminator() {
+        let set_addresses = [
+            (0x42000000, 0x92000000), // ba = 0x92000000
+            (0x4A000000, 0x90000000), // po = 0x90000000
+            (0x04001234, 0x00000001),
+            (0x14001234, 0x00000001),
+        ];
+
+        // E0 with 8000/8000 resets ba and po to 0x80000000
+        let mut lines = set_addresses.to_vec();
+        lines.extend(&[(0xE0000000, 0x80008000), (0x04001234, 0x00000001), (0x14001234, 0x00000001)]);
+        assert_eq!(target_addresses(&lines), vec!(Some(0x92001234), Some(0x90001234), Some(0x80001234), Some(0x80001234)));
+
+        // E0 with 0000/0000 leaves ba and po untouched
+        let mut lines = set_addresses.to_vec();
+        lines.extend(&[(0xE0000000, 0x00000000), (0x04001234, 0x00000001), (0x14001234, 0x00000001)]);
+        assert_eq!(target_addresses(&lines), vec!(Some(0x92001234), Some(0x90001234), Some(0x92001234), Some(0x90001234)));
+
+        // Only the upper 7 bits of ba are used, po is added in full
+        let lines = [
+            (0x42000000, 0x80F9FC20),
+            (0x4A000000, 0x80F9FC20),
+            (0x04000010, 0x00000001),
+            (0x14000010, 0x00000001),
+        ];
+        assert_eq!(target_addresses(&lines), vec!(Some(0x80000010), Some(0x80F9FC30)));
+    }
+
+    #[test]
+    fn resolve_targets_if_statement() {
+        let if_setting_po = [
+            (0x20001000, 0x00000000), // if ba + 0x1000 == 0
+            (0x4A000000, 0x81000000), // po = 0x81000000
+            (0x14000010, 0x00000001),
+        ];
+
+        // po depends on the if statement after the endif
+        let mut lines = if_setting_po.to_vec();
+        lines.extend(&[(0xE2000001, 0x00000000), (0x14000020, 0x00000001)]);
+        let targets = resolve_targets(&codes(&lines));
+        assert_eq!(targets, vec!(
+            (CodePath (vec!(0, 1)), Some(0x81000010)),
+            (CodePath (vec!(2)), None),
+        ));
+        assert_eq!(write_ranges(&codes(&lines)), vec!((CodePath (vec!(0, 1)), 0x81000010..0x81000014)));
+        assert!(matches!(codes(&lines).code(&CodePath (vec!(0, 1))), Some(WiiRDCode::WriteAndFill32 { address: 0x10, .. })));
+
+        // the endif resets po
+        let mut lines = if_setting_po.to_vec();
+        lines.extend(&[(0xE2000001, 0x00008000), (0x14000020, 0x00000001)]);
+        assert_eq!(target_addresses(&lines), vec!(Some(0x81000010), Some(0x80000020)));
+    }
+
+    #[test]
+    fn codes_for_fighter_attribution() {
+        let marth = crate::fighter_maps::fighter_id("Marth") as u32;
+        let lines = [
+            (0x04581000, 0x11040101), // frame speed modifier entry for marth
+            (0x04581008, 0x02050101), // frame speed modifier entry for link
+            (0x4A000000, 0x81200000), // po = 0x81200000
+            (0x04003000, 0x00000001), // unattributable write between the setup and its use
+            (0x14000010, 0x00000001), // write into marths region
+            (0x20500000, 0x00000011), // if [0x80500000] == marth
+            (0x04001000, 0x00000001),
+            (0xE2000001, 0x00000000),
+            (0x04002000, 0x00000001), // unattributable write
+        ];
+        let block = codes(&lines);
+
+        // only the frame speed modifier table is builtin
+        let selected = codes_for_fighter(&block, marth);
+        assert_eq!(selected.codes.len(), 1);
+        assert!(matches!(selected.codes[0], WiiRDCode::WriteAndFill32 { address: 0x581000, value: 0x11040101, .. }));
+
+        let mut map = FighterAddressMap::with_loaded_files(&[(marth, LoadedRegion::new(RamAddress::new(0x81200000), 0x1000))]);
+        assert_eq!(map.regions, vec!(FighterRegion { fighter_id: marth, range: 0x81200000 .. 0x81201000 }));
+        map.fighter_id_addresses.push(0x80500000);
+        let selected = codes_for_fighter_with_map(&block, marth, &map);
+        assert_eq!(selected.codes.len(), 4);
+        assert!(matches!(selected.codes[0], WiiRDCode::WriteAndFill32 { address: 0x581000, .. }));
+        assert!(matches!(selected.codes[1], WiiRDCode::SetPointerAddress { value: 0x81200000, .. }));
+        assert!(matches!(selected.codes[2], WiiRDCode::WriteAndFill32 { use_base_address: false, address: 0x10, .. }));
+        assert!(matches!(&selected.codes[3], WiiRDCode::IfStatement { then_branch, .. } if then_branch.codes.len() == 1));
+
+        let link = crate::fighter_maps::fighter_id("Link") as u32;
+        let selected = codes_for_fighter_with_map(&block, link, &map);
+        assert_eq!(selected.codes.len(), 1);
+        assert!(matches!(selected.codes[0], WiiRDCode::WriteAndFill32 { address: 0x581008, .. }));
+    }
+
+    #[test]
+    fn resolve_targets_unknown_address() {
+        let lines = [
+            (0x48000000, 0x80001000), // po = value at 0x80001000
+            (0x14000010, 0x00000001),
+            (0x04000010, 0x00000001),
+        ];
+        assert_eq!(target_addresses(&lines), vec!(None, Some(0x80000010)));
+    }
+
+    // 0x80001000 holds the u32 5 and 0x80001004 holds the u16 0x1234
+    const CONDITION_DATA: [u8; 8] = [0x00, 0x00, 0x00, 0x05, 0x12, 0x34, 0x00, 0x00];
+
+    fn condition(test: IfTest) -> Option<bool> {
+        let memory = BufferView::new(&CONDITION_DATA, RamAddress::new(0x80001000));
+        evaluate_condition(&test, Some(DEFAULT_BASE_ADDRESS), Some(DEFAULT_POINTER_ADDRESS), &memory)
+    }
+
+    #[test]
+    fn evaluate_condition_32_bit() {
+        let test = |value, f: fn(bool, u32, u32) -> IfTest| condition(f(true, 0x1000, value));
+        let is_equal        = |use_base_address, address, value| IfTest::IsEqual       { use_base_address, address, value };
+        let is_not_equal    = |use_base_address, address, value| IfTest::IsNotEqual    { use_base_address, address, value };
+        let is_greater_than = |use_base_address, address, value| IfTest::IsGreaterThan { use_base_address, address, value };
+        let is_less_than    = |use_base_address, address, value| IfTest::IsLessThan    { use_base_address, address, value };
+
+        assert_eq!(test(5, is_equal), Some(true));
+        assert_eq!(test(6, is_equal), Some(false));
+        assert_eq!(test(6, is_not_equal), Some(true));
+        assert_eq!(test(5, is_not_equal), Some(false));
+        assert_eq!(test(4, is_greater_than), Some(true));
+        assert_eq!(test(5, is_greater_than), Some(false));
+        assert_eq!(test(6, is_less_than), Some(true));
+        assert_eq!(test(5, is_less_than), Some(false));
+
+        // comparisons are unsigned
+        assert_eq!(test(0xFFFFFFFF, is_less_than), Some(true));
+    }
+
+    #[test]
+    fn evaluate_condition_16_bit_mask() {
+        // the bits set in the mask are cleared, leaving 0x1200
+        let test = |rhs_value, f: fn(bool, u32, u16, u16) -> IfTest| condition(f(true, 0x1004, 0x00FF, rhs_value));
+        let is_equal        = |use_base_address, address, lhs_mask, rhs_value| IfTest::IsEqualMask       { use_base_address, address, lhs_mask, rhs_value };
+        let is_not_equal    = |use_base_address, address, lhs_mask, rhs_value| IfTest::IsNotEqualMask    { use_base_address, address, lhs_mask, rhs_value };
+        let is_greater_than = |use_base_address, address, lhs_mask, rhs_value| IfTest::IsGreaterThanMask { use_base_address, address, lhs_mask, rhs_value };
+        let is_less_than    = |use_base_address, address, lhs_mask, rhs_value| IfTest::IsLessThanMask    { use_base_address, address, lhs_mask, rhs_value };
+
+        assert_eq!(test(0x1200, is_equal), Some(true));
+        assert_eq!(test(0x1234, is_equal), Some(false));
+        assert_eq!(test(0x1234, is_not_equal), Some(true));
+        assert_eq!(test(0x1200, is_not_equal), Some(false));
+        assert_eq!(test(0x11FF, is_greater_than), Some(true));
+        assert_eq!(test(0x1200, is_greater_than), Some(false));
+        assert_eq!(test(0x1201, is_less_than), Some(true));
+        assert_eq!(test(0x1200, is_less_than), Some(false));
+    }
+
+    #[test]
+    fn evaluate_condition_unknown() {
+        let memory = BufferView::new(&CONDITION_DATA, RamAddress::new(0x80001000));
+        let is_equal = IfTest::IsEqual { use_base_address: true, address: 0x1000, value: 5 };
+        let is_equal_po = IfTest::IsEqual { use_base_address: false, address: 0x1000, value: 5 };
+
+        // outside of the buffer
+        assert_eq!(condition(IfTest::IsEqual { use_base_address: true, address: 0x2000, value: 0 }), None);
+        assert_eq!(condition(IfTest::IsEqualMask { use_base_address: true, address: 0x1008, lhs_mask: 0, rhs_value: 0 }), None);
+        // partially outside of the buffer
+        assert_eq!(condition(IfTest::IsEqual { use_base_address: true, address: 0x1006, value: 0 }), None);
+
+        // the address depends on an unknown ba or po
+        assert_eq!(evaluate_condition(&is_equal, None, Some(DEFAULT_POINTER_ADDRESS), &memory), None);
+        assert_eq!(evaluate_condition(&is_equal_po, Some(DEFAULT_BASE_ADDRESS), None, &memory), None);
+        assert_eq!(evaluate_condition(&is_equal_po, None, Some(DEFAULT_POINTER_ADDRESS), &memory), Some(true));
+
+        // every address in MEM1 and MEM2 is known when running against WiiMemory
+        let wii_memory = WiiMemory::new();
+        assert_eq!(evaluate_condition(&IfTest::IsEqual { use_base_address: true, address: 0x2000, value: 0 }, Some(DEFAULT_BASE_ADDRESS), None, &wii_memory), Some(true));
+        assert_eq!(evaluate_condition(&IfTest::IsEqual { use_base_address: true, address: 0x1000, value: 0 }, Some(0x70000000), None, &wii_memory), None);
+    }
+
+    #[test]
+    fn resolve_targets_prunes_impossible_branch() {
+        let lines = [
+            (0x20001000, 0x00000005), // if ba + 0x1000 == 5
+            (0x4A000000, 0x81000000), // po = 0x81000000
+            (0x14000010, 0x00000001),
+            (0xE2000001, 0x00000000), // endif
+            (0x14000020, 0x00000001),
+        ];
+
+        // the test passes so po is known after the endif
+        let memory = BufferView::new(&CONDITION_DATA, RamAddress::new(0x80001000));
+        assert_eq!(resolve_targets_with_memory(&codes(&lines), &memory), vec!(
+            (CodePath (vec!(0, 1)), Some(0x81000010)),
+            (CodePath (vec!(2)), Some(0x81000020)),
+        ));
+
+        // the test fails so the write is never run and po is untouched
+        let memory = BufferView::new(&[0, 0, 0, 6], RamAddress::new(0x80001000));
+        assert_eq!(resolve_targets_with_memory(&codes(&lines), &memory), vec!(
+            (CodePath (vec!(2)), Some(0x80000020)),
+        ));
+        assert_eq!(write_ranges_with_memory(&codes(&lines), &memory), vec!((CodePath (vec!(2)), 0x80000020..0x80000024)));
+
+        // the tested value is not known so both possibilities remain
+        let memory = BufferView::new(&[], RamAddress::new(0x80001000));
+        assert_eq!(resolve_targets_with_memory(&codes(&lines), &memory), resolve_targets(&codes(&lines)));
+    }
+
+    fn decode(lines: &[(u32, u32)]) -> Vec<String> {
+        codes(lines).codes.iter().map(|x| format!("{:?}", x)).collect()
+    }
+
+    #[test]
+    fn gecko_register_codes() {
+        assert_eq!(decode(&[
+            (0x80000003, 0x12345678), // gr3 = 0x12345678
+            (0x80010003, 0x00000010), // gr3 = 0x10 + ba
+            (0x90010003, 0x00000010), // gr3 = 0x10 + po
+            (0x80100003, 0x00000001), // gr3 += 1
+            (0x90110003, 0x00000001), // gr3 += 1 + po
+        ]), vec!(
+            "SetGeckoRegister { add_result: false, add: None, register: 3, value: 305419896 }",
+            "SetGeckoRegister { add_result: false, add: BaseAddress, register: 3, value: 16 }",
+            "SetGeckoRegister { add_result: false, add: PointerAddress, register: 3, value: 16 }",
+            "SetGeckoRegister { add_result: true, add: None, register: 3, value: 1 }",
+            "SetGeckoRegister { add_result: true, add: PointerAddress, register: 3, value: 1 }",
+        ));
+
+        assert_eq!(decode(&[
+            (0x82200005, 0x80001000), // gr5 = 32 bit value at 0x80001000
+            (0x82110005, 0x00000010), // gr5 = 16 bit value at ba + 0x10
+            (0x92010005, 0x00000010), // gr5 = 8 bit value at po + 0x10
+            (0x84200005, 0x80001000), // 32 bit value at 0x80001000 = gr5
+            (0x842000F5, 0x80001000), // 32 bit values at 0x80001000 to 0x8000103C = gr5
+            (0x94110025, 0x00000010), // 16 bit values at po + 0x10 to po + 0x14 = gr5
+        ]), vec!(
+            "LoadGeckoRegister { value_size: 2, add_mem_address: None, register: 5, mem_address: 2147487744 }",
+            "LoadGeckoRegister { value_size: 1, add_mem_address: BaseAddress, register: 5, mem_address: 16 }",
+            "LoadGeckoRegister { value_size: 0, add_mem_address: PointerAddress, register: 5, mem_address: 16 }",
+            "StoreGeckoRegister { value_size: 2, add_mem_address: None, repeat: 0, register: 5, mem_address: 2147487744 }",
+            "StoreGeckoRegister { value_size: 2, add_mem_address: None, repeat: 15, register: 5, mem_address: 2147487744 }",
+            "StoreGeckoRegister { value_size: 1, add_mem_address: PointerAddress, repeat: 2, register: 5, mem_address: 16 }",
+        ));
+
+        assert_eq!(decode(&[
+            (0x86000002, 0x00000003), // gr2 = gr2 + 3
+            (0x86100002, 0x00000003), // gr2 = gr2 * 3
+            (0x86A30002, 0x80001000), // [gr2] = [gr2] + [0x80001000] as floats
+            (0x88200001, 0x00000004), // gr1 = gr1 | gr4
+            (0x88B10001, 0x00000004), // [gr1] = [gr1] * gr4 as floats
+        ]), vec!(
+            "OperationGeckoRegisterDirectValue { operation: Add, load_register: false, load_value: false, register: 2, value: 3 }",
+            "OperationGeckoRegisterDirectValue { operation: Mul, load_register: false, load_value: false, register: 2, value: 3 }",
+            "OperationGeckoRegisterDirectValue { operation: FloatAdd, load_register: true, load_value: true, register: 2, value: 2147487744 }",
+            "OperationGeckoRegister { operation: Or, load_register1: false, load_register2: false, register1: 1, register2: 4 }",
+            "OperationGeckoRegister { operation: FloatMul, load_register1: true, load_register2: false, register1: 1, register2: 4 }",
+        ));
+
+        assert_eq!(decode(&[
+            (0x8A001023, 0x00000100), // copy 0x10 bytes from [gr2] to [gr3] + 0x100
+            (0x8A00102F, 0x00000100), // copy 0x10 bytes from [gr2] to ba + 0x100
+            (0x8C001023, 0x00000100), // copy 0x10 bytes from [gr2] + 0x100 to [gr3]
+            (0x9C0010F3, 0x00000100), // copy 0x10 bytes from po + 0x100 to [gr3]
+        ]), vec!(
+            "MemoryCopy1 { use_base_address: true, count: 16, source_register: 2, dest_register: Some(3), dest_offset: 256 }",
+            "MemoryCopy1 { use_base_address: true, count: 16, source_register: 2, dest_register: None, dest_offset: 256 }",
+            "MemoryCopy2 { use_base_address: true, count: 16, source_register: Some(2), dest_register: 3, source_offset: 256 }",
+            "MemoryCopy2 { use_base_address: false, count: 16, source_register: None, dest_register: 3, source_offset: 256 }",
+        ));
+    }
+
+    #[test]
+    fn address_codes_are_stable() {
+        // the bits selecting ba/po and the 25th address bit do not change the code type of the other families
+        assert_eq!(decode(&[
+            (0x04001234, 0x00000001),
+            (0x15001234, 0x00000001),
+            (0x4A000000, 0x80000000),
+            (0x4A100000, 0x80000000),
+        ]), vec!(
+            "WriteAndFill32 { use_base_address: true, address: 4660, value: 1 }",
+            "WriteAndFill32 { use_base_address: false, address: 16781876, value: 1 }",
+            "SetPointerAddress { add_result: false, add: None, add_gecko_register: None, value: 2147483648 }",
+            "SetPointerAddress { add_result: true, add: None, add_gecko_register: None, value: 2147483648 }",
+        ));
+    }
+
+    #[test]
+    fn merge_conflicts() {
+        let a = codes(&[
+            (0x80000003, 0x00000001), // gr3 = 1
+            (0x60000004, 0x00000002), // repeat 4 times with block id 2
+            (0x84200003, 0x80001000), // 32 bit value at 0x80001000 = gr3
+            (0x62000000, 0x00000002), // execute repeat block id 2
+            (0x80000005, 0x00000001), // gr5 = 1
+        ]);
+        let b = codes(&[
+            (0x20001000, 0x00000000), // if ba + 0x1000 == 0
+            (0x86000003, 0x00000001), // gr3 = gr3 + 1
+            (0x68000001, 0x00000002), // subroutine with block id 2
+            (0xE2000001, 0x00000000), // endif
+            (0x84200005, 0x80001000), // 32 bit value at 0x80001000 = gr5
+            (0x84200006, 0x80001000), // 32 bit value at 0x80001000 = gr6
+        ]);
+
+        let usage = resource_usage(&b);
+        assert_eq!(usage.registers[3].reads, vec!(CodePath (vec!(0, 0))));
+        assert_eq!(usage.registers[3].writes, vec!(CodePath (vec!(0, 0))));
+        assert_eq!(usage.block_ids[2].writes, vec!(CodePath (vec!(0, 1))));
+        assert_eq!(usage.registers[6].reads, vec!(CodePath (vec!(3))));
+
+        let conflicts = check_merge_conflicts(&a, &b);
+        assert_eq!(conflicts, vec!(
+            MergeConflict {
+                resource: MergeResource::GeckoRegister (3),
+                a: ResourceUse { reads: vec!(CodePath (vec!(1, 0))), writes: vec!(CodePath (vec!(0))) },
+                b: ResourceUse { reads: vec!(CodePath (vec!(0, 0))), writes: vec!(CodePath (vec!(0, 0))) },
+            },
+            // a writes gr5 and b reads it
+            MergeConflict {
+                resource: MergeResource::GeckoRegister (5),
+                a: ResourceUse { reads: vec!(), writes: vec!(CodePath (vec!(2))) },
+                b: ResourceUse { reads: vec!(CodePath (vec!(2))), writes: vec!() },
+            },
+            MergeConflict {
+                resource: MergeResource::BlockId (2),
+                a: ResourceUse { reads: vec!(CodePath (vec!(1))), writes: vec!(CodePath (vec!(1))) },
+                b: ResourceUse { reads: vec!(), writes: vec!(CodePath (vec!(0, 1))) },
+            },
+        ));
+
+        // only reading the same register is not a conflict
+        let reads_gr6 = codes(&[(0x84200006, 0x80001004)]);
+        assert_eq!(check_merge_conflicts(&b, &reads_gr6), vec!());
+    }
+
+    #[test]
+    fn named_code_merge_conflicts() {
+        let code = |name: &str, enabled, lines: &[(u32, u32)]| NamedCode {
+            name: name.into(),
+            comments: vec!(),
+            enabled,
+            data: lines.iter().flat_map(|(left, right)| left.to_be_bytes().iter().chain(right.to_be_bytes().iter()).cloned().collect::<Vec<_>>()).collect(),
+        };
+        let codes = [
+            code("Sets gr3 [first]", true, &[(0x80000003, 0x00000001)]),
+            code("Stores gr3 [second]", true, &[(0x84200003, 0x80001000)]),
+            code("Also sets gr3 [disabled]", false, &[(0x80000003, 0x00000002)]),
+        ];
+
+        let conflicts = check_named_code_conflicts(&codes);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].a_name, "Sets gr3 [first]");
+        assert_eq!(conflicts[0].b_name, "Stores gr3 [second]");
+        assert_eq!(conflicts[0].conflict.resource, MergeResource::GeckoRegister (3));
+    }
+}
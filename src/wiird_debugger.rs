@@ -0,0 +1,276 @@
+//! Steps through a codeset one code at a time, so the state of the code handler can be inspected between codes.
+
+use std::ops::Range;
+
+use crate::address::RamAddress;
+use crate::wii_memory::WiiMemory;
+use crate::wiird::{CodePath, wiird_codes_with_spans};
+use crate::wiird_runner::{Runner, RunConfig, Step};
+
+/// Runs a codeset one code at a time.
+///
+/// Codes are executed by the same runner as `wiird_runner::run`, each step executes one line of the codeset.
+/// So the codes of an if statement that failed its test are still stepped through, and repeats are stepped through once per iteration.
+/// Codes are identified by their `CodePath` in the `WiiRDBlock` parsed from the codeset.
+/// The lines that belong to another code e.g. the terminator of an if statement or repeat, use the path of that code.
+pub struct WiirdDebugger<'a> {
+    runner: Runner<'a>,
+    /// The `CodePath` and bytes of each code in the codeset
+    spans: Vec<(CodePath, Range<usize>)>,
+    state: DebuggerState,
+    breakpoints: Vec<BreakCondition>,
+    /// Set when the last `run_until_break` stopped at a breakpoint, so the next one does not stop at the same code again
+    at_breakpoint: bool,
+}
+
+/// The state of the code handler between two codes
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct DebuggerState {
+    /// The code executed by the next step, None when every code has been executed
+    pub next_code: Option<CodePath>,
+    pub base_address: u32,
+    pub pointer_address: u32,
+    pub gecko_registers: [u32; 0x10],
+    pub last_write: Option<DebuggerWrite>,
+}
+
+/// The data written to memory by a code
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct DebuggerWrite {
+    pub code: CodePath,
+    pub address: u32,
+    pub data: Vec<u8>,
+}
+
+impl DebuggerWrite {
+    pub fn range(&self) -> Range<u32> {
+        self.address .. self.address.wrapping_add(self.data.len() as u32)
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum BreakCondition {
+    /// Break before a code that writes to any address in the range
+    WriteTo (Range<u32>),
+    /// Break before the code is executed
+    CodePath (CodePath),
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub enum StepResult {
+    /// The code was executed
+    Executed (CodePath),
+    /// The code is in an if statement that failed its test, so it had no effect
+    Skipped (CodePath),
+    /// The code has no effect because executing codes of this type is not implemented
+    Unsupported { code: CodePath, code_type: u8 },
+    /// Stopped before executing the code because of the breakpoint at this index into the added breakpoints
+    Breakpoint { code: CodePath, breakpoint: usize },
+    /// The code could not be read, so no further codes can be executed
+    Stopped (CodePath),
+    /// Every code has been executed
+    Finished,
+}
+
+impl<'a> WiirdDebugger<'a> {
+    /// Creates a debugger for `codeset` on a wii memory containing `buffer` at `ram_base`.
+    /// Writes to the buffers location are also applied to `buffer`.
+    pub fn new(codeset: &'a [u8], buffer: &'a mut [u8], ram_base: RamAddress, config: RunConfig) -> WiirdDebugger<'a> {
+        let (_, spans) = wiird_codes_with_spans(codeset);
+        let mut debugger = WiirdDebugger {
+            runner: Runner::new(codeset, buffer, ram_base, config),
+            spans,
+            state: DebuggerState {
+                next_code:       None,
+                base_address:    0,
+                pointer_address: 0,
+                gecko_registers: [0; 0x10],
+                last_write:      None,
+            },
+            breakpoints: vec!(),
+            at_breakpoint: false,
+        };
+        debugger.update_state();
+        debugger
+    }
+
+    pub fn state(&self) -> &DebuggerState {
+        &self.state
+    }
+
+    pub fn memory(&self) -> &WiiMemory {
+        &self.runner.memory
+    }
+
+    /// Returns the index of the breakpoint, used to identify it in `StepResult::Breakpoint`
+    pub fn add_breakpoint(&mut self, condition: BreakCondition) -> usize {
+        self.breakpoints.push(condition);
+        self.breakpoints.len() - 1
+    }
+
+    /// Executes the next code, ignoring breakpoints.
+    pub fn step(&mut self) -> StepResult {
+        let writes = self.runner.pending_writes();
+        let result = match self.runner.step() {
+            Step::Executed { offset }          => StepResult::Executed (self.code_path(offset)),
+            Step::Skipped { offset }           => StepResult::Skipped (self.code_path(offset)),
+            Step::Unsupported { offset, code } => StepResult::Unsupported { code: self.code_path(offset), code_type: code },
+            Step::Stopped { offset }           => StepResult::Stopped (self.code_path(offset)),
+            Step::Finished                     => return StepResult::Finished,
+        };
+        self.at_breakpoint = false;
+        if let Some((address, data)) = writes.into_iter().last() {
+            let code = self.state.next_code.clone().unwrap();
+            self.state.last_write = Some(DebuggerWrite { code, address, data });
+        }
+        self.update_state();
+        result
+    }
+
+    /// Steps until a breakpoint matches the next code or every code has been executed.
+    /// When called again after stopping at a breakpoint, execution continues past it.
+    pub fn run_until_break(&mut self) -> StepResult {
+        loop {
+            if !self.at_breakpoint {
+                if let Some(result) = self.matching_breakpoint() {
+                    self.at_breakpoint = true;
+                    return result;
+                }
+            }
+            match self.step() {
+                result @ StepResult::Finished |
+                result @ StepResult::Stopped (_) => return result,
+                _ => { }
+            }
+        }
+    }
+
+    fn matching_breakpoint(&self) -> Option<StepResult> {
+        let path = self.state.next_code.as_ref()?;
+        let writes = self.runner.pending_writes();
+        for (i, breakpoint) in self.breakpoints.iter().enumerate() {
+            let matches = match breakpoint {
+                BreakCondition::CodePath (break_path) => break_path == path,
+                BreakCondition::WriteTo (range) => writes.iter().any(|(address, data)| {
+                    let end = address.wrapping_add(data.len() as u32);
+                    *address < range.end && range.start < end
+                }),
+            };
+            if matches {
+                return Some(StepResult::Breakpoint { code: path.clone(), breakpoint: i });
+            }
+        }
+        None
+    }
+
+    /// The path of the innermost code that the codeset line at `offset` belongs to
+    fn code_path(&self, offset: usize) -> CodePath {
+        self.spans.iter()
+            .filter(|(_, span)| span.contains(&offset))
+            .max_by_key(|(path, _)| path.0.len())
+            .map(|(path, _)| path.clone())
+            .unwrap_or_else(|| CodePath (vec!()))
+    }
+
+    fn update_state(&mut self) {
+        self.state.next_code = self.runner.next_offset().map(|offset| self.code_path(offset));
+        self.state.base_address = self.runner.base_address;
+        self.state.pointer_address = self.runner.pointer_address;
+        self.state.gecko_registers = self.runner.gecko_registers;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codeset(lines: &[(u32, u32)]) -> Vec<u8> {
+        let mut codeset = vec!();
+        for (left, right) in lines {
+            codeset.extend(&left.to_be_bytes());
+            codeset.extend(&right.to_be_bytes());
+        }
+        codeset
+    }
+
+    #[test]
+    fn break_on_write() {
+        let location = RamAddress::new(0x80F9FC20);
+        let mut buffer = vec!(0; 0x20);
+        let codeset = codeset(&[
+            (0x04F9FC20, 0x00000001), // 32 bit write to buffer + 0
+            (0x4A000000, 0x80F9FC20), // po = 0x80F9FC20
+            (0x80000003, 0x00000007), // gr3 = 7
+            (0x20F9FC20, 0x00000001), // if [buffer + 0] == 1
+            (0x14000010, 0xDEADBEEF), //     32 bit write to po + 0x10
+            (0xE2000001, 0x00000000), // endif
+            (0x14000018, 0x11111111), // 32 bit write to po + 0x18
+        ]);
+
+        let mut debugger = WiirdDebugger::new(&codeset, &mut buffer, location, RunConfig::default());
+        let breakpoint = debugger.add_breakpoint(BreakCondition::WriteTo (0x80F9FC30 .. 0x80F9FC34));
+
+        assert_eq!(debugger.run_until_break(), StepResult::Breakpoint { code: CodePath (vec!(3, 0)), breakpoint });
+        let state = debugger.state();
+        assert_eq!(state.next_code, Some(CodePath (vec!(3, 0))));
+        assert_eq!(state.pointer_address, 0x80F9FC20);
+        assert_eq!(state.gecko_registers[3], 7);
+        assert_eq!(state.last_write, Some(DebuggerWrite { code: CodePath (vec!(0)), address: 0x80F9FC20, data: vec!(0, 0, 0, 1) }));
+        assert_eq!(debugger.memory().read_u32(RamAddress::new(0x80F9FC30)), 0);
+
+        // continuing executes the breakpoint code and then runs to the end
+        assert_eq!(debugger.run_until_break(), StepResult::Finished);
+        assert_eq!(debugger.state().next_code, None);
+        assert_eq!(debugger.state().last_write.as_ref().unwrap().range(), 0x80F9FC38 .. 0x80F9FC3C);
+        assert_eq!(debugger.step(), StepResult::Finished);
+
+        assert_eq!(&buffer[0x10..0x14], &[0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(&buffer[0x18..0x1C], &[0x11, 0x11, 0x11, 0x11]);
+    }
+
+    #[test]
+    fn step_through_branches_and_repeat() {
+        let location = RamAddress::new(0x80F9FC20);
+        let mut buffer = vec!(0; 0x20);
+        let codeset = codeset(&[
+            (0x20F9FC20, 0x00000001), // if [buffer + 0] == 1
+            (0x04F9FC24, 0x00000001), //     32 bit write to buffer + 4
+            (0xE2000001, 0x00000000), // endif
+            (0x20F9FC20, 0x00000000), // if [buffer + 0] == 0
+            (0x04F9FC28, 0x00000002), //     32 bit write to buffer + 8
+            (0xE2000001, 0x00000000), // endif
+            (0x60000001, 0x00000000), // repeat twice
+            (0x84000000, 0x00000000), //     store gr0, ignored
+            (0x62000000, 0x00000000), // execute repeat
+        ]);
+
+        let mut debugger = WiirdDebugger::new(&codeset, &mut buffer, location, RunConfig::default());
+        let mut results = vec!();
+        loop {
+            match debugger.step() {
+                StepResult::Finished => break,
+                result => results.push(result),
+            }
+        }
+        let path = |path: &[usize]| CodePath (path.to_vec());
+        // the lines terminating an if statement or repeat belong to it, the codes of a failed if statement are skipped
+        assert_eq!(results, vec!(
+            StepResult::Executed (path(&[0])),
+            StepResult::Skipped (path(&[0, 0])),
+            StepResult::Skipped (path(&[0])),
+            StepResult::Executed (path(&[2])),
+            StepResult::Executed (path(&[2, 0])),
+            StepResult::Executed (path(&[2])),
+            StepResult::Executed (path(&[4])),
+            StepResult::Unsupported { code: path(&[4, 0]), code_type: 0x84 },
+            StepResult::Executed (path(&[4])),
+            StepResult::Unsupported { code: path(&[4, 0]), code_type: 0x84 },
+            StepResult::Executed (path(&[4])),
+        ));
+        assert_eq!(&buffer[4..0xC], &[0, 0, 0, 0, 0, 0, 0, 2]);
+    }
+}
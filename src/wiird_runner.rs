@@ -1,142 +1,170 @@
-use byteorder::{BigEndian, ByteOrder, ReadBytesExt};
+use byteorder::{BigEndian, ReadBytesExt};
 
-use crate::wiird::{GeckoOperation, AddAddress, JumpFlag};
+use crate::wiird::{GeckoOperation, AddAddress, JumpFlag, IfTest, code_address, evaluate_condition, DEFAULT_BASE_ADDRESS, DEFAULT_POINTER_ADDRESS};
 
+use crate::address::{RamAddress, LoadedRegion};
 use crate::wii_memory::WiiMemory;
+use crate::ppc;
+
+/// Options for `run`
+#[derive(Clone, Debug, Default)]
+pub struct RunConfig {
+    /// Interpret ExecutePPC codes that only load constants into registers and store them to fixed addresses.
+    /// Codes that do anything else are still recorded but not executed.
+    pub interpret_ppc: bool,
+}
 
-pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: u32) -> WiiMemory {
-    let mut memory = WiiMemory::new();
-    let mut gecko_registers = [0_u32; 0x10];
-    let mut base_address    = 0x80000000;
-    let mut pointer_address = 0x80000000;
-
-    let mut execution_stack: Vec<bool> = vec!();
+/// The result of running a codeset
+pub struct RunReport {
+    pub memory: WiiMemory,
+    /// Every ExecutePPC and InsertPPC code in the codeset, in order
+    pub ppc_patches: Vec<PpcPatch>,
+}
 
-    // write buffer to memory
-    for (i, value) in buffer.iter().enumerate() {
-        memory.write_u8(buffer_ram_location as usize + i, *value);
-    }
+/// An ExecutePPC (C0) or InsertPPC (C2) code.
+/// The runner cannot execute PowerPC so these are recorded instead.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-full", derive(Deserialize))]
+pub struct PpcPatch {
+    /// The address the instructions are inserted at, None for ExecutePPC codes
+    pub address: Option<u32>,
+    pub instruction_count: usize,
+    /// None when an instruction is outside the small subset that can be disassembled
+    pub disassembly: Option<Vec<String>>,
+    /// The code was interpreted and its writes applied, only possible when `RunConfig::interpret_ppc` is set
+    pub interpreted: bool,
+}
 
-    let mut offset = 0;
-    while offset < codeset.len() {
-        // Not every code type uses this, but its safe to just create these for if we need them.
-        let use_base_address = codeset[offset] & 0b00010000 == 0;
-        let address = (&codeset[offset ..]).read_u32::<BigEndian>().unwrap() & 0x1FFFFFF;
+/// Runs the codeset with the default `RunConfig` and returns the resulting memory.
+pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: RamAddress) -> WiiMemory {
+    run(codeset, buffer, buffer_ram_location, &RunConfig::default()).memory
+}
 
-        let code = codeset[offset] & 0b11101110;
-        let execute = execution_stack.last().cloned().unwrap_or(true);
-        match code {
-            0x00 => {
-                let value = codeset[offset + 7];
-                let length = (&codeset[offset + 4..]).read_u16::<BigEndian>().unwrap() as u32 + 1;
+/// Runs the codeset on a wii memory containing `buffer` at `buffer_ram_location`.
+/// Writes to the buffers location are also applied to `buffer`.
+pub fn run(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: RamAddress, config: &RunConfig) -> RunReport {
+    phase_span!("wiird_apply", codeset_bytes = codeset.len(), bytes = buffer.len(), ram_location = ?buffer_ram_location);
+    let mut runner = Runner::new(codeset, buffer, buffer_ram_location, config.clone());
+    while runner.step() != Step::Finished { }
+    RunReport { memory: runner.memory, ppc_patches: runner.ppc_patches }
+}
 
-                let mem_address = if use_base_address {
-                    (base_address & 0xFE000000) + address
-                } else {
-                    pointer_address + address
-                };
+/// The result of executing a single code with `Runner::step`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Step {
+    /// The code at this offset into the codeset was executed
+    Executed { offset: usize },
+    /// The code at this offset is in an if statement that failed its test, so only its effect on the if statement nesting was applied
+    Skipped { offset: usize },
+    /// The code at this offset was read so the following code could be found, but executing it is not implemented
+    Unsupported { offset: usize, code: u8 },
+    /// The code at this offset could not be read, so the codes following it can not be found
+    Stopped { offset: usize },
+    /// The end of the codeset was reached
+    Finished,
+}
 
-                if execute {
-                    for i in 0..length {
-                        let current_address = mem_address + i;
+/// Executes a codeset one code at a time, keeping the state of the code handler between codes.
+pub(crate) struct Runner<'a> {
+    codeset: &'a [u8],
+    buffer: &'a mut [u8],
+    region: LoadedRegion,
+    config: RunConfig,
+    pub memory: WiiMemory,
+    pub ppc_patches: Vec<PpcPatch>,
+    pub gecko_registers: [u32; 0x10],
+    pub base_address: u32,
+    pub pointer_address: u32,
+    execution_stack: Vec<bool>,
+    /// Stores the (offset, count) set by SetRepeat for each block_id
+    repeat_blocks: [(usize, u16); 0x10],
+    /// The offset into the codeset of the next code
+    offset: usize,
+}
 
-                        // write to wii ram
-                        memory.write_u8(current_address as usize, value);
+impl<'a> Runner<'a> {
+    pub fn new(codeset: &'a [u8], buffer: &'a mut [u8], buffer_ram_location: RamAddress, config: RunConfig) -> Runner<'a> {
+        let mut memory = WiiMemory::new();
 
-                        // also write to the provided buffer if it would have been written to on a wii.
-                        if current_address >= buffer_ram_location && current_address < buffer_ram_location + buffer.len() as u32 {
-                            let buffer_offset = current_address - buffer_ram_location;
-                            buffer[buffer_offset as usize] = value;
-                        }
-                    }
-                }
+        // write buffer to memory
+        for (i, value) in buffer.iter().enumerate() {
+            memory.write_u8(buffer_ram_location + i as u32, *value);
+        }
 
-                offset += 8;
-            }
-            0x02 => {
-                let value = (&codeset[offset + 6..]).read_u16::<BigEndian>().unwrap();
-                let length = (&codeset[offset + 4..]).read_u16::<BigEndian>().unwrap() as u32 + 1;
+        Runner {
+            codeset,
+            region:          LoadedRegion::new(buffer_ram_location, buffer.len() as u32),
+            buffer,
+            config,
+            memory,
+            ppc_patches:     vec!(),
+            gecko_registers: [0; 0x10],
+            base_address:    DEFAULT_BASE_ADDRESS,
+            pointer_address: DEFAULT_POINTER_ADDRESS,
+            execution_stack: vec!(),
+            repeat_blocks:   [(0, 0); 0x10],
+            offset:          0,
+        }
+    }
 
-                let mem_address = if use_base_address {
-                    (base_address & 0xFE000000) + address
-                } else {
-                    pointer_address + address
-                };
+    /// The offset into the codeset of the code executed by the next step, None when the end of the codeset has been reached
+    pub fn next_offset(&self) -> Option<usize> {
+        if self.offset < self.codeset.len() && self.codeset[self.offset] != 0xF0 {
+            Some(self.offset)
+        } else {
+            None
+        }
+    }
 
-                if execute {
-                    for i in 0..length {
-                        let current_address = mem_address + i * 2;
+    /// Returns false while in an if statement that failed its test
+    fn execute(&self) -> bool {
+        self.execution_stack.last().cloned().unwrap_or(true)
+    }
 
-                        // write to wii ram
-                        memory.write_u16(current_address as usize, value);
+    /// Returns the address and data of each write the next step will perform
+    pub fn pending_writes(&self) -> Vec<(u32, Vec<u8>)> {
+        let offset = match self.next_offset() {
+            Some(offset) if self.execute() => offset,
+            _ => return vec!(),
+        };
+        match self.codeset[offset] & 0b11101110 {
+            0x00 | 0x02 | 0x04 | 0x06 => self.write_code(offset).map(|(address, data, _)| vec!((address, data))).unwrap_or_default(),
+            0xC0 => self.interpret_ppc(self.ppc_code(offset).0).unwrap_or_default(),
+            _ => vec!(),
+        }
+    }
 
-                        // also write to the provided buffer if it would have been written to on a wii.
-                        if current_address >= buffer_ram_location && current_address < buffer_ram_location + buffer.len() as u32 {
-                            let buffer_offset = current_address - buffer_ram_location;
-                            BigEndian::write_u16(&mut buffer[buffer_offset as usize..], value);
-                        }
-                    }
-                }
+    fn stop(&mut self, code_offset: usize) -> Step {
+        self.offset = self.codeset.len();
+        Step::Stopped { offset: code_offset }
+    }
 
-                offset += 8;
-            }
-            0x04 => {
-                let value = (&codeset[offset + 4..]).read_u32::<BigEndian>().unwrap();
+    /// Executes the next code
+    pub fn step(&mut self) -> Step {
+        let code_offset = match self.next_offset() {
+            Some(offset) => offset,
+            None => return Step::Finished,
+        };
+        let codeset = self.codeset;
+        let mut offset = code_offset;
+        // Not every code type uses this, but its safe to just create these for if we need them.
+        let use_base_address = codeset[offset] & 0b00010000 == 0;
+        let address = (&codeset[offset ..]).read_u32::<BigEndian>().unwrap() & 0x1FFFFFF;
 
-                let mem_address = if use_base_address {
-                    (base_address & 0xFE000000) + address
-                } else {
-                    pointer_address + address
-                };
+        // F codes dont use the base address or pointer address bits
+        let code = if codeset[offset] >= 0xF0 { codeset[offset] } else { codeset[offset] & 0b11101110 };
+        let execute = self.execute();
+        // Codes that are read so the following codes can be found but are not implemented
+        let mut supported = !matches!(code, 0x08 | 0x46 | 0x4E | 0x64 | 0x68 | 0x82 | 0x84 | 0x86 | 0x88 | 0x8A | 0x8C | 0xC2);
+        match code {
+            0x00 | 0x02 | 0x04 | 0x06 => {
+                let (mem_address, data, length) = self.write_code(code_offset).unwrap();
 
                 if execute {
-                    if mem_address >= buffer_ram_location && mem_address < buffer_ram_location + buffer.len() as u32 {
-                        // write to wii ram
-                        memory.write_u32(mem_address as usize, value);
-
-                        // also write to the provided buffer if it would have been written to on a wii.
-                        let buffer_offset = mem_address - buffer_ram_location;
-                        BigEndian::write_u32(&mut buffer[buffer_offset as usize..], value);
-                    }
+                    write_bytes(&mut self.memory, self.buffer, &self.region, mem_address, &data, Some(code_offset));
                 }
 
-                offset += 8;
-            }
-            0x06 => {
-                let mut values = vec!();
-                let count = (&codeset[offset + 4..]).read_u32::<BigEndian>().unwrap() as usize;
-                for i in 0..count {
-                    values.push(codeset[offset + 8 + i]);
-                }
-
-                offset += 8 + count;
-
-                // align the offset to 8 bytes
-                let count_mod = count % 8;
-                if count_mod != 0 {
-                    offset += 8 - count_mod;
-                }
-
-                let mem_address = if use_base_address {
-                    (base_address & 0xFE000000) + address
-                } else {
-                    pointer_address + address
-                };
-
-                if execute {
-                    for (i, value) in values.iter().enumerate() {
-                        let current_address = mem_address + i as u32;
-
-                        // write to wii ram
-                        memory.write_u8(current_address as usize, *value);
-
-                        // also write to the provided buffer if it would have been written to on a wii.
-                        if current_address >= buffer_ram_location && current_address < buffer_ram_location + buffer.len() as u32 {
-                            let buffer_offset = current_address - buffer_ram_location;
-                            buffer[buffer_offset as usize] = *value;
-                        }
-                    }
-                }
+                offset += length;
             }
             0x08 => {
                 let _initial_value = (&codeset[offset + 4..]).read_u32::<BigEndian>().unwrap();
@@ -149,54 +177,35 @@ pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: u32) -> W
             }
             0x20 | 0x22 | 0x24 | 0x26 | 0x28 | 0x2A | 0x2C | 0x2E => {
                 let value = (&codeset[offset + 4..]).read_u32::<BigEndian>().unwrap();
-                let _lhs_mask = (&codeset[offset + 4..]).read_u16::<BigEndian>().unwrap();
-                let _rhs_value = (&codeset[offset + 6..]).read_u16::<BigEndian>().unwrap();
+                let lhs_mask = (&codeset[offset + 4..]).read_u16::<BigEndian>().unwrap();
+                let rhs_value = (&codeset[offset + 6..]).read_u16::<BigEndian>().unwrap();
 
                 let insert_endif = address & 1 != 0;
                 let address = address & 0xFFFFFFFE;
 
                 if insert_endif {
-                    execution_stack.pop();
+                    self.execution_stack.pop();
                 }
 
-                let mem_address = if use_base_address {
-                    (base_address & 0xFE000000) + address
-                } else {
-                    pointer_address + address
-                };
-
                 if execute {
-                    match code {
-                        0x20 => { // Is equal
-                            execution_stack.push(value == memory.read_u32(mem_address as usize));
-                        }
-                        0x22 => { // Is not equal
-                            execution_stack.push(value != memory.read_u32(mem_address as usize));
-                        }
-                        0x24 => { // Is greater than
-                            execution_stack.push(memory.read_u32(mem_address as usize) > value);
-                        }
-                        0x26 => { // Is less than
-                            execution_stack.push(memory.read_u32(mem_address as usize) < value);
-                        }
-                        0x28 => { // Is equal mask
-                            execution_stack.push(false); // TODO
-                        }
-                        0x2A => { // Is not equal mask
-                            execution_stack.push(false); // TODO
-                        }
-                        0x2C => { // Is greater than mask
-                            execution_stack.push(false); // TODO
-                        }
-                        0x2E => { // Is less than mask
-                            execution_stack.push(false); // TODO
-                        }
-                        _ => unreachable!(),
-                    }
+                    let test = match code {
+                        0x20 => IfTest::IsEqual { use_base_address, address, value },
+                        0x22 => IfTest::IsNotEqual { use_base_address, address, value },
+                        0x24 => IfTest::IsGreaterThan { use_base_address, address, value },
+                        0x26 => IfTest::IsLessThan { use_base_address, address, value },
+                        0x28 => IfTest::IsEqualMask { use_base_address, address, lhs_mask, rhs_value },
+                        0x2A => IfTest::IsNotEqualMask { use_base_address, address, lhs_mask, rhs_value },
+                        0x2C => IfTest::IsGreaterThanMask { use_base_address, address, lhs_mask, rhs_value },
+                        0x2E => IfTest::IsLessThanMask { use_base_address, address, lhs_mask, rhs_value },
+                        _    => unreachable!(),
+                    };
+                    // Memory the codeset and the loaded file never wrote to reads as 0 rather than as the state of the game,
+                    // so tests of that memory are decided as if it were 0. Addresses outside of MEM1 and MEM2 fail the test.
+                    self.execution_stack.push(evaluate_condition(&test, Some(self.base_address), Some(self.pointer_address), &self.memory).unwrap_or(false));
                 }
                 else {
                     // TODO: Probably need this!?!?!
-                    execution_stack.push(execution_stack.last().cloned().unwrap_or(true));
+                    self.execution_stack.push(self.execution_stack.last().cloned().unwrap_or(true));
                 }
 
                 offset += 8;
@@ -223,20 +232,20 @@ pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: u32) -> W
                 if execute {
                     let mut actual_address = mem_address;
                     match add_mem_address {
-                        AddAddress::BaseAddress    => actual_address += base_address,
-                        AddAddress::PointerAddress => actual_address += pointer_address,
+                        AddAddress::BaseAddress    => actual_address = actual_address.wrapping_add(self.base_address),
+                        AddAddress::PointerAddress => actual_address = actual_address.wrapping_add(self.pointer_address),
                         AddAddress::None => { }
                     }
 
                     if let Some(gecko_register) = add_mem_address_gecko_register {
-                        actual_address += gecko_registers[gecko_register as usize];
+                        actual_address = actual_address.wrapping_add(self.gecko_registers[gecko_register as usize]);
                     }
 
                     if add_result {
-                        base_address += memory.read_u32(actual_address as usize);
+                        self.base_address = self.base_address.wrapping_add(self.memory.read_u32(RamAddress::new(actual_address)));
                     }
                     else {
-                        base_address = memory.read_u32(actual_address as usize);
+                        self.base_address = self.memory.read_u32(RamAddress::new(actual_address));
                     }
                 }
 
@@ -264,20 +273,20 @@ pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: u32) -> W
                 if execute {
                     let mut value = value;
                     match add {
-                        AddAddress::BaseAddress    => value += base_address,
-                        AddAddress::PointerAddress => value += pointer_address,
+                        AddAddress::BaseAddress    => value = value.wrapping_add(self.base_address),
+                        AddAddress::PointerAddress => value = value.wrapping_add(self.pointer_address),
                         AddAddress::None => { }
                     }
 
                     if let Some(gecko_register) = add_gecko_register {
-                        value += gecko_registers[gecko_register as usize];
+                        value = value.wrapping_add(self.gecko_registers[gecko_register as usize]);
                     }
 
                     if add_result {
-                        base_address += value;
+                        self.base_address = self.base_address.wrapping_add(value);
                     }
                     else {
-                        base_address = value;
+                        self.base_address = value;
                     }
                 }
 
@@ -304,16 +313,17 @@ pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: u32) -> W
                 if execute {
                     let mut actual_address = mem_address;
                     match add_mem_address {
-                        AddAddress::BaseAddress    => actual_address += base_address,
-                        AddAddress::PointerAddress => actual_address += pointer_address,
+                        AddAddress::BaseAddress    => actual_address = actual_address.wrapping_add(self.base_address),
+                        AddAddress::PointerAddress => actual_address = actual_address.wrapping_add(self.pointer_address),
                         AddAddress::None => { }
                     }
 
                     if let Some(gecko_register) = add_mem_address_gecko_register {
-                        actual_address += gecko_registers[gecko_register as usize];
+                        actual_address = actual_address.wrapping_add(self.gecko_registers[gecko_register as usize]);
                     }
 
-                    memory.write_u32(actual_address as usize, base_address);
+                    self.memory.write_u32(RamAddress::new(actual_address), self.base_address);
+                    self.memory.record_write(actual_address, 4, code_offset);
                 }
 
                 offset += 8;
@@ -323,7 +333,7 @@ pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: u32) -> W
 
                 if execute {
                     // Mess up the value so writes can be ignored while in this state
-                    base_address = 0;
+                    self.base_address = 0;
                 }
 
                 offset += 8;
@@ -350,20 +360,20 @@ pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: u32) -> W
                 if execute {
                     let mut actual_address = mem_address;
                     match add_mem_address {
-                        AddAddress::BaseAddress    => actual_address += base_address,
-                        AddAddress::PointerAddress => actual_address += pointer_address,
+                        AddAddress::BaseAddress    => actual_address = actual_address.wrapping_add(self.base_address),
+                        AddAddress::PointerAddress => actual_address = actual_address.wrapping_add(self.pointer_address),
                         AddAddress::None => { }
                     }
 
                     if let Some(gecko_register) = add_mem_address_gecko_register {
-                        actual_address += gecko_registers[gecko_register as usize];
+                        actual_address = actual_address.wrapping_add(self.gecko_registers[gecko_register as usize]);
                     }
 
                     if add_result {
-                        pointer_address += memory.read_u32(actual_address as usize);
+                        self.pointer_address = self.pointer_address.wrapping_add(self.memory.read_u32(RamAddress::new(actual_address)));
                     }
                     else {
-                        pointer_address = memory.read_u32(actual_address as usize);
+                        self.pointer_address = self.memory.read_u32(RamAddress::new(actual_address));
                     }
                 }
 
@@ -391,20 +401,20 @@ pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: u32) -> W
                 if execute {
                     let mut new_address = new_address;
                     match add {
-                        AddAddress::BaseAddress    => new_address += base_address,
-                        AddAddress::PointerAddress => new_address += pointer_address,
+                        AddAddress::BaseAddress    => new_address = new_address.wrapping_add(self.base_address),
+                        AddAddress::PointerAddress => new_address = new_address.wrapping_add(self.pointer_address),
                         AddAddress::None => { }
                     }
 
                     if let Some(gecko_register) = add_gecko_register {
-                        new_address += gecko_registers[gecko_register as usize];
+                        new_address = new_address.wrapping_add(self.gecko_registers[gecko_register as usize]);
                     }
 
                     if add_result {
-                        pointer_address += new_address;
+                        self.pointer_address = self.pointer_address.wrapping_add(new_address);
                     }
                     else {
-                        pointer_address = new_address;
+                        self.pointer_address = new_address;
                     }
                 }
 
@@ -431,16 +441,17 @@ pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: u32) -> W
                 if execute {
                     let mut actual_address = mem_address;
                     match add_mem_address {
-                        AddAddress::BaseAddress    => actual_address += base_address,
-                        AddAddress::PointerAddress => actual_address += pointer_address,
+                        AddAddress::BaseAddress    => actual_address = actual_address.wrapping_add(self.base_address),
+                        AddAddress::PointerAddress => actual_address = actual_address.wrapping_add(self.pointer_address),
                         AddAddress::None => { }
                     }
 
                     if let Some(gecko_register) = add_mem_address_gecko_register {
-                        actual_address += gecko_registers[gecko_register as usize];
+                        actual_address = actual_address.wrapping_add(self.gecko_registers[gecko_register as usize]);
                     }
 
-                    memory.write_u32(actual_address as usize, pointer_address);
+                    self.memory.write_u32(RamAddress::new(actual_address), self.pointer_address);
+                    self.memory.record_write(actual_address, 4, code_offset);
                 }
 
                 offset += 8;
@@ -449,22 +460,35 @@ pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: u32) -> W
                 let _address_offset = (&codeset[offset + 2..]).read_i16::<BigEndian>().unwrap();
 
                 if execute {
+                    // The codeset is not loaded anywhere in our model of ram, so there is no code location to point at.
                     // Mess up the value so writes can be ignored while in this state
-                    pointer_address = 0;
+                    self.pointer_address = 0;
                 }
 
                 offset += 8;
             }
             0x60 => {
-                let _count = (&codeset[offset + 2..]).read_u16::<BigEndian>().unwrap();
-                let _block_id = codeset[offset + 7];
+                let count = (&codeset[offset + 2..]).read_u16::<BigEndian>().unwrap();
+                let block_id = codeset[offset + 7] & 0xF;
 
                 offset += 8;
+
+                if execute {
+                    self.repeat_blocks[block_id as usize] = (offset, count);
+                }
             }
             0x62 => {
-                let _block_id = codeset[offset + 7] & 0xF;
+                let block_id = codeset[offset + 7] & 0xF;
 
                 offset += 8;
+
+                if execute {
+                    let (repeat_offset, count) = &mut self.repeat_blocks[block_id as usize];
+                    if *count != 0 {
+                        *count -= 1;
+                        offset = *repeat_offset;
+                    }
+                }
             }
             0x64 => {
                 let _flag = match codeset[offset + 1] {
@@ -473,7 +497,7 @@ pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: u32) -> W
                     0x20 => JumpFlag::Always,
                     flag => {
                         error!("Unknown jump flag '{}' in return", flag);
-                        break;
+                        return self.stop(code_offset);
                     }
                 };
                 let _block_id = codeset[offset + 7] & 0xF;
@@ -487,7 +511,7 @@ pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: u32) -> W
                     0x20 => JumpFlag::Always,
                     flag => {
                         error!("Unknown jump flag '{}' in goto", flag);
-                        break;
+                        return self.stop(code_offset);
                     }
                 };
 
@@ -518,7 +542,7 @@ pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: u32) -> W
                     0x20 => JumpFlag::Always,
                     flag => {
                         error!("Unknown jump flag '{}' in subroutine", flag);
-                        break;
+                        return self.stop(code_offset);
                     }
                 };
                 let _offset_lines = (&codeset[offset + 2..]).read_i16::<BigEndian>().unwrap();
@@ -541,16 +565,16 @@ pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: u32) -> W
                     };
 
                     match add {
-                        AddAddress::BaseAddress    => new_value += base_address,
-                        AddAddress::PointerAddress => new_value += pointer_address,
+                        AddAddress::BaseAddress    => new_value = new_value.wrapping_add(self.base_address),
+                        AddAddress::PointerAddress => new_value = new_value.wrapping_add(self.pointer_address),
                         AddAddress::None => { }
                     }
 
                     if add_result {
-                        gecko_registers[register as usize] += new_value;
+                        self.gecko_registers[register as usize] = self.gecko_registers[register as usize].wrapping_add(new_value);
                     }
                     else {
-                        gecko_registers[register as usize] = new_value;
+                        self.gecko_registers[register as usize] = new_value;
                     }
                 }
 
@@ -573,7 +597,7 @@ pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: u32) -> W
                 offset += 8;
             }
             0x86 => {
-                let operation_byte = codeset[offset + 1] & 0xF0;
+                let operation_byte = codeset[offset + 1] >> 4;
                 let _load_register  = codeset[offset + 1] & 0b00000001 != 0;
                 let _load_value     = codeset[offset + 1] & 0b00000010 != 0;
                 let _register       = codeset[offset + 3] & 0x0F;
@@ -584,7 +608,7 @@ pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: u32) -> W
                 offset += 8;
             }
             0x88 => {
-                let operation_byte = codeset[offset + 1] & 0xF0;
+                let operation_byte = codeset[offset + 1] >> 4;
                 let _load_register1 = codeset[offset + 1] & 0b00000001 != 0;
                 let _load_register2 = codeset[offset + 1] & 0b00000010 != 0;
                 let _register1      = codeset[offset + 3] & 0x0F;
@@ -596,7 +620,7 @@ pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: u32) -> W
             }
             0x8A => {
                 let _count           = (&codeset[offset + 1..]).read_u16::<BigEndian>().unwrap();
-                let _source_register =   codeset[offset + 3] & 0xF0;
+                let _source_register =   codeset[offset + 3] >> 4;
                 let _dest_register   =   codeset[offset + 3] & 0x0F;
                 let _dest_offset     = (&codeset[offset + 4..]).read_u32::<BigEndian>().unwrap();
 
@@ -604,41 +628,55 @@ pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: u32) -> W
             }
             0x8C => {
                 let _count           = (&codeset[offset + 1..]).read_u16::<BigEndian>().unwrap();
-                let _source_register =   codeset[offset + 3] & 0xF0;
+                let _source_register =   codeset[offset + 3] >> 4;
                 let _dest_register   =   codeset[offset + 3] & 0x0F;
                 let _source_offset   = (&codeset[offset + 4..]).read_u32::<BigEndian>().unwrap();
 
                 offset += 8;
             }
             0xC0 => {
-                let mut instruction_data = vec!();
-                let count = (&codeset[offset + 4..]).read_u32::<BigEndian>().unwrap() as usize;
-                for i in 0..count * 8 {
-                    instruction_data.push(codeset[offset + 8 + i]);
+                let (instruction_data, length) = self.ppc_code(code_offset);
+
+                let writes = if execute { self.interpret_ppc(instruction_data) } else { None };
+                if let Some(writes) = &writes {
+                    for (address, data) in writes {
+                        write_bytes(&mut self.memory, self.buffer, &self.region, *address, data, Some(code_offset));
+                    }
                 }
+                supported = writes.is_some();
 
-                offset += 8 + count * 8;
+                self.ppc_patches.push(PpcPatch {
+                    address:           None,
+                    instruction_count: instruction_data.len() / 4,
+                    disassembly:       ppc::disassemble(instruction_data),
+                    interpreted:       writes.is_some(),
+                });
+
+                offset += length;
             }
             0xC2 => {
-                let mut instruction_data = vec!();
-                let count = (&codeset[offset + 4..]).read_u32::<BigEndian>().unwrap() as usize;
-                for i in 0..count * 8 {
-                    instruction_data.push(codeset[offset + 8 + i]);
-                }
+                let (instruction_data, length) = self.ppc_code(code_offset);
 
-                offset += 8 + count * 8;
+                self.ppc_patches.push(PpcPatch {
+                    address:           Some(code_address(use_base_address, address, self.base_address, self.pointer_address)),
+                    instruction_count: instruction_data.len() / 4,
+                    disassembly:       ppc::disassemble(instruction_data),
+                    interpreted:       false,
+                });
+
+                offset += length;
             }
             0xE0 => {
                 let reset_base_address_high = (&codeset[offset + 4..]).read_u16::<BigEndian>().unwrap();
                 let reset_pointer_address_high = (&codeset[offset + 6..]).read_u16::<BigEndian>().unwrap();
 
-                execution_stack.clear();
+                self.execution_stack.clear();
 
                 if reset_base_address_high != 0 {
-                    base_address = (reset_base_address_high as u32) << 16
+                    self.base_address = (reset_base_address_high as u32) << 16
                 }
                 if reset_pointer_address_high != 0 {
-                    pointer_address = (reset_pointer_address_high as u32) << 16
+                    self.pointer_address = (reset_pointer_address_high as u32) << 16
                 }
 
                 offset += 8;
@@ -650,36 +688,286 @@ pub fn process(codeset: &[u8], buffer: &mut [u8], buffer_ram_location: u32) -> W
                 let reset_pointer_address_high = (&codeset[offset + 6..]).read_u16::<BigEndian>().unwrap();
 
                 for _ in 0..count {
-                    execution_stack.pop();
+                    self.execution_stack.pop();
                 }
 
                 if else_branch {
                     // TODO: not sure if this should be taken from the stack before it is pop'd
-                    let last = execution_stack.last().cloned().unwrap_or(true);
+                    let last = self.execution_stack.last().cloned().unwrap_or(true);
 
-                    execution_stack.push(!last);
+                    self.execution_stack.push(!last);
                 }
 
                 if reset_base_address_high != 0 {
-                    base_address = (reset_base_address_high as u32) << 16
+                    self.base_address = (reset_base_address_high as u32) << 16
                 }
                 if reset_pointer_address_high != 0 {
-                    pointer_address = (reset_pointer_address_high as u32) << 16
+                    self.pointer_address = (reset_pointer_address_high as u32) << 16
                 }
 
                 offset += 8;
             }
-            0xF0 => {
-                // End of codes
-            }
             unknown => {
                 // Can't really continue processing because we dont know what the correct offset should be.
                 // Report an error and return what we have so far.
                 error!("Cannot process WiiRD code starting with 0x{:x}", unknown);
-                break
+                return self.stop(code_offset);
+            }
+        }
+
+        self.offset = offset;
+        if !execute {
+            Step::Skipped { offset: code_offset }
+        } else if !supported {
+            Step::Unsupported { offset: code_offset, code }
+        } else {
+            Step::Executed { offset: code_offset }
+        }
+    }
+
+    /// Returns the address and data written by the WriteAndFill or StringWrite code at `offset`, followed by the length of the code
+    fn write_code(&self, offset: usize) -> Option<(u32, Vec<u8>, usize)> {
+        let codeset = self.codeset;
+        let use_base_address = codeset[offset] & 0b00010000 == 0;
+        let address = (&codeset[offset ..]).read_u32::<BigEndian>().unwrap() & 0x1FFFFFF;
+        let mem_address = code_address(use_base_address, address, self.base_address, self.pointer_address);
+        match codeset[offset] & 0b11101110 {
+            0x00 => {
+                let value = codeset[offset + 7];
+                let length = (&codeset[offset + 4..]).read_u16::<BigEndian>().unwrap() as usize + 1;
+                Some((mem_address, vec!(value; length), 8))
+            }
+            0x02 => {
+                let value = (&codeset[offset + 6..]).read_u16::<BigEndian>().unwrap();
+                let length = (&codeset[offset + 4..]).read_u16::<BigEndian>().unwrap() as u32 + 1;
+                let data: Vec<u8> = (0..length).flat_map(|_| value.to_be_bytes().to_vec()).collect();
+                Some((mem_address, data, 8))
             }
+            0x04 => {
+                let value = (&codeset[offset + 4..]).read_u32::<BigEndian>().unwrap();
+                Some((mem_address, value.to_be_bytes().to_vec(), 8))
+            }
+            0x06 => {
+                let count = (&codeset[offset + 4..]).read_u32::<BigEndian>().unwrap() as usize;
+                let values = codeset[offset + 8 .. offset + 8 + count].to_vec();
+
+                // align the length to 8 bytes
+                let mut length = 8 + count;
+                let count_mod = count % 8;
+                if count_mod != 0 {
+                    length += 8 - count_mod;
+                }
+                Some((mem_address, values, length))
+            }
+            _ => None,
         }
     }
 
-    memory
+    /// Returns the instructions of the ExecutePPC or InsertPPC code at `offset`, followed by the length of the code
+    fn ppc_code(&self, offset: usize) -> (&'a [u8], usize) {
+        let codeset = self.codeset;
+        let count = (&codeset[offset + 4..]).read_u32::<BigEndian>().unwrap() as usize;
+        (&codeset[offset + 8 .. offset + 8 + count * 8], 8 + count * 8)
+    }
+
+    /// Returns the address and data of each write performed by the instructions of an ExecutePPC code.
+    /// None when `RunConfig::interpret_ppc` is not set or the instructions cannot be interpreted.
+    fn interpret_ppc(&self, instruction_data: &[u8]) -> Option<Vec<(u32, Vec<u8>)>> {
+        if !self.config.interpret_ppc {
+            return None;
+        }
+        let writes = ppc::interpret(instruction_data)?;
+        Some(writes.iter().map(|write| {
+            let data = write.value.to_be_bytes();
+            let data = match write.size {
+                ppc::StoreSize::Byte => &data[3..],
+                ppc::StoreSize::Half => &data[2..],
+                ppc::StoreSize::Word => &data[..],
+            };
+            (write.address, data.to_vec())
+        }).collect())
+    }
+}
+
+/// Writes `data` to wii ram starting at `address`.
+/// Also writes to the provided buffer if it would have been written to on a wii.
+/// Writes `data` to both `memory` and `buffer`, the write is recorded against `code_offset` when it is known.
+pub(crate) fn write_bytes(memory: &mut WiiMemory, buffer: &mut [u8], region: &LoadedRegion, address: u32, data: &[u8], code_offset: Option<usize>) {
+    if let Some(code_offset) = code_offset {
+        memory.record_write(address, data.len() as u32, code_offset);
+    }
+    for (i, value) in data.iter().enumerate() {
+        let current_address = RamAddress::new(address.wrapping_add(i as u32));
+        memory.write_u8(current_address, *value);
+
+        if let Some(buffer_offset) = region.to_file_offset(current_address) {
+            buffer[buffer_offset.into_inner() as usize] = *value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codeset(lines: &[(u32, u32)]) -> Vec<u8> {
+        let mut codeset = vec!();
+        for (left, right) in lines {
+            codeset.extend(&left.to_be_bytes());
+            codeset.extend(&right.to_be_bytes());
+        }
+        codeset
+    }
+
+    #[test]
+    fn write_through_pointer_address() {
+        let location = RamAddress::new(0x80F9FC20);
+        let mut buffer = vec!(0; 0x20);
+        let codeset = codeset(&[
+            (0x4A000000, 0x80F9FC20), // po = 0x80F9FC20
+            (0x10000001, 0x000000AB), // 8 bit write to po + 1
+            (0x12000004, 0x0001BEEF), // 16 bit write to po + 4, twice
+            (0x14000010, 0xDEADBEEF), // 32 bit write to po + 0x10
+        ]);
+        let memory = process(&codeset, &mut buffer, location);
+
+        assert_eq!(&buffer[0..2],      &[0x00, 0xAB]);
+        assert_eq!(&buffer[4..8],      &[0xBE, 0xEF, 0xBE, 0xEF]);
+        assert_eq!(&buffer[0x10..0x14], &[0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(buffer.iter().filter(|x| **x != 0).count(), 9);
+        assert_eq!(memory.read_u32(location + 0x10), 0xDEADBEEF);
+    }
+
+    #[test]
+    fn pointer_address_is_independent_of_base_address() {
+        let location = RamAddress::new(0x80F9FC20);
+        let mut buffer = vec!(0; 0x20);
+        let codeset = codeset(&[
+            (0x4A000000, 0x80F9FC00), // po = 0x80F9FC00
+            (0x5A010000, 0x00000020), // po += 0x20
+            (0x42000000, 0x90000000), // ba = 0x90000000
+            (0x14000008, 0x11223344), // 32 bit write to po + 8
+            (0x04F9FC2C, 0x55667788), // 32 bit write to ba + 0x00F9FC2C, lands outside the buffer
+            (0x4C000000, 0x80000000), // store po at 0x80000000
+        ]);
+        let memory = process(&codeset, &mut buffer, location);
+
+        assert_eq!(&buffer[8..0xC], &[0x11, 0x22, 0x33, 0x44]);
+        assert_eq!(&buffer[0xC..0x10], &[0, 0, 0, 0]);
+        assert_eq!(memory.read_u32(RamAddress::new(0x90F9FC2C)), 0x55667788);
+        assert_eq!(memory.read_u32(RamAddress::new(0x80000000)), 0x80F9FC20);
+    }
+
+    #[test]
+    fn load_pointer_address_from_memory() {
+        let location = RamAddress::new(0x80F9FC20);
+        let mut buffer = vec!(0; 0x20);
+        let codeset = codeset(&[
+            (0x04000100, 0x80F9FC30), // store a pointer to buffer + 0x10 at 0x80000100
+            (0x48000000, 0x80000100), // po = [0x80000100]
+            (0x10000002, 0x000000FF), // 8 bit write to po + 2
+        ]);
+        process(&codeset, &mut buffer, location);
+
+        assert_eq!(buffer[0x12], 0xFF);
+        assert_eq!(buffer.iter().filter(|x| **x != 0).count(), 1);
+    }
+
+    #[test]
+    fn full_terminator_resets_address_high() {
+        let location = RamAddress::new(0x80F9FC20);
+        let mut buffer = vec!(0; 0x20);
+        let codeset = codeset(&[
+            (0x4A000000, 0x90000000), // po = 0x90000000
+            (0xE0000000, 0x00000000), // leaves po untouched
+            (0x14000000, 0x11111111), // 32 bit write to 0x90000000
+            (0xE0000000, 0x00008000), // po = 0x80000000
+            (0x14F9FC24, 0x22222222), // 32 bit write to 0x80F9FC24
+        ]);
+        let memory = process(&codeset, &mut buffer, location);
+
+        assert_eq!(memory.read_u32(RamAddress::new(0x90000000)), 0x11111111);
+        assert_eq!(&buffer[4..8], &[0x22, 0x22, 0x22, 0x22]);
+
+        let targets: Vec<_> = crate::wiird::resolve_targets(&crate::wiird::wiird_codes(&codeset)).into_iter().map(|(_, x)| x).collect();
+        assert_eq!(targets, vec!(Some(0x90000000), Some(0x80F9FC24)));
+    }
+
+    #[test]
+    fn mask_if_statements() {
+        let location = RamAddress::new(0x80F9FC20);
+        let mut buffer = vec!(0x12, 0x34, 0, 0, 0, 0, 0, 0);
+        let codeset = codeset(&[
+            (0x28F9FC20, 0x00FF1200), // if [0x80F9FC20] & ~0x00FF == 0x1200
+            (0x04F9FC24, 0x11111111),
+            (0xE2000001, 0x00000000), // endif
+            (0x2CF9FC20, 0x00001234), // if [0x80F9FC20] > 0x1234
+            (0x04F9FC24, 0x22222222),
+            (0xE2000001, 0x00000000), // endif
+        ]);
+        process(&codeset, &mut buffer, location);
+        assert_eq!(&buffer[4..8], &[0x11, 0x11, 0x11, 0x11]);
+    }
+
+    #[test]
+    fn interpret_execute_ppc() {
+        let location = RamAddress::new(0x80F9FC20);
+        let mut buffer = vec!(0; 0x20);
+        let codeset = codeset(&[
+            (0xC0000000, 0x00000003), // ExecutePPC with 3 lines
+            (0x3C6080FA, 0x3880002A), // lis r3, 0x80fa; li r4, 42
+            (0x3CA0DEAD, 0x60A5BEEF), // lis r5, 0xdead; ori r5, r5, 0xbeef
+            (0x90A3FC30, 0x4E800020), // stw r5, -0x3d0(r3); blr
+        ]);
+        let config = RunConfig { interpret_ppc: true };
+        let report = run(&codeset, &mut buffer, location, &config);
+
+        assert_eq!(&buffer[0x10..0x14], &[0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(report.memory.read_u32(location + 0x10), 0xDEADBEEF);
+        assert_eq!(report.ppc_patches, vec!(PpcPatch {
+            address: None,
+            instruction_count: 6,
+            disassembly: Some(vec!(
+                "lis r3, 0x80fa".to_string(),
+                "li r4, 42".to_string(),
+                "lis r5, 0xdead".to_string(),
+                "ori r5, r5, 0xbeef".to_string(),
+                "stw r5, -976(r3)".to_string(),
+                "blr".to_string(),
+            )),
+            interpreted: true,
+        }));
+
+        // not interpreted by default
+        let mut buffer = vec!(0; 0x20);
+        let report = run(&codeset, &mut buffer, location, &RunConfig::default());
+        assert!(buffer.iter().all(|x| *x == 0));
+        assert!(!report.ppc_patches[0].interpreted);
+    }
+
+    #[test]
+    fn branches_are_not_interpreted() {
+        let location = RamAddress::new(0x80F9FC20);
+        let mut buffer = vec!(0; 0x20);
+        let codeset = codeset(&[
+            (0xC0000000, 0x00000002), // ExecutePPC with 2 lines
+            (0x3C6080FA, 0x48000008), // lis r3, 0x80fa; b 8
+            (0x9063FC30, 0x4E800020), // stw r3, -0x3d0(r3); blr
+            (0xC2F9FC40, 0x00000001), // InsertPPC at 0x80F9FC40 with 1 line
+            (0x60000000, 0x00000000), // nop; branch back placeholder
+        ]);
+        let config = RunConfig { interpret_ppc: true };
+        let report = run(&codeset, &mut buffer, location, &config);
+
+        assert!(buffer.iter().all(|x| *x == 0));
+        assert_eq!(report.ppc_patches.len(), 2);
+        assert!(!report.ppc_patches[0].interpreted);
+        assert_eq!(report.ppc_patches[0].disassembly.as_ref().unwrap()[1], "b 8");
+        assert_eq!(report.ppc_patches[1], PpcPatch {
+            address: Some(0x80F9FC40),
+            instruction_count: 2,
+            disassembly: Some(vec!("nop".to_string(), ".long 0x0".to_string())),
+            interpreted: false,
+        });
+    }
 }